@@ -2,7 +2,875 @@
 //!
 //! This module provides ARM64 machine code generation helpers and instruction
 //! encoding utilities for translating RISC-V instructions to native ARM64.
+//!
+//! Register arguments are register numbers (0-31). Data processing helpers use
+//! the 32-bit `W` forms, matching the RV32 register width; helpers operating on
+//! 64-bit `X` registers carry an `_x` suffix. Register 31 means the zero
+//! register (`WZR`/`XZR`) in data processing and `SP` as an address base.
+//! Branch and addressing offsets are in bytes.
 
 /// RET instruction (return to link register)
 /// Encoding: 1101011_0010_11111_000000_11110_00000
 pub const RET: u32 = 0xD65F03C0;
+
+/// NOP instruction (hint #0)
+pub const NOP: u32 = 0xD503201F;
+
+/// Zero register number (`WZR`/`XZR`) in data processing instructions
+pub const ZR: u8 = 31;
+
+/// Stack pointer register number as an address base
+pub const SP: u8 = 31;
+
+/// Frame pointer register (x29)
+pub const FP: u8 = 29;
+
+/// Link register (x30)
+pub const LR: u8 = 30;
+
+/// Element arrangement of a 128-bit vector register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Arrangement {
+    /// Sixteen bytes
+    B16 = 0,
+    /// Eight halfwords
+    H8 = 1,
+    /// Four words
+    S4 = 2,
+    /// Two doublewords
+    D2 = 3,
+}
+
+impl Arrangement {
+    /// Get the arrangement with `2^size`-byte elements
+    pub fn from_size(size: u32) -> Option<Arrangement> {
+        Some(match size {
+            0 => Arrangement::B16,
+            1 => Arrangement::H8,
+            2 => Arrangement::S4,
+            3 => Arrangement::D2,
+            _ => return None,
+        })
+    }
+
+    /// Element size in bytes
+    pub fn element_bytes(self) -> usize {
+        1 << self as usize
+    }
+}
+
+/// Condition codes for conditional branches and selects
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum Condition {
+    /// Equal
+    Eq = 0,
+    /// Not equal
+    Ne = 1,
+    /// Unsigned higher or same
+    Hs = 2,
+    /// Unsigned lower
+    Lo = 3,
+    /// Negative
+    Mi = 4,
+    /// Positive or zero
+    Pl = 5,
+    /// Overflow
+    Vs = 6,
+    /// No overflow
+    Vc = 7,
+    /// Unsigned higher
+    Hi = 8,
+    /// Unsigned lower or same
+    Ls = 9,
+    /// Signed greater than or equal
+    Ge = 10,
+    /// Signed less than
+    Lt = 11,
+    /// Signed greater than
+    Gt = 12,
+    /// Signed less than or equal
+    Le = 13,
+}
+
+impl Condition {
+    /// Get the condition that holds exactly when this one does not
+    pub fn invert(self) -> Condition {
+        match self {
+            Condition::Eq => Condition::Ne,
+            Condition::Ne => Condition::Eq,
+            Condition::Hs => Condition::Lo,
+            Condition::Lo => Condition::Hs,
+            Condition::Mi => Condition::Pl,
+            Condition::Pl => Condition::Mi,
+            Condition::Vs => Condition::Vc,
+            Condition::Vc => Condition::Vs,
+            Condition::Hi => Condition::Ls,
+            Condition::Ls => Condition::Hi,
+            Condition::Ge => Condition::Lt,
+            Condition::Lt => Condition::Ge,
+            Condition::Gt => Condition::Le,
+            Condition::Le => Condition::Gt,
+        }
+    }
+}
+
+/// Encode a three-register data processing instruction
+fn reg3(base: u32, rd: u8, rn: u8, rm: u8) -> u32 {
+    base | (rm as u32 & 0x1F) << 16 | (rn as u32 & 0x1F) << 5 | (rd as u32 & 0x1F)
+}
+
+/// `ADD wd, wn, wm`
+pub fn add(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x0B000000, rd, rn, rm)
+}
+
+/// `SUB wd, wn, wm`
+pub fn sub(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x4B000000, rd, rn, rm)
+}
+
+/// `ADD xd, xn, xm`
+pub fn add_x(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x8B000000, rd, rn, rm)
+}
+
+/// `SUBS xd, xn, xm`
+pub fn subs_x(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0xEB000000, rd, rn, rm)
+}
+
+/// `ADD wd, wn, wm, LSL #amount`
+pub fn add_lsl(rd: u8, rn: u8, rm: u8, amount: u8) -> u32 {
+    reg3(0x0B000000, rd, rn, rm) | (amount as u32 & 0x1F) << 10
+}
+
+/// `ADD xd, xn, xm, LSL #amount`
+pub fn add_x_lsl(rd: u8, rn: u8, rm: u8, amount: u8) -> u32 {
+    reg3(0x8B000000, rd, rn, rm) | (amount as u32 & 0x3F) << 10
+}
+
+/// `ADD xd, xn, xm, LSR #amount`
+pub fn add_x_lsr(rd: u8, rn: u8, rm: u8, amount: u8) -> u32 {
+    reg3(0x8B400000, rd, rn, rm) | (amount as u32 & 0x3F) << 10
+}
+
+/// `AND wd, wn, wm`
+pub fn and(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x0A000000, rd, rn, rm)
+}
+
+/// `ORR wd, wn, wm`
+pub fn orr(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x2A000000, rd, rn, rm)
+}
+
+/// `EOR wd, wn, wm`
+pub fn eor(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x4A000000, rd, rn, rm)
+}
+
+/// `LSL wd, wn, wm` (shift amount taken modulo 32)
+pub fn lslv(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x1AC02000, rd, rn, rm)
+}
+
+/// `LSR wd, wn, wm` (shift amount taken modulo 32)
+pub fn lsrv(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x1AC02400, rd, rn, rm)
+}
+
+/// `ASR wd, wn, wm` (shift amount taken modulo 32)
+pub fn asrv(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x1AC02800, rd, rn, rm)
+}
+
+/// `MUL wd, wn, wm` (MADD wd, wn, wm, wzr)
+pub fn mul(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x1B007C00, rd, rn, rm)
+}
+
+/// `MUL xd, xn, xm` (MADD xd, xn, xm, xzr)
+pub fn mul_x(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x9B007C00, rd, rn, rm)
+}
+
+/// `MSUB wd, wn, wm, wa` (wd = wa - wn * wm)
+pub fn msub(rd: u8, rn: u8, rm: u8, ra: u8) -> u32 {
+    reg3(0x1B008000, rd, rn, rm) | (ra as u32 & 0x1F) << 10
+}
+
+/// `SMULL xd, wn, wm` (signed 32x32 to 64-bit multiply)
+pub fn smull(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x9B207C00, rd, rn, rm)
+}
+
+/// `UMULL xd, wn, wm` (unsigned 32x32 to 64-bit multiply)
+pub fn umull(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x9BA07C00, rd, rn, rm)
+}
+
+/// `SDIV wd, wn, wm` (division by zero gives zero)
+pub fn sdiv(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x1AC00C00, rd, rn, rm)
+}
+
+/// `UDIV wd, wn, wm` (division by zero gives zero)
+pub fn udiv(rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x1AC00800, rd, rn, rm)
+}
+
+/// `MOV wd, wm` (ORR wd, wzr, wm)
+pub fn mov(rd: u8, rm: u8) -> u32 {
+    orr(rd, ZR, rm)
+}
+
+/// `MOV xd, xm` (ORR xd, xzr, xm)
+pub fn mov_x(rd: u8, rm: u8) -> u32 {
+    reg3(0xAA000000, rd, ZR, rm)
+}
+
+/// `CMP wn, wm` (SUBS wzr, wn, wm)
+pub fn cmp(rn: u8, rm: u8) -> u32 {
+    reg3(0x6B000000, ZR, rn, rm)
+}
+
+/// `CSET wd, cond` (CSINC wd, wzr, wzr, !cond)
+pub fn cset(rd: u8, condition: Condition) -> u32 {
+    reg3(0x1A800400, rd, ZR, ZR) | (condition.invert() as u32) << 12
+}
+
+/// `CSINV wd, wn, wm, cond` (wd = cond ? wn : !wm)
+pub fn csinv(rd: u8, rn: u8, rm: u8, condition: Condition) -> u32 {
+    reg3(0x5A800000, rd, rn, rm) | (condition as u32) << 12
+}
+
+/// Encode a move-wide instruction
+fn move_wide(base: u32, rd: u8, imm: u16, shift: u8) -> u32 {
+    debug_assert!(shift == 0 || shift == 16, "shift must be 0 or 16");
+    base | ((shift as u32 / 16) << 21) | (imm as u32) << 5 | (rd as u32 & 0x1F)
+}
+
+/// `MOVZ wd, #imm, LSL #shift` (shift is 0 or 16)
+pub fn movz(rd: u8, imm: u16, shift: u8) -> u32 {
+    move_wide(0x52800000, rd, imm, shift)
+}
+
+/// `MOVK wd, #imm, LSL #shift` (shift is 0 or 16)
+pub fn movk(rd: u8, imm: u16, shift: u8) -> u32 {
+    move_wide(0x72800000, rd, imm, shift)
+}
+
+/// `MOVN wd, #imm, LSL #shift` (shift is 0 or 16)
+pub fn movn(rd: u8, imm: u16, shift: u8) -> u32 {
+    move_wide(0x12800000, rd, imm, shift)
+}
+
+/// Load a 32-bit constant into wd with the shortest move-wide sequence
+///
+/// Emits one instruction when the value fits MOVZ or MOVN, two otherwise.
+pub fn mov_imm(rd: u8, value: u32) -> Vec<u32> {
+    let low = value as u16;
+    let high = (value >> 16) as u16;
+    if high == 0 {
+        vec![movz(rd, low, 0)]
+    } else if low == 0 {
+        vec![movz(rd, high, 16)]
+    } else if high == 0xFFFF {
+        vec![movn(rd, !low, 0)]
+    } else {
+        vec![movz(rd, low, 0), movk(rd, high, 16)]
+    }
+}
+
+/// Encode an add/subtract immediate instruction
+fn add_sub_imm(base: u32, rd: u8, rn: u8, imm: u16) -> u32 {
+    debug_assert!(imm < 4096, "immediate must fit in 12 bits");
+    base | (imm as u32 & 0xFFF) << 10 | (rn as u32 & 0x1F) << 5 | (rd as u32 & 0x1F)
+}
+
+/// `ADD wd, wn, #imm` (imm < 4096; register 31 is WSP)
+pub fn add_imm(rd: u8, rn: u8, imm: u16) -> u32 {
+    add_sub_imm(0x11000000, rd, rn, imm)
+}
+
+/// `SUB wd, wn, #imm` (imm < 4096; register 31 is WSP)
+pub fn sub_imm(rd: u8, rn: u8, imm: u16) -> u32 {
+    add_sub_imm(0x51000000, rd, rn, imm)
+}
+
+/// `ADD xd, xn, #imm` (imm < 4096; register 31 is SP, so this also moves SP)
+pub fn add_imm_x(rd: u8, rn: u8, imm: u16) -> u32 {
+    add_sub_imm(0x91000000, rd, rn, imm)
+}
+
+/// `SUBS xd, xn, #imm` (imm < 4096)
+pub fn subs_imm_x(rd: u8, rn: u8, imm: u16) -> u32 {
+    add_sub_imm(0xF1000000, rd, rn, imm)
+}
+
+/// `CMP wn, #imm` (imm < 4096)
+pub fn cmp_imm(rn: u8, imm: u16) -> u32 {
+    add_sub_imm(0x71000000, ZR, rn, imm)
+}
+
+/// `LSL wd, wn, #shift` (UBFM wd, wn, #(-shift mod 32), #(31 - shift))
+pub fn lsl_imm(rd: u8, rn: u8, shift: u8) -> u32 {
+    let shift = shift as u32 & 0x1F;
+    reg3(0x53000000, rd, rn, 0) | ((32 - shift) & 0x1F) << 16 | (31 - shift) << 10
+}
+
+/// `LSR wd, wn, #shift` (UBFM wd, wn, #shift, #31)
+pub fn lsr_imm(rd: u8, rn: u8, shift: u8) -> u32 {
+    reg3(0x53000000, rd, rn, 0) | (shift as u32 & 0x1F) << 16 | 31 << 10
+}
+
+/// `ASR wd, wn, #shift` (SBFM wd, wn, #shift, #31)
+pub fn asr_imm(rd: u8, rn: u8, shift: u8) -> u32 {
+    reg3(0x13000000, rd, rn, 0) | (shift as u32 & 0x1F) << 16 | 31 << 10
+}
+
+/// `UBFX wd, wn, #lsb, #width` (UBFM wd, wn, #lsb, #(lsb + width - 1))
+pub fn ubfx(rd: u8, rn: u8, lsb: u8, width: u8) -> u32 {
+    debug_assert!(width > 0 && lsb + width <= 32, "bit field out of range");
+    reg3(0x53000000, rd, rn, 0)
+        | (lsb as u32 & 0x1F) << 16
+        | ((lsb + width - 1) as u32 & 0x1F) << 10
+}
+
+/// `SXTW xd, wn` (SBFM xd, xn, #0, #31)
+pub fn sxtw(rd: u8, rn: u8) -> u32 {
+    reg3(0x93407C00, rd, rn, 0)
+}
+
+/// `LSR xd, xn, #32` (UBFM xd, xn, #32, #63), the high word zero-extended
+pub fn lsr_32_x(rd: u8, rn: u8) -> u32 {
+    reg3(0xD360FC00, rd, rn, 0)
+}
+
+/// Encode an unsigned-offset load/store with the offset scaled by `scale`
+fn load_store(base: u32, rt: u8, rn: u8, offset: u32, scale: u32) -> u32 {
+    debug_assert!(
+        offset % scale == 0 && offset / scale < 4096,
+        "offset must be aligned and in range"
+    );
+    base | ((offset / scale) & 0xFFF) << 10 | (rn as u32 & 0x1F) << 5 | (rt as u32 & 0x1F)
+}
+
+/// `LDR wt, [xn, #offset]` (offset is a multiple of 4 below 16KB)
+pub fn ldr(rt: u8, rn: u8, offset: u32) -> u32 {
+    load_store(0xB9400000, rt, rn, offset, 4)
+}
+
+/// `STR wt, [xn, #offset]` (offset is a multiple of 4 below 16KB)
+pub fn str(rt: u8, rn: u8, offset: u32) -> u32 {
+    load_store(0xB9000000, rt, rn, offset, 4)
+}
+
+/// `LDR xt, [xn, #offset]` (offset is a multiple of 8 below 32KB)
+pub fn ldr_x(rt: u8, rn: u8, offset: u32) -> u32 {
+    load_store(0xF9400000, rt, rn, offset, 8)
+}
+
+/// `STR xt, [xn, #offset]` (offset is a multiple of 8 below 32KB)
+pub fn str_x(rt: u8, rn: u8, offset: u32) -> u32 {
+    load_store(0xF9000000, rt, rn, offset, 8)
+}
+
+/// `LDRSW xt, [xn, #offset]` (offset is a multiple of 4 below 16KB)
+pub fn ldrsw(rt: u8, rn: u8, offset: u32) -> u32 {
+    load_store(0xB9800000, rt, rn, offset, 4)
+}
+
+/// `LDRB wt, [xn, #offset]` (offset below 4KB)
+pub fn ldrb(rt: u8, rn: u8, offset: u32) -> u32 {
+    load_store(0x39400000, rt, rn, offset, 1)
+}
+
+/// `LDRB wt, [xn, xm]`
+pub fn ldrb_reg(rt: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x38606800, rt, rn, rm)
+}
+
+/// `LDRSB wt, [xn, xm]`
+pub fn ldrsb_reg(rt: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x38E06800, rt, rn, rm)
+}
+
+/// `LDRH wt, [xn, xm]`
+pub fn ldrh_reg(rt: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x78606800, rt, rn, rm)
+}
+
+/// `LDRH wt, [xn, xm, LSL #1]` (halfword index in `xm`)
+pub fn ldrh_index(rt: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x78607800, rt, rn, rm)
+}
+
+/// `LDRSH wt, [xn, xm]`
+pub fn ldrsh_reg(rt: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x78E06800, rt, rn, rm)
+}
+
+/// `LDR wt, [xn, xm]`
+pub fn ldr_reg(rt: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0xB8606800, rt, rn, rm)
+}
+
+/// `LDRSW xt, [xn, xm]`
+pub fn ldrsw_reg(rt: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0xB8A06800, rt, rn, rm)
+}
+
+/// `STRB wt, [xn, xm]`
+pub fn strb_reg(rt: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x38206800, rt, rn, rm)
+}
+
+/// `STRH wt, [xn, xm]`
+pub fn strh_reg(rt: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x78206800, rt, rn, rm)
+}
+
+/// `STR wt, [xn, xm]`
+pub fn str_reg(rt: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0xB8206800, rt, rn, rm)
+}
+
+/// Encode a 64-bit load/store pair with a signed offset scaled by 8
+fn pair(base: u32, rt: u8, rt2: u8, rn: u8, offset: i32) -> u32 {
+    debug_assert!(
+        offset % 8 == 0 && (-512..512).contains(&offset),
+        "offset must be aligned and in range"
+    );
+    base | ((offset / 8) as u32 & 0x7F) << 15
+        | (rt2 as u32 & 0x1F) << 10
+        | (rn as u32 & 0x1F) << 5
+        | (rt as u32 & 0x1F)
+}
+
+/// `STP xt, xt2, [xn, #offset]`
+pub fn stp_x(rt: u8, rt2: u8, rn: u8, offset: i32) -> u32 {
+    pair(0xA9000000, rt, rt2, rn, offset)
+}
+
+/// `LDP xt, xt2, [xn, #offset]`
+pub fn ldp_x(rt: u8, rt2: u8, rn: u8, offset: i32) -> u32 {
+    pair(0xA9400000, rt, rt2, rn, offset)
+}
+
+/// `STP xt, xt2, [xn, #offset]!` (pre-index)
+pub fn stp_x_pre(rt: u8, rt2: u8, rn: u8, offset: i32) -> u32 {
+    pair(0xA9800000, rt, rt2, rn, offset)
+}
+
+/// `LDP xt, xt2, [xn], #offset` (post-index)
+pub fn ldp_x_post(rt: u8, rt2: u8, rn: u8, offset: i32) -> u32 {
+    pair(0xA8C00000, rt, rt2, rn, offset)
+}
+
+/// Encode a signed branch offset in bytes into a `bits`-wide word offset field
+fn branch_offset(offset: i32, bits: u32) -> u32 {
+    debug_assert!(offset % 4 == 0, "branch offset must be a multiple of 4");
+    let words = offset >> 2;
+    debug_assert!(
+        words >= -(1 << (bits - 1)) && words < 1 << (bits - 1),
+        "branch offset out of range"
+    );
+    words as u32 & ((1 << bits) - 1)
+}
+
+/// `B label` (offset within +/-128MB)
+pub fn b(offset: i32) -> u32 {
+    0x14000000 | branch_offset(offset, 26)
+}
+
+/// `B.cond label` (offset within +/-1MB)
+pub fn b_cond(condition: Condition, offset: i32) -> u32 {
+    0x54000000 | branch_offset(offset, 19) << 5 | condition as u32
+}
+
+/// `CBZ wt, label` (offset within +/-1MB)
+pub fn cbz(rt: u8, offset: i32) -> u32 {
+    0x34000000 | branch_offset(offset, 19) << 5 | (rt as u32 & 0x1F)
+}
+
+/// `CBNZ wt, label` (offset within +/-1MB)
+pub fn cbnz(rt: u8, offset: i32) -> u32 {
+    0x35000000 | branch_offset(offset, 19) << 5 | (rt as u32 & 0x1F)
+}
+
+/// Encode a test-bit-and-branch instruction
+fn test_branch(base: u32, rt: u8, bit: u8, offset: i32) -> u32 {
+    let bit = bit as u32 & 0x3F;
+    base | (bit >> 5) << 31
+        | (bit & 0x1F) << 19
+        | branch_offset(offset, 14) << 5
+        | (rt as u32 & 0x1F)
+}
+
+/// `TBZ xt, #bit, label` (offset within +/-32KB; bits 32-63 test the X register)
+pub fn tbz(rt: u8, bit: u8, offset: i32) -> u32 {
+    test_branch(0x36000000, rt, bit, offset)
+}
+
+/// `TBNZ xt, #bit, label` (offset within +/-32KB; bits 32-63 test the X register)
+pub fn tbnz(rt: u8, bit: u8, offset: i32) -> u32 {
+    test_branch(0x37000000, rt, bit, offset)
+}
+
+/// `ADR xd, label` (offset within +/-1MB)
+pub fn adr(rd: u8, offset: i32) -> u32 {
+    debug_assert!(
+        (-(1 << 20)..1 << 20).contains(&offset),
+        "offset out of range"
+    );
+    let offset = offset as u32;
+    0x10000000 | (offset & 3) << 29 | (offset >> 2 & 0x7FFFF) << 5 | (rd as u32 & 0x1F)
+}
+
+/// `LDR wt, label`: load a 32-bit literal (offset within +/-1MB)
+pub fn ldr_literal(rt: u8, offset: i32) -> u32 {
+    0x18000000 | branch_offset(offset, 19) << 5 | (rt as u32 & 0x1F)
+}
+
+/// `BLR xn`
+pub fn blr(rn: u8) -> u32 {
+    0xD63F0000 | (rn as u32 & 0x1F) << 5
+}
+
+/// `BR xn`
+pub fn br(rn: u8) -> u32 {
+    0xD61F0000 | (rn as u32 & 0x1F) << 5
+}
+
+/// `ADD vd.T, vn.T, vm.T`
+pub fn add_v(arrangement: Arrangement, rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x4E208400 | (arrangement as u32) << 22, rd, rn, rm)
+}
+
+/// `SUB vd.T, vn.T, vm.T`
+pub fn sub_v(arrangement: Arrangement, rd: u8, rn: u8, rm: u8) -> u32 {
+    reg3(0x6E208400 | (arrangement as u32) << 22, rd, rn, rm)
+}
+
+/// `DUP vd.T, wn` (`xn` for `D2`): copy a register to every element
+pub fn dup_v(arrangement: Arrangement, rd: u8, rn: u8) -> u32 {
+    let imm5 = 1 << (arrangement as u32);
+    0x4E000C00 | imm5 << 16 | (rn as u32 & 0x1F) << 5 | (rd as u32 & 0x1F)
+}
+
+/// `LDR qt, [xn, #offset]` (offset is a multiple of 16 below 64KB)
+pub fn ldr_q(rt: u8, rn: u8, offset: u32) -> u32 {
+    load_store(0x3DC00000, rt, rn, offset, 16)
+}
+
+/// `STR qt, [xn, #offset]` (offset is a multiple of 16 below 64KB)
+pub fn str_q(rt: u8, rn: u8, offset: u32) -> u32 {
+    load_store(0x3D800000, rt, rn, offset, 16)
+}
+
+/// Condition mnemonics indexed by their encoding
+const CONDITIONS: [&str; 16] = [
+    "eq", "ne", "hs", "lo", "mi", "pl", "vs", "vc", "hi", "ls", "ge", "lt", "gt", "le", "al", "nv",
+];
+
+/// Name register `n` as a W or X register, with 31 as the stack pointer or
+/// the zero register
+fn name(n: u32, wide: bool, sp: bool) -> String {
+    match (n & 0x1F, wide, sp) {
+        (31, true, true) => "sp".to_string(),
+        (31, false, true) => "wsp".to_string(),
+        (31, true, false) => "xzr".to_string(),
+        (31, false, false) => "wzr".to_string(),
+        (n, true, _) => format!("x{}", n),
+        (n, false, _) => format!("w{}", n),
+    }
+}
+
+/// Arrangement suffixes indexed by element size
+const ARRANGEMENTS: [&str; 4] = ["16b", "8h", "4s", "2d"];
+
+/// Sign-extend the `bits`-wide field of `word` starting at `lsb`
+fn signed(word: u32, lsb: u32, bits: u32) -> i64 {
+    ((word >> lsb) as i64) << (64 - bits) >> (64 - bits)
+}
+
+/// Byte offset from `word` to the code it refers to, if it is a PC-relative
+/// branch, ADR or literal load
+///
+/// These are the only forms emitted that address code, so compiled code
+/// makes no reference to its own location in memory beyond them.
+pub fn pc_relative(word: u32) -> Option<i64> {
+    if word & 0xFC000000 == 0x14000000 {
+        Some(signed(word, 0, 26) * 4)
+    } else if word & 0xFF000010 == 0x54000000
+        || word & 0x7E000000 == 0x34000000
+        || word & 0xFF000000 == 0x18000000
+    {
+        Some(signed(word, 5, 19) * 4)
+    } else if word & 0x7E000000 == 0x36000000 {
+        Some(signed(word, 5, 14) * 4)
+    } else if word & 0x9F000000 == 0x10000000 {
+        Some(signed(word, 5, 19) << 2 | (word >> 29 & 3) as i64)
+    } else {
+        None
+    }
+}
+
+/// Mnemonic, register width and scale of a load/store by size and opc
+fn load_store_op(size: u32, opc: u32) -> Option<(&'static str, bool)> {
+    Some(match (size, opc) {
+        (0, 0) => ("strb", false),
+        (0, 1) => ("ldrb", false),
+        (0, 2) => ("ldrsb", true),
+        (0, 3) => ("ldrsb", false),
+        (1, 0) => ("strh", false),
+        (1, 1) => ("ldrh", false),
+        (1, 2) => ("ldrsh", true),
+        (1, 3) => ("ldrsh", false),
+        (2, 0) => ("str", false),
+        (2, 1) => ("ldr", false),
+        (2, 2) => ("ldrsw", true),
+        (3, 0) => ("str", true),
+        (3, 1) => ("ldr", true),
+        _ => return None,
+    })
+}
+
+/// Render an instruction word located `offset` bytes into the code
+///
+/// Covers the forms emitted by this module, in the syntax of its doc
+/// comments, with branch, ADR and literal targets as absolute code offsets. Other
+/// words, such as dispatch table entries, render as `.word`.
+pub fn disassemble(word: u32, offset: usize) -> String {
+    decode(word, offset).unwrap_or_else(|| format!(".word 0x{:08x}", word))
+}
+
+/// Decode a word into its assembly text, if it is a known form
+fn decode(word: u32, offset: usize) -> Option<String> {
+    let rd = word & 0x1F;
+    let rn = word >> 5 & 0x1F;
+    let rm = word >> 16 & 0x1F;
+    let wide = word >> 31 == 1;
+    let target = |delta: i64| format!("0x{:x}", offset as i64 + delta);
+
+    let text = if word == RET {
+        "ret".to_string()
+    } else if word == NOP {
+        "nop".to_string()
+    } else if word & 0xFFFFFC1F == 0xD61F0000 {
+        format!("br {}", name(rn, true, false))
+    } else if word & 0xFFFFFC1F == 0xD63F0000 {
+        format!("blr {}", name(rn, true, false))
+    } else if word & 0xFC000000 == 0x14000000 {
+        format!("b {}", target(pc_relative(word)?))
+    } else if word & 0xFF000010 == 0x54000000 {
+        let condition = CONDITIONS[(word & 0xF) as usize];
+        format!("b.{} {}", condition, target(pc_relative(word)?))
+    } else if word & 0x7E000000 == 0x34000000 {
+        let op = if word >> 24 & 1 == 0 { "cbz" } else { "cbnz" };
+        let rt = name(rd, wide, false);
+        format!("{} {}, {}", op, rt, target(pc_relative(word)?))
+    } else if word & 0x7E000000 == 0x36000000 {
+        let op = if word >> 24 & 1 == 0 { "tbz" } else { "tbnz" };
+        let bit = (word >> 31) << 5 | (word >> 19 & 0x1F);
+        let rt = name(rd, bit >= 32, false);
+        format!("{} {}, #{}, {}", op, rt, bit, target(pc_relative(word)?))
+    } else if word & 0xFF000000 == 0x18000000 {
+        let rt = name(rd, false, false);
+        format!("ldr {}, {}", rt, target(pc_relative(word)?))
+    } else if word & 0x9F000000 == 0x10000000 {
+        format!(
+            "adr {}, {}",
+            name(rd, true, false),
+            target(pc_relative(word)?)
+        )
+    } else if word & 0x1F200000 == 0x0A000000 || word & 0x1F200000 == 0x0B000000 {
+        // Logical and add/subtract (shifted register)
+        let shift = word >> 22 & 3;
+        let amount = word >> 10 & 0x3F;
+        let (rd, rn, rm) = (
+            name(rd, wide, false),
+            name(rn, wide, false),
+            name(rm, wide, false),
+        );
+        let op = if word >> 24 & 1 == 0 {
+            ["and", "orr", "eor", "ands"][(word >> 29 & 3) as usize]
+        } else {
+            ["add", "adds", "sub", "subs"][(word >> 29 & 3) as usize]
+        };
+        let suffix = match (shift, amount) {
+            (0, 0) => String::new(),
+            _ => format!(
+                ", {} #{}",
+                ["lsl", "lsr", "asr", "ror"][shift as usize],
+                amount
+            ),
+        };
+        match op {
+            "orr" if rn.ends_with("zr") && suffix.is_empty() => format!("mov {}, {}", rd, rm),
+            "subs" if rd.ends_with("zr") => format!("cmp {}, {}{}", rn, rm, suffix),
+            _ => format!("{} {}, {}, {}{}", op, rd, rn, rm, suffix),
+        }
+    } else if word & 0x7FE00000 == 0x1AC00000 {
+        let op = match word >> 10 & 0x3F {
+            2 => "udiv",
+            3 => "sdiv",
+            8 => "lsl",
+            9 => "lsr",
+            10 => "asr",
+            _ => return None,
+        };
+        let (rd, rn, rm) = (
+            name(rd, wide, false),
+            name(rn, wide, false),
+            name(rm, wide, false),
+        );
+        format!("{} {}, {}, {}", op, rd, rn, rm)
+    } else if word & 0x7F000000 == 0x1B000000 {
+        let ra = word >> 10 & 0x1F;
+        let subtract = word >> 15 & 1 == 1;
+        let (op, long) = match (word >> 21 & 7, subtract, ra) {
+            (0, false, 31) => ("mul", false),
+            (0, false, _) => ("madd", false),
+            (0, true, _) => ("msub", false),
+            (1, false, 31) if wide => ("smull", true),
+            (5, false, 31) if wide => ("umull", true),
+            _ => return None,
+        };
+        let sources = !long && wide;
+        let (rd, rn, rm) = (
+            name(rd, wide, false),
+            name(rn, sources, false),
+            name(rm, sources, false),
+        );
+        match op {
+            "madd" | "msub" => format!("{} {}, {}, {}, {}", op, rd, rn, rm, name(ra, wide, false)),
+            _ => format!("{} {}, {}, {}", op, rd, rn, rm),
+        }
+    } else if word & 0x3FE00800 == 0x1A800000 {
+        let condition = word >> 12 & 0xF;
+        let op =
+            ["csel", "csinc", "csinv", "csneg"][((word >> 29 & 2) | (word >> 10 & 1)) as usize];
+        let (rd, rn, rm) = (
+            name(rd, wide, false),
+            name(rn, wide, false),
+            name(rm, wide, false),
+        );
+        if op == "csinc" && rn.ends_with("zr") && rm.ends_with("zr") {
+            format!("cset {}, {}", rd, CONDITIONS[(condition ^ 1) as usize])
+        } else {
+            format!(
+                "{} {}, {}, {}, {}",
+                op, rd, rn, rm, CONDITIONS[condition as usize]
+            )
+        }
+    } else if word & 0x1F800000 == 0x12800000 {
+        let op = match word >> 29 & 3 {
+            0 => "movn",
+            2 => "movz",
+            3 => "movk",
+            _ => return None,
+        };
+        let imm = word >> 5 & 0xFFFF;
+        let shift = (word >> 21 & 3) * 16;
+        let rd = name(rd, wide, false);
+        match shift {
+            0 => format!("{} {}, #0x{:x}", op, rd, imm),
+            _ => format!("{} {}, #0x{:x}, lsl #{}", op, rd, imm, shift),
+        }
+    } else if word & 0x1F000000 == 0x11000000 {
+        let flags = word >> 29 & 1 == 1;
+        let imm = (word >> 10 & 0xFFF) << ((word >> 22 & 1) * 12);
+        let rn = name(rn, wide, true);
+        let rd = name(rd, wide, !flags);
+        let op = ["add", "adds", "sub", "subs"][(word >> 29 & 3) as usize];
+        if op == "subs" && rd.ends_with("zr") {
+            format!("cmp {}, #{}", rn, imm)
+        } else {
+            format!("{} {}, {}, #{}", op, rd, rn, imm)
+        }
+    } else if word & 0x1F800000 == 0x13000000 {
+        let signed = match word >> 29 & 3 {
+            0 => true,
+            2 => false,
+            _ => return None,
+        };
+        let immr = word >> 16 & 0x3F;
+        let imms = word >> 10 & 0x3F;
+        let top = if wide { 63 } else { 31 };
+        let source = name(rn, false, false);
+        let (rd, rn) = (name(rd, wide, false), name(rn, wide, false));
+        match (signed, immr, imms) {
+            (true, 0, 31) if wide => format!("sxtw {}, {}", rd, source),
+            (true, _, _) if imms == top => format!("asr {}, {}, #{}", rd, rn, immr),
+            (false, _, _) if imms == top => format!("lsr {}, {}, #{}", rd, rn, immr),
+            (false, _, _) if imms + 1 == immr => {
+                format!("lsl {}, {}, #{}", rd, rn, top - imms)
+            }
+            (false, _, _) if imms >= immr => {
+                format!("ubfx {}, {}, #{}, #{}", rd, rn, immr, imms - immr + 1)
+            }
+            _ => {
+                let op = if signed { "sbfm" } else { "ubfm" };
+                format!("{} {}, {}, #{}, #{}", op, rd, rn, immr, imms)
+            }
+        }
+    } else if word & 0x3F000000 == 0x39000000 {
+        let size = word >> 30;
+        let (op, wide) = load_store_op(size, word >> 22 & 3)?;
+        let offset = (word >> 10 & 0xFFF) << size;
+        let (rt, rn) = (name(rd, wide, false), name(rn, true, true));
+        match offset {
+            0 => format!("{} {}, [{}]", op, rt, rn),
+            _ => format!("{} {}, [{}, #{}]", op, rt, rn, offset),
+        }
+    } else if word & 0x3F200C00 == 0x38200800 && word >> 13 & 7 == 3 {
+        let size = word >> 30;
+        let (op, wide) = load_store_op(size, word >> 22 & 3)?;
+        let (rt, rn, rm) = (
+            name(rd, wide, false),
+            name(rn, true, true),
+            name(rm, true, false),
+        );
+        match word >> 12 & 1 {
+            1 if size > 0 => format!("{} {}, [{}, {}, lsl #{}]", op, rt, rn, rm, size),
+            _ => format!("{} {}, [{}, {}]", op, rt, rn, rm),
+        }
+    } else if word & 0xFF800000 == 0x3D800000 {
+        let op = if word >> 22 & 1 == 1 { "ldr" } else { "str" };
+        let offset = (word >> 10 & 0xFFF) << 4;
+        let rn = name(rn, true, true);
+        match offset {
+            0 => format!("{} q{}, [{}]", op, rd, rn),
+            _ => format!("{} q{}, [{}, #{}]", op, rd, rn, offset),
+        }
+    } else if word & 0xDF20FC00 == 0x4E208400 {
+        let op = if word >> 29 & 1 == 0 { "add" } else { "sub" };
+        let t = ARRANGEMENTS[(word >> 22 & 3) as usize];
+        format!("{} v{}.{t}, v{}.{t}, v{}.{t}", op, rd, rn, rm)
+    } else if word & 0xFFE0FC00 == 0x4E000C00 && rm & 0xF != 0 {
+        let size = rm.trailing_zeros();
+        let rn = name(rn, size == 3, false);
+        format!("dup v{}.{}, {}", rd, ARRANGEMENTS[size as usize], rn)
+    } else if word & 0xFE000000 == 0xA8000000 && word >> 23 & 3 != 0 {
+        let op = if word >> 22 & 1 == 1 { "ldp" } else { "stp" };
+        let offset = signed(word, 15, 7) * 8;
+        let (rt, rt2, rn) = (
+            name(rd, true, false),
+            name(word >> 10, true, false),
+            name(rn, true, true),
+        );
+        match word >> 23 & 3 {
+            1 => format!("{} {}, {}, [{}], #{}", op, rt, rt2, rn, offset),
+            2 if offset == 0 => format!("{} {}, {}, [{}]", op, rt, rt2, rn),
+            2 => format!("{} {}, {}, [{}, #{}]", op, rt, rt2, rn, offset),
+            _ => format!("{} {}, {}, [{}, #{}]!", op, rt, rt2, rn, offset),
+        }
+    } else {
+        return None;
+    };
+    Some(text)
+}
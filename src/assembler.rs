@@ -0,0 +1,186 @@
+//! Program builder with symbolic labels
+//!
+//! Hand-writing branch offsets is error prone: every inserted instruction shifts
+//! the targets that follow it. The `Assembler` lets code generators emit
+//! instructions in order, bind names to positions with `label()`, and refer to
+//! those names from branches and jumps. Targets are resolved when the program is
+//! finished, so labels may be used before they are defined. References to
+//! labels defined elsewhere can be left as `Relocation` records with
+//! `assemble_relocatable()` and patched later by a linker.
+//!
+//! # Example
+//! ```
+//! use jigs::{Assembler, Instruction};
+//!
+//! let mut asm = Assembler::new();
+//! asm.emit(Instruction::addi(1, 0, 10).unwrap());
+//! asm.label("loop").unwrap();
+//! asm.emit(Instruction::addi(1, 1, -1).unwrap());
+//! asm.emit_target(Instruction::Bne { rs1: 1, rs2: 0, imm: 0 }, "loop").unwrap();
+//! asm.emit(Instruction::Ecall);
+//!
+//! let program = asm.finish().unwrap();
+//! assert_eq!(program[2], Instruction::Bne { rs1: 1, rs2: 0, imm: -4 });
+//! ```
+
+use crate::{EncodeError, Instruction, Relocation};
+use std::{collections::BTreeMap, fmt};
+
+/// Builds a sequence of instructions with label-relative branches and jumps
+#[derive(Debug, Clone, Default)]
+pub struct Assembler {
+    /// Instructions emitted so far
+    instructions: Vec<Instruction>,
+    /// Label names mapped to instruction indices
+    labels: BTreeMap<String, usize>,
+    /// Branches and jumps whose immediate must be resolved to a label
+    fixups: Vec<Relocation>,
+}
+
+/// Errors that can occur while building a program
+#[derive(Debug, Clone, PartialEq)]
+pub enum AssembleError {
+    /// A label was defined more than once
+    DuplicateLabel(String),
+    /// A branch or jump refers to a label that was never defined
+    UndefinedLabel(String),
+    /// The label is too far away for the instruction's immediate
+    OutOfRange(String, i32),
+    /// Only branches and JAL can target a label
+    NotBranch(Instruction),
+    /// An instruction failed to encode
+    EncodeError(EncodeError),
+}
+
+impl Assembler {
+    /// Create an empty program
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append an instruction
+    pub fn emit(&mut self, instruction: Instruction) {
+        self.instructions.push(instruction);
+    }
+
+    /// Append a branch or JAL whose target is `label`
+    ///
+    /// The immediate of `instruction` is ignored and replaced by the offset to
+    /// `label` when the program is finished.
+    pub fn emit_target(
+        &mut self,
+        mut instruction: Instruction,
+        label: &str,
+    ) -> Result<(), AssembleError> {
+        let (_, relocation) = instruction
+            .encode_relocatable(self.offset(), label)
+            .map_err(|error| match error {
+                EncodeError::NotRelocatable => AssembleError::NotBranch(instruction.clone()),
+                error => AssembleError::EncodeError(error),
+            })?;
+        set_target(&mut instruction, 0);
+        self.fixups.push(relocation);
+        self.instructions.push(instruction);
+        Ok(())
+    }
+
+    /// Bind `name` to the position of the next emitted instruction
+    pub fn label(&mut self, name: &str) -> Result<(), AssembleError> {
+        if self.labels.contains_key(name) {
+            return Err(AssembleError::DuplicateLabel(name.to_string()));
+        }
+        self.labels
+            .insert(name.to_string(), self.instructions.len());
+        Ok(())
+    }
+
+    /// Byte offset of the next emitted instruction from the start of the program
+    pub fn offset(&self) -> u32 {
+        (self.instructions.len() * 4) as u32
+    }
+
+    /// Resolve all label references and return the instructions
+    pub fn finish(mut self) -> Result<Vec<Instruction>, AssembleError> {
+        for relocation in &self.fixups {
+            let label = &relocation.symbol;
+            let target = *self
+                .labels
+                .get(label)
+                .ok_or_else(|| AssembleError::UndefinedLabel(label.clone()))?;
+            let offset = ((target * 4) as u32).wrapping_sub(relocation.offset) as i32;
+
+            let instruction = &mut self.instructions[relocation.offset as usize / 4];
+            set_target(instruction, offset);
+            if let Err(EncodeError::InvalidImmediate(..)) = instruction.encode() {
+                return Err(AssembleError::OutOfRange(label.clone(), offset));
+            }
+        }
+        Ok(self.instructions)
+    }
+
+    /// Resolve all label references and encode the program as little-endian code
+    pub fn assemble(self) -> Result<Vec<u8>, AssembleError> {
+        let (code, relocations) = self.assemble_relocatable()?;
+        match relocations.into_iter().next() {
+            Some(relocation) => Err(AssembleError::UndefinedLabel(relocation.symbol)),
+            None => Ok(code),
+        }
+    }
+
+    /// Encode the program, resolving defined labels and returning relocations for the rest
+    ///
+    /// References to undefined labels are left as zero-offset placeholders, each
+    /// described by a returned `Relocation` for a linker to apply.
+    pub fn assemble_relocatable(self) -> Result<(Vec<u8>, Vec<Relocation>), AssembleError> {
+        let mut code =
+            Instruction::encode_all(&self.instructions).map_err(AssembleError::EncodeError)?;
+        let mut unresolved = Vec::new();
+        for relocation in self.fixups {
+            let Some(target) = self.labels.get(&relocation.symbol) else {
+                unresolved.push(relocation);
+                continue;
+            };
+            relocation
+                .apply(&mut code, (*target * 4) as u32)
+                .map_err(|error| match error {
+                    EncodeError::InvalidImmediate(_, offset) => {
+                        AssembleError::OutOfRange(relocation.symbol.clone(), offset)
+                    }
+                    error => AssembleError::EncodeError(error),
+                })?;
+        }
+        Ok((code, unresolved))
+    }
+}
+
+impl fmt::Display for AssembleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AssembleError::DuplicateLabel(label) => write!(f, "Duplicate label: {}", label),
+            AssembleError::UndefinedLabel(label) => write!(f, "Undefined label: {}", label),
+            AssembleError::OutOfRange(label, offset) => {
+                write!(f, "Label {} out of range: offset {}", label, offset)
+            }
+            AssembleError::NotBranch(instruction) => {
+                write!(f, "Instruction cannot target a label: {}", instruction)
+            }
+            AssembleError::EncodeError(error) => write!(f, "Encode error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for AssembleError {}
+
+/// Replace the target immediate of a branch or JAL
+fn set_target(instruction: &mut Instruction, offset: i32) {
+    match instruction {
+        Instruction::Beq { imm, .. }
+        | Instruction::Bne { imm, .. }
+        | Instruction::Blt { imm, .. }
+        | Instruction::Bge { imm, .. }
+        | Instruction::Bltu { imm, .. }
+        | Instruction::Bgeu { imm, .. }
+        | Instruction::Jal { imm, .. } => *imm = offset,
+        _ => {}
+    }
+}
@@ -0,0 +1,313 @@
+//! Single-file guest bundles
+//!
+//! A bundle ("jig") packages everything needed to run a guest: its RISC-V code
+//! and load address, the compiled code, the initial memory image as a list of
+//! data segments, the entry table mapping function indices to guest
+//! addresses, and a free-form manifest of key/value metadata. Bundles are
+//! saved as one file and turned into a ready-to-run `Instance` with a single
+//! call to `instantiate()`.
+//!
+//! `compile()` stores the compiled module (see `serial`) in the bundle, and
+//! `instantiate()` loads it instead of compiling. The RISC-V code is kept
+//! alongside, so bundles without compiled code, or whose compiled code is
+//! stale or from another jigs version, are compiled on instantiation. Compiled
+//! code runs as is, so only bundles from trusted sources may carry it.
+//!
+//! # Format
+//! All integers are little-endian `u32`; strings and byte blobs are prefixed
+//! with their length.
+//! ```text
+//! magic "JIG\0" | version
+//! manifest count | (key, value)...
+//! base | code
+//! entry count | address...
+//! segment count | (address, bytes)...
+//! compiled present | serialized module
+//! ```
+//!
+//! # Example
+//! ```
+//! use jigs::{Bundle, Instruction, Memory, Module, PageStore};
+//!
+//! let mut bundle = Bundle::new(0x1000, Instruction::Ecall.encode().unwrap().to_le_bytes().to_vec());
+//! bundle.manifest.insert("name".to_string(), "hello".to_string());
+//! bundle.segments.push((0x8000, b"hello".to_vec()));
+//! bundle.compile(&mut Module::new(4).unwrap()).unwrap();
+//!
+//! let bytes = bundle.to_bytes();
+//! let loaded = Bundle::from_bytes(&bytes).unwrap();
+//! assert_eq!(loaded, bundle);
+//!
+//! let store = PageStore::new(16);
+//! let mut module = Module::new(1024).unwrap();
+//! let instance = loaded.instantiate(&mut module, Memory::new(&store, 16, 4)).unwrap();
+//! assert_eq!(instance.pc(), 0x1000);
+//! // Loaded, not compiled
+//! assert!(module.compile_stats().is_none());
+//! ```
+
+use crate::{
+    CompileError, Instance, Module,
+    memory::{Memory, MemoryError},
+    metadata::source_hash,
+    serial::{Reader, SerializeError, push_blob, push_list, push_u32},
+};
+use std::{collections::BTreeMap, fmt, fs, io, path::Path};
+
+/// Magic bytes identifying a bundle file
+pub const BUNDLE_MAGIC: [u8; 4] = *b"JIG\0";
+
+/// Current bundle format version
+pub const BUNDLE_VERSION: u32 = 2;
+
+/// A guest program packaged with its memory image, entry table and manifest
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Bundle {
+    /// Free-form metadata (name, version, toolchain, ...)
+    pub manifest: BTreeMap<String, String>,
+    /// Guest address the code is loaded at
+    pub base: u32,
+    /// RISC-V machine code
+    pub code: Vec<u8>,
+    /// Guest addresses of callable functions, indexed by function index
+    pub entries: Vec<u32>,
+    /// Initial memory contents as (guest address, bytes)
+    pub segments: Vec<(u32, Vec<u8>)>,
+    /// The code compiled by `compile()`, as `Module::serialize()` saves it
+    pub compiled: Option<Vec<u8>>,
+}
+
+/// Errors that can occur while reading, writing or instantiating a bundle
+#[derive(Debug, Clone, PartialEq)]
+pub enum BundleError {
+    /// Reading or writing the bundle file failed
+    Io(io::ErrorKind),
+    /// The data does not start with the bundle magic
+    InvalidMagic,
+    /// The bundle was written by an unsupported format version
+    UnsupportedVersion(u32),
+    /// The data ended before the bundle was complete
+    Truncated,
+    /// A manifest string is not valid UTF-8
+    InvalidManifest,
+    /// Loading code or data into memory failed
+    MemoryError(MemoryError),
+    /// Compiling the code failed
+    CompileError(CompileError),
+    /// The compiled module is malformed
+    InvalidModule(SerializeError),
+}
+
+impl Bundle {
+    /// Create a bundle with the given code, no data, and an empty manifest
+    pub fn new(base: u32, code: Vec<u8>) -> Self {
+        Bundle {
+            base,
+            code,
+            ..Bundle::default()
+        }
+    }
+
+    /// Serialize the bundle
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = BUNDLE_MAGIC.to_vec();
+        push_u32(&mut bytes, BUNDLE_VERSION);
+
+        push_u32(&mut bytes, self.manifest.len() as u32);
+        for (key, value) in &self.manifest {
+            push_blob(&mut bytes, key.as_bytes());
+            push_blob(&mut bytes, value.as_bytes());
+        }
+
+        push_u32(&mut bytes, self.base);
+        push_blob(&mut bytes, &self.code);
+
+        push_list(&mut bytes, self.entries.iter().copied());
+
+        push_u32(&mut bytes, self.segments.len() as u32);
+        for (address, data) in &self.segments {
+            push_u32(&mut bytes, *address);
+            push_blob(&mut bytes, data);
+        }
+
+        push_u32(&mut bytes, self.compiled.is_some() as u32);
+        if let Some(compiled) = &self.compiled {
+            push_blob(&mut bytes, compiled);
+        }
+        bytes
+    }
+
+    /// Deserialize a bundle
+    pub fn from_bytes(bytes: &[u8]) -> Result<Bundle, BundleError> {
+        let mut reader = Reader::new(bytes);
+        if reader.take(4).map_err(truncated)? != BUNDLE_MAGIC {
+            return Err(BundleError::InvalidMagic);
+        }
+        let version = reader.u32().map_err(truncated)?;
+        if version != BUNDLE_VERSION {
+            return Err(BundleError::UnsupportedVersion(version));
+        }
+
+        let mut manifest = BTreeMap::new();
+        for _ in 0..reader.u32().map_err(truncated)? {
+            let key = string(&mut reader)?;
+            let value = string(&mut reader)?;
+            manifest.insert(key, value);
+        }
+
+        let base = reader.u32().map_err(truncated)?;
+        let code = reader.blob().map_err(truncated)?.to_vec();
+        let entries = reader.list().map_err(truncated)?;
+
+        let mut segments = Vec::new();
+        for _ in 0..reader.u32().map_err(truncated)? {
+            let address = reader.u32().map_err(truncated)?;
+            segments.push((address, reader.blob().map_err(truncated)?.to_vec()));
+        }
+
+        let compiled = match reader.u32().map_err(truncated)? {
+            0 => None,
+            1 => Some(reader.blob().map_err(truncated)?.to_vec()),
+            _ => {
+                let error = SerializeError::InvalidSetting("compiled module flag");
+                return Err(BundleError::InvalidModule(error));
+            }
+        };
+
+        Ok(Bundle {
+            manifest,
+            base,
+            code,
+            entries,
+            segments,
+            compiled,
+        })
+    }
+
+    /// Write the bundle to a file
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), BundleError> {
+        fs::write(path, self.to_bytes()).map_err(|error| BundleError::Io(error.kind()))
+    }
+
+    /// Read a bundle from a file
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Bundle, BundleError> {
+        let bytes = fs::read(path).map_err(|error| BundleError::Io(error.kind()))?;
+        Bundle::from_bytes(&bytes)
+    }
+
+    /// Write the code and data segments into guest memory
+    pub fn load_memory(&self, memory: &mut Memory) -> Result<(), BundleError> {
+        let code = std::iter::once((self.base, &self.code));
+        let segments = self.segments.iter().map(|(address, data)| (*address, data));
+        for (address, data) in code.chain(segments) {
+            memory
+                .write(address, data)
+                .map_err(BundleError::MemoryError)?;
+        }
+        Ok(())
+    }
+
+    /// Compile the code into `module` with its options and store the result
+    /// in the bundle
+    ///
+    /// The code is compiled at the bundle's base address, which becomes the
+    /// module's base, with the entry table as the module's function table.
+    pub fn compile(&mut self, module: &mut Module) -> Result<(), CompileError> {
+        module.set_base(self.base);
+        module.set_functions(&self.entries);
+        module.set_code(&self.code)?;
+        let compiled = module.serialize().map_err(|_| CompileError::InvalidCode)?;
+        self.compiled = Some(compiled);
+        Ok(())
+    }
+
+    /// Load or compile the bundle into `module` and create an instance ready
+    /// to run
+    ///
+    /// When the bundle holds code compiled from its code and entry table by
+    /// this jigs version, `module` is replaced by the compiled module,
+    /// options included. Otherwise the code is compiled into `module` as
+    /// `compile()` does, without storing it. Either way
+    /// `Instance::call_function(i)` enters entry `i`.
+    /// The memory is initialized with the code and data segments, the PC is set
+    /// to the first entry (or the code base if there are no entries), and the
+    /// instance is attached to the module, which writes any data of the module
+    /// itself.
+    pub fn instantiate(
+        &self,
+        module: &mut Module,
+        memory: Memory,
+    ) -> Result<Instance, BundleError> {
+        match self.precompiled()? {
+            Some(compiled) => {
+                if module.instance_count != 0 {
+                    return Err(BundleError::CompileError(CompileError::InstancesAttached));
+                }
+                *module = compiled;
+            }
+            None => {
+                module.set_base(self.base);
+                module.set_functions(&self.entries);
+                module
+                    .set_code(&self.code)
+                    .map_err(BundleError::CompileError)?;
+            }
+        }
+
+        let mut instance = Instance::new(memory);
+        self.load_memory(instance.memory_mut())?;
+        instance.set_pc(self.entries.first().copied().unwrap_or(self.base));
+        instance.attach(module).map_err(BundleError::MemoryError)?;
+        Ok(instance)
+    }
+
+    /// The compiled module, if it was compiled from the code and entry table
+    /// by this jigs version
+    fn precompiled(&self) -> Result<Option<Module>, BundleError> {
+        let Some(bytes) = &self.compiled else {
+            return Ok(None);
+        };
+        let module = match Module::deserialize(bytes) {
+            Ok(module) => module,
+            Err(SerializeError::CompilerMismatch(_)) => return Ok(None),
+            Err(error) => return Err(BundleError::InvalidModule(error)),
+        };
+        let current = module.base() == self.base
+            && module.functions() == self.entries
+            && module
+                .metadata()
+                .is_some_and(|metadata| metadata.source_hash == source_hash(&self.code));
+        Ok(current.then_some(module))
+    }
+}
+
+impl fmt::Display for BundleError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleError::Io(kind) => write!(f, "I/O error: {}", kind),
+            BundleError::InvalidMagic => write!(f, "Not a bundle"),
+            BundleError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported bundle version: {}", version)
+            }
+            BundleError::Truncated => write!(f, "Bundle is truncated"),
+            BundleError::InvalidManifest => write!(f, "Manifest is not valid UTF-8"),
+            BundleError::MemoryError(error) => write!(f, "Memory error: {}", error),
+            BundleError::CompileError(error) => write!(f, "Compile error: {:?}", error),
+            BundleError::InvalidModule(error) => write!(f, "Invalid compiled module: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for BundleError {}
+
+/// Map a read past the end of the data, the only error the reads in
+/// `Bundle::from_bytes()` give, to `BundleError::Truncated`
+fn truncated(_error: SerializeError) -> BundleError {
+    BundleError::Truncated
+}
+
+/// Read a length-prefixed UTF-8 string
+fn string(reader: &mut Reader) -> Result<String, BundleError> {
+    let bytes = reader.blob().map_err(truncated)?;
+    String::from_utf8(bytes.to_vec()).map_err(|_| BundleError::InvalidManifest)
+}
@@ -0,0 +1,140 @@
+//! Cache of compiled code shared between modules
+//!
+//! Compilation is deterministic and compiled code holds no host addresses
+//! (see `compiler`), so code compiled once can be copied into any module. A
+//! `CodeCache` maps RISC-V code and the options it was compiled with (the
+//! `OptLevel`, whether it is resumable or counts blocks, the base address, the
+//! `ConstantStrategy`, the function table and the `Decoder`) to the compiled
+//! code, its `SourceMap` and the decoded instructions. Modules given a cache
+//! with `Module::set_code_cache()` copy their code from it instead of
+//! recompiling when it has been compiled before.
+//!
+//! Entries are keyed by the full RISC-V bytes, so differing code never
+//! shares an entry. Clones of a cache share its entries, and the cache is
+//! not thread-safe, like `PageStore`.
+//!
+//! # Example
+//! ```
+//! use jigs::{CodeCache, Module};
+//!
+//! let cache = CodeCache::new();
+//! let code = 0x00100093u32.to_le_bytes(); // addi x1, x0, 1
+//! for _ in 0..3 {
+//!     let mut module = Module::new(4).unwrap();
+//!     module.set_code_cache(cache.clone());
+//!     module.set_code(&code).unwrap();
+//! }
+//! assert_eq!(cache.len(), 1);
+//! assert_eq!((cache.hits(), cache.misses()), (2, 1));
+//! ```
+
+use crate::{
+    Instruction,
+    compiler::{ConstantStrategy, OptLevel, SourceMap},
+    decoder::Decoder,
+};
+use std::{
+    cell::{Cell, RefCell},
+    collections::HashMap,
+    rc::Rc,
+};
+
+/// Shared cache of compiled code, keyed by RISC-V code and options
+#[derive(Debug, Clone, Default)]
+pub struct CodeCache {
+    inner: Rc<Inner>,
+}
+
+/// State shared by the clones of a `CodeCache`
+#[derive(Debug, Default)]
+struct Inner {
+    /// Compiled code by what it was compiled from
+    entries: RefCell<HashMap<Key, Rc<Compiled>>>,
+    /// Lookups that found an entry
+    hits: Cell<u64>,
+    /// Lookups that found none
+    misses: Cell<u64>,
+}
+
+/// What a compilation depends on
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub(crate) struct Key {
+    /// RISC-V code as given to `Module::set_code()`
+    pub(crate) code: Vec<u8>,
+    /// Optimization passes run
+    pub(crate) opt_level: OptLevel,
+    /// Whether the code has the resume entry
+    pub(crate) resumable: bool,
+    /// Whether the code counts basic block entries
+    pub(crate) block_counters: bool,
+    /// Guest address of the first instruction
+    pub(crate) base: u32,
+    /// How constants get into registers
+    pub(crate) constants: ConstantStrategy,
+    /// Guest PC of each function given an entry
+    pub(crate) functions: Vec<u32>,
+    /// Decoder restricting the instruction set
+    pub(crate) decoder: Decoder,
+}
+
+/// Result of compiling one piece of code
+#[derive(Debug)]
+pub(crate) struct Compiled {
+    /// ARM64 code, as written to the module's buffer
+    pub(crate) code: Vec<u8>,
+    /// Guest addresses of the code
+    pub(crate) source_map: SourceMap,
+    /// Decoded RISC-V instructions
+    pub(crate) instructions: Vec<Instruction>,
+}
+
+impl CodeCache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Number of cached compilations
+    pub fn len(&self) -> usize {
+        self.inner.entries.borrow().len()
+    }
+
+    /// Whether nothing is cached
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop every cached compilation, keeping the hit and miss counts
+    pub fn clear(&self) {
+        self.inner.entries.borrow_mut().clear();
+    }
+
+    /// Number of compilations skipped because the code was cached
+    pub fn hits(&self) -> u64 {
+        self.inner.hits.get()
+    }
+
+    /// Number of compilations that found nothing cached
+    pub fn misses(&self) -> u64 {
+        self.inner.misses.get()
+    }
+
+    /// Look up a compilation, counting the hit or miss
+    pub(crate) fn get(&self, key: &Key) -> Option<Rc<Compiled>> {
+        let compiled = self.inner.entries.borrow().get(key).cloned();
+        let counter = match compiled {
+            Some(_) => &self.inner.hits,
+            None => &self.inner.misses,
+        };
+        counter.set(counter.get() + 1);
+        compiled
+    }
+
+    /// Record a compilation
+    pub(crate) fn insert(&self, key: Key, compiled: Compiled) {
+        self.inner
+            .entries
+            .borrow_mut()
+            .insert(key, Rc::new(compiled));
+    }
+}
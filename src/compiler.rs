@@ -2,33 +2,727 @@
 //!
 //! This module provides AOT (Ahead-Of-Time) compilation of RISC-V instructions
 //! to native ARM64 machine code.
+//!
+//! # Determinism
+//! Compiling the same instructions always produces byte-identical output, so
+//! hashes of compiled code can be used for attestation and caching. Code
+//! generation must therefore never embed host pointers or addresses (runtime
+//! state is reached through registers set up by the caller), must iterate
+//! only over ordered collections, and must not depend on the contents of the
+//! output buffer beyond what it writes.
+//!
+//! The code is also position independent (see `translator`): it runs at
+//! whatever address it is copied to, so compiled buffers can be stored and
+//! reloaded.
+//!
+//! # Calling convention
+//! Compiled code is an `extern "C" fn(*mut Context)` entered at its first
+//! instruction, at guest PC `Compiler::base()`, or at the entry of one of the
+//! functions given to `Compiler::set_functions()`, whose offsets are in the
+//! `SourceMap`.
+//! It runs until control leaves the module or an instruction needs the host,
+//! then returns with the exit reason and guest PC stored in the context. See
+//! the `translator` module for the code layout.
+//!
+//! # Basic blocks
+//! `Compiler::basic_blocks` splits instructions into straight-line runs
+//! entered only at their first instruction, with the edges between them.
+//! Gas is charged per block, and the blocks are the unit for analyses over
+//! the control flow graph.
 
-use crate::{Instruction, arm64};
+use crate::{
+    Disassembler, Instruction, arm64, fold,
+    module::CompileError,
+    peephole,
+    serial::{Reader, SerializeError, push_list, push_u32},
+    translator::{self, Options, Translator},
+    unwind::{self, Frame},
+};
+use std::{
+    fmt::Write,
+    ops::Range,
+    time::{Duration, Instant},
+};
 
 /// Compiles RISC-V instructions to ARM64 machine code
-pub struct Compiler;
+pub struct Compiler {
+    /// Which optimization passes run
+    opt_level: OptLevel,
+    /// Whether to add the resume entry
+    resumable: bool,
+    /// Whether to count basic block entries
+    block_counters: bool,
+    /// Guest address of the first instruction
+    base: u32,
+    /// How constants get into registers
+    constants: ConstantStrategy,
+    /// Most threads translating at once
+    threads: usize,
+    /// Guest PC of each function given an entry
+    functions: Vec<u32>,
+    /// Statistics of the last successful compile
+    stats: Option<CompileStats>,
+}
+
+/// Optimization passes run by the compiler, trading compile time for code
+/// quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum OptLevel {
+    /// No optimization, for the fastest compiles
+    #[default]
+    O0,
+    /// Per-instruction passes: `peephole::optimize`, branches testing a
+    /// register against zero directly and fused `fusion` pairs
+    O1,
+    /// O1 plus `fold::fold`, which needs the basic blocks
+    O2,
+}
+
+/// How compiled code loads constants needing more than one move, trading
+/// code size for latency
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum ConstantStrategy {
+    /// MOVZ/MOVK pairs in the code, for the lowest latency
+    #[default]
+    MoveWide,
+    /// One LDR (literal) each from a per-module pool of distinct values, for
+    /// smaller code when values repeat; code that could exceed the 1MB reach
+    /// of LDR keeps the moves
+    LiteralPool,
+}
+
+/// Stage of a compile, as timed in `CompileStats`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pass {
+    /// `fold::fold`, at `OptLevel::O2`
+    Fold,
+    /// `peephole::optimize`, from `OptLevel::O1`
+    Peephole,
+    /// Translation to ARM64, up to patching the branches
+    Translate,
+    /// Branch fixups and veneer placement
+    Link,
+}
+
+/// Statistics of one compile, for tracking code quality and compile time
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CompileStats {
+    /// Guest instructions in
+    pub instructions: usize,
+    /// ARM64 instructions out, including the entries, stubs, dispatch table
+    /// and literal pool
+    pub words: usize,
+    /// Loads and stores of guest registers kept in memory rather than in a
+    /// host register
+    pub spills: usize,
+    /// Veneers for branches beyond the reach of B
+    pub veneers: usize,
+    /// Time spent in each pass run, in order
+    pub passes: Vec<(Pass, Duration)>,
+}
+
+impl CompileStats {
+    /// ARM64 instructions out per guest instruction in, 0 for no
+    /// instructions
+    pub fn expansion(&self) -> f64 {
+        match self.instructions {
+            0 => 0.0,
+            count => self.words as f64 / count as f64,
+        }
+    }
+
+    /// Time spent in all passes
+    pub fn time(&self) -> Duration {
+        self.passes.iter().map(|&(_, time)| time).sum()
+    }
+}
+
+/// Run of instructions entered only at its first and left only after its last
+///
+/// Blocks start at the entry, at branch and JAL targets inside the module and
+/// after every branch, JAL, JALR, ECALL and EBREAK. Faults and unsupported
+/// instructions can stop any instruction and are not edges.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BasicBlock {
+    /// Index of the first instruction
+    pub start: usize,
+    /// Index one past the last instruction
+    pub end: usize,
+    /// Indices of the blocks control can continue to, in ascending order
+    pub successors: Vec<usize>,
+    /// Whether control can leave for a target outside the known blocks: a
+    /// branch or JAL outside the module, JALR, an ECALL or EBREAK stopping
+    /// execution, or running off the end of the module
+    pub exits: bool,
+}
 
 impl Compiler {
-    /// Creates a new compiler instance
+    /// Creates a new compiler instance, with optimizations disabled
     pub fn new() -> Self {
-        Self
+        Self::with_opt_level(OptLevel::O0)
+    }
+
+    /// Creates a compiler running the passes of `opt_level`
+    ///
+    /// Optimized code is never longer than unoptimized code, and stays
+    /// deterministic for each level.
+    pub fn with_opt_level(opt_level: OptLevel) -> Self {
+        Self {
+            opt_level,
+            resumable: false,
+            block_counters: false,
+            base: 0,
+            constants: ConstantStrategy::default(),
+            threads: 1,
+            functions: Vec::new(),
+            stats: None,
+        }
+    }
+
+    /// Set which optimization passes run
+    pub fn set_opt_level(&mut self, opt_level: OptLevel) {
+        self.opt_level = opt_level;
+    }
+
+    /// Which optimization passes run
+    pub fn opt_level(&self) -> OptLevel {
+        self.opt_level
+    }
+
+    /// Set whether compiled code can also be entered at any instruction
+    ///
+    /// Resumable code has a second entry, at `SourceMap::resume()`, which
+    /// starts at the PC in the `Context` instead of the first instruction,
+    /// charging the rest of its basic block as a JALR landing there would.
+    pub fn set_resumable(&mut self, resumable: bool) {
+        self.resumable = resumable;
+    }
+
+    /// Whether compiled code can be entered at any instruction
+    pub fn resumable(&self) -> bool {
+        self.resumable
+    }
+
+    /// Set whether compiled code counts entries into each basic block
+    ///
+    /// Each block increments its own counter after charging its gas; the
+    /// guest PC of each counted block is in `SourceMap::counters()` and the
+    /// counts are read with `Instance::block_counts()`. Entries landing in
+    /// the middle of a block, through a JALR or the resume entry, are not
+    /// counted.
+    pub fn set_block_counters(&mut self, block_counters: bool) {
+        self.block_counters = block_counters;
+    }
+
+    /// Whether compiled code counts entries into each basic block
+    pub fn block_counters(&self) -> bool {
+        self.block_counters
+    }
+
+    /// Set the guest address the code is loaded at (0 by default), rounded
+    /// down to a multiple of 4
+    ///
+    /// The instruction at index `i` gets guest PC `base + 4 * i`, which AUIPC
+    /// results, link values, exit PCs and `SourceMap` lookups use. Branch and
+    /// JAL targets are PC-relative, so the same code works at any base, but
+    /// what AUIPC computes does not.
+    pub fn set_base(&mut self, base: u32) {
+        self.base = base & !3;
+    }
+
+    /// Guest address of the first instruction
+    pub fn base(&self) -> u32 {
+        self.base
+    }
+
+    /// Set how constants get into registers (`MoveWide` by default)
+    pub fn set_constants(&mut self, constants: ConstantStrategy) {
+        self.constants = constants;
+    }
+
+    /// How constants get into registers
+    pub fn constants(&self) -> ConstantStrategy {
+        self.constants
+    }
+
+    /// Set the most threads translating at once (1 by default; 0 counts as 1)
+    ///
+    /// Large inputs are split into regions starting at basic blocks, each
+    /// translated on its own thread, and the regions are stitched together by
+    /// the fixup pass. The output is the same for any number of threads.
+    pub fn set_threads(&mut self, threads: usize) {
+        self.threads = threads.max(1);
+    }
+
+    /// Most threads translating at once
+    pub fn threads(&self) -> usize {
+        self.threads
+    }
+
+    /// Set the guest PC of each function, in function table order (none by
+    /// default)
+    ///
+    /// Each function gets an entry, at `SourceMap::function()` for its index,
+    /// which starts at its PC, charging the rest of its basic block as a JALR
+    /// landing there would. Functions sharing a PC share an entry. Every PC
+    /// must be an instruction inside the code (see `check_functions()`).
+    pub fn set_functions(&mut self, functions: &[u32]) {
+        self.functions = functions.to_vec();
+    }
+
+    /// Guest PC of each function given an entry
+    pub fn functions(&self) -> &[u32] {
+        &self.functions
+    }
+
+    /// Upper bound on the bytes of ARM64 code produced for `count` instructions
+    pub fn code_size(count: usize) -> usize {
+        translator::code_size(count)
+    }
+
+    /// Upper bound on the bytes of ARM64 code produced for `instructions`,
+    /// at any optimization level, base address and with any options
+    ///
+    /// Adds up the longest code of each instruction's class (a few words for
+    /// arithmetic, more for branches, the most for loads, stores and ECALLs)
+    /// instead of assuming the longest sequence everywhere, so it is usually
+    /// far below `code_size()`, which it never exceeds.
+    ///
+    /// Leaves no room for function entries; see `estimate_size_with()`.
+    pub fn estimate_size(instructions: &[Instruction]) -> usize {
+        Self::estimate_size_with(instructions, 0)
+    }
+
+    /// Like `estimate_size()`, with room for `functions` function entries
+    pub fn estimate_size_with(instructions: &[Instruction], functions: usize) -> usize {
+        translator::max_size(instructions, functions)
+    }
+
+    /// Statistics of the last compile, or `None` if it wrote nothing
+    pub fn stats(&self) -> Option<&CompileStats> {
+        self.stats.as_ref()
     }
 
     /// Compiles a slice of RISC-V instructions to ARM64
     ///
-    /// Currently only emits a single RET instruction regardless of input
-    /// Returns the number of bytes written to the buffer
-    pub fn compile(&mut self, _instructions: &[Instruction], buffer: &mut [u8]) -> usize {
-        // For now, just emit a RET instruction
-        let ret_bytes = arm64::RET.to_le_bytes();
+    /// Returns the number of bytes written to the buffer, or 0 if the buffer
+    /// is smaller than `estimate_size_with()` gives for the instructions and
+    /// functions, or a function is not at an instruction
+    pub fn compile(&mut self, instructions: &[Instruction], buffer: &mut [u8]) -> usize {
+        self.compile_mapped(instructions, buffer).0
+    }
 
-        // Ensure buffer has enough space
-        if buffer.len() < ret_bytes.len() {
-            return 0;
+    /// Compiles like `compile()`, also returning where each instruction's
+    /// code starts
+    ///
+    /// The map is empty when nothing was written.
+    pub fn compile_mapped(
+        &mut self,
+        instructions: &[Instruction],
+        buffer: &mut [u8],
+    ) -> (usize, SourceMap) {
+        if buffer.len() < Self::estimate_size_with(instructions, self.functions.len())
+            || Self::check_functions(instructions, self.base, &self.functions).is_err()
+        {
+            self.stats = None;
+            return (0, SourceMap::default());
+        }
+
+        let translate = |instructions: &[Instruction], optimize| {
+            let options = Options {
+                base: self.base,
+                optimize,
+                resumable: self.resumable,
+                count_blocks: self.block_counters,
+                literal_pool: self.constants == ConstantStrategy::LiteralPool,
+                functions: &self.functions,
+                threads: self.threads,
+            };
+            Translator::translate(instructions, &options)
+        };
+        let mut passes = Vec::new();
+        let optimized = match self.opt_level {
+            OptLevel::O0 => None,
+            OptLevel::O1 => Some(timed(&mut passes, Pass::Peephole, || {
+                peephole::optimize(instructions)
+            })),
+            OptLevel::O2 => {
+                let folded = timed(&mut passes, Pass::Fold, || {
+                    fold::fold_at(instructions, self.base)
+                });
+                Some(timed(&mut passes, Pass::Peephole, || {
+                    peephole::optimize(&folded)
+                }))
+            }
+        };
+        let start = Instant::now();
+        let translation = translate(
+            optimized.as_deref().unwrap_or(instructions),
+            optimized.is_some(),
+        );
+        let time = start.elapsed().saturating_sub(translation.link);
+        passes.extend([(Pass::Translate, time), (Pass::Link, translation.link)]);
+        debug_assert_eq!(translation.offsets.len(), instructions.len() + 1);
+        let code = translation.code;
+        for (chunk, word) in buffer.chunks_exact_mut(4).zip(&code) {
+            chunk.copy_from_slice(&word.to_le_bytes());
+        }
+        let offsets = translation
+            .offsets
+            .iter()
+            .map(|offset| offset * 4)
+            .collect();
+        let resume = translation.resume.map(|offset| offset * 4);
+        let functions = translation
+            .functions
+            .iter()
+            .map(|offset| offset * 4)
+            .collect();
+        let frames = translation
+            .frames
+            .iter()
+            .map(|&(offset, frame)| (offset * 4, frame))
+            .collect();
+        let counters = translation.counters;
+        self.stats = Some(CompileStats {
+            instructions: instructions.len(),
+            words: code.len(),
+            spills: translation.spills,
+            veneers: translation.veneers,
+            passes,
+        });
+        (
+            code.len() * 4,
+            SourceMap {
+                base: self.base,
+                offsets,
+                resume,
+                functions,
+                frames,
+                counters,
+            },
+        )
+    }
+
+    /// Render compiled code as a listing annotated with the guest code
+    ///
+    /// Each guest instruction, with branch targets resolved, is followed by
+    /// the ARM64 instructions `map` places under it. Code outside any guest
+    /// instruction is listed under `prologue:` and `end:` headers.
+    pub fn listing(instructions: &[Instruction], code: &[u8], map: &SourceMap) -> String {
+        let words = |range: Range<usize>, output: &mut String| {
+            let end = range.end.min(code.len());
+            for offset in (range.start..end).step_by(4) {
+                let word = u32::from_le_bytes(code[offset..offset + 4].try_into().unwrap());
+                let text = arm64::disassemble(word, offset);
+                writeln!(output, "    0x{:04x}:  {}", offset, text).unwrap();
+            }
+        };
+        let mut output = String::new();
+        if map.is_empty() {
+            return output;
+        }
+        let disassembler = Disassembler::new(map.base);
+        output.push_str("prologue:\n");
+        words(0..map.offsets[0], &mut output);
+        for (index, instruction) in instructions.iter().enumerate().take(map.len()) {
+            let pc = map.base.wrapping_add(index as u32 * 4);
+            let text = disassembler.format(instruction, pc);
+            writeln!(output, "0x{:08x}:  {}", pc, text).unwrap();
+            words(map.offsets[index]..map.offsets[index + 1], &mut output);
+        }
+        output.push_str("end:\n");
+        words(map.offsets[map.len()]..code.len(), &mut output);
+        output
+    }
+}
+
+impl Compiler {
+    /// Split instructions into basic blocks, in address order
+    pub fn basic_blocks(instructions: &[Instruction]) -> Vec<BasicBlock> {
+        let count = instructions.len();
+        // Index of the instruction at a guest PC, if inside the module
+        let index = |pc: u32| {
+            let index = (pc / 4) as usize;
+            (pc % 4 == 0 && index < count).then_some(index)
+        };
+
+        let mut leaders = vec![false; count + 1];
+        leaders[0] = true;
+        for (at, instruction) in instructions.iter().enumerate() {
+            if let Some(targets) = transfers(at as u32 * 4, instruction) {
+                leaders[at + 1] = true;
+                for target in targets.into_iter().flatten().filter_map(index) {
+                    leaders[target] = true;
+                }
+            }
+        }
+
+        // Block number of each leader
+        let mut numbers = vec![0; count];
+        let mut starts = Vec::new();
+        for at in (0..count).filter(|&at| leaders[at]) {
+            numbers[at] = starts.len();
+            starts.push(at);
+        }
+
+        let mut blocks = Vec::with_capacity(starts.len());
+        for (number, &start) in starts.iter().enumerate() {
+            let end = starts.get(number + 1).copied().unwrap_or(count);
+            let pc = (end - 1) as u32 * 4;
+            let targets = transfers(pc, &instructions[end - 1])
+                .unwrap_or_else(|| vec![Some(pc.wrapping_add(4))]);
+            let mut successors = Vec::new();
+            let mut exits = false;
+            for target in targets {
+                match target.and_then(index) {
+                    Some(target) => successors.push(numbers[target]),
+                    None => exits = true,
+                }
+            }
+            successors.sort_unstable();
+            successors.dedup();
+            blocks.push(BasicBlock {
+                start,
+                end,
+                successors,
+                exits,
+            });
+        }
+        blocks
+    }
+
+    /// Check that every static branch and JAL target inside the module lands
+    /// on an instruction boundary
+    ///
+    /// Targets outside the module are left alone, as they exit to the host.
+    ///
+    /// # Errors
+    /// Returns `CompileError::InvalidJumpTarget` for the first instruction
+    /// whose target is inside the module but not at an instruction
+    pub fn check_targets(instructions: &[Instruction]) -> Result<(), CompileError> {
+        let end = instructions.len() as u64 * 4;
+        for (at, instruction) in instructions.iter().enumerate() {
+            let offset = at as u32 * 4;
+            let targets = transfers(offset, instruction).unwrap_or_default();
+            for target in targets.into_iter().flatten() {
+                if (target as u64) < end && target % 4 != 0 {
+                    return Err(CompileError::InvalidJumpTarget { offset, target });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Check that the PC of every function is an instruction of the code
+    /// loaded at `base`
+    ///
+    /// # Errors
+    /// Returns `CompileError::InvalidFunction` for the first function outside
+    /// the code or not at an instruction boundary
+    pub fn check_functions(
+        instructions: &[Instruction],
+        base: u32,
+        functions: &[u32],
+    ) -> Result<(), CompileError> {
+        for (index, &address) in functions.iter().enumerate() {
+            let offset = address.wrapping_sub(base);
+            if offset % 4 != 0 || offset as usize / 4 >= instructions.len() {
+                return Err(CompileError::InvalidFunction { index, address });
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Run `pass`, recording the time it takes in `passes`
+fn timed<T>(passes: &mut Vec<(Pass, Duration)>, pass: Pass, run: impl FnOnce() -> T) -> T {
+    let start = Instant::now();
+    let result = run();
+    passes.push((pass, start.elapsed()));
+    result
+}
+
+/// Guest PCs control can pass to after an instruction that ends a basic block,
+/// with `None` for targets not known statically, or `None` overall for
+/// instructions that only fall through
+fn transfers(pc: u32, instruction: &Instruction) -> Option<Vec<Option<u32>>> {
+    use Instruction::*;
+
+    let next = pc.wrapping_add(4);
+    Some(match *instruction {
+        Beq { imm, .. }
+        | Bne { imm, .. }
+        | Blt { imm, .. }
+        | Bge { imm, .. }
+        | Bltu { imm, .. }
+        | Bgeu { imm, .. } => vec![Some(next), Some(pc.wrapping_add(imm as u32))],
+        Jal { imm, .. } => vec![Some(pc.wrapping_add(imm as u32))],
+        Jalr { .. } | Ebreak => vec![None],
+        // The ECALL handler may resume after it or stop
+        Ecall => vec![Some(next), None],
+        _ => return None,
+    })
+}
+
+/// Mapping between guest instruction addresses and compiled code offsets
+///
+/// Offsets are in bytes from the start of the compiled code. Each guest
+/// instruction's code begins with the gas charge when it starts a basic
+/// block; instructions that emit nothing share their offset with the next.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SourceMap {
+    /// Guest PC of the first instruction
+    base: u32,
+    /// Code offset of each instruction, then of the end sequence
+    offsets: Vec<usize>,
+    /// Code offset of the resume entry
+    resume: Option<usize>,
+    /// Code offset of the entry of each function
+    functions: Vec<usize>,
+    /// Code offset where each state of the frame starts
+    frames: Vec<(usize, Frame)>,
+    /// Guest PC of each counted basic block
+    counters: Vec<u32>,
+}
+
+impl SourceMap {
+    /// Number of guest instructions mapped
+    pub fn len(&self) -> usize {
+        self.offsets.len().saturating_sub(1)
+    }
+
+    /// Whether no instructions are mapped
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Guest PC of the first instruction
+    pub fn base(&self) -> u32 {
+        self.base
+    }
+
+    /// Range of code offsets emitted for the instruction at guest `pc`
+    pub fn code(&self, pc: u32) -> Option<Range<usize>> {
+        let offset = pc.wrapping_sub(self.base);
+        let index = (offset % 4 == 0).then_some(offset as usize / 4)?;
+        (index < self.len()).then(|| self.offsets[index]..self.offsets[index + 1])
+    }
+
+    /// Code offset of the entry starting at the `Context` PC, for code
+    /// compiled resumable
+    pub fn resume(&self) -> Option<usize> {
+        self.resume
+    }
+
+    /// Code offset of the entry of the function at `index` in the function
+    /// table
+    ///
+    /// Without a function table, the code is a single function entered at
+    /// its first instruction, offset 0.
+    pub fn function(&self, index: usize) -> Option<usize> {
+        if self.functions.is_empty() {
+            (index == 0).then_some(0)
+        } else {
+            self.functions.get(index).copied()
+        }
+    }
+
+    /// Number of entries in the function table
+    pub fn functions(&self) -> usize {
+        self.functions.len()
+    }
+
+    /// Build an `.eh_frame` section describing the frames of the mapped
+    /// code, loaded at host address `address`
+    ///
+    /// See the `unwind` module. Empty when nothing is mapped.
+    pub fn unwind_info(&self, address: u64, len: usize) -> Vec<u8> {
+        if self.frames.is_empty() {
+            Vec::new()
+        } else {
+            unwind::eh_frame(address, len, &self.frames)
         }
+    }
+
+    /// Guest PC of the basic block behind each `Instance::block_counts()`
+    /// entry, in address order; empty unless compiled with block counters
+    pub fn counters(&self) -> &[u32] {
+        &self.counters
+    }
+
+    /// Code offset of the instruction at guest `pc`
+    pub fn native(&self, pc: u32) -> Option<usize> {
+        self.code(pc).map(|code| code.start)
+    }
 
-        buffer[..ret_bytes.len()].copy_from_slice(&ret_bytes);
-        ret_bytes.len()
+    /// Guest PC of the instruction whose code contains `offset`
+    ///
+    /// Offsets in the prologue, the end sequence, the exit stub and the
+    /// dispatch routine have none.
+    pub fn guest(&self, offset: usize) -> Option<u32> {
+        let index = self
+            .offsets
+            .partition_point(|&start| start <= offset)
+            .checked_sub(1)?;
+        (index < self.len() && offset < self.offsets[index + 1])
+            .then(|| self.base.wrapping_add(index as u32 * 4))
+    }
+
+    /// Append the map in the `serial` format
+    pub(crate) fn write(&self, bytes: &mut Vec<u8>) {
+        push_u32(bytes, self.base);
+        push_list(bytes, self.offsets.iter().map(|&offset| offset as u32));
+        push_u32(bytes, self.resume.map_or(0, |offset| offset as u32 + 1));
+        push_list(bytes, self.functions.iter().map(|&offset| offset as u32));
+        push_u32(bytes, self.frames.len() as u32);
+        for &(offset, frame) in &self.frames {
+            push_u32(bytes, offset as u32);
+            push_u32(bytes, frame as u32);
+        }
+        push_list(bytes, self.counters.iter().copied());
+    }
+
+    /// Read a map in the `serial` format for `len` bytes of code, checking
+    /// that every offset is within the code
+    pub(crate) fn read(reader: &mut Reader, len: usize) -> Result<SourceMap, SerializeError> {
+        let base = reader.u32()?;
+        let offsets: Vec<usize> = reader
+            .list()?
+            .into_iter()
+            .map(|offset| offset as usize)
+            .collect();
+        let resume = reader.u32()?.checked_sub(1).map(|offset| offset as usize);
+        let functions: Vec<usize> = reader
+            .list()?
+            .into_iter()
+            .map(|offset| offset as usize)
+            .collect();
+        let mut frames = Vec::new();
+        for _ in 0..reader.u32()? {
+            let offset = reader.u32()? as usize;
+            let frame = match reader.u32()? {
+                0 => Frame::Entry,
+                1 => Frame::Pushed,
+                2 => Frame::Framed,
+                _ => return Err(SerializeError::InvalidSourceMap),
+            };
+            frames.push((offset, frame));
+        }
+        let counters = reader.list()?;
+        let offsets_valid = offsets.is_sorted() && offsets.last().is_none_or(|&end| end <= len);
+        let entries_valid = resume.iter().chain(&functions).all(|&entry| entry < len);
+        if !offsets_valid || !entries_valid || frames.iter().any(|&(offset, _)| offset > len) {
+            return Err(SerializeError::InvalidSourceMap);
+        }
+        Ok(SourceMap {
+            base,
+            offsets,
+            resume,
+            functions,
+            frames,
+            counters,
+        })
     }
 }
 
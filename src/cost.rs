@@ -0,0 +1,79 @@
+//! Per-instruction cost model
+//!
+//! Assigns a cost to each instruction according to its `InstructionKind`. The
+//! default table approximates execution latency in cycles on a simple in-order
+//! core (a divide costs far more than an add), which is useful for estimating
+//! the performance of guest code. Custom tables can weight kinds differently,
+//! for example to price gas non-uniformly.
+//!
+//! # Example
+//! ```
+//! use jigs::{CostModel, Instruction, InstructionKind};
+//!
+//! let model = CostModel::default().with(InstructionKind::Div, 40);
+//! let code = [
+//!     Instruction::Addi { rd: 1, rs1: 0, imm: 7 },
+//!     Instruction::Div { rd: 2, rs1: 1, rs2: 1 },
+//! ];
+//! assert_eq!(model.cost(&code[0]), 1);
+//! assert_eq!(model.total(&code), 41);
+//! ```
+
+use crate::{Instruction, InstructionKind};
+use std::collections::BTreeMap;
+
+/// Cost table keyed by instruction kind
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CostModel {
+    /// Cost of every instruction kind
+    costs: BTreeMap<InstructionKind, u32>,
+}
+
+impl CostModel {
+    /// Create a model where every instruction costs the same
+    pub fn uniform(cost: u32) -> Self {
+        CostModel {
+            costs: InstructionKind::ALL
+                .into_iter()
+                .map(|kind| (kind, cost))
+                .collect(),
+        }
+    }
+
+    /// Set the cost of an instruction kind
+    pub fn with(mut self, kind: InstructionKind, cost: u32) -> Self {
+        self.costs.insert(kind, cost);
+        self
+    }
+
+    /// Cost of an instruction kind
+    pub fn get(&self, kind: InstructionKind) -> u32 {
+        self.costs[&kind]
+    }
+
+    /// Cost of a single instruction
+    pub fn cost(&self, instruction: &Instruction) -> u32 {
+        self.get(instruction.kind())
+    }
+
+    /// Summed cost of a sequence of instructions
+    pub fn total(&self, instructions: &[Instruction]) -> u64 {
+        instructions
+            .iter()
+            .map(|instruction| self.cost(instruction) as u64)
+            .sum()
+    }
+}
+
+impl Default for CostModel {
+    /// Approximate latencies in cycles
+    fn default() -> Self {
+        CostModel::uniform(1)
+            .with(InstructionKind::Load, 3)
+            .with(InstructionKind::Jump, 2)
+            .with(InstructionKind::Mul, 3)
+            .with(InstructionKind::Div, 20)
+            .with(InstructionKind::Vector, 4)
+            .with(InstructionKind::Float, 4)
+    }
+}
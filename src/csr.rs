@@ -0,0 +1,120 @@
+//! Control and status register (CSR) name registry
+//!
+//! Maps 12-bit CSR addresses to their standard names and back. Used to render
+//! CSR operands symbolically (e.g. `csrr x5, cycle`) and to resolve symbolic
+//! CSR references when parsing assembly.
+//!
+//! # Example
+//! ```
+//! use jigs::csr;
+//!
+//! assert_eq!(csr::name(0xC00), Some("cycle"));
+//! assert_eq!(csr::address("mstatus"), Some(0x300));
+//! assert_eq!(csr::format(0x7FF), "0x7ff");
+//! ```
+
+/// Cycle counter for RDCYCLE
+pub const CYCLE: u16 = 0xC00;
+
+/// Timer for RDTIME
+pub const TIME: u16 = 0xC01;
+
+/// Instructions-retired counter for RDINSTRET
+pub const INSTRET: u16 = 0xC02;
+
+/// Upper 32 bits of cycle (RV32 only)
+pub const CYCLEH: u16 = 0xC80;
+
+/// Upper 32 bits of time (RV32 only)
+pub const TIMEH: u16 = 0xC81;
+
+/// Upper 32 bits of instret (RV32 only)
+pub const INSTRETH: u16 = 0xC82;
+
+/// Known CSRs as (address, name), sorted by address
+const REGISTRY: &[(u16, &str)] = &[
+    // Unprivileged floating-point
+    (0x001, "fflags"),
+    (0x002, "frm"),
+    (0x003, "fcsr"),
+    // Unprivileged vector
+    (0x008, "vstart"),
+    (0x009, "vxsat"),
+    (0x00A, "vxrm"),
+    (0x00F, "vcsr"),
+    // Supervisor
+    (0x100, "sstatus"),
+    (0x104, "sie"),
+    (0x105, "stvec"),
+    (0x106, "scounteren"),
+    (0x140, "sscratch"),
+    (0x141, "sepc"),
+    (0x142, "scause"),
+    (0x143, "stval"),
+    (0x144, "sip"),
+    (0x180, "satp"),
+    // Machine trap setup
+    (0x300, "mstatus"),
+    (0x301, "misa"),
+    (0x302, "medeleg"),
+    (0x303, "mideleg"),
+    (0x304, "mie"),
+    (0x305, "mtvec"),
+    (0x306, "mcounteren"),
+    (0x310, "mstatush"),
+    // Machine trap handling
+    (0x340, "mscratch"),
+    (0x341, "mepc"),
+    (0x342, "mcause"),
+    (0x343, "mtval"),
+    (0x344, "mip"),
+    // Machine counters
+    (0xB00, "mcycle"),
+    (0xB02, "minstret"),
+    (0xB80, "mcycleh"),
+    (0xB82, "minstreth"),
+    // Unprivileged counters
+    (CYCLE, "cycle"),
+    (TIME, "time"),
+    (INSTRET, "instret"),
+    (0xC20, "vl"),
+    (0xC21, "vtype"),
+    (0xC22, "vlenb"),
+    (CYCLEH, "cycleh"),
+    (TIMEH, "timeh"),
+    (INSTRETH, "instreth"),
+    // Machine information
+    (0xF11, "mvendorid"),
+    (0xF12, "marchid"),
+    (0xF13, "mimpid"),
+    (0xF14, "mhartid"),
+];
+
+/// Get the standard name of a CSR
+pub fn name(address: u16) -> Option<&'static str> {
+    REGISTRY
+        .binary_search_by_key(&address, |(csr, _)| *csr)
+        .ok()
+        .map(|index| REGISTRY[index].1)
+}
+
+/// Get the address of a CSR by its standard name
+pub fn address(name: &str) -> Option<u16> {
+    REGISTRY
+        .iter()
+        .find(|(_, csr)| *csr == name)
+        .map(|(address, _)| *address)
+}
+
+/// Render a CSR by name, or as `0x`-prefixed hex if it has no standard name
+pub fn format(address: u16) -> String {
+    match name(address) {
+        Some(name) => name.to_string(),
+        None => format!("0x{:x}", address),
+    }
+}
+
+/// Iterate over all known CSRs as (address, name) in address order
+pub fn all() -> impl Iterator<Item = (u16, &'static str)> {
+    REGISTRY.iter().copied()
+}
@@ -0,0 +1,139 @@
+//! `std::io` adapter over guest memory
+//!
+//! `MemoryCursor` exposes a window of guest memory as a seekable byte stream,
+//! so anything that reads from an `io::Read` or writes to an `io::Write` (image
+//! loaders, serializers, compressors) can stream directly into or out of a
+//! guest without an intermediate buffer. Like `std::io::Cursor` over a slice,
+//! the window has a fixed length: reads stop at the end and writes past the end
+//! are truncated.
+//!
+//! # Example
+//! ```
+//! use jigs::{Memory, MemoryCursor, PageStore};
+//! use std::io::{Read, Seek, SeekFrom, Write};
+//!
+//! let store = PageStore::new(16);
+//! let mut memory = Memory::new(&store, 16, 4);
+//! let mut cursor = MemoryCursor::new(&mut memory, 0x1000, 64);
+//! cursor.write_all(b"hello").unwrap();
+//! cursor.seek(SeekFrom::Start(0)).unwrap();
+//! let mut text = [0u8; 5];
+//! cursor.read_exact(&mut text).unwrap();
+//! assert_eq!(&text, b"hello");
+//! ```
+
+use crate::Memory;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+/// Seekable reader and writer over `[address, address + len)` of guest memory
+///
+/// Reads go through `Memory::read` (unallocated memory reads as zeros) and
+/// writes through `Memory::write`, so page permissions are not checked.
+#[derive(Debug)]
+pub struct MemoryCursor<'a> {
+    /// Memory being accessed
+    memory: &'a mut Memory,
+    /// Guest address of the start of the window
+    address: u32,
+    /// Length of the window in bytes
+    len: u64,
+    /// Offset of the next access from `address`
+    position: u64,
+}
+
+impl<'a> MemoryCursor<'a> {
+    /// Create a cursor over `len` bytes starting at `address`
+    ///
+    /// The window may extend up to 4GB and wraps past 0xFFFFFFFF like
+    /// `Memory::read`.
+    pub fn new(memory: &'a mut Memory, address: u32, len: u64) -> Self {
+        MemoryCursor {
+            memory,
+            address,
+            len: len.min(1 << 32),
+            position: 0,
+        }
+    }
+
+    /// Guest address of the start of the window
+    pub fn address(&self) -> u32 {
+        self.address
+    }
+
+    /// Length of the window in bytes
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Check if the window is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Offset of the next access from the start of the window
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// Set the offset of the next access
+    pub fn set_position(&mut self, position: u64) {
+        self.position = position;
+    }
+
+    /// Guest address of the next access
+    pub fn guest_address(&self) -> u32 {
+        self.address.wrapping_add(self.position as u32)
+    }
+
+    /// Number of bytes that can be accessed at the current position, up to `wanted`
+    fn available(&self, wanted: usize) -> usize {
+        self.len.saturating_sub(self.position).min(wanted as u64) as usize
+    }
+}
+
+impl Read for MemoryCursor<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let count = self.available(buf.len());
+        self.memory.read(self.guest_address(), &mut buf[..count]);
+        self.position += count as u64;
+        Ok(count)
+    }
+}
+
+impl Write for MemoryCursor<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let count = self.available(buf.len());
+        self.memory
+            .write(self.guest_address(), &buf[..count])
+            .map_err(io::Error::other)?;
+        self.position += count as u64;
+        Ok(count)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Seek for MemoryCursor<'_> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(offset) => {
+                self.position = offset;
+                return Ok(offset);
+            }
+            SeekFrom::End(offset) => (self.len, offset),
+            SeekFrom::Current(offset) => (self.position, offset),
+        };
+        match base.checked_add_signed(offset) {
+            Some(position) => {
+                self.position = position;
+                Ok(position)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
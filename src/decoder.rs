@@ -0,0 +1,227 @@
+//! Configurable instruction decoding
+//!
+//! `Instruction::decode` accepts every encoding the crate understands. Sandboxed
+//! runtimes often need to restrict the accepted ISA surface, so a `Decoder` is
+//! configured with a set of enabled `Extensions`; instructions belonging to a
+//! disabled extension decode as `Instruction::Unsupported` and are treated as
+//! illegal by the interpreter and compiler.
+//!
+//! # Example
+//! ```
+//! use jigs::{Decoder, Extensions, Instruction};
+//!
+//! let decoder = Decoder::new(Extensions { m: false, ..Extensions::default() });
+//!
+//! // add x1, x2, x3 is base ISA and always accepted
+//! assert_eq!(decoder.decode(0x003100B3), Instruction::Add { rd: 1, rs1: 2, rs2: 3 });
+//!
+//! // mul x1, x2, x3 is rejected with M disabled
+//! assert_eq!(decoder.decode(0x023100B3), Instruction::Unsupported(0x023100B3));
+//! ```
+
+use crate::Instruction;
+
+/// RISC-V ISA extensions recognized by the decoder
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Extension {
+    /// Base integer instruction set (always enabled)
+    I,
+    /// Integer multiplication and division
+    M,
+    /// Compressed 16-bit instructions
+    C,
+    /// Vector operations
+    V,
+    /// Half-precision floating-point
+    Zfh,
+}
+
+/// Set of enabled ISA extensions
+///
+/// The default enables RV32IM. Extensions the runtime cannot execute yet (such
+/// as V and Zfh) must be enabled explicitly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Extensions {
+    /// Integer multiplication and division
+    pub m: bool,
+    /// Compressed instructions
+    ///
+    /// Compressed encodings are not decoded yet; enabling C relaxes the
+    /// instruction alignment to 2 bytes and makes streams split off 16-bit
+    /// parcels.
+    pub c: bool,
+    /// Vector operations
+    pub v: bool,
+    /// Half-precision floating-point
+    pub zfh: bool,
+}
+
+/// Iterator decoding a buffer of mixed-width instructions
+///
+/// Yields each instruction with its byte offset in the buffer, advancing by
+/// its width under the decoder (see `Decoder::width()`). With C enabled,
+/// compressed encodings are not decoded yet and are yielded as 2-byte
+/// `Unsupported` parcels; without it the buffer is read as whole words, like
+/// `Decoder::decode_words()`. Trailing bytes that do not form a complete
+/// instruction are ignored.
+#[derive(Debug, Clone)]
+pub struct Stream<'a> {
+    /// Decoder applied to each instruction
+    decoder: Decoder,
+    /// Code being decoded
+    code: &'a [u8],
+    /// Offset of the next instruction
+    offset: usize,
+}
+
+/// Instruction decoder restricted to a set of extensions
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Decoder {
+    /// Enabled extensions
+    extensions: Extensions,
+}
+
+impl Extensions {
+    /// Only the base integer instruction set
+    pub const fn base() -> Self {
+        Extensions {
+            m: false,
+            c: false,
+            v: false,
+            zfh: false,
+        }
+    }
+
+    /// Every extension the crate knows about
+    pub const fn all() -> Self {
+        Extensions {
+            m: true,
+            c: true,
+            v: true,
+            zfh: true,
+        }
+    }
+
+    /// Check if an extension is enabled
+    pub fn enabled(&self, extension: Extension) -> bool {
+        match extension {
+            Extension::I => true,
+            Extension::M => self.m,
+            Extension::C => self.c,
+            Extension::V => self.v,
+            Extension::Zfh => self.zfh,
+        }
+    }
+
+    /// Required alignment of instruction addresses in bytes (2 with C, else 4)
+    pub fn alignment(&self) -> u32 {
+        if self.c { 2 } else { 4 }
+    }
+}
+
+impl Default for Extensions {
+    fn default() -> Self {
+        Extensions {
+            m: true,
+            c: false,
+            v: false,
+            zfh: false,
+        }
+    }
+}
+
+impl Decoder {
+    /// Create a decoder accepting the given extensions
+    pub fn new(extensions: Extensions) -> Self {
+        Decoder { extensions }
+    }
+
+    /// Get the enabled extensions
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Decode an instruction word, rejecting disabled extensions
+    ///
+    /// Instructions from a disabled extension decode as `Unsupported(word)`.
+    pub fn decode(&self, word: u32) -> Instruction {
+        let instruction = Instruction::decode(word);
+        if self.accepts(&instruction) {
+            instruction
+        } else {
+            Instruction::Unsupported(word)
+        }
+    }
+
+    /// Width in bytes of the instruction starting with the 16-bit `parcel`
+    ///
+    /// Compressed encodings are 2 bytes with C enabled; every other
+    /// instruction, and every instruction without C, is a 4-byte word.
+    pub fn width(&self, parcel: u32) -> u32 {
+        if self.extensions.c {
+            Instruction::length(parcel)
+        } else {
+            4
+        }
+    }
+
+    /// Size in bytes of an instruction this decoder produced (2 or 4)
+    ///
+    /// Every recognized instruction is 4 bytes. An unsupported word is as wide
+    /// as `width()` of its low parcel: a 16-bit parcel split off with C
+    /// enabled is 2 bytes, and without C every word is 4, whatever its value.
+    pub fn size(&self, instruction: &Instruction) -> u32 {
+        match instruction {
+            Instruction::Unsupported(word) => self.width(*word),
+            _ => 4,
+        }
+    }
+
+    /// Decode little-endian code as whole 32-bit words, ignoring trailing
+    /// bytes
+    ///
+    /// This is the layout modules compile, one instruction per word at
+    /// `base + 4 * index`: compressed encodings are not compiled, so their
+    /// parcels are not split even with C enabled.
+    pub fn decode_words(&self, code: &[u8]) -> Vec<Instruction> {
+        code.chunks_exact(4)
+            .map(|chunk| self.decode(u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]])))
+            .collect()
+    }
+
+    /// Decode a buffer of little-endian code into a stream of (offset, instruction)
+    pub fn stream<'a>(&self, code: &'a [u8]) -> Stream<'a> {
+        Stream {
+            decoder: *self,
+            code,
+            offset: 0,
+        }
+    }
+
+    /// Check if an instruction belongs to an enabled extension
+    pub fn accepts(&self, instruction: &Instruction) -> bool {
+        self.extensions.enabled(instruction.extension())
+    }
+}
+
+impl Iterator for Stream<'_> {
+    type Item = (u32, Instruction);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let rest = self.code.get(self.offset..)?;
+        let parcel = u16::from_le_bytes([*rest.first()?, *rest.get(1)?]) as u32;
+        let width = self.decoder.width(parcel);
+        let instruction = match width {
+            2 => Instruction::Unsupported(parcel),
+            _ => {
+                let bytes = rest.get(..4)?;
+                let word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+                self.decoder.decode(word)
+            }
+        };
+        let offset = self.offset as u32;
+        self.offset += width as usize;
+        Some((offset, instruction))
+    }
+}
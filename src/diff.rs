@@ -0,0 +1,265 @@
+//! Comparison of guest memory
+//!
+//! `Memory::diff()` compares two instances and `Memory::diff_snapshot()`
+//! compares an instance against a `MemorySnapshot` taken earlier, reporting the
+//! pages and byte ranges that differ. This is the building block for
+//! differential testing (running the same program under the JIT and the
+//! interpreter) and for checking that replicas reached the same state.
+//!
+//! Only guest-visible state is compared: an unallocated page reads as zeros
+//! with default permissions, so it matches an allocated page of zeros.
+//!
+//! `Memory::hash_range()` and `state_root()` fingerprint guest contents with
+//! any `std::hash::Hasher`, so hosts can compare state without keeping a copy.
+//! They follow the same rule: zero-filled pages are skipped whether allocated
+//! or not, so memories with equal contents hash equally.
+//!
+//! # Example
+//! ```
+//! use jigs::{DiffRange, Memory, PageStore};
+//!
+//! let store = PageStore::new(16);
+//! let mut memory = Memory::new(&store, 8, 4);
+//! memory.write(0x4000, &[1, 2, 3, 4]).unwrap();
+//! let snapshot = memory.snapshot();
+//!
+//! memory.write(0x4001, &[0xff, 0xff]).unwrap();
+//! let diff = memory.diff_snapshot(&snapshot);
+//! assert_eq!(diff.pages, [0x4000]);
+//! assert_eq!(diff.ranges, [DiffRange { address: 0x4001, len: 2 }]);
+//! ```
+
+use crate::memory::{Memory, PAGE_SIZE, Permissions};
+use std::{borrow::Cow, collections::BTreeMap, fmt, hash::Hasher, ops::Range};
+
+/// Contents of an unallocated page
+static ZERO_PAGE: [u8; PAGE_SIZE] = [0; PAGE_SIZE];
+
+/// Copy of the mapped pages of a Memory and their permissions
+///
+/// Created with `Memory::snapshot()`. The snapshot owns its copy, so it stays
+/// valid after the memory is modified, reset or dropped.
+#[derive(Clone, PartialEq, Eq)]
+pub struct MemorySnapshot {
+    /// Permissions and contents of each mapped page, by page address
+    pages: BTreeMap<u32, (Permissions, Box<[u8]>)>,
+}
+
+impl MemorySnapshot {
+    /// Number of pages captured
+    pub fn pages(&self) -> usize {
+        self.pages.len()
+    }
+
+    /// Compare with another snapshot
+    pub fn diff(&self, other: &MemorySnapshot) -> MemoryDiff {
+        diff_pages(self, other)
+    }
+
+    /// Hash the captured contents, matching `Memory::state_root()` at the
+    /// time the snapshot was taken
+    pub fn state_root<H: Hasher + Default>(&self) -> u64 {
+        let mut hasher = H::default();
+        hash_pages(self, 0..1 << 32, &mut hasher);
+        hasher.finish()
+    }
+}
+
+impl fmt::Debug for MemorySnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("MemorySnapshot")
+            .field("pages", &self.pages.len())
+            .finish()
+    }
+}
+
+/// Differences between two memories
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemoryDiff {
+    /// Addresses of pages whose contents or permissions differ, in order
+    pub pages: Vec<u32>,
+    /// Ranges of differing bytes in address order, merged across pages
+    pub ranges: Vec<DiffRange>,
+}
+
+impl MemoryDiff {
+    /// Check if the memories are identical
+    pub fn is_empty(&self) -> bool {
+        self.pages.is_empty()
+    }
+
+    /// Total number of differing bytes
+    pub fn bytes(&self) -> u64 {
+        self.ranges.iter().map(|range| range.len).sum()
+    }
+}
+
+/// Contiguous range of differing bytes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DiffRange {
+    /// Guest address of the first differing byte
+    pub address: u32,
+    /// Number of bytes
+    pub len: u64,
+}
+
+impl DiffRange {
+    /// Address one past the last byte (2^32 for a range ending at 0xFFFFFFFF)
+    pub fn end(&self) -> u64 {
+        self.address as u64 + self.len
+    }
+}
+
+impl fmt::Display for DiffRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:08x}-0x{:08x}", self.address, self.end() - 1)
+    }
+}
+
+/// Source of page contents for a comparison
+trait Pages {
+    /// Addresses of the mapped pages, in order
+    fn mapped(&self) -> Vec<u32>;
+    /// Permissions and contents of the mapped page at `page_base`
+    fn page(&self, page_base: u32) -> Option<(Permissions, Cow<'_, [u8]>)>;
+}
+
+impl Pages for Memory {
+    fn mapped(&self) -> Vec<u32> {
+        self.regions()
+            .flat_map(|region| {
+                (0..region.pages).map(move |page| region.address + page * PAGE_SIZE as u32)
+            })
+            .collect()
+    }
+
+    fn page(&self, page_base: u32) -> Option<(Permissions, Cow<'_, [u8]>)> {
+        let bytes = self.page_bytes(page_base)?;
+        Some((self.permissions(page_base), bytes))
+    }
+}
+
+impl Pages for MemorySnapshot {
+    fn mapped(&self) -> Vec<u32> {
+        self.pages.keys().copied().collect()
+    }
+
+    fn page(&self, page_base: u32) -> Option<(Permissions, Cow<'_, [u8]>)> {
+        let (permissions, bytes) = self.pages.get(&page_base)?;
+        Some((*permissions, Cow::Borrowed(bytes)))
+    }
+}
+
+/// Compare every page mapped on either side
+fn diff_pages(left: &impl Pages, right: &impl Pages) -> MemoryDiff {
+    let mut bases = left.mapped();
+    bases.extend(right.mapped());
+    bases.sort_unstable();
+    bases.dedup();
+
+    let unmapped = || (Permissions::ALL, Cow::Borrowed(&ZERO_PAGE[..]));
+    let mut diff = MemoryDiff::default();
+    for base in bases {
+        let (left_permissions, left_bytes) = left.page(base).unwrap_or_else(unmapped);
+        let (right_permissions, right_bytes) = right.page(base).unwrap_or_else(unmapped);
+        let mut differs = left_permissions != right_permissions;
+        for (offset, (a, b)) in left_bytes.iter().zip(right_bytes.iter()).enumerate() {
+            if a == b {
+                continue;
+            }
+            differs = true;
+            let address = base + offset as u32;
+            match diff.ranges.last_mut() {
+                Some(last) if last.end() == address as u64 => last.len += 1,
+                _ => diff.ranges.push(DiffRange { address, len: 1 }),
+            }
+        }
+        if differs {
+            diff.pages.push(base);
+        }
+    }
+    diff
+}
+
+/// Feed the non-zero parts of the pages overlapping `range` to a hasher
+///
+/// Each page contributes the address, length and bytes of its span from the
+/// first to the last non-zero byte.
+fn hash_pages(pages: &impl Pages, range: Range<u64>, hasher: &mut impl Hasher) {
+    let end = range.end.min(1 << 32);
+    for base in pages.mapped() {
+        let start = range.start.max(base as u64);
+        let stop = end.min(base as u64 + PAGE_SIZE as u64);
+        if start >= stop {
+            continue;
+        }
+        let Some((_, bytes)) = pages.page(base) else {
+            continue;
+        };
+        let bytes = &bytes[(start - base as u64) as usize..(stop - base as u64) as usize];
+        let Some(first) = bytes.iter().position(|&byte| byte != 0) else {
+            continue;
+        };
+        let last = bytes.iter().rposition(|&byte| byte != 0).unwrap_or(first);
+        hasher.write_u32((start + first as u64) as u32);
+        hasher.write_usize(last + 1 - first);
+        hasher.write(&bytes[first..=last]);
+    }
+}
+
+impl Memory {
+    /// Feed the contents of `range` to `hasher`
+    ///
+    /// Unallocated and zero-filled pages are skipped and leading and trailing
+    /// zeros are trimmed from each page, so the result only depends on the
+    /// non-zero bytes and their addresses: two memories
+    /// with the same contents in `range` produce the same hash regardless of
+    /// which pages are allocated or how they are backed. Permissions are not
+    /// hashed. `range` is clamped to the 4GB address space and does not wrap.
+    pub fn hash_range(&self, range: Range<u64>, hasher: &mut impl Hasher) {
+        hash_pages(self, range, hasher);
+    }
+
+    /// Hash the contents of the whole address space with a new `H`
+    ///
+    /// ```
+    /// use jigs::{Memory, PageStore};
+    /// use std::collections::hash_map::DefaultHasher;
+    ///
+    /// let store = PageStore::new(16);
+    /// let mut left = Memory::new(&store, 8, 4);
+    /// let mut right = Memory::new(&store, 8, 4);
+    /// left.write(0x4000, b"state").unwrap();
+    /// right.write(0x4000, b"state").unwrap();
+    /// right.write(0x8000, &[0; 16]).unwrap();
+    /// assert_eq!(left.state_root::<DefaultHasher>(), right.state_root::<DefaultHasher>());
+    /// ```
+    pub fn state_root<H: Hasher + Default>(&self) -> u64 {
+        let mut hasher = H::default();
+        self.hash_range(0..1 << 32, &mut hasher);
+        hasher.finish()
+    }
+
+    /// Copy the mapped pages and their permissions
+    pub fn snapshot(&self) -> MemorySnapshot {
+        let pages = Pages::mapped(self)
+            .into_iter()
+            .filter_map(|base| {
+                let (permissions, bytes) = Pages::page(self, base)?;
+                Some((base, (permissions, bytes.into_owned().into_boxed_slice())))
+            })
+            .collect();
+        MemorySnapshot { pages }
+    }
+
+    /// Compare with another memory
+    pub fn diff(&self, other: &Memory) -> MemoryDiff {
+        diff_pages(self, other)
+    }
+
+    /// Compare with a snapshot, reporting how this memory has changed since it
+    /// was taken
+    pub fn diff_snapshot(&self, snapshot: &MemorySnapshot) -> MemoryDiff {
+        diff_pages(snapshot, self)
+    }
+}
@@ -0,0 +1,140 @@
+//! PC-aware disassembly of RISC-V code
+//!
+//! `Instruction`'s `Display` implementation prints branch and jump immediates as
+//! raw byte offsets, which is the correct form for a single instruction but hard
+//! to follow in a dump of guest code. The `Disassembler` knows the address each
+//! instruction lives at, so it resolves BEQ/BNE/BLT/BGE/BLTU/BGEU/JAL targets to
+//! absolute addresses and substitutes a symbolic `<label>` when one is registered.
+//!
+//! # Example
+//! ```
+//! use jigs::{Disassembler, Instruction};
+//!
+//! let code: Vec<u8> = [
+//!     Instruction::Addi { rd: 1, rs1: 1, imm: -1 },
+//!     Instruction::Bne { rs1: 1, rs2: 0, imm: -4 },
+//! ]
+//! .iter()
+//! .flat_map(|instr| instr.encode().unwrap().to_le_bytes())
+//! .collect();
+//!
+//! let mut disassembler = Disassembler::new(0x1000);
+//! disassembler.label(0x1000, "loop");
+//! assert_eq!(
+//!     disassembler.disassemble(&code),
+//!     "loop:\n0x00001000:  addi x1, x1, -1\n0x00001004:  bne x1, x0, <loop>\n"
+//! );
+//! ```
+
+use crate::{Decoder, Instruction};
+use std::{collections::BTreeMap, fmt, fmt::Write};
+
+/// Disassembles RISC-V code located at a known base address
+pub struct Disassembler {
+    /// Guest address of the first byte of code
+    base: u32,
+    /// Symbolic names for guest addresses
+    labels: BTreeMap<u32, String>,
+    /// Decoder splitting and decoding the code
+    decoder: Decoder,
+}
+
+impl Disassembler {
+    /// Create a disassembler for code starting at `base`
+    pub fn new(base: u32) -> Self {
+        Self {
+            base,
+            labels: BTreeMap::new(),
+            decoder: Decoder::default(),
+        }
+    }
+
+    /// Set the decoder `disassemble()` decodes with, such as the one the code
+    /// runs under
+    ///
+    /// `Decoder::default()` (RV32IM) by default. Compressed parcels are only
+    /// split off with C enabled (see `Decoder::stream()`).
+    pub fn set_decoder(&mut self, decoder: Decoder) {
+        self.decoder = decoder;
+    }
+
+    /// Register a symbolic name for a guest address
+    ///
+    /// Branches and jumps targeting `address` are rendered as `<name>`, and the
+    /// listing emits a `name:` line before the instruction at that address.
+    pub fn label(&mut self, address: u32, name: &str) {
+        self.labels.insert(address, name.to_string());
+    }
+
+    /// Disassemble a buffer of little-endian instruction words into a listing
+    ///
+    /// Each line has the form `0x{pc:08x}:  {instruction}`. Instructions are
+    /// advanced by their width under the decoder, and trailing bytes that do
+    /// not form a complete instruction are ignored.
+    pub fn disassemble(&self, code: &[u8]) -> String {
+        let mut output = String::new();
+        for (offset, instruction) in self.decoder.stream(code) {
+            let pc = self.base.wrapping_add(offset);
+
+            if let Some(name) = self.labels.get(&pc) {
+                writeln!(output, "{}:", name).unwrap();
+            }
+            writeln!(output, "0x{:08x}:  {}", pc, self.format(&instruction, pc)).unwrap();
+        }
+        output
+    }
+
+    /// Render a single instruction located at `pc`
+    ///
+    /// Branch and JAL targets are resolved against `pc`; all other instructions
+    /// render exactly as their `Display` implementation.
+    pub fn format(&self, instruction: &Instruction, pc: u32) -> String {
+        Located {
+            instruction,
+            pc,
+            labels: &self.labels,
+        }
+        .to_string()
+    }
+}
+
+/// Display adapter rendering an instruction with resolved targets
+struct Located<'a> {
+    instruction: &'a Instruction,
+    pc: u32,
+    labels: &'a BTreeMap<u32, String>,
+}
+
+impl Located<'_> {
+    /// Format a target address as `<label>` if known, otherwise as hex
+    fn target(&self, address: u32) -> String {
+        match self.labels.get(&address) {
+            Some(name) => format!("<{}>", name),
+            None => format!("0x{:x}", address),
+        }
+    }
+}
+
+impl fmt::Display for Located<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some(address) = self.instruction.target(self.pc) else {
+            return write!(f, "{}", self.instruction);
+        };
+        let target = self.target(address);
+
+        match self.instruction {
+            Instruction::Beq { rs1, rs2, .. } => write!(f, "beq x{}, x{}, {}", rs1, rs2, target),
+            Instruction::Bne { rs1, rs2, .. } => write!(f, "bne x{}, x{}, {}", rs1, rs2, target),
+            Instruction::Blt { rs1, rs2, .. } => write!(f, "blt x{}, x{}, {}", rs1, rs2, target),
+            Instruction::Bge { rs1, rs2, .. } => write!(f, "bge x{}, x{}, {}", rs1, rs2, target),
+            Instruction::Bltu { rs1, rs2, .. } => {
+                write!(f, "bltu x{}, x{}, {}", rs1, rs2, target)
+            }
+            Instruction::Bgeu { rs1, rs2, .. } => {
+                write!(f, "bgeu x{}, x{}, {}", rs1, rs2, target)
+            }
+            Instruction::Jal { rd, .. } => write!(f, "jal x{}, {}", rd, target),
+            _ => write!(f, "{}", self.instruction),
+        }
+    }
+}
@@ -0,0 +1,571 @@
+//! RISC-V ELF executables
+//!
+//! `Elf::parse()` reads a 32-bit little-endian RISC-V executable as produced
+//! by a toolchain: its entry point, the PT_LOAD segments making up the
+//! program image and, when present, the section headers with their names.
+//! From these it derives the two things a guest needs to run:
+//! - the code image, the span of the executable sections (or executable
+//!   segments when the file has no section headers) that `compile()` loads
+//!   into a `Module` at its guest address
+//! - the initial memory image, the contents of every loadable segment and
+//!   the length of its zero-initialized tail, which `load_memory()` writes
+//!   into guest memory without allocating the tail
+//! - the function table, the entry point followed by the functions the
+//!   symbol table exports, which gives each its own native entry in the
+//!   module (see `functions()`)
+//!
+//! Only code and memory are loaded: relocations, dynamic linking and
+//! program interpreters are not supported, so executables must be linked
+//! statically at a fixed address. Code images are limited to
+//! `MAX_CODE_SIZE` bytes.
+//!
+//! # Example
+//! ```
+//! use jigs::{Elf, Instruction, Memory, Module, PageStore};
+//!
+//! // An ELF header and one PT_LOAD segment with an ECALL at 0x1000
+//! let ecall = Instruction::Ecall.encode().unwrap();
+//! let mut bytes = vec![0; 88];
+//! bytes[..7].copy_from_slice(&[0x7F, b'E', b'L', b'F', 1, 1, 1]);
+//! for (offset, value) in [(16, 2), (18, 243), (42, 32), (44, 1)] {
+//!     bytes[offset..offset + 2].copy_from_slice(&u16::to_le_bytes(value));
+//! }
+//! let header = [(20, 1), (24, 0x1000), (28, 52)];
+//! let segment = [(52, 1), (56, 84), (60, 0x1000), (68, 4), (72, 4), (76, 5)];
+//! for (offset, value) in header.into_iter().chain(segment).chain([(84, ecall)]) {
+//!     bytes[offset..offset + 4].copy_from_slice(&u32::to_le_bytes(value));
+//! }
+//!
+//! let elf = Elf::parse(&bytes).unwrap();
+//! assert_eq!(elf.entry, 0x1000);
+//! assert_eq!(elf.code(), Some((0x1000, ecall.to_le_bytes().to_vec())));
+//!
+//! let mut module = Module::new(1024).unwrap();
+//! elf.compile(&mut module).unwrap();
+//! assert_eq!(module.base(), 0x1000);
+//!
+//! let store = PageStore::new(16);
+//! let mut memory = Memory::new(&store, 16, 4);
+//! elf.load_memory(&mut memory).unwrap();
+//! assert_eq!(memory.read_u32(0x1000), ecall);
+//! ```
+
+use crate::{
+    CompileError, Module,
+    memory::{Memory, MemoryError},
+};
+use std::{fmt, fs, io, path::Path};
+
+/// `e_machine` of RISC-V
+pub const EM_RISCV: u16 = 243;
+
+/// `e_type` of executables
+const ET_EXEC: u16 = 2;
+
+/// `p_type` of loadable segments
+const PT_LOAD: u32 = 1;
+
+/// `p_flags` bit of executable segments
+const PF_X: u32 = 1;
+
+/// `sh_type` of sections without file contents (.bss)
+const SHT_NOBITS: u32 = 8;
+
+/// `sh_type` of symbol tables
+const SHT_SYMTAB: u32 = 2;
+
+/// `sh_flags` bit of sections holding instructions
+const SHF_EXECINSTR: u32 = 4;
+
+/// Symbol type of functions
+pub const STT_FUNC: u8 = 2;
+
+/// Symbol binding of global symbols
+pub const STB_GLOBAL: u8 = 1;
+
+/// Symbol binding of weak symbols
+pub const STB_WEAK: u8 = 2;
+
+/// `st_shndx` of undefined symbols
+const SHN_UNDEF: u16 = 0;
+
+/// Size of the ELF header
+const HEADER_SIZE: usize = 52;
+
+/// Size of a program header
+const PROGRAM_HEADER_SIZE: usize = 32;
+
+/// Size of a section header
+const SECTION_HEADER_SIZE: usize = 40;
+
+/// Size of a symbol table entry
+const SYMBOL_SIZE: usize = 16;
+
+/// Largest code image, from the first to the last byte of code (16MB)
+pub const MAX_CODE_SIZE: u32 = 1 << 24;
+
+/// Initial memory contents as (guest address, bytes, length of the zeroed
+/// tail following the bytes)
+pub type Image = Vec<(u32, Vec<u8>, u32)>;
+
+/// A parsed RISC-V executable
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Elf {
+    /// Guest address execution starts at
+    pub entry: u32,
+    /// `e_flags`, holding the float ABI and whether compressed
+    /// instructions are used
+    pub flags: u32,
+    /// Loadable segments, in file order
+    pub segments: Vec<LoadSegment>,
+    /// Section headers, in file order; empty when the file has none
+    pub sections: Vec<Section>,
+    /// Entries of the symbol table after the null symbol, in file order;
+    /// empty when the file has none
+    pub symbols: Vec<Symbol>,
+}
+
+/// A PT_LOAD segment
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoadSegment {
+    /// Guest address of the first byte
+    pub address: u32,
+    /// Contents from the file
+    pub data: Vec<u8>,
+    /// Size in memory, at least the size of `data`; the rest is zeroed
+    pub size: u32,
+    /// `p_flags` (PF_X = 1, PF_W = 2, PF_R = 4)
+    pub flags: u32,
+}
+
+/// A section header
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    /// Name from the section name table
+    pub name: String,
+    /// `sh_type`
+    pub kind: u32,
+    /// `sh_flags`
+    pub flags: u32,
+    /// Guest address, or 0 for sections not loaded
+    pub address: u32,
+    /// Offset of the contents in the file
+    pub offset: u32,
+    /// Size in bytes
+    pub size: u32,
+}
+
+/// A symbol table entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    /// Name from the symbol's string table
+    pub name: String,
+    /// `st_value`, the guest address of defined symbols
+    pub address: u32,
+    /// Size in bytes
+    pub size: u32,
+    /// Type, such as `STT_FUNC`
+    pub kind: u8,
+    /// Binding, such as `STB_GLOBAL`
+    pub binding: u8,
+    /// Index of the section defining it, 0 for undefined symbols
+    pub section: u16,
+}
+
+/// Errors that can occur while reading or loading an ELF file
+#[derive(Debug, Clone, PartialEq)]
+pub enum ElfError {
+    /// Reading the file failed
+    Io(io::ErrorKind),
+    /// The data does not start with the ELF magic
+    InvalidMagic,
+    /// The file is not 32-bit (`EI_CLASS`)
+    UnsupportedClass(u8),
+    /// The file is not little-endian (`EI_DATA`)
+    UnsupportedEncoding(u8),
+    /// The file is not for RISC-V (`e_machine`)
+    UnsupportedMachine(u16),
+    /// The file is not an executable (`e_type`)
+    UnsupportedType(u16),
+    /// A header, segment or section lies outside the file
+    Truncated,
+    /// The PT_LOAD segment at this index is larger in the file than in
+    /// memory, or wraps around the address space
+    InvalidSegment(usize),
+    /// The name of the section at this index is not in the section name
+    /// table or not valid UTF-8
+    InvalidSectionName(usize),
+    /// The name of the symbol at this index of the symbol table is not in
+    /// its string table or not valid UTF-8
+    InvalidSymbolName(usize),
+    /// The file has no executable code
+    NoCode,
+    /// The code image spans this many bytes, more than `MAX_CODE_SIZE`
+    CodeTooLarge(u64),
+    /// Loading data into memory failed
+    MemoryError(MemoryError),
+    /// Compiling the code failed
+    CompileError(CompileError),
+}
+
+impl Elf {
+    /// Parse a 32-bit little-endian RISC-V executable
+    pub fn parse(bytes: &[u8]) -> Result<Elf, ElfError> {
+        let header = bytes.get(..HEADER_SIZE).ok_or(ElfError::Truncated)?;
+        if header[..4] != [0x7F, b'E', b'L', b'F'] {
+            return Err(ElfError::InvalidMagic);
+        }
+        if header[4] != 1 {
+            return Err(ElfError::UnsupportedClass(header[4]));
+        }
+        if header[5] != 1 {
+            return Err(ElfError::UnsupportedEncoding(header[5]));
+        }
+        let kind = u16_at(header, 16);
+        if kind != ET_EXEC {
+            return Err(ElfError::UnsupportedType(kind));
+        }
+        let machine = u16_at(header, 18);
+        if machine != EM_RISCV {
+            return Err(ElfError::UnsupportedMachine(machine));
+        }
+
+        let mut segments = Vec::new();
+        let program_headers = table(
+            bytes,
+            u32_at(header, 28),
+            u16_at(header, 42),
+            u16_at(header, 44),
+            PROGRAM_HEADER_SIZE,
+        )?;
+        for entry in program_headers {
+            if u32_at(entry, 0) != PT_LOAD {
+                continue;
+            }
+            let address = u32_at(entry, 8);
+            let data = range(bytes, u32_at(entry, 4), u32_at(entry, 16))?;
+            let size = u32_at(entry, 20);
+            if (data.len() as u32) > size || address.checked_add(size).is_none() {
+                return Err(ElfError::InvalidSegment(segments.len()));
+            }
+            segments.push(LoadSegment {
+                address,
+                data: data.to_vec(),
+                size,
+                flags: u32_at(entry, 24),
+            });
+        }
+
+        let section_headers = table(
+            bytes,
+            u32_at(header, 32),
+            u16_at(header, 46),
+            u16_at(header, 48),
+            SECTION_HEADER_SIZE,
+        )?;
+        let names = match section_headers.get(u16_at(header, 50) as usize) {
+            Some(entry) => range(bytes, u32_at(entry, 16), u32_at(entry, 20))?,
+            None => &[],
+        };
+        let mut sections = Vec::new();
+        for (index, entry) in section_headers.iter().enumerate() {
+            let name = names
+                .get(u32_at(entry, 0) as usize..)
+                .and_then(|name| name.split(|byte| *byte == 0).next())
+                .and_then(|name| str::from_utf8(name).ok())
+                .ok_or(ElfError::InvalidSectionName(index))?;
+            sections.push(Section {
+                name: name.to_string(),
+                kind: u32_at(entry, 4),
+                flags: u32_at(entry, 8),
+                address: u32_at(entry, 12),
+                offset: u32_at(entry, 16),
+                size: u32_at(entry, 20),
+            });
+        }
+
+        let mut symbols = Vec::new();
+        let symbol_table = section_headers
+            .iter()
+            .find(|entry| u32_at(entry, 4) == SHT_SYMTAB);
+        if let Some(entry) = symbol_table {
+            let table = range(bytes, u32_at(entry, 16), u32_at(entry, 20))?;
+            let names = match section_headers.get(u32_at(entry, 24) as usize) {
+                Some(names) => range(bytes, u32_at(names, 16), u32_at(names, 20))?,
+                None => &[],
+            };
+            for (index, symbol) in table.chunks_exact(SYMBOL_SIZE).enumerate().skip(1) {
+                let name = names
+                    .get(u32_at(symbol, 0) as usize..)
+                    .and_then(|name| name.split(|byte| *byte == 0).next())
+                    .and_then(|name| str::from_utf8(name).ok())
+                    .ok_or(ElfError::InvalidSymbolName(index))?;
+                symbols.push(Symbol {
+                    name: name.to_string(),
+                    address: u32_at(symbol, 4),
+                    size: u32_at(symbol, 8),
+                    kind: symbol[12] & 0xF,
+                    binding: symbol[12] >> 4,
+                    section: u16_at(symbol, 14),
+                });
+            }
+        }
+
+        let elf = Elf {
+            entry: u32_at(header, 24),
+            flags: u32_at(header, 36),
+            segments,
+            sections,
+            symbols,
+        };
+        match elf.span() {
+            Some((start, end)) if end - start > MAX_CODE_SIZE as u64 => {
+                Err(ElfError::CodeTooLarge(end - start))
+            }
+            _ => Ok(elf),
+        }
+    }
+
+    /// Read and parse the executable at `path`
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Elf, ElfError> {
+        let bytes = fs::read(path).map_err(|error| ElfError::Io(error.kind()))?;
+        Elf::parse(&bytes)
+    }
+
+    /// Get the section named `name`
+    pub fn section(&self, name: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.name == name)
+    }
+
+    /// Guest address and contents of the code image, if there is any code
+    ///
+    /// The image spans every executable section, or every executable segment
+    /// when there are no section headers, with gaps between them zeroed. Its
+    /// length is rounded up to whole instructions. Images spanning more than
+    /// `MAX_CODE_SIZE` bytes, which `parse()` rejects, give `None`.
+    pub fn code(&self) -> Option<(u32, Vec<u8>)> {
+        let (start, end) = self
+            .span()
+            .filter(|(start, end)| end - start <= MAX_CODE_SIZE as u64)?;
+        let mut code = vec![0; ((end - start) as usize).next_multiple_of(4)];
+        for (address, data) in self.pieces() {
+            let offset = (address as u64 - start) as usize;
+            code[offset..offset + data.len()].copy_from_slice(data);
+        }
+        Some((start as u32, code))
+    }
+
+    /// Function table for a module running the code: the entry point, then
+    /// each function the symbol table exports from the code image
+    ///
+    /// Exported functions are the defined global and weak `STT_FUNC`
+    /// symbols, in symbol table order. Addresses already in the table are
+    /// not repeated, so aliases share an index.
+    pub fn functions(&self) -> Vec<u32> {
+        let mut functions = vec![self.entry];
+        let Some((start, end)) = self.code().map(|(start, code)| {
+            let start = start as u64;
+            (start, start + code.len() as u64)
+        }) else {
+            return functions;
+        };
+        for symbol in self.exports() {
+            if (start..end).contains(&(symbol.address as u64))
+                && !functions.contains(&symbol.address)
+            {
+                functions.push(symbol.address);
+            }
+        }
+        functions
+    }
+
+    /// Index in `functions()` of the exported function named `name`
+    pub fn function_index(&self, name: &str) -> Option<usize> {
+        let symbol = self.exports().find(|symbol| symbol.name == name)?;
+        self.functions()
+            .iter()
+            .position(|address| *address == symbol.address)
+    }
+
+    /// Initial memory contents, one entry per loadable segment with the
+    /// length of its zeroed tail
+    pub fn image(&self) -> Image {
+        self.segments
+            .iter()
+            .map(|segment| {
+                let tail = segment.size.saturating_sub(segment.data.len() as u32);
+                (segment.address, segment.data.clone(), tail)
+            })
+            .collect()
+    }
+
+    /// Compile the code image into `module` at its guest address
+    pub fn compile(&self, module: &mut Module) -> Result<(), ElfError> {
+        let (base, code) = self.code().ok_or(ElfError::NoCode)?;
+        module.set_base(base);
+        module.set_code(&code).map_err(ElfError::CompileError)
+    }
+
+    /// Write the memory image into guest memory, filling the zeroed tails
+    /// in place
+    pub fn load_memory(&self, memory: &mut Memory) -> Result<(), ElfError> {
+        for segment in &self.segments {
+            let tail = segment.size.saturating_sub(segment.data.len() as u32);
+            memory
+                .write(segment.address, &segment.data)
+                .and_then(|_| {
+                    let end = segment.address.wrapping_add(segment.data.len() as u32);
+                    memory.fill(end, tail, 0)
+                })
+                .map_err(ElfError::MemoryError)?;
+        }
+        Ok(())
+    }
+
+    /// Address and contents of each executable section, or each executable
+    /// segment when there are no section headers
+    fn pieces(&self) -> Vec<(u32, &[u8])> {
+        if self.sections.is_empty() {
+            return self
+                .segments
+                .iter()
+                .filter(|segment| segment.flags & PF_X != 0 && !segment.data.is_empty())
+                .map(|segment| (segment.address, segment.data.as_slice()))
+                .collect();
+        }
+        self.sections
+            .iter()
+            .filter(|section| {
+                section.flags & SHF_EXECINSTR != 0
+                    && section.kind != SHT_NOBITS
+                    && section.size != 0
+            })
+            .map(|section| {
+                let data = self.contents(section.address, section.size);
+                (section.address, data)
+            })
+            .collect()
+    }
+
+    /// First and end address of the code image, if there is any code
+    fn span(&self) -> Option<(u64, u64)> {
+        let pieces = self.pieces();
+        let start = pieces.iter().map(|(address, _)| *address as u64).min()?;
+        let end = pieces
+            .iter()
+            .map(|(address, data)| *address as u64 + data.len() as u64)
+            .max()?;
+        Some((start, end))
+    }
+
+    /// Defined global and weak functions of the symbol table
+    fn exports(&self) -> impl Iterator<Item = &Symbol> {
+        self.symbols.iter().filter(|symbol| {
+            symbol.kind == STT_FUNC
+                && matches!(symbol.binding, STB_GLOBAL | STB_WEAK)
+                && symbol.section != SHN_UNDEF
+        })
+    }
+
+    /// Loaded bytes at `address`, as far as the segment holding it goes
+    fn contents(&self, address: u32, size: u32) -> &[u8] {
+        self.segments
+            .iter()
+            .find_map(|segment| {
+                let offset = address.checked_sub(segment.address)? as usize;
+                if offset >= segment.data.len() {
+                    return None;
+                }
+                let end = (offset + size as usize).min(segment.data.len());
+                Some(&segment.data[offset..end])
+            })
+            .unwrap_or_default()
+    }
+}
+
+impl fmt::Display for ElfError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElfError::Io(kind) => write!(f, "I/O error: {}", kind),
+            ElfError::InvalidMagic => write!(f, "Not an ELF file"),
+            ElfError::UnsupportedClass(class) => write!(f, "Unsupported ELF class: {}", class),
+            ElfError::UnsupportedEncoding(encoding) => {
+                write!(f, "Unsupported ELF data encoding: {}", encoding)
+            }
+            ElfError::UnsupportedMachine(machine) => {
+                write!(f, "Unsupported machine: {}", machine)
+            }
+            ElfError::UnsupportedType(kind) => write!(f, "Not an executable: type {}", kind),
+            ElfError::Truncated => write!(f, "ELF file is truncated"),
+            ElfError::InvalidSegment(index) => write!(f, "Invalid segment {}", index),
+            ElfError::InvalidSectionName(index) => {
+                write!(f, "Invalid name of section {}", index)
+            }
+            ElfError::InvalidSymbolName(index) => {
+                write!(f, "Invalid name of symbol {}", index)
+            }
+            ElfError::NoCode => write!(f, "ELF file has no code"),
+            ElfError::CodeTooLarge(size) => {
+                write!(
+                    f,
+                    "Code image spans {} bytes, more than {}",
+                    size, MAX_CODE_SIZE
+                )
+            }
+            ElfError::MemoryError(error) => write!(f, "Memory error: {}", error),
+            ElfError::CompileError(error) => write!(f, "Compile error: {:?}", error),
+        }
+    }
+}
+
+impl std::error::Error for ElfError {}
+
+/// Read a little-endian u16 at `offset`
+fn u16_at(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+/// Read a little-endian u32 at `offset`
+fn u32_at(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+/// The `size` bytes at `offset` in the file
+fn range(bytes: &[u8], offset: u32, size: u32) -> Result<&[u8], ElfError> {
+    let start = offset as usize;
+    bytes
+        .get(
+            start
+                ..start
+                    .checked_add(size as usize)
+                    .ok_or(ElfError::Truncated)?,
+        )
+        .ok_or(ElfError::Truncated)
+}
+
+/// Entries of the header table at `offset`, `count` entries of `entry_size`
+/// bytes each, of which the first `size` are read
+fn table(
+    bytes: &[u8],
+    offset: u32,
+    entry_size: u16,
+    count: u16,
+    size: usize,
+) -> Result<Vec<&[u8]>, ElfError> {
+    if count == 0 {
+        return Ok(Vec::new());
+    }
+    if (entry_size as usize) < size {
+        return Err(ElfError::Truncated);
+    }
+    let table = range(bytes, offset, entry_size as u32 * count as u32)?;
+    Ok(table
+        .chunks_exact(entry_size as usize)
+        .map(|entry| &entry[..size])
+        .collect())
+}
@@ -0,0 +1,252 @@
+//! Executable memory for compiled code
+//!
+//! `ExecutableBuffer` owns a mapping that is only ever writable or
+//! executable, never both (W^X). Code is written through `write()`, which
+//! makes the buffer writable, runs the writer, makes it executable again and
+//! performs the instruction cache maintenance the platform needs.
+//!
+//! A buffer can reserve more address space than it starts with and `grow()`
+//! into it later. The start never moves, so code already handed out stays
+//! valid as the buffer grows.
+//!
+//! # Platforms
+//! - macOS maps the buffer with `MAP_JIT` and toggles the calling thread's
+//!   view with `pthread_jit_write_protect_np`, as hardened runtimes refuse
+//!   `mprotect` transitions to executable
+//! - Elsewhere the whole buffer moves between read-write and read-execute
+//!   with `mprotect`; the reserved space beyond it stays inaccessible and
+//!   takes no memory until it is grown into
+//!
+//! # Instruction cache
+//! AArch64 does not keep the instruction cache coherent with data writes, so
+//! after writing, each data cache line is cleaned to the point of
+//! unification and each instruction cache line invalidated (`dc cvau`,
+//! `dsb ish`, `ic ivau`, `dsb ish`, `isb`), with the line sizes read from
+//! `CTR_EL0`. macOS does the same through `sys_icache_invalidate`. Other
+//! architectures keep the caches coherent.
+
+use std::{io, ptr};
+
+/// Page-aligned buffer of machine code, writable only through `write()`
+pub struct ExecutableBuffer {
+    /// Start of the mapping
+    ptr: *mut u8,
+    /// Size of the usable part of the mapping in bytes
+    len: usize,
+    /// Size of the whole mapping in bytes
+    capacity: usize,
+}
+
+impl ExecutableBuffer {
+    /// Map a zeroed, executable buffer of `len` bytes
+    ///
+    /// # Errors
+    /// Returns the OS error if the mapping fails, including for `len` 0
+    pub fn new(len: usize) -> io::Result<Self> {
+        Self::with_capacity(len, len)
+    }
+
+    /// Map a zeroed, executable buffer of `len` bytes, reserving address
+    /// space for `grow()` up to `capacity` bytes (at least `len`)
+    ///
+    /// # Errors
+    /// Returns the OS error if the mapping fails, including for `len` 0
+    pub fn with_capacity(len: usize, capacity: usize) -> io::Result<Self> {
+        if len == 0 {
+            return Err(io::ErrorKind::InvalidInput.into());
+        }
+        let capacity = capacity.max(len);
+        #[cfg(target_os = "macos")]
+        let (prot, flags) = (
+            libc::PROT_READ | libc::PROT_WRITE | libc::PROT_EXEC,
+            libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_JIT,
+        );
+        #[cfg(not(target_os = "macos"))]
+        let (prot, flags) = (
+            libc::PROT_NONE,
+            libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_NORESERVE,
+        );
+
+        let ptr = unsafe { libc::mmap(ptr::null_mut(), capacity, prot, flags, -1, 0) };
+        if ptr == libc::MAP_FAILED {
+            return Err(io::Error::last_os_error());
+        }
+        let mut buffer = Self {
+            ptr: ptr as *mut u8,
+            len,
+            capacity,
+        };
+        buffer.set_writable(false)?;
+        Ok(buffer)
+    }
+
+    /// Size of the buffer in bytes
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Size the buffer can grow to in bytes
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Extend the buffer to `len` bytes of its reserved space, zeroed past
+    /// the current contents; smaller sizes leave it as it is
+    ///
+    /// # Errors
+    /// Returns `OutOfMemory` if `len` exceeds the capacity, and the OS error
+    /// if the new part cannot be made executable
+    pub fn grow(&mut self, len: usize) -> io::Result<()> {
+        if len <= self.len {
+            return Ok(());
+        }
+        if len > self.capacity {
+            return Err(io::ErrorKind::OutOfMemory.into());
+        }
+        let previous = self.len;
+        self.len = len;
+        self.set_writable(false)
+            .inspect_err(|_| self.len = previous)
+    }
+
+    /// Check if the buffer has no bytes
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Pointer to the start of the buffer, page-aligned
+    pub fn as_ptr(&self) -> *const u8 {
+        self.ptr
+    }
+
+    /// Get the buffer contents
+    pub fn as_slice(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.ptr, self.len) }
+    }
+
+    /// Run `write` on the writable buffer, then make it executable again
+    /// with the instruction cache brought up to date
+    ///
+    /// The buffer is made executable again even if `write` panics. If that
+    /// fails, all access to the buffer is revoked instead, so it is never
+    /// left writable.
+    ///
+    /// # Errors
+    /// Returns the OS error if the protection cannot be changed
+    pub fn write<R>(&mut self, write: impl FnOnce(&mut [u8]) -> R) -> io::Result<R> {
+        self.set_writable(true)?;
+        let mut guard = Writable {
+            buffer: self,
+            restored: false,
+        };
+        let result =
+            write(unsafe { std::slice::from_raw_parts_mut(guard.buffer.ptr, guard.buffer.len) });
+        guard.restore()?;
+        Ok(result)
+    }
+
+    /// Switch the buffer between writable and executable
+    #[cfg(target_os = "macos")]
+    fn set_writable(&mut self, writable: bool) -> io::Result<()> {
+        unsafe { libc::pthread_jit_write_protect_np(!writable as libc::c_int) };
+        Ok(())
+    }
+
+    /// Switch the buffer between writable and executable
+    #[cfg(not(target_os = "macos"))]
+    fn set_writable(&mut self, writable: bool) -> io::Result<()> {
+        let prot = if writable {
+            libc::PROT_READ | libc::PROT_WRITE
+        } else {
+            libc::PROT_READ | libc::PROT_EXEC
+        };
+        match unsafe { libc::mprotect(self.ptr as *mut libc::c_void, self.len, prot) } {
+            0 => Ok(()),
+            _ => Err(io::Error::last_os_error()),
+        }
+    }
+}
+
+/// Write access to an `ExecutableBuffer`, given up when dropped
+struct Writable<'a> {
+    /// Buffer currently writable
+    buffer: &'a mut ExecutableBuffer,
+    /// Whether `restore()` already ran
+    restored: bool,
+}
+
+impl Writable<'_> {
+    /// Make the buffer executable again, or inaccessible if that fails
+    fn restore(&mut self) -> io::Result<()> {
+        self.restored = true;
+        let buffer = &mut *self.buffer;
+        if let Err(error) = buffer.set_writable(false) {
+            #[cfg(not(target_os = "macos"))]
+            unsafe {
+                libc::mprotect(buffer.ptr as *mut libc::c_void, buffer.len, libc::PROT_NONE);
+            }
+            return Err(error);
+        }
+        flush_icache(buffer.ptr, buffer.len);
+        Ok(())
+    }
+}
+
+impl Drop for Writable<'_> {
+    fn drop(&mut self) {
+        if !self.restored {
+            let _ = self.restore();
+        }
+    }
+}
+
+impl Drop for ExecutableBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            libc::munmap(self.ptr as *mut libc::c_void, self.capacity);
+        }
+    }
+}
+
+#[cfg(target_os = "macos")]
+unsafe extern "C" {
+    fn sys_icache_invalidate(start: *mut libc::c_void, len: libc::size_t);
+}
+
+/// Make `len` bytes of freshly written code at `start` visible to
+/// instruction fetch
+#[cfg(target_os = "macos")]
+fn flush_icache(start: *mut u8, len: usize) {
+    unsafe { sys_icache_invalidate(start as *mut libc::c_void, len) };
+}
+
+/// Make `len` bytes of freshly written code at `start` visible to
+/// instruction fetch
+#[cfg(all(target_arch = "aarch64", not(target_os = "macos")))]
+fn flush_icache(start: *mut u8, len: usize) {
+    use std::arch::asm;
+
+    let ctr: u64;
+    unsafe { asm!("mrs {}, ctr_el0", out(reg) ctr, options(nomem, nostack)) };
+    // Line sizes are encoded as log2 of the number of 4-byte words
+    let dline = 4 << ((ctr >> 16) & 0xF);
+    let iline = 4 << (ctr & 0xF);
+    let start = start as usize;
+    let end = start + len;
+
+    for line in (start & !(dline - 1)..end).step_by(dline) {
+        unsafe { asm!("dc cvau, {}", in(reg) line, options(nostack)) };
+    }
+    unsafe { asm!("dsb ish", options(nostack)) };
+    for line in (start & !(iline - 1)..end).step_by(iline) {
+        unsafe { asm!("ic ivau, {}", in(reg) line, options(nostack)) };
+    }
+    unsafe { asm!("dsb ish", "isb", options(nostack)) };
+}
+
+/// Make `len` bytes of freshly written code at `start` visible to
+/// instruction fetch
+///
+/// Nothing to do where instruction fetch snoops data writes.
+#[cfg(not(any(target_os = "macos", target_arch = "aarch64")))]
+fn flush_icache(_start: *mut u8, _len: usize) {}
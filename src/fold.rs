@@ -0,0 +1,190 @@
+//! Constant folding and propagation
+//!
+//! Tracks which registers hold known constants through each basic block,
+//! starting from nothing but x0 at every block entry, and rewrites
+//! instructions to use them. Instructions whose result is known become a
+//! single `addi rd, x0, c` or `lui rd, c` when the constant fits one, ADD and
+//! SUB of a known small constant become ADDI, and register shifts by a known
+//! amount become immediate shifts. Like the peephole pass, every instruction
+//! keeps its PC, so branch offsets stay valid.
+//!
+//! # Example
+//! ```
+//! use jigs::Instruction;
+//! use jigs::fold;
+//!
+//! let code = [
+//!     Instruction::Addi { rd: 5, rs1: 0, imm: 3 },
+//!     Instruction::Slli { rd: 5, rs1: 5, shamt: 4 },
+//!     Instruction::Add { rd: 6, rs1: 7, rs2: 5 },
+//!     Instruction::Srl { rd: 8, rs1: 7, rs2: 5 },
+//! ];
+//! assert_eq!(
+//!     fold::fold(&code),
+//!     [
+//!         Instruction::Addi { rd: 5, rs1: 0, imm: 3 },
+//!         Instruction::Addi { rd: 5, rs1: 0, imm: 48 },
+//!         Instruction::Addi { rd: 6, rs1: 7, imm: 48 },
+//!         Instruction::Srli { rd: 8, rs1: 7, shamt: 16 },
+//!     ]
+//! );
+//! ```
+
+use crate::{Instruction, Register, compiler::Compiler};
+
+/// Rewrite instructions using the constants known in each basic block
+pub fn fold(instructions: &[Instruction]) -> Vec<Instruction> {
+    fold_at(instructions, 0)
+}
+
+/// Rewrite instructions at guest address `base` using the constants known in
+/// each basic block
+///
+/// AUIPC results depend on the address of the instruction.
+pub fn fold_at(instructions: &[Instruction], base: u32) -> Vec<Instruction> {
+    let mut output = Vec::with_capacity(instructions.len());
+    for block in Compiler::basic_blocks(instructions) {
+        let mut known = [None; 32];
+        known[0] = Some(0);
+        for (index, instruction) in instructions[block.start..block.end].iter().enumerate() {
+            let pc = base.wrapping_add((block.start + index) as u32 * 4);
+            let value = evaluate(pc, instruction, &known);
+            let folded = rewrite(instruction, value, &known);
+            if let Some(rd @ 1..) = instruction.rd().and_then(Register::integer) {
+                known[rd as usize] = value;
+            }
+            output.push(folded);
+        }
+    }
+    output
+}
+
+/// Cheapest form of `instruction` given the known registers and its result
+fn rewrite(
+    instruction: &Instruction,
+    value: Option<u32>,
+    known: &[Option<u32>; 32],
+) -> Instruction {
+    use Instruction::*;
+
+    let small = |reg: u8| known[reg as usize].filter(|&value| fits(value));
+    if let (Some(Register::X(rd @ 1..)), Some(value)) = (instruction.rd(), value) {
+        if fits(value) {
+            return Addi {
+                rd,
+                rs1: 0,
+                imm: value as i32,
+            };
+        }
+        if value & 0xFFF == 0 {
+            return Lui {
+                rd,
+                imm: value >> 12,
+            };
+        }
+    }
+    match *instruction {
+        Add { rd, rs1, rs2 } => match (small(rs1), small(rs2)) {
+            (_, Some(value)) => Addi {
+                rd,
+                rs1,
+                imm: value as i32,
+            },
+            (Some(value), _) => Addi {
+                rd,
+                rs1: rs2,
+                imm: value as i32,
+            },
+            _ => instruction.clone(),
+        },
+        Sub { rd, rs1, rs2 } => match known[rs2 as usize].map(u32::wrapping_neg) {
+            Some(value) if fits(value) => Addi {
+                rd,
+                rs1,
+                imm: value as i32,
+            },
+            _ => instruction.clone(),
+        },
+        Sll { rd, rs1, rs2 } | Srl { rd, rs1, rs2 } | Sra { rd, rs1, rs2 } => {
+            let Some(amount) = known[rs2 as usize] else {
+                return instruction.clone();
+            };
+            let shamt = (amount & 0x1F) as u8;
+            match instruction {
+                Sll { .. } => Slli { rd, rs1, shamt },
+                Srl { .. } => Srli { rd, rs1, shamt },
+                _ => Srai { rd, rs1, shamt },
+            }
+        }
+        _ => instruction.clone(),
+    }
+}
+
+/// Whether a value is a sign-extended 12-bit immediate
+fn fits(value: u32) -> bool {
+    (-0x800..0x800).contains(&(value as i32))
+}
+
+/// Result of an integer instruction at `pc` whose sources are all known
+fn evaluate(pc: u32, instruction: &Instruction, known: &[Option<u32>; 32]) -> Option<u32> {
+    use Instruction::*;
+
+    let reg = |reg: u8| known[reg as usize];
+    let value = match *instruction {
+        Lui { imm, .. } => imm << 12,
+        Auipc { imm, .. } => pc.wrapping_add(imm << 12),
+        Addi { rs1, imm, .. } => reg(rs1)?.wrapping_add(imm as u32),
+        Slti { rs1, imm, .. } => ((reg(rs1)? as i32) < imm) as u32,
+        Sltiu { rs1, imm, .. } => (reg(rs1)? < imm as u32) as u32,
+        Xori { rs1, imm, .. } => reg(rs1)? ^ imm as u32,
+        Ori { rs1, imm, .. } => reg(rs1)? | imm as u32,
+        Andi { rs1, imm, .. } => reg(rs1)? & imm as u32,
+        Slli { rs1, shamt, .. } => reg(rs1)? << (shamt & 0x1F),
+        Srli { rs1, shamt, .. } => reg(rs1)? >> (shamt & 0x1F),
+        Srai { rs1, shamt, .. } => ((reg(rs1)? as i32) >> (shamt & 0x1F)) as u32,
+        Add { rs1, rs2, .. }
+        | Sub { rs1, rs2, .. }
+        | Sll { rs1, rs2, .. }
+        | Xor { rs1, rs2, .. }
+        | Or { rs1, rs2, .. }
+        | Srl { rs1, rs2, .. }
+        | Sra { rs1, rs2, .. }
+        | Slt { rs1, rs2, .. }
+        | Sltu { rs1, rs2, .. }
+        | And { rs1, rs2, .. }
+        | Mul { rs1, rs2, .. }
+        | Mulh { rs1, rs2, .. }
+        | Mulhsu { rs1, rs2, .. }
+        | Mulhu { rs1, rs2, .. }
+        | Div { rs1, rs2, .. }
+        | Divu { rs1, rs2, .. }
+        | Rem { rs1, rs2, .. }
+        | Remu { rs1, rs2, .. } => {
+            let (a, b) = (reg(rs1)?, reg(rs2)?);
+            match *instruction {
+                Add { .. } => a.wrapping_add(b),
+                Sub { .. } => a.wrapping_sub(b),
+                Sll { .. } => a << (b & 0x1F),
+                Xor { .. } => a ^ b,
+                Or { .. } => a | b,
+                Srl { .. } => a >> (b & 0x1F),
+                Sra { .. } => ((a as i32) >> (b & 0x1F)) as u32,
+                Slt { .. } => ((a as i32) < (b as i32)) as u32,
+                Sltu { .. } => (a < b) as u32,
+                And { .. } => a & b,
+                Mul { .. } => a.wrapping_mul(b),
+                Mulh { .. } => ((a as i32 as i64 * b as i32 as i64) >> 32) as u32,
+                Mulhsu { .. } => ((a as i32 as i64).wrapping_mul(b as i64) >> 32) as u32,
+                Mulhu { .. } => ((a as u64 * b as u64) >> 32) as u32,
+                Div { .. } if b == 0 => u32::MAX,
+                Div { .. } => (a as i32).wrapping_div(b as i32) as u32,
+                Divu { .. } => a.checked_div(b).unwrap_or(u32::MAX),
+                Rem { .. } if b == 0 => a,
+                Rem { .. } => (a as i32).wrapping_rem(b as i32) as u32,
+                _ => a.checked_rem(b).unwrap_or(a),
+            }
+        }
+        _ => return None,
+    };
+    Some(value)
+}
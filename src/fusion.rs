@@ -0,0 +1,155 @@
+//! Macro-op fusion detection
+//!
+//! Some adjacent instruction pairs are idioms for a single operation: LUI+ADDI
+//! materializes a 32-bit constant, AUIPC+JALR makes a PC-relative call or jump,
+//! SLLI+SRLI by the same amount zero-extends the low bits of a register, and
+//! SLT/SLTU followed by BEQ/BNE testing the result against zero is a
+//! compare-and-branch. The compiler translates a fused pair as one native
+//! operation when optimizing, and code-quality reports can count how often
+//! the idioms occur.
+//!
+//! # Example
+//! ```
+//! use jigs::Instruction;
+//! use jigs::fusion::{self, FusionKind};
+//!
+//! let code = [
+//!     Instruction::Lui { rd: 5, imm: 0x12345 },
+//!     Instruction::Addi { rd: 5, rs1: 5, imm: 0x678 },
+//!     Instruction::Ecall,
+//! ];
+//! let pairs = fusion::find(&code);
+//! assert_eq!(pairs.len(), 1);
+//! assert_eq!(pairs[0].start, 0);
+//! assert_eq!(pairs[0].kind, FusionKind::LoadImmediate);
+//! ```
+
+use crate::{
+    Instruction,
+    pattern::{Field, Operand, Pattern, Template},
+};
+use std::fmt;
+
+/// Idiom recognized by a fusible pair
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum FusionKind {
+    /// `lui rd, hi` ; `addi rd, rd, lo`
+    LoadImmediate,
+    /// `auipc rs, hi` ; `jalr rd, rs, lo`
+    FarJump,
+    /// `slli rd, rs, n` ; `srli rd, rd, n`
+    ZeroExtend,
+    /// `slt[u] rd, rs1, rs2` ; `beq`/`bne rd, x0, offset`
+    CompareBranch,
+}
+
+/// A fusible pair of adjacent instructions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fusion {
+    /// Index of the first instruction of the pair
+    pub start: usize,
+    /// Idiom the pair implements
+    pub kind: FusionKind,
+}
+
+impl fmt::Display for FusionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FusionKind::LoadImmediate => "lui+addi",
+            FusionKind::FarJump => "auipc+jalr",
+            FusionKind::ZeroExtend => "slli+srli",
+            FusionKind::CompareBranch => "slt+beq",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Patterns recognizing the forms of the given idiom
+pub fn patterns(kind: FusionKind) -> Vec<Pattern> {
+    match kind {
+        FusionKind::LoadImmediate => vec![
+            Pattern::new()
+                .then(Template::new("lui").with(Field::Rd, Operand::Var("rd")))
+                .then(
+                    Template::new("addi")
+                        .with(Field::Rd, Operand::Var("rd"))
+                        .with(Field::Rs1, Operand::Var("rd")),
+                ),
+        ],
+        FusionKind::FarJump => vec![
+            Pattern::new()
+                .then(Template::new("auipc").with(Field::Rd, Operand::Var("base")))
+                .then(Template::new("jalr").with(Field::Rs1, Operand::Var("base"))),
+        ],
+        FusionKind::ZeroExtend => vec![
+            Pattern::new()
+                .then(
+                    Template::new("slli")
+                        .with(Field::Rd, Operand::Var("rd"))
+                        .with(Field::Imm, Operand::Var("shamt")),
+                )
+                .then(
+                    Template::new("srli")
+                        .with(Field::Rd, Operand::Var("rd"))
+                        .with(Field::Rs1, Operand::Var("rd"))
+                        .with(Field::Imm, Operand::Var("shamt")),
+                ),
+        ],
+        FusionKind::CompareBranch => ["slt", "sltu"]
+            .into_iter()
+            .flat_map(|compare| {
+                ["beq", "bne"].map(|branch| {
+                    Pattern::new()
+                        .then(Template::new(compare).with(Field::Rd, Operand::Var("rd")))
+                        .then(
+                            Template::new(branch)
+                                .with(Field::Rs1, Operand::Var("rd"))
+                                .with(Field::Rs2, Operand::Value(0)),
+                        )
+                })
+            })
+            .collect(),
+    }
+}
+
+/// Find non-overlapping fusible pairs, scanning from the start
+///
+/// LUI+ADDI and SLLI+SRLI pairs targeting x0 are not reported, since they have
+/// no architectural effect to fuse, and neither are compare-and-branch pairs
+/// testing x0.
+pub fn find(instructions: &[Instruction]) -> Vec<Fusion> {
+    let patterns: Vec<(FusionKind, Pattern)> = [
+        FusionKind::LoadImmediate,
+        FusionKind::FarJump,
+        FusionKind::ZeroExtend,
+        FusionKind::CompareBranch,
+    ]
+    .into_iter()
+    .flat_map(|kind| {
+        patterns(kind)
+            .into_iter()
+            .map(move |pattern| (kind, pattern))
+    })
+    .collect();
+
+    let mut fusions = Vec::new();
+    let mut index = 0;
+    while index < instructions.len() {
+        let found = patterns.iter().find(|(_, pattern)| {
+            pattern
+                .match_at(instructions, index)
+                .is_some_and(|found| found.get("rd") != Some(0))
+        });
+        match found {
+            Some((kind, _)) => {
+                fusions.push(Fusion {
+                    start: index,
+                    kind: *kind,
+                });
+                index += 2;
+            }
+            None => index += 1,
+        }
+    }
+    fusions
+}
@@ -0,0 +1,71 @@
+//! Random instruction generation for fuzzing and property tests
+//!
+//! Available with the `arbitrary` feature. `encoding()` draws a random valid
+//! instruction word from an `arbitrary::Unstructured` byte source: an
+//! instruction is chosen from the `isa` encoding table and the remaining
+//! operand bits are filled with random data. `Instruction` implements
+//! `Arbitrary` on top of it, so fuzz targets can take instructions directly.
+//!
+//! # Example
+//! ```
+//! use arbitrary::{Arbitrary, Unstructured};
+//! use jigs::{Extensions, Instruction, generate};
+//!
+//! let bytes = [0x5A; 64];
+//! let mut u = Unstructured::new(&bytes);
+//!
+//! let word = generate::encoding(&mut u, &Extensions::all()).unwrap();
+//! assert_eq!(Instruction::decode(word).encode(), Ok(word));
+//!
+//! let instruction = Instruction::arbitrary(&mut u).unwrap();
+//! assert!(instruction.encode().is_ok());
+//! ```
+
+use crate::{
+    Extensions, Instruction,
+    isa::{ENCODINGS, Encoding},
+};
+use arbitrary::{Arbitrary, Unstructured};
+
+/// Attempts to draw an encoding before falling back to a NOP
+///
+/// A few table entries contain reserved operand values (such as floating-point
+/// rounding modes 5 and 6) that are redrawn.
+const MAX_ATTEMPTS: usize = 16;
+
+/// Canonical NOP (`addi x0, x0, 0`)
+const NOP: u32 = 0x00000013;
+
+/// Draw a random valid instruction word from the enabled extensions
+///
+/// Every returned word decodes to a supported instruction that encodes back to
+/// the same word.
+pub fn encoding(u: &mut Unstructured, extensions: &Extensions) -> arbitrary::Result<u32> {
+    let candidates: Vec<&Encoding> = ENCODINGS
+        .iter()
+        .filter(|encoding| extensions.enabled(encoding.extension))
+        .collect();
+    for _ in 0..MAX_ATTEMPTS {
+        let encoding = *u.choose(&candidates)?;
+        let word = (u32::arbitrary(u)? & !encoding.mask) | encoding.value;
+        if !matches!(Instruction::decode(word), Instruction::Unsupported(_)) {
+            return Ok(word);
+        }
+    }
+    Ok(NOP)
+}
+
+/// Draw a random valid instruction from the enabled extensions
+pub fn instruction(
+    u: &mut Unstructured,
+    extensions: &Extensions,
+) -> arbitrary::Result<Instruction> {
+    encoding(u, extensions).map(Instruction::decode)
+}
+
+impl<'a> Arbitrary<'a> for Instruction {
+    /// Generate a valid instruction from any extension the crate supports
+    fn arbitrary(u: &mut Unstructured<'a>) -> arbitrary::Result<Self> {
+        instruction(u, &Extensions::all())
+    }
+}
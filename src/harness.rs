@@ -0,0 +1,151 @@
+//! Smoke tests for compiled code
+//!
+//! A `Harness` compiles a short instruction sequence into a module of its
+//! own, runs it from its first instruction against a scratch register file
+//! and memory, and reports the registers it leaves behind. Backend changes
+//! can then be checked one idiom at a time instead of through whole modules.
+//!
+//! State carries over between runs, so a snippet can pick up where the last
+//! left off, and each run gets a fresh gas budget, so a snippet that loops
+//! forever stops. Compiled code only runs on aarch64 hosts; elsewhere the
+//! snippet is still compiled, but reports that it could not run.
+//!
+//! # Example
+//! ```
+//! use jigs::Instruction;
+//! use jigs::harness::Harness;
+//!
+//! let mut harness = Harness::new();
+//! harness.set_register(5, 40);
+//! let outcome = harness
+//!     .run(&[Instruction::Addi { rd: 6, rs1: 5, imm: 2 }])
+//!     .unwrap();
+//! if cfg!(target_arch = "aarch64") {
+//!     assert_eq!(outcome.result, Ok(()));
+//!     assert_eq!((outcome.registers[6], outcome.pc), (42, 4));
+//! }
+//! ```
+
+use crate::{
+    Instance, Instruction, Memory, PageStore,
+    compiler::OptLevel,
+    module::{CompileError, Module},
+};
+use std::array;
+
+/// Pages of scratch memory, allocated on first use anywhere in the address
+/// space
+const PAGES: usize = 16;
+
+/// L2 tables of scratch memory
+const L2_TABLES: usize = 4;
+
+/// Runs instruction sequences as compiled code against scratch state
+pub struct Harness {
+    /// Registers and memory the snippets run against
+    instance: Instance,
+    /// Passes snippets are compiled with
+    opt_level: OptLevel,
+    /// Gas for each run
+    gas: u64,
+}
+
+/// Guest state after a snippet ran
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Outcome {
+    /// Registers x0-x31
+    pub registers: [u32; 32],
+    /// PC execution stopped at, or jumped to outside the snippet
+    pub pc: u32,
+    /// `Ok` when control left the snippet, otherwise why it stopped, as
+    /// `Instance::call_function()` reports it
+    pub result: Result<(), &'static str>,
+}
+
+impl Harness {
+    /// Default gas for each run
+    pub const DEFAULT_GAS: u64 = 1_000_000;
+
+    /// Create a harness with zeroed registers and empty memory, compiling
+    /// without optimizations
+    pub fn new() -> Self {
+        let store = PageStore::new(PAGES);
+        Harness {
+            instance: Instance::new(Memory::new(&store, PAGES, L2_TABLES)),
+            opt_level: OptLevel::O0,
+            gas: Self::DEFAULT_GAS,
+        }
+    }
+
+    /// Set the passes subsequent snippets are compiled with
+    pub fn set_opt_level(&mut self, opt_level: OptLevel) {
+        self.opt_level = opt_level;
+    }
+
+    /// Set the gas each run starts with
+    pub fn set_gas(&mut self, gas: u64) {
+        self.gas = gas;
+    }
+
+    /// Read a guest register
+    pub fn register(&self, reg: u8) -> u32 {
+        self.instance.read_register(reg)
+    }
+
+    /// Write a guest register; writes to x0 are ignored
+    pub fn set_register(&mut self, reg: u8, value: u32) {
+        self.instance.write_register(reg, value);
+    }
+
+    /// Get the scratch memory
+    pub fn memory(&self) -> &Memory {
+        self.instance.memory()
+    }
+
+    /// Get the scratch memory for setting up or changing
+    pub fn memory_mut(&mut self) -> &mut Memory {
+        self.instance.memory_mut()
+    }
+
+    /// Compile `instructions` at guest address 0 and run them from the first
+    ///
+    /// # Errors
+    /// Returns `CompileError::InvalidCode` for instructions that cannot be
+    /// encoded, and the errors of `Module::set_code()`
+    pub fn run(&mut self, instructions: &[Instruction]) -> Result<Outcome, CompileError> {
+        let code = Instruction::encode_all(instructions).map_err(|_| CompileError::InvalidCode)?;
+        let mut module = Module::new(code.len())?;
+        module.set_opt_level(self.opt_level);
+        module.set_code(&code)?;
+
+        self.instance.set_gas(self.gas);
+        self.instance.set_pc(0);
+        let result = self.call(&mut module);
+        Ok(Outcome {
+            registers: array::from_fn(|reg| self.instance.read_register(reg as u8)),
+            pc: self.instance.pc(),
+            result,
+        })
+    }
+
+    /// Attach the instance to `module` and call its code from the start
+    fn call(&mut self, module: &mut Module) -> Result<(), &'static str> {
+        if !cfg!(target_arch = "aarch64") {
+            return Err("Compiled code needs an aarch64 host");
+        }
+        self.instance
+            .attach(module)
+            .map_err(|_| "Module data does not fit in memory")?;
+        // The code was just compiled for this host and the instance is
+        // attached
+        let result = unsafe { self.instance.call_function(0) };
+        self.instance.detach();
+        result
+    }
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,12 +1,180 @@
-use crate::{memory::Memory, module::Module};
+use crate::{
+    layout::MemoryLayout,
+    memory::{Access, Memory, MemoryError},
+    module::Module,
+};
 use std::{mem, ptr};
 
+/// Compiled code ran off its end or jumped to a PC it cannot branch to
+pub(crate) const EXIT_JUMP: u32 = 0;
+/// A guest load or store failed
+pub(crate) const EXIT_FAULT: u32 = 1;
+/// ECALL instruction reached
+pub(crate) const EXIT_ECALL: u32 = 2;
+/// EBREAK instruction reached
+pub(crate) const EXIT_EBREAK: u32 = 3;
+/// Instruction that compiled code does not support reached
+pub(crate) const EXIT_ILLEGAL: u32 = 4;
+/// Not enough gas left to enter a basic block
+pub(crate) const EXIT_OUT_OF_GAS: u32 = 5;
+
+/// Bit set in a load helper result when the access failed
+pub(crate) const LOAD_FAULT_BIT: u8 = 32;
+
+/// Guest load called by compiled code, returning the value or `LOAD_FAULT_BIT`
+pub(crate) type LoadHelper = extern "C" fn(*mut Memory, u32, u32) -> u64;
+/// Guest store called by compiled code, returning nonzero on failure
+pub(crate) type StoreHelper = extern "C" fn(*mut Memory, u32, u32, u32) -> u32;
+/// ECALL trampoline called by compiled code with the ECALL's PC, returning
+/// nonzero to resume after it
+pub(crate) type EcallHelper = extern "C" fn(*mut Context, u32) -> u32;
+
+/// Host function servicing guest ECALLs from compiled code
+///
+/// Receives the instance with its registers and PC at the ECALL, and returns
+/// true to resume at the following instruction or false to stop with an
+/// environment call error.
+pub type EcallHandler = Box<dyn FnMut(&mut Instance) -> bool>;
+
+/// State shared between `call_function` and compiled code
+///
+/// Compiled code receives a pointer to this in x0 and reaches all runtime
+/// state through it, so no host addresses are embedded in the code itself.
+#[repr(C)]
+pub(crate) struct Context {
+    /// Guest register file x0-x31
+    pub(crate) registers: *mut u32,
+    /// Memory accessed by guest loads and stores
+    pub(crate) memory: *mut Memory,
+    /// Load helper
+    pub(crate) load: LoadHelper,
+    /// Store helper
+    pub(crate) store: StoreHelper,
+    /// Guest PC to resume at on entry, and at exit
+    pub(crate) pc: u32,
+    /// Exit reason (`EXIT_*`)
+    pub(crate) exit: u32,
+    /// Nonzero when the memory has MMIO devices, which sends every guest
+    /// access through the helpers instead of the inline page table walk
+    pub(crate) devices: u32,
+    /// Remaining gas, charged a basic block at a time
+    pub(crate) gas: u64,
+    /// Instance running the code, passed to its ECALL handler
+    pub(crate) instance: *mut Instance,
+    /// ECALL trampoline
+    pub(crate) ecall: EcallHelper,
+    /// Block entry counters, for code compiled with them
+    pub(crate) counters: *mut u64,
+    /// Page compiled loads last went to
+    pub(crate) tlb_read: TlbEntry,
+    /// Page compiled stores last went to
+    pub(crate) tlb_write: TlbEntry,
+}
+
+/// Page a compiled load or store found by walking the page tables, so the
+/// next access of the same kind to that page can skip the walk
+///
+/// Only the inline walk fills entries, so they never cover devices, and they
+/// start empty on every entry into compiled code and after every ECALL, the
+/// only points where the memory's pages, permissions or devices can change.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TlbEntry {
+    /// Host address of the page
+    pub(crate) host: *mut u8,
+    /// Guest page number, or `u32::MAX`, above every page, when empty
+    pub(crate) page: u32,
+}
+
+impl TlbEntry {
+    /// Entry matching no page
+    pub(crate) const EMPTY: TlbEntry = TlbEntry {
+        host: ptr::null_mut(),
+        page: u32::MAX,
+    };
+}
+
+/// Perform a guest load of the width given by the RISC-V load `funct3`
+///
+/// Mirrors the interpreter: device ranges are passed to the device, other
+/// accesses are checked against page permissions. LB and LH sign-extend.
+extern "C" fn load(memory: *mut Memory, address: u32, funct3: u32) -> u64 {
+    let memory = unsafe { &mut *memory };
+    let len = 1 << (funct3 & 3);
+    let value = if memory.mmio(address, len) {
+        match memory.mmio_read(address, len) {
+            Some(value) => value,
+            None => return 1 << LOAD_FAULT_BIT,
+        }
+    } else {
+        if memory.check(address, len, Access::Read).is_err() {
+            return 1 << LOAD_FAULT_BIT;
+        }
+        let mut bytes = [0; 4];
+        memory.read(address, &mut bytes[..len as usize]);
+        u32::from_le_bytes(bytes)
+    };
+    let value = match funct3 {
+        0 => value as i8 as u32,
+        1 => value as i16 as u32,
+        _ => value,
+    };
+    value as u64
+}
+
+/// Perform a guest store of the width given by the RISC-V store `funct3`
+extern "C" fn store(memory: *mut Memory, address: u32, value: u32, funct3: u32) -> u32 {
+    let memory = unsafe { &mut *memory };
+    let len = 1 << (funct3 & 3);
+    if memory.mmio(address, len) {
+        return !memory.mmio_write(address, len, value) as u32;
+    }
+    if memory.check(address, len, Access::Write).is_err() {
+        return 1;
+    }
+    let bytes = value.to_le_bytes();
+    memory.write(address, &bytes[..len as usize]).is_err() as u32
+}
+
+/// Run the instance's ECALL handler for the ECALL at `pc`
+///
+/// Compiled code writes its mapped registers back before the call and reloads
+/// them after, so the handler sees and may change every register. Gas and the
+/// device flag are synced around the call, as the handler may change either,
+/// and the TLB entries are dropped, as it may change the memory's pages.
+extern "C" fn ecall(context: *mut Context, pc: u32) -> u32 {
+    let context = unsafe { &mut *context };
+    let instance = unsafe { &mut *context.instance };
+    let Some(mut handler) = instance.ecall.take() else {
+        return 0;
+    };
+    instance.pc = pc;
+    instance.gas = context.gas;
+    let resume = handler(instance);
+    instance.ecall = Some(handler);
+    context.gas = instance.gas;
+    context.devices = instance.memory.devices() as u32;
+    context.tlb_read = TlbEntry::EMPTY;
+    context.tlb_write = TlbEntry::EMPTY;
+    resume as u32
+}
+
 /// Runtime instance for executing compiled RISC-V code
 pub struct Instance {
     /// Pointer to the compiled module (null if detached)
     module: *mut Module,
     /// Memory system for this instance (Box for stable pointer)
     memory: Box<Memory>,
+    /// Guest register file x0-x31 (Box for stable pointer, x0 is always zero)
+    registers: Box<[u32; 32]>,
+    /// Guest program counter
+    pc: u32,
+    /// Gas left for compiled code
+    gas: u64,
+    /// Host function servicing ECALLs from compiled code
+    ecall: Option<EcallHandler>,
+    /// Entries into each basic block, for code compiled with block counters
+    counters: Vec<u64>,
 }
 
 impl Instance {
@@ -15,23 +183,40 @@ impl Instance {
         Instance {
             module: ptr::null_mut(),
             memory: Box::new(memory),
+            registers: Box::new([0; 32]),
+            pc: 0,
+            gas: u64::MAX,
+            ecall: None,
+            counters: Vec::new(),
         }
     }
 
-    /// Attach this instance to a module
+    /// Attach this instance to a module, clearing the block counts and
+    /// writing the module's data (see `Module::set_data()`) into memory
     ///
     /// # Safety
     /// The module must outlive this instance unless detached
-    pub fn attach(&mut self, module: &mut Module) {
+    ///
+    /// # Errors
+    /// Returns the error of the first data write that fails, leaving the
+    /// instance detached with the data written so far
+    pub fn attach(&mut self, module: &mut Module) -> Result<(), MemoryError> {
         if !self.module.is_null() {
             self.detach();
         }
+        for (address, data, tail) in module.data() {
+            self.memory.write(*address, data)?;
+            let end = address.wrapping_add(data.len() as u32);
+            self.memory.fill(end, *tail, 0)?;
+        }
         self.module = module as *mut Module;
+        self.counters.clear();
         unsafe {
             (*self.module).instance_count += 1;
             // Set the module's memory pointer to point to this instance's memory
             *(*self.module).memory_ptr = &mut *self.memory as *mut Memory;
         }
+        Ok(())
     }
 
     /// Detach this instance from its module
@@ -51,6 +236,11 @@ impl Instance {
         !self.module.is_null()
     }
 
+    /// Check if this instance is attached to `module`
+    pub(crate) fn attached_to(&self, module: &Module) -> bool {
+        ptr::eq(self.module, module)
+    }
+
     /// Get a reference to this instance's memory
     pub fn memory(&self) -> &Memory {
         &self.memory
@@ -61,13 +251,141 @@ impl Instance {
         &mut self.memory
     }
 
+    /// Read a guest register
+    ///
+    /// Register x0 always reads as zero. Registers outside 0-31 read as zero.
+    pub fn read_register(&self, reg: u8) -> u32 {
+        match reg {
+            1..=31 => self.registers[reg as usize],
+            _ => 0,
+        }
+    }
+
+    /// Write a guest register
+    ///
+    /// Writes to x0 and to registers outside 0-31 are ignored.
+    pub fn write_register(&mut self, reg: u8, value: u32) {
+        if let 1..=31 = reg {
+            self.registers[reg as usize] = value;
+        }
+    }
+
+    /// Point the stack pointer (x2) at the top of the layout's stack
+    pub fn setup_stack(&mut self, layout: &MemoryLayout) {
+        self.write_register(2, layout.stack_top());
+    }
+
+    /// Get the guest program counter
+    pub fn pc(&self) -> u32 {
+        self.pc
+    }
+
+    /// Set the guest program counter
+    pub fn set_pc(&mut self, pc: u32) {
+        self.pc = pc;
+    }
+
+    /// Gas left for compiled code (`u64::MAX`, effectively unlimited, until set)
+    pub fn gas(&self) -> u64 {
+        self.gas
+    }
+
+    /// Set the gas available to compiled code
+    ///
+    /// Every guest instruction costs one unit. Compiled code charges each
+    /// basic block on entry and stops with an out-of-gas error, before running
+    /// any of the block, when the remaining gas cannot cover it.
+    ///
+    /// The budget is read from the instance on every call rather than
+    /// compiled in, so one module serves callers with different budgets.
+    pub fn set_gas(&mut self, gas: u64) {
+        self.gas = gas;
+    }
+
+    /// Entries into each basic block counted by compiled code
+    ///
+    /// Indexed like `SourceMap::counters()`, which gives the guest PC of each
+    /// block. Counts add up over calls until reset, and are empty unless the
+    /// module was compiled with `Module::set_block_counters(true)`.
+    pub fn block_counts(&self) -> &[u64] {
+        &self.counters
+    }
+
+    /// Set every block count to zero
+    pub fn reset_block_counts(&mut self) {
+        self.counters.fill(0);
+    }
+
+    /// Set the host function servicing ECALLs from compiled code
+    ///
+    /// Without a handler, compiled code stops at every ECALL.
+    pub fn set_ecall_handler(&mut self, handler: EcallHandler) {
+        self.ecall = Some(handler);
+    }
+
+    /// Remove the ECALL handler, returning it
+    pub fn take_ecall_handler(&mut self) -> Option<EcallHandler> {
+        self.ecall.take()
+    }
+
     /// Call a function in the compiled module
     ///
+    /// Runs the compiled code from the entry of the function at
+    /// `function_index` in the module's function table (see
+    /// `Module::set_functions()`), or from its first instruction (the
+    /// module's base) for index 0 without a table, against this instance's
+    /// registers and memory. Other indices are an error. Returns Ok when the code
+    /// jumps to a PC outside the module, such as a return to a caller, and an
+    /// error when it stops at a fault, an ECALL its handler declines (or any
+    /// ECALL without a handler), EBREAK, unsupported instruction or for lack
+    /// of gas. Either way the PC is left at the jump
+    /// target or stopping instruction, and the gas left is kept for the next
+    /// call.
+    ///
+    /// # Safety
+    /// - Instance must be attached to a module
+    /// - Module's compiled code must be valid ARM64 instructions
+    /// - A module loaded with `Module::deserialize()` must come from bytes of a
+    ///   trusted source
+    pub unsafe fn call_function(&mut self, function_index: usize) -> Result<(), &'static str> {
+        let entry = |module: &Module| {
+            let entry = module.source_map().function(function_index);
+            entry.ok_or("Invalid function index")
+        };
+        unsafe { self.enter(entry, 0) }
+    }
+
+    /// Run the compiled module from the instance's PC
+    ///
+    /// Works like `call_function()`, entering the code at the current PC
+    /// instead of the first instruction and charging the gas for the rest of its
+    /// basic block. A PC outside the module or not on an instruction boundary
+    /// returns Ok at once, with the PC and gas unchanged. The module
+    /// must have been compiled with `Module::set_resumable(true)`.
+    ///
     /// # Safety
     /// - Instance must be attached to a module
-    /// - Function index must be valid
     /// - Module's compiled code must be valid ARM64 instructions
-    pub unsafe fn call_function(&mut self, _function_index: usize) -> Result<(), &'static str> {
+    /// - A module loaded with `Module::deserialize()` must come from bytes of a
+    ///   trusted source
+    pub unsafe fn resume(&mut self) -> Result<(), &'static str> {
+        let pc = self.pc;
+        let entry = |module: &Module| {
+            module
+                .source_map()
+                .resume()
+                .ok_or("Module is not resumable")
+        };
+        unsafe { self.enter(entry, pc) }
+    }
+
+    /// Run the module's code from the code offset `entry` picks, with `pc` in
+    /// the `Context`
+    unsafe fn enter(
+        &mut self,
+        entry: impl FnOnce(&Module) -> Result<usize, &'static str>,
+        pc: u32,
+    ) -> Result<(), &'static str> {
         unsafe {
             if self.module.is_null() {
                 return Err("Instance not attached to module");
@@ -80,16 +398,47 @@ impl Instance {
             if code.is_empty() {
                 return Err("Module has no compiled code");
             }
+            let entry = entry(module)?;
 
-            // Cast the code buffer to a function pointer
-            // The compiled code should start with the function we want to call
-            let fn_ptr = code.as_ptr() as *const ();
-            let func: extern "C" fn() = mem::transmute(fn_ptr);
+            // Compiled stores to mapped pages bypass `Memory::write`, so they
+            // cannot invalidate a reservation; drop it up front, as SC may fail
+            // spuriously anyway
+            self.memory.clear_reservation();
+            self.counters
+                .resize(module.source_map().counters().len(), 0);
+            let mut context = Context {
+                registers: self.registers.as_mut_ptr(),
+                memory: &mut *self.memory,
+                load,
+                store,
+                pc,
+                exit: EXIT_JUMP,
+                devices: self.memory.devices() as u32,
+                gas: self.gas,
+                counters: self.counters.as_mut_ptr(),
+                instance: self,
+                ecall,
+                tlb_read: TlbEntry::EMPTY,
+                tlb_write: TlbEntry::EMPTY,
+            };
+
+            // Cast the entry to a function pointer
+            let fn_ptr = code.as_ptr().add(entry) as *const ();
+            let func: unsafe extern "C" fn(*mut Context) = mem::transmute(fn_ptr);
 
             // Call the function
-            func();
+            func(&mut context);
 
-            Ok(())
+            self.pc = context.pc;
+            self.gas = context.gas;
+            match context.exit {
+                EXIT_JUMP => Ok(()),
+                EXIT_FAULT => Err("Memory access fault"),
+                EXIT_ECALL => Err("Environment call"),
+                EXIT_EBREAK => Err("Breakpoint"),
+                EXIT_OUT_OF_GAS => Err("Out of gas"),
+                _ => Err("Unsupported instruction"),
+            }
         }
     }
 }
@@ -56,6 +56,16 @@
 //! - Division: DIV, DIVU
 //! - Remainder: REM, REMU
 //!
+//! ## Zfh Extension (Half-Precision Floating-Point, subset)
+//! - Memory: FLH, FSH
+//! - Arithmetic: FADD.H, FSUB.H, FMUL.H, FDIV.H
+//! - Conversions: FCVT.W.H, FCVT.WU.H, FCVT.H.W, FCVT.H.WU, FCVT.S.H, FCVT.H.S
+//!
+//! ## V Extension (Vector, subset)
+//! - Configuration: VSETVLI
+//! - Unit-stride memory: VLE8/16/32/64.V, VSE8/16/32/64.V
+//! - Arithmetic: VADD.VV, VADD.VX, VADD.VI
+//!
 //! # Examples
 //!
 //! ## Decoding
@@ -111,6 +121,19 @@
 //! }
 //! ```
 //!
+//! ## Validated Construction
+//! ```
+//! use jigs::{Instruction, EncodeError};
+//!
+//! // Constructors apply the encoder's range checks up front
+//! let instruction = Instruction::addi(5, 6, 100).unwrap();
+//! assert_eq!(instruction, Instruction::Addi { rd: 5, rs1: 6, imm: 100 });
+//! assert_eq!(
+//!     Instruction::addi(5, 6, 4096),
+//!     Err(EncodeError::InvalidImmediate("imm", 4096))
+//! );
+//! ```
+//!
 //! ## Round-trip Encoding and Decoding
 //! ```
 //! use jigs::Instruction;
@@ -128,6 +151,7 @@
 //! assert_eq!(original, decoded);
 //! ```
 
+use crate::{csr, decoder::Extension};
 use std::fmt;
 
 /// Error type for instruction encoding failures.
@@ -139,6 +163,10 @@ pub enum EncodeError {
     InvalidRegister(&'static str, u8),
     /// An immediate value exceeds the valid range for the instruction type
     InvalidImmediate(&'static str, i32),
+    /// The output buffer is too small to hold the encoding (holds the buffer length)
+    BufferTooSmall(usize),
+    /// Only branches and JAL can be encoded with a relocation
+    NotRelocatable,
 }
 
 impl fmt::Display for EncodeError {
@@ -161,12 +189,143 @@ impl fmt::Display for EncodeError {
             EncodeError::InvalidImmediate(field, value) => {
                 write!(f, "Invalid immediate value for {}: {}", field, value)
             }
+            EncodeError::BufferTooSmall(length) => {
+                write!(f, "Buffer too small for instruction: {} bytes", length)
+            }
+            EncodeError::NotRelocatable => {
+                write!(f, "Only branches and JAL can be relocated")
+            }
         }
     }
 }
 
 impl std::error::Error for EncodeError {}
 
+/// Broad classification of instructions
+///
+/// Used for gas bucketing, execution statistics and trace filtering. Loads and
+/// stores are classified by their memory access regardless of extension, so
+/// `Vector` and `Float` cover the remaining vector and floating-point operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum InstructionKind {
+    /// Integer arithmetic, logic, shifts, comparisons, LUI and AUIPC
+    Arithmetic,
+    /// Memory loads
+    Load,
+    /// Memory stores
+    Store,
+    /// Conditional branches
+    Branch,
+    /// JAL and JALR
+    Jump,
+    /// ECALL and EBREAK
+    System,
+    /// Integer multiplication
+    Mul,
+    /// Integer division and remainder
+    Div,
+    /// Vector configuration and arithmetic
+    Vector,
+    /// Floating-point arithmetic and conversions
+    Float,
+    /// Unrecognized encodings
+    Unsupported,
+}
+
+/// A register operand together with the register file it names
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Register {
+    /// Integer register `x0`-`x31`
+    X(u8),
+    /// Floating-point register `f0`-`f31`
+    F(u8),
+    /// Vector register `v0`-`v31`
+    V(u8),
+}
+
+/// PC-relative immediate field patched by a relocation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocationKind {
+    /// B-type conditional branch offset (±4 KiB)
+    Branch,
+    /// J-type JAL offset (±1 MiB)
+    Jump,
+}
+
+/// A placeholder target in encoded code, to be patched once its symbol is known
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Relocation {
+    /// Byte offset of the instruction in the code
+    pub offset: u32,
+    /// Field to patch
+    pub kind: RelocationKind,
+    /// Symbol whose address is the target
+    pub symbol: String,
+}
+
+impl InstructionKind {
+    /// Every kind, in declaration order
+    pub const ALL: [InstructionKind; 11] = [
+        InstructionKind::Arithmetic,
+        InstructionKind::Load,
+        InstructionKind::Store,
+        InstructionKind::Branch,
+        InstructionKind::Jump,
+        InstructionKind::System,
+        InstructionKind::Mul,
+        InstructionKind::Div,
+        InstructionKind::Vector,
+        InstructionKind::Float,
+        InstructionKind::Unsupported,
+    ];
+}
+
+impl Register {
+    /// Register number within its file
+    pub fn number(self) -> u8 {
+        match self {
+            Register::X(reg) | Register::F(reg) | Register::V(reg) => reg,
+        }
+    }
+
+    /// Integer register number, or None for floating-point and vector registers
+    pub fn integer(self) -> Option<u8> {
+        match self {
+            Register::X(reg) => Some(reg),
+            Register::F(_) | Register::V(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for Register {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Register::X(reg) => write!(f, "x{}", reg),
+            Register::F(reg) => write!(f, "f{}", reg),
+            Register::V(reg) => write!(f, "v{}", reg),
+        }
+    }
+}
+
+impl fmt::Display for InstructionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            InstructionKind::Arithmetic => "arithmetic",
+            InstructionKind::Load => "load",
+            InstructionKind::Store => "store",
+            InstructionKind::Branch => "branch",
+            InstructionKind::Jump => "jump",
+            InstructionKind::System => "system",
+            InstructionKind::Mul => "mul",
+            InstructionKind::Div => "div",
+            InstructionKind::Vector => "vector",
+            InstructionKind::Float => "float",
+            InstructionKind::Unsupported => "unsupported",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 // Masks for extracting instruction fields
 const OPCODE_MASK: u32 = 0x7F;
 const RD_MASK: u32 = 0xF80;
@@ -220,6 +379,17 @@ const IMM_J_10_1_SHIFT: u32 = 21;
 const IMM_U_MASK: u32 = 0xFFFFF000; // bits 31:12 -> imm[31:12]
 const IMM_U_SHIFT: u32 = 12;
 
+// Floating-point rounding mode (funct3) selecting the dynamic mode from fcsr
+const ROUNDING_DYNAMIC: u8 = 7;
+
+// Vector instruction fields
+const VM_MASK: u32 = 0x2000000; // bit 25 -> vm (1 = unmasked)
+const VM_SHIFT: u32 = 25;
+const FUNCT6_MASK: u32 = 0xFC000000; // bits 31:26 -> funct6
+const FUNCT6_SHIFT: u32 = 26;
+const VECTOR_MEMORY_MASK: u32 = 0xFC000000; // bits 31:26 -> nf, mew, mop
+const VTYPEI_MASK: u32 = 0x7FF00000; // bits 30:20 -> vtypei
+
 /// RISC-V instruction representation for 32-bit IM
 #[derive(Debug, Clone, PartialEq)]
 pub enum Instruction {
@@ -485,6 +655,176 @@ pub enum Instruction {
     /// Causes the processor to enter debug mode.
     Ebreak,
 
+    /// Rdcycle instruction (Zicntr)
+    ///
+    /// Reads the low 32 bits of the cycle counter into register `rd`.
+    /// Encoded as `csrrs rd, cycle, x0`.
+    Rdcycle { rd: u8 },
+
+    /// Rdcycleh instruction (Zicntr)
+    ///
+    /// Reads the high 32 bits of the cycle counter into register `rd`.
+    /// Encoded as `csrrs rd, cycleh, x0`.
+    Rdcycleh { rd: u8 },
+
+    /// Rdtime instruction (Zicntr)
+    ///
+    /// Reads the low 32 bits of the real-time counter into register `rd`.
+    /// Encoded as `csrrs rd, time, x0`.
+    Rdtime { rd: u8 },
+
+    /// Rdtimeh instruction (Zicntr)
+    ///
+    /// Reads the high 32 bits of the real-time counter into register `rd`.
+    /// Encoded as `csrrs rd, timeh, x0`.
+    Rdtimeh { rd: u8 },
+
+    /// Rdinstret instruction (Zicntr)
+    ///
+    /// Reads the low 32 bits of the instructions-retired counter into register `rd`.
+    /// Encoded as `csrrs rd, instret, x0`.
+    Rdinstret { rd: u8 },
+
+    /// Rdinstreth instruction (Zicntr)
+    ///
+    /// Reads the high 32 bits of the instructions-retired counter into register `rd`.
+    /// Encoded as `csrrs rd, instreth, x0`.
+    Rdinstreth { rd: u8 },
+
+    /// Vsetvli instruction
+    ///
+    /// Sets the vector length `vl` from the application vector length in register `rs1` and the
+    /// vector type from the 11-bit immediate `vtypei`, writing the new `vl` to `rd`.
+    /// Part of the V extension.
+    Vsetvli { rd: u8, rs1: u8, vtypei: u16 },
+
+    /// Vle instruction (vle8.v, vle16.v, vle32.v, vle64.v)
+    ///
+    /// Loads `vl` elements of `width` bits from consecutive memory starting at the address in
+    /// register `rs1` into vector register `vd`. The operation is masked by `v0` when `vm` is false.
+    /// Part of the V extension.
+    Vle {
+        vd: u8,
+        rs1: u8,
+        width: u8,
+        vm: bool,
+    },
+
+    /// Vse instruction (vse8.v, vse16.v, vse32.v, vse64.v)
+    ///
+    /// Stores `vl` elements of `width` bits from vector register `vs3` to consecutive memory
+    /// starting at the address in register `rs1`. The operation is masked by `v0` when `vm` is false.
+    /// Part of the V extension.
+    Vse {
+        vs3: u8,
+        rs1: u8,
+        width: u8,
+        vm: bool,
+    },
+
+    /// Vadd.vv instruction
+    ///
+    /// Adds the elements of vector registers `vs2` and `vs1` and stores the results in `vd`.
+    /// The operation is masked by `v0` when `vm` is false.
+    /// Part of the V extension.
+    VaddVv { vd: u8, vs2: u8, vs1: u8, vm: bool },
+
+    /// Vadd.vx instruction
+    ///
+    /// Adds the value in scalar register `rs1` to each element of vector register `vs2` and stores
+    /// the results in `vd`. The operation is masked by `v0` when `vm` is false.
+    /// Part of the V extension.
+    VaddVx { vd: u8, vs2: u8, rs1: u8, vm: bool },
+
+    /// Vadd.vi instruction
+    ///
+    /// Adds the sign-extended 5-bit immediate to each element of vector register `vs2` and stores
+    /// the results in `vd`. The operation is masked by `v0` when `vm` is false.
+    /// Part of the V extension.
+    VaddVi { vd: u8, vs2: u8, imm: i32, vm: bool },
+
+    /// Flh instruction
+    ///
+    /// Loads a half-precision float from memory at address `rs1 + imm` into float register `rd`.
+    /// Part of the Zfh extension.
+    Flh { rd: u8, rs1: u8, imm: i32 },
+
+    /// Fsh instruction
+    ///
+    /// Stores the half-precision float in float register `rs2` to memory at address `rs1 + imm`.
+    /// Part of the Zfh extension.
+    Fsh { rs1: u8, rs2: u8, imm: i32 },
+
+    /// Fadd.h instruction
+    ///
+    /// Adds the half-precision floats in float registers `rs1` and `rs2` and stores the result in
+    /// float register `rd`, rounding with rounding mode `rm`.
+    /// Part of the Zfh extension.
+    FaddH { rd: u8, rs1: u8, rs2: u8, rm: u8 },
+
+    /// Fsub.h instruction
+    ///
+    /// Subtracts the half-precision float in float register `rs2` from `rs1` and stores the result
+    /// in float register `rd`, rounding with rounding mode `rm`.
+    /// Part of the Zfh extension.
+    FsubH { rd: u8, rs1: u8, rs2: u8, rm: u8 },
+
+    /// Fmul.h instruction
+    ///
+    /// Multiplies the half-precision floats in float registers `rs1` and `rs2` and stores the
+    /// result in float register `rd`, rounding with rounding mode `rm`.
+    /// Part of the Zfh extension.
+    FmulH { rd: u8, rs1: u8, rs2: u8, rm: u8 },
+
+    /// Fdiv.h instruction
+    ///
+    /// Divides the half-precision float in float register `rs1` by `rs2` and stores the result in
+    /// float register `rd`, rounding with rounding mode `rm`.
+    /// Part of the Zfh extension.
+    FdivH { rd: u8, rs1: u8, rs2: u8, rm: u8 },
+
+    /// Fcvt.w.h instruction
+    ///
+    /// Converts the half-precision float in float register `rs1` to a signed 32-bit integer in
+    /// register `rd`, rounding with rounding mode `rm`.
+    /// Part of the Zfh extension.
+    FcvtWH { rd: u8, rs1: u8, rm: u8 },
+
+    /// Fcvt.wu.h instruction
+    ///
+    /// Converts the half-precision float in float register `rs1` to an unsigned 32-bit integer in
+    /// register `rd`, rounding with rounding mode `rm`.
+    /// Part of the Zfh extension.
+    FcvtWuH { rd: u8, rs1: u8, rm: u8 },
+
+    /// Fcvt.h.w instruction
+    ///
+    /// Converts the signed 32-bit integer in register `rs1` to a half-precision float in float
+    /// register `rd`, rounding with rounding mode `rm`.
+    /// Part of the Zfh extension.
+    FcvtHW { rd: u8, rs1: u8, rm: u8 },
+
+    /// Fcvt.h.wu instruction
+    ///
+    /// Converts the unsigned 32-bit integer in register `rs1` to a half-precision float in float
+    /// register `rd`, rounding with rounding mode `rm`.
+    /// Part of the Zfh extension.
+    FcvtHWu { rd: u8, rs1: u8, rm: u8 },
+
+    /// Fcvt.s.h instruction
+    ///
+    /// Converts the half-precision float in float register `rs1` to single precision in float
+    /// register `rd`. The conversion is exact; `rm` is encoded but has no effect.
+    /// Part of the Zfh extension.
+    FcvtSH { rd: u8, rs1: u8, rm: u8 },
+
+    /// Fcvt.h.s instruction
+    ///
+    /// Converts the single-precision float in float register `rs1` to half precision in float
+    /// register `rd`, rounding with rounding mode `rm`.
+    /// Part of the Zfh extension.
+    FcvtHS { rd: u8, rs1: u8, rm: u8 },
+
     /// Unsupported instruction
     ///
     /// Represents an instruction that is not yet implemented or recognized.
@@ -635,6 +975,72 @@ impl fmt::Display for Instruction {
             Instruction::Ebreak => {
                 write!(f, "ebreak")
             }
+            Instruction::Rdcycle { rd } => write!(f, "rdcycle x{}", rd),
+            Instruction::Rdcycleh { rd } => write!(f, "rdcycleh x{}", rd),
+            Instruction::Rdtime { rd } => write!(f, "rdtime x{}", rd),
+            Instruction::Rdtimeh { rd } => write!(f, "rdtimeh x{}", rd),
+            Instruction::Rdinstret { rd } => write!(f, "rdinstret x{}", rd),
+            Instruction::Rdinstreth { rd } => write!(f, "rdinstreth x{}", rd),
+            Instruction::Vsetvli { rd, rs1, vtypei } => {
+                write!(f, "vsetvli x{}, x{}, ", rd, rs1)?;
+                format_vtype(f, *vtypei)
+            }
+            Instruction::Vle { vd, rs1, width, vm } => {
+                write!(f, "vle{}.v v{}, (x{}){}", width, vd, rs1, mask(*vm))
+            }
+            Instruction::Vse {
+                vs3,
+                rs1,
+                width,
+                vm,
+            } => {
+                write!(f, "vse{}.v v{}, (x{}){}", width, vs3, rs1, mask(*vm))
+            }
+            Instruction::VaddVv { vd, vs2, vs1, vm } => {
+                write!(f, "vadd.vv v{}, v{}, v{}{}", vd, vs2, vs1, mask(*vm))
+            }
+            Instruction::VaddVx { vd, vs2, rs1, vm } => {
+                write!(f, "vadd.vx v{}, v{}, x{}{}", vd, vs2, rs1, mask(*vm))
+            }
+            Instruction::VaddVi { vd, vs2, imm, vm } => {
+                write!(f, "vadd.vi v{}, v{}, {}{}", vd, vs2, imm, mask(*vm))
+            }
+            Instruction::Flh { rd, rs1, imm } => {
+                write!(f, "flh f{}, {}(x{})", rd, imm, rs1)
+            }
+            Instruction::Fsh { rs1, rs2, imm } => {
+                write!(f, "fsh f{}, {}(x{})", rs2, imm, rs1)
+            }
+            Instruction::FaddH { rd, rs1, rs2, rm } => {
+                write!(f, "fadd.h f{}, f{}, f{}{}", rd, rs1, rs2, rounding(*rm))
+            }
+            Instruction::FsubH { rd, rs1, rs2, rm } => {
+                write!(f, "fsub.h f{}, f{}, f{}{}", rd, rs1, rs2, rounding(*rm))
+            }
+            Instruction::FmulH { rd, rs1, rs2, rm } => {
+                write!(f, "fmul.h f{}, f{}, f{}{}", rd, rs1, rs2, rounding(*rm))
+            }
+            Instruction::FdivH { rd, rs1, rs2, rm } => {
+                write!(f, "fdiv.h f{}, f{}, f{}{}", rd, rs1, rs2, rounding(*rm))
+            }
+            Instruction::FcvtWH { rd, rs1, rm } => {
+                write!(f, "fcvt.w.h x{}, f{}{}", rd, rs1, rounding(*rm))
+            }
+            Instruction::FcvtWuH { rd, rs1, rm } => {
+                write!(f, "fcvt.wu.h x{}, f{}{}", rd, rs1, rounding(*rm))
+            }
+            Instruction::FcvtHW { rd, rs1, rm } => {
+                write!(f, "fcvt.h.w f{}, x{}{}", rd, rs1, rounding(*rm))
+            }
+            Instruction::FcvtHWu { rd, rs1, rm } => {
+                write!(f, "fcvt.h.wu f{}, x{}{}", rd, rs1, rounding(*rm))
+            }
+            Instruction::FcvtSH { rd, rs1, rm } => {
+                write!(f, "fcvt.s.h f{}, f{}{}", rd, rs1, rounding(*rm))
+            }
+            Instruction::FcvtHS { rd, rs1, rm } => {
+                write!(f, "fcvt.h.s f{}, f{}{}", rd, rs1, rounding(*rm))
+            }
             Instruction::Unsupported(word) => {
                 write!(f, "unsupported: 0x{:08x}", word)
             }
@@ -642,6 +1048,47 @@ impl fmt::Display for Instruction {
     }
 }
 
+/// Suffix for an explicit rounding mode (empty for the dynamic mode)
+fn rounding(rm: u8) -> &'static str {
+    match rm {
+        0 => ", rne",
+        1 => ", rtz",
+        2 => ", rdn",
+        3 => ", rup",
+        4 => ", rmm",
+        ROUNDING_DYNAMIC => "",
+        _ => ", invalid",
+    }
+}
+
+/// Suffix for a masked vector operation
+fn mask(vm: bool) -> &'static str {
+    if vm { "" } else { ", v0.t" }
+}
+
+/// Format a vtype immediate as `e<sew>, m<lmul>, t<a|u>, m<a|u>`
+///
+/// Values with reserved bits set are printed as a raw number.
+fn format_vtype(f: &mut fmt::Formatter<'_>, vtypei: u16) -> fmt::Result {
+    let lmul = match vtypei & 0x7 {
+        0 => "m1",
+        1 => "m2",
+        2 => "m4",
+        3 => "m8",
+        5 => "mf8",
+        6 => "mf4",
+        7 => "mf2",
+        _ => return write!(f, "{}", vtypei),
+    };
+    let sew = (vtypei >> 3) & 0x7;
+    if sew > 3 || vtypei >> 8 != 0 {
+        return write!(f, "{}", vtypei);
+    }
+    let tail = if vtypei & 0x40 != 0 { "ta" } else { "tu" };
+    let mask = if vtypei & 0x80 != 0 { "ma" } else { "mu" };
+    write!(f, "e{}, {}, {}, {}", 8 << sew, lmul, tail, mask)
+}
+
 impl Instruction {
     /// Decode a 32-bit instruction word into an Instruction
     ///
@@ -909,8 +1356,126 @@ impl Instruction {
                         0x001 => Instruction::Ebreak, // EBREAK
                         _ => Instruction::Unsupported(word),
                     }
+                } else if funct3 == 0x2 && rs1 == 0 {
+                    // CSRRS rd, csr, x0 reads a CSR; only the Zicntr counters are supported
+                    match imm as u16 {
+                        csr::CYCLE => Instruction::Rdcycle { rd },
+                        csr::CYCLEH => Instruction::Rdcycleh { rd },
+                        csr::TIME => Instruction::Rdtime { rd },
+                        csr::TIMEH => Instruction::Rdtimeh { rd },
+                        csr::INSTRET => Instruction::Rdinstret { rd },
+                        csr::INSTRETH => Instruction::Rdinstreth { rd },
+                        _ => Instruction::Unsupported(word),
+                    }
+                } else {
+                    Instruction::Unsupported(word)
+                }
+            }
+            0x07 | 0x27 if (word & FUNCT3_MASK) >> FUNCT3_SHIFT == 0x1 => {
+                // Half-precision loads (LOAD-FP) and stores (STORE-FP)
+                let rd = ((word & RD_MASK) >> RD_SHIFT) as u8;
+                let rs1 = ((word & RS1_MASK) >> RS1_SHIFT) as u8;
+                let rs2 = ((word & RS2_MASK) >> RS2_SHIFT) as u8;
+                if opcode == 0x07 {
+                    let imm = ((word & IMM_I_MASK) as i32) >> IMM_I_SHIFT;
+                    Instruction::Flh { rd, rs1, imm }
                 } else {
+                    let imm_11_5 = (word & IMM_S_11_5_MASK) as i32 >> IMM_S_11_5_SHIFT;
+                    let imm_4_0 = ((word & IMM_S_4_0_MASK) >> IMM_S_4_0_SHIFT) as i32;
+                    let imm = (imm_11_5 << 5) | imm_4_0;
+                    Instruction::Fsh { rs1, rs2, imm }
+                }
+            }
+            0x53 => {
+                // Floating-point computational instructions (OP-FP), half precision only
+                let rm = (((word & FUNCT3_MASK) >> FUNCT3_SHIFT) & 0x7) as u8;
+                let funct7 = (word & FUNCT7_MASK) >> FUNCT7_SHIFT;
+                let rd = ((word & RD_MASK) >> RD_SHIFT) as u8;
+                let rs1 = ((word & RS1_MASK) >> RS1_SHIFT) as u8;
+                let rs2 = ((word & RS2_MASK) >> RS2_SHIFT) as u8;
+
+                // Rounding modes 5 and 6 are reserved
+                if rm == 5 || rm == 6 {
+                    return Instruction::Unsupported(word);
+                }
+
+                match (funct7, rs2) {
+                    (0x02, _) => Instruction::FaddH { rd, rs1, rs2, rm }, // FADD.H
+                    (0x06, _) => Instruction::FsubH { rd, rs1, rs2, rm }, // FSUB.H
+                    (0x0A, _) => Instruction::FmulH { rd, rs1, rs2, rm }, // FMUL.H
+                    (0x0E, _) => Instruction::FdivH { rd, rs1, rs2, rm }, // FDIV.H
+                    (0x62, 0) => Instruction::FcvtWH { rd, rs1, rm },     // FCVT.W.H
+                    (0x62, 1) => Instruction::FcvtWuH { rd, rs1, rm },    // FCVT.WU.H
+                    (0x6A, 0) => Instruction::FcvtHW { rd, rs1, rm },     // FCVT.H.W
+                    (0x6A, 1) => Instruction::FcvtHWu { rd, rs1, rm },    // FCVT.H.WU
+                    (0x20, 2) => Instruction::FcvtSH { rd, rs1, rm },     // FCVT.S.H
+                    (0x22, 0) => Instruction::FcvtHS { rd, rs1, rm },     // FCVT.H.S
+                    _ => Instruction::Unsupported(word),
+                }
+            }
+            0x07 | 0x27 => {
+                // Vector unit-stride loads (LOAD-FP) and stores (STORE-FP)
+                // nf, mew, mop and lumop/sumop must be zero for unit-stride accesses
+                let funct3 = (((word & FUNCT3_MASK) >> FUNCT3_SHIFT) & 0x7) as u8;
+                let reg = ((word & RD_MASK) >> RD_SHIFT) as u8;
+                let rs1 = ((word & RS1_MASK) >> RS1_SHIFT) as u8;
+                let vm = word & VM_MASK != 0;
+                let width = match funct3 {
+                    0x0 => 8,
+                    0x5 => 16,
+                    0x6 => 32,
+                    0x7 => 64,
+                    _ => return Instruction::Unsupported(word),
+                };
+
+                if word & (VECTOR_MEMORY_MASK | RS2_MASK) != 0 {
                     Instruction::Unsupported(word)
+                } else if opcode == 0x07 {
+                    Instruction::Vle {
+                        vd: reg,
+                        rs1,
+                        width,
+                        vm,
+                    }
+                } else {
+                    Instruction::Vse {
+                        vs3: reg,
+                        rs1,
+                        width,
+                        vm,
+                    }
+                }
+            }
+            0x57 => {
+                // Vector arithmetic (OP-V) and configuration instructions
+                let funct3 = (((word & FUNCT3_MASK) >> FUNCT3_SHIFT) & 0x7) as u8;
+                let funct6 = (word & FUNCT6_MASK) >> FUNCT6_SHIFT;
+                let vd = ((word & RD_MASK) >> RD_SHIFT) as u8;
+                let rs1 = ((word & RS1_MASK) >> RS1_SHIFT) as u8;
+                let vs2 = ((word & RS2_MASK) >> RS2_SHIFT) as u8;
+                let vm = word & VM_MASK != 0;
+
+                match (funct3, funct6) {
+                    // VSETVLI has bit 31 clear and an 11-bit vtype immediate
+                    (0x7, _) if word & 0x80000000 == 0 => Instruction::Vsetvli {
+                        rd: vd,
+                        rs1,
+                        vtypei: ((word & VTYPEI_MASK) >> IMM_I_SHIFT) as u16,
+                    },
+                    (0x0, 0x00) => Instruction::VaddVv {
+                        vd,
+                        vs2,
+                        vs1: rs1,
+                        vm,
+                    }, // OPIVV
+                    (0x4, 0x00) => Instruction::VaddVx { vd, vs2, rs1, vm }, // OPIVX
+                    (0x3, 0x00) => Instruction::VaddVi {
+                        vd,
+                        vs2,
+                        imm: ((rs1 as i32) << 27) >> 27,
+                        vm,
+                    }, // OPIVI
+                    _ => Instruction::Unsupported(word),
                 }
             }
             _ => Instruction::Unsupported(word),
@@ -995,9 +1560,908 @@ impl Instruction {
             Instruction::Auipc { rd, imm } => encode_u_type(0x17, *rd, *imm),
             Instruction::Ecall => Ok(0x00000073),
             Instruction::Ebreak => Ok(0x00100073),
+            Instruction::Rdcycle { rd } => encode_counter(*rd, csr::CYCLE),
+            Instruction::Rdcycleh { rd } => encode_counter(*rd, csr::CYCLEH),
+            Instruction::Rdtime { rd } => encode_counter(*rd, csr::TIME),
+            Instruction::Rdtimeh { rd } => encode_counter(*rd, csr::TIMEH),
+            Instruction::Rdinstret { rd } => encode_counter(*rd, csr::INSTRET),
+            Instruction::Rdinstreth { rd } => encode_counter(*rd, csr::INSTRETH),
+            Instruction::Vsetvli { rd, rs1, vtypei } => {
+                if *vtypei > 0x7FF {
+                    return Err(EncodeError::InvalidImmediate("vtypei", *vtypei as i32));
+                }
+                encode_i_type(0x57, *rd, 0x7, *rs1, *vtypei as i32)
+            }
+            Instruction::Vle { vd, rs1, width, vm } => {
+                encode_vector_memory(0x07, *vd, *rs1, *width, *vm)
+            }
+            Instruction::Vse {
+                vs3,
+                rs1,
+                width,
+                vm,
+            } => encode_vector_memory(0x27, *vs3, *rs1, *width, *vm),
+            Instruction::VaddVv { vd, vs2, vs1, vm } => {
+                encode_vector_arith(0x0, 0x00, *vd, *vs1, *vs2, *vm)
+            }
+            Instruction::VaddVx { vd, vs2, rs1, vm } => {
+                encode_vector_arith(0x4, 0x00, *vd, *rs1, *vs2, *vm)
+            }
+            Instruction::VaddVi { vd, vs2, imm, vm } => {
+                // The 5-bit signed immediate occupies the vs1 field
+                if !(-16..=15).contains(imm) {
+                    return Err(EncodeError::InvalidImmediate("imm", *imm));
+                }
+                encode_vector_arith(0x3, 0x00, *vd, (*imm & 0x1F) as u8, *vs2, *vm)
+            }
+            Instruction::Flh { rd, rs1, imm } => encode_i_type(0x07, *rd, 0x1, *rs1, *imm),
+            Instruction::Fsh { rs1, rs2, imm } => encode_s_type(0x27, 0x1, *rs1, *rs2, *imm),
+            Instruction::FaddH { rd, rs1, rs2, rm } => encode_fp_type(*rd, *rm, *rs1, *rs2, 0x02),
+            Instruction::FsubH { rd, rs1, rs2, rm } => encode_fp_type(*rd, *rm, *rs1, *rs2, 0x06),
+            Instruction::FmulH { rd, rs1, rs2, rm } => encode_fp_type(*rd, *rm, *rs1, *rs2, 0x0A),
+            Instruction::FdivH { rd, rs1, rs2, rm } => encode_fp_type(*rd, *rm, *rs1, *rs2, 0x0E),
+            Instruction::FcvtWH { rd, rs1, rm } => encode_fp_type(*rd, *rm, *rs1, 0, 0x62),
+            Instruction::FcvtWuH { rd, rs1, rm } => encode_fp_type(*rd, *rm, *rs1, 1, 0x62),
+            Instruction::FcvtHW { rd, rs1, rm } => encode_fp_type(*rd, *rm, *rs1, 0, 0x6A),
+            Instruction::FcvtHWu { rd, rs1, rm } => encode_fp_type(*rd, *rm, *rs1, 1, 0x6A),
+            Instruction::FcvtSH { rd, rs1, rm } => encode_fp_type(*rd, *rm, *rs1, 2, 0x20),
+            Instruction::FcvtHS { rd, rs1, rm } => encode_fp_type(*rd, *rm, *rs1, 0, 0x22),
             Instruction::Unsupported(_) => Err(EncodeError::NotImplemented("Unsupported")),
         }
     }
+
+    /// Encode an instruction into the first 4 bytes of `buffer` (little-endian)
+    ///
+    /// # Errors
+    ///
+    /// Returns `EncodeError::BufferTooSmall` if `buffer` is shorter than 4 bytes, or any
+    /// error returned by `encode()`.
+    pub fn encode_to(&self, buffer: &mut [u8]) -> Result<(), EncodeError> {
+        let word = self.encode()?;
+        let length = buffer.len();
+        let bytes = buffer
+            .get_mut(..4)
+            .ok_or(EncodeError::BufferTooSmall(length))?;
+        bytes.copy_from_slice(&word.to_le_bytes());
+        Ok(())
+    }
+
+    /// Encode a sequence of instructions into little-endian machine code
+    ///
+    /// The result can be passed directly to `Module::set_code`. Encoding stops at
+    /// the first instruction that fails to encode.
+    pub fn encode_all(instructions: &[Instruction]) -> Result<Vec<u8>, EncodeError> {
+        let mut code = vec![0; instructions.len() * 4];
+        for (instruction, bytes) in instructions.iter().zip(code.chunks_exact_mut(4)) {
+            instruction.encode_to(bytes)?;
+        }
+        Ok(code)
+    }
+
+    /// Encode a branch or JAL at code `offset` with a placeholder target
+    ///
+    /// The immediate is encoded as zero and a relocation record targeting
+    /// `symbol` is returned alongside the word, to be applied once the symbol's
+    /// location is known.
+    pub fn encode_relocatable(
+        &self,
+        offset: u32,
+        symbol: &str,
+    ) -> Result<(u32, Relocation), EncodeError> {
+        let (placeholder, kind) = match *self {
+            Instruction::Beq { rs1, rs2, .. } => (
+                Instruction::Beq { rs1, rs2, imm: 0 },
+                RelocationKind::Branch,
+            ),
+            Instruction::Bne { rs1, rs2, .. } => (
+                Instruction::Bne { rs1, rs2, imm: 0 },
+                RelocationKind::Branch,
+            ),
+            Instruction::Blt { rs1, rs2, .. } => (
+                Instruction::Blt { rs1, rs2, imm: 0 },
+                RelocationKind::Branch,
+            ),
+            Instruction::Bge { rs1, rs2, .. } => (
+                Instruction::Bge { rs1, rs2, imm: 0 },
+                RelocationKind::Branch,
+            ),
+            Instruction::Bltu { rs1, rs2, .. } => (
+                Instruction::Bltu { rs1, rs2, imm: 0 },
+                RelocationKind::Branch,
+            ),
+            Instruction::Bgeu { rs1, rs2, .. } => (
+                Instruction::Bgeu { rs1, rs2, imm: 0 },
+                RelocationKind::Branch,
+            ),
+            Instruction::Jal { rd, .. } => (Instruction::Jal { rd, imm: 0 }, RelocationKind::Jump),
+            _ => return Err(EncodeError::NotRelocatable),
+        };
+        Ok((placeholder.encode()?, Relocation::new(offset, kind, symbol)))
+    }
+
+    /// Length in bytes of the encoding starting with `word` (2 for compressed, else 4)
+    ///
+    /// Only the low two bits are examined: compressed 16-bit encodings never
+    /// have both set. Longer encodings are not supported and report 4.
+    pub fn length(word: u32) -> u32 {
+        if word & 0b11 == 0b11 { 4 } else { 2 }
+    }
+
+    /// Check if this is the canonical NOP (`addi x0, x0, 0`)
+    pub fn nop(&self) -> bool {
+        matches!(
+            self,
+            Instruction::Addi {
+                rd: 0,
+                rs1: 0,
+                imm: 0
+            }
+        )
+    }
+
+    /// Check if this is a HINT encoding
+    ///
+    /// The base ISA reserves integer computational instructions writing to x0
+    /// as HINTs: they have no architectural effect and may be skipped entirely.
+    /// The canonical NOP is not considered a HINT. Loads writing x0 are excluded
+    /// because they can still fault, and M extension instructions are not part
+    /// of the HINT space.
+    pub fn hint(&self) -> bool {
+        match self {
+            Instruction::Addi { rd: 0, .. } => !self.nop(),
+            Instruction::Add { rd: 0, .. }
+            | Instruction::Sub { rd: 0, .. }
+            | Instruction::Sll { rd: 0, .. }
+            | Instruction::Xor { rd: 0, .. }
+            | Instruction::Or { rd: 0, .. }
+            | Instruction::Srl { rd: 0, .. }
+            | Instruction::Sra { rd: 0, .. }
+            | Instruction::Slt { rd: 0, .. }
+            | Instruction::Sltu { rd: 0, .. }
+            | Instruction::And { rd: 0, .. }
+            | Instruction::Slti { rd: 0, .. }
+            | Instruction::Sltiu { rd: 0, .. }
+            | Instruction::Xori { rd: 0, .. }
+            | Instruction::Ori { rd: 0, .. }
+            | Instruction::Andi { rd: 0, .. }
+            | Instruction::Slli { rd: 0, .. }
+            | Instruction::Srli { rd: 0, .. }
+            | Instruction::Srai { rd: 0, .. }
+            | Instruction::Lui { rd: 0, .. }
+            | Instruction::Auipc { rd: 0, .. } => true,
+            _ => false,
+        }
+    }
+
+    /// ISA extension this instruction belongs to
+    ///
+    /// `Unsupported` is reported as part of the base ISA since it carries no
+    /// extension of its own.
+    pub fn extension(&self) -> Extension {
+        match self {
+            Instruction::Mul { .. }
+            | Instruction::Mulh { .. }
+            | Instruction::Mulhsu { .. }
+            | Instruction::Mulhu { .. }
+            | Instruction::Div { .. }
+            | Instruction::Divu { .. }
+            | Instruction::Rem { .. }
+            | Instruction::Remu { .. } => Extension::M,
+            Instruction::Vsetvli { .. }
+            | Instruction::Vle { .. }
+            | Instruction::Vse { .. }
+            | Instruction::VaddVv { .. }
+            | Instruction::VaddVx { .. }
+            | Instruction::VaddVi { .. } => Extension::V,
+            Instruction::Flh { .. }
+            | Instruction::Fsh { .. }
+            | Instruction::FaddH { .. }
+            | Instruction::FsubH { .. }
+            | Instruction::FmulH { .. }
+            | Instruction::FdivH { .. }
+            | Instruction::FcvtWH { .. }
+            | Instruction::FcvtWuH { .. }
+            | Instruction::FcvtHW { .. }
+            | Instruction::FcvtHWu { .. }
+            | Instruction::FcvtSH { .. }
+            | Instruction::FcvtHS { .. } => Extension::Zfh,
+            _ => Extension::I,
+        }
+    }
+
+    /// Classify the instruction
+    pub fn kind(&self) -> InstructionKind {
+        match self {
+            Instruction::Lb { .. }
+            | Instruction::Lh { .. }
+            | Instruction::Lw { .. }
+            | Instruction::Lbu { .. }
+            | Instruction::Lhu { .. }
+            | Instruction::Vle { .. }
+            | Instruction::Flh { .. } => InstructionKind::Load,
+            Instruction::Sb { .. }
+            | Instruction::Sh { .. }
+            | Instruction::Sw { .. }
+            | Instruction::Vse { .. }
+            | Instruction::Fsh { .. } => InstructionKind::Store,
+            Instruction::Beq { .. }
+            | Instruction::Bne { .. }
+            | Instruction::Blt { .. }
+            | Instruction::Bge { .. }
+            | Instruction::Bltu { .. }
+            | Instruction::Bgeu { .. } => InstructionKind::Branch,
+            Instruction::Jal { .. } | Instruction::Jalr { .. } => InstructionKind::Jump,
+            Instruction::Ecall
+            | Instruction::Ebreak
+            | Instruction::Rdcycle { .. }
+            | Instruction::Rdcycleh { .. }
+            | Instruction::Rdtime { .. }
+            | Instruction::Rdtimeh { .. }
+            | Instruction::Rdinstret { .. }
+            | Instruction::Rdinstreth { .. } => InstructionKind::System,
+            Instruction::Mul { .. }
+            | Instruction::Mulh { .. }
+            | Instruction::Mulhsu { .. }
+            | Instruction::Mulhu { .. } => InstructionKind::Mul,
+            Instruction::Div { .. }
+            | Instruction::Divu { .. }
+            | Instruction::Rem { .. }
+            | Instruction::Remu { .. } => InstructionKind::Div,
+            Instruction::Vsetvli { .. }
+            | Instruction::VaddVv { .. }
+            | Instruction::VaddVx { .. }
+            | Instruction::VaddVi { .. } => InstructionKind::Vector,
+            Instruction::FaddH { .. }
+            | Instruction::FsubH { .. }
+            | Instruction::FmulH { .. }
+            | Instruction::FdivH { .. }
+            | Instruction::FcvtWH { .. }
+            | Instruction::FcvtWuH { .. }
+            | Instruction::FcvtHW { .. }
+            | Instruction::FcvtHWu { .. }
+            | Instruction::FcvtSH { .. }
+            | Instruction::FcvtHS { .. } => InstructionKind::Float,
+            Instruction::Unsupported(_) => InstructionKind::Unsupported,
+            _ => InstructionKind::Arithmetic,
+        }
+    }
+
+    /// Assembly mnemonic, as printed by `Display` (e.g. `"addi"`, `"vle32.v"`)
+    pub fn mnemonic(&self) -> &'static str {
+        match self {
+            Instruction::Add { .. } => "add",
+            Instruction::Sub { .. } => "sub",
+            Instruction::Sll { .. } => "sll",
+            Instruction::Xor { .. } => "xor",
+            Instruction::Or { .. } => "or",
+            Instruction::Srl { .. } => "srl",
+            Instruction::Sra { .. } => "sra",
+            Instruction::Slt { .. } => "slt",
+            Instruction::Sltu { .. } => "sltu",
+            Instruction::And { .. } => "and",
+            Instruction::Mul { .. } => "mul",
+            Instruction::Mulh { .. } => "mulh",
+            Instruction::Mulhsu { .. } => "mulhsu",
+            Instruction::Mulhu { .. } => "mulhu",
+            Instruction::Div { .. } => "div",
+            Instruction::Divu { .. } => "divu",
+            Instruction::Rem { .. } => "rem",
+            Instruction::Remu { .. } => "remu",
+            Instruction::Addi { .. } => "addi",
+            Instruction::Slti { .. } => "slti",
+            Instruction::Sltiu { .. } => "sltiu",
+            Instruction::Xori { .. } => "xori",
+            Instruction::Ori { .. } => "ori",
+            Instruction::Andi { .. } => "andi",
+            Instruction::Slli { .. } => "slli",
+            Instruction::Srli { .. } => "srli",
+            Instruction::Srai { .. } => "srai",
+            Instruction::Lb { .. } => "lb",
+            Instruction::Lh { .. } => "lh",
+            Instruction::Lw { .. } => "lw",
+            Instruction::Lbu { .. } => "lbu",
+            Instruction::Lhu { .. } => "lhu",
+            Instruction::Sb { .. } => "sb",
+            Instruction::Sh { .. } => "sh",
+            Instruction::Sw { .. } => "sw",
+            Instruction::Beq { .. } => "beq",
+            Instruction::Bne { .. } => "bne",
+            Instruction::Blt { .. } => "blt",
+            Instruction::Bge { .. } => "bge",
+            Instruction::Bltu { .. } => "bltu",
+            Instruction::Bgeu { .. } => "bgeu",
+            Instruction::Jal { .. } => "jal",
+            Instruction::Jalr { .. } => "jalr",
+            Instruction::Lui { .. } => "lui",
+            Instruction::Auipc { .. } => "auipc",
+            Instruction::Ecall => "ecall",
+            Instruction::Ebreak => "ebreak",
+            Instruction::Rdcycle { .. } => "rdcycle",
+            Instruction::Rdcycleh { .. } => "rdcycleh",
+            Instruction::Rdtime { .. } => "rdtime",
+            Instruction::Rdtimeh { .. } => "rdtimeh",
+            Instruction::Rdinstret { .. } => "rdinstret",
+            Instruction::Rdinstreth { .. } => "rdinstreth",
+            Instruction::Vsetvli { .. } => "vsetvli",
+            Instruction::Vle { width, .. } => match width {
+                8 => "vle8.v",
+                16 => "vle16.v",
+                32 => "vle32.v",
+                _ => "vle64.v",
+            },
+            Instruction::Vse { width, .. } => match width {
+                8 => "vse8.v",
+                16 => "vse16.v",
+                32 => "vse32.v",
+                _ => "vse64.v",
+            },
+            Instruction::VaddVv { .. } => "vadd.vv",
+            Instruction::VaddVx { .. } => "vadd.vx",
+            Instruction::VaddVi { .. } => "vadd.vi",
+            Instruction::Flh { .. } => "flh",
+            Instruction::Fsh { .. } => "fsh",
+            Instruction::FaddH { .. } => "fadd.h",
+            Instruction::FsubH { .. } => "fsub.h",
+            Instruction::FmulH { .. } => "fmul.h",
+            Instruction::FdivH { .. } => "fdiv.h",
+            Instruction::FcvtWH { .. } => "fcvt.w.h",
+            Instruction::FcvtWuH { .. } => "fcvt.wu.h",
+            Instruction::FcvtHW { .. } => "fcvt.h.w",
+            Instruction::FcvtHWu { .. } => "fcvt.h.wu",
+            Instruction::FcvtSH { .. } => "fcvt.s.h",
+            Instruction::FcvtHS { .. } => "fcvt.h.s",
+            Instruction::Unsupported(_) => "unsupported",
+        }
+    }
+
+    /// Destination register, if the instruction writes one
+    pub fn rd(&self) -> Option<Register> {
+        match self {
+            Instruction::Add { rd, .. }
+            | Instruction::Sub { rd, .. }
+            | Instruction::Sll { rd, .. }
+            | Instruction::Xor { rd, .. }
+            | Instruction::Or { rd, .. }
+            | Instruction::Srl { rd, .. }
+            | Instruction::Sra { rd, .. }
+            | Instruction::Slt { rd, .. }
+            | Instruction::Sltu { rd, .. }
+            | Instruction::And { rd, .. }
+            | Instruction::Mul { rd, .. }
+            | Instruction::Mulh { rd, .. }
+            | Instruction::Mulhsu { rd, .. }
+            | Instruction::Mulhu { rd, .. }
+            | Instruction::Div { rd, .. }
+            | Instruction::Divu { rd, .. }
+            | Instruction::Rem { rd, .. }
+            | Instruction::Remu { rd, .. }
+            | Instruction::Addi { rd, .. }
+            | Instruction::Slti { rd, .. }
+            | Instruction::Sltiu { rd, .. }
+            | Instruction::Xori { rd, .. }
+            | Instruction::Ori { rd, .. }
+            | Instruction::Andi { rd, .. }
+            | Instruction::Slli { rd, .. }
+            | Instruction::Srli { rd, .. }
+            | Instruction::Srai { rd, .. }
+            | Instruction::Lb { rd, .. }
+            | Instruction::Lh { rd, .. }
+            | Instruction::Lw { rd, .. }
+            | Instruction::Lbu { rd, .. }
+            | Instruction::Lhu { rd, .. }
+            | Instruction::Jal { rd, .. }
+            | Instruction::Jalr { rd, .. }
+            | Instruction::Lui { rd, .. }
+            | Instruction::Auipc { rd, .. }
+            | Instruction::Rdcycle { rd }
+            | Instruction::Rdcycleh { rd }
+            | Instruction::Rdtime { rd }
+            | Instruction::Rdtimeh { rd }
+            | Instruction::Rdinstret { rd }
+            | Instruction::Rdinstreth { rd }
+            | Instruction::Vsetvli { rd, .. }
+            | Instruction::FcvtWH { rd, .. }
+            | Instruction::FcvtWuH { rd, .. } => Some(Register::X(*rd)),
+            Instruction::Vle { vd, .. }
+            | Instruction::VaddVv { vd, .. }
+            | Instruction::VaddVx { vd, .. }
+            | Instruction::VaddVi { vd, .. } => Some(Register::V(*vd)),
+            Instruction::Flh { rd, .. }
+            | Instruction::FaddH { rd, .. }
+            | Instruction::FsubH { rd, .. }
+            | Instruction::FmulH { rd, .. }
+            | Instruction::FdivH { rd, .. }
+            | Instruction::FcvtHW { rd, .. }
+            | Instruction::FcvtHWu { rd, .. }
+            | Instruction::FcvtSH { rd, .. }
+            | Instruction::FcvtHS { rd, .. } => Some(Register::F(*rd)),
+            _ => None,
+        }
+    }
+
+    /// First source register (the `rs1` field), if the instruction reads one
+    ///
+    /// For VADD.VV this is `vs1`.
+    pub fn rs1(&self) -> Option<Register> {
+        match self {
+            Instruction::Add { rs1, .. }
+            | Instruction::Sub { rs1, .. }
+            | Instruction::Sll { rs1, .. }
+            | Instruction::Xor { rs1, .. }
+            | Instruction::Or { rs1, .. }
+            | Instruction::Srl { rs1, .. }
+            | Instruction::Sra { rs1, .. }
+            | Instruction::Slt { rs1, .. }
+            | Instruction::Sltu { rs1, .. }
+            | Instruction::And { rs1, .. }
+            | Instruction::Mul { rs1, .. }
+            | Instruction::Mulh { rs1, .. }
+            | Instruction::Mulhsu { rs1, .. }
+            | Instruction::Mulhu { rs1, .. }
+            | Instruction::Div { rs1, .. }
+            | Instruction::Divu { rs1, .. }
+            | Instruction::Rem { rs1, .. }
+            | Instruction::Remu { rs1, .. }
+            | Instruction::Addi { rs1, .. }
+            | Instruction::Slti { rs1, .. }
+            | Instruction::Sltiu { rs1, .. }
+            | Instruction::Xori { rs1, .. }
+            | Instruction::Ori { rs1, .. }
+            | Instruction::Andi { rs1, .. }
+            | Instruction::Slli { rs1, .. }
+            | Instruction::Srli { rs1, .. }
+            | Instruction::Srai { rs1, .. }
+            | Instruction::Lb { rs1, .. }
+            | Instruction::Lh { rs1, .. }
+            | Instruction::Lw { rs1, .. }
+            | Instruction::Lbu { rs1, .. }
+            | Instruction::Lhu { rs1, .. }
+            | Instruction::Sb { rs1, .. }
+            | Instruction::Sh { rs1, .. }
+            | Instruction::Sw { rs1, .. }
+            | Instruction::Beq { rs1, .. }
+            | Instruction::Bne { rs1, .. }
+            | Instruction::Blt { rs1, .. }
+            | Instruction::Bge { rs1, .. }
+            | Instruction::Bltu { rs1, .. }
+            | Instruction::Bgeu { rs1, .. }
+            | Instruction::Jalr { rs1, .. }
+            | Instruction::Vsetvli { rs1, .. }
+            | Instruction::Vle { rs1, .. }
+            | Instruction::Vse { rs1, .. }
+            | Instruction::VaddVx { rs1, .. }
+            | Instruction::Flh { rs1, .. }
+            | Instruction::Fsh { rs1, .. }
+            | Instruction::FcvtHW { rs1, .. }
+            | Instruction::FcvtHWu { rs1, .. } => Some(Register::X(*rs1)),
+            Instruction::VaddVv { vs1, .. } => Some(Register::V(*vs1)),
+            Instruction::FaddH { rs1, .. }
+            | Instruction::FsubH { rs1, .. }
+            | Instruction::FmulH { rs1, .. }
+            | Instruction::FdivH { rs1, .. }
+            | Instruction::FcvtWH { rs1, .. }
+            | Instruction::FcvtWuH { rs1, .. }
+            | Instruction::FcvtSH { rs1, .. }
+            | Instruction::FcvtHS { rs1, .. } => Some(Register::F(*rs1)),
+            _ => None,
+        }
+    }
+
+    /// Second source register (the `rs2` field), if the instruction reads one
+    ///
+    /// For vector arithmetic this is `vs2`. The store data register `vs3` of
+    /// VSE occupies the `rd` field and is not reported.
+    pub fn rs2(&self) -> Option<Register> {
+        match self {
+            Instruction::Add { rs2, .. }
+            | Instruction::Sub { rs2, .. }
+            | Instruction::Sll { rs2, .. }
+            | Instruction::Xor { rs2, .. }
+            | Instruction::Or { rs2, .. }
+            | Instruction::Srl { rs2, .. }
+            | Instruction::Sra { rs2, .. }
+            | Instruction::Slt { rs2, .. }
+            | Instruction::Sltu { rs2, .. }
+            | Instruction::And { rs2, .. }
+            | Instruction::Mul { rs2, .. }
+            | Instruction::Mulh { rs2, .. }
+            | Instruction::Mulhsu { rs2, .. }
+            | Instruction::Mulhu { rs2, .. }
+            | Instruction::Div { rs2, .. }
+            | Instruction::Divu { rs2, .. }
+            | Instruction::Rem { rs2, .. }
+            | Instruction::Remu { rs2, .. }
+            | Instruction::Sb { rs2, .. }
+            | Instruction::Sh { rs2, .. }
+            | Instruction::Sw { rs2, .. }
+            | Instruction::Beq { rs2, .. }
+            | Instruction::Bne { rs2, .. }
+            | Instruction::Blt { rs2, .. }
+            | Instruction::Bge { rs2, .. }
+            | Instruction::Bltu { rs2, .. }
+            | Instruction::Bgeu { rs2, .. } => Some(Register::X(*rs2)),
+            Instruction::VaddVv { vs2, .. }
+            | Instruction::VaddVx { vs2, .. }
+            | Instruction::VaddVi { vs2, .. } => Some(Register::V(*vs2)),
+            Instruction::Fsh { rs2, .. }
+            | Instruction::FaddH { rs2, .. }
+            | Instruction::FsubH { rs2, .. }
+            | Instruction::FmulH { rs2, .. }
+            | Instruction::FdivH { rs2, .. } => Some(Register::F(*rs2)),
+            _ => None,
+        }
+    }
+
+    /// Immediate operand, if the instruction has one
+    ///
+    /// Values are returned as stored in the variant: shift amounts for the
+    /// immediate shifts, the 20-bit upper immediate for LUI and AUIPC, and
+    /// `vtypei` for VSETVLI.
+    pub fn imm(&self) -> Option<i32> {
+        match self {
+            Instruction::Addi { imm, .. }
+            | Instruction::Slti { imm, .. }
+            | Instruction::Sltiu { imm, .. }
+            | Instruction::Xori { imm, .. }
+            | Instruction::Ori { imm, .. }
+            | Instruction::Andi { imm, .. }
+            | Instruction::Lb { imm, .. }
+            | Instruction::Lh { imm, .. }
+            | Instruction::Lw { imm, .. }
+            | Instruction::Lbu { imm, .. }
+            | Instruction::Lhu { imm, .. }
+            | Instruction::Sb { imm, .. }
+            | Instruction::Sh { imm, .. }
+            | Instruction::Sw { imm, .. }
+            | Instruction::Beq { imm, .. }
+            | Instruction::Bne { imm, .. }
+            | Instruction::Blt { imm, .. }
+            | Instruction::Bge { imm, .. }
+            | Instruction::Bltu { imm, .. }
+            | Instruction::Bgeu { imm, .. }
+            | Instruction::Jal { imm, .. }
+            | Instruction::Jalr { imm, .. }
+            | Instruction::VaddVi { imm, .. }
+            | Instruction::Flh { imm, .. }
+            | Instruction::Fsh { imm, .. } => Some(*imm),
+            Instruction::Slli { shamt, .. }
+            | Instruction::Srli { shamt, .. }
+            | Instruction::Srai { shamt, .. } => Some(*shamt as i32),
+            Instruction::Lui { imm, .. } | Instruction::Auipc { imm, .. } => Some(*imm as i32),
+            Instruction::Vsetvli { vtypei, .. } => Some(*vtypei as i32),
+            _ => None,
+        }
+    }
+
+    /// Absolute target address of a branch or JAL located at `pc`
+    ///
+    /// Returns `None` for instructions without a statically known target,
+    /// including JALR whose target depends on a register value.
+    pub fn target(&self, pc: u32) -> Option<u32> {
+        match self {
+            Instruction::Beq { imm, .. }
+            | Instruction::Bne { imm, .. }
+            | Instruction::Blt { imm, .. }
+            | Instruction::Bge { imm, .. }
+            | Instruction::Bltu { imm, .. }
+            | Instruction::Bgeu { imm, .. }
+            | Instruction::Jal { imm, .. } => Some(pc.wrapping_add(*imm as u32)),
+            _ => None,
+        }
+    }
+}
+
+impl Relocation {
+    /// Create a relocation for the instruction at `offset`
+    pub fn new(offset: u32, kind: RelocationKind, symbol: &str) -> Self {
+        Relocation {
+            offset,
+            kind,
+            symbol: symbol.to_string(),
+        }
+    }
+
+    /// Patch the instruction at `offset` in `code` to target the code offset `target`
+    ///
+    /// Only the immediate bits are rewritten; the opcode and register fields of
+    /// the placeholder are preserved.
+    ///
+    /// # Errors
+    ///
+    /// Returns `EncodeError::BufferTooSmall` if the instruction lies outside `code`, or
+    /// `EncodeError::InvalidImmediate` if the target is out of range or misaligned.
+    pub fn apply(&self, code: &mut [u8], target: u32) -> Result<(), EncodeError> {
+        let length = code.len();
+        let bytes = (self.offset as usize)
+            .checked_add(4)
+            .and_then(|end| code.get_mut(self.offset as usize..end))
+            .ok_or(EncodeError::BufferTooSmall(length))?;
+
+        let delta = target.wrapping_sub(self.offset) as i32;
+        let (mask, field) = match self.kind {
+            RelocationKind::Branch => (
+                IMM_B_12_MASK | IMM_B_11_MASK | IMM_B_10_5_MASK | IMM_B_4_1_MASK,
+                encode_b_type(0, 0, 0, 0, delta)?,
+            ),
+            RelocationKind::Jump => (
+                IMM_J_20_MASK | IMM_J_19_12_MASK | IMM_J_11_MASK | IMM_J_10_1_MASK,
+                encode_j_type(0, 0, delta)?,
+            ),
+        };
+        let word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+        bytes.copy_from_slice(&((word & !mask) | field).to_le_bytes());
+        Ok(())
+    }
+}
+
+/// Validated constructors
+///
+/// Each constructor range-checks its operands with the same rules as `encode()`,
+/// so code generators catch invalid registers and immediates when an instruction
+/// is built rather than when it is later encoded or compiled.
+impl Instruction {
+    /// Create a validated ADD instruction
+    pub fn add(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Add { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated SUB instruction
+    pub fn sub(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Sub { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated SLL instruction
+    pub fn sll(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Sll { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated XOR instruction
+    pub fn xor(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Xor { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated OR instruction
+    pub fn or(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Or { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated SRL instruction
+    pub fn srl(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Srl { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated SRA instruction
+    pub fn sra(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Sra { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated SLT instruction
+    pub fn slt(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Slt { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated SLTU instruction
+    pub fn sltu(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Sltu { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated AND instruction
+    pub fn and(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::And { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated MUL instruction
+    pub fn mul(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Mul { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated MULH instruction
+    pub fn mulh(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Mulh { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated MULHSU instruction
+    pub fn mulhsu(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Mulhsu { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated MULHU instruction
+    pub fn mulhu(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Mulhu { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated DIV instruction
+    pub fn div(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Div { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated DIVU instruction
+    pub fn divu(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Divu { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated REM instruction
+    pub fn rem(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Rem { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated REMU instruction
+    pub fn remu(rd: u8, rs1: u8, rs2: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Remu { rd, rs1, rs2 }.validated()
+    }
+
+    /// Create a validated ADDI instruction
+    pub fn addi(rd: u8, rs1: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Addi { rd, rs1, imm }.validated()
+    }
+
+    /// Create a validated SLTI instruction
+    pub fn slti(rd: u8, rs1: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Slti { rd, rs1, imm }.validated()
+    }
+
+    /// Create a validated SLTIU instruction
+    pub fn sltiu(rd: u8, rs1: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Sltiu { rd, rs1, imm }.validated()
+    }
+
+    /// Create a validated XORI instruction
+    pub fn xori(rd: u8, rs1: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Xori { rd, rs1, imm }.validated()
+    }
+
+    /// Create a validated ORI instruction
+    pub fn ori(rd: u8, rs1: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Ori { rd, rs1, imm }.validated()
+    }
+
+    /// Create a validated ANDI instruction
+    pub fn andi(rd: u8, rs1: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Andi { rd, rs1, imm }.validated()
+    }
+
+    /// Create a validated LB instruction
+    pub fn lb(rd: u8, rs1: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Lb { rd, rs1, imm }.validated()
+    }
+
+    /// Create a validated LH instruction
+    pub fn lh(rd: u8, rs1: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Lh { rd, rs1, imm }.validated()
+    }
+
+    /// Create a validated LW instruction
+    pub fn lw(rd: u8, rs1: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Lw { rd, rs1, imm }.validated()
+    }
+
+    /// Create a validated LBU instruction
+    pub fn lbu(rd: u8, rs1: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Lbu { rd, rs1, imm }.validated()
+    }
+
+    /// Create a validated LHU instruction
+    pub fn lhu(rd: u8, rs1: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Lhu { rd, rs1, imm }.validated()
+    }
+
+    /// Create a validated JALR instruction
+    pub fn jalr(rd: u8, rs1: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Jalr { rd, rs1, imm }.validated()
+    }
+
+    /// Create a validated SLLI instruction
+    pub fn slli(rd: u8, rs1: u8, shamt: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Slli { rd, rs1, shamt }.validated()
+    }
+
+    /// Create a validated SRLI instruction
+    pub fn srli(rd: u8, rs1: u8, shamt: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Srli { rd, rs1, shamt }.validated()
+    }
+
+    /// Create a validated SRAI instruction
+    pub fn srai(rd: u8, rs1: u8, shamt: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Srai { rd, rs1, shamt }.validated()
+    }
+
+    /// Create a validated SB instruction
+    pub fn sb(rs1: u8, rs2: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Sb { rs1, rs2, imm }.validated()
+    }
+
+    /// Create a validated SH instruction
+    pub fn sh(rs1: u8, rs2: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Sh { rs1, rs2, imm }.validated()
+    }
+
+    /// Create a validated SW instruction
+    pub fn sw(rs1: u8, rs2: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Sw { rs1, rs2, imm }.validated()
+    }
+
+    /// Create a validated BEQ instruction
+    pub fn beq(rs1: u8, rs2: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Beq { rs1, rs2, imm }.validated()
+    }
+
+    /// Create a validated BNE instruction
+    pub fn bne(rs1: u8, rs2: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Bne { rs1, rs2, imm }.validated()
+    }
+
+    /// Create a validated BLT instruction
+    pub fn blt(rs1: u8, rs2: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Blt { rs1, rs2, imm }.validated()
+    }
+
+    /// Create a validated BGE instruction
+    pub fn bge(rs1: u8, rs2: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Bge { rs1, rs2, imm }.validated()
+    }
+
+    /// Create a validated BLTU instruction
+    pub fn bltu(rs1: u8, rs2: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Bltu { rs1, rs2, imm }.validated()
+    }
+
+    /// Create a validated BGEU instruction
+    pub fn bgeu(rs1: u8, rs2: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Bgeu { rs1, rs2, imm }.validated()
+    }
+
+    /// Create a validated JAL instruction
+    pub fn jal(rd: u8, imm: i32) -> Result<Instruction, EncodeError> {
+        Instruction::Jal { rd, imm }.validated()
+    }
+
+    /// Create a validated LUI instruction
+    pub fn lui(rd: u8, imm: u32) -> Result<Instruction, EncodeError> {
+        Instruction::Lui { rd, imm }.validated()
+    }
+
+    /// Create a validated AUIPC instruction
+    pub fn auipc(rd: u8, imm: u32) -> Result<Instruction, EncodeError> {
+        Instruction::Auipc { rd, imm }.validated()
+    }
+
+    /// Create an ECALL instruction
+    pub fn ecall() -> Instruction {
+        Instruction::Ecall
+    }
+
+    /// Create an EBREAK instruction
+    pub fn ebreak() -> Instruction {
+        Instruction::Ebreak
+    }
+
+    /// Create a validated RDCYCLE instruction
+    pub fn rdcycle(rd: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Rdcycle { rd }.validated()
+    }
+
+    /// Create a validated RDTIME instruction
+    pub fn rdtime(rd: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Rdtime { rd }.validated()
+    }
+
+    /// Create a validated RDINSTRET instruction
+    pub fn rdinstret(rd: u8) -> Result<Instruction, EncodeError> {
+        Instruction::Rdinstret { rd }.validated()
+    }
+
+    /// Return the instruction if all of its operands are encodable
+    fn validated(self) -> Result<Instruction, EncodeError> {
+        self.encode().map(|_| self)
+    }
+}
+
+/// Encode a counter read (`csrrs rd, csr, x0`)
+fn encode_counter(rd: u8, csr: u16) -> Result<u32, EncodeError> {
+    if rd > 31 {
+        return Err(EncodeError::InvalidRegister("rd", rd));
+    }
+    Ok(0x73 | ((rd as u32) << RD_SHIFT) | (0x2 << FUNCT3_SHIFT) | ((csr as u32) << IMM_I_SHIFT))
 }
 
 /// Encode an R-type instruction
@@ -1137,6 +2601,76 @@ fn encode_j_type(opcode: u32, rd: u8, imm: i32) -> Result<u32, EncodeError> {
         | (bit_20 << IMM_J_20_SHIFT))
 }
 
+/// Encode a floating-point computational instruction (OP-FP) with a rounding mode
+fn encode_fp_type(rd: u8, rm: u8, rs1: u8, rs2: u8, funct7: u32) -> Result<u32, EncodeError> {
+    // Rounding modes 5 and 6 are reserved
+    if rm > 7 || rm == 5 || rm == 6 {
+        return Err(EncodeError::InvalidImmediate("rm", rm as i32));
+    }
+    encode_r_type(0x53, rd, rm as u32, rs1, rs2, funct7)
+}
+
+/// Encode a vector unit-stride load or store
+fn encode_vector_memory(
+    opcode: u32,
+    reg: u8,
+    rs1: u8,
+    width: u8,
+    vm: bool,
+) -> Result<u32, EncodeError> {
+    let field = if opcode == 0x07 { "vd" } else { "vs3" };
+    if reg > 31 {
+        return Err(EncodeError::InvalidRegister(field, reg));
+    }
+    if rs1 > 31 {
+        return Err(EncodeError::InvalidRegister("rs1", rs1));
+    }
+    let funct3 = match width {
+        8 => 0x0,
+        16 => 0x5,
+        32 => 0x6,
+        64 => 0x7,
+        _ => return Err(EncodeError::InvalidImmediate("width", width as i32)),
+    };
+
+    Ok(opcode
+        | ((reg as u32) << RD_SHIFT)
+        | (funct3 << FUNCT3_SHIFT)
+        | ((rs1 as u32) << RS1_SHIFT)
+        | ((vm as u32) << VM_SHIFT))
+}
+
+/// Encode a vector arithmetic instruction (OP-V)
+///
+/// `src1` is the vs1 register, rs1 register or 5-bit immediate depending on `funct3`.
+fn encode_vector_arith(
+    funct3: u32,
+    funct6: u32,
+    vd: u8,
+    src1: u8,
+    vs2: u8,
+    vm: bool,
+) -> Result<u32, EncodeError> {
+    if vd > 31 {
+        return Err(EncodeError::InvalidRegister("vd", vd));
+    }
+    if src1 > 31 {
+        let field = if funct3 == 0x4 { "rs1" } else { "vs1" };
+        return Err(EncodeError::InvalidRegister(field, src1));
+    }
+    if vs2 > 31 {
+        return Err(EncodeError::InvalidRegister("vs2", vs2));
+    }
+
+    Ok(0x57
+        | ((vd as u32) << RD_SHIFT)
+        | (funct3 << FUNCT3_SHIFT)
+        | ((src1 as u32) << RS1_SHIFT)
+        | ((vs2 as u32) << RS2_SHIFT)
+        | ((vm as u32) << VM_SHIFT)
+        | (funct6 << FUNCT6_SHIFT))
+}
+
 fn encode_u_type(opcode: u32, rd: u8, imm: u32) -> Result<u32, EncodeError> {
     if rd > 31 {
         return Err(EncodeError::InvalidRegister("rd", rd));
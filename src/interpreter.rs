@@ -0,0 +1,598 @@
+//! Reference interpreter for RISC-V instructions
+//!
+//! Executes RV32IM instructions one at a time against an `Instance`'s register
+//! file, program counter and memory. Instructions are fetched from guest memory
+//! at the current PC. The interpreter is used for single-stepping in the REPL
+//! and as a reference for validating compiled code.
+//!
+//! # Counters
+//! The Zicntr counters read by RDCYCLE, RDTIME and RDINSTRET all report the
+//! number of instructions retired by the interpreter before the read.
+//!
+//! # Permissions
+//! Loads, stores and instruction fetches are checked against the page
+//! permissions set with `Memory::protect`, trapping with `Trap::Protection`.
+//! Accesses to guard regions reserved with `Memory::guard` trap with `Trap::Guard`.
+//!
+//! # Alignment
+//! Misaligned halfword and word loads and stores are serviced by default. With
+//! `set_check_alignment(true)` they trap with `Trap::MisalignedAccess` before
+//! any other check, as on hardware without misaligned access support.
+//!
+//! # Watchpoints
+//! `watch` registers a guest address range that either stops execution with
+//! `Trap::Watchpoint` or calls a host handler when a matching access touches
+//! it. Watchpoints are checked after permissions and before the access takes
+//! effect, so a trapping access leaves registers and memory unchanged. Host
+//! accesses through `Memory` are not watched.
+//!
+//! # Devices
+//! Loads and stores lying entirely inside a range mapped with
+//! `Memory::map_mmio` are passed to the device instead of RAM. Device accesses
+//! skip page permissions but are still watched; an access straddling the edge
+//! of a device range traps with `Trap::Fault`. A device load stopped by a
+//! watchpoint never reaches the device, so handlers notified before the trap
+//! see a zero value.
+//!
+//! # Traps
+//! When an instruction cannot complete normally, `step` returns a `Trap` and
+//! leaves the PC pointing at the trapping instruction, so the caller can inspect
+//! the state and decide how to resume (e.g. advance past an ECALL after
+//! servicing it).
+
+use crate::{
+    Decoder, Instance, Instruction,
+    memory::{Access, MemoryError},
+};
+use std::fmt;
+
+/// Reason execution stopped before an instruction completed
+#[derive(Debug, Clone, PartialEq)]
+pub enum Trap {
+    /// ECALL instruction reached
+    Ecall,
+    /// EBREAK instruction reached
+    Ebreak,
+    /// Instruction word could not be decoded
+    Illegal(u32),
+    /// Memory access at the given guest address failed
+    Fault(u32),
+    /// Instruction fetch from a misaligned PC (4-byte, or 2-byte with C enabled)
+    Misaligned(u32),
+    /// Access at the given guest address violated the page permissions
+    Protection(u32, Access),
+    /// Access at the given guest address hit a guard region
+    Guard(u32),
+    /// Access at the given guest address touched a trapping watchpoint
+    Watchpoint(u32, Access),
+    /// Halfword or word access at a misaligned guest address, with alignment
+    /// checking enabled
+    MisalignedAccess(u32, Access),
+}
+
+impl fmt::Display for Trap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Trap::Ecall => write!(f, "ecall"),
+            Trap::Ebreak => write!(f, "ebreak"),
+            Trap::Illegal(word) => write!(f, "illegal instruction 0x{:08x}", word),
+            Trap::Fault(address) => write!(f, "memory fault at 0x{:08x}", address),
+            Trap::Misaligned(pc) => write!(f, "misaligned fetch at 0x{:08x}", pc),
+            Trap::Protection(address, access) => {
+                write!(f, "{} protection fault at 0x{:08x}", access, address)
+            }
+            Trap::Guard(address) => write!(f, "guard page fault at 0x{:08x}", address),
+            Trap::Watchpoint(address, access) => {
+                write!(f, "{} watchpoint at 0x{:08x}", access, address)
+            }
+            Trap::MisalignedAccess(address, access) => {
+                write!(f, "misaligned {} at 0x{:08x}", access, address)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Trap {}
+
+/// Host callback invoked when a watchpoint is hit
+pub type WatchHandler = Box<dyn FnMut(&WatchHit)>;
+
+/// What happens when a guest access touches a watchpoint
+pub enum WatchAction {
+    /// Stop with `Trap::Watchpoint` before the access takes effect
+    Trap,
+    /// Call the handler, then let the access proceed
+    Notify(WatchHandler),
+}
+
+/// A guest access that touched a watchpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    /// Index returned by `Interpreter::watch`
+    pub index: usize,
+    /// PC of the accessing instruction
+    pub pc: u32,
+    /// Guest address of the access
+    pub address: u32,
+    /// Kind of access
+    pub access: Access,
+    /// Value loaded, stored or fetched, zero-extended
+    pub value: u32,
+}
+
+/// A watched guest address range
+struct Watchpoint {
+    /// First watched address
+    address: u32,
+    /// Number of watched bytes
+    len: u32,
+    /// Kind of access that triggers the watchpoint
+    access: Access,
+    /// Action taken on a hit
+    action: WatchAction,
+}
+
+impl Watchpoint {
+    /// Check if an access of `len` bytes at `address` overlaps the watched range
+    fn overlaps(&self, address: u32, len: u32) -> bool {
+        self.len > 0
+            && len > 0
+            && (address.wrapping_sub(self.address) < self.len
+                || self.address.wrapping_sub(address) < len)
+    }
+}
+
+/// Interprets guest instructions on an `Instance`
+pub struct Interpreter {
+    /// Decoder restricting the accepted instruction set
+    decoder: Decoder,
+    /// Number of instructions retired since creation
+    retired: u64,
+    /// Watchpoints by index; removed entries are `None`
+    watchpoints: Vec<Option<Watchpoint>>,
+    /// Whether misaligned loads and stores trap
+    check_alignment: bool,
+}
+
+impl Interpreter {
+    /// Create a new interpreter accepting RV32IM
+    pub fn new() -> Self {
+        Self::with_decoder(Decoder::default())
+    }
+
+    /// Create an interpreter that decodes instructions with the given decoder
+    pub fn with_decoder(decoder: Decoder) -> Self {
+        Self {
+            decoder,
+            retired: 0,
+            watchpoints: Vec::new(),
+            check_alignment: false,
+        }
+    }
+
+    /// Get the decoder instructions are decoded with
+    pub fn decoder(&self) -> &Decoder {
+        &self.decoder
+    }
+
+    /// Trap with `Trap::MisalignedAccess` on halfword and word accesses at
+    /// addresses that are not a multiple of their size
+    ///
+    /// Disabled by default, in which case misaligned accesses are serviced.
+    pub fn set_check_alignment(&mut self, check: bool) {
+        self.check_alignment = check;
+    }
+
+    /// Whether misaligned loads and stores trap
+    pub fn check_alignment(&self) -> bool {
+        self.check_alignment
+    }
+
+    /// Watch `len` bytes at `address` for guest accesses of the given kind,
+    /// returning the watchpoint's index
+    pub fn watch(&mut self, address: u32, len: u32, access: Access, action: WatchAction) -> usize {
+        self.watchpoints.push(Some(Watchpoint {
+            address,
+            len,
+            access,
+            action,
+        }));
+        self.watchpoints.len() - 1
+    }
+
+    /// Remove a watchpoint, returning whether it existed
+    pub fn unwatch(&mut self, index: usize) -> bool {
+        self.watchpoints
+            .get_mut(index)
+            .and_then(Option::take)
+            .is_some()
+    }
+
+    /// Report an access to the watchpoints it touches
+    ///
+    /// Handlers of notifying watchpoints are called in index order until a
+    /// trapping watchpoint is found.
+    fn watched(&mut self, pc: u32, address: u32, bytes: &[u8], access: Access) -> Result<(), Trap> {
+        let mut value = [0u8; 4];
+        let count = bytes.len().min(4);
+        value[..count].copy_from_slice(&bytes[..count]);
+        for (index, watchpoint) in self.watchpoints.iter_mut().enumerate() {
+            let Some(watchpoint) = watchpoint else {
+                continue;
+            };
+            if watchpoint.access != access || !watchpoint.overlaps(address, bytes.len() as u32) {
+                continue;
+            }
+            match &mut watchpoint.action {
+                WatchAction::Trap => return Err(Trap::Watchpoint(address, access)),
+                WatchAction::Notify(handler) => handler(&WatchHit {
+                    index,
+                    pc,
+                    address,
+                    access,
+                    value: u32::from_le_bytes(value),
+                }),
+            }
+        }
+        Ok(())
+    }
+
+    /// Check if an access would stop at a trapping watchpoint
+    fn traps(&self, address: u32, len: u32, access: Access) -> bool {
+        self.watchpoints.iter().flatten().any(|watchpoint| {
+            watchpoint.access == access
+                && matches!(watchpoint.action, WatchAction::Trap)
+                && watchpoint.overlaps(address, len)
+        })
+    }
+
+    /// Number of instructions retired by this interpreter
+    pub fn retired(&self) -> u64 {
+        self.retired
+    }
+
+    /// Fetch, decode and execute the instruction at the instance's PC
+    pub fn step(&mut self, instance: &mut Instance) -> Result<(), Trap> {
+        let pc = instance.pc();
+        if pc % self.decoder.extensions().alignment() != 0 {
+            return Err(Trap::Misaligned(pc));
+        }
+
+        let word = instance.memory().read_u32(pc);
+        let length = Instruction::length(word);
+        instance
+            .memory()
+            .check(pc, length, Access::Execute)
+            .map_err(|error| access_trap(error, pc, Access::Execute))?;
+        if !self.watchpoints.is_empty() {
+            let bytes = word.to_le_bytes();
+            self.watched(pc, pc, &bytes[..length as usize], Access::Execute)?;
+        }
+        let instruction = self.decoder.decode(word);
+        self.execute(instance, &instruction)
+    }
+
+    /// Execute up to `max_steps` instructions, stopping at the first trap
+    ///
+    /// Returns `Ok(())` if the step limit was reached without trapping. The
+    /// number of instructions executed is reflected in `retired()`.
+    pub fn run(&mut self, instance: &mut Instance, max_steps: usize) -> Result<(), Trap> {
+        for _ in 0..max_steps {
+            self.step(instance)?;
+        }
+        Ok(())
+    }
+
+    /// Execute a single decoded instruction as if it were located at the current PC
+    pub fn execute(
+        &mut self,
+        instance: &mut Instance,
+        instruction: &Instruction,
+    ) -> Result<(), Trap> {
+        let pc = instance.pc();
+        let mut next_pc = pc.wrapping_add(self.decoder.size(instruction));
+        let reg = |r: &u8| instance.read_register(*r);
+
+        match instruction {
+            Instruction::Add { rd, rs1, rs2 } => {
+                instance.write_register(*rd, reg(rs1).wrapping_add(reg(rs2)))
+            }
+            Instruction::Sub { rd, rs1, rs2 } => {
+                instance.write_register(*rd, reg(rs1).wrapping_sub(reg(rs2)))
+            }
+            Instruction::Sll { rd, rs1, rs2 } => {
+                instance.write_register(*rd, reg(rs1) << (reg(rs2) & 0x1F))
+            }
+            Instruction::Xor { rd, rs1, rs2 } => instance.write_register(*rd, reg(rs1) ^ reg(rs2)),
+            Instruction::Or { rd, rs1, rs2 } => instance.write_register(*rd, reg(rs1) | reg(rs2)),
+            Instruction::Srl { rd, rs1, rs2 } => {
+                instance.write_register(*rd, reg(rs1) >> (reg(rs2) & 0x1F))
+            }
+            Instruction::Sra { rd, rs1, rs2 } => {
+                instance.write_register(*rd, ((reg(rs1) as i32) >> (reg(rs2) & 0x1F)) as u32)
+            }
+            Instruction::Slt { rd, rs1, rs2 } => {
+                instance.write_register(*rd, ((reg(rs1) as i32) < (reg(rs2) as i32)) as u32)
+            }
+            Instruction::Sltu { rd, rs1, rs2 } => {
+                instance.write_register(*rd, (reg(rs1) < reg(rs2)) as u32)
+            }
+            Instruction::And { rd, rs1, rs2 } => instance.write_register(*rd, reg(rs1) & reg(rs2)),
+            Instruction::Mul { rd, rs1, rs2 } => {
+                instance.write_register(*rd, reg(rs1).wrapping_mul(reg(rs2)))
+            }
+            Instruction::Mulh { rd, rs1, rs2 } => {
+                let product = (reg(rs1) as i32 as i64) * (reg(rs2) as i32 as i64);
+                instance.write_register(*rd, (product >> 32) as u32)
+            }
+            Instruction::Mulhsu { rd, rs1, rs2 } => {
+                let product = (reg(rs1) as i32 as i64).wrapping_mul(reg(rs2) as i64);
+                instance.write_register(*rd, (product >> 32) as u32)
+            }
+            Instruction::Mulhu { rd, rs1, rs2 } => {
+                let product = (reg(rs1) as u64) * (reg(rs2) as u64);
+                instance.write_register(*rd, (product >> 32) as u32)
+            }
+            Instruction::Div { rd, rs1, rs2 } => {
+                let (dividend, divisor) = (reg(rs1) as i32, reg(rs2) as i32);
+                // Division by zero yields -1; overflow (MIN / -1) yields MIN
+                let quotient = if divisor == 0 {
+                    -1
+                } else {
+                    dividend.wrapping_div(divisor)
+                };
+                instance.write_register(*rd, quotient as u32)
+            }
+            Instruction::Divu { rd, rs1, rs2 } => {
+                let quotient = reg(rs1).checked_div(reg(rs2)).unwrap_or(u32::MAX);
+                instance.write_register(*rd, quotient)
+            }
+            Instruction::Rem { rd, rs1, rs2 } => {
+                let (dividend, divisor) = (reg(rs1) as i32, reg(rs2) as i32);
+                // Remainder by zero yields the dividend; overflow (MIN % -1) yields 0
+                let remainder = if divisor == 0 {
+                    dividend
+                } else {
+                    dividend.wrapping_rem(divisor)
+                };
+                instance.write_register(*rd, remainder as u32)
+            }
+            Instruction::Remu { rd, rs1, rs2 } => {
+                let remainder = reg(rs1).checked_rem(reg(rs2)).unwrap_or(reg(rs1));
+                instance.write_register(*rd, remainder)
+            }
+            Instruction::Addi { rd, rs1, imm } => {
+                instance.write_register(*rd, reg(rs1).wrapping_add(*imm as u32))
+            }
+            Instruction::Slti { rd, rs1, imm } => {
+                instance.write_register(*rd, ((reg(rs1) as i32) < *imm) as u32)
+            }
+            Instruction::Sltiu { rd, rs1, imm } => {
+                instance.write_register(*rd, (reg(rs1) < *imm as u32) as u32)
+            }
+            Instruction::Xori { rd, rs1, imm } => {
+                instance.write_register(*rd, reg(rs1) ^ *imm as u32)
+            }
+            Instruction::Ori { rd, rs1, imm } => {
+                instance.write_register(*rd, reg(rs1) | *imm as u32)
+            }
+            Instruction::Andi { rd, rs1, imm } => {
+                instance.write_register(*rd, reg(rs1) & *imm as u32)
+            }
+            Instruction::Slli { rd, rs1, shamt } => {
+                instance.write_register(*rd, reg(rs1) << (shamt & 0x1F))
+            }
+            Instruction::Srli { rd, rs1, shamt } => {
+                instance.write_register(*rd, reg(rs1) >> (shamt & 0x1F))
+            }
+            Instruction::Srai { rd, rs1, shamt } => {
+                instance.write_register(*rd, ((reg(rs1) as i32) >> (shamt & 0x1F)) as u32)
+            }
+            Instruction::Lb { rd, rs1, imm } => {
+                let address = reg(rs1).wrapping_add(*imm as u32);
+                let value = self.load::<1>(instance, address)?[0] as i8 as i32 as u32;
+                instance.write_register(*rd, value)
+            }
+            Instruction::Lh { rd, rs1, imm } => {
+                let address = reg(rs1).wrapping_add(*imm as u32);
+                let value = i16::from_le_bytes(self.load::<2>(instance, address)?) as i32 as u32;
+                instance.write_register(*rd, value)
+            }
+            Instruction::Lw { rd, rs1, imm } => {
+                let address = reg(rs1).wrapping_add(*imm as u32);
+                let value = u32::from_le_bytes(self.load::<4>(instance, address)?);
+                instance.write_register(*rd, value)
+            }
+            Instruction::Lbu { rd, rs1, imm } => {
+                let address = reg(rs1).wrapping_add(*imm as u32);
+                let value = self.load::<1>(instance, address)?[0] as u32;
+                instance.write_register(*rd, value)
+            }
+            Instruction::Lhu { rd, rs1, imm } => {
+                let address = reg(rs1).wrapping_add(*imm as u32);
+                let value = u16::from_le_bytes(self.load::<2>(instance, address)?) as u32;
+                instance.write_register(*rd, value)
+            }
+            Instruction::Sb { rs1, rs2, imm } => {
+                let address = reg(rs1).wrapping_add(*imm as u32);
+                self.store(instance, address, &(reg(rs2) as u8).to_le_bytes())?
+            }
+            Instruction::Sh { rs1, rs2, imm } => {
+                let address = reg(rs1).wrapping_add(*imm as u32);
+                self.store(instance, address, &(reg(rs2) as u16).to_le_bytes())?
+            }
+            Instruction::Sw { rs1, rs2, imm } => {
+                let address = reg(rs1).wrapping_add(*imm as u32);
+                self.store(instance, address, &reg(rs2).to_le_bytes())?
+            }
+            Instruction::Beq { rs1, rs2, imm } => {
+                if reg(rs1) == reg(rs2) {
+                    next_pc = pc.wrapping_add(*imm as u32);
+                }
+            }
+            Instruction::Bne { rs1, rs2, imm } => {
+                if reg(rs1) != reg(rs2) {
+                    next_pc = pc.wrapping_add(*imm as u32);
+                }
+            }
+            Instruction::Blt { rs1, rs2, imm } => {
+                if (reg(rs1) as i32) < (reg(rs2) as i32) {
+                    next_pc = pc.wrapping_add(*imm as u32);
+                }
+            }
+            Instruction::Bge { rs1, rs2, imm } => {
+                if (reg(rs1) as i32) >= (reg(rs2) as i32) {
+                    next_pc = pc.wrapping_add(*imm as u32);
+                }
+            }
+            Instruction::Bltu { rs1, rs2, imm } => {
+                if reg(rs1) < reg(rs2) {
+                    next_pc = pc.wrapping_add(*imm as u32);
+                }
+            }
+            Instruction::Bgeu { rs1, rs2, imm } => {
+                if reg(rs1) >= reg(rs2) {
+                    next_pc = pc.wrapping_add(*imm as u32);
+                }
+            }
+            Instruction::Jal { rd, imm } => {
+                instance.write_register(*rd, next_pc);
+                next_pc = pc.wrapping_add(*imm as u32);
+            }
+            Instruction::Jalr { rd, rs1, imm } => {
+                // Compute the target before writing rd, since rd may equal rs1
+                let target = reg(rs1).wrapping_add(*imm as u32) & !1;
+                instance.write_register(*rd, next_pc);
+                next_pc = target;
+            }
+            Instruction::Lui { rd, imm } => instance.write_register(*rd, imm << 12),
+            Instruction::Auipc { rd, imm } => {
+                instance.write_register(*rd, pc.wrapping_add(imm << 12))
+            }
+            Instruction::Ecall => return Err(Trap::Ecall),
+            Instruction::Ebreak => return Err(Trap::Ebreak),
+            // There is no clock: cycle and time count retired instructions, so
+            // counter reads are deterministic
+            Instruction::Rdcycle { rd }
+            | Instruction::Rdtime { rd }
+            | Instruction::Rdinstret { rd } => instance.write_register(*rd, self.retired as u32),
+            Instruction::Rdcycleh { rd }
+            | Instruction::Rdtimeh { rd }
+            | Instruction::Rdinstreth { rd } => {
+                instance.write_register(*rd, (self.retired >> 32) as u32)
+            }
+            Instruction::Unsupported(word) => return Err(Trap::Illegal(*word)),
+            // Vector and floating-point instructions are recognized but not executed
+            Instruction::Vsetvli { .. }
+            | Instruction::Vle { .. }
+            | Instruction::Vse { .. }
+            | Instruction::VaddVv { .. }
+            | Instruction::VaddVx { .. }
+            | Instruction::VaddVi { .. }
+            | Instruction::Flh { .. }
+            | Instruction::Fsh { .. }
+            | Instruction::FaddH { .. }
+            | Instruction::FsubH { .. }
+            | Instruction::FmulH { .. }
+            | Instruction::FdivH { .. }
+            | Instruction::FcvtWH { .. }
+            | Instruction::FcvtWuH { .. }
+            | Instruction::FcvtHW { .. }
+            | Instruction::FcvtHWu { .. }
+            | Instruction::FcvtSH { .. }
+            | Instruction::FcvtHS { .. } => {
+                return Err(Trap::Illegal(instruction.encode().unwrap_or(0)));
+            }
+        }
+
+        instance.set_pc(next_pc);
+        self.retired += 1;
+        Ok(())
+    }
+}
+
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    /// Load `N` bytes of guest memory (unmapped memory reads as zero)
+    fn load<const N: usize>(
+        &mut self,
+        instance: &mut Instance,
+        address: u32,
+    ) -> Result<[u8; N], Trap> {
+        if self.check_alignment && address % N as u32 != 0 {
+            return Err(Trap::MisalignedAccess(address, Access::Read));
+        }
+        if instance.memory().mmio(address, N as u32) {
+            // Device reads can have side effects, so trap before reading;
+            // handlers notified ahead of the trap see a zero value
+            if self.traps(address, N as u32, Access::Read) {
+                self.watched(instance.pc(), address, &[0; N], Access::Read)?;
+            }
+            let value = instance
+                .memory_mut()
+                .mmio_read(address, N as u32)
+                .ok_or(Trap::Fault(address))?;
+            let mut bytes = [0u8; N];
+            bytes.copy_from_slice(&value.to_le_bytes()[..N]);
+            if !self.watchpoints.is_empty() {
+                self.watched(instance.pc(), address, &bytes, Access::Read)?;
+            }
+            return Ok(bytes);
+        }
+        instance
+            .memory()
+            .check(address, N as u32, Access::Read)
+            .map_err(|error| access_trap(error, address, Access::Read))?;
+        let bytes = instance.memory().read_array(address);
+        if !self.watchpoints.is_empty() {
+            self.watched(instance.pc(), address, &bytes, Access::Read)?;
+        }
+        Ok(bytes)
+    }
+
+    /// Store bytes to guest memory, allocating pages as needed
+    fn store(&mut self, instance: &mut Instance, address: u32, bytes: &[u8]) -> Result<(), Trap> {
+        if self.check_alignment && address % bytes.len() as u32 != 0 {
+            return Err(Trap::MisalignedAccess(address, Access::Write));
+        }
+        if instance.memory().mmio(address, bytes.len() as u32) {
+            if !self.watchpoints.is_empty() {
+                self.watched(instance.pc(), address, bytes, Access::Write)?;
+            }
+            let mut value = [0u8; 4];
+            value[..bytes.len()].copy_from_slice(bytes);
+            let value = u32::from_le_bytes(value);
+            if !instance
+                .memory_mut()
+                .mmio_write(address, bytes.len() as u32, value)
+            {
+                return Err(Trap::Fault(address));
+            }
+            return Ok(());
+        }
+        instance
+            .memory()
+            .check(address, bytes.len() as u32, Access::Write)
+            .map_err(|error| access_trap(error, address, Access::Write))?;
+        if !self.watchpoints.is_empty() {
+            self.watched(instance.pc(), address, bytes, Access::Write)?;
+        }
+        instance
+            .memory_mut()
+            .write(address, bytes)
+            .map_err(|_| Trap::Fault(address))
+    }
+}
+
+/// Trap for a rejected `Memory::check`
+fn access_trap(error: MemoryError, address: u32, access: Access) -> Trap {
+    match error {
+        MemoryError::Guard => Trap::Guard(address),
+        _ => Trap::Protection(address, access),
+    }
+}
@@ -0,0 +1,287 @@
+//! Machine-readable table of supported encodings
+//!
+//! Lists every instruction the decoder recognizes with the fixed bits of its
+//! encoding, its base format, its extension and the operands it takes. External
+//! tooling (assemblers, test generators, documentation) can consume the table to
+//! stay in sync with what jigs supports. With the `serde` feature the table is
+//! serializable and `to_json()` exports it as JSON.
+//!
+//! # Example
+//! ```
+//! use jigs::isa::{self, Format};
+//!
+//! let addi = isa::lookup("addi").unwrap();
+//! assert_eq!(addi.format, Format::I);
+//! assert_eq!(addi.opcode(), 0x13);
+//! assert_eq!(addi.funct3(), Some(0));
+//! assert_eq!(addi.funct7(), None);
+//! assert!(addi.matches(0x00A00093)); // addi x1, x0, 10
+//! ```
+
+use crate::Extension;
+use Extension::{I as BASE, M, V, Zfh};
+use Format::{B, I, J, R, S, U};
+use std::fmt;
+
+/// Base RISC-V instruction format, describing where operands are placed
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Format {
+    /// Register-register (rd, rs1, rs2, funct3, funct7)
+    R,
+    /// Register-immediate with a 12-bit immediate in bits 31:20
+    I,
+    /// Store with a split 12-bit immediate
+    S,
+    /// Conditional branch with a split 13-bit offset
+    B,
+    /// 20-bit upper immediate in bits 31:12
+    U,
+    /// Jump with a split 21-bit offset
+    J,
+}
+
+/// A supported instruction encoding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Encoding {
+    /// Assembly mnemonic, as returned by `Instruction::mnemonic`
+    pub mnemonic: &'static str,
+    /// Bits fixed by the encoding
+    pub mask: u32,
+    /// Value of the fixed bits
+    pub value: u32,
+    /// Operand layout
+    pub format: Format,
+    /// Extension the instruction belongs to
+    pub extension: Extension,
+    /// Operand names in assembly order
+    pub operands: &'static [&'static str],
+}
+
+/// Bits 6:0
+const OPCODE_MASK: u32 = 0x7F;
+/// Bits 14:12
+const FUNCT3_MASK: u32 = 0x7000;
+/// Bits 31:25
+const FUNCT7_MASK: u32 = 0xFE000000;
+
+const RD_RS1_RS2: &[&str] = &["rd", "rs1", "rs2"];
+const RD_RS1_IMM: &[&str] = &["rd", "rs1", "imm"];
+const RD_RS1_SHAMT: &[&str] = &["rd", "rs1", "shamt"];
+const RS1_RS2_IMM: &[&str] = &["rs1", "rs2", "imm"];
+const RD_IMM: &[&str] = &["rd", "imm"];
+const RD: &[&str] = &["rd"];
+const NONE: &[&str] = &[];
+const VD_RS1_VM: &[&str] = &["vd", "rs1", "vm"];
+const VS3_RS1_VM: &[&str] = &["vs3", "rs1", "vm"];
+const RD_RS1_RS2_RM: &[&str] = &["rd", "rs1", "rs2", "rm"];
+const RD_RS1_RM: &[&str] = &["rd", "rs1", "rm"];
+
+/// Build a table entry
+const fn entry(
+    mask: u32,
+    value: u32,
+    mnemonic: &'static str,
+    format: Format,
+    extension: Extension,
+    operands: &'static [&'static str],
+) -> Encoding {
+    Encoding {
+        mnemonic,
+        mask,
+        value,
+        format,
+        extension,
+        operands,
+    }
+}
+
+/// Every supported encoding
+pub const ENCODINGS: &[Encoding] = &[
+    // R-type
+    entry(0xFE00707F, 0x00000033, "add", R, BASE, RD_RS1_RS2),
+    entry(0xFE00707F, 0x40000033, "sub", R, BASE, RD_RS1_RS2),
+    entry(0xFE00707F, 0x00001033, "sll", R, BASE, RD_RS1_RS2),
+    entry(0xFE00707F, 0x00002033, "slt", R, BASE, RD_RS1_RS2),
+    entry(0xFE00707F, 0x00003033, "sltu", R, BASE, RD_RS1_RS2),
+    entry(0xFE00707F, 0x00004033, "xor", R, BASE, RD_RS1_RS2),
+    entry(0xFE00707F, 0x00005033, "srl", R, BASE, RD_RS1_RS2),
+    entry(0xFE00707F, 0x40005033, "sra", R, BASE, RD_RS1_RS2),
+    entry(0xFE00707F, 0x00006033, "or", R, BASE, RD_RS1_RS2),
+    entry(0xFE00707F, 0x00007033, "and", R, BASE, RD_RS1_RS2),
+    // M extension
+    entry(0xFE00707F, 0x02000033, "mul", R, M, RD_RS1_RS2),
+    entry(0xFE00707F, 0x02001033, "mulh", R, M, RD_RS1_RS2),
+    entry(0xFE00707F, 0x02002033, "mulhsu", R, M, RD_RS1_RS2),
+    entry(0xFE00707F, 0x02003033, "mulhu", R, M, RD_RS1_RS2),
+    entry(0xFE00707F, 0x02004033, "div", R, M, RD_RS1_RS2),
+    entry(0xFE00707F, 0x02005033, "divu", R, M, RD_RS1_RS2),
+    entry(0xFE00707F, 0x02006033, "rem", R, M, RD_RS1_RS2),
+    entry(0xFE00707F, 0x02007033, "remu", R, M, RD_RS1_RS2),
+    // I-type arithmetic
+    entry(0x0000707F, 0x00000013, "addi", I, BASE, RD_RS1_IMM),
+    entry(0x0000707F, 0x00002013, "slti", I, BASE, RD_RS1_IMM),
+    entry(0x0000707F, 0x00003013, "sltiu", I, BASE, RD_RS1_IMM),
+    entry(0x0000707F, 0x00004013, "xori", I, BASE, RD_RS1_IMM),
+    entry(0x0000707F, 0x00006013, "ori", I, BASE, RD_RS1_IMM),
+    entry(0x0000707F, 0x00007013, "andi", I, BASE, RD_RS1_IMM),
+    entry(0xFE00707F, 0x00001013, "slli", I, BASE, RD_RS1_SHAMT),
+    entry(0xFE00707F, 0x00005013, "srli", I, BASE, RD_RS1_SHAMT),
+    entry(0xFE00707F, 0x40005013, "srai", I, BASE, RD_RS1_SHAMT),
+    // Loads and stores
+    entry(0x0000707F, 0x00000003, "lb", I, BASE, RD_RS1_IMM),
+    entry(0x0000707F, 0x00001003, "lh", I, BASE, RD_RS1_IMM),
+    entry(0x0000707F, 0x00002003, "lw", I, BASE, RD_RS1_IMM),
+    entry(0x0000707F, 0x00004003, "lbu", I, BASE, RD_RS1_IMM),
+    entry(0x0000707F, 0x00005003, "lhu", I, BASE, RD_RS1_IMM),
+    entry(0x0000707F, 0x00000023, "sb", S, BASE, RS1_RS2_IMM),
+    entry(0x0000707F, 0x00001023, "sh", S, BASE, RS1_RS2_IMM),
+    entry(0x0000707F, 0x00002023, "sw", S, BASE, RS1_RS2_IMM),
+    // Branches and jumps
+    entry(0x0000707F, 0x00000063, "beq", B, BASE, RS1_RS2_IMM),
+    entry(0x0000707F, 0x00001063, "bne", B, BASE, RS1_RS2_IMM),
+    entry(0x0000707F, 0x00004063, "blt", B, BASE, RS1_RS2_IMM),
+    entry(0x0000707F, 0x00005063, "bge", B, BASE, RS1_RS2_IMM),
+    entry(0x0000707F, 0x00006063, "bltu", B, BASE, RS1_RS2_IMM),
+    entry(0x0000707F, 0x00007063, "bgeu", B, BASE, RS1_RS2_IMM),
+    entry(0x0000007F, 0x0000006F, "jal", J, BASE, RD_IMM),
+    entry(0x0000707F, 0x00000067, "jalr", I, BASE, RD_RS1_IMM),
+    // Upper immediates
+    entry(0x0000007F, 0x00000037, "lui", U, BASE, RD_IMM),
+    entry(0x0000007F, 0x00000017, "auipc", U, BASE, RD_IMM),
+    // System
+    entry(0xFFFFFFFF, 0x00000073, "ecall", I, BASE, NONE),
+    entry(0xFFFFFFFF, 0x00100073, "ebreak", I, BASE, NONE),
+    // Zicntr counters (csrrs rd, csr, x0)
+    entry(0xFFFFF07F, 0xC0002073, "rdcycle", I, BASE, RD),
+    entry(0xFFFFF07F, 0xC8002073, "rdcycleh", I, BASE, RD),
+    entry(0xFFFFF07F, 0xC0102073, "rdtime", I, BASE, RD),
+    entry(0xFFFFF07F, 0xC8102073, "rdtimeh", I, BASE, RD),
+    entry(0xFFFFF07F, 0xC0202073, "rdinstret", I, BASE, RD),
+    entry(0xFFFFF07F, 0xC8202073, "rdinstreth", I, BASE, RD),
+    // V extension
+    entry(
+        0x8000707F,
+        0x00007057,
+        "vsetvli",
+        I,
+        V,
+        &["rd", "rs1", "vtypei"],
+    ),
+    entry(0xFDF0707F, 0x00000007, "vle8.v", R, V, VD_RS1_VM),
+    entry(0xFDF0707F, 0x00005007, "vle16.v", R, V, VD_RS1_VM),
+    entry(0xFDF0707F, 0x00006007, "vle32.v", R, V, VD_RS1_VM),
+    entry(0xFDF0707F, 0x00007007, "vle64.v", R, V, VD_RS1_VM),
+    entry(0xFDF0707F, 0x00000027, "vse8.v", R, V, VS3_RS1_VM),
+    entry(0xFDF0707F, 0x00005027, "vse16.v", R, V, VS3_RS1_VM),
+    entry(0xFDF0707F, 0x00006027, "vse32.v", R, V, VS3_RS1_VM),
+    entry(0xFDF0707F, 0x00007027, "vse64.v", R, V, VS3_RS1_VM),
+    entry(
+        0xFC00707F,
+        0x00000057,
+        "vadd.vv",
+        R,
+        V,
+        &["vd", "vs2", "vs1", "vm"],
+    ),
+    entry(
+        0xFC00707F,
+        0x00004057,
+        "vadd.vx",
+        R,
+        V,
+        &["vd", "vs2", "rs1", "vm"],
+    ),
+    entry(
+        0xFC00707F,
+        0x00003057,
+        "vadd.vi",
+        R,
+        V,
+        &["vd", "vs2", "imm", "vm"],
+    ),
+    // Zfh extension
+    entry(0x0000707F, 0x00001007, "flh", I, Zfh, RD_RS1_IMM),
+    entry(0x0000707F, 0x00001027, "fsh", S, Zfh, RS1_RS2_IMM),
+    entry(0xFE00007F, 0x04000053, "fadd.h", R, Zfh, RD_RS1_RS2_RM),
+    entry(0xFE00007F, 0x0C000053, "fsub.h", R, Zfh, RD_RS1_RS2_RM),
+    entry(0xFE00007F, 0x14000053, "fmul.h", R, Zfh, RD_RS1_RS2_RM),
+    entry(0xFE00007F, 0x1C000053, "fdiv.h", R, Zfh, RD_RS1_RS2_RM),
+    entry(0xFFF0007F, 0xC4000053, "fcvt.w.h", R, Zfh, RD_RS1_RM),
+    entry(0xFFF0007F, 0xC4100053, "fcvt.wu.h", R, Zfh, RD_RS1_RM),
+    entry(0xFFF0007F, 0xD4000053, "fcvt.h.w", R, Zfh, RD_RS1_RM),
+    entry(0xFFF0007F, 0xD4100053, "fcvt.h.wu", R, Zfh, RD_RS1_RM),
+    entry(0xFFF0007F, 0x40200053, "fcvt.s.h", R, Zfh, RD_RS1_RM),
+    entry(0xFFF0007F, 0x44000053, "fcvt.h.s", R, Zfh, RD_RS1_RM),
+];
+
+impl Encoding {
+    /// Major opcode (bits 6:0)
+    pub fn opcode(&self) -> u32 {
+        self.value & OPCODE_MASK
+    }
+
+    /// funct3 (bits 14:12), if fixed by the encoding
+    pub fn funct3(&self) -> Option<u32> {
+        (self.mask & FUNCT3_MASK == FUNCT3_MASK).then_some((self.value & FUNCT3_MASK) >> 12)
+    }
+
+    /// funct7 (bits 31:25), if fixed by the encoding
+    pub fn funct7(&self) -> Option<u32> {
+        (self.mask & FUNCT7_MASK == FUNCT7_MASK).then_some((self.value & FUNCT7_MASK) >> 25)
+    }
+
+    /// Check if an instruction word has this encoding's fixed bits
+    ///
+    /// Operand values are not checked, so a matching word may still decode as
+    /// `Unsupported` (for example with a reserved rounding mode).
+    pub fn matches(&self, word: u32) -> bool {
+        word & self.mask == self.value
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Format::R => "R",
+            Format::I => "I",
+            Format::S => "S",
+            Format::B => "B",
+            Format::U => "U",
+            Format::J => "J",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for Encoding {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut row = serializer.serialize_struct("Encoding", 9)?;
+        row.serialize_field("mnemonic", self.mnemonic)?;
+        row.serialize_field("extension", &self.extension)?;
+        row.serialize_field("format", &self.format)?;
+        row.serialize_field("opcode", &self.opcode())?;
+        row.serialize_field("funct3", &self.funct3())?;
+        row.serialize_field("funct7", &self.funct7())?;
+        row.serialize_field("mask", &self.mask)?;
+        row.serialize_field("match", &self.value)?;
+        row.serialize_field("operands", self.operands)?;
+        row.end()
+    }
+}
+
+/// Find the encoding of a mnemonic
+pub fn lookup(mnemonic: &str) -> Option<&'static Encoding> {
+    ENCODINGS
+        .iter()
+        .find(|encoding| encoding.mnemonic == mnemonic)
+}
+
+/// Export the table as a JSON array
+#[cfg(feature = "serde")]
+pub fn to_json() -> String {
+    serde_json::to_string_pretty(ENCODINGS).expect("encoding table serializes")
+}
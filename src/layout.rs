@@ -0,0 +1,167 @@
+//! Guest address-space layout
+//!
+//! A `MemoryLayout` places the guest's text, read-only data, heap and stack in
+//! one description shared by the embedder and the runtime.
+//! `Memory::apply_layout()` protects the segments, sets up the heap and guards
+//! the page(s) below the stack, and `Instance::setup_stack()` points the stack
+//! pointer at the top of the stack.
+//!
+//! # Example
+//! ```
+//! use jigs::{Instance, Memory, MemoryLayout, PageStore, Permissions};
+//!
+//! let store = PageStore::new(64);
+//! let layout = MemoryLayout::default();
+//! let mut memory = Memory::new(&store, 64, 8);
+//! memory.apply_layout(&layout).unwrap();
+//! assert_eq!(memory.permissions(layout.text.base), Permissions::READ_EXECUTE);
+//!
+//! let mut instance = Instance::new(memory);
+//! instance.setup_stack(&layout);
+//! assert_eq!(instance.read_register(2), layout.stack_top());
+//! ```
+
+use crate::memory::{Memory, MemoryError, PAGE_OFFSET_MASK, PAGE_SIZE, Permissions};
+
+/// Page-aligned range of guest addresses
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Segment {
+    /// Guest address of the first byte
+    pub base: u32,
+    /// Size in bytes (a multiple of PAGE_SIZE)
+    pub size: u32,
+}
+
+impl Segment {
+    /// Create a segment
+    pub const fn new(base: u32, size: u32) -> Self {
+        Segment { base, size }
+    }
+
+    /// Address one past the last byte (2^32 for a segment ending at 0xFFFFFFFF)
+    pub fn end(&self) -> u64 {
+        self.base as u64 + self.size as u64
+    }
+
+    /// Check if the segments share at least one byte
+    pub fn overlaps(&self, other: &Segment) -> bool {
+        self.size > 0
+            && other.size > 0
+            && (self.base as u64) < other.end()
+            && (other.base as u64) < self.end()
+    }
+}
+
+/// Placement of the guest's text, read-only data, heap and stack
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLayout {
+    /// Code, mapped read/execute
+    pub text: Segment,
+    /// Read-only data
+    pub rodata: Segment,
+    /// Linear heap; its size is the maximum the heap can grow to
+    pub heap: Segment,
+    /// Stack, growing down from `stack_top()`
+    pub stack: Segment,
+    /// Size of the guard region directly below the stack (0 for none)
+    pub stack_guard: u32,
+}
+
+impl Default for MemoryLayout {
+    /// 1MB of text at 0x10000, 1MB of read-only data, a 16MB heap at 16MB and
+    /// a 1MB stack ending at 2GB with one guard page
+    fn default() -> Self {
+        MemoryLayout {
+            text: Segment::new(0x0001_0000, 0x0010_0000),
+            rodata: Segment::new(0x0011_0000, 0x0010_0000),
+            heap: Segment::new(0x0100_0000, 0x0100_0000),
+            stack: Segment::new(0x7ff0_0000, 0x0010_0000),
+            stack_guard: PAGE_SIZE as u32,
+        }
+    }
+}
+
+impl MemoryLayout {
+    /// Initial stack pointer: the end of the stack, wrapping to 0 at the top of
+    /// the address space
+    pub fn stack_top(&self) -> u32 {
+        self.stack.base.wrapping_add(self.stack.size)
+    }
+
+    /// Guard region below the stack
+    pub fn guard(&self) -> Segment {
+        Segment::new(
+            self.stack.base.wrapping_sub(self.stack_guard),
+            self.stack_guard,
+        )
+    }
+
+    /// Check that the layout can be applied
+    ///
+    /// # Errors
+    /// - `MemoryError::Misaligned`: A base or size is not a multiple of `PAGE_SIZE`
+    /// - `MemoryError::PageLimit`: A segment or the stack guard extends past
+    ///   0xFFFFFFFF or below 0
+    /// - `MemoryError::Overlap`: Two segments (or a segment and the stack guard) overlap
+    pub fn validate(&self) -> Result<(), MemoryError> {
+        let segments = [self.text, self.rodata, self.heap, self.stack, self.guard()];
+        let aligned = segments
+            .iter()
+            .all(|segment| (segment.base | segment.size) & PAGE_OFFSET_MASK == 0);
+        if !aligned {
+            return Err(MemoryError::Misaligned);
+        }
+        if segments.iter().any(|segment| segment.end() > 1 << 32)
+            || self.stack_guard > self.stack.base
+        {
+            return Err(MemoryError::PageLimit);
+        }
+        for (index, segment) in segments.iter().enumerate() {
+            if segments[index + 1..]
+                .iter()
+                .any(|other| segment.overlaps(other))
+            {
+                return Err(MemoryError::Overlap);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Memory {
+    /// Prepare this memory for a layout
+    ///
+    /// Text becomes read/execute and read-only data read-only, the heap base
+    /// and limit are set from the heap segment, and the stack guard is reserved
+    /// with `guard()`. No pages are allocated. Permissions, unlike the guard
+    /// and heap settings, are cleared by `reset()` and must be reapplied.
+    ///
+    /// # Errors
+    /// - Any error from `MemoryLayout::validate()`
+    /// - `MemoryError::Overlap`: The heap has already been grown, or the stack
+    ///   guard covers allocated pages
+    /// - `MemoryError::NoL2Tables`: No more L2 tables available to hold the permissions
+    pub fn apply_layout(&mut self, layout: &MemoryLayout) -> Result<(), MemoryError> {
+        layout.validate()?;
+        self.set_heap_base(layout.heap.base)?;
+        self.set_heap_limit(layout.heap.size / PAGE_SIZE as u32)?;
+        let guard = layout.guard();
+        self.guard(guard.base, guard.size)?;
+        self.protect(
+            layout.text.base,
+            layout.text.size,
+            Permissions::READ_EXECUTE,
+        )?;
+        self.protect(
+            layout.rodata.base,
+            layout.rodata.size,
+            Permissions::READ_ONLY,
+        )?;
+        self.protect(layout.heap.base, layout.heap.size, Permissions::READ_WRITE)?;
+        self.protect(
+            layout.stack.base,
+            layout.stack.size,
+            Permissions::READ_WRITE,
+        )
+    }
+}
@@ -5,16 +5,79 @@
 //! - Gas-metered execution for controlled resource usage
 
 pub mod arm64;
+pub mod assembler;
+pub mod bundle;
+pub mod cache;
 pub mod compiler;
+pub mod cost;
+pub mod csr;
+pub mod cursor;
+pub mod decoder;
+pub mod diff;
+pub mod disassembler;
+pub mod elf;
+pub mod executable;
+pub mod fold;
+pub mod fusion;
+#[cfg(feature = "arbitrary")]
+pub mod generate;
+pub mod harness;
 pub mod instance;
 pub mod instruction;
+pub mod interpreter;
+pub mod isa;
+pub mod layout;
 pub mod memory;
+pub mod memory64;
+pub mod metadata;
+pub mod mmio;
 pub mod module;
+pub mod neon;
+pub mod parser;
+pub mod pattern;
+pub mod peephole;
+mod regalloc;
+pub mod repl;
+pub mod serial;
+pub mod syscall;
+pub mod tier;
+mod translator;
+mod unwind;
+mod veneer;
+pub mod verify;
+pub mod visitor;
 
 #[cfg(test)]
 mod tests;
 
-pub use instance::Instance;
-pub use instruction::{EncodeError, Instruction};
-pub use memory::{Memory, PageStore};
+pub use assembler::{AssembleError, Assembler};
+pub use bundle::{Bundle, BundleError};
+pub use cache::CodeCache;
+pub use compiler::{CompileStats, ConstantStrategy, OptLevel, Pass, SourceMap};
+pub use cost::CostModel;
+pub use cursor::MemoryCursor;
+pub use decoder::{Decoder, Extension, Extensions, Stream};
+pub use diff::{DiffRange, MemoryDiff, MemorySnapshot};
+pub use disassembler::Disassembler;
+pub use elf::{Elf, ElfError};
+pub use executable::ExecutableBuffer;
+pub use instance::{EcallHandler, Instance};
+pub use instruction::{
+    EncodeError, Instruction, InstructionKind, Register, Relocation, RelocationKind,
+};
+pub use interpreter::{Interpreter, Trap, WatchAction, WatchHit};
+pub use layout::{MemoryLayout, Segment};
+pub use memory::{
+    Access, InstanceUsage, L2TableInfo, Memory, MemoryError, MemoryStats, PageEvent, PageObserver,
+    PageStore, PageStoreStats, PageTuning, Permissions, PhysicalOffset, Region, RegionKind,
+    Reservation, SharedPages,
+};
+pub use memory64::Memory64;
+pub use metadata::Metadata;
+pub use mmio::MmioDevice;
 pub use module::{CompileError, Module};
+pub use parser::ParseError;
+pub use repl::{Repl, ReplError};
+pub use serial::SerializeError;
+pub use tier::Tiered;
+pub use visitor::InstructionVisitor;
@@ -1,9 +1,136 @@
-use jigs::Instruction;
+use jigs::{Bundle, Instance, Instruction, Memory, Module, PageStore, Repl, bundle::BUNDLE_MAGIC};
+use std::{
+    env, fs,
+    io::{self, BufRead, Write},
+    process,
+};
+
+/// Pages available to the REPL instance (4MB)
+const REPL_PAGES: usize = 256;
+
+/// L2 tables available to the REPL instance
+const REPL_L2_TABLES: usize = 16;
 
 fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    match args.first().map(String::as_str) {
+        Some("repl") => repl(args.get(1)),
+        Some("bundle") => bundle(&args[1..]),
+        _ => example(),
+    }
+}
+
+/// Decode and display an example instruction
+fn example() {
     // Example: decode and display an ADD instruction
     // add x1, x2, x3
     let instruction_word = 0x003100B3;
     let instruction = Instruction::decode(instruction_word);
     println!("Decoded instruction: {}", instruction);
 }
+
+/// Package raw RISC-V code and data files into a bundle with its compiled module
+///
+/// Usage: `jigs bundle <code> <output> [--base ADDR] [--entry ADDR]...
+/// [--data ADDR FILE]... [--set KEY=VALUE]...`
+fn bundle(args: &[String]) {
+    let (Some(code), Some(output)) = (args.first(), args.get(1)) else {
+        fail(
+            "Usage: jigs bundle <code> <output> [--base ADDR] [--entry ADDR]... [--data ADDR FILE]... [--set KEY=VALUE]...",
+        );
+    };
+    let mut bundle = Bundle::new(0, read(code));
+
+    let mut options = args[2..].iter();
+    while let Some(option) = options.next() {
+        let mut value = || {
+            options
+                .next()
+                .unwrap_or_else(|| fail(&format!("Missing value for {}", option)))
+        };
+        match option.as_str() {
+            "--base" => bundle.base = address(value()),
+            "--entry" => bundle.entries.push(address(value())),
+            "--data" => {
+                let address = address(value());
+                bundle.segments.push((address, read(value())));
+            }
+            "--set" => {
+                let (key, value) = value()
+                    .split_once('=')
+                    .unwrap_or_else(|| fail("Expected KEY=VALUE for --set"));
+                bundle.manifest.insert(key.to_string(), value.to_string());
+            }
+            _ => fail(&format!("Unknown option: {}", option)),
+        }
+    }
+
+    let compiled =
+        Module::new(bundle.code.len()).and_then(|mut module| bundle.compile(&mut module));
+    if let Err(error) = compiled {
+        fail(&format!("Failed to compile {}: {:?}", code, error));
+    }
+    if let Err(error) = bundle.save(output) {
+        fail(&format!("Failed to write {}: {}", output, error));
+    }
+}
+
+/// Run an interactive REPL, optionally loading a bundle or a raw code image at address 0
+fn repl(path: Option<&String>) {
+    let store = PageStore::new(REPL_PAGES);
+    let memory = Memory::new(&store, REPL_PAGES, REPL_L2_TABLES);
+    let mut instance = Instance::new(memory);
+
+    if let Some(path) = path {
+        let image = read(path);
+        if image.starts_with(&BUNDLE_MAGIC) {
+            let bundle = Bundle::from_bytes(&image)
+                .and_then(|bundle| bundle.load_memory(instance.memory_mut()).map(|_| bundle))
+                .unwrap_or_else(|error| fail(&format!("Failed to load {}: {}", path, error)));
+            instance.set_pc(bundle.entries.first().copied().unwrap_or(bundle.base));
+        } else if let Err(error) = instance.memory_mut().write(0, &image) {
+            fail(&format!("Failed to load {}: {}", path, error));
+        }
+    }
+
+    let mut repl = Repl::new(instance);
+    let stdin = io::stdin();
+    loop {
+        print!("jigs> ");
+        io::stdout().flush().unwrap();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line).unwrap_or(0) == 0 {
+            break;
+        }
+        if matches!(line.trim(), "quit" | "exit") {
+            break;
+        }
+
+        match repl.execute(&line) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => println!("{}", output),
+            Err(error) => println!("{}", error),
+        }
+    }
+}
+
+/// Read a file or exit with an error
+fn read(path: &str) -> Vec<u8> {
+    fs::read(path).unwrap_or_else(|error| fail(&format!("Failed to read {}: {}", path, error)))
+}
+
+/// Parse a decimal or `0x`-prefixed hexadecimal address or exit with an error
+fn address(arg: &str) -> u32 {
+    let parsed = match arg.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => arg.parse(),
+    };
+    parsed.unwrap_or_else(|_| fail(&format!("Invalid address: {}", arg)))
+}
+
+/// Print an error and exit
+fn fail(message: &str) -> ! {
+    eprintln!("{}", message);
+    process::exit(1);
+}
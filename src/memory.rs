@@ -8,6 +8,11 @@
 /// - Lazy page allocation from a global shared pool
 /// - Direct pointer access for native ARM64 code
 /// - Reset functionality between executions
+/// - Per-page read/write/execute permissions checked on guest accesses
+/// - Host buffers mapped into the guest address space without copying
+/// - Guard regions that are never allocated and always fault
+/// - A wasm-style linear heap grown a page at a time with `grow()`
+/// - Read-only pages shared by many instances (e.g. code and rodata images)
 ///
 /// # Two-Layer Page Table Architecture
 ///
@@ -19,10 +24,15 @@
 /// Each L2 table covers 4MB of address space (256 pages × 16KB).
 /// Maximum coverage is 1020MB with 255 L2 tables.
 ///
-/// # Safety
-/// PageStore MUST outlive all Memory instances. The PageStore will panic
-/// if dropped while Memory instances still exist.
-use std::fmt;
+/// # Ownership
+/// A `PageStore` is a handle to a reference-counted `PagePool`. Every Memory
+/// instance keeps the pool alive, so the store may be dropped before the
+/// Memory instances created from it.
+use crate::{
+    diff::DiffRange,
+    mmio::{MmioDevice, MmioRegion},
+};
+use std::{borrow::Cow, cell::UnsafeCell, fmt, ops::Range, rc::Rc};
 
 /// Success return code for memory operations
 pub const MEM_SUCCESS: i32 = 0;
@@ -36,6 +46,30 @@ pub const MEM_ERR_PAGE_LIMIT: i32 = 2;
 /// Error: PageStore has no available pages
 pub const MEM_ERR_NO_PAGES_AVAILABLE: i32 = 3;
 
+/// Error: Access violates the page permissions
+pub const MEM_ERR_PERMISSION: i32 = 4;
+
+/// Error: Address or length is not page-aligned
+pub const MEM_ERR_MISALIGNED: i32 = 5;
+
+/// Error: Range overlaps allocated pages or another host mapping
+pub const MEM_ERR_OVERLAP: i32 = 6;
+
+/// Error: Address lies in a guard region
+pub const MEM_ERR_GUARD: i32 = 7;
+
+/// Error: Page is shared read-only
+pub const MEM_ERR_READ_ONLY: i32 = 8;
+
+/// Error: No NUL terminator within the maximum string length
+pub const MEM_ERR_UNTERMINATED: i32 = 9;
+
+/// Error: String is not valid UTF-8 or contains an interior NUL
+pub const MEM_ERR_INVALID_STRING: i32 = 10;
+
+/// Error: Range extends past the end of the address space
+pub const MEM_ERR_OUT_OF_RANGE: i32 = 11;
+
 /// Size of a memory page in bytes (16KB)
 pub const PAGE_SIZE: usize = 1 << 14;
 
@@ -45,19 +79,22 @@ pub const PAGE_OFFSET_BITS: usize = 14;
 /// Mask for extracting page offset from address
 pub const PAGE_OFFSET_MASK: u32 = (PAGE_SIZE - 1) as u32;
 
+/// Mask for page numbers (address >> PAGE_OFFSET_BITS)
+const PAGE_NUMBER_MASK: u32 = u32::MAX >> PAGE_OFFSET_BITS;
+
 // Two-layer page table constants
 
 /// Number of bits for L1 index (bits 31-22 of address)
 const L1_INDEX_BITS: usize = 10;
 
 /// Number of bits for L2 index (bits 21-14 of address)
-const L2_INDEX_BITS: usize = 8;
+pub(crate) const L2_INDEX_BITS: usize = 8;
 
 /// Bit position where L1 index starts
-const L1_INDEX_SHIFT: usize = 22;
+pub(crate) const L1_INDEX_SHIFT: usize = 22;
 
 /// Bit position where L2 index starts (same as page offset)
-const L2_INDEX_SHIFT: usize = PAGE_OFFSET_BITS;
+pub(crate) const L2_INDEX_SHIFT: usize = PAGE_OFFSET_BITS;
 
 /// Number of entries in L1 table (2^10 = 1024)
 const L1_TABLE_SIZE: usize = 1 << L1_INDEX_BITS;
@@ -76,7 +113,7 @@ const L2_INDEX_MASK: u32 = (L2_TABLE_SIZE - 1) as u32;
 pub const MAX_L2_TABLES: usize = 255;
 
 /// Special value indicating an unmapped L2 table in L1 entries
-const UNMAPPED_L2_TABLE: u8 = 0xFF;
+pub(crate) const UNMAPPED_L2_TABLE: u8 = 0xFF;
 
 /// Maximum number of pages that can be allocated
 /// Limited to 65535 because UNMAPPED_PAGE uses the value 0xFFFF
@@ -87,10 +124,250 @@ pub const MAX_PAGES: usize = 65535;
 /// Uses 0xFFFF which is why MAX_PAGES must be one less
 pub const UNMAPPED_PAGE: u16 = 0xFFFF;
 
+/// Most bytes `Memory::dump()` shows; longer dumps are cut off
+pub const MAX_DUMP_LENGTH: u32 = 1 << 16;
+
+/// Permission bit: page may be read
+pub(crate) const PERMISSION_READ: u8 = 1 << 0;
+
+/// Permission bit: page may be written
+pub(crate) const PERMISSION_WRITE: u8 = 1 << 1;
+
+/// Permission bit: page may be executed
+const PERMISSION_EXECUTE: u8 = 1 << 2;
+
+/// Marks shared pages, which are never written even if `PERMISSION_WRITE` is set
+///
+/// Not part of `Permissions`; compiled stores check it alongside the write bit.
+pub(crate) const PERMISSION_SHARED: u8 = 1 << 3;
+
+/// Access rights of a guest page
+///
+/// Pages are readable, writable and executable until restricted with
+/// `Memory::protect`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Permissions {
+    /// Guest loads are allowed
+    pub read: bool,
+    /// Guest stores are allowed
+    pub write: bool,
+    /// Instruction fetches are allowed
+    pub execute: bool,
+}
+
+/// Errors returned by memory operations
+///
+/// Each variant corresponds to one of the `MEM_ERR_*` codes used by native code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryError {
+    /// No more L2 tables available
+    NoL2Tables,
+    /// Instance page limit reached
+    PageLimit,
+    /// PageStore has no available pages
+    NoPagesAvailable,
+    /// Access violates the page permissions
+    Permission,
+    /// Address or length is not page-aligned
+    Misaligned,
+    /// Range overlaps allocated pages or another host mapping
+    Overlap,
+    /// Address lies in a guard region
+    Guard,
+    /// Page is shared read-only
+    ReadOnly,
+    /// No NUL terminator within the maximum string length
+    Unterminated,
+    /// String is not valid UTF-8 or contains an interior NUL
+    InvalidString,
+    /// Range extends past the end of the address space
+    OutOfRange,
+    /// ABI-level result code that is not a `MEM_ERR_*` constant
+    Unknown(i32),
+}
+
+/// Kind of guest memory access
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Data load
+    Read,
+    /// Data store
+    Write,
+    /// Instruction fetch
+    Execute,
+}
+
+/// Usage counters of a Memory instance, for tuning `max_pages` and `max_l2_tables`
+///
+/// Counters accumulate across `reset()`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MemoryStats {
+    /// Pages allocated from the PageStore
+    pub pages_allocated: u64,
+    /// Highest number of pages allocated at once
+    pub peak_pages: usize,
+    /// Highest number of L2 tables in use at once
+    pub peak_l2_tables: usize,
+    /// Allocations that failed with `MemoryError::NoL2Tables`
+    pub no_l2_tables: u64,
+    /// Allocations that failed with `MemoryError::PageLimit`
+    pub page_limit: u64,
+    /// Allocations that failed with `MemoryError::NoPagesAvailable`
+    pub no_pages_available: u64,
+    /// Allocations refused inside a guard region
+    pub guard: u64,
+    /// Pages zeroed and returned to the pool by `reset()`
+    pub pages_zeroed: u64,
+}
+
+/// Usage counters of a PageStore across all of its Memory instances
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[repr(C)]
+pub struct PageStoreStats {
+    /// Highest number of pages taken from the pool at once
+    pub peak_pages: usize,
+    /// Requests that found the pool empty
+    pub exhausted: u64,
+    /// Pages zeroed and returned to the pool
+    pub pages_zeroed: u64,
+    /// Pages whose memory was released to the OS when returned to the pool
+    pub pages_released: u64,
+}
+
+/// OS-level tuning of the memory backing a PageStore
+///
+/// Both options are hints: they are ignored on platforms that do not support
+/// them, and pages always read as zeros when taken from the pool.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PageTuning {
+    /// Back the pool with transparent huge pages to reduce TLB pressure
+    /// (Linux only)
+    pub huge_pages: bool,
+    /// Release the memory of pages returned to the pool to the OS with
+    /// `madvise`, reducing the RSS of idle stores at the cost of a page fault
+    /// on next use. On Linux this splits any huge page containing the page.
+    pub release_on_return: bool,
+}
+
+/// Page allocation event reported to a PageStore's observer
+///
+/// `instance` is the id of the Memory involved (see `Memory::id()`), or None
+/// for pages backing `SharedPages`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PageEvent {
+    /// A page was taken from the pool
+    Allocated { instance: Option<usize>, page: u16 },
+    /// A page was returned to the pool
+    Freed { instance: Option<usize>, page: u16 },
+    /// A request for pages found the pool empty (counted in `PageStoreStats::exhausted`)
+    Exhausted { instance: Option<usize> },
+}
+
+/// Callback receiving a PageStore's `PageEvent`s
+pub type PageObserver = Box<dyn FnMut(PageEvent)>;
+
+/// Byte that redzones are filled with by `Memory::add_redzone()`
+pub const REDZONE_BYTE: u8 = 0xFD;
+
+/// Alignment of the pool memory when backed by huge pages
+const HUGE_PAGE_SIZE: usize = 2 << 20;
+
+/// Location of a guest byte in the PageStore's page memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PhysicalOffset {
+    /// Global page index in the PageStore
+    pub page: u16,
+    /// Byte offset from the start of the PageStore's page memory
+    pub offset: usize,
+}
+
+/// Occupancy of an allocated L2 table
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct L2TableInfo {
+    /// Index of the L1 entry pointing at the table
+    pub l1_index: usize,
+    /// Index of the table among the instance's L2 tables
+    pub table: u8,
+    /// Guest address of the first byte covered by the table
+    pub address: u32,
+    /// Number of entries mapped to a page
+    pub mapped: usize,
+}
+
+/// Backing of a region of guest memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegionKind {
+    /// Pages allocated from the PageStore for this instance
+    Private,
+    /// Read-only pages mapped with `Memory::map_shared`
+    Shared,
+    /// Host buffer mapped with `Memory::map_host`
+    Host,
+}
+
+/// Contiguous range of mapped guest pages with the same backing and permissions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Region {
+    /// Guest address of the first page
+    pub address: u32,
+    /// Number of pages
+    pub pages: u32,
+    /// Backing of the pages
+    pub kind: RegionKind,
+    /// Permissions of the pages
+    pub permissions: Permissions,
+}
+
+impl Region {
+    /// Size in bytes
+    pub fn size(&self) -> u64 {
+        self.pages as u64 * PAGE_SIZE as u64
+    }
+
+    /// Address one past the last byte (2^32 for a region ending at 0xFFFFFFFF)
+    pub fn end(&self) -> u64 {
+        self.address as u64 + self.size()
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "0x{:08x}-0x{:08x} {} {}",
+            self.address,
+            self.end() - 1,
+            self.permissions,
+            self.kind
+        )
+    }
+}
+
+impl fmt::Display for RegionKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            RegionKind::Private => "private",
+            RegionKind::Shared => "shared",
+            RegionKind::Host => "host",
+        };
+        write!(f, "{}", name)
+    }
+}
+
 /// Global page store that manages memory pages across all VM instances
+///
+/// A handle to the shared `PagePool`, whose counters it exposes through
+/// getters that copy them out. Memory instances created from the store share
+/// ownership of the pool, which is freed when the store and all of its
+/// Memory instances have been dropped.
+pub struct PageStore {
+    pool: Rc<UnsafeCell<PagePool>>,
+}
+
+/// Pool of pages shared by a PageStore and its Memory instances
 /// Pages are allocated from and returned to a pool
 #[repr(C)]
-pub struct PageStore {
+pub struct PagePool {
     /// Linear memory for all pages - allows direct offset calculation
     /// Page N starts at offset N * PAGE_SIZE (or N << 14)
     /// Offset: 0x00
@@ -116,6 +393,52 @@ pub struct PageStore {
     /// Number of Memory instances using this PageStore
     /// Offset: 0x28
     pub instance_count: usize,
+
+    /// Usage counters
+    /// Offset: 0x30
+    pub stats: PageStoreStats,
+
+    /// OS-level tuning of page_memory
+    tuning: PageTuning,
+
+    /// Size of the mmap reserved for page_memory, covering MAX_PAGES
+    mapping_size: usize,
+
+    /// Free pages promised to instances' minimums but not yet taken
+    reserved_pages: usize,
+
+    /// Page usage of each live Memory instance, indexed by instance id
+    instances: Vec<Option<InstanceUsage>>,
+
+    /// Callback fired on allocation, free and exhaustion
+    observer: Option<PageObserver>,
+
+    /// Events waiting to be reported once the pool is no longer borrowed
+    events: Vec<PageEvent>,
+
+    /// Whether the observer has been taken out of the pool to be called
+    observing: bool,
+
+    /// Whether `set_observer()` ran while the observer was being called
+    observer_replaced: bool,
+}
+
+/// Page usage of one Memory instance in its PageStore
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstanceUsage {
+    /// Instance id, as returned by `Memory::id()`
+    pub id: usize,
+    /// Pages currently taken from the pool
+    pub pages: usize,
+    /// Pages guaranteed to the instance, set with `Memory::set_min_pages()`
+    pub min_pages: usize,
+}
+
+impl InstanceUsage {
+    /// Guaranteed pages the instance has not taken yet
+    fn outstanding(&self) -> usize {
+        self.min_pages.saturating_sub(self.pages)
+    }
 }
 
 impl PageStore {
@@ -124,17 +447,40 @@ impl PageStore {
     /// # Panics
     /// Panics if total_pages > MAX_PAGES (65535)
     pub fn new(total_pages: usize) -> Self {
+        Self::with_tuning(total_pages, PageTuning::default())
+    }
+
+    /// Create a new page store whose memory is tuned with `tuning`
+    ///
+    /// # Panics
+    /// Panics if total_pages > MAX_PAGES (65535), or if the pool memory cannot
+    /// be mapped; `try_with_tuning()` returns these as errors
+    pub fn with_tuning(total_pages: usize, tuning: PageTuning) -> Self {
         assert!(
             total_pages <= MAX_PAGES,
             "total_pages {} exceeds maximum allowed ({})",
             total_pages,
             MAX_PAGES
         );
+        Self::try_with_tuning(total_pages, tuning)
+            .unwrap_or_else(|error| panic!("failed to map page pool: {}", error))
+    }
+
+    /// Create a new page store whose memory is tuned with `tuning`, failing
+    /// instead of panicking
+    ///
+    /// # Errors
+    /// - `MemoryError::PageLimit`: total_pages > MAX_PAGES (65535)
+    /// - `MemoryError::NoPagesAvailable`: The OS refused to map the pool memory
+    pub fn try_with_tuning(total_pages: usize, tuning: PageTuning) -> Result<Self, MemoryError> {
+        if total_pages > MAX_PAGES {
+            return Err(MemoryError::PageLimit);
+        }
 
-        // Pre-allocate linear memory for all pages
+        // Reserve address space for MAX_PAGES so that grow() never moves the
+        // pages, and make the first total_pages accessible
         let total_bytes = total_pages * PAGE_SIZE;
-        let page_memory = vec![0u8; total_bytes].into_boxed_slice();
-        let page_memory_ptr = Box::into_raw(page_memory) as *mut u8;
+        let (page_memory_ptr, mapping_size) = map_pool(total_bytes, tuning.huge_pages)?;
 
         // Initialize available pages array [0, 1, 2, ..., total_pages-1]
         let mut available_pages = Vec::with_capacity(total_pages);
@@ -144,35 +490,450 @@ impl PageStore {
         let available_pages = available_pages.into_boxed_slice();
         let available_pages_ptr = Box::into_raw(available_pages) as *mut u16;
 
-        Self {
+        let pool = PagePool {
             page_memory: page_memory_ptr,
             page_memory_size: total_bytes,
             available_pages: available_pages_ptr,
             available_pages_capacity: total_pages,
             num_available_pages: total_pages,
             instance_count: 0,
+            stats: PageStoreStats::default(),
+            tuning,
+            mapping_size,
+            reserved_pages: 0,
+            instances: Vec::new(),
+            observer: None,
+            events: Vec::new(),
+            observing: false,
+            observer_replaced: false,
+        };
+        Ok(Self {
+            pool: Rc::new(UnsafeCell::new(pool)),
+        })
+    }
+}
+
+/// Reserve address space for a page pool of MAX_PAGES and make the first
+/// `size` bytes accessible (zeroed)
+///
+/// Returns the start of the pool and the size of the mapping. The rest of the
+/// mapping is inaccessible until committed by `PageStore::grow()`. With
+/// `huge_pages` the pool is aligned to HUGE_PAGE_SIZE and advised as
+/// MADV_HUGEPAGE where supported. Fails with `MemoryError::NoPagesAvailable`
+/// if the OS refuses the mapping or the first `size` bytes.
+fn map_pool(size: usize, huge_pages: bool) -> Result<(*mut u8, usize), MemoryError> {
+    let reserved = (MAX_PAGES * PAGE_SIZE).next_multiple_of(HUGE_PAGE_SIZE);
+    let padding = if huge_pages { HUGE_PAGE_SIZE } else { 0 };
+    unsafe {
+        let ptr = libc::mmap(
+            std::ptr::null_mut(),
+            reserved + padding,
+            libc::PROT_NONE,
+            libc::MAP_PRIVATE | libc::MAP_ANON,
+            -1,
+            0,
+        );
+        if ptr == libc::MAP_FAILED {
+            return Err(MemoryError::NoPagesAvailable);
+        }
+        let ptr = ptr as *mut u8;
+
+        // Trim the unaligned head and the tail of the padding
+        let head = ptr.align_offset(HUGE_PAGE_SIZE).min(padding);
+        let start = ptr.add(head);
+        if head > 0 {
+            libc::munmap(ptr as *mut libc::c_void, head);
+        }
+        if padding > head {
+            libc::munmap(start.add(reserved) as *mut libc::c_void, padding - head);
+        }
+
+        #[cfg(target_os = "linux")]
+        if huge_pages {
+            libc::madvise(start as *mut libc::c_void, reserved, libc::MADV_HUGEPAGE);
+        }
+        if !commit(start, size) {
+            libc::munmap(start as *mut libc::c_void, reserved);
+            return Err(MemoryError::NoPagesAvailable);
+        }
+        Ok((start, reserved))
+    }
+}
+
+/// Make `size` bytes of a reserved pool mapping readable and writable
+///
+/// # Safety
+/// `start` must be page-aligned and `[start, start + size)` inside a pool mapping.
+unsafe fn commit(start: *mut u8, size: usize) -> bool {
+    size == 0
+        || unsafe {
+            libc::mprotect(
+                start as *mut libc::c_void,
+                size,
+                libc::PROT_READ | libc::PROT_WRITE,
+            ) == 0
+        }
+}
+
+impl PageStore {
+    /// Copy a value out of the pool
+    ///
+    /// The pool is only mutated by PageStore and Memory methods, none of
+    /// which `read` can run while it holds the borrow, and the value cannot
+    /// borrow from the pool.
+    fn read<T>(&self, read: impl FnOnce(&PagePool) -> T) -> T {
+        read(unsafe { &*self.pool.get() })
+    }
+
+    /// Pages currently free in the pool
+    pub fn available_pages(&self) -> usize {
+        self.read(|pool| pool.num_available_pages)
+    }
+
+    /// Pages in the pool, free or not
+    pub fn total_pages(&self) -> usize {
+        self.read(|pool| pool.available_pages_capacity)
+    }
+
+    /// Number of live Memory instances created from the store
+    pub fn instance_count(&self) -> usize {
+        self.read(|pool| pool.instance_count)
+    }
+
+    /// Start of the page memory; page N starts N * PAGE_SIZE bytes in
+    pub fn page_memory(&self) -> *mut u8 {
+        self.read(|pool| pool.page_memory)
+    }
+
+    /// Usage counters across all Memory instances and shared pages
+    pub fn stats(&self) -> PageStoreStats {
+        self.read(|pool| pool.stats)
+    }
+
+    /// OS-level tuning of the pool memory
+    pub fn tuning(&self) -> PageTuning {
+        self.read(|pool| pool.tuning)
+    }
+
+    /// Set the callback fired on every page allocation, free and exhaustion,
+    /// replacing any previous one
+    ///
+    /// Events are reported once the operation causing them has updated the
+    /// pool, so the callback may use the store; events it causes itself are
+    /// reported after it returns. Pages taken or returned by native code are
+    /// not reported.
+    pub fn set_observer(&self, observer: Option<PageObserver>) {
+        let pool = unsafe { &mut *self.pool.get() };
+        pool.observer = observer;
+        pool.observer_replaced = true;
+    }
+
+    /// Another handle to the same pool
+    pub(crate) fn handle(&self) -> PageStore {
+        PageStore {
+            pool: Rc::clone(&self.pool),
+        }
+    }
+
+    /// Add `additional_pages` free pages to the pool
+    ///
+    /// Page memory never moves, so Memory instances created from the store
+    /// keep working and can use the new pages immediately.
+    ///
+    /// # Errors
+    /// - `MemoryError::PageLimit`: The pool would exceed MAX_PAGES (65535)
+    /// - `MemoryError::NoPagesAvailable`: The OS refused to commit the memory
+    pub fn grow(&self, additional_pages: usize) -> Result<(), MemoryError> {
+        // No borrow of the pool outlives a PageStore or Memory call and the
+        // pool is not shared across threads, so this borrow is the only one
+        let pool = unsafe { &mut *self.pool.get() };
+        let old_pages = pool.available_pages_capacity;
+        let new_pages = old_pages
+            .checked_add(additional_pages)
+            .filter(|pages| *pages <= MAX_PAGES)
+            .ok_or(MemoryError::PageLimit)?;
+        let committed = unsafe {
+            commit(
+                pool.page_memory.add(pool.page_memory_size),
+                additional_pages * PAGE_SIZE,
+            )
+        };
+        if !committed {
+            return Err(MemoryError::NoPagesAvailable);
+        }
+
+        // Move the free indices into a larger array and append the new pages
+        let mut available_pages = Vec::with_capacity(new_pages);
+        unsafe {
+            available_pages.extend_from_slice(std::slice::from_raw_parts(
+                pool.available_pages,
+                pool.num_available_pages,
+            ));
+            drop(Box::from_raw(std::slice::from_raw_parts_mut(
+                pool.available_pages,
+                old_pages,
+            )));
+        }
+        available_pages.extend((old_pages..new_pages).map(|page| page as u16));
+        available_pages.resize(new_pages, 0);
+        pool.num_available_pages += additional_pages;
+        pool.available_pages = Box::into_raw(available_pages.into_boxed_slice()) as *mut u16;
+        pool.available_pages_capacity = new_pages;
+        pool.page_memory_size = new_pages * PAGE_SIZE;
+        Ok(())
+    }
+
+    /// Free pages held back for instances' guaranteed minimums
+    pub fn reserved_pages(&self) -> usize {
+        self.read(|pool| pool.reserved_pages)
+    }
+
+    /// Free pages any instance may take
+    pub fn unreserved_pages(&self) -> usize {
+        self.read(|pool| pool.num_available_pages - pool.reserved_pages)
+    }
+
+    /// Page usage of each live Memory instance, in id order
+    pub fn instances(&self) -> Vec<InstanceUsage> {
+        self.read(|pool| pool.instances.iter().flatten().copied().collect())
+    }
+
+    /// Copy `data` into pages that Memory instances can map read-only with
+    /// `Memory::map_shared()`
+    ///
+    /// The data is zero-padded to a whole number of pages. The pages are taken
+    /// from the pool once, however many instances map them, and return to the
+    /// pool when the `SharedPages` and all of its mappings have been dropped.
+    ///
+    /// # Errors
+    /// - `MemoryError::NoPagesAvailable`: Not enough pages in the pool
+    pub fn share(&self, data: &[u8]) -> Result<SharedPages, MemoryError> {
+        let count = data.len().div_ceil(PAGE_SIZE);
+        let pool = unsafe { &mut *self.pool.get() };
+        if count > pool.num_available_pages - pool.reserved_pages {
+            pool.stats.exhausted += 1;
+            pool.notify(PageEvent::Exhausted { instance: None });
+            unsafe { PagePool::flush(self.pool.get()) };
+            return Err(MemoryError::NoPagesAvailable);
+        }
+        // The check above leaves enough unreserved pages for every take
+        let pages: Vec<u16> = (0..count).filter_map(|_| pool.take_page(None)).collect();
+        debug_assert_eq!(pages.len(), count);
+        for (chunk, &page_idx) in data.chunks(PAGE_SIZE).zip(&pages) {
+            unsafe {
+                let page_addr = pool.page_memory.add(page_idx as usize * PAGE_SIZE);
+                std::ptr::copy_nonoverlapping(chunk.as_ptr(), page_addr, chunk.len());
+            }
+        }
+        unsafe { PagePool::flush(self.pool.get()) };
+        Ok(SharedPages {
+            inner: Rc::new(SharedInner {
+                pool: Rc::clone(&self.pool),
+                pages,
+            }),
+        })
+    }
+}
+
+impl PagePool {
+    /// Take a free page index from the pool on behalf of an instance
+    ///
+    /// Pages reserved for other instances' minimums are not handed out.
+    fn take_page(&mut self, instance: Option<usize>) -> Option<u16> {
+        debug_assert!(instance.is_none_or(|id| self.instances[id].is_some()));
+        let usage = instance.and_then(|id| self.instances[id].as_mut());
+        let guaranteed = usage.as_ref().is_some_and(|usage| usage.outstanding() > 0);
+        if !guaranteed && self.num_available_pages <= self.reserved_pages {
+            self.stats.exhausted += 1;
+            self.notify(PageEvent::Exhausted { instance });
+            return None;
+        }
+        if let Some(usage) = usage {
+            usage.pages += 1;
+        }
+        if guaranteed {
+            self.reserved_pages -= 1;
+        }
+        self.num_available_pages -= 1;
+        let in_use = self.available_pages_capacity - self.num_available_pages;
+        self.stats.peak_pages = self.stats.peak_pages.max(in_use);
+        let page = unsafe { *self.available_pages.add(self.num_available_pages) };
+        self.notify(PageEvent::Allocated { instance, page });
+        Some(page)
+    }
+
+    /// Zero a page and return it to the pool on behalf of an instance
+    fn return_page(&mut self, page_idx: u16, instance: Option<usize>) {
+        debug_assert!(instance.is_none_or(|id| self.instances[id].is_some()));
+        if let Some(usage) = instance.and_then(|id| self.instances[id].as_mut()) {
+            usage.pages -= 1;
+            if usage.outstanding() > 0 {
+                self.reserved_pages += 1;
+            }
+        }
+        self.zero_page(page_idx);
+        unsafe {
+            *self.available_pages.add(self.num_available_pages) = page_idx;
+        }
+        self.num_available_pages += 1;
+        self.notify(PageEvent::Freed {
+            instance,
+            page: page_idx,
+        });
+    }
+
+    /// Queue an event for the observer, if any
+    fn notify(&mut self, event: PageEvent) {
+        if self.observer.is_some() || self.observing {
+            self.events.push(event);
+        }
+    }
+
+    /// Report queued events to the observer
+    ///
+    /// The observer is taken out of the pool while it runs, so it can use the
+    /// store: events it causes are queued and reported when it returns, and
+    /// an observer it sets replaces it for the events not reported yet.
+    ///
+    /// # Safety
+    /// `pool` must be valid, with no reference into it live.
+    unsafe fn flush(pool: *mut PagePool) {
+        loop {
+            let (mut observer, events) = {
+                let pool = unsafe { &mut *pool };
+                if pool.events.is_empty() || pool.observing {
+                    return;
+                }
+                let Some(observer) = pool.observer.take() else {
+                    pool.events.clear();
+                    return;
+                };
+                pool.observing = true;
+                pool.observer_replaced = false;
+                (observer, std::mem::take(&mut pool.events))
+            };
+            let mut events = events.into_iter();
+            for event in events.by_ref() {
+                observer(event);
+                if unsafe { (*pool).observer_replaced } {
+                    break;
+                }
+            }
+            let pool = unsafe { &mut *pool };
+            pool.observing = false;
+            if !pool.observer_replaced {
+                pool.observer = Some(observer);
+            } else if pool.observer.is_some() {
+                // Events not reported yet go to the new observer
+                pool.events.splice(0..0, events);
+            } else {
+                pool.events.clear();
+            }
+        }
+    }
+
+    /// Zero a page, releasing its memory to the OS if the pool is tuned to
+    fn zero_page(&mut self, page_idx: u16) {
+        unsafe {
+            let page_ptr = self.page_memory.add(page_idx as usize * PAGE_SIZE);
+            if self.tuning.release_on_return {
+                release_page(page_ptr);
+                self.stats.pages_released += 1;
+            } else {
+                std::ptr::write_bytes(page_ptr, 0, PAGE_SIZE);
+            }
         }
+        self.stats.pages_zeroed += 1;
+    }
+}
+
+/// Read-only pages in a PageStore that many Memory instances can map
+///
+/// Created with `PageStore::share()`. Cloning is cheap and shares the pages.
+#[derive(Clone)]
+pub struct SharedPages {
+    inner: Rc<SharedInner>,
+}
+
+/// Pages owned by a `SharedPages` and its clones
+struct SharedInner {
+    /// Keeps the pool alive
+    pool: Rc<UnsafeCell<PagePool>>,
+    /// Global page indices, in address order
+    pages: Vec<u16>,
+}
+
+impl SharedPages {
+    /// Number of pages
+    pub fn pages(&self) -> usize {
+        self.inner.pages.len()
+    }
+
+    /// Length in bytes (a multiple of PAGE_SIZE)
+    pub fn len(&self) -> usize {
+        self.pages() * PAGE_SIZE
+    }
+
+    /// Check if there are no pages
+    pub fn is_empty(&self) -> bool {
+        self.inner.pages.is_empty()
+    }
+}
+
+impl fmt::Debug for SharedPages {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SharedPages")
+            .field("pages", &self.pages())
+            .field("mappings", &(Rc::strong_count(&self.inner) - 1))
+            .finish()
     }
 }
 
-impl Drop for PageStore {
+impl Drop for SharedInner {
     fn drop(&mut self) {
-        if self.instance_count > 0 {
-            panic!(
-                "PageStore dropped while {} Memory instance(s) still exist",
-                self.instance_count
-            );
+        let pool = unsafe { &mut *self.pool.get() };
+        for &page_idx in &self.pages {
+            pool.return_page(page_idx, None);
+        }
+        unsafe { PagePool::flush(self.pool.get()) };
+    }
+}
+
+/// Release the memory of a page to the OS, leaving it zeroed
+///
+/// Pages are only released when they are made of whole OS pages; with larger
+/// OS pages the advice would fail or reach into neighbouring pages, so the
+/// page is just zeroed.
+///
+/// # Safety
+/// `page_ptr` must point to a page of a mapped pool.
+unsafe fn release_page(page_ptr: *mut u8) {
+    let page = page_ptr as *mut libc::c_void;
+    let os_page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+    let releasable = os_page_size > 0 && PAGE_SIZE % os_page_size as usize == 0;
+    unsafe {
+        // Private anonymous pages read as zeros after MADV_DONTNEED
+        #[cfg(target_os = "linux")]
+        if !releasable || libc::madvise(page, PAGE_SIZE, libc::MADV_DONTNEED) != 0 {
+            std::ptr::write_bytes(page_ptr, 0, PAGE_SIZE);
+        }
+
+        // MADV_FREE pages keep their contents until reclaimed, so zero first
+        #[cfg(not(target_os = "linux"))]
+        {
+            std::ptr::write_bytes(page_ptr, 0, PAGE_SIZE);
+            if releasable {
+                libc::madvise(page, PAGE_SIZE, libc::MADV_FREE);
+            }
         }
+    }
+}
 
+impl Drop for PagePool {
+    fn drop(&mut self) {
         // Clean up allocated memory
         unsafe {
-            if !self.page_memory.is_null() {
-                let page_memory = Box::from_raw(std::slice::from_raw_parts_mut(
-                    self.page_memory,
-                    self.page_memory_size,
-                ));
-                drop(page_memory);
-            }
+            libc::munmap(self.page_memory as *mut libc::c_void, self.mapping_size);
 
             if !self.available_pages.is_null() {
                 let available_pages = Box::from_raw(std::slice::from_raw_parts_mut(
@@ -191,12 +952,12 @@ impl Drop for PageStore {
 /// address space usage, which is common in embedded and sandboxed environments.
 #[repr(C)]
 pub struct Memory {
-    /// Pointer to the PageStore - PageStore must outlive this Memory
+    /// Pointer to the shared PagePool, kept alive by `pool`
     /// Offset: 0x000
-    pub page_store: *mut PageStore,
+    pub page_store: *mut PagePool,
 
     /// Cached pointer to the start of page memory for fast access
-    /// This points to the same memory as PageStore.page_memory
+    /// This points to the same memory as PagePool.page_memory
     /// Offset: 0x008
     pub page_memory: *mut u8,
 
@@ -233,65 +994,508 @@ pub struct Memory {
     /// Maximum number of L2 tables this VM instance can allocate
     /// Offset: 0x438
     pub max_l2_tables: usize,
-}
 
-impl Memory {
-    /// Create a new memory system that uses the provided page store
-    ///
-    /// # Safety
-    /// The PageStore must outlive this Memory instance
-    ///
-    /// # Panics
-    /// - Panics if max_pages > MAX_PAGES (65535)
-    /// - Panics if max_pages > PageStore's available pages
-    /// - Panics if max_l2_tables > MAX_L2_TABLES (255)
-    pub fn new(page_store: &mut PageStore, max_pages: usize, max_l2_tables: usize) -> Self {
-        assert!(
-            max_pages <= MAX_PAGES,
-            "max_pages {} exceeds maximum allowed ({})",
-            max_pages,
-            MAX_PAGES
-        );
-        assert!(
-            max_pages <= page_store.num_available_pages,
-            "max_pages {} exceeds available pages in PageStore ({})",
-            max_pages,
-            page_store.num_available_pages
-        );
-        assert!(
-            max_l2_tables <= MAX_L2_TABLES,
-            "max_l2_tables {} exceeds maximum allowed ({})",
-            max_l2_tables,
-            MAX_L2_TABLES
-        );
+    /// Permission bits for each L2 table entry, laid out like `l2_tables`
+    /// Pages without an L2 table have all permissions
+    /// Offset: 0x440
+    pub page_permissions: *mut u8,
 
-        page_store.instance_count += 1;
+    /// Shared ownership of the pool behind `page_store`
+    /// Offset: 0x448
+    pool: Rc<UnsafeCell<PagePool>>,
 
-        // Allocate L2 tables as contiguous array
-        // Each table is L2_TABLE_SIZE entries, all tables in a row
-        let total_l2_entries = max_l2_tables * L2_TABLE_SIZE;
-        let l2_tables = vec![UNMAPPED_PAGE; total_l2_entries].into_boxed_slice();
-        let l2_tables_ptr = Box::into_raw(l2_tables) as *mut u16;
+    /// Pages that may currently be allocated, at most `max_pages`
+    /// Lowered by `Memory64` so its windows share one budget
+    pub(crate) page_limit: usize,
 
-        // Allocate allocated_indices array
-        let allocated_indices = vec![0u16; max_pages].into_boxed_slice();
-        let allocated_indices_ptr = Box::into_raw(allocated_indices) as *mut u16;
+    /// L2 tables that may currently be allocated, at most `max_l2_tables`
+    pub(crate) l2_table_limit: usize,
 
-        Self {
-            page_store: page_store as *mut PageStore,
-            page_memory: page_store.page_memory,
-            l1_table: [UNMAPPED_L2_TABLE; L1_TABLE_SIZE],
-            l2_tables: l2_tables_ptr,
+    /// Host buffers mapped into the guest address space, checked before the page tables
+    host_mappings: Vec<HostMapping>,
+
+    /// Ranges that are never allocated; kept across `reset()`
+    guard_regions: Vec<GuardRegion>,
+
+    /// Device ranges serviced by host callbacks; kept across `reset()`
+    mmio_regions: Vec<MmioRegion>,
+
+    /// Reservation held by the last load-reserved access, if still valid
+    reservation: Option<Reservation>,
+
+    /// Index of this instance's usage entry in the PageStore
+    id: usize,
+
+    /// Page-aligned start of the linear heap
+    heap_base: u32,
+
+    /// Current heap size in pages; the heap ends at `brk()`
+    heap_pages: u32,
+
+    /// Maximum heap size in pages
+    heap_limit: u32,
+
+    /// Usage counters
+    stats: MemoryStats,
+
+    /// Shared read-only pages mapped into the L2 tables, by guest address,
+    /// with the permission bits of each page before it was mapped
+    shared_mappings: Vec<(u32, SharedPages, Vec<u8>)>,
+
+    /// Byte freshly allocated pages are filled with, if poisoning is enabled
+    poison: Option<u8>,
+
+    /// Ranges filled with REDZONE_BYTE that must not be written, as (address, len)
+    redzones: Vec<(u32, u32)>,
+
+    /// Overwritten redzone bytes found by `reset()` and `soft_reset()`
+    redzone_violations: Vec<DiffRange>,
+}
+
+/// A page-granular range of guest addresses that always faults
+#[derive(Debug, Clone, Copy)]
+struct GuardRegion {
+    /// Page number (address >> PAGE_OFFSET_BITS) of the first page
+    first_page: u32,
+    /// Number of pages
+    pages: u32,
+}
+
+impl GuardRegion {
+    /// Check if the page containing `address` is inside this region
+    fn contains(&self, address: u32) -> bool {
+        let page = address >> PAGE_OFFSET_BITS;
+        (page.wrapping_sub(self.first_page) & PAGE_NUMBER_MASK) < self.pages
+    }
+}
+
+/// Reservation set registered by a load-reserved access
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reservation {
+    /// Guest address of the first reserved byte
+    pub address: u32,
+    /// Number of reserved bytes
+    pub size: u32,
+}
+
+impl Reservation {
+    /// Check if `[address, address + len)` shares at least one byte with the reservation
+    fn overlaps(&self, address: u32, len: u32) -> bool {
+        len > 0
+            && self.size > 0
+            && (address.wrapping_sub(self.address) < self.size
+                || self.address.wrapping_sub(address) < len)
+    }
+}
+
+/// A host buffer mapped at a page-aligned guest address
+#[derive(Debug, Clone, Copy)]
+struct HostMapping {
+    /// Guest address of the first byte
+    address: u32,
+    /// Length in bytes, a multiple of PAGE_SIZE
+    len: usize,
+    /// Host pointer to the first byte
+    data: *mut u8,
+}
+
+impl HostMapping {
+    /// Host pointer for a guest address, if inside this mapping
+    fn pointer(&self, address: u32) -> Option<*mut u8> {
+        let offset = address.wrapping_sub(self.address) as usize;
+        (offset < self.len).then(|| unsafe { self.data.add(offset) })
+    }
+}
+
+impl Permissions {
+    /// Read, write and execute (the default)
+    pub const ALL: Permissions = Permissions {
+        read: true,
+        write: true,
+        execute: true,
+    };
+
+    /// Read and write, for data and stack
+    pub const READ_WRITE: Permissions = Permissions {
+        read: true,
+        write: true,
+        execute: false,
+    };
+
+    /// Read and execute, for code
+    pub const READ_EXECUTE: Permissions = Permissions {
+        read: true,
+        write: false,
+        execute: true,
+    };
+
+    /// Read only, for constants
+    pub const READ_ONLY: Permissions = Permissions {
+        read: true,
+        write: false,
+        execute: false,
+    };
+
+    /// No access
+    pub const NONE: Permissions = Permissions {
+        read: false,
+        write: false,
+        execute: false,
+    };
+
+    /// Check if the permissions allow an access
+    pub fn allows(&self, access: Access) -> bool {
+        match access {
+            Access::Read => self.read,
+            Access::Write => self.write,
+            Access::Execute => self.execute,
+        }
+    }
+
+    /// Pack into permission bits
+    fn bits(&self) -> u8 {
+        (self.read as u8 * PERMISSION_READ)
+            | (self.write as u8 * PERMISSION_WRITE)
+            | (self.execute as u8 * PERMISSION_EXECUTE)
+    }
+
+    /// Unpack from permission bits
+    fn from_bits(bits: u8) -> Self {
+        Permissions {
+            read: bits & PERMISSION_READ != 0,
+            write: bits & PERMISSION_WRITE != 0,
+            execute: bits & PERMISSION_EXECUTE != 0,
+        }
+    }
+}
+
+impl fmt::Display for Permissions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flag = |set, c| if set { c } else { '-' };
+        write!(
+            f,
+            "{}{}{}",
+            flag(self.read, 'r'),
+            flag(self.write, 'w'),
+            flag(self.execute, 'x')
+        )
+    }
+}
+
+impl MemoryError {
+    /// ABI-level error code (`MEM_ERR_*`, or the code an `Unknown` error carries)
+    pub fn code(&self) -> i32 {
+        match self {
+            MemoryError::NoL2Tables => MEM_ERR_NO_L2_TABLES,
+            MemoryError::PageLimit => MEM_ERR_PAGE_LIMIT,
+            MemoryError::NoPagesAvailable => MEM_ERR_NO_PAGES_AVAILABLE,
+            MemoryError::Permission => MEM_ERR_PERMISSION,
+            MemoryError::Misaligned => MEM_ERR_MISALIGNED,
+            MemoryError::Overlap => MEM_ERR_OVERLAP,
+            MemoryError::Guard => MEM_ERR_GUARD,
+            MemoryError::ReadOnly => MEM_ERR_READ_ONLY,
+            MemoryError::Unterminated => MEM_ERR_UNTERMINATED,
+            MemoryError::InvalidString => MEM_ERR_INVALID_STRING,
+            MemoryError::OutOfRange => MEM_ERR_OUT_OF_RANGE,
+            MemoryError::Unknown(code) => *code,
+        }
+    }
+
+    /// Convert an ABI-level result code, where `MEM_SUCCESS` is `Ok` and
+    /// codes that are not a `MEM_ERR_*` constant are `MemoryError::Unknown`
+    pub fn from_code(code: i32) -> Result<(), MemoryError> {
+        match code {
+            MEM_SUCCESS => Ok(()),
+            MEM_ERR_NO_L2_TABLES => Err(MemoryError::NoL2Tables),
+            MEM_ERR_PAGE_LIMIT => Err(MemoryError::PageLimit),
+            MEM_ERR_NO_PAGES_AVAILABLE => Err(MemoryError::NoPagesAvailable),
+            MEM_ERR_PERMISSION => Err(MemoryError::Permission),
+            MEM_ERR_MISALIGNED => Err(MemoryError::Misaligned),
+            MEM_ERR_OVERLAP => Err(MemoryError::Overlap),
+            MEM_ERR_GUARD => Err(MemoryError::Guard),
+            MEM_ERR_READ_ONLY => Err(MemoryError::ReadOnly),
+            MEM_ERR_UNTERMINATED => Err(MemoryError::Unterminated),
+            MEM_ERR_INVALID_STRING => Err(MemoryError::InvalidString),
+            MEM_ERR_OUT_OF_RANGE => Err(MemoryError::OutOfRange),
+            code => Err(MemoryError::Unknown(code)),
+        }
+    }
+}
+
+impl fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MemoryError::NoL2Tables => write!(f, "No more L2 tables available"),
+            MemoryError::PageLimit => write!(f, "Instance page limit reached"),
+            MemoryError::NoPagesAvailable => write!(f, "Page store has no available pages"),
+            MemoryError::Permission => write!(f, "Access violates page permissions"),
+            MemoryError::Misaligned => write!(f, "Range is not page-aligned"),
+            MemoryError::Overlap => write!(f, "Range overlaps mapped memory"),
+            MemoryError::Guard => write!(f, "Address is in a guard region"),
+            MemoryError::ReadOnly => write!(f, "Page is shared read-only"),
+            MemoryError::Unterminated => write!(f, "String is not NUL-terminated"),
+            MemoryError::InvalidString => write!(f, "String is not valid"),
+            MemoryError::OutOfRange => write!(f, "Range extends past the address space"),
+            MemoryError::Unknown(code) => write!(f, "Unknown memory error code {}", code),
+        }
+    }
+}
+
+impl std::error::Error for MemoryError {}
+
+impl fmt::Display for Access {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Access::Read => "read",
+            Access::Write => "write",
+            Access::Execute => "execute",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Memory {
+    /// Create a new memory system that uses the provided page store
+    ///
+    /// The Memory shares ownership of the store's pool, so it may outlive the store.
+    ///
+    /// # Panics
+    /// - Panics if max_pages > MAX_PAGES (65535)
+    /// - Panics if max_pages > PageStore's available pages
+    /// - Panics if max_l2_tables > MAX_L2_TABLES (255)
+    pub fn new(page_store: &PageStore, max_pages: usize, max_l2_tables: usize) -> Self {
+        assert!(
+            max_pages <= MAX_PAGES,
+            "max_pages {} exceeds maximum allowed ({})",
+            max_pages,
+            MAX_PAGES
+        );
+        assert!(
+            max_pages <= page_store.available_pages(),
+            "max_pages {} exceeds available pages in PageStore ({})",
+            max_pages,
+            page_store.available_pages()
+        );
+        assert!(
+            max_l2_tables <= MAX_L2_TABLES,
+            "max_l2_tables {} exceeds maximum allowed ({})",
+            max_l2_tables,
+            MAX_L2_TABLES
+        );
+
+        let pool = Rc::clone(&page_store.pool);
+        let pool_ptr = pool.get();
+        let id = unsafe {
+            let pool = &mut *pool_ptr;
+            pool.instance_count += 1;
+            let id = pool
+                .instances
+                .iter()
+                .position(Option::is_none)
+                .unwrap_or(pool.instances.len());
+            let usage = Some(InstanceUsage {
+                id,
+                pages: 0,
+                min_pages: 0,
+            });
+            match pool.instances.get_mut(id) {
+                Some(slot) => *slot = usage,
+                None => pool.instances.push(usage),
+            }
+            id
+        };
+
+        // Allocate L2 tables as contiguous array
+        // Each table is L2_TABLE_SIZE entries, all tables in a row
+        let total_l2_entries = max_l2_tables * L2_TABLE_SIZE;
+        let l2_tables = vec![UNMAPPED_PAGE; total_l2_entries].into_boxed_slice();
+        let l2_tables_ptr = Box::into_raw(l2_tables) as *mut u16;
+
+        // Allocate allocated_indices array
+        let allocated_indices = vec![0u16; max_pages].into_boxed_slice();
+        let allocated_indices_ptr = Box::into_raw(allocated_indices) as *mut u16;
+
+        // Allocate permission bits alongside the L2 tables
+        let permission_bits = Permissions::ALL.bits();
+        let page_permissions = vec![permission_bits; total_l2_entries].into_boxed_slice();
+        let page_permissions_ptr = Box::into_raw(page_permissions) as *mut u8;
+
+        Self {
+            page_store: pool_ptr,
+            page_memory: page_store.page_memory(),
+            l1_table: [UNMAPPED_L2_TABLE; L1_TABLE_SIZE],
+            l2_tables: l2_tables_ptr,
             allocated_indices: allocated_indices_ptr,
             num_pages: 0,
             max_pages,
             num_l2_tables: 0,
             max_l2_tables,
+            page_permissions: page_permissions_ptr,
+            pool,
+            page_limit: max_pages,
+            l2_table_limit: max_l2_tables,
+            host_mappings: Vec::new(),
+            guard_regions: Vec::new(),
+            mmio_regions: Vec::new(),
+            reservation: None,
+            id,
+            heap_base: 0,
+            heap_pages: 0,
+            heap_limit: u32::MAX,
+            stats: MemoryStats::default(),
+            shared_mappings: Vec::new(),
+            poison: None,
+            redzones: Vec::new(),
+            redzone_violations: Vec::new(),
+        }
+    }
+
+    /// Map an L2 table for the given L1 index, returning its table index
+    ///
+    /// Returns `None` if the L1 entry is unmapped and no more L2 tables are available.
+    fn map_l2_table(&mut self, l1_idx: usize) -> Option<u8> {
+        if self.l1_table[l1_idx] != UNMAPPED_L2_TABLE {
+            return Some(self.l1_table[l1_idx]);
+        }
+        if self.num_l2_tables >= self.l2_table_limit {
+            return None;
+        }
+
+        let new_l2_idx = self.num_l2_tables as u8;
+        self.l1_table[l1_idx] = new_l2_idx;
+
+        // L2 table is already initialized with UNMAPPED_PAGE values
+        self.num_l2_tables += 1;
+        self.stats.peak_l2_tables = self.stats.peak_l2_tables.max(self.num_l2_tables);
+        Some(new_l2_idx)
+    }
+
+    /// Set the permissions of every page overlapping `[address, address + len)`
+    ///
+    /// Pages do not need to be allocated; the permissions apply once they are.
+    /// Permissions are cleared back to `Permissions::ALL` by `reset()`.
+    ///
+    /// # Errors
+    /// - `MemoryError::NoL2Tables`: No more L2 tables available to hold the permissions
+    pub fn protect(
+        &mut self,
+        address: u32,
+        len: u32,
+        permissions: Permissions,
+    ) -> Result<(), MemoryError> {
+        for page_base in pages(address, len) {
+            let l1_idx = ((page_base >> L1_INDEX_SHIFT) & L1_INDEX_MASK) as usize;
+            let l2_idx = ((page_base >> L2_INDEX_SHIFT) & L2_INDEX_MASK) as usize;
+            let l2_table_idx = self.map_l2_table(l1_idx).ok_or(MemoryError::NoL2Tables)?;
+            let shared = if self.shared(page_base) {
+                PERMISSION_SHARED
+            } else {
+                0
+            };
+            unsafe {
+                let l2_entry_offset = (l2_table_idx as usize) * L2_TABLE_SIZE + l2_idx;
+                *self.page_permissions.add(l2_entry_offset) = permissions.bits() | shared;
+            }
+        }
+        Ok(())
+    }
+
+    /// Change the permissions of `[address, address + len)` at the guest's request
+    ///
+    /// Follows `mprotect(2)`: `address` must be page-aligned and `len` is
+    /// rounded up to whole pages. Unlike `protect()`, the guest cannot make
+    /// shared pages writable or change guard regions, and the whole range is
+    /// checked before any page changes.
+    ///
+    /// # Errors
+    /// - `MemoryError::Misaligned`: `address` is not a multiple of `PAGE_SIZE`
+    /// - `MemoryError::OutOfRange`: The range wraps past 0xFFFFFFFF
+    /// - `MemoryError::ReadOnly`: Write permission requested for a shared page
+    /// - `MemoryError::Guard`: At least one page lies in a guard region
+    /// - `MemoryError::NoL2Tables`: No more L2 tables available to hold the permissions
+    pub fn mprotect(
+        &mut self,
+        address: u32,
+        len: u32,
+        permissions: Permissions,
+    ) -> Result<(), MemoryError> {
+        if address & PAGE_OFFSET_MASK != 0 {
+            return Err(MemoryError::Misaligned);
+        }
+        if address as u64 + len as u64 > 1 << 32 {
+            return Err(MemoryError::OutOfRange);
+        }
+        // L1 entries of the range still needing an L2 table; pages are in
+        // address order, so repeats are adjacent
+        let mut missing_l1 = None;
+        let mut l2_tables = 0;
+        for page_base in pages(address, len) {
+            if self.guarded(page_base) {
+                return Err(MemoryError::Guard);
+            }
+            if permissions.write && self.shared(page_base) {
+                return Err(MemoryError::ReadOnly);
+            }
+            let l1_idx = ((page_base >> L1_INDEX_SHIFT) & L1_INDEX_MASK) as usize;
+            if self.l1_table[l1_idx] == UNMAPPED_L2_TABLE && missing_l1 != Some(l1_idx) {
+                missing_l1 = Some(l1_idx);
+                l2_tables += 1;
+            }
+        }
+        if self.num_l2_tables + l2_tables > self.l2_table_limit {
+            return Err(MemoryError::NoL2Tables);
+        }
+        self.protect(address, len, permissions)
+    }
+
+    /// Permissions of the page containing `address`
+    pub fn permissions(&self, address: u32) -> Permissions {
+        let l1_idx = ((address >> L1_INDEX_SHIFT) & L1_INDEX_MASK) as usize;
+        let l2_idx = ((address >> L2_INDEX_SHIFT) & L2_INDEX_MASK) as usize;
+        let l2_table_idx = self.l1_table[l1_idx];
+        if l2_table_idx == UNMAPPED_L2_TABLE {
+            return Permissions::ALL;
+        }
+        unsafe {
+            let l2_entry_offset = (l2_table_idx as usize) * L2_TABLE_SIZE + l2_idx;
+            Permissions::from_bits(*self.page_permissions.add(l2_entry_offset))
+        }
+    }
+
+    /// Check that every page overlapping `[address, address + len)` allows `access`
+    ///
+    /// `read()` and `write()` are host accesses and do not check permissions;
+    /// guest loads, stores and instruction fetches must be checked first.
+    ///
+    /// # Errors
+    /// - `MemoryError::Guard`: At least one page lies in a guard region
+    /// - `MemoryError::Permission`: At least one page denies the access
+    pub fn check(&self, address: u32, len: u32, access: Access) -> Result<(), MemoryError> {
+        if pages(address, len).any(|page_base| self.guarded(page_base)) {
+            return Err(MemoryError::Guard);
+        }
+        if pages(address, len).all(|page_base| self.permissions(page_base).allows(access)) {
+            Ok(())
+        } else {
+            Err(MemoryError::Permission)
         }
     }
 
     /// Allocate a page for the given address if not already allocated
     ///
+    /// See `allocate_page_code()` for the allocation process.
+    ///
+    /// # Errors
+    /// - `MemoryError::NoL2Tables`: No more L2 tables available
+    /// - `MemoryError::PageLimit`: Instance page limit reached
+    /// - `MemoryError::NoPagesAvailable`: PageStore has no available pages
+    /// - `MemoryError::Guard`: Address lies in a guard region
+    pub fn allocate_page(&mut self, address: u32) -> Result<(), MemoryError> {
+        MemoryError::from_code(self.allocate_page_code(address))
+    }
+
+    /// Allocate a page for the given address, returning an ABI-level result code
+    ///
     /// # Returns
     /// - `MEM_SUCCESS` (0): Page successfully allocated or already mapped
     /// - `MEM_ERR_NO_L2_TABLES` (1): No more L2 tables available
@@ -304,27 +1508,42 @@ impl Memory {
     /// 3. If not, allocate a new L2 table from the pool
     /// 4. Look up the page in the L2 table
     /// 5. If unmapped, allocate a page from the PageStore
-    pub fn allocate_page(&mut self, address: u32) -> i32 {
+    fn allocate_page_code(&mut self, address: u32) -> i32 {
+        let code = self.map_page(address);
+        match code {
+            MEM_ERR_NO_L2_TABLES => self.stats.no_l2_tables += 1,
+            MEM_ERR_PAGE_LIMIT => self.stats.page_limit += 1,
+            MEM_ERR_NO_PAGES_AVAILABLE => self.stats.no_pages_available += 1,
+            MEM_ERR_GUARD => self.stats.guard += 1,
+            _ => {}
+        }
+        code
+    }
+
+    /// Map a page for the given address, without recording failures in the stats
+    fn map_page(&mut self, address: u32) -> i32 {
+        if self.host_pointer(address).is_some() {
+            return MEM_SUCCESS; // Backed by a host buffer
+        }
+        if self.guarded(address) {
+            return MEM_ERR_GUARD;
+        }
+        let result = self.map_pool_page(address);
+        unsafe { PagePool::flush(self.page_store) };
+        result
+    }
+
+    /// Map the page containing `address` to a page of the pool, like
+    /// `map_page()` without reporting the pool's events
+    fn map_pool_page(&mut self, address: u32) -> i32 {
         // Extract L1 and L2 indices from address
         // Address layout: [L1 Index: 10 bits][L2 Index: 8 bits][Page Offset: 14 bits]
         let l1_idx = ((address >> L1_INDEX_SHIFT) & L1_INDEX_MASK) as usize;
         let l2_idx = ((address >> L2_INDEX_SHIFT) & L2_INDEX_MASK) as usize;
 
-        // Check if L2 table exists for this L1 entry
-        let l2_table_idx = if self.l1_table[l1_idx] == UNMAPPED_L2_TABLE {
-            // Need to allocate new L2 table
-            if self.num_l2_tables >= self.max_l2_tables {
-                return MEM_ERR_NO_L2_TABLES;
-            }
-
-            let new_l2_idx = self.num_l2_tables as u8;
-            self.l1_table[l1_idx] = new_l2_idx;
-
-            // L2 table is already initialized with UNMAPPED_PAGE values
-            self.num_l2_tables += 1;
-            new_l2_idx
-        } else {
-            self.l1_table[l1_idx]
+        // Check if L2 table exists for this L1 entry, allocating one if needed
+        let Some(l2_table_idx) = self.map_l2_table(l1_idx) else {
+            return MEM_ERR_NO_L2_TABLES;
         };
 
         // Check if page is already mapped in L2 table
@@ -337,7 +1556,7 @@ impl Memory {
         }
 
         // Check if we have room for another page
-        if self.num_pages >= self.max_pages {
+        if self.num_pages >= self.page_limit {
             return MEM_ERR_PAGE_LIMIT;
         }
 
@@ -345,24 +1564,27 @@ impl Memory {
         unsafe {
             let store = &mut *self.page_store;
 
-            // Check if PageStore has available pages
-            if store.num_available_pages == 0 {
-                return MEM_ERR_NO_PAGES_AVAILABLE;
-            }
-
             // Get next available page
-            store.num_available_pages -= 1;
-            let page_idx = *store.available_pages.add(store.num_available_pages);
+            let Some(page_idx) = store.take_page(Some(self.id)) else {
+                return MEM_ERR_NO_PAGES_AVAILABLE;
+            };
 
             // Track this allocation
             *self.allocated_indices.add(self.num_pages) = page_idx;
             self.num_pages += 1;
+            self.stats.pages_allocated += 1;
+            self.stats.peak_pages = self.stats.peak_pages.max(self.num_pages);
 
             // Map in L2 table
             let l2_table_idx = self.l1_table[l1_idx] as usize;
             let l2_entry_offset = l2_table_idx * L2_TABLE_SIZE + l2_idx;
             *self.l2_tables.add(l2_entry_offset) = page_idx;
 
+            if let Some(byte) = self.poison {
+                let page_ptr = self.page_memory.add(page_idx as usize * PAGE_SIZE);
+                std::ptr::write_bytes(page_ptr, byte, PAGE_SIZE);
+            }
+
             MEM_SUCCESS
         }
     }
@@ -376,6 +1598,7 @@ impl Memory {
     /// - Reading across page boundaries
     /// - Sparse memory regions (unallocated pages read as zeros)
     /// - Partial page reads
+    /// - Host buffers mapped with `map_host()`
     /// - Address wraparound (reading past 0xFFFFFFFF continues from 0x00000000)
     ///
     /// # Arguments
@@ -402,7 +1625,15 @@ impl Memory {
 
             // Check if L2 table exists
             let l2_table_idx = self.l1_table[l1_idx];
-            if l2_table_idx == UNMAPPED_L2_TABLE {
+            if let Some(host_addr) = self.host_pointer(addr) {
+                unsafe {
+                    std::ptr::copy_nonoverlapping(
+                        host_addr,
+                        buffer[offset..].as_mut_ptr(),
+                        bytes_in_page,
+                    );
+                }
+            } else if l2_table_idx == UNMAPPED_L2_TABLE {
                 // No L2 table - fill with zeros
                 buffer[offset..offset + bytes_in_page].fill(0);
             } else {
@@ -437,29 +1668,381 @@ impl Memory {
     ///
     /// Writes `buffer.len()` bytes starting at the given address. If a page
     /// is not allocated, it will be allocated on demand. If allocation fails,
-    /// an error code is returned.
+    /// an error is returned and the bytes before the failing page have been written.
     ///
     /// This method is optimized for performance and handles:
     /// - Writing across page boundaries
     /// - Automatic page allocation on write
     /// - Partial page writes
     /// - Address wraparound (writing past 0xFFFFFFFF continues from 0x00000000)
+    /// - Invalidating a reservation that overlaps the written range
     ///
     /// # Arguments
     /// * `address` - The starting address to write to
     /// * `buffer` - The buffer containing data to write
     ///
-    /// # Returns
-    /// - `MEM_SUCCESS` (0): Write completed successfully
-    /// - `MEM_ERR_NO_L2_TABLES` (1): No more L2 tables available
-    /// - `MEM_ERR_PAGE_LIMIT` (2): Instance page limit reached
-    /// - `MEM_ERR_NO_PAGES_AVAILABLE` (3): PageStore has no available pages
+    /// # Errors
+    /// - `MemoryError::NoL2Tables`: No more L2 tables available
+    /// - `MemoryError::PageLimit`: Instance page limit reached
+    /// - `MemoryError::NoPagesAvailable`: PageStore has no available pages
+    /// - `MemoryError::Guard`: Part of the range lies in a guard region
+    /// - `MemoryError::ReadOnly`: Part of the range is mapped to shared pages
     ///
     /// # Address Wraparound
     /// The method uses `wrapping_add` for address arithmetic, so writes that
     /// extend past the end of the 32-bit address space (0xFFFFFFFF) will wrap
     /// around to the beginning (0x00000000) and continue writing.
-    pub fn write(&mut self, address: u32, buffer: &[u8]) -> i32 {
+    pub fn write(&mut self, address: u32, buffer: &[u8]) -> Result<(), MemoryError> {
+        if self
+            .reservation
+            .is_some_and(|reservation| reservation.overlaps(address, buffer.len() as u32))
+        {
+            self.reservation = None;
+        }
+        MemoryError::from_code(self.write_code(address, buffer))
+    }
+
+    /// Set `len` bytes starting at `address` to `byte`
+    ///
+    /// Behaves like `write()` with a buffer of `len` copies of `byte`, without
+    /// the buffer: pages are allocated as needed and filled in place. Filling
+    /// with zero leaves unallocated pages unallocated, since they already read
+    /// as zeros.
+    ///
+    /// # Errors
+    /// Same as `write()`; the bytes before the failing page have been filled.
+    pub fn fill(&mut self, address: u32, len: u32, byte: u8) -> Result<(), MemoryError> {
+        if self
+            .reservation
+            .is_some_and(|reservation| reservation.overlaps(address, len))
+        {
+            self.reservation = None;
+        }
+
+        let mut addr = address;
+        let mut remaining = len as usize;
+        while remaining > 0 {
+            let page_offset = (addr & PAGE_OFFSET_MASK) as usize;
+            let bytes_in_page = (PAGE_SIZE - page_offset).min(remaining);
+            let page_base = addr & !PAGE_OFFSET_MASK;
+
+            if self.shared(addr) {
+                return Err(MemoryError::ReadOnly);
+            }
+            let target = if let Some(host_addr) = self.host_pointer(addr) {
+                Some(host_addr)
+            } else if byte == 0
+                && self.poison.is_none()
+                && self.page_index(page_base) == UNMAPPED_PAGE
+            {
+                if self.guarded(page_base) {
+                    return Err(MemoryError::Guard);
+                }
+                None
+            } else {
+                self.allocate_page(page_base)?;
+                let page_idx = self.page_index(page_base);
+                Some(unsafe {
+                    self.page_memory
+                        .add(page_idx as usize * PAGE_SIZE + page_offset)
+                })
+            };
+            if let Some(target) = target {
+                unsafe { std::ptr::write_bytes(target, byte, bytes_in_page) };
+            }
+
+            remaining -= bytes_in_page;
+            addr = addr.wrapping_add(bytes_in_page as u32);
+        }
+        Ok(())
+    }
+
+    /// Copy `len` bytes from guest address `src` to guest address `dst`
+    ///
+    /// Like `memmove`, the ranges may overlap. The copy is done page by page
+    /// without an intermediate buffer: unallocated source pages read as zeros,
+    /// and destination pages are allocated as needed except where zeros would
+    /// be copied into unallocated pages. Addresses wrap like `write()`.
+    ///
+    /// # Errors
+    /// Same as `write()` for the destination range. On error the copy is
+    /// partial.
+    pub fn copy_within(&mut self, src: u32, dst: u32, len: u32) -> Result<(), MemoryError> {
+        if self
+            .reservation
+            .is_some_and(|reservation| reservation.overlaps(dst, len))
+        {
+            self.reservation = None;
+        }
+        if src == dst {
+            return Ok(());
+        }
+
+        let len = len as usize;
+        if (dst.wrapping_sub(src) as usize) < len {
+            // The destination starts inside the source: copy from the end
+            let mut remaining = len;
+            while remaining > 0 {
+                let src_last = src.wrapping_add(remaining as u32 - 1);
+                let dst_last = dst.wrapping_add(remaining as u32 - 1);
+                let count = remaining
+                    .min((src_last & PAGE_OFFSET_MASK) as usize + 1)
+                    .min((dst_last & PAGE_OFFSET_MASK) as usize + 1);
+                remaining -= count;
+                self.copy_chunk(
+                    src.wrapping_add(remaining as u32),
+                    dst.wrapping_add(remaining as u32),
+                    count,
+                )?;
+            }
+        } else {
+            let mut offset = 0;
+            while offset < len {
+                let src_addr = src.wrapping_add(offset as u32);
+                let dst_addr = dst.wrapping_add(offset as u32);
+                let count = (len - offset)
+                    .min(PAGE_SIZE - (src_addr & PAGE_OFFSET_MASK) as usize)
+                    .min(PAGE_SIZE - (dst_addr & PAGE_OFFSET_MASK) as usize);
+                self.copy_chunk(src_addr, dst_addr, count)?;
+                offset += count;
+            }
+        }
+        Ok(())
+    }
+
+    /// Copy `count` bytes that lie within one source and one destination page
+    fn copy_chunk(&mut self, src: u32, dst: u32, count: usize) -> Result<(), MemoryError> {
+        if self.shared(dst) {
+            return Err(MemoryError::ReadOnly);
+        }
+        let source = self.byte_pointer(src);
+        let target = match self.host_pointer(dst) {
+            Some(host_addr) => Some(host_addr),
+            None => {
+                let page_base = dst & !PAGE_OFFSET_MASK;
+                if source.is_none()
+                    && self.poison.is_none()
+                    && self.page_index(page_base) == UNMAPPED_PAGE
+                {
+                    if self.guarded(page_base) {
+                        return Err(MemoryError::Guard);
+                    }
+                    None
+                } else {
+                    self.allocate_page(page_base)?;
+                    self.byte_pointer(dst)
+                }
+            }
+        };
+        if let Some(target) = target {
+            unsafe {
+                match source {
+                    Some(source) => std::ptr::copy(source, target, count),
+                    None => std::ptr::write_bytes(target, 0, count),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Host pointer to the byte at a guest address, or None if its page is unmapped
+    fn byte_pointer(&self, address: u32) -> Option<*mut u8> {
+        if let Some(host_addr) = self.host_pointer(address) {
+            return Some(host_addr);
+        }
+        let page_idx = self.page_index(address);
+        (page_idx != UNMAPPED_PAGE).then(|| unsafe {
+            self.page_memory
+                .add(page_idx as usize * PAGE_SIZE + (address & PAGE_OFFSET_MASK) as usize)
+        })
+    }
+
+    /// Reserve `size` bytes at `address`, as a load-reserved (LR) access does
+    ///
+    /// Replaces any previous reservation. The reservation is invalidated by a
+    /// `write()` overlapping it, by `write_conditional()` and by `reset()`.
+    pub fn reserve(&mut self, address: u32, size: u32) {
+        self.reservation = Some(Reservation { address, size });
+    }
+
+    /// Reservation currently held, if any
+    pub fn reservation(&self) -> Option<Reservation> {
+        self.reservation
+    }
+
+    /// Drop the reservation currently held
+    pub fn clear_reservation(&mut self) {
+        self.reservation = None;
+    }
+
+    /// Write `buffer` at `address` only if a reservation covering it is held,
+    /// as a store-conditional (SC) access does
+    ///
+    /// Returns whether the write happened. The reservation is cleared whether
+    /// or not it succeeds.
+    ///
+    /// # Errors
+    /// Same as `write()`
+    pub fn write_conditional(&mut self, address: u32, buffer: &[u8]) -> Result<bool, MemoryError> {
+        let Some(reservation) = self.reservation.take() else {
+            return Ok(false);
+        };
+        let offset = address.wrapping_sub(reservation.address) as u64;
+        if offset + buffer.len() as u64 > reservation.size as u64 {
+            return Ok(false);
+        }
+        self.write(address, buffer).map(|_| true)
+    }
+
+    /// Read a fixed number of bytes starting at the given address
+    ///
+    /// Behaves like `read()`: unallocated pages read as zeros and addresses wrap.
+    pub fn read_array<const N: usize>(&self, address: u32) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        self.read(address, &mut bytes);
+        bytes
+    }
+
+    /// Read a little-endian unsigned byte
+    pub fn read_u8(&self, address: u32) -> u8 {
+        u8::from_le_bytes(self.read_array(address))
+    }
+
+    /// Read a little-endian unsigned halfword
+    pub fn read_u16(&self, address: u32) -> u16 {
+        u16::from_le_bytes(self.read_array(address))
+    }
+
+    /// Read a little-endian unsigned word
+    pub fn read_u32(&self, address: u32) -> u32 {
+        u32::from_le_bytes(self.read_array(address))
+    }
+
+    /// Read a little-endian unsigned doubleword
+    pub fn read_u64(&self, address: u32) -> u64 {
+        u64::from_le_bytes(self.read_array(address))
+    }
+
+    /// Read a little-endian signed byte
+    pub fn read_i8(&self, address: u32) -> i8 {
+        i8::from_le_bytes(self.read_array(address))
+    }
+
+    /// Read a little-endian signed halfword
+    pub fn read_i16(&self, address: u32) -> i16 {
+        i16::from_le_bytes(self.read_array(address))
+    }
+
+    /// Read a little-endian signed word
+    pub fn read_i32(&self, address: u32) -> i32 {
+        i32::from_le_bytes(self.read_array(address))
+    }
+
+    /// Read a little-endian signed doubleword
+    pub fn read_i64(&self, address: u32) -> i64 {
+        i64::from_le_bytes(self.read_array(address))
+    }
+
+    /// Write a little-endian unsigned byte, allocating pages as `write()` does
+    pub fn write_u8(&mut self, address: u32, value: u8) -> Result<(), MemoryError> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    /// Write a little-endian unsigned halfword, allocating pages as `write()` does
+    pub fn write_u16(&mut self, address: u32, value: u16) -> Result<(), MemoryError> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    /// Write a little-endian unsigned word, allocating pages as `write()` does
+    pub fn write_u32(&mut self, address: u32, value: u32) -> Result<(), MemoryError> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    /// Write a little-endian unsigned doubleword, allocating pages as `write()` does
+    pub fn write_u64(&mut self, address: u32, value: u64) -> Result<(), MemoryError> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    /// Write a little-endian signed byte, allocating pages as `write()` does
+    pub fn write_i8(&mut self, address: u32, value: i8) -> Result<(), MemoryError> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    /// Write a little-endian signed halfword, allocating pages as `write()` does
+    pub fn write_i16(&mut self, address: u32, value: i16) -> Result<(), MemoryError> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    /// Write a little-endian signed word, allocating pages as `write()` does
+    pub fn write_i32(&mut self, address: u32, value: i32) -> Result<(), MemoryError> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    /// Write a little-endian signed doubleword, allocating pages as `write()` does
+    pub fn write_i64(&mut self, address: u32, value: i64) -> Result<(), MemoryError> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    /// Read a NUL-terminated string, returning its bytes without the terminator
+    ///
+    /// At most `max` bytes are examined, including the terminator, so the
+    /// result is at most `max - 1` bytes long. Unallocated memory reads as
+    /// zeros and therefore terminates the string.
+    ///
+    /// # Errors
+    /// - `MemoryError::Unterminated`: No NUL within the first `max` bytes
+    pub fn read_cstr(&self, address: u32, max: usize) -> Result<Vec<u8>, MemoryError> {
+        let mut bytes = Vec::new();
+        let mut chunk = [0u8; 64];
+        while bytes.len() < max {
+            let count = chunk.len().min(max - bytes.len());
+            self.read(
+                address.wrapping_add(bytes.len() as u32),
+                &mut chunk[..count],
+            );
+            if let Some(end) = chunk[..count].iter().position(|&byte| byte == 0) {
+                bytes.extend_from_slice(&chunk[..end]);
+                return Ok(bytes);
+            }
+            bytes.extend_from_slice(&chunk[..count]);
+        }
+        Err(MemoryError::Unterminated)
+    }
+
+    /// Read `len` bytes as a UTF-8 string
+    ///
+    /// # Errors
+    /// - `MemoryError::InvalidString`: The bytes are not valid UTF-8
+    pub fn read_utf8(&self, address: u32, len: usize) -> Result<String, MemoryError> {
+        let mut bytes = vec![0u8; len];
+        self.read(address, &mut bytes);
+        String::from_utf8(bytes).map_err(|_| MemoryError::InvalidString)
+    }
+
+    /// Write a string followed by a NUL terminator
+    ///
+    /// # Errors
+    /// - `MemoryError::InvalidString`: The string contains a NUL, which would
+    ///   truncate it when read back with `read_cstr()`; nothing is written
+    /// - Any error from `write()`
+    pub fn write_str(&mut self, address: u32, string: &str) -> Result<(), MemoryError> {
+        if string.contains('\0') {
+            return Err(MemoryError::InvalidString);
+        }
+        let mut bytes = Vec::with_capacity(string.len() + 1);
+        bytes.extend_from_slice(string.as_bytes());
+        bytes.push(0);
+        self.write(address, &bytes)
+    }
+
+    /// Write data from a buffer into memory, returning an ABI-level result code
+    ///
+    /// # Returns
+    /// - `MEM_SUCCESS` (0): Write completed successfully
+    /// - `MEM_ERR_NO_L2_TABLES` (1): No more L2 tables available
+    /// - `MEM_ERR_PAGE_LIMIT` (2): Instance page limit reached
+    /// - `MEM_ERR_NO_PAGES_AVAILABLE` (3): PageStore has no available pages
+    fn write_code(&mut self, address: u32, buffer: &[u8]) -> i32 {
         let mut addr = address;
         let mut offset = 0;
         let len = buffer.len();
@@ -469,9 +2052,25 @@ impl Memory {
             let page_offset = (addr & PAGE_OFFSET_MASK) as usize;
             let bytes_in_page = (PAGE_SIZE - page_offset).min(len - offset);
 
+            if self.shared(addr) {
+                return MEM_ERR_READ_ONLY;
+            }
+            if let Some(host_addr) = self.host_pointer(addr) {
+                unsafe {
+                    std::ptr::copy_nonoverlapping(
+                        buffer[offset..].as_ptr(),
+                        host_addr,
+                        bytes_in_page,
+                    );
+                }
+                offset += bytes_in_page;
+                addr = addr.wrapping_add(bytes_in_page as u32);
+                continue;
+            }
+
             // Ensure page is allocated
             let page_base = addr & !PAGE_OFFSET_MASK;
-            let alloc_result = self.allocate_page(page_base);
+            let alloc_result = self.allocate_page_code(page_base);
             if alloc_result != MEM_SUCCESS {
                 return alloc_result;
             }
@@ -500,15 +2099,672 @@ impl Memory {
         MEM_SUCCESS
     }
 
+    /// Map a host buffer at a guest address
+    ///
+    /// Guest reads and writes in `[address, address + buffer.len())` access the
+    /// buffer directly instead of pages from the PageStore. The mapping lasts
+    /// until `unmap_host()`, `reset()` or the Memory is dropped. Permissions set
+    /// with `protect()` apply to mapped pages as usual.
+    ///
+    /// # Safety
+    /// The buffer must stay valid, and must not be accessed by the host, until
+    /// it is unmapped.
+    ///
+    /// # Errors
+    /// - `MemoryError::Misaligned`: `address` or `buffer.len()` is not a multiple of
+    ///   `PAGE_SIZE`, the buffer is empty, or the range extends past 0xFFFFFFFF
+    /// - `MemoryError::Overlap`: The range contains allocated pages, another mapping or
+    ///   a guard region
+    pub unsafe fn map_host(&mut self, address: u32, buffer: &mut [u8]) -> Result<(), MemoryError> {
+        let len = buffer.len();
+        if address & PAGE_OFFSET_MASK != 0
+            || len == 0
+            || len % PAGE_SIZE != 0
+            || address as u64 + len as u64 > 1 << 32
+        {
+            return Err(MemoryError::Misaligned);
+        }
+        let overlaps = (0..len / PAGE_SIZE).any(|page| {
+            let page_base = address + (page * PAGE_SIZE) as u32;
+            self.page_index(page_base) != UNMAPPED_PAGE
+                || self.host_pointer(page_base).is_some()
+                || self.guarded(page_base)
+        });
+        if overlaps {
+            return Err(MemoryError::Overlap);
+        }
+        self.host_mappings.push(HostMapping {
+            address,
+            len,
+            data: buffer.as_mut_ptr(),
+        });
+        Ok(())
+    }
+
+    /// Unmap the host buffer mapped at `address`, returning whether one was mapped
+    ///
+    /// The guest range reads as unallocated memory again.
+    pub fn unmap_host(&mut self, address: u32) -> bool {
+        let count = self.host_mappings.len();
+        self.host_mappings
+            .retain(|mapping| mapping.address != address);
+        self.host_mappings.len() != count
+    }
+
+    /// Fill freshly allocated pages with `poison` instead of zeros (None to disable)
+    ///
+    /// A debugging aid: guests (or host code) that rely on memory they never
+    /// wrote being zero read the poison pattern instead. Unallocated memory
+    /// still reads as zeros, but `fill()` and `copy_within()` allocate pages
+    /// when writing zeros so that the zeros are not lost to poison later.
+    /// `soft_reset()` refills kept pages with the poison.
+    pub fn set_poison(&mut self, poison: Option<u8>) {
+        self.poison = poison;
+    }
+
+    /// Byte freshly allocated pages are filled with, if poisoning is enabled
+    pub fn poison(&self) -> Option<u8> {
+        self.poison
+    }
+
+    /// Fill `[address, address + len)` with REDZONE_BYTE and watch it for writes
+    ///
+    /// Redzones mark memory around buffers that must never be written.
+    /// `check_redzones()` reports overwritten bytes, and `reset()` and
+    /// `soft_reset()` record them for `take_redzone_violations()` before
+    /// dropping every redzone.
+    ///
+    /// # Errors
+    /// Same as `fill()`; the range is not watched if filling fails.
+    pub fn add_redzone(&mut self, address: u32, len: u32) -> Result<(), MemoryError> {
+        self.fill(address, len, REDZONE_BYTE)?;
+        self.redzones.push((address, len));
+        Ok(())
+    }
+
+    /// Ranges of redzone bytes that no longer hold REDZONE_BYTE, in redzone order
+    pub fn check_redzones(&self) -> Vec<DiffRange> {
+        let mut violations: Vec<DiffRange> = Vec::new();
+        for &(address, len) in &self.redzones {
+            let mut bytes = vec![0u8; len as usize];
+            self.read(address, &mut bytes);
+            let mut previous = None;
+            for (offset, &byte) in bytes.iter().enumerate() {
+                if byte == REDZONE_BYTE {
+                    previous = None;
+                    continue;
+                }
+                match (previous, violations.last_mut()) {
+                    (Some(last), Some(range)) if last + 1 == offset => range.len += 1,
+                    _ => violations.push(DiffRange {
+                        address: address.wrapping_add(offset as u32),
+                        len: 1,
+                    }),
+                }
+                previous = Some(offset);
+            }
+        }
+        violations
+    }
+
+    /// Take the redzone violations recorded by `reset()` and `soft_reset()`
+    pub fn take_redzone_violations(&mut self) -> Vec<DiffRange> {
+        std::mem::take(&mut self.redzone_violations)
+    }
+
+    /// Record overwritten redzone bytes and stop watching every redzone
+    fn retire_redzones(&mut self) {
+        let violations = self.check_redzones();
+        self.redzone_violations.extend(violations);
+        self.redzones.clear();
+    }
+
+    /// Reserve every page overlapping `[address, address + len)` as a guard region
+    ///
+    /// Guard pages are never allocated: `write()` and `allocate_page()` fail with
+    /// `MemoryError::Guard`, `check()` rejects every access and `read()` returns
+    /// zeros. Guard regions are kept across `reset()`. A typical use is a guard
+    /// below the stack so that a runaway stack faults instead of allocating pages.
+    ///
+    /// # Errors
+    /// - `MemoryError::Overlap`: The range contains allocated pages or a host mapping
+    pub fn guard(&mut self, address: u32, len: u32) -> Result<(), MemoryError> {
+        let overlaps = pages(address, len).any(|page_base| {
+            self.page_index(page_base) != UNMAPPED_PAGE || self.host_pointer(page_base).is_some()
+        });
+        if overlaps {
+            return Err(MemoryError::Overlap);
+        }
+        if len > 0 {
+            self.guard_regions.push(GuardRegion {
+                first_page: address >> PAGE_OFFSET_BITS,
+                pages: page_count(address, len),
+            });
+        }
+        Ok(())
+    }
+
+    /// Check if the page containing `address` is in a guard region
+    pub fn guarded(&self, address: u32) -> bool {
+        self.guard_regions
+            .iter()
+            .any(|region| region.contains(address))
+    }
+
+    /// Route guest accesses to `[address, address + len)` to a device
+    ///
+    /// Interpreted loads and stores lying entirely inside the range go to
+    /// `device` instead of RAM and bypass page permissions. Devices are kept
+    /// across `reset()`.
+    ///
+    /// # Errors
+    /// - `MemoryError::Overlap`: The range is empty, wraps past 0xFFFFFFFF or
+    ///   overlaps another device
+    pub fn map_mmio(
+        &mut self,
+        address: u32,
+        len: u32,
+        device: Box<dyn MmioDevice>,
+    ) -> Result<(), MemoryError> {
+        if len == 0
+            || address.checked_add(len - 1).is_none()
+            || self
+                .mmio_regions
+                .iter()
+                .any(|region| region.overlaps(address, len))
+        {
+            return Err(MemoryError::Overlap);
+        }
+        self.mmio_regions.push(MmioRegion {
+            address,
+            len,
+            device,
+        });
+        Ok(())
+    }
+
+    /// Remove the device mapped at `address`, returning it
+    pub fn unmap_mmio(&mut self, address: u32) -> Option<Box<dyn MmioDevice>> {
+        let index = self
+            .mmio_regions
+            .iter()
+            .position(|region| region.address == address)?;
+        Some(self.mmio_regions.remove(index).device)
+    }
+
+    /// Check if any device is mapped
+    pub(crate) fn devices(&self) -> bool {
+        !self.mmio_regions.is_empty()
+    }
+
+    /// Check if any byte of `[address, address + len)` is routed to a device
+    pub fn mmio(&self, address: u32, len: u32) -> bool {
+        self.mmio_regions
+            .iter()
+            .any(|region| region.overlaps(address, len))
+    }
+
+    /// Perform a `size`-byte device load, or return None if no device covers
+    /// the whole access
+    pub fn mmio_read(&mut self, address: u32, size: u32) -> Option<u32> {
+        self.mmio_regions.iter_mut().find_map(|region| {
+            let offset = region.offset(address, size)?;
+            Some(region.device.read(offset, size) & size_mask(size))
+        })
+    }
+
+    /// Perform a `size`-byte device store, returning whether a device covers
+    /// the whole access
+    pub fn mmio_write(&mut self, address: u32, size: u32, value: u32) -> bool {
+        self.mmio_regions
+            .iter_mut()
+            .find_map(|region| {
+                let offset = region.offset(address, size)?;
+                region.device.write(offset, size, value & size_mask(size));
+                Some(())
+            })
+            .is_some()
+    }
+
+    /// Map shared read-only pages at a guest address
+    ///
+    /// The L2 entries point directly at the shared pages, which do not count
+    /// towards `max_pages`. The pages get `Permissions::READ_EXECUTE`; `write()`
+    /// fails with `MemoryError::ReadOnly` even if `protect()` later allows writes.
+    /// The mapping lasts until `unmap_shared()` or `reset()`.
+    ///
+    /// # Errors
+    /// - `MemoryError::Misaligned`: `address` is not a multiple of `PAGE_SIZE`, or
+    ///   the pages would extend past 0xFFFFFFFF
+    /// - `MemoryError::Overlap`: The range contains allocated pages, another mapping
+    ///   or a guard region
+    /// - `MemoryError::NoL2Tables`: No more L2 tables available
+    pub fn map_shared(&mut self, address: u32, shared: &SharedPages) -> Result<(), MemoryError> {
+        if address & PAGE_OFFSET_MASK != 0 || address as u64 + shared.len() as u64 > 1 << 32 {
+            return Err(MemoryError::Misaligned);
+        }
+        let page_bases = (0..shared.pages()).map(|page| address + (page * PAGE_SIZE) as u32);
+        let overlaps = page_bases.clone().any(|page_base| {
+            self.page_index(page_base) != UNMAPPED_PAGE
+                || self.host_pointer(page_base).is_some()
+                || self.guarded(page_base)
+        });
+        if overlaps {
+            return Err(MemoryError::Overlap);
+        }
+        for page_base in page_bases.clone() {
+            let l1_idx = ((page_base >> L1_INDEX_SHIFT) & L1_INDEX_MASK) as usize;
+            self.map_l2_table(l1_idx).ok_or(MemoryError::NoL2Tables)?;
+        }
+        let mut previous = Vec::with_capacity(shared.pages());
+        for (page_base, &page_idx) in page_bases.zip(&shared.inner.pages) {
+            let l2_entry_offset = self.l2_entry_offset(page_base);
+            unsafe {
+                *self.l2_tables.add(l2_entry_offset) = page_idx;
+                previous.push(*self.page_permissions.add(l2_entry_offset));
+                *self.page_permissions.add(l2_entry_offset) =
+                    Permissions::READ_EXECUTE.bits() | PERMISSION_SHARED;
+            }
+        }
+        self.shared_mappings
+            .push((address, shared.clone(), previous));
+        Ok(())
+    }
+
+    /// Unmap the shared pages mapped at `address`, returning whether any were mapped
+    ///
+    /// The guest range reads as unallocated memory again, with the
+    /// permissions its pages had before `map_shared()` (the defaults after a
+    /// `soft_reset()`).
+    pub fn unmap_shared(&mut self, address: u32) -> bool {
+        let Some(index) = self
+            .shared_mappings
+            .iter()
+            .position(|(base, _, _)| *base == address)
+        else {
+            return false;
+        };
+        let (_, shared, previous) = self.shared_mappings.remove(index);
+        for (page, bits) in (0..shared.pages()).zip(previous) {
+            let l2_entry_offset = self.l2_entry_offset(address + (page * PAGE_SIZE) as u32);
+            unsafe {
+                *self.l2_tables.add(l2_entry_offset) = UNMAPPED_PAGE;
+                *self.page_permissions.add(l2_entry_offset) = bits;
+            }
+        }
+        true
+    }
+
+    /// Check if the page containing `address` is mapped to shared pages
+    pub fn shared(&self, address: u32) -> bool {
+        self.shared_mappings
+            .iter()
+            .any(|(base, shared, _)| (address.wrapping_sub(*base) as usize) < shared.len())
+    }
+
+    /// Usage counters
+    pub fn stats(&self) -> &MemoryStats {
+        &self.stats
+    }
+
+    /// Id of this instance in its PageStore's `instances()`
+    ///
+    /// Ids are reused once an instance is dropped.
+    pub fn id(&self) -> usize {
+        self.id
+    }
+
+    /// Guarantee this instance `pages` pages from the pool
+    ///
+    /// Free pages are held back so that the instance can always allocate up
+    /// to `pages`, whatever other instances sharing the PageStore allocate.
+    /// Pages above the minimum are taken from the unreserved pages, first come
+    /// first served. Setting 0 releases the guarantee.
+    ///
+    /// # Errors
+    /// - `MemoryError::PageLimit`: `pages` exceeds the instance's `max_pages`
+    /// - `MemoryError::NoPagesAvailable`: Not enough unreserved pages to hold back
+    pub fn set_min_pages(&mut self, pages: usize) -> Result<(), MemoryError> {
+        if pages > self.max_pages {
+            return Err(MemoryError::PageLimit);
+        }
+        let pool = unsafe { &mut *self.page_store };
+        // Instances stay registered until dropped
+        debug_assert!(pool.instances[self.id].is_some());
+        let Some(usage) = pool.instances[self.id].as_mut() else {
+            return Ok(());
+        };
+        let old = usage.outstanding();
+        let new = pages.saturating_sub(usage.pages);
+        if new > old && new - old > pool.num_available_pages - pool.reserved_pages {
+            pool.stats.exhausted += 1;
+            pool.notify(PageEvent::Exhausted {
+                instance: Some(self.id),
+            });
+            unsafe { PagePool::flush(self.page_store) };
+            return Err(MemoryError::NoPagesAvailable);
+        }
+        usage.min_pages = pages;
+        pool.reserved_pages = pool.reserved_pages + new - old;
+        Ok(())
+    }
+
+    /// Pages guaranteed to this instance
+    pub fn min_pages(&self) -> usize {
+        let pool = unsafe { &*self.page_store };
+        pool.instances[self.id].map_or(0, |usage| usage.min_pages)
+    }
+
+    /// Contiguous mapped ranges in address order
+    ///
+    /// Adjacent pages are merged while their backing and permissions match.
+    /// Unallocated pages and guard regions are not included.
+    pub fn regions(&self) -> impl Iterator<Item = Region> {
+        let mut regions: Vec<Region> = Vec::new();
+        for l1_idx in 0..L1_TABLE_SIZE as u32 {
+            let table_base = l1_idx << L1_INDEX_SHIFT;
+            let table_end = table_base as u64 + (L2_TABLE_SIZE * PAGE_SIZE) as u64;
+            let has_host = self.host_mappings.iter().any(|mapping| {
+                (mapping.address as u64) < table_end
+                    && mapping.address as u64 + mapping.len as u64 > table_base as u64
+            });
+            if self.l1_table[l1_idx as usize] == UNMAPPED_L2_TABLE && !has_host {
+                continue;
+            }
+            for l2_idx in 0..L2_TABLE_SIZE as u32 {
+                let page_base = table_base | (l2_idx << L2_INDEX_SHIFT);
+                let kind = if self.host_pointer(page_base).is_some() {
+                    RegionKind::Host
+                } else if self.page_index(page_base) == UNMAPPED_PAGE {
+                    continue;
+                } else if self.shared(page_base) {
+                    RegionKind::Shared
+                } else {
+                    RegionKind::Private
+                };
+                let permissions = self.permissions(page_base);
+                match regions.last_mut() {
+                    Some(last)
+                        if last.end() == page_base as u64
+                            && last.kind == kind
+                            && last.permissions == permissions =>
+                    {
+                        last.pages += 1
+                    }
+                    _ => regions.push(Region {
+                        address: page_base,
+                        pages: 1,
+                        kind,
+                        permissions,
+                    }),
+                }
+            }
+        }
+        regions.into_iter()
+    }
+
+    /// Address of the first occurrence of `needle` in `range`
+    ///
+    /// Only mapped pages are scanned: a match never covers unallocated pages
+    /// or guard regions, so searching for zeros does not report the (zero)
+    /// contents of unallocated memory. Matches may span adjacent mapped pages.
+    /// `range` is clamped to the 4GB address space and does not wrap. An empty
+    /// needle matches at the start of a non-empty range.
+    pub fn find(&self, needle: &[u8], range: Range<u64>) -> Option<u32> {
+        let end = range.end.min(1 << 32);
+        if range.start >= end {
+            return None;
+        }
+        if needle.is_empty() {
+            return Some(range.start as u32);
+        }
+
+        // Tail of the previous page, so matches can cross page boundaries
+        let mut window: Vec<u8> = Vec::new();
+        let mut window_start = 0u64;
+        for region in self.regions() {
+            let region_start = (region.address as u64).max(range.start);
+            let region_end = region.end().min(end);
+            if region_start >= region_end {
+                continue;
+            }
+            if window_start + window.len() as u64 != region_start {
+                window.clear();
+                window_start = region_start;
+            }
+            let mut address = region_start;
+            while address < region_end {
+                let page_base = address as u32 & !PAGE_OFFSET_MASK;
+                let page = self.page_bytes(page_base)?;
+                let chunk_end = (page_base as u64 + PAGE_SIZE as u64).min(region_end);
+                window.extend_from_slice(
+                    &page[(address - page_base as u64) as usize
+                        ..(chunk_end - page_base as u64) as usize],
+                );
+                if let Some(position) = window
+                    .windows(needle.len())
+                    .position(|candidate| candidate == needle)
+                {
+                    return Some((window_start + position as u64) as u32);
+                }
+                let keep = window.len().min(needle.len() - 1);
+                window.drain(..window.len() - keep);
+                window_start = chunk_end - keep as u64;
+                address = chunk_end;
+            }
+        }
+        None
+    }
+
+    /// Location of the byte at `address` in the PageStore's page memory
+    ///
+    /// Returns None if the page is unmapped or backed by a host buffer. Shared
+    /// pages translate to their location in the pool.
+    pub fn translate(&self, address: u32) -> Option<PhysicalOffset> {
+        if self.host_pointer(address).is_some() {
+            return None;
+        }
+        let page = self.page_index(address);
+        if page == UNMAPPED_PAGE {
+            return None;
+        }
+        Some(PhysicalOffset {
+            page,
+            offset: page as usize * PAGE_SIZE + (address & PAGE_OFFSET_MASK) as usize,
+        })
+    }
+
+    /// Allocated L2 tables in address order with the number of mapped entries
+    pub fn l2_tables(&self) -> impl Iterator<Item = L2TableInfo> + '_ {
+        self.l1_table
+            .iter()
+            .enumerate()
+            .filter(|(_, table)| **table != UNMAPPED_L2_TABLE)
+            .map(|(l1_index, &table)| {
+                let entries = unsafe {
+                    std::slice::from_raw_parts(
+                        self.l2_tables.add(table as usize * L2_TABLE_SIZE),
+                        L2_TABLE_SIZE,
+                    )
+                };
+                L2TableInfo {
+                    l1_index,
+                    table,
+                    address: (l1_index << L1_INDEX_SHIFT) as u32,
+                    mapped: entries
+                        .iter()
+                        .filter(|&&entry| entry != UNMAPPED_PAGE)
+                        .count(),
+                }
+            })
+    }
+
+    /// Hexdump `len` bytes starting at `address`, 16 bytes per line
+    ///
+    /// Each line is the address followed by the bytes in hex, e.g.
+    /// `0x00002000: 01 02 ff 00`. Unallocated memory shows as zeros. At most
+    /// `MAX_DUMP_LENGTH` bytes are shown, read one line at a time.
+    pub fn dump(&self, address: u32, len: u32) -> String {
+        let len = len.min(MAX_DUMP_LENGTH);
+        let lines: Vec<String> = (0..len)
+            .step_by(16)
+            .map(|offset| {
+                let line_address = address.wrapping_add(offset);
+                let mut bytes = [0u8; 16];
+                let chunk = &mut bytes[..(len - offset).min(16) as usize];
+                self.read(line_address, chunk);
+                let hex: Vec<String> = chunk.iter().map(|byte| format!("{:02x}", byte)).collect();
+                format!("0x{:08x}: {}", line_address, hex.join(" "))
+            })
+            .collect();
+        lines.join("\n")
+    }
+
+    /// Contents of the mapped page at `page_base`, or None if it is unmapped
+    pub(crate) fn page_bytes(&self, page_base: u32) -> Option<Cow<'_, [u8]>> {
+        if self.host_pointer(page_base).is_some() {
+            let mut bytes = vec![0u8; PAGE_SIZE];
+            self.read(page_base, &mut bytes);
+            return Some(Cow::Owned(bytes));
+        }
+        let page_idx = self.page_index(page_base);
+        if page_idx == UNMAPPED_PAGE {
+            return None;
+        }
+        unsafe {
+            let page_ptr = self.page_memory.add(page_idx as usize * PAGE_SIZE);
+            Some(Cow::Borrowed(std::slice::from_raw_parts(
+                page_ptr, PAGE_SIZE,
+            )))
+        }
+    }
+
+    /// Offset of the L2 entry for an address whose L2 table is mapped
+    fn l2_entry_offset(&self, address: u32) -> usize {
+        let l1_idx = ((address >> L1_INDEX_SHIFT) & L1_INDEX_MASK) as usize;
+        let l2_idx = ((address >> L2_INDEX_SHIFT) & L2_INDEX_MASK) as usize;
+        self.l1_table[l1_idx] as usize * L2_TABLE_SIZE + l2_idx
+    }
+
+    /// Set the start of the linear heap
+    ///
+    /// The heap starts empty at address 0.
+    ///
+    /// # Errors
+    /// - `MemoryError::Misaligned`: `address` is not a multiple of `PAGE_SIZE`
+    /// - `MemoryError::Overlap`: The heap has already been grown
+    pub fn set_heap_base(&mut self, address: u32) -> Result<(), MemoryError> {
+        if address & PAGE_OFFSET_MASK != 0 {
+            return Err(MemoryError::Misaligned);
+        }
+        if self.heap_pages != 0 {
+            return Err(MemoryError::Overlap);
+        }
+        self.heap_base = address;
+        Ok(())
+    }
+
+    /// Grow the linear heap by `pages`, returning the previous size in pages
+    ///
+    /// Like wasm's `memory.grow`, the new pages are allocated (zero-filled)
+    /// immediately, so running out of memory is reported here rather than on a
+    /// later access. On failure the heap size is unchanged; pages allocated
+    /// before the failing one stay allocated and are reused by the next `grow()`.
+    ///
+    /// # Errors
+    /// - `MemoryError::PageLimit`: Instance page limit reached, or the heap would
+    ///   exceed its limit or extend past 0xFFFFFFFF
+    /// - `MemoryError::NoL2Tables`: No more L2 tables available
+    /// - `MemoryError::NoPagesAvailable`: PageStore has no available pages
+    /// - `MemoryError::Guard`: The heap would extend into a guard region
+    pub fn grow(&mut self, pages: u32) -> Result<u32, MemoryError> {
+        let old_pages = self.heap_pages;
+        let new_pages = old_pages as u64 + pages as u64;
+        if new_pages > self.heap_limit as u64
+            || self.heap_base as u64 + new_pages * PAGE_SIZE as u64 > 1 << 32
+        {
+            return Err(MemoryError::PageLimit);
+        }
+        for page in old_pages..new_pages as u32 {
+            let page_base = self.heap_base + page * PAGE_SIZE as u32;
+            self.allocate_page(page_base)?;
+        }
+        self.heap_pages = new_pages as u32;
+        Ok(old_pages)
+    }
+
+    /// Limit the heap to `pages`; the heap is unlimited by default
+    ///
+    /// # Errors
+    /// - `MemoryError::Overlap`: The heap has already grown past `pages`
+    pub fn set_heap_limit(&mut self, pages: u32) -> Result<(), MemoryError> {
+        if self.heap_pages > pages {
+            return Err(MemoryError::Overlap);
+        }
+        self.heap_limit = pages;
+        Ok(())
+    }
+
+    /// Maximum heap size in pages
+    pub fn heap_limit(&self) -> u32 {
+        self.heap_limit
+    }
+
+    /// Current heap size in pages
+    pub fn heap_size(&self) -> u32 {
+        self.heap_pages
+    }
+
+    /// End of the linear heap (the program break)
+    ///
+    /// Wraps to 0 if the heap reaches the top of the address space.
+    pub fn brk(&self) -> u32 {
+        self.heap_base
+            .wrapping_add(self.heap_pages.wrapping_mul(PAGE_SIZE as u32))
+    }
+
+    /// Host pointer backing a guest address inside a mapped host buffer
+    fn host_pointer(&self, address: u32) -> Option<*mut u8> {
+        self.host_mappings
+            .iter()
+            .find_map(|mapping| mapping.pointer(address))
+    }
+
+    /// Global page index mapped at a guest address, or `UNMAPPED_PAGE`
+    fn page_index(&self, address: u32) -> u16 {
+        let l1_idx = ((address >> L1_INDEX_SHIFT) & L1_INDEX_MASK) as usize;
+        let l2_idx = ((address >> L2_INDEX_SHIFT) & L2_INDEX_MASK) as usize;
+        let l2_table_idx = self.l1_table[l1_idx];
+        if l2_table_idx == UNMAPPED_L2_TABLE {
+            return UNMAPPED_PAGE;
+        }
+        unsafe {
+            *self
+                .l2_tables
+                .add((l2_table_idx as usize) * L2_TABLE_SIZE + l2_idx)
+        }
+    }
+
     /// Reset this memory instance, returning all pages to the pool
     ///
     /// This clears both levels of the page table hierarchy:
     /// 1. Returns all allocated pages to the PageStore
     /// 2. Clears all L2 table entries
     /// 3. Resets all L1 table entries to unmapped
-    /// 4. Resets L2 table allocation counter
+    /// 4. Restores the default permissions of all pages
+    /// 5. Resets L2 table allocation counter
+    ///
+    /// Host buffers and shared pages are unmapped and any reservation is
+    /// dropped. Overwritten redzone bytes are recorded for
+    /// `take_redzone_violations()` and the redzones dropped. Guard regions,
+    /// poisoning and the heap base are kept, and the heap shrinks back to empty.
     pub fn reset(&mut self) {
-        if self.num_pages == 0 {
+        self.retire_redzones();
+        self.reservation = None;
+        self.host_mappings.clear();
+        self.shared_mappings.clear();
+        self.heap_pages = 0;
+        if self.num_pages == 0 && self.num_l2_tables == 0 {
             return;
         }
 
@@ -517,26 +2773,21 @@ impl Memory {
 
             // Return each page to the pool
             for i in 0..self.num_pages {
-                let page_idx = *self.allocated_indices.add(i);
-
-                // Clear the page memory
-                let offset = page_idx as usize * PAGE_SIZE;
-                let page_ptr = self.page_memory.add(offset);
-                std::ptr::write_bytes(page_ptr, 0, PAGE_SIZE);
-
-                // Add page back to available pool
-                *store.available_pages.add(store.num_available_pages) = page_idx;
-                store.num_available_pages += 1;
+                store.return_page(*self.allocated_indices.add(i), Some(self.id));
             }
+            PagePool::flush(self.page_store);
+            self.stats.pages_zeroed += self.num_pages as u64;
 
             // Clear all L1 table entries
             self.l1_table.fill(UNMAPPED_L2_TABLE);
 
-            // Clear all allocated L2 tables
+            // Clear all allocated L2 tables and their permissions
+            let permission_bits = Permissions::ALL.bits();
             for l2_idx in 0..self.num_l2_tables {
                 let table_offset = l2_idx * L2_TABLE_SIZE;
                 for i in 0..L2_TABLE_SIZE {
                     *self.l2_tables.add(table_offset + i) = UNMAPPED_PAGE;
+                    *self.page_permissions.add(table_offset + i) = permission_bits;
                 }
             }
 
@@ -544,6 +2795,52 @@ impl Memory {
             self.num_pages = 0;
         }
     }
+
+    /// Reset the contents of this memory instance while keeping its pages
+    ///
+    /// Allocated pages are zeroed in place and keep their L1/L2 mappings, so a
+    /// workload that runs again touches the same pages without taking them from
+    /// the pool. Guest-visible state matches `reset()`: every page reads as
+    /// zeros with default permissions, host buffers are unmapped, and the heap
+    /// shrinks back to empty (`grow()` reuses its pages). Shared pages stay
+    /// mapped, since their contents cannot have changed.
+    pub fn soft_reset(&mut self) {
+        self.retire_redzones();
+        self.reservation = None;
+        self.host_mappings.clear();
+        self.heap_pages = 0;
+
+        unsafe {
+            let store = &mut *self.page_store;
+            for i in 0..self.num_pages {
+                let page_idx = *self.allocated_indices.add(i);
+                store.zero_page(page_idx);
+                if let Some(byte) = self.poison {
+                    let page_ptr = self.page_memory.add(page_idx as usize * PAGE_SIZE);
+                    std::ptr::write_bytes(page_ptr, byte, PAGE_SIZE);
+                }
+            }
+            self.stats.pages_zeroed += self.num_pages as u64;
+
+            let permission_bits = Permissions::ALL.bits();
+            for i in 0..self.num_l2_tables * L2_TABLE_SIZE {
+                *self.page_permissions.add(i) = permission_bits;
+            }
+        }
+
+        // Shared pages keep their read-only permissions, and unmapping them
+        // restores the defaults
+        let permission_bits = Permissions::READ_EXECUTE.bits() | PERMISSION_SHARED;
+        for (base, shared, _) in &self.shared_mappings {
+            for page in 0..shared.pages() {
+                let l2_entry_offset = self.l2_entry_offset(base + (page * PAGE_SIZE) as u32);
+                unsafe { *self.page_permissions.add(l2_entry_offset) = permission_bits };
+            }
+        }
+        for (_, _, previous) in &mut self.shared_mappings {
+            previous.fill(Permissions::ALL.bits());
+        }
+    }
 }
 
 impl fmt::Debug for Memory {
@@ -568,6 +2865,9 @@ impl Drop for Memory {
 
             let store = &mut *self.page_store;
             store.instance_count -= 1;
+            if let Some(usage) = store.instances[self.id].take() {
+                store.reserved_pages -= usage.outstanding();
+            }
 
             // Clean up L2 tables
             if !self.l2_tables.is_null() {
@@ -579,6 +2879,16 @@ impl Drop for Memory {
                 drop(l2_tables);
             }
 
+            // Clean up permission bits
+            if !self.page_permissions.is_null() {
+                let total_l2_entries = self.max_l2_tables * L2_TABLE_SIZE;
+                let page_permissions = Box::from_raw(std::slice::from_raw_parts_mut(
+                    self.page_permissions,
+                    total_l2_entries,
+                ));
+                drop(page_permissions);
+            }
+
             // Clean up allocated_indices
             if !self.allocated_indices.is_null() {
                 let allocated_indices = Box::from_raw(std::slice::from_raw_parts_mut(
@@ -590,3 +2900,26 @@ impl Drop for Memory {
         }
     }
 }
+
+/// Base addresses of the pages overlapping `[address, address + len)`
+///
+/// Ranges past 0xFFFFFFFF wrap around to address 0, as in `read()` and `write()`.
+fn pages(address: u32, len: u32) -> impl Iterator<Item = u32> {
+    let first = address >> PAGE_OFFSET_BITS;
+    (0..page_count(address, len)).map(move |page| first.wrapping_add(page) << PAGE_OFFSET_BITS)
+}
+
+/// Mask selecting the low `size` bytes of a value
+fn size_mask(size: u32) -> u32 {
+    u32::MAX.checked_shr(32 - 8 * size.min(4)).unwrap_or(0)
+}
+
+/// Number of pages overlapping `[address, address + len)`
+fn page_count(address: u32, len: u32) -> u32 {
+    if len == 0 {
+        return 0;
+    }
+    let first = address >> PAGE_OFFSET_BITS;
+    let last = address.wrapping_add(len - 1) >> PAGE_OFFSET_BITS;
+    (last.wrapping_sub(first) & PAGE_NUMBER_MASK) + 1
+}
@@ -0,0 +1,257 @@
+//! 64-bit guest address space
+//!
+//! `Memory64` extends the two-level page tables of `Memory` with a sparse top
+//! level: the upper 32 bits of an address select a 4GB window, which is an
+//! ordinary `Memory` created on first use, and the lower 32 bits are
+//! translated by that window's L1/L2 tables. All windows share one PageStore
+//! and one page and L2 table budget, so RV64 guests (or 32-bit guests given
+//! widely separated segments) pay only for the windows they touch.
+//!
+//! # Example
+//! ```
+//! use jigs::{Memory64, PageStore};
+//!
+//! let store = PageStore::new(16);
+//! let mut memory = Memory64::new(&store, 8, 4);
+//! memory.write_u64(0x7fff_0000_0000, 0x1122_3344_5566_7788).unwrap();
+//! memory.write(0xffff_ffff_ffff_fffe, b"wrap").unwrap();
+//! assert_eq!(memory.read_u64(0x7fff_0000_0000), 0x1122_3344_5566_7788);
+//! assert_eq!(memory.read_u16(0), u16::from_le_bytes(*b"ap"));
+//! assert_eq!(memory.windows().count(), 3);
+//! ```
+
+use crate::memory::{Access, MAX_L2_TABLES, Memory, MemoryError, PageStore, Permissions};
+use std::collections::{BTreeMap, btree_map::Entry};
+
+/// Size of the address range translated by one window
+pub const WINDOW_SIZE: u64 = 1 << 32;
+
+/// Largest range handed to a window in one call, so lengths fit in a `u32`
+const MAX_CHUNK: u64 = 1 << 31;
+
+/// Guest memory with 64-bit addresses
+///
+/// Addresses wrap past 0xFFFFFFFF_FFFFFFFF. Unallocated memory reads as zeros,
+/// and reads never create windows.
+pub struct Memory64 {
+    /// Store the windows allocate from
+    store: PageStore,
+    /// Pages that may be allocated across all windows
+    max_pages: usize,
+    /// L2 tables that may be allocated across all windows
+    max_l2_tables: usize,
+    /// Top-level table: windows by the upper 32 bits of their addresses
+    windows: BTreeMap<u32, Memory>,
+}
+
+impl Memory64 {
+    /// Create a 64-bit memory allocating at most `max_pages` pages and
+    /// `max_l2_tables` L2 tables in total
+    ///
+    /// # Panics
+    /// Panics if `max_pages` exceeds the store's available pages, like `Memory::new()`.
+    pub fn new(store: &PageStore, max_pages: usize, max_l2_tables: usize) -> Self {
+        assert!(
+            max_pages <= store.available_pages(),
+            "max_pages {} exceeds available pages in PageStore ({})",
+            max_pages,
+            store.available_pages()
+        );
+        Memory64 {
+            store: store.handle(),
+            max_pages,
+            max_l2_tables,
+            windows: BTreeMap::new(),
+        }
+    }
+
+    /// Number of pages allocated across all windows
+    pub fn num_pages(&self) -> usize {
+        self.windows.values().map(|memory| memory.num_pages).sum()
+    }
+
+    /// Number of L2 tables allocated across all windows
+    pub fn num_l2_tables(&self) -> usize {
+        self.windows
+            .values()
+            .map(|memory| memory.num_l2_tables)
+            .sum()
+    }
+
+    /// Maximum number of pages across all windows
+    pub fn max_pages(&self) -> usize {
+        self.max_pages
+    }
+
+    /// Windows in use, as (base address, memory) in address order
+    pub fn windows(&self) -> impl Iterator<Item = (u64, &Memory)> {
+        self.windows
+            .iter()
+            .map(|(index, memory)| ((*index as u64) << 32, memory))
+    }
+
+    /// Memory translating the window containing `address`, if it is in use
+    pub fn window(&self, address: u64) -> Option<&Memory> {
+        self.windows.get(&((address >> 32) as u32))
+    }
+
+    /// Read bytes into a buffer
+    pub fn read(&self, address: u64, buf: &mut [u8]) {
+        let mut done = 0;
+        for (index, offset, len) in chunks(address, buf.len() as u64) {
+            let part = &mut buf[done..done + len as usize];
+            match self.windows.get(&index) {
+                Some(memory) => memory.read(offset, part),
+                None => part.fill(0),
+            }
+            done += len as usize;
+        }
+    }
+
+    /// Write bytes from a buffer
+    ///
+    /// # Errors
+    /// Same as `Memory::write()`, with page and L2 table limits applying
+    /// across all windows. On error the write is partial.
+    pub fn write(&mut self, address: u64, data: &[u8]) -> Result<(), MemoryError> {
+        let mut done = 0;
+        for (index, offset, len) in chunks(address, data.len() as u64) {
+            self.window_mut(index)?
+                .write(offset, &data[done..done + len as usize])?;
+            done += len as usize;
+        }
+        Ok(())
+    }
+
+    /// Set the permissions of every page overlapping `[address, address + len)`
+    ///
+    /// # Errors
+    /// - `MemoryError::NoL2Tables`: No more L2 tables available to hold the permissions
+    pub fn protect(
+        &mut self,
+        address: u64,
+        len: u64,
+        permissions: Permissions,
+    ) -> Result<(), MemoryError> {
+        for (index, offset, len) in chunks(address, len) {
+            self.window_mut(index)?
+                .protect(offset, len as u32, permissions)?;
+        }
+        Ok(())
+    }
+
+    /// Permissions of the page containing `address`
+    pub fn permissions(&self, address: u64) -> Permissions {
+        self.window(address).map_or(Permissions::ALL, |memory| {
+            memory.permissions(address as u32)
+        })
+    }
+
+    /// Check that every page overlapping `[address, address + len)` allows `access`
+    ///
+    /// # Errors
+    /// Same as `Memory::check()`.
+    pub fn check(&self, address: u64, len: u64, access: Access) -> Result<(), MemoryError> {
+        for (index, offset, len) in chunks(address, len) {
+            if let Some(memory) = self.windows.get(&index) {
+                memory.check(offset, len as u32, access)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Read a fixed number of bytes starting at the given address
+    pub fn read_array<const N: usize>(&self, address: u64) -> [u8; N] {
+        let mut bytes = [0u8; N];
+        self.read(address, &mut bytes);
+        bytes
+    }
+
+    /// Read a little-endian unsigned byte
+    pub fn read_u8(&self, address: u64) -> u8 {
+        u8::from_le_bytes(self.read_array(address))
+    }
+
+    /// Read a little-endian unsigned halfword
+    pub fn read_u16(&self, address: u64) -> u16 {
+        u16::from_le_bytes(self.read_array(address))
+    }
+
+    /// Read a little-endian unsigned word
+    pub fn read_u32(&self, address: u64) -> u32 {
+        u32::from_le_bytes(self.read_array(address))
+    }
+
+    /// Read a little-endian unsigned doubleword
+    pub fn read_u64(&self, address: u64) -> u64 {
+        u64::from_le_bytes(self.read_array(address))
+    }
+
+    /// Write a little-endian unsigned byte, allocating pages as `write()` does
+    pub fn write_u8(&mut self, address: u64, value: u8) -> Result<(), MemoryError> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    /// Write a little-endian unsigned halfword, allocating pages as `write()` does
+    pub fn write_u16(&mut self, address: u64, value: u16) -> Result<(), MemoryError> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    /// Write a little-endian unsigned word, allocating pages as `write()` does
+    pub fn write_u32(&mut self, address: u64, value: u32) -> Result<(), MemoryError> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    /// Write a little-endian unsigned doubleword, allocating pages as `write()` does
+    pub fn write_u64(&mut self, address: u64, value: u64) -> Result<(), MemoryError> {
+        self.write(address, &value.to_le_bytes())
+    }
+
+    /// Free every page and drop all windows
+    pub fn reset(&mut self) {
+        self.windows.clear();
+    }
+
+    /// Window `index`, created if needed, limited to the remaining budget
+    fn window_mut(&mut self, index: u32) -> Result<&mut Memory, MemoryError> {
+        let free_pages = self.max_pages - self.num_pages();
+        let free_l2_tables = self.max_l2_tables - self.num_l2_tables();
+        let store = &self.store;
+        let memory = match self.windows.entry(index) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                if free_l2_tables == 0 {
+                    return Err(MemoryError::NoL2Tables);
+                }
+                let pages = free_pages.min(store.available_pages());
+                let l2_tables = free_l2_tables.min(MAX_L2_TABLES);
+                entry.insert(Memory::new(store, pages, l2_tables))
+            }
+        };
+        // max_pages and max_l2_tables size the window's tables, so only the
+        // allocation limits shrink with the shared budget
+        memory.page_limit = memory.max_pages.min(memory.num_pages + free_pages);
+        memory.l2_table_limit = memory
+            .max_l2_tables
+            .min(memory.num_l2_tables + free_l2_tables);
+        Ok(memory)
+    }
+}
+
+/// Split `[address, address + len)` into (window, offset, len) pieces that
+/// stay inside one window
+fn chunks(address: u64, len: u64) -> impl Iterator<Item = (u32, u32, u64)> {
+    let mut address = address;
+    let mut remaining = len;
+    std::iter::from_fn(move || {
+        if remaining == 0 {
+            return None;
+        }
+        let offset = address as u32;
+        let len = remaining.min(WINDOW_SIZE - offset as u64).min(MAX_CHUNK);
+        let chunk = ((address >> 32) as u32, offset, len);
+        address = address.wrapping_add(len);
+        remaining -= len;
+        Some(chunk)
+    })
+}
@@ -0,0 +1,177 @@
+//! Metadata of compiled code
+//!
+//! `Module::set_code()` records what its code was compiled from and how in a
+//! `Metadata`: a hash of the RISC-V code, the jigs version whose compiler
+//! produced it, the compile options, the ISA extensions the code uses and
+//! when it was compiled. Hosts check it before reusing compiled code, such as
+//! a module loaded with `Module::deserialize()`, and log it when debugging.
+//!
+//! `source_hash()` is 64-bit FNV-1a, which unlike the standard library's
+//! default hasher is the same on every platform and release, so hashes can
+//! be stored. It is not cryptographic.
+//!
+//! # Example
+//! ```
+//! use jigs::{Instruction, Module, metadata};
+//!
+//! let code = Instruction::Mul { rd: 5, rs1: 6, rs2: 7 }.encode().unwrap().to_le_bytes();
+//! let mut module = Module::new(4).unwrap();
+//! assert!(module.metadata().is_none());
+//! module.set_code(&code).unwrap();
+//!
+//! let metadata = module.metadata().unwrap();
+//! assert_eq!(metadata.source_hash, metadata::source_hash(&code));
+//! assert!(metadata.extensions.m);
+//! assert!(metadata.current(&code, &module));
+//! ```
+
+use crate::{
+    Instruction, Module,
+    compiler::{ConstantStrategy, OptLevel},
+    decoder::{Decoder, Extension, Extensions},
+    serial::{
+        COMPILER, Reader, SerializeError, push_blob, push_extensions, push_list, push_u32, push_u64,
+    },
+};
+use std::time::{Duration, SystemTime};
+
+/// FNV-1a offset basis
+const FNV_OFFSET: u64 = 0xCBF2_9CE4_8422_2325;
+
+/// FNV-1a prime
+const FNV_PRIME: u64 = 0x0000_0100_0000_01B3;
+
+/// What compiled code was compiled from and how
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Metadata {
+    /// `source_hash()` of the RISC-V code
+    pub source_hash: u64,
+    /// Version of jigs whose compiler produced the code
+    pub compiler: String,
+    /// Optimization passes run
+    pub opt_level: OptLevel,
+    /// Whether the code has the resume entry
+    pub resumable: bool,
+    /// Whether the code counts basic block entries
+    pub block_counters: bool,
+    /// Guest address of the first instruction
+    pub base: u32,
+    /// How constants get into registers
+    pub constants: ConstantStrategy,
+    /// Guest PC of each function in the function table
+    pub functions: Vec<u32>,
+    /// Decoder the code was decoded with
+    pub decoder: Decoder,
+    /// Extensions the decoded instructions belong to, beyond the base set
+    pub extensions: Extensions,
+    /// When `set_code()` produced the code, compiling it or taking it from
+    /// a cache
+    pub created: SystemTime,
+}
+
+impl Metadata {
+    /// Describe `instructions`, decoded from `code`, as compiled by `module`
+    /// now
+    pub(crate) fn new(code: &[u8], instructions: &[Instruction], module: &Module) -> Self {
+        let mut extensions = Extensions::base();
+        for instruction in instructions {
+            match instruction.extension() {
+                Extension::I => {}
+                Extension::M => extensions.m = true,
+                Extension::C => extensions.c = true,
+                Extension::V => extensions.v = true,
+                Extension::Zfh => extensions.zfh = true,
+            }
+        }
+        Metadata {
+            source_hash: source_hash(code),
+            compiler: COMPILER.to_string(),
+            opt_level: module.opt_level(),
+            resumable: module.resumable(),
+            block_counters: module.block_counters(),
+            base: module.base(),
+            constants: module.constants(),
+            functions: module.functions().to_vec(),
+            decoder: *module.decoder(),
+            extensions,
+            created: SystemTime::now(),
+        }
+    }
+
+    /// Whether compiling `code` with the current settings of `module` would
+    /// produce the code this describes
+    ///
+    /// Compares the hash, the jigs version and every compile option, the
+    /// decoder included.
+    pub fn current(&self, code: &[u8], module: &Module) -> bool {
+        self.source_hash == source_hash(code)
+            && self.compiler == COMPILER
+            && self.opt_level == module.opt_level()
+            && self.resumable == module.resumable()
+            && self.block_counters == module.block_counters()
+            && self.base == module.base()
+            && self.constants == module.constants()
+            && self.functions == module.functions()
+            && self.decoder == *module.decoder()
+    }
+
+    /// Append the metadata in the `serial` format
+    pub(crate) fn write(&self, bytes: &mut Vec<u8>) {
+        push_u64(bytes, self.source_hash);
+        push_blob(bytes, self.compiler.as_bytes());
+        push_u32(bytes, self.opt_level as u32);
+        push_u32(bytes, self.resumable as u32);
+        push_u32(bytes, self.block_counters as u32);
+        push_u32(bytes, self.base);
+        push_u32(bytes, self.constants as u32);
+        push_list(bytes, self.functions.iter().copied());
+        push_extensions(bytes, self.decoder.extensions());
+        push_extensions(bytes, &self.extensions);
+        let created = self
+            .created
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default();
+        push_u64(bytes, created.as_secs());
+        push_u32(bytes, created.subsec_nanos());
+    }
+
+    /// Read metadata in the `serial` format
+    pub(crate) fn read(reader: &mut Reader) -> Result<Metadata, SerializeError> {
+        let source_hash = reader.u64()?;
+        let compiler = String::from_utf8_lossy(reader.blob()?).into_owned();
+        let opt_level = reader.opt_level()?;
+        let resumable = reader.bool("resumable setting")?;
+        let block_counters = reader.bool("block counters setting")?;
+        let base = reader.u32()?;
+        let constants = reader.constants()?;
+        let functions = reader.list()?;
+        let decoder = Decoder::new(reader.extensions()?);
+        let extensions = reader.extensions()?;
+        let seconds = reader.u64()?;
+        let nanos = reader.u32()?;
+        let created = (nanos < 1_000_000_000)
+            .then(|| SystemTime::UNIX_EPOCH.checked_add(Duration::new(seconds, nanos)))
+            .flatten()
+            .ok_or(SerializeError::InvalidSetting("creation time"))?;
+        Ok(Metadata {
+            source_hash,
+            compiler,
+            opt_level,
+            resumable,
+            block_counters,
+            base,
+            constants,
+            functions,
+            decoder,
+            extensions,
+            created,
+        })
+    }
+}
+
+/// 64-bit FNV-1a hash of RISC-V code
+pub fn source_hash(code: &[u8]) -> u64 {
+    code.iter().fold(FNV_OFFSET, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
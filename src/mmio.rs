@@ -0,0 +1,79 @@
+//! Memory-mapped I/O devices
+//!
+//! A device registered with `Memory::map_mmio` owns a range of guest addresses:
+//! guest loads and stores that fall entirely inside the range are passed to the
+//! device instead of RAM, so hosts can model UARTs, timers or request queues
+//! for bare-metal guests. The interpreter consults devices on every access.
+//! While any device is mapped, compiled code sends every load and store to
+//! the instance's memory helpers instead of walking the page tables inline,
+//! and the helpers pass accesses inside a device's range to the device. Host accesses
+//! through `Memory::read` and `Memory::write` still go to RAM.
+//!
+//! # Example
+//! ```
+//! use jigs::{Memory, MmioDevice, PageStore};
+//!
+//! /// Transmit-only UART collecting written bytes
+//! struct Uart(Vec<u8>);
+//!
+//! impl MmioDevice for Uart {
+//!     fn write(&mut self, offset: u32, _size: u32, value: u32) {
+//!         if offset == 0 {
+//!             self.0.push(value as u8);
+//!         }
+//!     }
+//! }
+//!
+//! let store = PageStore::new(16);
+//! let mut memory = Memory::new(&store, 16, 4);
+//! memory.map_mmio(0x1000_0000, 8, Box::new(Uart(Vec::new()))).unwrap();
+//! assert!(memory.mmio_write(0x1000_0000, 1, b'!' as u32));
+//! assert_eq!(memory.mmio_read(0x1000_0004, 4), Some(0));
+//! assert_eq!(memory.mmio_read(0x2000_0000, 4), None);
+//! ```
+
+use std::any::Any;
+
+/// Device model servicing guest accesses to an MMIO range
+///
+/// Offsets are relative to the start of the range. `size` is the access width
+/// in bytes (1, 2 or 4); stores pass the value truncated to that width and
+/// loads are truncated to it. Both methods default to a device that reads as
+/// zero and ignores writes.
+pub trait MmioDevice: Any {
+    /// Service a guest load
+    fn read(&mut self, offset: u32, size: u32) -> u32 {
+        let _ = (offset, size);
+        0
+    }
+
+    /// Service a guest store
+    fn write(&mut self, offset: u32, size: u32, value: u32) {
+        let _ = (offset, size, value);
+    }
+}
+
+/// A device mapped at a guest address range
+pub(crate) struct MmioRegion {
+    /// Guest address of the first byte
+    pub(crate) address: u32,
+    /// Length in bytes
+    pub(crate) len: u32,
+    /// Device servicing the range
+    pub(crate) device: Box<dyn MmioDevice>,
+}
+
+impl MmioRegion {
+    /// Offset of an access inside this region, if it lies entirely inside
+    pub(crate) fn offset(&self, address: u32, size: u32) -> Option<u32> {
+        let offset = address.wrapping_sub(self.address);
+        (offset < self.len && size <= self.len - offset).then_some(offset)
+    }
+
+    /// Check if `[address, address + len)` shares at least one byte with this region
+    pub(crate) fn overlaps(&self, address: u32, len: u32) -> bool {
+        len > 0
+            && (address.wrapping_sub(self.address) < self.len
+                || self.address.wrapping_sub(address) < len)
+    }
+}
@@ -1,10 +1,22 @@
-use crate::{compiler::Compiler, instruction::Instruction, memory::Memory};
-use std::ptr;
+use crate::{
+    Instruction,
+    cache::{CodeCache, Compiled, Key},
+    compiler::{CompileStats, Compiler, ConstantStrategy, OptLevel, SourceMap},
+    decoder::Decoder,
+    elf::{Elf, ElfError, Image},
+    executable::ExecutableBuffer,
+    memory::Memory,
+    metadata::Metadata,
+    serial::{
+        COMPILER, MODULE_MAGIC, MODULE_VERSION, Reader, SerializeError, push_blob, push_extensions,
+        push_list, push_u32,
+    },
+};
+use std::{path::Path, thread};
 
-/// Maximum ARM64 code size as a multiple of RISC-V code size
-/// ARM64 instructions can require more space for register spilling,
-/// immediate loading sequences, and syscall handling
-const ARM64_CODE_SIZE_MULTIPLIER: usize = 4;
+/// Address space reserved for a module's ARM64 code, which its buffer grows
+/// into; small enough for one branch to reach across it
+pub const CODE_RESERVE: usize = 128 << 20;
 
 /// Compiled ARM64 code module containing translated RISC-V instructions
 pub struct Module {
@@ -15,11 +27,40 @@ pub struct Module {
     /// through this stable pointer, even when the instance changes
     pub(crate) memory_ptr: Box<*mut Memory>,
     /// Buffer containing compiled ARM64 machine code
-    code_buffer: *mut u8,
-    /// Size of the code buffer in bytes
-    code_buffer_size: usize,
+    code_buffer: ExecutableBuffer,
+    /// Size of RISC-V code the buffer was first sized for
+    max_code_size: usize,
     /// Size of the actual compiled code in bytes
     code_size: usize,
+    /// Decoder restricting the accepted instruction set
+    decoder: Decoder,
+    /// Optimization passes run when compiling
+    opt_level: OptLevel,
+    /// Whether compiled code gets the resume entry
+    resumable: bool,
+    /// Whether compiled code counts basic block entries
+    block_counters: bool,
+    /// Guest address the code is loaded at
+    base: u32,
+    /// How compiled code loads constants
+    constants: ConstantStrategy,
+    /// Most threads compiling at once
+    compile_threads: usize,
+    /// Guest PC of each function in the function table
+    functions: Vec<u32>,
+    /// Initial memory contents written into instances on attach
+    data: Image,
+    /// Guest addresses of the compiled code
+    source_map: SourceMap,
+    /// Statistics of the compile that produced the code, if it was compiled
+    /// here
+    stats: Option<CompileStats>,
+    /// What the code was compiled from and how, once there is code
+    metadata: Option<Metadata>,
+    /// Decoded RISC-V instructions of the compiled code
+    instructions: Vec<Instruction>,
+    /// Cache consulted before compiling
+    code_cache: Option<CodeCache>,
 }
 
 impl Module {
@@ -28,43 +69,76 @@ impl Module {
     /// # Arguments
     /// * `max_code_size` - Maximum expected size of RISC-V code (for buffer allocation)
     ///
+    /// The buffer grows for larger code, up to `CODE_RESERVE` bytes of ARM64
+    /// code.
+    ///
     /// # Returns
     /// Empty module ready to receive code via set_code()
     pub fn new(max_code_size: usize) -> Result<Module, CompileError> {
-        // Calculate ARM64 code buffer size based on RISC-V code size
-        let code_buffer_size = max_code_size * ARM64_CODE_SIZE_MULTIPLIER;
-
-        // macOS requires MAP_JIT flag to allocate executable memory on ARM64
-        #[cfg(target_os = "macos")]
-        let mmap_flags = libc::MAP_PRIVATE | libc::MAP_ANON | libc::MAP_JIT;
-        #[cfg(not(target_os = "macos"))]
-        let mmap_flags = libc::MAP_PRIVATE | libc::MAP_ANON;
-
-        // Allocate code buffer with read/write permissions initially
-        let code_buffer = unsafe {
-            let ptr = libc::mmap(
-                ptr::null_mut(),
-                code_buffer_size,
-                libc::PROT_READ | libc::PROT_WRITE,
-                mmap_flags,
-                -1,
-                0,
-            );
+        // Size the ARM64 code buffer for the largest accepted RISC-V code
+        let code_buffer_size = Compiler::code_size(max_code_size.div_ceil(4));
+        Self::with_buffer(max_code_size, code_buffer_size)
+    }
 
-            if ptr == libc::MAP_FAILED {
-                return Err(CompileError::AllocationFailed);
-            }
+    /// Create a Module with a code buffer sized for `code`
+    ///
+    /// Sizes the ARM64 buffer with `Compiler::estimate_size()` of its
+    /// instructions under the default decoder instead of for the worst case
+    /// of its length. Code needing more room grows the buffer.
+    pub fn sized_for(code: &[u8]) -> Result<Module, CompileError> {
+        let instructions = Decoder::default().decode_words(code);
+        Self::with_buffer(code.len(), Compiler::estimate_size(&instructions))
+    }
 
-            // mmap returns page-aligned memory, which is always 4-byte aligned
-            ptr as *mut u8
-        };
+    /// Compile the RISC-V executable at `path` into a new Module
+    ///
+    /// Reads the file, checks that it is a 32-bit little-endian RISC-V
+    /// executable and compiles its code image (see `Elf::code()`) at its guest
+    /// address, with `Elf::functions()` as its function table (the entry
+    /// point as function 0, then the exported functions) and the initial
+    /// memory image (see `Elf::image()`) as its data. `Elf::function_index()`
+    /// finds a function's index by name.
+    ///
+    /// # Errors
+    /// Returns `ElfError::Io` if the file cannot be read, and the errors of
+    /// `Elf::parse()` and `Elf::compile()`
+    pub fn from_elf_file<P: AsRef<Path>>(path: P) -> Result<Module, ElfError> {
+        let elf = Elf::load(path)?;
+        let (_, code) = elf.code().ok_or(ElfError::NoCode)?;
+        let mut module = Module::new(code.len()).map_err(ElfError::CompileError)?;
+        module.set_functions(&elf.functions());
+        elf.compile(&mut module)?;
+        module.set_data(elf.image());
+        Ok(module)
+    }
+
+    /// Create an empty Module sized for `max_code_size` bytes of RISC-V
+    /// code, with a `code_buffer_size`-byte ARM64 code buffer in
+    /// `CODE_RESERVE` bytes of address space
+    fn with_buffer(max_code_size: usize, code_buffer_size: usize) -> Result<Module, CompileError> {
+        let code_buffer = ExecutableBuffer::with_capacity(code_buffer_size, CODE_RESERVE)
+            .map_err(|_| CompileError::AllocationFailed)?;
 
         Ok(Module {
             instance_count: 0,
             memory_ptr: Box::new(std::ptr::null_mut()),
             code_buffer,
-            code_buffer_size,
+            max_code_size,
             code_size: 0,
+            decoder: Decoder::default(),
+            opt_level: OptLevel::default(),
+            resumable: false,
+            block_counters: false,
+            base: 0,
+            constants: ConstantStrategy::default(),
+            compile_threads: thread::available_parallelism().map_or(1, |threads| threads.get()),
+            functions: Vec::new(),
+            data: Vec::new(),
+            source_map: SourceMap::default(),
+            stats: None,
+            metadata: None,
+            instructions: Vec::new(),
+            code_cache: None,
         })
     }
 
@@ -77,67 +151,424 @@ impl Module {
     /// Ok(()) if compilation succeeds
     ///
     /// # Errors
-    /// Returns error if instances are attached, code is too large, or compilation fails
+    /// Returns error if instances are attached, the compiled code could
+    /// exceed `CODE_RESERVE`, a branch or JAL targets the middle of an
+    /// instruction, a function is not at an instruction, or compilation fails
     pub fn set_code(&mut self, code: &[u8]) -> Result<(), CompileError> {
         // Check that no instances are attached
         if self.instance_count != 0 {
             return Err(CompileError::InstancesAttached);
         }
 
-        // Check that code size doesn't exceed buffer capacity
-        let required_size = code.len() * ARM64_CODE_SIZE_MULTIPLIER;
-        if required_size > self.code_buffer_size {
-            return Err(CompileError::CodeTooLarge);
+        let key = self.code_cache.as_ref().map(|_| Key {
+            code: code.to_vec(),
+            opt_level: self.opt_level,
+            resumable: self.resumable,
+            block_counters: self.block_counters,
+            base: self.base,
+            constants: self.constants,
+            functions: self.functions.clone(),
+            decoder: self.decoder,
+        });
+        let cached = self
+            .code_cache
+            .as_ref()
+            .zip(key.as_ref())
+            .and_then(|(cache, key)| cache.get(key));
+        if let Some(compiled) = cached {
+            self.install(&compiled)?;
+            self.metadata = Some(Metadata::new(code, &self.instructions, self));
+            return Ok(());
         }
 
         // Decode RISC-V instructions
-        let mut instructions = Vec::new();
-        for chunk in code.chunks_exact(4) {
-            let word = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
-            let instr = Instruction::decode(word);
-            instructions.push(instr);
-        }
+        let instructions = self.decoder.decode_words(code);
+        Compiler::check_targets(&instructions)?;
+        Compiler::check_functions(&instructions, self.base, &self.functions)?;
+        self.reserve(Compiler::estimate_size_with(
+            &instructions,
+            self.functions.len(),
+        ))?;
 
-        // Ensure the buffer is writable (might have been set to exec-only previously)
-        unsafe {
-            if libc::mprotect(
-                self.code_buffer as *mut libc::c_void,
-                self.code_buffer_size,
-                libc::PROT_READ | libc::PROT_WRITE,
-            ) != 0
-            {
-                return Err(CompileError::AllocationFailed);
-            }
-        }
+        // Clear code from any previous compilation so the buffer contents depend
+        // only on the current input, then compile directly into it
+        let mut compiler = Compiler::with_opt_level(self.opt_level);
+        compiler.set_resumable(self.resumable);
+        compiler.set_block_counters(self.block_counters);
+        compiler.set_base(self.base);
+        compiler.set_constants(self.constants);
+        compiler.set_threads(self.compile_threads);
+        compiler.set_functions(&self.functions);
+        (self.code_size, self.source_map) = self
+            .code_buffer
+            .write(|buffer| {
+                buffer.fill(0);
+                compiler.compile_mapped(&instructions, buffer)
+            })
+            .map_err(|_| CompileError::AllocationFailed)?;
+        self.stats = compiler.stats().cloned();
+        self.metadata = Some(Metadata::new(code, &instructions, self));
+        self.instructions = instructions;
 
-        // Compile to ARM64 directly into the code buffer
-        let mut compiler = Compiler::new();
-        let buffer_slice =
-            unsafe { std::slice::from_raw_parts_mut(self.code_buffer, self.code_buffer_size) };
-        self.code_size = compiler.compile(&instructions, buffer_slice);
-
-        unsafe {
-            // Make the code executable
-            if libc::mprotect(
-                self.code_buffer as *mut libc::c_void,
-                self.code_buffer_size,
-                libc::PROT_READ | libc::PROT_EXEC,
-            ) != 0
-            {
-                return Err(CompileError::AllocationFailed);
-            }
+        if let (Some(cache), Some(key)) = (&self.code_cache, key) {
+            let compiled = Compiled {
+                code: self.code().to_vec(),
+                source_map: self.source_map.clone(),
+                instructions: self.instructions.clone(),
+            };
+            cache.insert(key, compiled);
         }
+        Ok(())
+    }
 
+    /// Replace the code with code compiled elsewhere
+    ///
+    /// Unlike `set_code()` this works with instances attached, so callers
+    /// must make sure none of them is running the code.
+    pub(crate) fn install(&mut self, compiled: &Compiled) -> Result<(), CompileError> {
+        self.reserve(compiled.code.len())?;
+        self.code_buffer
+            .write(|buffer| {
+                buffer.fill(0);
+                buffer[..compiled.code.len()].copy_from_slice(&compiled.code);
+            })
+            .map_err(|_| CompileError::AllocationFailed)?;
+        self.code_size = compiled.code.len();
+        self.source_map = compiled.source_map.clone();
+        self.stats = None;
+        self.metadata = None;
+        self.instructions = compiled.instructions.clone();
         Ok(())
     }
 
+    /// Grow the code buffer to at least `size` bytes
+    ///
+    /// The buffer stays where it is, so attached instances are unaffected.
+    fn reserve(&mut self, size: usize) -> Result<(), CompileError> {
+        if size > self.code_buffer.capacity() {
+            return Err(CompileError::CodeTooLarge);
+        }
+        self.code_buffer
+            .grow(size)
+            .map_err(|_| CompileError::AllocationFailed)
+    }
+
+    /// Size of RISC-V code the buffer was first sized for; larger code grows
+    /// it
+    pub fn max_code_size(&self) -> usize {
+        self.max_code_size
+    }
+
+    /// Bytes of ARM64 code the buffer holds before it has to grow
+    pub fn code_capacity(&self) -> usize {
+        self.code_buffer.len()
+    }
+
+    /// Set the decoder used by subsequent calls to `set_code()`
+    ///
+    /// Instructions from disabled extensions decode as unsupported.
+    pub fn set_decoder(&mut self, decoder: Decoder) {
+        self.decoder = decoder;
+    }
+
+    /// Get the decoder used when compiling
+    pub fn decoder(&self) -> &Decoder {
+        &self.decoder
+    }
+
+    /// Set the optimization passes run by subsequent calls to `set_code()`
+    ///
+    /// Defaults to `OptLevel::O0`, for the fastest compiles.
+    pub fn set_opt_level(&mut self, opt_level: OptLevel) {
+        self.opt_level = opt_level;
+    }
+
+    /// Get the optimization passes run when compiling
+    pub fn opt_level(&self) -> OptLevel {
+        self.opt_level
+    }
+
+    /// Set whether subsequent `set_code()` calls compile code that
+    /// `Instance::resume()` can enter at any instruction
+    ///
+    /// Off by default, as it adds a second entry and the dispatch routine.
+    pub fn set_resumable(&mut self, resumable: bool) {
+        self.resumable = resumable;
+    }
+
+    /// Whether compiled code can be entered at any instruction
+    pub fn resumable(&self) -> bool {
+        self.resumable
+    }
+
+    /// Set whether subsequent `set_code()` calls compile code counting entries
+    /// into each basic block
+    ///
+    /// Off by default. See `Compiler::set_block_counters()`.
+    pub fn set_block_counters(&mut self, block_counters: bool) {
+        self.block_counters = block_counters;
+    }
+
+    /// Whether compiled code counts basic block entries
+    pub fn block_counters(&self) -> bool {
+        self.block_counters
+    }
+
+    /// Set the guest address subsequent `set_code()` calls load the code at
+    ///
+    /// 0 by default. See `Compiler::set_base()`.
+    pub fn set_base(&mut self, base: u32) {
+        self.base = base & !3;
+    }
+
+    /// Get the guest address of the first instruction
+    pub fn base(&self) -> u32 {
+        self.base
+    }
+
+    /// Set how subsequent `set_code()` calls load constants
+    ///
+    /// `MoveWide` by default. See `ConstantStrategy`.
+    pub fn set_constants(&mut self, constants: ConstantStrategy) {
+        self.constants = constants;
+    }
+
+    /// Get how compiled code loads constants
+    pub fn constants(&self) -> ConstantStrategy {
+        self.constants
+    }
+
+    /// Set the most threads subsequent `set_code()` calls compile on
+    ///
+    /// Defaults to the available parallelism. Only large code is split
+    /// between threads (see `Compiler::set_threads()`), and the compiled code
+    /// is the same for any number.
+    pub fn set_compile_threads(&mut self, threads: usize) {
+        self.compile_threads = threads.max(1);
+    }
+
+    /// Get the most threads compiling at once
+    pub fn compile_threads(&self) -> usize {
+        self.compile_threads
+    }
+
+    /// Set the guest PC of each function subsequent `set_code()` calls give
+    /// an entry, in function table order
+    ///
+    /// `Instance::call_function()` enters the function at its index. Without
+    /// a table (the default) the code is one function, index 0, entered at
+    /// its base. See `Compiler::set_functions()`.
+    pub fn set_functions(&mut self, functions: &[u32]) {
+        self.functions = functions.to_vec();
+    }
+
+    /// Get the guest PC of each function in the function table
+    pub fn functions(&self) -> &[u32] {
+        &self.functions
+    }
+
+    /// Set the initial memory contents, as (guest address, bytes, length of
+    /// the zeroed tail), that `Instance::attach()` writes into the memory of
+    /// each instance
+    ///
+    /// Each instance gets its own copy of the data in its own pages, so
+    /// instances never see each other's writes. Tails are filled in place,
+    /// so large zeroed regions cost no memory here. Empty by default.
+    pub fn set_data(&mut self, data: Image) {
+        self.data = data;
+    }
+
+    /// Get the initial memory contents written into instances on attach
+    pub fn data(&self) -> &[(u32, Vec<u8>, u32)] {
+        &self.data
+    }
+
+    /// Share compiled code through `cache` in subsequent `set_code()` calls
+    ///
+    /// Code compiled before with the same options is copied from the cache
+    /// instead of being recompiled, and new compilations are added to it.
+    pub fn set_code_cache(&mut self, cache: CodeCache) {
+        self.code_cache = Some(cache);
+    }
+
+    /// Get the cache consulted when compiling, if any
+    pub fn code_cache(&self) -> Option<&CodeCache> {
+        self.code_cache.as_ref()
+    }
+
+    /// Get the statistics of the compile behind the current code
+    ///
+    /// `None` before any code is set and for code taken from the cache or
+    /// compiled elsewhere. See `CompileStats`.
+    pub fn compile_stats(&self) -> Option<&CompileStats> {
+        self.stats.as_ref()
+    }
+
+    /// Get the mapping between guest PCs and offsets into `code()`
+    pub fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    /// Get what the code was compiled from and how, if it came from
+    /// `set_code()` or a module serialized with it
+    pub fn metadata(&self) -> Option<&Metadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Render the compiled code as a listing annotated with the RISC-V code
+    ///
+    /// See `Compiler::listing()` for the format.
+    pub fn listing(&self) -> String {
+        Compiler::listing(&self.instructions, self.code(), &self.source_map)
+    }
+
+    /// Build an `.eh_frame` section describing the compiled code where it
+    /// is, for registering with the host unwinder
+    ///
+    /// Describes the frame at every instruction with DWARF CFI, holding the
+    /// code's absolute address, so it is only valid until the code changes.
+    /// Empty without code.
+    pub fn unwind_info(&self) -> Vec<u8> {
+        let code = self.code();
+        self.source_map
+            .unwind_info(code.as_ptr() as u64, code.len())
+    }
+
     /// Get a slice of the compiled ARM64 code
     pub fn code(&self) -> &[u8] {
-        if self.code_size == 0 {
-            &[]
+        &self.code_buffer.as_slice()[..self.code_size]
+    }
+
+    /// Save the compiled code with its source map, settings, instructions and
+    /// data in the `serial` format
+    ///
+    /// The code cache and compile statistics are not saved.
+    ///
+    /// # Errors
+    /// Returns `SerializeError::CompileError(CompileError::InvalidCode)` if
+    /// an instruction of the code does not encode
+    pub fn serialize(&self) -> Result<Vec<u8>, SerializeError> {
+        let mut bytes = MODULE_MAGIC.to_vec();
+        push_u32(&mut bytes, MODULE_VERSION);
+        push_blob(&mut bytes, COMPILER.as_bytes());
+
+        push_u32(&mut bytes, self.max_code_size as u32);
+        push_u32(&mut bytes, self.opt_level as u32);
+        push_u32(&mut bytes, self.resumable as u32);
+        push_u32(&mut bytes, self.block_counters as u32);
+        push_u32(&mut bytes, self.base);
+        push_u32(&mut bytes, self.constants as u32);
+        push_extensions(&mut bytes, self.decoder.extensions());
+        push_list(&mut bytes, self.functions.iter().copied());
+
+        let words = self
+            .instructions
+            .iter()
+            .map(|instruction| match instruction {
+                Instruction::Unsupported(word) => Ok(*word),
+                _ => instruction
+                    .encode()
+                    .map_err(|_| SerializeError::CompileError(CompileError::InvalidCode)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        push_list(&mut bytes, words.into_iter());
+        push_blob(&mut bytes, self.code());
+        self.source_map.write(&mut bytes);
+        push_u32(&mut bytes, self.metadata.is_some() as u32);
+        if let Some(metadata) = &self.metadata {
+            metadata.write(&mut bytes);
+        }
+
+        push_u32(&mut bytes, self.data.len() as u32);
+        for (address, data, tail) in &self.data {
+            push_u32(&mut bytes, *address);
+            push_blob(&mut bytes, data);
+            push_u32(&mut bytes, *tail);
+        }
+        Ok(bytes)
+    }
+
+    /// Load a module saved by `serialize()`, ready to attach without
+    /// compiling
+    ///
+    /// The module's buffer is sized for the saved code and grows for new code
+    /// given with `set_code()` as usual, whatever its maximum code size.
+    ///
+    /// The source map is checked against the code, base and function table,
+    /// but the ARM64 code itself cannot be: the bytes must come from a
+    /// trusted source, such as `serialize()` on this host, for running the
+    /// module with `Instance::call_function()` or `Instance::resume()` to be
+    /// sound.
+    ///
+    /// # Errors
+    /// Returns an error if the data is not a complete module in the current
+    /// format compiled by this version of jigs, if a setting is invalid, if
+    /// the source map does not match the code, base or function table, or if
+    /// the code buffer cannot be allocated
+    pub fn deserialize(bytes: &[u8]) -> Result<Module, SerializeError> {
+        let mut reader = Reader::new(bytes);
+        if reader.take(4)? != MODULE_MAGIC {
+            return Err(SerializeError::InvalidMagic);
+        }
+        let version = reader.u32()?;
+        if version != MODULE_VERSION {
+            return Err(SerializeError::UnsupportedVersion(version));
+        }
+        let compiler = reader.blob()?;
+        if compiler != COMPILER.as_bytes() {
+            let compiler = String::from_utf8_lossy(compiler).into_owned();
+            return Err(SerializeError::CompilerMismatch(compiler));
+        }
+
+        // The maximum code size comes from the data, so it does not size the
+        // buffer; installing the code grows it
+        let max_code_size = reader.u32()? as usize;
+        let mut module = Module::with_buffer(max_code_size, Compiler::code_size(0))
+            .map_err(SerializeError::CompileError)?;
+        module.opt_level = reader.opt_level()?;
+        module.resumable = reader.bool("resumable setting")?;
+        module.block_counters = reader.bool("block counters setting")?;
+        module.base = reader.u32()?;
+        module.constants = reader.constants()?;
+        module.decoder = Decoder::new(reader.extensions()?);
+        module.functions = reader.list()?;
+
+        let instructions: Vec<_> = reader
+            .list()?
+            .into_iter()
+            .map(|word| module.decoder.decode(word))
+            .collect();
+        let code = reader.blob()?.to_vec();
+        let source_map = SourceMap::read(&mut reader, code.len())?;
+        if source_map.len() != instructions.len()
+            || source_map.base() != module.base
+            || source_map.functions() != module.functions.len()
+            || Compiler::check_functions(&instructions, module.base, &module.functions).is_err()
+        {
+            return Err(SerializeError::InvalidSourceMap);
+        }
+        let metadata = if reader.bool("metadata flag")? {
+            Some(Metadata::read(&mut reader)?)
         } else {
-            unsafe { std::slice::from_raw_parts(self.code_buffer, self.code_size) }
+            None
+        };
+
+        for _ in 0..reader.u32()? {
+            let address = reader.u32()?;
+            let data = reader.blob()?.to_vec();
+            module.data.push((address, data, reader.u32()?));
         }
+
+        let compiled = Compiled {
+            code,
+            source_map,
+            instructions,
+        };
+        module
+            .install(&compiled)
+            .map_err(SerializeError::CompileError)?;
+        module.metadata = metadata;
+        Ok(module)
     }
 }
 
@@ -149,11 +580,6 @@ impl Drop for Module {
                 self.instance_count
             );
         }
-
-        // Free the code buffer
-        unsafe {
-            libc::munmap(self.code_buffer as *mut libc::c_void, self.code_buffer_size);
-        }
     }
 }
 
@@ -168,6 +594,22 @@ pub enum CompileError {
     AllocationFailed,
     /// Cannot set code while instances are attached
     InstancesAttached,
-    /// Code size exceeds the module's buffer capacity
+    /// Compiled code could exceed the module's reserved address space
     CodeTooLarge,
+    /// The branch or JAL at byte `offset` targets the middle of an
+    /// instruction inside the module
+    InvalidJumpTarget {
+        /// Offset of the jumping instruction
+        offset: u32,
+        /// Guest address it jumps to
+        target: u32,
+    },
+    /// The function at `index` in the function table is not at an
+    /// instruction of the code
+    InvalidFunction {
+        /// Index in the function table
+        index: usize,
+        /// Guest address of the function
+        address: u32,
+    },
 }
@@ -0,0 +1,133 @@
+//! NEON lowering of RISC-V vector code
+//!
+//! Guest vector registers are `VLEN` = 128 bits wide, the width of a NEON Q
+//! register, so an RVV element-wise integer operation is a single NEON
+//! instruction whenever its shape is known at compile time: one register
+//! per group (LMUL=1), every element active (`vl` = VLMAX) and no mask.
+//! `plan()` follows VSETVLI through each basic block to find such
+//! instructions and gives the NEON arrangement they run with; `lower()`
+//! gives their code, working on a vector register file in memory through
+//! the `SCRATCH` registers.
+//!
+//! These are the hooks for the translator: vector instructions still stop
+//! both the interpreter and compiled code until instances carry vector
+//! state, at which point planned instructions compile through `lower()` and
+//! the rest fall back to a helper.
+//!
+//! # Example
+//! ```
+//! use jigs::Instruction;
+//! use jigs::arm64::Arrangement;
+//! use jigs::neon;
+//!
+//! let code = [
+//!     // vsetvli x5, x0, e32, m1, ta, ma
+//!     Instruction::Vsetvli { rd: 5, rs1: 0, vtypei: 0xD0 },
+//!     Instruction::VaddVv { vd: 1, vs2: 2, vs1: 3, vm: true },
+//! ];
+//! let plan = neon::plan(&code);
+//! assert_eq!(plan, [None, Some(Arrangement::S4)]);
+//! let lowered = neon::lower(&code[1], Arrangement::S4, 20, 9).unwrap();
+//! assert_eq!(lowered.len(), 4);
+//! ```
+
+use crate::{
+    Instruction,
+    arm64::{self, Arrangement},
+    compiler::Compiler,
+};
+
+/// Bits in a guest vector register
+pub const VLEN: usize = 128;
+
+/// Q registers `lower()` uses for operands: caller-saved, and not the low
+/// halves of v8-v15 that the procedure call standard preserves
+pub const SCRATCH: [u8; 2] = [16, 17];
+
+/// Arrangement of a vector register under the `vtype` setting `vtypei`, if
+/// it describes a single full register
+///
+/// Grouped (LMUL > 1) and fractional registers, reserved element widths and
+/// reserved bits give `None`.
+pub fn arrangement(vtypei: u16) -> Option<Arrangement> {
+    if vtypei & 0x7 != 0 || vtypei >> 8 != 0 {
+        return None;
+    }
+    Arrangement::from_size((vtypei >> 3 & 0x7) as u32)
+}
+
+/// Arrangement each instruction runs with when it can be lowered to NEON,
+/// by index
+///
+/// The vector type is tracked within each basic block: it is known after a
+/// `vsetvli rd, x0` (with `rd` not x0), which sets `vl` to VLMAX, and
+/// unknown at the start of a block and after any other VSETVLI. An
+/// unmasked element-wise add under a known single-register type is planned.
+pub fn plan(instructions: &[Instruction]) -> Vec<Option<Arrangement>> {
+    let mut plan = vec![None; instructions.len()];
+    for block in Compiler::basic_blocks(instructions) {
+        let mut vtype = None;
+        for index in block.start..block.end {
+            match instructions[index] {
+                Instruction::Vsetvli { rd, rs1, vtypei } => {
+                    vtype = match (rd, rs1) {
+                        (1.., 0) => arrangement(vtypei),
+                        _ => None,
+                    };
+                }
+                Instruction::VaddVv { vm: true, .. }
+                | Instruction::VaddVx { vm: true, .. }
+                | Instruction::VaddVi { vm: true, .. } => plan[index] = vtype,
+                _ => {}
+            }
+        }
+    }
+    plan
+}
+
+/// NEON code for `instruction` run with `arrangement`, if it has any
+///
+/// Vector register `n` lives at offset `16 * n` from host register `file`.
+/// For `.vx` forms, host register `scalar` holds the value of `rs1`; `.vi`
+/// forms overwrite it with the immediate. The `SCRATCH` registers are
+/// clobbered. 64-bit elements with a scalar operand, which RVV sign-extends
+/// from 32 bits, give `None`, as do masked and non-element-wise
+/// instructions.
+pub fn lower(
+    instruction: &Instruction,
+    arrangement: Arrangement,
+    file: u8,
+    scalar: u8,
+) -> Option<Vec<u32>> {
+    let [first, second] = SCRATCH;
+    let slot = |reg: u8| 16 * reg as u32;
+    let wide = arrangement == Arrangement::D2;
+    let (vd, vs2, mut code) = match *instruction {
+        Instruction::VaddVv {
+            vd,
+            vs2,
+            vs1,
+            vm: true,
+        } => (vd, vs2, vec![arm64::ldr_q(second, file, slot(vs1))]),
+        Instruction::VaddVx {
+            vd, vs2, vm: true, ..
+        } if !wide => (vd, vs2, vec![arm64::dup_v(arrangement, second, scalar)]),
+        Instruction::VaddVi {
+            vd,
+            vs2,
+            imm,
+            vm: true,
+        } if !wide => {
+            let mut code = arm64::mov_imm(scalar, imm as u32);
+            code.push(arm64::dup_v(arrangement, second, scalar));
+            (vd, vs2, code)
+        }
+        _ => return None,
+    };
+    code.extend([
+        arm64::ldr_q(first, file, slot(vs2)),
+        arm64::add_v(arrangement, first, first, second),
+        arm64::str_q(first, file, slot(vd)),
+    ]);
+    Some(code)
+}
@@ -0,0 +1,595 @@
+//! Textual assembly parsing
+//!
+//! Parses a single instruction written in the syntax `Instruction`'s `Display`
+//! implementation emits, so text, instructions and encodings can be converted
+//! freely in tests and tooling:
+//! - Operands are separated by commas: `addi x1, x2, -5`
+//! - Loads, stores and JALR use the `imm(rs)` form: `lw x5, 8(x6)`
+//! - Vector memory operands are written `(rs)`, and a trailing `v0.t` masks the operation
+//! - A trailing rounding mode (`rne`, `rtz`, `rdn`, `rup`, `rmm`) selects a static mode
+//!   for floating-point instructions; without one (or with `dyn`) the dynamic mode is used
+//!
+//! Integer registers may also be written with their ABI names (`a0`, `sp`, ...),
+//! and immediates are decimal or `0x`-prefixed hexadecimal. Parsed instructions
+//! are validated with the encoder, so out-of-range immediates are rejected.
+//!
+//! # Example
+//! ```
+//! use jigs::Instruction;
+//!
+//! let instruction = Instruction::parse("addi x1, x2, -5").unwrap();
+//! assert_eq!(instruction, Instruction::Addi { rd: 1, rs1: 2, imm: -5 });
+//! assert_eq!(instruction.to_string().parse::<Instruction>(), Ok(instruction));
+//!
+//! assert_eq!("lw a0, 8(sp)".parse(), Ok(Instruction::Lw { rd: 10, rs1: 2, imm: 8 }));
+//! ```
+
+use crate::{EncodeError, Instruction};
+use std::{fmt, str::FromStr};
+
+/// ABI register names indexed by register number
+const ABI_NAMES: [&str; 32] = [
+    "zero", "ra", "sp", "gp", "tp", "t0", "t1", "t2", "s0", "s1", "a0", "a1", "a2", "a3", "a4",
+    "a5", "a6", "a7", "s2", "s3", "s4", "s5", "s6", "s7", "s8", "s9", "s10", "s11", "t3", "t4",
+    "t5", "t6",
+];
+
+/// Floating-point rounding mode names indexed by `rm` value
+const ROUNDING_MODES: [&str; 5] = ["rne", "rtz", "rdn", "rup", "rmm"];
+
+/// Rounding mode selecting the dynamic mode from `fcsr`
+const ROUNDING_DYNAMIC: u8 = 7;
+
+/// Errors that can occur while parsing an instruction
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    /// The input contains no instruction
+    Empty,
+    /// The mnemonic is not recognized
+    UnknownMnemonic(String),
+    /// The instruction has the wrong number of operands (expected, found)
+    OperandCount(usize, usize),
+    /// An operand is not a valid register of the required kind
+    InvalidRegister(String),
+    /// An operand is not a valid number
+    InvalidImmediate(String),
+    /// An operand is malformed (memory operand or vector type)
+    InvalidOperand(String),
+    /// The instruction is well-formed but cannot be encoded
+    EncodeError(EncodeError),
+}
+
+/// Instruction constructor taking two registers and a third operand
+type Constructor<T> = fn(u8, u8, T) -> Instruction;
+
+/// Instruction constructor taking three registers and a rounding mode
+type RoundedConstructor = fn(u8, u8, u8, u8) -> Instruction;
+
+/// Register file an operand refers to
+#[derive(Clone, Copy)]
+enum RegisterFile {
+    /// Integer registers (`x0`-`x31` or ABI names)
+    Integer,
+    /// Floating-point registers (`f0`-`f31`)
+    Float,
+    /// Vector registers (`v0`-`v31`)
+    Vector,
+}
+
+impl Instruction {
+    /// Parse an instruction from assembly text
+    pub fn parse(text: &str) -> Result<Instruction, ParseError> {
+        text.parse()
+    }
+}
+
+impl FromStr for Instruction {
+    type Err = ParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(ParseError::Empty);
+        }
+        let (mnemonic, operands) = text.split_once(char::is_whitespace).unwrap_or((text, ""));
+        let mnemonic = mnemonic.to_ascii_lowercase();
+        let operands: Vec<&str> = match operands.trim() {
+            "" => Vec::new(),
+            operands => operands.split(',').map(str::trim).collect(),
+        };
+
+        let instruction = parse_instruction(&mnemonic, &operands)?;
+        match instruction {
+            Instruction::Unsupported(_) => Ok(instruction),
+            _ => match instruction.encode() {
+                Ok(_) => Ok(instruction),
+                Err(error) => Err(ParseError::EncodeError(error)),
+            },
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "Empty instruction"),
+            ParseError::UnknownMnemonic(mnemonic) => write!(f, "Unknown mnemonic: {}", mnemonic),
+            ParseError::OperandCount(expected, found) => {
+                write!(f, "Expected {} operands, found {}", expected, found)
+            }
+            ParseError::InvalidRegister(operand) => write!(f, "Invalid register: {}", operand),
+            ParseError::InvalidImmediate(operand) => write!(f, "Invalid immediate: {}", operand),
+            ParseError::InvalidOperand(operand) => write!(f, "Invalid operand: {}", operand),
+            ParseError::EncodeError(error) => write!(f, "Encode error: {}", error),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Parse an integer register name (`x0`-`x31`, ABI names, or `fp`)
+pub fn register(name: &str) -> Option<u8> {
+    if let Some(index) = name.strip_prefix('x') {
+        return index.parse::<u8>().ok().filter(|reg| *reg < 32);
+    }
+    if name == "fp" {
+        return Some(8);
+    }
+    ABI_NAMES
+        .iter()
+        .position(|abi| *abi == name)
+        .map(|reg| reg as u8)
+}
+
+/// Build an instruction from its lowercase mnemonic and operand list
+fn parse_instruction(mnemonic: &str, operands: &[&str]) -> Result<Instruction, ParseError> {
+    use RegisterFile::{Float, Integer, Vector};
+
+    if let Some(build) = register_type(mnemonic) {
+        let [rd, rs1, rs2] = fixed(operands)?;
+        return Ok(build(
+            reg(rd, Integer)?,
+            reg(rs1, Integer)?,
+            reg(rs2, Integer)?,
+        ));
+    }
+    if let Some(build) = immediate_type(mnemonic) {
+        let [rd, rs1, imm] = fixed(operands)?;
+        return Ok(build(
+            reg(rd, Integer)?,
+            reg(rs1, Integer)?,
+            immediate(imm)?,
+        ));
+    }
+    if let Some(build) = shift_type(mnemonic) {
+        let [rd, rs1, shamt] = fixed(operands)?;
+        let shamt = immediate(shamt)?;
+        let shamt = u8::try_from(shamt)
+            .map_err(|_| ParseError::EncodeError(EncodeError::InvalidImmediate("shamt", shamt)))?;
+        return Ok(build(reg(rd, Integer)?, reg(rs1, Integer)?, shamt));
+    }
+    if let Some(build) = load_type(mnemonic) {
+        let [rd, address] = fixed(operands)?;
+        let (imm, rs1) = memory(address)?;
+        return Ok(build(reg(rd, Integer)?, rs1, imm));
+    }
+    if let Some(build) = store_type(mnemonic) {
+        let [rs2, address] = fixed(operands)?;
+        let (imm, rs1) = memory(address)?;
+        return Ok(build(rs1, reg(rs2, Integer)?, imm));
+    }
+    if let Some(build) = branch_type(mnemonic) {
+        let [rs1, rs2, imm] = fixed(operands)?;
+        return Ok(build(
+            reg(rs1, Integer)?,
+            reg(rs2, Integer)?,
+            immediate(imm)?,
+        ));
+    }
+    if let Some(build) = float_type(mnemonic) {
+        let (operands, rm) = rounding(operands);
+        let [rd, rs1, rs2] = fixed(operands)?;
+        return Ok(build(
+            reg(rd, Float)?,
+            reg(rs1, Float)?,
+            reg(rs2, Float)?,
+            rm,
+        ));
+    }
+    if let Some((build, rd_file, rs1_file)) = convert_type(mnemonic) {
+        let (operands, rm) = rounding(operands);
+        let [rd, rs1] = fixed(operands)?;
+        return Ok(build(reg(rd, rd_file)?, reg(rs1, rs1_file)?, rm));
+    }
+    if let Some((store, width)) = vector_memory_type(mnemonic) {
+        let (operands, vm) = vector_mask(operands);
+        let [vd, address] = fixed(operands)?;
+        let rs1 = address
+            .strip_prefix('(')
+            .and_then(|rest| rest.strip_suffix(')'))
+            .ok_or_else(|| ParseError::InvalidOperand(address.to_string()))?;
+        let (vd, rs1) = (reg(vd, Vector)?, reg(rs1, Integer)?);
+        return Ok(if store {
+            Instruction::Vse {
+                vs3: vd,
+                rs1,
+                width,
+                vm,
+            }
+        } else {
+            Instruction::Vle { vd, rs1, width, vm }
+        });
+    }
+
+    match mnemonic {
+        "jal" => {
+            let [rd, imm] = fixed(operands)?;
+            Ok(Instruction::Jal {
+                rd: reg(rd, Integer)?,
+                imm: immediate(imm)?,
+            })
+        }
+        "jalr" => {
+            let [rd, address] = fixed(operands)?;
+            let (imm, rs1) = memory(address)?;
+            Ok(Instruction::Jalr {
+                rd: reg(rd, Integer)?,
+                rs1,
+                imm,
+            })
+        }
+        "lui" | "auipc" => {
+            let [rd, imm] = fixed(operands)?;
+            let (rd, imm) = (reg(rd, Integer)?, immediate(imm)? as u32);
+            Ok(if mnemonic == "lui" {
+                Instruction::Lui { rd, imm }
+            } else {
+                Instruction::Auipc { rd, imm }
+            })
+        }
+        "ecall" => fixed::<0>(operands).map(|_| Instruction::Ecall),
+        "ebreak" => fixed::<0>(operands).map(|_| Instruction::Ebreak),
+        "rdcycle" | "rdcycleh" | "rdtime" | "rdtimeh" | "rdinstret" | "rdinstreth" => {
+            let [rd] = fixed(operands)?;
+            let rd = reg(rd, Integer)?;
+            Ok(match mnemonic {
+                "rdcycle" => Instruction::Rdcycle { rd },
+                "rdcycleh" => Instruction::Rdcycleh { rd },
+                "rdtime" => Instruction::Rdtime { rd },
+                "rdtimeh" => Instruction::Rdtimeh { rd },
+                "rdinstret" => Instruction::Rdinstret { rd },
+                _ => Instruction::Rdinstreth { rd },
+            })
+        }
+        "vsetvli" => {
+            if operands.len() < 3 {
+                return Err(ParseError::OperandCount(3, operands.len()));
+            }
+            Ok(Instruction::Vsetvli {
+                rd: reg(operands[0], Integer)?,
+                rs1: reg(operands[1], Integer)?,
+                vtypei: vtype(&operands[2..])?,
+            })
+        }
+        "vadd.vv" | "vadd.vx" | "vadd.vi" => {
+            let (operands, vm) = vector_mask(operands);
+            let [vd, vs2, source] = fixed(operands)?;
+            let (vd, vs2) = (reg(vd, Vector)?, reg(vs2, Vector)?);
+            Ok(match mnemonic {
+                "vadd.vv" => Instruction::VaddVv {
+                    vd,
+                    vs2,
+                    vs1: reg(source, Vector)?,
+                    vm,
+                },
+                "vadd.vx" => Instruction::VaddVx {
+                    vd,
+                    vs2,
+                    rs1: reg(source, Integer)?,
+                    vm,
+                },
+                _ => Instruction::VaddVi {
+                    vd,
+                    vs2,
+                    imm: immediate(source)?,
+                    vm,
+                },
+            })
+        }
+        "flh" => {
+            let [rd, address] = fixed(operands)?;
+            let (imm, rs1) = memory(address)?;
+            Ok(Instruction::Flh {
+                rd: reg(rd, Float)?,
+                rs1,
+                imm,
+            })
+        }
+        "fsh" => {
+            let [rs2, address] = fixed(operands)?;
+            let (imm, rs1) = memory(address)?;
+            Ok(Instruction::Fsh {
+                rs1,
+                rs2: reg(rs2, Float)?,
+                imm,
+            })
+        }
+        "unsupported:" => {
+            let [word] = fixed(operands)?;
+            Ok(Instruction::Unsupported(immediate(word)? as u32))
+        }
+        _ => Err(ParseError::UnknownMnemonic(mnemonic.to_string())),
+    }
+}
+
+/// Constructor for R-type integer instructions
+fn register_type(mnemonic: &str) -> Option<Constructor<u8>> {
+    Some(match mnemonic {
+        "add" => |rd, rs1, rs2| Instruction::Add { rd, rs1, rs2 },
+        "sub" => |rd, rs1, rs2| Instruction::Sub { rd, rs1, rs2 },
+        "sll" => |rd, rs1, rs2| Instruction::Sll { rd, rs1, rs2 },
+        "xor" => |rd, rs1, rs2| Instruction::Xor { rd, rs1, rs2 },
+        "or" => |rd, rs1, rs2| Instruction::Or { rd, rs1, rs2 },
+        "srl" => |rd, rs1, rs2| Instruction::Srl { rd, rs1, rs2 },
+        "sra" => |rd, rs1, rs2| Instruction::Sra { rd, rs1, rs2 },
+        "slt" => |rd, rs1, rs2| Instruction::Slt { rd, rs1, rs2 },
+        "sltu" => |rd, rs1, rs2| Instruction::Sltu { rd, rs1, rs2 },
+        "and" => |rd, rs1, rs2| Instruction::And { rd, rs1, rs2 },
+        "mul" => |rd, rs1, rs2| Instruction::Mul { rd, rs1, rs2 },
+        "mulh" => |rd, rs1, rs2| Instruction::Mulh { rd, rs1, rs2 },
+        "mulhsu" => |rd, rs1, rs2| Instruction::Mulhsu { rd, rs1, rs2 },
+        "mulhu" => |rd, rs1, rs2| Instruction::Mulhu { rd, rs1, rs2 },
+        "div" => |rd, rs1, rs2| Instruction::Div { rd, rs1, rs2 },
+        "divu" => |rd, rs1, rs2| Instruction::Divu { rd, rs1, rs2 },
+        "rem" => |rd, rs1, rs2| Instruction::Rem { rd, rs1, rs2 },
+        "remu" => |rd, rs1, rs2| Instruction::Remu { rd, rs1, rs2 },
+        _ => return None,
+    })
+}
+
+/// Constructor for I-type arithmetic instructions
+fn immediate_type(mnemonic: &str) -> Option<Constructor<i32>> {
+    Some(match mnemonic {
+        "addi" => |rd, rs1, imm| Instruction::Addi { rd, rs1, imm },
+        "slti" => |rd, rs1, imm| Instruction::Slti { rd, rs1, imm },
+        "sltiu" => |rd, rs1, imm| Instruction::Sltiu { rd, rs1, imm },
+        "xori" => |rd, rs1, imm| Instruction::Xori { rd, rs1, imm },
+        "ori" => |rd, rs1, imm| Instruction::Ori { rd, rs1, imm },
+        "andi" => |rd, rs1, imm| Instruction::Andi { rd, rs1, imm },
+        _ => return None,
+    })
+}
+
+/// Constructor for immediate shift instructions
+fn shift_type(mnemonic: &str) -> Option<Constructor<u8>> {
+    Some(match mnemonic {
+        "slli" => |rd, rs1, shamt| Instruction::Slli { rd, rs1, shamt },
+        "srli" => |rd, rs1, shamt| Instruction::Srli { rd, rs1, shamt },
+        "srai" => |rd, rs1, shamt| Instruction::Srai { rd, rs1, shamt },
+        _ => return None,
+    })
+}
+
+/// Constructor for integer loads
+fn load_type(mnemonic: &str) -> Option<Constructor<i32>> {
+    Some(match mnemonic {
+        "lb" => |rd, rs1, imm| Instruction::Lb { rd, rs1, imm },
+        "lh" => |rd, rs1, imm| Instruction::Lh { rd, rs1, imm },
+        "lw" => |rd, rs1, imm| Instruction::Lw { rd, rs1, imm },
+        "lbu" => |rd, rs1, imm| Instruction::Lbu { rd, rs1, imm },
+        "lhu" => |rd, rs1, imm| Instruction::Lhu { rd, rs1, imm },
+        _ => return None,
+    })
+}
+
+/// Constructor for integer stores
+fn store_type(mnemonic: &str) -> Option<Constructor<i32>> {
+    Some(match mnemonic {
+        "sb" => |rs1, rs2, imm| Instruction::Sb { rs1, rs2, imm },
+        "sh" => |rs1, rs2, imm| Instruction::Sh { rs1, rs2, imm },
+        "sw" => |rs1, rs2, imm| Instruction::Sw { rs1, rs2, imm },
+        _ => return None,
+    })
+}
+
+/// Constructor for conditional branches
+fn branch_type(mnemonic: &str) -> Option<Constructor<i32>> {
+    Some(match mnemonic {
+        "beq" => |rs1, rs2, imm| Instruction::Beq { rs1, rs2, imm },
+        "bne" => |rs1, rs2, imm| Instruction::Bne { rs1, rs2, imm },
+        "blt" => |rs1, rs2, imm| Instruction::Blt { rs1, rs2, imm },
+        "bge" => |rs1, rs2, imm| Instruction::Bge { rs1, rs2, imm },
+        "bltu" => |rs1, rs2, imm| Instruction::Bltu { rs1, rs2, imm },
+        "bgeu" => |rs1, rs2, imm| Instruction::Bgeu { rs1, rs2, imm },
+        _ => return None,
+    })
+}
+
+/// Constructor for half-precision arithmetic
+fn float_type(mnemonic: &str) -> Option<RoundedConstructor> {
+    Some(match mnemonic {
+        "fadd.h" => |rd, rs1, rs2, rm| Instruction::FaddH { rd, rs1, rs2, rm },
+        "fsub.h" => |rd, rs1, rs2, rm| Instruction::FsubH { rd, rs1, rs2, rm },
+        "fmul.h" => |rd, rs1, rs2, rm| Instruction::FmulH { rd, rs1, rs2, rm },
+        "fdiv.h" => |rd, rs1, rs2, rm| Instruction::FdivH { rd, rs1, rs2, rm },
+        _ => return None,
+    })
+}
+
+/// Constructor and (rd, rs1) register files for half-precision conversions
+fn convert_type(mnemonic: &str) -> Option<(Constructor<u8>, RegisterFile, RegisterFile)> {
+    use RegisterFile::{Float, Integer};
+
+    Some(match mnemonic {
+        "fcvt.w.h" => (
+            |rd, rs1, rm| Instruction::FcvtWH { rd, rs1, rm },
+            Integer,
+            Float,
+        ),
+        "fcvt.wu.h" => (
+            |rd, rs1, rm| Instruction::FcvtWuH { rd, rs1, rm },
+            Integer,
+            Float,
+        ),
+        "fcvt.h.w" => (
+            |rd, rs1, rm| Instruction::FcvtHW { rd, rs1, rm },
+            Float,
+            Integer,
+        ),
+        "fcvt.h.wu" => (
+            |rd, rs1, rm| Instruction::FcvtHWu { rd, rs1, rm },
+            Float,
+            Integer,
+        ),
+        "fcvt.s.h" => (
+            |rd, rs1, rm| Instruction::FcvtSH { rd, rs1, rm },
+            Float,
+            Float,
+        ),
+        "fcvt.h.s" => (
+            |rd, rs1, rm| Instruction::FcvtHS { rd, rs1, rm },
+            Float,
+            Float,
+        ),
+        _ => return None,
+    })
+}
+
+/// Direction (true for stores) and element width of vector unit-stride memory instructions
+fn vector_memory_type(mnemonic: &str) -> Option<(bool, u8)> {
+    let (store, width) = if let Some(width) = mnemonic.strip_prefix("vle") {
+        (false, width)
+    } else {
+        (true, mnemonic.strip_prefix("vse")?)
+    };
+    let width = match width {
+        "8.v" => 8,
+        "16.v" => 16,
+        "32.v" => 32,
+        "64.v" => 64,
+        _ => return None,
+    };
+    Some((store, width))
+}
+
+/// Require exactly `N` operands
+fn fixed<'a, const N: usize>(operands: &[&'a str]) -> Result<[&'a str; N], ParseError> {
+    operands
+        .try_into()
+        .map_err(|_| ParseError::OperandCount(N, operands.len()))
+}
+
+/// Parse a register from the given register file
+fn reg(operand: &str, file: RegisterFile) -> Result<u8, ParseError> {
+    let name = operand.to_ascii_lowercase();
+    let parsed = match file {
+        RegisterFile::Integer => register(&name),
+        RegisterFile::Float => numbered(&name, 'f'),
+        RegisterFile::Vector => numbered(&name, 'v'),
+    };
+    parsed.ok_or_else(|| ParseError::InvalidRegister(operand.to_string()))
+}
+
+/// Parse a register written as a prefix letter followed by 0-31
+fn numbered(name: &str, prefix: char) -> Option<u8> {
+    let index = name.strip_prefix(prefix)?;
+    index.parse::<u8>().ok().filter(|reg| *reg < 32)
+}
+
+/// Parse a signed decimal or `0x`-prefixed hexadecimal immediate
+///
+/// Hexadecimal values are taken as 32-bit patterns, so `0xffffffff` is -1.
+fn immediate(operand: &str) -> Result<i32, ParseError> {
+    let (negative, digits) = match operand.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, operand),
+    };
+    let value = match digits.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16).ok().map(|value| {
+            let value = value as i32;
+            if negative {
+                value.wrapping_neg()
+            } else {
+                value
+            }
+        }),
+        None => operand.parse::<i32>().ok(),
+    };
+    value.ok_or_else(|| ParseError::InvalidImmediate(operand.to_string()))
+}
+
+/// Parse an `imm(rs)` memory operand into (imm, rs)
+fn memory(operand: &str) -> Result<(i32, u8), ParseError> {
+    let invalid = || ParseError::InvalidOperand(operand.to_string());
+    let (offset, base) = operand.split_once('(').ok_or_else(invalid)?;
+    let base = base.strip_suffix(')').ok_or_else(invalid)?;
+    let offset = match offset.trim() {
+        "" => 0,
+        offset => immediate(offset)?,
+    };
+    Ok((offset, reg(base.trim(), RegisterFile::Integer)?))
+}
+
+/// Split off an optional trailing rounding mode (`dyn` selects the dynamic mode)
+fn rounding<'a, 'b>(operands: &'b [&'a str]) -> (&'b [&'a str], u8) {
+    let Some((last, rest)) = operands.split_last() else {
+        return (operands, ROUNDING_DYNAMIC);
+    };
+    if last.eq_ignore_ascii_case("dyn") {
+        return (rest, ROUNDING_DYNAMIC);
+    }
+    match ROUNDING_MODES
+        .iter()
+        .position(|mode| mode.eq_ignore_ascii_case(last))
+    {
+        Some(rm) => (rest, rm as u8),
+        None => (operands, ROUNDING_DYNAMIC),
+    }
+}
+
+/// Split off an optional trailing `v0.t` mask operand (masked when present)
+fn vector_mask<'a, 'b>(operands: &'b [&'a str]) -> (&'b [&'a str], bool) {
+    match operands.split_last() {
+        Some((last, rest)) if last.eq_ignore_ascii_case("v0.t") => (rest, false),
+        _ => (operands, true),
+    }
+}
+
+/// Parse a vector type, either `eSEW, mLMUL, ta|tu, ma|mu` or a raw number
+fn vtype(operands: &[&str]) -> Result<u16, ParseError> {
+    if let [raw] = operands {
+        let value = immediate(raw)?;
+        return u16::try_from(value).map_err(|_| ParseError::InvalidOperand(raw.to_string()));
+    }
+    let [sew, lmul, tail, mask] = fixed(operands)?;
+    let invalid = |operand: &str| ParseError::InvalidOperand(operand.to_string());
+
+    let sew = match sew {
+        "e8" => 0,
+        "e16" => 1,
+        "e32" => 2,
+        "e64" => 3,
+        _ => return Err(invalid(sew)),
+    };
+    let lmul = match lmul {
+        "m1" => 0,
+        "m2" => 1,
+        "m4" => 2,
+        "m8" => 3,
+        "mf8" => 5,
+        "mf4" => 6,
+        "mf2" => 7,
+        _ => return Err(invalid(lmul)),
+    };
+    let tail = match tail {
+        "tu" => 0,
+        "ta" => 1,
+        _ => return Err(invalid(tail)),
+    };
+    let mask = match mask {
+        "mu" => 0,
+        "ma" => 1,
+        _ => return Err(invalid(mask)),
+    };
+    Ok(lmul | (sew << 3) | (tail << 6) | (mask << 7))
+}
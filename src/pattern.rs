@@ -0,0 +1,233 @@
+//! Declarative matching over instruction sequences
+//!
+//! Peephole optimizations and lint passes look for short runs of instructions
+//! with related operands, such as a LUI followed by an ADDI into the same
+//! register. A `Pattern` describes such a run as a sequence of `Template`s, each
+//! constraining an instruction's mnemonic and operand fields. Operands bound to
+//! the same variable must hold the same value across the whole sequence, and the
+//! bound values are reported in the resulting `Match`.
+//!
+//! # Example
+//! ```
+//! use jigs::Instruction;
+//! use jigs::pattern::{Field, Operand, Pattern, Template};
+//!
+//! // lui a, _ ; addi a, a, _
+//! let pattern = Pattern::new()
+//!     .then(Template::new("lui").with(Field::Rd, Operand::Var("a")))
+//!     .then(
+//!         Template::new("addi")
+//!             .with(Field::Rd, Operand::Var("a"))
+//!             .with(Field::Rs1, Operand::Var("a")),
+//!     );
+//!
+//! let code = [
+//!     Instruction::Lui { rd: 5, imm: 0x12345 },
+//!     Instruction::Addi { rd: 5, rs1: 5, imm: 0x678 },
+//! ];
+//! let found = pattern.find(&code);
+//! assert_eq!(found.len(), 1);
+//! assert_eq!(found[0].get("a"), Some(5));
+//! ```
+
+use crate::Instruction;
+use std::collections::BTreeMap;
+
+/// Operand field of an instruction, read through its accessor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// Destination register (`Instruction::rd`)
+    Rd,
+    /// First source register (`Instruction::rs1`)
+    Rs1,
+    /// Second source register (`Instruction::rs2`)
+    Rs2,
+    /// Immediate (`Instruction::imm`)
+    Imm,
+}
+
+/// Constraint on an operand field
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operand {
+    /// The field is present with any value
+    Any,
+    /// The field equals the given value
+    Value(i32),
+    /// The field is bound to a variable; every use of the variable must agree
+    Var(&'static str),
+}
+
+/// Constraint on a single instruction
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    /// Required mnemonic (None matches any instruction)
+    mnemonic: Option<&'static str>,
+    /// Field constraints
+    operands: Vec<(Field, Operand)>,
+}
+
+/// A sequence of templates matched against consecutive instructions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Pattern {
+    /// Templates in order
+    templates: Vec<Template>,
+}
+
+/// A successful match of a pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    /// Index of the first matched instruction
+    pub start: usize,
+    /// Number of matched instructions
+    pub len: usize,
+    /// Values bound to variables
+    pub bindings: BTreeMap<&'static str, i32>,
+}
+
+impl Field {
+    /// Read the field from an instruction
+    pub fn get(&self, instruction: &Instruction) -> Option<i32> {
+        match self {
+            Field::Rd => instruction.rd().map(|reg| reg.number() as i32),
+            Field::Rs1 => instruction.rs1().map(|reg| reg.number() as i32),
+            Field::Rs2 => instruction.rs2().map(|reg| reg.number() as i32),
+            Field::Imm => instruction.imm(),
+        }
+    }
+}
+
+impl Template {
+    /// Match instructions with the given mnemonic (as returned by `Instruction::mnemonic`)
+    pub fn new(mnemonic: &'static str) -> Self {
+        Template {
+            mnemonic: Some(mnemonic),
+            operands: Vec::new(),
+        }
+    }
+
+    /// Match any instruction
+    pub fn any() -> Self {
+        Template {
+            mnemonic: None,
+            operands: Vec::new(),
+        }
+    }
+
+    /// Add a constraint on an operand field
+    pub fn with(mut self, field: Field, operand: Operand) -> Self {
+        self.operands.push((field, operand));
+        self
+    }
+
+    /// Match a single instruction, extending `bindings` on success
+    ///
+    /// On failure `bindings` may contain partial bindings and should be discarded.
+    fn matches(
+        &self,
+        instruction: &Instruction,
+        bindings: &mut BTreeMap<&'static str, i32>,
+    ) -> bool {
+        if let Some(mnemonic) = self.mnemonic
+            && instruction.mnemonic() != mnemonic
+        {
+            return false;
+        }
+        self.operands.iter().all(|(field, operand)| {
+            let Some(value) = field.get(instruction) else {
+                return false;
+            };
+            match operand {
+                Operand::Any => true,
+                Operand::Value(expected) => value == *expected,
+                Operand::Var(name) => *bindings.entry(name).or_insert(value) == value,
+            }
+        })
+    }
+}
+
+impl Pattern {
+    /// Create an empty pattern
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a template to the sequence
+    pub fn then(mut self, template: Template) -> Self {
+        self.templates.push(template);
+        self
+    }
+
+    /// Number of instructions the pattern spans
+    pub fn len(&self) -> usize {
+        self.templates.len()
+    }
+
+    /// Check if the pattern has no templates
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+
+    /// Match the pattern against the instructions starting at `start`
+    pub fn match_at(&self, instructions: &[Instruction], start: usize) -> Option<Match> {
+        if self.templates.is_empty() {
+            return None;
+        }
+        let window = instructions.get(start..start.checked_add(self.len())?)?;
+        let mut bindings = BTreeMap::new();
+        self.templates
+            .iter()
+            .zip(window)
+            .all(|(template, instruction)| template.matches(instruction, &mut bindings))
+            .then(|| Match {
+                start,
+                len: self.len(),
+                bindings,
+            })
+    }
+
+    /// Find all non-overlapping matches, scanning from the start
+    pub fn find(&self, instructions: &[Instruction]) -> Vec<Match> {
+        let mut matches = Vec::new();
+        let mut index = 0;
+        while index < instructions.len() {
+            match self.match_at(instructions, index) {
+                Some(found) => {
+                    index += found.len;
+                    matches.push(found);
+                }
+                None => index += 1,
+            }
+        }
+        matches
+    }
+
+    /// Replace matches with the instructions returned by `rewrite`
+    ///
+    /// Matches are found as in `find()`. When `rewrite` returns `None` the
+    /// matched instructions are kept unchanged.
+    pub fn rewrite<F>(&self, instructions: &[Instruction], mut rewrite: F) -> Vec<Instruction>
+    where
+        F: FnMut(&Match, &[Instruction]) -> Option<Vec<Instruction>>,
+    {
+        let mut output = Vec::with_capacity(instructions.len());
+        let mut index = 0;
+        for found in self.find(instructions) {
+            output.extend_from_slice(&instructions[index..found.start]);
+            let matched = &instructions[found.start..found.start + found.len];
+            match rewrite(&found, matched) {
+                Some(replacement) => output.extend(replacement),
+                None => output.extend_from_slice(matched),
+            }
+            index = found.start + found.len;
+        }
+        output.extend_from_slice(&instructions[index..]);
+        output
+    }
+}
+
+impl Match {
+    /// Value bound to a variable
+    pub fn get(&self, name: &str) -> Option<i32> {
+        self.bindings.get(name).copied()
+    }
+}
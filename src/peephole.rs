@@ -0,0 +1,82 @@
+//! RISC-V level peephole optimization
+//!
+//! Rewrites instructions into cheaper equivalents before translation, one
+//! instruction at a time so every instruction keeps its PC and branch offsets
+//! stay valid. Writes to x0 without side effects become the canonical NOP,
+//! register copies spelled as arithmetic (`add rd, rs, x0`, `ori rd, rs, 0`,
+//! `slli rd, rs, 0`, ...) become `addi rd, rs, 0`, and copies of a register to
+//! itself become the NOP. Loads writing x0 are kept, as they can still fault.
+//!
+//! # Example
+//! ```
+//! use jigs::Instruction;
+//! use jigs::peephole;
+//!
+//! let code = [
+//!     Instruction::Or { rd: 5, rs1: 0, rs2: 6 },
+//!     Instruction::Slli { rd: 7, rs1: 7, shamt: 0 },
+//!     Instruction::Lui { rd: 0, imm: 1 },
+//! ];
+//! assert_eq!(
+//!     peephole::optimize(&code),
+//!     [
+//!         Instruction::Addi { rd: 5, rs1: 6, imm: 0 },
+//!         Instruction::Addi { rd: 0, rs1: 0, imm: 0 },
+//!         Instruction::Addi { rd: 0, rs1: 0, imm: 0 },
+//!     ]
+//! );
+//! ```
+
+use crate::{Instruction, InstructionKind, Register};
+
+/// The canonical NOP (`addi x0, x0, 0`)
+const NOP: Instruction = Instruction::Addi {
+    rd: 0,
+    rs1: 0,
+    imm: 0,
+};
+
+/// Simplify every instruction, keeping their number and order
+pub fn optimize(instructions: &[Instruction]) -> Vec<Instruction> {
+    instructions.iter().map(simplify).collect()
+}
+
+/// Rewrite one instruction into its simplest equivalent
+pub fn simplify(instruction: &Instruction) -> Instruction {
+    use Instruction::*;
+
+    if instruction.rd() == Some(Register::X(0))
+        && matches!(
+            instruction.kind(),
+            InstructionKind::Arithmetic | InstructionKind::Mul | InstructionKind::Div
+        )
+    {
+        return NOP;
+    }
+    let source = match *instruction {
+        Add { rs1, rs2: 0, .. }
+        | Sub { rs1, rs2: 0, .. }
+        | Or { rs1, rs2: 0, .. }
+        | Xor { rs1, rs2: 0, .. }
+        | Sll { rs1, rs2: 0, .. }
+        | Srl { rs1, rs2: 0, .. }
+        | Sra { rs1, rs2: 0, .. }
+        | Addi { rs1, imm: 0, .. }
+        | Ori { rs1, imm: 0, .. }
+        | Xori { rs1, imm: 0, .. }
+        | Slli { rs1, shamt: 0, .. }
+        | Srli { rs1, shamt: 0, .. }
+        | Srai { rs1, shamt: 0, .. } => rs1,
+        Add { rs1: 0, rs2, .. } | Or { rs1: 0, rs2, .. } | Xor { rs1: 0, rs2, .. } => rs2,
+        _ => return instruction.clone(),
+    };
+    match instruction.rd() {
+        Some(Register::X(rd)) if rd == source => NOP,
+        Some(Register::X(rd)) => Addi {
+            rd,
+            rs1: source,
+            imm: 0,
+        },
+        _ => instruction.clone(),
+    }
+}
@@ -0,0 +1,68 @@
+//! Guest register allocation for compiled code
+//!
+//! Compiled code keeps the guest registers a module uses most in ARM64
+//! callee-saved registers for the whole call, so they survive helper calls
+//! without saving. The remaining guest registers are spilled: they stay in the
+//! in-memory register file and are loaded and stored around each use. Mapped
+//! registers are loaded from the register file on entry and written back on
+//! every exit, so the register file is up to date whenever the host sees it.
+//!
+//! Allocation is static and deterministic: guest registers are ranked by the
+//! number of instructions referencing them, ties going to the lower register
+//! number, and every host register is always assigned so the prologue and exit
+//! stub have a fixed size.
+
+use crate::{Instruction, Register};
+
+/// ARM64 registers holding mapped guest registers (callee-saved x21-x28)
+pub(crate) const HOST_REGISTERS: [u8; 8] = [21, 22, 23, 24, 25, 26, 27, 28];
+
+/// Assignment of guest registers to ARM64 registers
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct RegisterMap {
+    /// ARM64 register holding each guest register, or None if spilled
+    host: [Option<u8>; 32],
+}
+
+impl RegisterMap {
+    /// Map the guest registers most used by `instructions`
+    ///
+    /// x0 is never mapped; it reads as the zero register instead.
+    pub(crate) fn allocate(instructions: &[Instruction]) -> Self {
+        let mut uses = [0usize; 32];
+        for instruction in instructions {
+            for reg in integer_registers(instruction).into_iter().flatten() {
+                uses[reg as usize & 0x1F] += 1;
+            }
+        }
+
+        // The sort is stable, so equally used registers stay in number order
+        let mut ranked: Vec<u8> = (1..32).collect();
+        ranked.sort_by_key(|&reg| std::cmp::Reverse(uses[reg as usize]));
+
+        let mut host = [None; 32];
+        for (&reg, &register) in ranked.iter().zip(&HOST_REGISTERS) {
+            host[reg as usize] = Some(register);
+        }
+        RegisterMap { host }
+    }
+
+    /// ARM64 register holding guest register `reg`, or None if it is spilled
+    pub(crate) fn host(&self, reg: u8) -> Option<u8> {
+        self.host.get(reg as usize).copied().flatten()
+    }
+
+    /// Iterate over (guest, host) register pairs in guest register order
+    pub(crate) fn mapped(&self) -> impl Iterator<Item = (u8, u8)> + '_ {
+        self.host
+            .iter()
+            .enumerate()
+            .filter_map(|(reg, host)| host.map(|host| (reg as u8, host)))
+    }
+}
+
+/// Integer registers named by an instruction's rd, rs1 and rs2 fields
+fn integer_registers(instruction: &Instruction) -> [Option<u8>; 3] {
+    [instruction.rd(), instruction.rs1(), instruction.rs2()]
+        .map(|register| register.and_then(Register::integer))
+}
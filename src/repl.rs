@@ -0,0 +1,372 @@
+//! Interactive command interpreter for poking at instances
+//!
+//! `Repl` wraps an `Instance` and executes textual commands against it: reading
+//! and writing registers and memory, disassembling around the PC, single-stepping
+//! through the interpreter, managing breakpoints and calling compiled functions.
+//! It is a lightweight alternative to full debugger integration and backs the
+//! `jigs repl` command of the example binary.
+//!
+//! # Commands
+//! - `regs` - Show all registers and the PC
+//! - `reg <r> [value]` - Read or write a register (`x5`, `a0`, `pc`, ...)
+//! - `mem <addr> [len]` - Hexdump guest memory, at most `MAX_DUMP_LENGTH` bytes
+//! - `regions` - List mapped memory regions
+//! - `write <addr> <byte>...` - Write bytes to guest memory
+//! - `dis [addr] [count]` - Disassemble instructions (defaults to around the PC)
+//! - `step [n]` - Execute `n` instructions with the interpreter
+//! - `continue [limit]` - Run until a breakpoint, trap or step limit
+//! - `break <addr>` / `delete <addr>` / `breaks` - Manage breakpoints
+//! - `call <index>` - Call a function in the attached module (aarch64 hosts
+//!   only, since it runs the compiled code)
+//!
+//! Numbers are decimal or `0x`-prefixed hexadecimal.
+//!
+//! # Example
+//! ```
+//! use jigs::{Instance, Memory, PageStore, Repl};
+//!
+//! let store = PageStore::new(16);
+//! let memory = Memory::new(&store, 16, 4);
+//! let mut repl = Repl::new(Instance::new(memory));
+//!
+//! repl.execute("write 0 0x93 0x00 0x50 0x00").unwrap(); // addi x1, x0, 5
+//! repl.execute("step").unwrap();
+//! assert_eq!(repl.execute("reg x1").unwrap(), "x1 = 0x00000005");
+//! ```
+
+use crate::{
+    Disassembler, Instance, Instruction, Interpreter,
+    memory::{MAX_DUMP_LENGTH, MemoryError},
+    parser::register,
+};
+use std::{collections::BTreeSet, fmt, fmt::Write};
+
+/// Default number of bytes shown by `mem`
+const DEFAULT_DUMP_LENGTH: u32 = 64;
+
+/// Default number of instructions shown by `dis`
+const DEFAULT_DISASSEMBLY_COUNT: u32 = 8;
+
+/// Default step limit for `continue`
+const DEFAULT_CONTINUE_LIMIT: usize = 1_000_000;
+
+/// Errors produced while executing a REPL command
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplError {
+    /// The command name is not recognized
+    UnknownCommand(String),
+    /// A required argument was not provided
+    MissingArgument(&'static str),
+    /// An argument could not be parsed
+    InvalidArgument(String),
+    /// A memory write failed
+    MemoryError(MemoryError),
+    /// Calling a compiled function failed
+    CallFailed(&'static str),
+}
+
+impl fmt::Display for ReplError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplError::UnknownCommand(command) => write!(f, "Unknown command: {}", command),
+            ReplError::MissingArgument(name) => write!(f, "Missing argument: {}", name),
+            ReplError::InvalidArgument(argument) => write!(f, "Invalid argument: {}", argument),
+            ReplError::MemoryError(error) => write!(f, "Memory error: {}", error),
+            ReplError::CallFailed(reason) => write!(f, "Call failed: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ReplError {}
+
+/// Interactive command interpreter operating on an instance
+pub struct Repl {
+    /// Instance being inspected
+    instance: Instance,
+    /// Interpreter used for stepping
+    interpreter: Interpreter,
+    /// Guest addresses that stop `continue`
+    breakpoints: BTreeSet<u32>,
+}
+
+impl Repl {
+    /// Create a REPL operating on the given instance
+    pub fn new(instance: Instance) -> Self {
+        Self {
+            instance,
+            interpreter: Interpreter::new(),
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    /// Get a reference to the instance
+    pub fn instance(&self) -> &Instance {
+        &self.instance
+    }
+
+    /// Get a mutable reference to the instance
+    pub fn instance_mut(&mut self) -> &mut Instance {
+        &mut self.instance
+    }
+
+    /// Consume the REPL and return the instance
+    pub fn into_instance(self) -> Instance {
+        self.instance
+    }
+
+    /// Execute a single command line and return its output
+    ///
+    /// Blank lines produce empty output.
+    pub fn execute(&mut self, line: &str) -> Result<String, ReplError> {
+        let mut words = line.split_whitespace();
+        let Some(command) = words.next() else {
+            return Ok(String::new());
+        };
+        let args: Vec<&str> = words.collect();
+
+        match command {
+            "help" => Ok(help()),
+            "regs" => Ok(self.registers()),
+            "reg" => self.register(&args),
+            "mem" => self.dump(&args),
+            "regions" => Ok(self
+                .instance
+                .memory()
+                .regions()
+                .map(|region| region.to_string())
+                .collect::<Vec<_>>()
+                .join("\n")),
+            "write" => self.write(&args),
+            "dis" => self.disassemble(&args),
+            "step" => self.step(&args),
+            "continue" => self.resume(&args),
+            "break" => {
+                let address = number(args.first(), "address")?;
+                self.breakpoints.insert(address);
+                Ok(format!("breakpoint at 0x{:08x}", address))
+            }
+            "delete" => {
+                let address = number(args.first(), "address")?;
+                if self.breakpoints.remove(&address) {
+                    Ok(format!("deleted breakpoint at 0x{:08x}", address))
+                } else {
+                    Err(ReplError::InvalidArgument(args[0].to_string()))
+                }
+            }
+            "breaks" => Ok(self
+                .breakpoints
+                .iter()
+                .map(|address| format!("0x{:08x}", address))
+                .collect::<Vec<_>>()
+                .join("\n")),
+            "call" => self.call(&args),
+            _ => Err(ReplError::UnknownCommand(command.to_string())),
+        }
+    }
+
+    /// Format all registers, four per line, followed by the PC
+    fn registers(&self) -> String {
+        let mut output = String::new();
+        for row in 0..8u8 {
+            let line: Vec<String> = (0..4u8)
+                .map(|column| {
+                    let reg = row * 4 + column;
+                    format!(
+                        "{:>4} = 0x{:08x}",
+                        format!("x{}", reg),
+                        self.instance.read_register(reg)
+                    )
+                })
+                .collect();
+            writeln!(output, "{}", line.join("  ")).unwrap();
+        }
+        write!(output, "  pc = 0x{:08x}", self.instance.pc()).unwrap();
+        output
+    }
+
+    /// Read or write a single register
+    fn register(&mut self, args: &[&str]) -> Result<String, ReplError> {
+        let name = *args.first().ok_or(ReplError::MissingArgument("register"))?;
+        let value = args
+            .get(1)
+            .map(|arg| number(Some(arg), "value"))
+            .transpose()?;
+
+        if name == "pc" {
+            if let Some(value) = value {
+                self.instance.set_pc(value);
+            }
+            return Ok(format!("pc = 0x{:08x}", self.instance.pc()));
+        }
+
+        let reg = register(name).ok_or_else(|| ReplError::InvalidArgument(name.to_string()))?;
+        if let Some(value) = value {
+            self.instance.write_register(reg, value);
+        }
+        Ok(format!(
+            "x{} = 0x{:08x}",
+            reg,
+            self.instance.read_register(reg)
+        ))
+    }
+
+    /// Hexdump guest memory, 16 bytes per line
+    fn dump(&self, args: &[&str]) -> Result<String, ReplError> {
+        let address = number(args.first(), "address")?;
+        let length = match args.get(1) {
+            Some(arg) => number(Some(arg), "length")?,
+            None => DEFAULT_DUMP_LENGTH,
+        };
+        if length > MAX_DUMP_LENGTH {
+            return Err(ReplError::InvalidArgument(args[1].to_string()));
+        }
+
+        Ok(self.instance.memory().dump(address, length))
+    }
+
+    /// Write bytes to guest memory
+    fn write(&mut self, args: &[&str]) -> Result<String, ReplError> {
+        let address = number(args.first(), "address")?;
+        if args.len() < 2 {
+            return Err(ReplError::MissingArgument("bytes"));
+        }
+
+        let mut bytes = Vec::with_capacity(args.len() - 1);
+        for arg in &args[1..] {
+            let value = number(Some(arg), "byte")?;
+            let byte =
+                u8::try_from(value).map_err(|_| ReplError::InvalidArgument(arg.to_string()))?;
+            bytes.push(byte);
+        }
+
+        self.instance
+            .memory_mut()
+            .write(address, &bytes)
+            .map_err(ReplError::MemoryError)?;
+        Ok(format!("wrote {} bytes at 0x{:08x}", bytes.len(), address))
+    }
+
+    /// Disassemble instructions, marking the current PC with `=>`
+    fn disassemble(&self, args: &[&str]) -> Result<String, ReplError> {
+        let pc = self.instance.pc();
+        let start = match args.first() {
+            Some(arg) => number(Some(arg), "address")?,
+            None => pc.saturating_sub(8),
+        };
+        let count = match args.get(1) {
+            Some(arg) => number(Some(arg), "count")?,
+            None => DEFAULT_DISASSEMBLY_COUNT,
+        };
+
+        let decoder = *self.interpreter.decoder();
+        let mut disassembler = Disassembler::new(start);
+        disassembler.set_decoder(decoder);
+        let mut lines = Vec::new();
+        let mut address = start;
+        for _ in 0..count {
+            let mut bytes = [0u8; 4];
+            self.instance.memory().read(address, &mut bytes);
+            let Some((_, instruction)) = decoder.stream(&bytes).next() else {
+                break;
+            };
+            let marker = if address == pc { "=>" } else { "  " };
+            lines.push(format!(
+                "{} 0x{:08x}:  {}",
+                marker,
+                address,
+                disassembler.format(&instruction, address)
+            ));
+            address = address
+                .wrapping_add(decoder.width(u16::from_le_bytes([bytes[0], bytes[1]]) as u32));
+        }
+        Ok(lines.join("\n"))
+    }
+
+    /// Execute instructions with the interpreter, ignoring breakpoints
+    fn step(&mut self, args: &[&str]) -> Result<String, ReplError> {
+        let count = match args.first() {
+            Some(arg) => number(Some(arg), "count")? as usize,
+            None => 1,
+        };
+
+        for _ in 0..count {
+            if let Err(trap) = self.interpreter.step(&mut self.instance) {
+                return Ok(self.stopped(&format!("trap: {}", trap)));
+            }
+        }
+        Ok(self.stopped("stepped"))
+    }
+
+    /// Run until a breakpoint is reached, a trap occurs or the step limit expires
+    fn resume(&mut self, args: &[&str]) -> Result<String, ReplError> {
+        let limit = match args.first() {
+            Some(arg) => number(Some(arg), "limit")? as usize,
+            None => DEFAULT_CONTINUE_LIMIT,
+        };
+
+        for count in 0..limit {
+            // Always execute at least one instruction so continuing from a
+            // breakpoint makes progress
+            if count > 0 && self.breakpoints.contains(&self.instance.pc()) {
+                return Ok(self.stopped("breakpoint"));
+            }
+            if let Err(trap) = self.interpreter.step(&mut self.instance) {
+                return Ok(self.stopped(&format!("trap: {}", trap)));
+            }
+        }
+        Ok(self.stopped("step limit reached"))
+    }
+
+    /// Call a function of the attached module
+    fn call(&mut self, args: &[&str]) -> Result<String, ReplError> {
+        let index = number(args.first(), "index")? as usize;
+        if !cfg!(target_arch = "aarch64") {
+            return Err(ReplError::CallFailed("compiled code only runs on aarch64"));
+        }
+        // SAFETY: attach() requires the module to outlive the instance, and the
+        // module only contains code produced by the compiler
+        unsafe { self.instance.call_function(index) }.map_err(ReplError::CallFailed)?;
+        Ok(format!("function {} returned", index))
+    }
+
+    /// Describe why execution stopped along with the instruction at the PC
+    fn stopped(&self, reason: &str) -> String {
+        let pc = self.instance.pc();
+        let instruction = Instruction::decode(self.instance.memory().read_u32(pc));
+        format!(
+            "{} at 0x{:08x}:  {}",
+            reason,
+            pc,
+            Disassembler::new(pc).format(&instruction, pc)
+        )
+    }
+}
+
+/// List the available commands
+fn help() -> String {
+    [
+        "regs                     show all registers",
+        "reg <r> [value]          read or write a register",
+        "mem <addr> [len]         hexdump memory",
+        "regions                  list mapped memory regions",
+        "write <addr> <byte>...   write bytes to memory",
+        "dis [addr] [count]       disassemble instructions",
+        "step [n]                 execute n instructions",
+        "continue [limit]         run until breakpoint or trap",
+        "break <addr>             set a breakpoint",
+        "delete <addr>            remove a breakpoint",
+        "breaks                   list breakpoints",
+        "call <index>             call a compiled function (aarch64)",
+    ]
+    .join("\n")
+}
+
+/// Parse a decimal or `0x`-prefixed hexadecimal number
+fn number(arg: Option<&&str>, name: &'static str) -> Result<u32, ReplError> {
+    let arg = *arg.ok_or(ReplError::MissingArgument(name))?;
+    let parsed = match arg.strip_prefix("0x") {
+        Some(hex) => u32::from_str_radix(hex, 16),
+        None => arg.parse(),
+    };
+    parsed.map_err(|_| ReplError::InvalidArgument(arg.to_string()))
+}
@@ -0,0 +1,232 @@
+//! Precompiled modules
+//!
+//! `Module::serialize()` saves a compiled module: its ARM64 code, the
+//! `SourceMap` between guest PCs and code offsets, its settings and
+//! `Metadata`, the RISC-V instructions behind the code and its data.
+//! `Module::deserialize()` turns the bytes back into a module ready to
+//! attach without compiling, so hosts can compile ahead of time once and
+//! start instantly afterwards.
+//!
+//! Compiled code holds no host addresses (see `compiler`), so it can be
+//! saved as is. It does depend on the compiler, so the bytes record the
+//! jigs version that produced them and only that version loads them.
+//!
+//! # Format
+//! All integers are little-endian `u32`, with `u64` stored as two of them,
+//! low half first; strings, byte blobs and lists are prefixed with their
+//! length.
+//! ```text
+//! magic "JIGM" | format version | jigs version
+//! max code size | opt level | resumable | block counters | base | constants
+//! extensions (bit 0 M, 1 C, 2 V, 3 Zfh) | function count | pc...
+//! instruction count | word...
+//! code
+//! source map: base | offsets | resume (0 for none, else 1 + offset)
+//!             | functions | frames (offset, state)... | counters
+//! metadata present | source hash (u64) | jigs version | opt level
+//!             | resumable | block counters | base | constants | functions
+//!             | decoder extensions | used extensions
+//!             | created (u64 seconds since the epoch) | nanoseconds
+//! data count | (address, bytes, zeroed tail length)...
+//! ```
+//!
+//! # Example
+//! ```
+//! use jigs::{Instruction, Module};
+//!
+//! let mut module = Module::new(4).unwrap();
+//! module.set_code(&Instruction::Ecall.encode().unwrap().to_le_bytes()).unwrap();
+//!
+//! let bytes = module.serialize().unwrap();
+//! let loaded = Module::deserialize(&bytes).unwrap();
+//! assert_eq!(loaded.code(), module.code());
+//! assert_eq!(loaded.source_map(), module.source_map());
+//! assert!(loaded.compile_stats().is_none());
+//! ```
+
+use crate::{
+    compiler::{ConstantStrategy, OptLevel},
+    decoder::Extensions,
+    module::CompileError,
+};
+use std::fmt;
+
+/// Magic bytes identifying a serialized module
+pub const MODULE_MAGIC: [u8; 4] = *b"JIGM";
+
+/// Current serialized module format version
+pub const MODULE_VERSION: u32 = 3;
+
+/// Version of jigs, whose compiler produced the code
+pub(crate) const COMPILER: &str = env!("CARGO_PKG_VERSION");
+
+/// Errors that can occur while deserializing a module
+#[derive(Debug, Clone, PartialEq)]
+pub enum SerializeError {
+    /// The data does not start with the module magic
+    InvalidMagic,
+    /// The module was written in an unsupported format version
+    UnsupportedVersion(u32),
+    /// The code was compiled by another jigs version
+    CompilerMismatch(String),
+    /// The data ended before the module was complete
+    Truncated,
+    /// A setting has a value no module can have
+    InvalidSetting(&'static str),
+    /// The source map points outside the code or does not match the base or
+    /// function table
+    InvalidSourceMap,
+    /// Creating the module failed
+    CompileError(CompileError),
+}
+
+/// Cursor over serialized data
+pub(crate) struct Reader<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Read from the start of `bytes`
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Reader { bytes, offset: 0 }
+    }
+
+    /// Take the next `length` bytes
+    pub(crate) fn take(&mut self, length: usize) -> Result<&'a [u8], SerializeError> {
+        let end = self
+            .offset
+            .checked_add(length)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(SerializeError::Truncated)?;
+        let bytes = &self.bytes[self.offset..end];
+        self.offset = end;
+        Ok(bytes)
+    }
+
+    /// Read a little-endian u32
+    pub(crate) fn u32(&mut self) -> Result<u32, SerializeError> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Read a little-endian u64 stored as two u32, low half first
+    pub(crate) fn u64(&mut self) -> Result<u64, SerializeError> {
+        let low = self.u32()? as u64;
+        Ok(low | (self.u32()? as u64) << 32)
+    }
+
+    /// Read a u32 that must be 0 or 1
+    pub(crate) fn bool(&mut self, setting: &'static str) -> Result<bool, SerializeError> {
+        match self.u32()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SerializeError::InvalidSetting(setting)),
+        }
+    }
+
+    /// Read an `OptLevel`
+    pub(crate) fn opt_level(&mut self) -> Result<OptLevel, SerializeError> {
+        match self.u32()? {
+            0 => Ok(OptLevel::O0),
+            1 => Ok(OptLevel::O1),
+            2 => Ok(OptLevel::O2),
+            _ => Err(SerializeError::InvalidSetting("opt level")),
+        }
+    }
+
+    /// Read a `ConstantStrategy`
+    pub(crate) fn constants(&mut self) -> Result<ConstantStrategy, SerializeError> {
+        match self.u32()? {
+            0 => Ok(ConstantStrategy::MoveWide),
+            1 => Ok(ConstantStrategy::LiteralPool),
+            _ => Err(SerializeError::InvalidSetting("constant strategy")),
+        }
+    }
+
+    /// Read `Extensions` as bits (0 M, 1 C, 2 V, 3 Zfh)
+    pub(crate) fn extensions(&mut self) -> Result<Extensions, SerializeError> {
+        let bits = self.u32()?;
+        if bits > 0xF {
+            return Err(SerializeError::InvalidSetting("extensions"));
+        }
+        Ok(Extensions {
+            m: bits & 1 != 0,
+            c: bits & 2 != 0,
+            v: bits & 4 != 0,
+            zfh: bits & 8 != 0,
+        })
+    }
+
+    /// Read a length-prefixed byte blob
+    pub(crate) fn blob(&mut self) -> Result<&'a [u8], SerializeError> {
+        let length = self.u32()? as usize;
+        self.take(length)
+    }
+
+    /// Read a length-prefixed list of u32
+    pub(crate) fn list(&mut self) -> Result<Vec<u32>, SerializeError> {
+        let count = self.u32()? as usize;
+        let bytes = self.take(count.checked_mul(4).ok_or(SerializeError::Truncated)?)?;
+        Ok(bytes
+            .chunks_exact(4)
+            .map(|word| u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+            .collect())
+    }
+}
+
+impl fmt::Display for SerializeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SerializeError::InvalidMagic => write!(f, "Not a serialized module"),
+            SerializeError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported module format version: {}", version)
+            }
+            SerializeError::CompilerMismatch(version) => {
+                write!(f, "Module was compiled by jigs {}", version)
+            }
+            SerializeError::Truncated => write!(f, "Module is truncated"),
+            SerializeError::InvalidSetting(setting) => write!(f, "Invalid {}", setting),
+            SerializeError::InvalidSourceMap => write!(f, "Source map does not match the code"),
+            SerializeError::CompileError(error) => write!(f, "Compile error: {:?}", error),
+        }
+    }
+}
+
+impl std::error::Error for SerializeError {}
+
+/// Append a little-endian u32
+pub(crate) fn push_u32(bytes: &mut Vec<u8>, value: u32) {
+    bytes.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Append a little-endian u64 as two u32, low half first
+pub(crate) fn push_u64(bytes: &mut Vec<u8>, value: u64) {
+    push_u32(bytes, value as u32);
+    push_u32(bytes, (value >> 32) as u32);
+}
+
+/// Append `Extensions` as bits (0 M, 1 C, 2 V, 3 Zfh)
+pub(crate) fn push_extensions(bytes: &mut Vec<u8>, extensions: &Extensions) {
+    let flags = [extensions.m, extensions.c, extensions.v, extensions.zfh];
+    let bits = flags
+        .iter()
+        .enumerate()
+        .map(|(bit, &enabled)| (enabled as u32) << bit)
+        .sum();
+    push_u32(bytes, bits);
+}
+
+/// Append a length-prefixed byte blob
+pub(crate) fn push_blob(bytes: &mut Vec<u8>, blob: &[u8]) {
+    push_u32(bytes, blob.len() as u32);
+    bytes.extend_from_slice(blob);
+}
+
+/// Append a length-prefixed list of u32
+pub(crate) fn push_list(bytes: &mut Vec<u8>, list: impl ExactSizeIterator<Item = u32>) {
+    push_u32(bytes, list.len() as u32);
+    for value in list {
+        push_u32(bytes, value);
+    }
+}
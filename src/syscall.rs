@@ -0,0 +1,612 @@
+//! Guest syscall policy and auditing
+//!
+//! Guests request host services with ECALL using the RISC-V Linux convention:
+//! the syscall number in `a7`, arguments in `a0`-`a5` and the result returned in
+//! `a0`. A `Policy` decides what happens to each request. Rules match on the
+//! syscall number and optional argument predicates (including path prefixes read
+//! from guest memory), and either allow the call, deny it with an errno, or route
+//! it to a custom handler. Every decision is recorded in an audit log so
+//! operators can review exactly what a guest attempted and what it was permitted
+//! to do.
+//!
+//! Policies also enforce host resource quotas: a token bucket limits the syscall
+//! rate, and calls serviced by the host are limited in the number of files held
+//! open and total bytes written. Exceeding a quota fails the call with an errno
+//! in the guest and is counted in the host-visible `Metrics`.
+//!
+//! # Example
+//! ```
+//! use jigs::{Instance, Memory, PageStore};
+//! use jigs::syscall::{Action, EPERM, Policy, Predicate, Rule, Verdict};
+//!
+//! let store = PageStore::new(16);
+//! let mut instance = Instance::new(Memory::new(&store, 16, 4));
+//!
+//! let mut policy = Policy::new(Action::Deny(EPERM));
+//! policy.add(Rule::new(64, Action::Allow).with(Predicate::Equals { arg: 0, value: 1 }));
+//!
+//! // write(1, ...) is allowed
+//! instance.write_register(17, 64);
+//! instance.write_register(10, 1);
+//! assert_eq!(policy.dispatch(&mut instance), Verdict::Allowed);
+//!
+//! // write(2, ...) falls through to the default and fails with -EPERM
+//! instance.write_register(10, 2);
+//! assert_eq!(policy.dispatch(&mut instance), Verdict::Denied(EPERM));
+//! assert_eq!(instance.read_register(10), -EPERM as u32);
+//! assert_eq!(policy.audit().len(), 2);
+//! ```
+
+use crate::{
+    Instance,
+    memory::{Memory, MemoryError, Permissions},
+};
+use std::{
+    collections::BTreeSet,
+    fmt,
+    time::{Duration, Instant},
+};
+
+/// Operation not permitted
+pub const EPERM: i32 = 1;
+
+/// No such file or directory
+pub const ENOENT: i32 = 2;
+
+/// Resource temporarily unavailable
+pub const EAGAIN: i32 = 11;
+
+/// Out of memory
+pub const ENOMEM: i32 = 12;
+
+/// Permission denied
+pub const EACCES: i32 = 13;
+
+/// Invalid argument
+pub const EINVAL: i32 = 22;
+
+/// Too many open files
+pub const EMFILE: i32 = 24;
+
+/// Function not implemented
+pub const ENOSYS: i32 = 38;
+
+/// Disk quota exceeded
+pub const EDQUOT: i32 = 122;
+
+/// `openat` syscall number
+pub const SYS_OPENAT: u32 = 56;
+
+/// `close` syscall number
+pub const SYS_CLOSE: u32 = 57;
+
+/// `write` syscall number
+pub const SYS_WRITE: u32 = 64;
+
+/// `mprotect` syscall number
+pub const SYS_MPROTECT: u32 = 226;
+
+/// Pages may be read
+pub const PROT_READ: u32 = 0x1;
+
+/// Pages may be written
+pub const PROT_WRITE: u32 = 0x2;
+
+/// Pages may be executed
+pub const PROT_EXEC: u32 = 0x4;
+
+/// Register holding the syscall number (a7)
+const NUMBER_REGISTER: u8 = 17;
+
+/// First argument register (a0), also receives the result
+const ARGUMENT_REGISTER: u8 = 10;
+
+/// Number of syscall argument registers (a0-a5)
+const ARGUMENT_COUNT: usize = 6;
+
+/// Maximum length of a guest path read for predicate matching
+const MAX_PATH_LENGTH: usize = 4096;
+
+/// Custom syscall handler returning the value placed in `a0`
+pub type Handler = Box<dyn FnMut(&mut Instance, &Syscall) -> u32>;
+
+/// A syscall request captured from guest registers
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Syscall {
+    /// Syscall number (a7)
+    pub number: u32,
+    /// Arguments (a0-a5)
+    pub args: [u32; ARGUMENT_COUNT],
+}
+
+/// Condition on a syscall argument
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Predicate {
+    /// Argument equals `value`
+    Equals { arg: usize, value: u32 },
+    /// Argument masked with `mask` equals `value`
+    Masked { arg: usize, mask: u32, value: u32 },
+    /// Argument points to a NUL-terminated guest path starting with `prefix`
+    PathPrefix { arg: usize, prefix: String },
+}
+
+/// What to do with a matching syscall
+pub enum Action {
+    /// Let the host implementation service the call
+    Allow,
+    /// Fail the call, returning `-errno` in `a0`
+    Deny(i32),
+    /// Service the call with a custom handler
+    Handle(Handler),
+}
+
+/// A policy rule matching a syscall number and argument predicates
+pub struct Rule {
+    /// Syscall number this rule applies to
+    number: u32,
+    /// Predicates that must all hold
+    predicates: Vec<Predicate>,
+    /// Action taken when the rule matches
+    action: Action,
+}
+
+/// Outcome of a policy decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    /// The call was allowed and must be serviced by the caller
+    Allowed,
+    /// The call was denied with the given errno
+    Denied(i32),
+    /// The call was serviced by a handler which returned the given value
+    Handled(u32),
+    /// The call was rejected because a quota was exceeded
+    Exceeded(Quota),
+}
+
+/// Host resource quota kinds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quota {
+    /// Syscall rate limit (fails with `EAGAIN`)
+    Rate,
+    /// Maximum open files (fails with `EMFILE`)
+    OpenFiles,
+    /// Maximum total bytes written (fails with `EDQUOT`)
+    BytesWritten,
+}
+
+/// Resource limits enforced by a policy (None means unlimited)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum files held open through host-serviced `openat`
+    pub open_files: Option<u32>,
+    /// Maximum total bytes written through host-serviced `write`
+    pub bytes_written: Option<u64>,
+    /// Token bucket rate limit as (syscalls per second, burst size)
+    pub rate: Option<(u32, u32)>,
+}
+
+/// Host-visible syscall metrics
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Metrics {
+    /// Total syscalls dispatched
+    pub syscalls: u64,
+    /// Syscalls allowed through to the host
+    pub allowed: u64,
+    /// Syscalls denied by a rule or the default action
+    pub denied: u64,
+    /// Syscalls serviced by a custom handler
+    pub handled: u64,
+    /// Syscalls rejected by the rate limit
+    pub throttled: u64,
+    /// Syscalls rejected by the open file or bytes written quota
+    pub exceeded: u64,
+    /// Files currently open through host-serviced `openat`
+    pub open_files: u32,
+    /// Highest number of files open at once
+    pub peak_open_files: u32,
+    /// Total bytes written
+    pub bytes_written: u64,
+}
+
+/// Token bucket rate limiter
+#[derive(Debug, Clone, Copy)]
+pub struct TokenBucket {
+    /// Tokens added per second
+    rate: u32,
+    /// Maximum tokens held
+    burst: u32,
+    /// Tokens currently available (fractional to allow slow rates)
+    tokens: f64,
+    /// Time of the last refill
+    refilled: Instant,
+}
+
+/// A single audited policy decision
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// Guest PC of the ECALL
+    pub pc: u32,
+    /// The syscall request
+    pub syscall: Syscall,
+    /// Index of the matching rule (None if the default action applied or
+    /// the rate limit rejected the call before any rule was evaluated)
+    pub rule: Option<usize>,
+    /// The decision taken
+    pub verdict: Verdict,
+}
+
+/// Syscall allow/deny policy with an audit log
+pub struct Policy {
+    /// Rules evaluated in insertion order, first match wins
+    rules: Vec<Rule>,
+    /// Action taken when no rule matches
+    default: Action,
+    /// Log of every decision
+    audit: Vec<AuditEntry>,
+    /// Resource limits
+    limits: Limits,
+    /// Rate limiter (present when a rate limit is configured)
+    bucket: Option<TokenBucket>,
+    /// Usage counters
+    metrics: Metrics,
+    /// File descriptors returned by host-serviced `openat` and not yet closed
+    files: BTreeSet<u32>,
+    /// Allowed syscall awaiting its result
+    pending: Option<Syscall>,
+}
+
+impl Syscall {
+    /// Capture the syscall request from an instance's registers
+    pub fn from_instance(instance: &Instance) -> Self {
+        let mut args = [0; ARGUMENT_COUNT];
+        for (index, arg) in args.iter_mut().enumerate() {
+            *arg = instance.read_register(ARGUMENT_REGISTER + index as u8);
+        }
+        Syscall {
+            number: instance.read_register(NUMBER_REGISTER),
+            args,
+        }
+    }
+}
+
+impl Predicate {
+    /// Check whether the predicate holds for a syscall
+    ///
+    /// Arguments outside a0-a5 never match. Path predicates read the guest
+    /// string from memory and do not match if it is not NUL-terminated within
+    /// the maximum path length.
+    pub fn matches(&self, syscall: &Syscall, memory: &Memory) -> bool {
+        match self {
+            Predicate::Equals { arg, value } => syscall.args.get(*arg) == Some(value),
+            Predicate::Masked { arg, mask, value } => syscall
+                .args
+                .get(*arg)
+                .is_some_and(|actual| actual & mask == *value),
+            Predicate::PathPrefix { arg, prefix } => syscall
+                .args
+                .get(*arg)
+                .and_then(|address| path(memory, *address))
+                .is_some_and(|path| path.starts_with(prefix.as_bytes())),
+        }
+    }
+}
+
+impl Rule {
+    /// Create a rule applying `action` to every call of syscall `number`
+    pub fn new(number: u32, action: Action) -> Self {
+        Rule {
+            number,
+            predicates: Vec::new(),
+            action,
+        }
+    }
+
+    /// Add a predicate that must hold for the rule to match
+    pub fn with(mut self, predicate: Predicate) -> Self {
+        self.predicates.push(predicate);
+        self
+    }
+
+    /// Check whether the rule matches a syscall
+    pub fn matches(&self, syscall: &Syscall, memory: &Memory) -> bool {
+        syscall.number == self.number
+            && self
+                .predicates
+                .iter()
+                .all(|predicate| predicate.matches(syscall, memory))
+    }
+}
+
+impl Quota {
+    /// Errno returned to the guest when this quota is exceeded
+    pub fn errno(&self) -> i32 {
+        match self {
+            Quota::Rate => EAGAIN,
+            Quota::OpenFiles => EMFILE,
+            Quota::BytesWritten => EDQUOT,
+        }
+    }
+}
+
+impl TokenBucket {
+    /// Create a full bucket adding `rate` tokens per second up to `burst`
+    pub fn new(rate: u32, burst: u32, now: Instant) -> Self {
+        TokenBucket {
+            rate,
+            burst,
+            tokens: burst as f64,
+            refilled: now,
+        }
+    }
+
+    /// Take a token at time `now`, returning false if none are available
+    pub fn take(&mut self, now: Instant) -> bool {
+        let elapsed = now.saturating_duration_since(self.refilled);
+        self.refilled = now;
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.rate as f64).min(self.burst as f64);
+        if self.tokens < 1.0 {
+            return false;
+        }
+        self.tokens -= 1.0;
+        true
+    }
+
+    /// Time until the next token becomes available (zero if one is available)
+    ///
+    /// A bucket with rate 0 never refills, so once empty it waits
+    /// `Duration::MAX`.
+    pub fn wait(&self) -> Duration {
+        if self.tokens >= 1.0 {
+            return Duration::ZERO;
+        }
+        if self.rate == 0 {
+            return Duration::MAX;
+        }
+        Duration::from_secs_f64((1.0 - self.tokens) / self.rate as f64)
+    }
+}
+
+impl Policy {
+    /// Create a policy with no rules and the given default action
+    pub fn new(default: Action) -> Self {
+        Policy {
+            rules: Vec::new(),
+            default,
+            audit: Vec::new(),
+            limits: Limits::default(),
+            bucket: None,
+            metrics: Metrics::default(),
+            files: BTreeSet::new(),
+            pending: None,
+        }
+    }
+
+    /// Set the resource limits enforced by this policy
+    ///
+    /// Setting a rate limit starts with a full bucket. Current usage counters
+    /// are kept, so lowering a limit below current usage rejects further calls.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.bucket = limits
+            .rate
+            .map(|(rate, burst)| TokenBucket::new(rate, burst, Instant::now()));
+        self.limits = limits;
+    }
+
+    /// Get the resource limits
+    pub fn limits(&self) -> &Limits {
+        &self.limits
+    }
+
+    /// Get the usage metrics
+    pub fn metrics(&self) -> &Metrics {
+        &self.metrics
+    }
+
+    /// Append a rule, returning its index
+    ///
+    /// Rules are evaluated in the order they were added.
+    pub fn add(&mut self, rule: Rule) -> usize {
+        self.rules.push(rule);
+        self.rules.len() - 1
+    }
+
+    /// Decide and apply the policy for the syscall requested by an instance
+    ///
+    /// Denied calls and calls exceeding a quota write `-errno` to `a0`, and
+    /// handled calls write the handler's return value to `a0`. Allowed calls
+    /// leave the registers untouched for the caller to service, after which the
+    /// caller reports the result with `complete()`. The PC is not modified;
+    /// callers resume execution after the ECALL.
+    pub fn dispatch(&mut self, instance: &mut Instance) -> Verdict {
+        self.dispatch_at(instance, Instant::now())
+    }
+
+    /// Dispatch a syscall as if it was requested at time `now`
+    pub fn dispatch_at(&mut self, instance: &mut Instance, now: Instant) -> Verdict {
+        let syscall = Syscall::from_instance(instance);
+        self.metrics.syscalls += 1;
+        self.pending = None;
+
+        let (rule, verdict) = match self.bucket.as_mut().map(|bucket| bucket.take(now)) {
+            Some(false) => (None, Verdict::Exceeded(Quota::Rate)),
+            _ => self.decide(instance, &syscall),
+        };
+
+        match verdict {
+            Verdict::Allowed => {
+                self.metrics.allowed += 1;
+                self.pending = Some(syscall);
+            }
+            Verdict::Denied(errno) => {
+                self.metrics.denied += 1;
+                instance.write_register(ARGUMENT_REGISTER, errno.wrapping_neg() as u32);
+            }
+            Verdict::Handled(result) => {
+                self.metrics.handled += 1;
+                instance.write_register(ARGUMENT_REGISTER, result);
+            }
+            Verdict::Exceeded(quota) => {
+                match quota {
+                    Quota::Rate => self.metrics.throttled += 1,
+                    _ => self.metrics.exceeded += 1,
+                }
+                instance.write_register(ARGUMENT_REGISTER, quota.errno().wrapping_neg() as u32);
+            }
+        }
+
+        self.audit.push(AuditEntry {
+            pc: instance.pc(),
+            syscall,
+            rule,
+            verdict,
+        });
+        verdict
+    }
+
+    /// Record the result of the last allowed syscall after the host serviced it
+    ///
+    /// Reads the result from `a0` to track open files and bytes written. Only
+    /// closing a descriptor returned by a tracked `openat` lowers the open file
+    /// count. Does nothing if the last dispatched syscall was not allowed.
+    pub fn complete(&mut self, instance: &Instance) {
+        let Some(syscall) = self.pending.take() else {
+            return;
+        };
+        let result = instance.read_register(ARGUMENT_REGISTER) as i32;
+        if result < 0 {
+            return;
+        }
+
+        match syscall.number {
+            SYS_OPENAT => {
+                if self.files.insert(result as u32) {
+                    self.metrics.open_files += 1;
+                }
+                self.metrics.peak_open_files =
+                    self.metrics.peak_open_files.max(self.metrics.open_files);
+            }
+            SYS_CLOSE => {
+                if self.files.remove(&syscall.args[0]) {
+                    self.metrics.open_files -= 1;
+                }
+            }
+            SYS_WRITE => self.metrics.bytes_written += result as u64,
+            _ => {}
+        }
+    }
+
+    /// Evaluate rules and quotas, running the handler if one matches
+    fn decide(&mut self, instance: &mut Instance, syscall: &Syscall) -> (Option<usize>, Verdict) {
+        let rule = self
+            .rules
+            .iter()
+            .position(|rule| rule.matches(syscall, instance.memory()));
+        let action = match rule {
+            Some(index) => &mut self.rules[index].action,
+            None => &mut self.default,
+        };
+
+        let verdict = match action {
+            Action::Allow => match self.exceeded(syscall) {
+                Some(quota) => Verdict::Exceeded(quota),
+                None => Verdict::Allowed,
+            },
+            Action::Deny(errno) => Verdict::Denied(*errno),
+            Action::Handle(handler) => Verdict::Handled(handler(instance, syscall)),
+        };
+        (rule, verdict)
+    }
+
+    /// Check whether servicing a syscall on the host would exceed a quota
+    fn exceeded(&self, syscall: &Syscall) -> Option<Quota> {
+        match syscall.number {
+            SYS_OPENAT => self
+                .limits
+                .open_files
+                .filter(|limit| self.metrics.open_files >= *limit)
+                .map(|_| Quota::OpenFiles),
+            SYS_WRITE => self
+                .limits
+                .bytes_written
+                .filter(|limit| self.metrics.bytes_written + syscall.args[2] as u64 > *limit)
+                .map(|_| Quota::BytesWritten),
+            _ => None,
+        }
+    }
+
+    /// Get the audit log
+    pub fn audit(&self) -> &[AuditEntry] {
+        &self.audit
+    }
+
+    /// Take the audit log, leaving it empty
+    pub fn take_audit(&mut self) -> Vec<AuditEntry> {
+        std::mem::take(&mut self.audit)
+    }
+}
+
+impl fmt::Display for Quota {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Quota::Rate => write!(f, "rate"),
+            Quota::OpenFiles => write!(f, "open files"),
+            Quota::BytesWritten => write!(f, "bytes written"),
+        }
+    }
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "0x{:08x}: syscall {}(", self.pc, self.syscall.number)?;
+        for (index, arg) in self.syscall.args.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "0x{:x}", arg)?;
+        }
+        write!(f, ") ")?;
+        match self.verdict {
+            Verdict::Allowed => write!(f, "allowed")?,
+            Verdict::Denied(errno) => write!(f, "denied errno {}", errno)?,
+            Verdict::Handled(result) => write!(f, "handled -> 0x{:x}", result)?,
+            Verdict::Exceeded(quota) => write!(f, "exceeded {} quota", quota)?,
+        }
+        match (self.rule, self.verdict) {
+            (_, Verdict::Exceeded(Quota::Rate)) => Ok(()),
+            (Some(index), _) => write!(f, " (rule {})", index),
+            (None, _) => write!(f, " (default)"),
+        }
+    }
+}
+
+/// Handler servicing `mprotect(addr, len, prot)` with `Memory::mprotect()`
+///
+/// Returns 0 on success or `-errno`: `EINVAL` for a misaligned address or
+/// unknown `prot` bits, `EACCES` for making shared pages writable and `ENOMEM`
+/// for guard regions, ranges past the address space or when no L2 table is
+/// left. Install it with
+/// `Rule::new(SYS_MPROTECT, Action::Handle(Box::new(mprotect)))`.
+pub fn mprotect(instance: &mut Instance, syscall: &Syscall) -> u32 {
+    let [address, len, prot, ..] = syscall.args;
+    if prot & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0 {
+        return EINVAL.wrapping_neg() as u32;
+    }
+    let permissions = Permissions {
+        read: prot & PROT_READ != 0,
+        write: prot & PROT_WRITE != 0,
+        execute: prot & PROT_EXEC != 0,
+    };
+    let errno = match instance.memory_mut().mprotect(address, len, permissions) {
+        Ok(()) => return 0,
+        Err(MemoryError::Misaligned) => EINVAL,
+        Err(MemoryError::ReadOnly) => EACCES,
+        Err(_) => ENOMEM,
+    };
+    errno.wrapping_neg() as u32
+}
+
+/// Read a NUL-terminated path from guest memory
+fn path(memory: &Memory, address: u32) -> Option<Vec<u8>> {
+    memory.read_cstr(address, MAX_PATH_LENGTH).ok()
+}
@@ -0,0 +1,330 @@
+use crate::arm64::{self, Arrangement, Condition, FP, LR, SP, ZR};
+
+// Expected words come from an external assembler (llvm-mc -triple=aarch64)
+
+#[test]
+fn data_processing() {
+    assert_eq!(arm64::add(1, 2, 3), 0x0B030041);
+    assert_eq!(arm64::sub(4, 5, 6), 0x4B0600A4);
+    assert_eq!(arm64::and(7, 8, 9), 0x0A090107);
+    assert_eq!(arm64::orr(10, 11, 12), 0x2A0C016A);
+    assert_eq!(arm64::eor(13, 14, 15), 0x4A0F01CD);
+    assert_eq!(arm64::lslv(1, 2, 3), 0x1AC32041);
+    assert_eq!(arm64::lsrv(1, 2, 3), 0x1AC32441);
+    assert_eq!(arm64::asrv(1, 2, 3), 0x1AC32841);
+    assert_eq!(arm64::mov(9, ZR), 0x2A1F03E9);
+    assert_eq!(arm64::mov_x(19, 0), 0xAA0003F3);
+}
+
+#[test]
+fn multiply_divide() {
+    assert_eq!(arm64::mul(9, 9, 10), 0x1B0A7D29);
+    assert_eq!(arm64::mul_x(9, 9, 10), 0x9B0A7D29);
+    assert_eq!(arm64::msub(9, 11, 10, 9), 0x1B0AA569);
+    assert_eq!(arm64::smull(9, 9, 10), 0x9B2A7D29);
+    assert_eq!(arm64::umull(9, 9, 10), 0x9BAA7D29);
+    assert_eq!(arm64::sdiv(11, 9, 10), 0x1ACA0D2B);
+    assert_eq!(arm64::udiv(11, 9, 10), 0x1ACA092B);
+}
+
+#[test]
+fn extends() {
+    assert_eq!(arm64::sxtw(9, 9), 0x93407D29);
+    assert_eq!(arm64::lsr_32_x(9, 9), 0xD360FD29);
+    assert_eq!(arm64::ubfx(17, 1, 14, 8), 0x530E5431);
+    assert_eq!(arm64::ubfx(17, 1, 0, 14), 0x53003431);
+}
+
+#[test]
+fn compare_and_set() {
+    assert_eq!(arm64::cmp(9, 10), 0x6B0A013F);
+    assert_eq!(arm64::cmp_imm(16, 255), 0x7103FE1F);
+    assert_eq!(arm64::subs_imm_x(16, 16, 12), 0xF1003210);
+    assert_eq!(arm64::subs_x(16, 16, 17), 0xEB110210);
+    assert_eq!(arm64::cset(9, Condition::Lt), 0x1A9FA7E9);
+    assert_eq!(arm64::cset(9, Condition::Lo), 0x1A9F27E9);
+    assert_eq!(arm64::cmp(10, ZR), 0x6B1F015F);
+    assert_eq!(arm64::csinv(9, 11, ZR, Condition::Ne), 0x5A9F1169);
+}
+
+#[test]
+fn condition_invert() {
+    assert_eq!(Condition::Eq.invert(), Condition::Ne);
+    assert_eq!(Condition::Lo.invert(), Condition::Hs);
+    assert_eq!(Condition::Ge.invert(), Condition::Lt);
+    assert_eq!(Condition::Le.invert().invert(), Condition::Le);
+}
+
+#[test]
+fn move_wide() {
+    assert_eq!(arm64::movz(9, 0x1234, 0), 0x52824689);
+    assert_eq!(arm64::movz(9, 0xABCD, 16), 0x52B579A9);
+    assert_eq!(arm64::movk(9, 0x5678, 16), 0x72AACF09);
+    assert_eq!(arm64::movn(9, 1, 0), 0x12800029);
+}
+
+#[test]
+fn move_immediate_sequences() {
+    assert_eq!(arm64::mov_imm(9, 0x1234), vec![0x52824689]);
+    assert_eq!(arm64::mov_imm(9, 0xABCD_0000), vec![0x52B579A9]);
+    assert_eq!(arm64::mov_imm(9, 0xFFFF_FFFE), vec![0x12800029]);
+    assert_eq!(arm64::mov_imm(9, 0x5678_1234), vec![0x52824689, 0x72AACF09]);
+    assert_eq!(arm64::mov_imm(9, 0), vec![arm64::movz(9, 0, 0)]);
+}
+
+#[test]
+fn immediates() {
+    assert_eq!(arm64::add_imm(9, 9, 4095), 0x113FFD29);
+    assert_eq!(arm64::sub_imm(1, 1, 2048), 0x51200021);
+    assert_eq!(arm64::add_imm_x(FP, SP, 0), 0x910003FD);
+}
+
+#[test]
+fn shift_immediates() {
+    assert_eq!(arm64::lsl_imm(9, 9, 3), 0x531D7129);
+    assert_eq!(arm64::lsl_imm(9, 9, 0), 0x53007D29);
+    assert_eq!(arm64::lsl_imm(9, 9, 31), 0x53010129);
+    assert_eq!(arm64::lsr_imm(9, 9, 5), 0x53057D29);
+    assert_eq!(arm64::asr_imm(9, 9, 31), 0x131F7D29);
+}
+
+#[test]
+fn loads_and_stores() {
+    assert_eq!(arm64::ldr(9, 20, 124), 0xB9407E89);
+    assert_eq!(arm64::str(9, 20, 4), 0xB9000689);
+    assert_eq!(arm64::ldr_x(20, 19, 8), 0xF9400674);
+    assert_eq!(arm64::str_x(1, 19, 16), 0xF9000A61);
+    assert_eq!(arm64::ldrb(16, 16, 16), 0x39404210);
+    assert_eq!(arm64::ldrsw(17, 16, 8), 0xB9800A11);
+}
+
+#[test]
+fn register_offset_loads_and_stores() {
+    assert_eq!(arm64::ldrb_reg(17, 17, 16), 0x38706A31);
+    assert_eq!(arm64::ldrsb_reg(0, 16, 17), 0x38F16A00);
+    assert_eq!(arm64::ldrh_reg(0, 16, 17), 0x78716A00);
+    assert_eq!(arm64::ldrh_index(17, 17, 16), 0x78707A31);
+    assert_eq!(arm64::ldrsh_reg(0, 16, 17), 0x78F16A00);
+    assert_eq!(arm64::ldr_reg(0, 16, 17), 0xB8716A00);
+    assert_eq!(arm64::ldrsw_reg(17, 16, 9), 0xB8A96A11);
+    assert_eq!(arm64::strb_reg(2, 16, 17), 0x38316A02);
+    assert_eq!(arm64::strh_reg(2, 16, 17), 0x78316A02);
+    assert_eq!(arm64::str_reg(2, 16, 17), 0xB8316A02);
+}
+
+#[test]
+fn pairs() {
+    assert_eq!(arm64::stp_x_pre(FP, LR, SP, -32), 0xA9BE7BFD);
+    assert_eq!(arm64::stp_x(19, 20, SP, 16), 0xA90153F3);
+    assert_eq!(arm64::ldp_x(19, 20, SP, 16), 0xA94153F3);
+    assert_eq!(arm64::ldp_x_post(FP, LR, SP, 32), 0xA8C27BFD);
+}
+
+#[test]
+fn vectors() {
+    assert_eq!(arm64::add_v(Arrangement::B16, 0, 1, 2), 0x4E228420);
+    assert_eq!(arm64::add_v(Arrangement::S4, 16, 16, 17), 0x4EB18610);
+    assert_eq!(arm64::sub_v(Arrangement::H8, 0, 1, 2), 0x6E628420);
+    assert_eq!(arm64::dup_v(Arrangement::S4, 0, 1), 0x4E040C20);
+    assert_eq!(arm64::dup_v(Arrangement::B16, 17, 9), 0x4E010D31);
+    assert_eq!(arm64::dup_v(Arrangement::D2, 1, 2), 0x4E080C41);
+    assert_eq!(arm64::ldr_q(16, 19, 32), 0x3DC00A70);
+    assert_eq!(arm64::str_q(0, 20, 0), 0x3D800280);
+    assert_eq!(Arrangement::from_size(1), Some(Arrangement::H8));
+    assert_eq!(Arrangement::from_size(4), None);
+    assert_eq!(Arrangement::D2.element_bytes(), 8);
+}
+
+#[test]
+fn branches() {
+    assert_eq!(arm64::b(-8), 0x17FFFFFE);
+    assert_eq!(arm64::b(128), 0x14000020);
+    assert_eq!(arm64::b_cond(Condition::Ne, 8), 0x54000041);
+    assert_eq!(arm64::b_cond(Condition::Ge, -48), 0x54FFFE8A);
+    assert_eq!(arm64::cbz(0, 12), 0x34000060);
+    assert_eq!(arm64::cbnz(3, -4), 0x35FFFFE3);
+    assert_eq!(arm64::tbz(0, 32, 20), 0xB60000A0);
+    assert_eq!(arm64::tbnz(9, 3, -8), 0x371FFFC9);
+    assert_eq!(arm64::blr(16), 0xD63F0200);
+    assert_eq!(arm64::br(17), 0xD61F0220);
+}
+
+#[test]
+fn addresses() {
+    assert_eq!(arm64::adr(16, 40), 0x10000150);
+    assert_eq!(arm64::adr(16, -12), 0x10FFFFB0);
+    assert_eq!(arm64::adr(16, 6), 0x50000030);
+    assert_eq!(arm64::ldr_literal(0, 8), 0x18000040);
+    assert_eq!(arm64::ldr_literal(5, -4), 0x18FFFFE5);
+    assert_eq!(arm64::add_x(16, 16, 17), 0x8B110210);
+    assert_eq!(arm64::add_lsl(16, 17, 16, 8), 0x0B102230);
+    assert_eq!(arm64::add_x_lsr(16, 0, 1, 22), 0x8B415810);
+    assert_eq!(arm64::add_x_lsl(16, 16, 17, 14), 0x8B113A10);
+}
+
+#[test]
+fn pc_relative_offsets() {
+    assert_eq!(arm64::pc_relative(arm64::b(-8)), Some(-8));
+    assert_eq!(
+        arm64::pc_relative(arm64::b_cond(Condition::Ge, -48)),
+        Some(-48)
+    );
+    assert_eq!(arm64::pc_relative(arm64::cbnz(3, 4096)), Some(4096));
+    assert_eq!(arm64::pc_relative(arm64::tbz(0, 32, 20)), Some(20));
+    assert_eq!(arm64::pc_relative(arm64::adr(16, -12)), Some(-12));
+    assert_eq!(arm64::pc_relative(arm64::adr(16, 6)), Some(6));
+    assert_eq!(arm64::pc_relative(arm64::ldr_literal(21, -40)), Some(-40));
+    assert_eq!(arm64::pc_relative(arm64::br(17)), None);
+    assert_eq!(arm64::pc_relative(arm64::ldr_x(16, 19, 48)), None);
+    assert_eq!(arm64::pc_relative(arm64::NOP), None);
+}
+
+#[test]
+fn fixed_instructions() {
+    assert_eq!(arm64::NOP, 0xD503201F);
+    assert_eq!(arm64::RET, 0xD65F03C0);
+}
+
+#[test]
+fn disassemble_data_processing() {
+    let cases = [
+        (arm64::add(1, 2, 3), "add w1, w2, w3"),
+        (arm64::sub(4, 5, 6), "sub w4, w5, w6"),
+        (arm64::add_x(16, 16, 17), "add x16, x16, x17"),
+        (arm64::subs_x(10, 10, 17), "subs x10, x10, x17"),
+        (arm64::add_lsl(16, 17, 16, 8), "add w16, w17, w16, lsl #8"),
+        (
+            arm64::add_x_lsl(16, 16, 17, 14),
+            "add x16, x16, x17, lsl #14",
+        ),
+        (arm64::add_x_lsr(16, 0, 1, 22), "add x16, x0, x1, lsr #22"),
+        (arm64::and(7, 8, 9), "and w7, w8, w9"),
+        (arm64::orr(10, 11, 12), "orr w10, w11, w12"),
+        (arm64::eor(13, 14, 15), "eor w13, w14, w15"),
+        (arm64::lslv(1, 2, 3), "lsl w1, w2, w3"),
+        (arm64::lsrv(1, 2, 3), "lsr w1, w2, w3"),
+        (arm64::asrv(1, 2, 3), "asr w1, w2, w3"),
+        (arm64::mov(9, ZR), "mov w9, wzr"),
+        (arm64::mov_x(19, 0), "mov x19, x0"),
+        (arm64::cmp(9, 10), "cmp w9, w10"),
+        (arm64::cset(9, Condition::Lt), "cset w9, lt"),
+        (
+            arm64::csinv(9, 10, ZR, Condition::Ne),
+            "csinv w9, w10, wzr, ne",
+        ),
+        (arm64::mul(1, 2, 3), "mul w1, w2, w3"),
+        (arm64::mul_x(1, 2, 3), "mul x1, x2, x3"),
+        (arm64::msub(1, 2, 3, 4), "msub w1, w2, w3, w4"),
+        (arm64::smull(1, 2, 3), "smull x1, w2, w3"),
+        (arm64::umull(1, 2, 3), "umull x1, w2, w3"),
+        (arm64::sdiv(1, 2, 3), "sdiv w1, w2, w3"),
+        (arm64::udiv(1, 2, 3), "udiv w1, w2, w3"),
+    ];
+    for (word, text) in cases {
+        assert_eq!(arm64::disassemble(word, 0), text);
+    }
+}
+
+#[test]
+fn disassemble_immediates() {
+    let cases = [
+        (arm64::movz(9, 0x1234, 0), "movz w9, #0x1234"),
+        (arm64::movz(9, 0x8000, 16), "movz w9, #0x8000, lsl #16"),
+        (arm64::movk(10, 0xFFFF, 16), "movk w10, #0xffff, lsl #16"),
+        (arm64::movn(11, 7, 0), "movn w11, #0x7"),
+        (arm64::add_imm(1, 2, 4095), "add w1, w2, #4095"),
+        (arm64::sub_imm(1, 2, 1), "sub w1, w2, #1"),
+        (arm64::add_imm_x(SP, SP, 16), "add sp, sp, #16"),
+        (arm64::subs_imm_x(16, 16, 3), "subs x16, x16, #3"),
+        (arm64::cmp_imm(9, 42), "cmp w9, #42"),
+        (arm64::lsl_imm(1, 2, 3), "lsl w1, w2, #3"),
+        (arm64::lsr_imm(1, 2, 3), "lsr w1, w2, #3"),
+        (arm64::asr_imm(1, 2, 3), "asr w1, w2, #3"),
+        (arm64::ubfx(16, 1, 12, 10), "ubfx w16, w1, #12, #10"),
+        (arm64::sxtw(1, 2), "sxtw x1, w2"),
+        (arm64::lsr_32_x(1, 2), "lsr x1, x2, #32"),
+    ];
+    for (word, text) in cases {
+        assert_eq!(arm64::disassemble(word, 0), text);
+    }
+}
+
+#[test]
+fn disassemble_memory() {
+    let cases = [
+        (arm64::ldr(9, 19, 36), "ldr w9, [x19, #36]"),
+        (arm64::str(21, 20, 4), "str w21, [x20, #4]"),
+        (arm64::ldr_x(16, 19, 48), "ldr x16, [x19, #48]"),
+        (arm64::str_x(16, 19, 0), "str x16, [x19]"),
+        (arm64::ldrsw(17, 16, 0), "ldrsw x17, [x16]"),
+        (arm64::ldrb(9, 0, 7), "ldrb w9, [x0, #7]"),
+        (arm64::ldrb_reg(1, 16, 17), "ldrb w1, [x16, x17]"),
+        (arm64::ldrsb_reg(1, 16, 17), "ldrsb w1, [x16, x17]"),
+        (arm64::ldrh_reg(1, 16, 17), "ldrh w1, [x16, x17]"),
+        (arm64::ldrh_index(1, 16, 17), "ldrh w1, [x16, x17, lsl #1]"),
+        (arm64::ldrsh_reg(1, 16, 17), "ldrsh w1, [x16, x17]"),
+        (arm64::ldr_reg(1, 16, 17), "ldr w1, [x16, x17]"),
+        (arm64::ldrsw_reg(1, 16, 17), "ldrsw x1, [x16, x17]"),
+        (arm64::strb_reg(1, 16, 17), "strb w1, [x16, x17]"),
+        (arm64::strh_reg(1, 16, 17), "strh w1, [x16, x17]"),
+        (arm64::str_reg(1, 16, 17), "str w1, [x16, x17]"),
+        (arm64::stp_x(19, 20, SP, 16), "stp x19, x20, [sp, #16]"),
+        (arm64::ldp_x(19, 20, SP, 0), "ldp x19, x20, [sp]"),
+        (
+            arm64::stp_x_pre(FP, LR, SP, -96),
+            "stp x29, x30, [sp, #-96]!",
+        ),
+        (arm64::ldp_x_post(FP, LR, SP, 96), "ldp x29, x30, [sp], #96"),
+        (arm64::ldr_q(16, 19, 32), "ldr q16, [x19, #32]"),
+        (arm64::str_q(0, 20, 0), "str q0, [x20]"),
+    ];
+    for (word, text) in cases {
+        assert_eq!(arm64::disassemble(word, 0), text);
+    }
+}
+
+#[test]
+fn disassemble_vectors() {
+    let cases = [
+        (
+            arm64::add_v(Arrangement::S4, 16, 16, 17),
+            "add v16.4s, v16.4s, v17.4s",
+        ),
+        (
+            arm64::sub_v(Arrangement::D2, 0, 1, 2),
+            "sub v0.2d, v1.2d, v2.2d",
+        ),
+        (arm64::dup_v(Arrangement::B16, 17, 9), "dup v17.16b, w9"),
+        (arm64::dup_v(Arrangement::D2, 17, 9), "dup v17.2d, x9"),
+    ];
+    for (word, text) in cases {
+        assert_eq!(arm64::disassemble(word, 0), text);
+    }
+}
+
+#[test]
+fn disassemble_branches_at_offset() {
+    let cases = [
+        (arm64::b(-8), "b 0x18"),
+        (arm64::b_cond(Condition::Hs, 8), "b.hs 0x28"),
+        (arm64::cbz(0, 12), "cbz w0, 0x2c"),
+        (arm64::cbnz(3, -4), "cbnz w3, 0x1c"),
+        (arm64::tbz(0, 32, 20), "tbz x0, #32, 0x34"),
+        (arm64::tbnz(9, 1, -8), "tbnz w9, #1, 0x18"),
+        (arm64::adr(16, 40), "adr x16, 0x48"),
+        (arm64::ldr_literal(21, 64), "ldr w21, 0x60"),
+        (arm64::blr(16), "blr x16"),
+        (arm64::br(17), "br x17"),
+        (arm64::RET, "ret"),
+        (arm64::NOP, "nop"),
+    ];
+    for (word, text) in cases {
+        assert_eq!(arm64::disassemble(word, 0x20), text);
+    }
+}
+
+#[test]
+fn disassemble_unknown_as_word() {
+    assert_eq!(arm64::disassemble(0x00000040, 0), ".word 0x00000040");
+    assert_eq!(arm64::disassemble(0xFFFFFFFF, 0), ".word 0xffffffff");
+}
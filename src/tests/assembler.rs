@@ -0,0 +1,236 @@
+use crate::{AssembleError, Assembler, EncodeError, Instruction, Relocation, RelocationKind};
+
+const NOP: Instruction = Instruction::Addi {
+    rd: 0,
+    rs1: 0,
+    imm: 0,
+};
+
+#[test]
+fn backward() {
+    let mut asm = Assembler::new();
+    asm.label("loop").unwrap();
+    asm.emit(Instruction::addi(1, 1, -1).unwrap());
+    asm.emit_target(
+        Instruction::Bne {
+            rs1: 1,
+            rs2: 0,
+            imm: 0,
+        },
+        "loop",
+    )
+    .unwrap();
+    assert_eq!(
+        asm.finish().unwrap()[1],
+        Instruction::Bne {
+            rs1: 1,
+            rs2: 0,
+            imm: -4
+        }
+    );
+}
+
+#[test]
+fn forward() {
+    let mut asm = Assembler::new();
+    asm.emit_target(Instruction::Jal { rd: 0, imm: 0 }, "end")
+        .unwrap();
+    asm.emit(NOP);
+    asm.emit(NOP);
+    asm.label("end").unwrap();
+    asm.emit(Instruction::Ecall);
+    assert_eq!(
+        asm.finish().unwrap()[0],
+        Instruction::Jal { rd: 0, imm: 12 }
+    );
+}
+
+#[test]
+fn self_target() {
+    let mut asm = Assembler::new();
+    asm.label("here").unwrap();
+    asm.emit_target(Instruction::Jal { rd: 0, imm: 99 }, "here")
+        .unwrap();
+    assert_eq!(
+        asm.finish().unwrap(),
+        vec![Instruction::Jal { rd: 0, imm: 0 }]
+    );
+}
+
+#[test]
+fn offset() {
+    let mut asm = Assembler::new();
+    assert_eq!(asm.offset(), 0);
+    asm.emit(Instruction::Ecall);
+    asm.emit(Instruction::Ebreak);
+    assert_eq!(asm.offset(), 8);
+}
+
+#[test]
+fn assemble() {
+    let mut asm = Assembler::new();
+    asm.label("start").unwrap();
+    asm.emit_target(
+        Instruction::Beq {
+            rs1: 0,
+            rs2: 0,
+            imm: 0,
+        },
+        "start",
+    )
+    .unwrap();
+    // beq x0, x0, 0
+    assert_eq!(asm.assemble(), Ok(vec![0x63, 0x00, 0x00, 0x00]));
+}
+
+#[test]
+fn duplicate_label() {
+    let mut asm = Assembler::new();
+    asm.label("a").unwrap();
+    assert_eq!(
+        asm.label("a"),
+        Err(AssembleError::DuplicateLabel("a".to_string()))
+    );
+}
+
+#[test]
+fn undefined_label() {
+    let mut asm = Assembler::new();
+    asm.emit_target(Instruction::Jal { rd: 0, imm: 0 }, "missing")
+        .unwrap();
+    assert_eq!(
+        asm.finish(),
+        Err(AssembleError::UndefinedLabel("missing".to_string()))
+    );
+}
+
+#[test]
+fn not_branch() {
+    let mut asm = Assembler::new();
+    assert_eq!(
+        asm.emit_target(Instruction::Ecall, "a"),
+        Err(AssembleError::NotBranch(Instruction::Ecall))
+    );
+    assert_eq!(asm.offset(), 0);
+}
+
+#[test]
+fn branch_out_of_range() {
+    let mut asm = Assembler::new();
+    asm.emit_target(
+        Instruction::Beq {
+            rs1: 0,
+            rs2: 0,
+            imm: 0,
+        },
+        "far",
+    )
+    .unwrap();
+    for _ in 0..1024 {
+        asm.emit(NOP);
+    }
+    asm.label("far").unwrap();
+    assert_eq!(
+        asm.finish(),
+        Err(AssembleError::OutOfRange("far".to_string(), 4100))
+    );
+}
+
+#[test]
+fn jump_range() {
+    // JAL reaches targets a branch cannot
+    let mut asm = Assembler::new();
+    asm.emit_target(Instruction::Jal { rd: 0, imm: 0 }, "far")
+        .unwrap();
+    for _ in 0..1024 {
+        asm.emit(NOP);
+    }
+    asm.label("far").unwrap();
+    assert_eq!(
+        asm.finish().unwrap()[0],
+        Instruction::Jal { rd: 0, imm: 4100 }
+    );
+}
+
+#[test]
+fn encode_error() {
+    let mut asm = Assembler::new();
+    asm.emit(Instruction::Unsupported(0));
+    assert_eq!(
+        asm.assemble(),
+        Err(AssembleError::EncodeError(EncodeError::NotImplemented(
+            "Unsupported"
+        )))
+    );
+}
+
+#[test]
+fn display() {
+    let error = AssembleError::OutOfRange("far".to_string(), 4100);
+    assert_eq!(error.to_string(), "Label far out of range: offset 4100");
+    let error = AssembleError::NotBranch(Instruction::Ecall);
+    assert_eq!(
+        error.to_string(),
+        "Instruction cannot target a label: ecall"
+    );
+}
+
+#[test]
+fn relocatable() {
+    let mut asm = Assembler::new();
+    asm.label("start").unwrap();
+    asm.emit_target(Instruction::Jal { rd: 1, imm: 0 }, "external")
+        .unwrap();
+    asm.emit_target(
+        Instruction::Beq {
+            rs1: 0,
+            rs2: 0,
+            imm: 0,
+        },
+        "start",
+    )
+    .unwrap();
+    let (mut code, relocations) = asm.assemble_relocatable().unwrap();
+    assert_eq!(
+        relocations,
+        vec![Relocation::new(0, RelocationKind::Jump, "external")]
+    );
+    // beq x0, x0, -4 resolved locally, jal x1 left as a placeholder
+    assert_eq!(code[4..], 0xFE000EE3u32.to_le_bytes());
+    assert_eq!(code[..4], 0x000000EFu32.to_le_bytes());
+    relocations[0].apply(&mut code, 16).unwrap();
+    assert_eq!(code[..4], 0x010000EFu32.to_le_bytes());
+}
+
+#[test]
+fn assemble_undefined() {
+    let mut asm = Assembler::new();
+    asm.emit_target(Instruction::Jal { rd: 0, imm: 0 }, "missing")
+        .unwrap();
+    assert_eq!(
+        asm.assemble(),
+        Err(AssembleError::UndefinedLabel("missing".to_string()))
+    );
+}
+
+#[test]
+fn assemble_out_of_range() {
+    let mut asm = Assembler::new();
+    asm.emit_target(
+        Instruction::Bne {
+            rs1: 0,
+            rs2: 0,
+            imm: 0,
+        },
+        "far",
+    )
+    .unwrap();
+    for _ in 0..1024 {
+        asm.emit(NOP);
+    }
+    asm.label("far").unwrap();
+    assert_eq!(
+        asm.assemble(),
+        Err(AssembleError::OutOfRange("far".to_string(), 4100))
+    );
+}
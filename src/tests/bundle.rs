@@ -0,0 +1,365 @@
+use crate::{
+    Bundle, BundleError, CompileError, Instance, Instruction, Memory, MemoryError, Module,
+    OptLevel, PageStore, SerializeError,
+    bundle::{BUNDLE_MAGIC, BUNDLE_VERSION},
+    serial::COMPILER,
+};
+use std::{env, fs, io, process};
+
+/// A bundle exercising every section
+fn sample() -> Bundle {
+    let ecall = Instruction::Ecall.encode().unwrap().to_le_bytes();
+    let mut bundle = Bundle::new(0x1000, [ecall, ecall].concat());
+    bundle
+        .manifest
+        .insert("name".to_string(), "sample".to_string());
+    bundle
+        .manifest
+        .insert("version".to_string(), "1.0".to_string());
+    bundle.entries = vec![0x1000, 0x1004];
+    bundle.segments.push((0x8000, b"data".to_vec()));
+    bundle.segments.push((0x20000, vec![0xFF; 100]));
+    bundle
+}
+
+#[test]
+fn roundtrip() {
+    let bundle = sample();
+    assert_eq!(Bundle::from_bytes(&bundle.to_bytes()), Ok(bundle));
+}
+
+#[test]
+fn compiled_roundtrip() {
+    let mut bundle = sample();
+    bundle.compile(&mut Module::new(4).unwrap()).unwrap();
+    assert_eq!(Bundle::from_bytes(&bundle.to_bytes()), Ok(bundle));
+}
+
+#[test]
+fn empty_roundtrip() {
+    let bundle = Bundle::default();
+    assert_eq!(Bundle::from_bytes(&bundle.to_bytes()), Ok(bundle));
+}
+
+#[test]
+fn header() {
+    let bytes = Bundle::default().to_bytes();
+    assert_eq!(bytes[..4], BUNDLE_MAGIC);
+    assert_eq!(bytes[4..8], BUNDLE_VERSION.to_le_bytes());
+}
+
+#[test]
+fn invalid_magic() {
+    let mut bytes = sample().to_bytes();
+    bytes[0] = b'X';
+    assert_eq!(Bundle::from_bytes(&bytes), Err(BundleError::InvalidMagic));
+}
+
+#[test]
+fn unsupported_version() {
+    let mut bytes = sample().to_bytes();
+    bytes[4] = 99;
+    assert_eq!(
+        Bundle::from_bytes(&bytes),
+        Err(BundleError::UnsupportedVersion(99))
+    );
+}
+
+#[test]
+fn truncated() {
+    let bytes = sample().to_bytes();
+    for length in [0, 3, 8, 20, bytes.len() - 1] {
+        assert_eq!(
+            Bundle::from_bytes(&bytes[..length]),
+            Err(BundleError::Truncated)
+        );
+    }
+}
+
+#[test]
+fn invalid_manifest() {
+    let mut bundle = Bundle::default();
+    bundle
+        .manifest
+        .insert("key".to_string(), "value".to_string());
+    let mut bytes = bundle.to_bytes();
+    // First byte of the key
+    bytes[16] = 0xFF;
+    assert_eq!(
+        Bundle::from_bytes(&bytes),
+        Err(BundleError::InvalidManifest)
+    );
+}
+
+#[test]
+fn invalid_compiled_flag() {
+    let mut bytes = Bundle::default().to_bytes();
+    // The flag is the last word of a bundle without a compiled module
+    let flag = bytes.len() - 4;
+    bytes[flag] = 2;
+    assert_eq!(
+        Bundle::from_bytes(&bytes),
+        Err(BundleError::InvalidModule(SerializeError::InvalidSetting(
+            "compiled module flag"
+        )))
+    );
+}
+
+#[test]
+fn save_and_load() {
+    let path = env::temp_dir().join(format!("jigs-bundle-{}.jig", process::id()));
+    let bundle = sample();
+    bundle.save(&path).unwrap();
+    let loaded = Bundle::load(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(loaded, Ok(bundle));
+}
+
+#[test]
+fn load_missing() {
+    let path = env::temp_dir().join("jigs-bundle-missing.jig");
+    assert_eq!(
+        Bundle::load(path),
+        Err(BundleError::Io(io::ErrorKind::NotFound))
+    );
+}
+
+#[test]
+fn compile() {
+    let mut bundle = sample();
+    let mut module = Module::new(4).unwrap();
+    module.set_opt_level(OptLevel::O1);
+    bundle.compile(&mut module).unwrap();
+    assert_eq!(bundle.compiled, Some(module.serialize().unwrap()));
+    assert_eq!(module.base(), 0x1000);
+    assert_eq!(module.functions(), bundle.entries);
+
+    bundle.entries.push(0x2000);
+    assert_eq!(
+        bundle.compile(&mut module),
+        Err(CompileError::InvalidFunction {
+            index: 2,
+            address: 0x2000
+        })
+    );
+}
+
+#[test]
+fn instantiate_compiled() {
+    let store = PageStore::new(16);
+    let mut bundle = sample();
+    let mut compiler = Module::new(4).unwrap();
+    compiler.set_opt_level(OptLevel::O1);
+    bundle.compile(&mut compiler).unwrap();
+    let mut module = Module::new(4).unwrap();
+    let instance = bundle
+        .instantiate(&mut module, Memory::new(&store, 16, 4))
+        .unwrap();
+    assert!(instance.attached());
+    assert_eq!(instance.pc(), 0x1000);
+    assert!(module.compile_stats().is_none());
+    assert_eq!(module.opt_level(), OptLevel::O1);
+    assert_eq!(module.code(), compiler.code());
+    assert_eq!(module.functions(), bundle.entries);
+}
+
+#[test]
+fn instantiate_recompiles() {
+    let store = PageStore::new(16);
+    let mut compiled = sample();
+    compiled.compile(&mut Module::new(4).unwrap()).unwrap();
+
+    // Code changed since it was compiled
+    let mut bundle = compiled.clone();
+    bundle.code.extend(bundle.code[..4].to_vec());
+    let mut module = Module::new(4).unwrap();
+    bundle
+        .instantiate(&mut module, Memory::new(&store, 16, 4))
+        .unwrap();
+    assert!(module.compile_stats().is_some());
+    assert_eq!(module.source_map().len(), 3);
+
+    // Entry table changed
+    let mut bundle = compiled.clone();
+    bundle.entries.pop();
+    let mut module = Module::new(4).unwrap();
+    bundle
+        .instantiate(&mut module, Memory::new(&store, 16, 4))
+        .unwrap();
+    assert!(module.compile_stats().is_some());
+
+    // Compiled by another jigs version
+    let mut bundle = compiled;
+    let version = bundle.compiled.as_mut().unwrap();
+    version[11 + COMPILER.len()] = b'x';
+    let mut module = Module::new(4).unwrap();
+    bundle
+        .instantiate(&mut module, Memory::new(&store, 16, 4))
+        .unwrap();
+    assert!(module.compile_stats().is_some());
+}
+
+#[test]
+fn instantiate_invalid_module() {
+    let store = PageStore::new(16);
+    let mut bundle = sample();
+    bundle.compiled = Some(b"JIGX".to_vec());
+    let mut module = Module::new(4).unwrap();
+    let result = bundle.instantiate(&mut module, Memory::new(&store, 16, 4));
+    assert_eq!(
+        result.err(),
+        Some(BundleError::InvalidModule(SerializeError::InvalidMagic))
+    );
+}
+
+#[test]
+fn instantiate_compiled_attached() {
+    let store = PageStore::new(16);
+    let mut bundle = sample();
+    bundle.compile(&mut Module::new(4).unwrap()).unwrap();
+    let mut module = Module::new(4).unwrap();
+    let mut instance = Instance::new(Memory::new(&store, 4, 1));
+    instance.attach(&mut module).unwrap();
+    let result = bundle.instantiate(&mut module, Memory::new(&store, 4, 4));
+    assert_eq!(
+        result.err(),
+        Some(BundleError::CompileError(CompileError::InstancesAttached))
+    );
+}
+
+#[test]
+fn instantiate() {
+    let store = PageStore::new(16);
+    let mut module = Module::new(1024).unwrap();
+    let bundle = sample();
+    let instance = bundle
+        .instantiate(&mut module, Memory::new(&store, 16, 4))
+        .unwrap();
+    assert!(instance.attached());
+    assert_eq!(instance.pc(), 0x1000);
+    assert_eq!(module.functions(), bundle.entries);
+    let mut data = [0u8; 4];
+    instance.memory().read(0x8000, &mut data);
+    assert_eq!(&data, b"data");
+    instance.memory().read(0x1000, &mut data);
+    assert_eq!(data, bundle.code[..4]);
+}
+
+/// A JAL over an ECALL to another ECALL, at 0x1000
+fn jump() -> Bundle {
+    let code = [
+        Instruction::Jal { rd: 1, imm: 8 },
+        Instruction::Ecall,
+        Instruction::Ecall,
+    ];
+    Bundle::new(0x1000, Instruction::encode_all(&code).unwrap())
+}
+
+#[test]
+fn instantiate_at_base() {
+    let store = PageStore::new(16);
+    let mut module = Module::new(1024).unwrap();
+    let mut instance = jump()
+        .instantiate(&mut module, Memory::new(&store, 16, 4))
+        .unwrap();
+    assert_eq!(instance.pc(), 0x1000);
+    instance.detach();
+    assert_eq!(module.base(), 0x1000);
+    assert_eq!(module.source_map().base(), 0x1000);
+    assert!(module.source_map().code(0x1008).is_some());
+    assert_eq!(module.source_map().code(0x8), None);
+}
+
+#[test]
+fn instantiate_without_entries() {
+    let store = PageStore::new(16);
+    let mut module = Module::new(1024).unwrap();
+    let bundle = Bundle::new(0x400, vec![0x73, 0, 0, 0]);
+    let instance = bundle
+        .instantiate(&mut module, Memory::new(&store, 16, 4))
+        .unwrap();
+    assert_eq!(instance.pc(), 0x400);
+}
+
+#[test]
+fn instantiate_memory_error() {
+    let store = PageStore::new(16);
+    let mut module = Module::new(1024).unwrap();
+    let bundle = sample();
+    // Only one page available for code and two data segments
+    let result = bundle.instantiate(&mut module, Memory::new(&store, 1, 4));
+    assert_eq!(
+        result.err(),
+        Some(BundleError::MemoryError(MemoryError::PageLimit))
+    );
+}
+
+#[test]
+fn instantiate_compile_error() {
+    let store = PageStore::new(16);
+    let mut module = Module::new(4).unwrap();
+    // nop; beq x0, x0, 6; nop
+    let code = [0x00000013u32, 0x00000363, 0x00000013];
+    let bundle = Bundle::new(0, code.iter().flat_map(|w| w.to_le_bytes()).collect());
+    let result = bundle.instantiate(&mut module, Memory::new(&store, 16, 4));
+    assert_eq!(
+        result.err(),
+        Some(BundleError::CompileError(CompileError::InvalidJumpTarget {
+            offset: 4,
+            target: 10
+        }))
+    );
+
+    // Entries must be instructions of the code
+    let mut bundle = sample();
+    bundle.entries.push(0x2000);
+    let result = bundle.instantiate(&mut module, Memory::new(&store, 16, 4));
+    assert_eq!(
+        result.err(),
+        Some(BundleError::CompileError(CompileError::InvalidFunction {
+            index: 2,
+            address: 0x2000
+        }))
+    );
+}
+
+#[test]
+fn display() {
+    assert_eq!(BundleError::InvalidMagic.to_string(), "Not a bundle");
+    assert_eq!(
+        BundleError::UnsupportedVersion(2).to_string(),
+        "Unsupported bundle version: 2"
+    );
+    assert_eq!(BundleError::Truncated.to_string(), "Bundle is truncated");
+    assert_eq!(
+        BundleError::InvalidModule(SerializeError::Truncated).to_string(),
+        "Invalid compiled module: Module is truncated"
+    );
+}
+
+#[test]
+#[cfg(target_arch = "aarch64")]
+fn run_at_base() {
+    let store = PageStore::new(16);
+    let mut module = Module::new(1024).unwrap();
+    let mut instance = jump()
+        .instantiate(&mut module, Memory::new(&store, 16, 4))
+        .unwrap();
+    let result = unsafe { instance.call_function(0) };
+    assert_eq!(result, Err("Environment call"));
+    assert_eq!(instance.pc(), 0x1008);
+    assert_eq!(instance.read_register(1), 0x1004);
+}
+
+#[test]
+#[cfg(target_arch = "aarch64")]
+fn run_entry() {
+    let store = PageStore::new(16);
+    let mut module = Module::new(1024).unwrap();
+    let mut instance = sample()
+        .instantiate(&mut module, Memory::new(&store, 16, 4))
+        .unwrap();
+    let result = unsafe { instance.call_function(1) };
+    assert_eq!(result, Err("Environment call"));
+    assert_eq!(instance.pc(), 0x1004);
+}
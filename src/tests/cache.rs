@@ -0,0 +1,171 @@
+use crate::{CodeCache, ConstantStrategy, Decoder, Extensions, Module, OptLevel};
+
+/// addi x1, x1, 1; add x2, x1, x0; mul x3, x1, x2
+const CODE: [u32; 3] = [0x00108093, 0x00008133, 0x022081B3];
+
+fn bytes(words: &[u32]) -> Vec<u8> {
+    words.iter().flat_map(|word| word.to_le_bytes()).collect()
+}
+
+fn compiled(cache: Option<&CodeCache>, opt_level: OptLevel, decoder: Decoder) -> Vec<u8> {
+    let mut module = Module::new(64).unwrap();
+    if let Some(cache) = cache {
+        module.set_code_cache(cache.clone());
+    }
+    module.set_opt_level(opt_level);
+    module.set_decoder(decoder);
+    module.set_code(&bytes(&CODE)).unwrap();
+    module.code().to_vec()
+}
+
+#[test]
+fn identical_code_hits() {
+    let cache = CodeCache::new();
+    assert!(cache.is_empty());
+    let first = compiled(Some(&cache), OptLevel::O0, Decoder::default());
+    let second = compiled(Some(&cache), OptLevel::O0, Decoder::default());
+    assert_eq!(first, second);
+    assert_eq!(first, compiled(None, OptLevel::O0, Decoder::default()));
+    assert_eq!(cache.len(), 1);
+    assert_eq!((cache.hits(), cache.misses()), (1, 1));
+}
+
+#[test]
+fn options_are_part_of_key() {
+    let cache = CodeCache::new();
+    let base = Decoder::new(Extensions::base());
+    let plain = compiled(Some(&cache), OptLevel::O0, Decoder::default());
+    let optimized = compiled(Some(&cache), OptLevel::O1, Decoder::default());
+    let restricted = compiled(Some(&cache), OptLevel::O0, base);
+    assert_eq!(cache.len(), 3);
+    assert_eq!((cache.hits(), cache.misses()), (0, 3));
+    assert_ne!(plain, optimized);
+    assert_ne!(plain, restricted);
+    assert_eq!(optimized, compiled(None, OptLevel::O1, Decoder::default()));
+    assert_eq!(restricted, compiled(None, OptLevel::O0, base));
+}
+
+#[test]
+fn differing_code_misses() {
+    let cache = CodeCache::new();
+    let mut module = Module::new(64).unwrap();
+    module.set_code_cache(cache.clone());
+    module.set_code(&bytes(&CODE)).unwrap();
+    module.set_code(&bytes(&CODE[..2])).unwrap();
+    assert_eq!(module.source_map().len(), 2);
+    module.set_code(&bytes(&CODE)).unwrap();
+    assert_eq!(module.source_map().len(), 3);
+    assert_eq!(cache.len(), 2);
+    assert_eq!((cache.hits(), cache.misses()), (1, 2));
+}
+
+#[test]
+fn hit_restores_source_map_and_listing() {
+    let cache = CodeCache::new();
+    let mut first = Module::new(64).unwrap();
+    first.set_code_cache(cache.clone());
+    first.set_code(&bytes(&CODE)).unwrap();
+    let mut second = Module::new(64).unwrap();
+    second.set_code_cache(cache.clone());
+    second.set_code(&bytes(&CODE)).unwrap();
+    assert_eq!(cache.hits(), 1);
+    assert_eq!(first.source_map(), second.source_map());
+    assert_eq!(first.listing(), second.listing());
+    // Nothing was compiled for the second
+    assert!(first.compile_stats().is_some());
+    assert_eq!(second.compile_stats(), None);
+}
+
+#[test]
+fn clear_drops_entries() {
+    let cache = CodeCache::new();
+    compiled(Some(&cache), OptLevel::O0, Decoder::default());
+    cache.clear();
+    assert!(cache.is_empty());
+    compiled(Some(&cache), OptLevel::O0, Decoder::default());
+    assert_eq!((cache.hits(), cache.misses()), (0, 2));
+}
+
+#[test]
+fn clones_share_entries() {
+    let cache = CodeCache::new();
+    let clone = cache.clone();
+    compiled(Some(&clone), OptLevel::O0, Decoder::default());
+    assert_eq!(cache.len(), 1);
+    let mut module = Module::new(64).unwrap();
+    assert!(module.code_cache().is_none());
+    module.set_code_cache(cache);
+    assert_eq!(module.code_cache().unwrap().len(), 1);
+}
+
+#[test]
+fn resumable_is_part_of_key() {
+    let cache = CodeCache::new();
+    let plain = compiled(Some(&cache), OptLevel::O0, Decoder::default());
+    let mut module = Module::new(64).unwrap();
+    module.set_code_cache(cache.clone());
+    module.set_resumable(true);
+    module.set_code(&bytes(&CODE)).unwrap();
+    assert_eq!((cache.hits(), cache.misses()), (0, 2));
+    assert_ne!(module.code(), plain);
+    assert!(module.source_map().resume().is_some());
+}
+
+#[test]
+fn block_counters_are_part_of_key() {
+    let cache = CodeCache::new();
+    let plain = compiled(Some(&cache), OptLevel::O0, Decoder::default());
+    let mut module = Module::new(64).unwrap();
+    module.set_code_cache(cache.clone());
+    module.set_block_counters(true);
+    assert!(module.block_counters());
+    module.set_code(&bytes(&CODE)).unwrap();
+    assert_eq!((cache.hits(), cache.misses()), (0, 2));
+    assert_ne!(module.code(), plain);
+    assert_eq!(module.source_map().counters(), [0]);
+}
+
+#[test]
+fn base_is_part_of_key() {
+    let cache = CodeCache::new();
+    // auipc x1, 1
+    let code = bytes(&[0x00001097]);
+    let mut module = Module::new(64).unwrap();
+    module.set_code_cache(cache.clone());
+    module.set_code(&code).unwrap();
+    let plain = module.code().to_vec();
+    module.set_base(0x1002);
+    assert_eq!(module.base(), 0x1000);
+    module.set_code(&code).unwrap();
+    assert_eq!((cache.hits(), cache.misses()), (0, 2));
+    assert_ne!(module.code(), plain);
+    assert_eq!(module.source_map().base(), 0x1000);
+}
+
+#[test]
+fn constants_are_part_of_key() {
+    let cache = CodeCache::new();
+    // lui x1, 0x12345; lui x2, 0x12345
+    let code = bytes(&[0x123450B7, 0x12345137]);
+    let mut module = Module::new(64).unwrap();
+    module.set_code_cache(cache.clone());
+    assert_eq!(module.constants(), ConstantStrategy::MoveWide);
+    module.set_code(&code).unwrap();
+    let plain = module.code().to_vec();
+    module.set_constants(ConstantStrategy::LiteralPool);
+    module.set_code(&code).unwrap();
+    assert_eq!((cache.hits(), cache.misses()), (0, 2));
+    assert_eq!(module.code().len(), plain.len() - 4);
+}
+
+#[test]
+fn functions_are_part_of_key() {
+    let cache = CodeCache::new();
+    let mut module = Module::new(64).unwrap();
+    module.set_code_cache(cache.clone());
+    module.set_code(&bytes(&CODE)).unwrap();
+    module.set_functions(&[4]);
+    module.set_code(&bytes(&CODE)).unwrap();
+    assert_eq!((cache.hits(), cache.misses()), (0, 2));
+    assert_eq!(module.source_map().functions(), 1);
+}
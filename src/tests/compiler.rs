@@ -1,42 +1,212 @@
 use crate::Instruction;
-use crate::compiler::Compiler;
+use crate::arm64::{self, Condition};
+use crate::compiler::{
+    BasicBlock, CompileStats, Compiler, ConstantStrategy, OptLevel, Pass, SourceMap,
+};
+use crate::instance::Context;
+use crate::module::CompileError;
+use crate::regalloc::RegisterMap;
+use crate::translator::{
+    self, COUNTER_WORDS, MAX_BRANCHES, MAX_WORDS, MIN_REGION, Options, PROLOGUE_WORDS, Translation,
+    Translator,
+};
+use crate::veneer::{self, VENEER_WORDS};
+use crate::{fold, peephole};
+use std::mem::offset_of;
+
+/// Compile instructions and return the ARM64 words and instruction offsets
+fn compile(instructions: &[Instruction]) -> Translation {
+    let mut buffer = vec![0u8; Compiler::code_size(instructions.len())];
+    let size = Compiler::new().compile(instructions, &mut buffer);
+    let translation = Translator::translate(instructions, &Options::default());
+    assert_eq!(size, translation.code.len() * 4);
+    for (chunk, word) in buffer.chunks_exact(4).zip(&translation.code) {
+        assert_eq!(u32::from_le_bytes(chunk.try_into().unwrap()), *word);
+    }
+    translation
+}
+
+/// Get the words generated for the instruction at `index`, after the gas
+/// charge if it starts a basic block
+fn slot(translation: &Translation, index: usize) -> &[u32] {
+    let offsets = &translation.offsets;
+    let words = &translation.code[offsets[index]..offsets[index + 1]];
+    &words[charge(translation, index).len()..]
+}
+
+/// Get the gas charge opening the basic block at `index`, if any (the end
+/// sequence has none)
+fn charge(translation: &Translation, index: usize) -> &[u32] {
+    let offsets = &translation.offsets;
+    let Some(&end) = offsets.get(index + 1) else {
+        return &[];
+    };
+    let words = &translation.code[offsets[index]..end];
+    if words.first() != Some(&arm64::ldr_x(16, 19, 48)) {
+        return &[];
+    }
+    let end = words
+        .iter()
+        .position(|&word| word == arm64::str_x(16, 19, 48))
+        .unwrap();
+    &words[..=end]
+}
 
 #[test]
 fn basic_ret_compilation() {
-    let mut compiler = Compiler::new();
-    let instructions = vec![Instruction::Jalr {
+    let code = compile(&[Instruction::Jalr {
         rd: 0,
         rs1: 1,
         imm: 0,
-    }];
+    }]);
 
-    let mut buffer = vec![0u8; 1024];
-    let size = compiler.compile(&instructions, &mut buffer);
+    assert_eq!(
+        code.code[0],
+        arm64::stp_x_pre(arm64::FP, arm64::LR, arm64::SP, -96)
+    );
 
-    // Should emit a single RET instruction (4 bytes)
-    assert_eq!(size, 4);
+    // JALR computes the target from x1 (mapped to w21) and dispatches on it
+    let jalr = slot(&code, 0);
+    assert_eq!(jalr[0], arm64::mov(9, 21));
+    assert_eq!(jalr[1], arm64::movn(10, 1, 0));
+    assert_eq!(jalr[2], arm64::and(9, 9, 10));
 
-    // RET instruction encoding: 0xD65F03C0 (little-endian)
-    assert_eq!(&buffer[..size], vec![0xC0, 0x03, 0x5F, 0xD6]);
+    // The shared exit stub ends with a RET back to the caller
+    assert!(code.code.contains(&arm64::RET));
 }
 
 #[test]
-fn empty_compilation() {
-    let mut compiler = Compiler::new();
-    let instructions = vec![];
+fn frame_restores_what_prologue_saves() {
+    use arm64::{FP, LR, SP};
+    let instructions = [
+        Instruction::Ecall,
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 1,
+            imm: 0,
+        },
+    ];
+    let code = Translator::translate(
+        &instructions,
+        &Options {
+            resumable: true,
+            ..Options::default()
+        },
+    );
+    let saved = [(19, 20), (21, 22), (23, 24), (25, 26), (27, 28)];
+
+    let mut prologue = vec![
+        arm64::stp_x_pre(FP, LR, SP, -96),
+        arm64::add_imm_x(FP, SP, 0),
+    ];
+    let mut restore = Vec::new();
+    for (slot, &(first, second)) in saved.iter().enumerate() {
+        prologue.push(arm64::stp_x(first, second, SP, 16 * (1 + slot as i32)));
+        restore.insert(0, arm64::ldp_x(first, second, SP, 16 * (1 + slot as i32)));
+    }
+    restore.extend([arm64::ldp_x_post(FP, LR, SP, 96), arm64::RET]);
+    assert_eq!(code.code[..prologue.len()], prologue[..]);
+    let resume = code.resume.unwrap();
+    assert_eq!(code.code[resume..resume + prologue.len()], prologue[..]);
+
+    // One RET, ending the exit stub, which every exit path branches to
+    let rets: Vec<usize> = (0..code.code.len())
+        .filter(|&at| code.code[at] == arm64::RET)
+        .collect();
+    assert_eq!(rets.len(), 1);
+    assert_eq!(
+        code.code[rets[0] + 1 - restore.len()..=rets[0]],
+        restore[..]
+    );
+}
+
+#[test]
+fn indirect_jump_dispatch() {
+    let code = compile(&[
+        Instruction::Addi {
+            rd: 1,
+            rs1: 1,
+            imm: 4,
+        },
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 1,
+            imm: 0,
+        },
+    ]);
+
+    let ret = code
+        .code
+        .iter()
+        .position(|&word| word == arm64::RET)
+        .unwrap();
+    let dispatch = ret + 1;
+    assert_eq!(
+        *slot(&code, 1).last().unwrap(),
+        arm64::b(((dispatch - code.offsets[2]) as i32 + 1) * 4)
+    );
+
+    // Bounds and alignment checks fall through to an EXIT_JUMP and running
+    // out of gas to an EXIT_OUT_OF_GAS
+    assert_eq!(
+        code.code[dispatch..dispatch + 15],
+        [
+            arm64::movz(10, 8, 0),
+            arm64::cmp(9, 10),
+            arm64::b_cond(Condition::Hs, 52),
+            arm64::tbnz(9, 0, 48),
+            arm64::tbnz(9, 1, 44),
+            arm64::adr(16, 56),
+            arm64::add_x_lsl(16, 16, 9, 1),
+            arm64::ldr(17, 16, 4),
+            arm64::ldr_x(10, 19, 48),
+            arm64::subs_x(10, 10, 17),
+            arm64::b_cond(Condition::Lo, 28),
+            arm64::str_x(10, 19, 48),
+            arm64::ldrsw(17, 16, 0),
+            arm64::add_x(16, 16, 17),
+            arm64::br(16),
+        ]
+    );
+    assert_eq!(code.code[dispatch + 15], arm64::movz(10, 0, 0));
+    assert_eq!(
+        code.code[dispatch + 16],
+        arm64::b((ret as i32 - 16 - (dispatch + 16) as i32) * 4)
+    );
+    assert_eq!(code.code[dispatch + 17], arm64::movz(10, 5, 0));
+
+    // Each entry holds the byte offset from itself back to the instruction
+    // and the gas left to charge: none for the block leader, which charges
+    // the whole block itself, and one for the JALR inside the block
+    let table = dispatch + 19;
+    assert_eq!(code.code.len(), table + 4);
+    for (index, cost) in [0, 1].into_iter().enumerate() {
+        let entry = table + index * 2;
+        let offset = (code.offsets[index] as i32 - entry as i32) * 4;
+        assert_eq!(code.code[entry..entry + 2], [offset as u32, cost]);
+    }
+}
 
+#[test]
+fn no_dispatch_without_jalr() {
+    let code = compile(&[Instruction::Jal { rd: 1, imm: 0 }]);
+    assert_eq!(*code.code.last().unwrap(), arm64::RET);
+}
+
+#[test]
+fn empty_compilation() {
     let mut buffer = vec![0u8; 1024];
-    let size = compiler.compile(&instructions, &mut buffer);
+    let size = Compiler::new().compile(&[], &mut buffer);
 
-    // Should still emit a RET for safety
-    assert_eq!(size, 4);
-    assert_eq!(&buffer[..size], vec![0xC0, 0x03, 0x5F, 0xD6]);
+    // Still emits the prologue, the end sequence and the exit stub
+    assert!(size > PROLOGUE_WORDS * 4);
+    assert!(size <= Compiler::code_size(0));
+    assert_eq!(&buffer[size - 4..size], arm64::RET.to_le_bytes());
 }
 
 #[test]
 fn multiple_instructions() {
-    let mut compiler = Compiler::new();
-    let instructions = vec![
+    let code = compile(&[
         Instruction::Add {
             rd: 1,
             rs1: 2,
@@ -47,25 +217,1724 @@ fn multiple_instructions() {
             rs1: 5,
             rs2: 6,
         },
-    ];
+    ]);
 
-    let mut buffer = vec![0u8; 1024];
-    let size = compiler.compile(&instructions, &mut buffer);
+    // x1-x6 are mapped to w21-w26 in order
+    assert_eq!(slot(&code, 0)[0], arm64::add(21, 22, 23));
+    assert_eq!(slot(&code, 1), [arm64::sub(24, 25, 26)]);
 
-    // Should still just emit a RET for now
-    assert_eq!(size, 4);
-    assert_eq!(&buffer[..size], vec![0xC0, 0x03, 0x5F, 0xD6]);
+    // Only the first instruction opens a basic block and charges for both
+    let charge = charge(&code, 0).len();
+    assert_eq!(charge, 7);
+    assert!(self::charge(&code, 1).is_empty());
+    assert_eq!(
+        code.offsets,
+        [
+            PROLOGUE_WORDS,
+            PROLOGUE_WORDS + charge + 1,
+            PROLOGUE_WORDS + charge + 2
+        ]
+    );
 }
 
 #[test]
 fn insufficient_buffer_space() {
-    let mut compiler = Compiler::new();
     let instructions = vec![Instruction::Add {
         rd: 1,
         rs1: 2,
         rs2: 3,
     }];
-    let mut buffer = vec![0u8; 3]; // Too small for 4-byte RET
-    let size = compiler.compile(&instructions, &mut buffer);
+    let mut buffer = vec![0u8; 3];
+    assert_eq!(Compiler::new().compile(&instructions, &mut buffer), 0);
+
+    let mut buffer = vec![0u8; Compiler::estimate_size(&instructions) - 4];
+    assert_eq!(Compiler::new().compile(&instructions, &mut buffer), 0);
+
+    let mut buffer = vec![0u8; Compiler::estimate_size(&instructions)];
+    assert_ne!(Compiler::new().compile(&instructions, &mut buffer), 0);
+}
+
+#[test]
+fn estimate_size_bounds_every_option() {
+    let program = long_program(40);
+    let estimate = Compiler::estimate_size(&program);
+    assert!(estimate < Compiler::code_size(program.len()) / 2);
+    for opt_level in [OptLevel::O0, OptLevel::O1, OptLevel::O2] {
+        for options in 0..16 {
+            let mut compiler = Compiler::with_opt_level(opt_level);
+            compiler.set_resumable(options & 1 != 0);
+            compiler.set_block_counters(options & 2 != 0);
+            if options & 4 != 0 {
+                compiler.set_constants(ConstantStrategy::LiteralPool);
+            }
+            let mut estimate = estimate;
+            if options & 8 != 0 {
+                let functions: Vec<u32> = (0..40).step_by(3).map(|index| index * 4).collect();
+                compiler.set_functions(&functions);
+                estimate = Compiler::estimate_size_with(&program, functions.len());
+            }
+            let mut buffer = vec![0u8; estimate];
+            let size = compiler.compile(&program, &mut buffer);
+            assert!(size > 0 && size <= estimate);
+        }
+    }
+}
+
+#[test]
+fn estimate_size_follows_instruction_classes() {
+    let add = Instruction::Add {
+        rd: 1,
+        rs1: 2,
+        rs2: 3,
+    };
+    let load = Instruction::Lw {
+        rd: 1,
+        rs1: 2,
+        imm: 0,
+    };
+    let empty = Compiler::estimate_size(&[]);
+    let adds = Compiler::estimate_size(&[add.clone(), add.clone()]) - empty;
+    let loads = Compiler::estimate_size(&[load.clone(), load]) - empty;
+    assert!(adds < loads);
+    assert!(loads < Compiler::code_size(2) - Compiler::code_size(0));
+}
+
+#[test]
+fn code_size_grows_per_instruction() {
+    let step = Compiler::code_size(1) - Compiler::code_size(0);
+    // Room for the code, a two word dispatch table entry and a function entry
+    assert_eq!(step, (MAX_WORDS + 2 + 3) * 4);
+    assert_eq!(Compiler::code_size(10), Compiler::code_size(0) + 10 * step);
+    assert_eq!(Compiler::code_size(usize::MAX), usize::MAX);
+}
+
+#[test]
+fn code_size_has_room_for_veneers() {
+    // Past the reach of B, each instruction, its copy out of line and its
+    // function entry can get an island with a veneer for each of its branches
+    let step = |count| Compiler::code_size(count) - Compiler::code_size(count - 1);
+    let words = MAX_WORDS + 2 + 3;
+    let count = veneer::REACH / (words * 4);
+    let island = (1 + VENEER_WORDS * MAX_BRANCHES) * 4;
+    assert_eq!(step(count / 2), words * 4);
+    assert_eq!(step(count * 2), words * 4 + 3 * island);
+}
+
+#[test]
+fn x0_reads_as_zero_and_ignores_writes() {
+    let code = compile(&[
+        Instruction::Add {
+            rd: 0,
+            rs1: 1,
+            rs2: 2,
+        },
+        Instruction::Addi {
+            rd: 3,
+            rs1: 0,
+            imm: 5,
+        },
+    ]);
+
+    assert!(slot(&code, 0).is_empty());
+    assert_eq!(slot(&code, 1), [arm64::movz(23, 5, 0)]);
+}
+
+#[test]
+fn backward_branch_fixup() {
+    let code = compile(&[
+        Instruction::Addi {
+            rd: 1,
+            rs1: 1,
+            imm: -1,
+        },
+        Instruction::Bne {
+            rs1: 1,
+            rs2: 0,
+            imm: -4,
+        },
+    ]);
+
+    let branch = slot(&code, 1);
+    assert_eq!(branch[0], arm64::cmp(21, arm64::ZR));
+    assert_eq!(branch[1], arm64::b_cond(Condition::Eq, 8));
+    // Back over the compare, the skip, the one word of the ADDI and the
+    // seven of the gas charge opening its block
+    assert_eq!(branch[2], arm64::b(-40));
+}
+
+#[test]
+fn forward_branch_fixup() {
+    let code = compile(&[
+        Instruction::Jal { rd: 0, imm: 12 },
+        Instruction::Add {
+            rd: 1,
+            rs1: 2,
+            rs2: 3,
+        },
+        Instruction::Mulhsu {
+            rd: 1,
+            rs1: 2,
+            rs2: 3,
+        },
+        Instruction::Addi {
+            rd: 1,
+            rs1: 1,
+            imm: 1,
+        },
+    ]);
+
+    // Jumps over one word of ADD, three of MULHSU and the charge for the
+    // block they form, landing on the charge of the target block
+    assert_eq!(slot(&code, 0), [arm64::b(48)]);
+    assert_eq!(code.offsets[3] - code.offsets[0], 19);
+}
+
+#[test]
+fn exit_branches_target_exit_stub() {
+    let code = compile(&[Instruction::Ebreak, Instruction::Rdcycle { rd: 1 }]);
+
+    // Both exits and the end sequence branch to the first word of the stub
+    let stub = code.offsets[2] + 3;
+    for (index, &start) in code.offsets.iter().enumerate() {
+        let at = start + charge(&code, index).len() + 2;
+        let offset = (stub - at) as i32 * 4;
+        assert_eq!(code.code[at], arm64::b(offset), "exit {}", index);
+    }
+    assert_eq!(code.code[stub], arm64::str(21, 20, 4));
+}
+
+#[test]
+fn branch_outside_module_exits() {
+    let code = compile(&[Instruction::Beq {
+        rs1: 1,
+        rs2: 2,
+        imm: 0x100,
+    }]);
+
+    let branch = slot(&code, 0);
+    assert_eq!(branch.len(), 5);
+    assert_eq!(branch[0], arm64::cmp(21, 22));
+    assert_eq!(branch[1], arm64::b_cond(Condition::Ne, 16));
+    assert_eq!(branch[2], arm64::movz(9, 0x100, 0));
+    assert_eq!(branch[3], arm64::movz(10, 0, 0));
+}
+
+#[test]
+fn unsupported_instruction_exits() {
+    let code = compile(&[Instruction::Ebreak, Instruction::Rdcycle { rd: 1 }]);
+
+    assert_eq!(
+        slot(&code, 0)[..2],
+        [arm64::movz(9, 0, 0), arm64::movz(10, 3, 0)]
+    );
+    assert_eq!(
+        slot(&code, 1)[..2],
+        [arm64::movz(9, 4, 0), arm64::movz(10, 4, 0)]
+    );
+}
+
+#[test]
+fn ebreak_exits_and_ends_block() {
+    let code = compile(&[
+        Instruction::Addi {
+            rd: 1,
+            rs1: 1,
+            imm: 1,
+        },
+        Instruction::Ebreak,
+        Instruction::Addi {
+            rd: 1,
+            rs1: 1,
+            imm: 1,
+        },
+    ]);
+
+    // Exits at its own PC with nothing following in the block
+    let ebreak = slot(&code, 1);
+    assert_eq!(ebreak.len(), 3);
+    assert_eq!(ebreak[..2], [arm64::movz(9, 4, 0), arm64::movz(10, 3, 0)]);
+    assert_eq!(charge(&code, 0)[1], arm64::subs_imm_x(16, 16, 2));
+    assert_eq!(charge(&code, 2)[1], arm64::subs_imm_x(16, 16, 1));
+}
+
+#[test]
+fn ecall_calls_trampoline() {
+    let code = compile(&[
+        Instruction::Add {
+            rd: 10,
+            rs1: 10,
+            rs2: 10,
+        },
+        Instruction::Ecall,
+        Instruction::Ebreak,
+    ]);
+
+    // Mapped registers are written back around the call so the handler sees
+    // and may change them
+    let ecall = slot(&code, 1);
+    assert_eq!(ecall[0], arm64::str(22, 20, 4));
+    assert_eq!(ecall[7], arm64::str(21, 20, 40));
+    assert_eq!(
+        ecall[8..12],
+        [
+            arm64::mov_x(0, 19),
+            arm64::movz(1, 4, 0),
+            arm64::ldr_x(16, 19, 64),
+            arm64::blr(16),
+        ]
+    );
+    assert_eq!(ecall[12], arm64::ldr(22, 20, 4));
+    assert_eq!(ecall[19], arm64::ldr(21, 20, 40));
+
+    // A declined call exits at the ECALL
+    assert_eq!(
+        ecall[20..23],
+        [
+            arm64::cbnz(0, 16),
+            arm64::movz(9, 4, 0),
+            arm64::movz(10, 2, 0)
+        ]
+    );
+
+    // The ECALL ends its basic block
+    assert!(!charge(&code, 2).is_empty());
+}
+
+#[test]
+fn constants_fold_pc() {
+    let code = compile(&[
+        Instruction::Addi {
+            rd: 0,
+            rs1: 0,
+            imm: 0,
+        },
+        Instruction::Auipc {
+            rd: 5,
+            imm: 0x12345,
+        },
+    ]);
+
+    assert_eq!(
+        slot(&code, 1),
+        [arm64::movz(21, 0x5004, 0), arm64::movk(21, 0x1234, 16)]
+    );
+}
+
+#[test]
+fn division_by_zero_fixup() {
+    let code = compile(&[
+        Instruction::Div {
+            rd: 1,
+            rs1: 2,
+            rs2: 3,
+        },
+        Instruction::Remu {
+            rd: 1,
+            rs1: 2,
+            rs2: 3,
+        },
+    ]);
+
+    // Quotients are forced to all ones when the divisor is zero
+    assert_eq!(
+        slot(&code, 0)[..3],
+        [
+            arm64::sdiv(11, 22, 23),
+            arm64::cmp(23, arm64::ZR),
+            arm64::csinv(21, 11, arm64::ZR, Condition::Ne),
+        ]
+    );
+    // Remainders fall out of the zero quotient
+    assert_eq!(
+        slot(&code, 1)[..2],
+        [arm64::udiv(11, 22, 23), arm64::msub(21, 11, 23, 22)]
+    );
+}
+
+#[test]
+fn multiply_high_word() {
+    let code = compile(&[Instruction::Mulhsu {
+        rd: 1,
+        rs1: 2,
+        rs2: 3,
+    }]);
+
+    assert_eq!(
+        slot(&code, 0)[..3],
+        [
+            arm64::sxtw(11, 22),
+            arm64::mul_x(21, 11, 23),
+            arm64::lsr_32_x(21, 21),
+        ]
+    );
+}
+
+#[test]
+fn mapped_registers_loaded_and_written_back() {
+    let code = compile(&[Instruction::Add {
+        rd: 10,
+        rs1: 10,
+        rs2: 10,
+    }]);
+
+    // x10 is used most, then x1-x7 in register order
+    let prologue = &code.code[PROLOGUE_WORDS - 8..PROLOGUE_WORDS];
+    assert_eq!(prologue[0], arm64::ldr(22, 20, 4));
+    assert_eq!(prologue[6], arm64::ldr(28, 20, 28));
+    assert_eq!(prologue[7], arm64::ldr(21, 20, 40));
+
+    // The end sequence is a MOVZ pair and a branch to the exit stub
+    let exit = &code.code[code.offsets[1] + 3..];
+    assert_eq!(exit[0], arm64::str(22, 20, 4));
+    assert_eq!(exit[7], arm64::str(21, 20, 40));
+    assert_eq!(exit[8], arm64::str(9, 19, 32));
+}
+
+#[test]
+fn spilled_registers_use_register_file() {
+    // x10 is used three times, x1-x9 twice and x11-x12 once, so x8, x9,
+    // x11 and x12 are spilled
+    let mut program: Vec<_> = (1..=9)
+        .map(|reg| Instruction::Addi {
+            rd: reg,
+            rs1: reg,
+            imm: 1,
+        })
+        .collect();
+    program.push(Instruction::Add {
+        rd: 10,
+        rs1: 10,
+        rs2: 10,
+    });
+    program.push(Instruction::Sw {
+        rs1: 11,
+        rs2: 12,
+        imm: -4,
+    });
+    let code = compile(&program);
+
+    assert_eq!(slot(&code, 0), [arm64::add_imm(22, 22, 1)]);
+    assert_eq!(
+        slot(&code, 7)[..3],
+        [
+            arm64::ldr(9, 20, 32),
+            arm64::add_imm(9, 9, 1),
+            arm64::str(9, 20, 32),
+        ]
+    );
+    assert_eq!(slot(&code, 9), [arm64::add(21, 21, 21)]);
+    assert_eq!(
+        slot(&code, 10)[..3],
+        [
+            arm64::ldr(1, 20, 44),
+            arm64::sub_imm(1, 1, 4),
+            arm64::ldr(2, 20, 48),
+        ]
+    );
+}
+
+#[test]
+fn basic_blocks_charge_on_entry() {
+    let code = compile(&[
+        Instruction::Addi {
+            rd: 5,
+            rs1: 0,
+            imm: 3,
+        },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 5,
+            imm: -1,
+        },
+        Instruction::Bne {
+            rs1: 5,
+            rs2: 0,
+            imm: -4,
+        },
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 1,
+            imm: 0,
+        },
+    ]);
+
+    // Blocks start at the entry, the branch target and after the branch
+    let loop_block = charge(&code, 1);
+    assert_eq!(
+        loop_block[..5],
+        [
+            arm64::ldr_x(16, 19, 48),
+            arm64::subs_imm_x(16, 16, 2),
+            arm64::b_cond(Condition::Hs, 16),
+            arm64::movz(9, 4, 0),
+            arm64::movz(10, 5, 0),
+        ]
+    );
+    assert_eq!(loop_block[6], arm64::str_x(16, 19, 48));
+    assert_eq!(charge(&code, 0)[1], arm64::subs_imm_x(16, 16, 1));
+    assert!(charge(&code, 2).is_empty());
+    assert_eq!(charge(&code, 3)[1], arm64::subs_imm_x(16, 16, 1));
+}
+
+#[test]
+fn load_walks_page_tables() {
+    let code = compile(&[Instruction::Lw {
+        rd: 2,
+        rs1: 1,
+        imm: 8,
+    }]);
+    let load = slot(&code, 0);
+    let tlb = offset_of!(Context, tlb_read) as u32;
+
+    // A misaligned address sends the access to the helper
+    assert_eq!(load[2..4], [arm64::tbnz(1, 0, 124), arm64::tbnz(1, 1, 120)]);
+    // The page in the TLB entry skips the walk
+    assert_eq!(
+        load[4..9],
+        [
+            arm64::ldr(16, 19, tlb + 8),
+            arm64::lsr_imm(17, 1, 14),
+            arm64::cmp(16, 17),
+            arm64::b_cond(Condition::Ne, 20),
+            arm64::ldr_x(16, 19, tlb),
+        ]
+    );
+    // The inline load skips the helper call
+    assert_eq!(load[10..12], [arm64::ldr_reg(0, 16, 17), arm64::b(116)]);
+    // Devices send the access to the helper
+    assert_eq!(load[13], arm64::cbnz(16, 80));
+    // Read permission is checked in the byte alongside the L2 entry
+    assert_eq!(load[22], arm64::tbz(17, 0, 44));
+    // The page found refills the entry, then takes the inline load
+    assert_eq!(
+        load[29..33],
+        [
+            arm64::str_x(16, 19, tlb),
+            arm64::lsr_imm(17, 1, 14),
+            arm64::str(17, 19, tlb + 8),
+            arm64::b(-92),
+        ]
+    );
+    assert_eq!(load[33], arm64::movz(2, 2, 0));
+    assert_eq!(*load.last().unwrap(), arm64::mov(22, 0));
+}
+
+#[test]
+fn store_refuses_shared_pages() {
+    let code = compile(&[Instruction::Sb {
+        rs1: 1,
+        rs2: 2,
+        imm: 0,
+    }]);
+    let store = slot(&code, 0);
+    let tlb = offset_of!(Context, tlb_write) as u32;
+
+    // Byte accesses are always aligned, and stores have a TLB entry of their
+    // own
+    assert_eq!(store[3], arm64::ldr(16, 19, tlb + 8));
+    assert_eq!(store[7], arm64::ldr_x(16, 19, tlb));
+    assert_eq!(store[9..11], [arm64::strb_reg(2, 16, 17), arm64::b(120)]);
+    assert_eq!(store[13], arm64::add_x_lsr(16, 0, 1, 22));
+    assert_eq!(
+        store[21..23],
+        [arm64::tbz(17, 1, 48), arm64::tbnz(17, 3, 44)]
+    );
+    assert_eq!(store[29], arm64::str_x(16, 19, tlb));
+}
+
+#[test]
+fn longest_sequence_fits() {
+    // A store between spilled registers far enough in that its PC takes two
+    // words to materialize on the exits, opening a block long enough that its
+    // cost takes two words too, and after enough blocks that its counter
+    // offset takes two words as well
+    let addi = |index: usize| Instruction::Addi {
+        rd: index as u8 % 8 + 1,
+        rs1: 0,
+        imm: 0,
+    };
+    let mut program: Vec<_> = (0..0x4000)
+        .map(|index| match index % 2 {
+            0 => addi(index),
+            _ => Instruction::Jal { rd: 0, imm: 4 },
+        })
+        .collect();
+    program.push(Instruction::Jal { rd: 0, imm: 4 });
+    program.push(Instruction::Sw {
+        rs1: 9,
+        rs2: 10,
+        imm: -4,
+    });
+    program.extend((0..0x10000).map(addi));
+    let code = compile(&program);
+    assert_eq!(charge(&code, 0x4001).len(), 10);
+    assert_eq!(
+        code.offsets[0x4002] - code.offsets[0x4001],
+        MAX_WORDS - COUNTER_WORDS
+    );
+
+    let counted = Translator::translate(
+        &program,
+        &Options {
+            count_blocks: true,
+            ..Options::default()
+        },
+    );
+    assert!(counted.counters.len() > 4096);
+    assert_eq!(counted.offsets[0x4002] - counted.offsets[0x4001], MAX_WORDS);
+}
+
+#[test]
+fn block_counters() {
+    let program = [
+        Instruction::Addi {
+            rd: 1,
+            rs1: 0,
+            imm: 1,
+        },
+        Instruction::Bne {
+            rs1: 1,
+            rs2: 0,
+            imm: 8,
+        },
+        Instruction::Addi {
+            rd: 2,
+            rs1: 0,
+            imm: 2,
+        },
+        Instruction::Addi {
+            rd: 3,
+            rs1: 0,
+            imm: 3,
+        },
+    ];
+    let plain = Translator::translate(&program, &Options::default());
+    assert!(plain.counters.is_empty());
+    let counted = Translator::translate(
+        &program,
+        &Options {
+            count_blocks: true,
+            ..Options::default()
+        },
+    );
+    assert_eq!(counted.counters, [0, 8, 12]);
+
+    // Each block increments its counter right after the gas charge
+    let counters = std::mem::offset_of!(crate::instance::Context, counters) as u32;
+    for (number, index) in [0, 2, 3].into_iter().enumerate() {
+        let charge = charge(&counted, index).len();
+        let words = &counted.code[counted.offsets[index]..];
+        let offset = number as u32 * 8;
+        assert_eq!(
+            words[charge..charge + 4],
+            [
+                arm64::ldr_x(17, 19, counters),
+                arm64::ldr_x(16, 17, offset),
+                arm64::add_imm_x(16, 16, 1),
+                arm64::str_x(16, 17, offset),
+            ]
+        );
+        assert_eq!(
+            &words[charge + 4..counted.offsets[index + 1] - counted.offsets[index]],
+            slot(&plain, index)
+        );
+    }
+    // The branch does not start a block, so only its offset moves
+    assert_eq!(slot(&counted, 1).len(), slot(&plain, 1).len());
+}
+
+/// Build a block for comparison
+fn block(start: usize, end: usize, successors: &[usize], exits: bool) -> BasicBlock {
+    BasicBlock {
+        start,
+        end,
+        successors: successors.to_vec(),
+        exits,
+    }
+}
+
+#[test]
+fn basic_blocks_split_at_branches_and_targets() {
+    let blocks = Compiler::basic_blocks(&[
+        Instruction::Addi {
+            rd: 5,
+            rs1: 0,
+            imm: 3,
+        },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 5,
+            imm: -1,
+        },
+        Instruction::Bne {
+            rs1: 5,
+            rs2: 0,
+            imm: -4,
+        },
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 1,
+            imm: 0,
+        },
+    ]);
+
+    assert_eq!(
+        blocks,
+        [
+            block(0, 1, &[1], false),
+            block(1, 3, &[1, 2], false),
+            block(3, 4, &[], true),
+        ]
+    );
+}
+
+#[test]
+fn basic_blocks_exit_the_module() {
+    let add = || Instruction::Add {
+        rd: 1,
+        rs1: 1,
+        rs2: 1,
+    };
+    let blocks = Compiler::basic_blocks(&[
+        Instruction::Beq {
+            rs1: 1,
+            rs2: 2,
+            imm: 0x100,
+        },
+        Instruction::Jal { rd: 1, imm: 8 },
+        Instruction::Ecall,
+        add(),
+        Instruction::Ebreak,
+        add(),
+    ]);
+
+    // Branches outside the module, ECALL stops, EBREAK and running off the
+    // end leave the known blocks; the JAL lands where the ECALL resumes
+    assert_eq!(
+        blocks,
+        [
+            block(0, 1, &[1], true),
+            block(1, 2, &[3], false),
+            block(2, 3, &[3], true),
+            block(3, 5, &[], true),
+            block(5, 6, &[], true),
+        ]
+    );
+}
+
+#[test]
+fn basic_blocks_of_nothing() {
+    assert!(Compiler::basic_blocks(&[]).is_empty());
+    assert_eq!(
+        Compiler::basic_blocks(&[Instruction::Ecall]),
+        [block(0, 1, &[], true)]
+    );
+}
+
+#[test]
+fn check_targets_inside_module() {
+    let nop = || Instruction::Addi {
+        rd: 0,
+        rs1: 0,
+        imm: 0,
+    };
+    let branch = |imm| Instruction::Bne {
+        rs1: 1,
+        rs2: 0,
+        imm,
+    };
+    // Instruction boundaries, the end of the module and targets outside it
+    for imm in [-4, 0, 8, 12, 0x102, -6] {
+        assert_eq!(
+            Compiler::check_targets(&[nop(), branch(imm), nop()]),
+            Ok(())
+        );
+    }
+    assert_eq!(
+        Compiler::check_targets(&[nop(), branch(6), nop()]),
+        Err(CompileError::InvalidJumpTarget {
+            offset: 4,
+            target: 10
+        })
+    );
+    assert_eq!(
+        Compiler::check_targets(&[nop(), nop(), Instruction::Jal { rd: 1, imm: -6 }]),
+        Err(CompileError::InvalidJumpTarget {
+            offset: 8,
+            target: 2
+        })
+    );
+}
+
+#[test]
+fn optimization_disabled_by_default() {
+    let mut compiler = Compiler::new();
+    assert_eq!(compiler.opt_level(), OptLevel::O0);
+    compiler.set_opt_level(OptLevel::O1);
+    assert_eq!(compiler.opt_level(), OptLevel::O1);
+    assert_eq!(
+        Compiler::with_opt_level(OptLevel::O2).opt_level(),
+        OptLevel::O2
+    );
+}
+
+#[test]
+fn opt_levels_select_passes() {
+    let program = [
+        Instruction::Addi {
+            rd: 1,
+            rs1: 0,
+            imm: 3,
+        },
+        Instruction::Add {
+            rd: 2,
+            rs1: 3,
+            rs2: 1,
+        },
+        Instruction::Or {
+            rd: 3,
+            rs1: 3,
+            rs2: 0,
+        },
+    ];
+    let compile = |opt_level| {
+        let mut buffer = vec![0u8; Compiler::code_size(program.len())];
+        let size = Compiler::with_opt_level(opt_level).compile(&program, &mut buffer);
+        buffer.truncate(size);
+        buffer
+    };
+    let words = |translation: Translation| -> Vec<u8> {
+        translation
+            .code
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect()
+    };
+
+    // O1 only drops the copy of x3 to itself; O2 also turns the ADD of the
+    // known x1 into an ADDI
+    let o1 = Translator::translate(
+        &peephole::optimize(&program),
+        &Options {
+            optimize: true,
+            ..Options::default()
+        },
+    );
+    assert_eq!(slot(&o1, 1), [arm64::add(22, 23, 21)]);
+    assert!(slot(&o1, 2).is_empty());
+    let o2 = Translator::translate(
+        &peephole::optimize(&fold::fold(&program)),
+        &Options {
+            optimize: true,
+            ..Options::default()
+        },
+    );
+    assert_eq!(slot(&o2, 1), [arm64::add_imm(22, 23, 3)]);
+
+    assert_eq!(compile(OptLevel::O1), words(o1));
+    assert_eq!(compile(OptLevel::O2), words(o2));
+    assert!(compile(OptLevel::O1).len() < compile(OptLevel::O0).len());
+}
+
+#[test]
+fn optimized_branches_test_zero() {
+    let program = [
+        Instruction::Or {
+            rd: 1,
+            rs1: 1,
+            rs2: 0,
+        },
+        Instruction::Beq {
+            rs1: 1,
+            rs2: 0,
+            imm: -4,
+        },
+        Instruction::Blt {
+            rs1: 1,
+            rs2: 0,
+            imm: -4,
+        },
+        Instruction::Bltu {
+            rs1: 1,
+            rs2: 0,
+            imm: -8,
+        },
+    ];
+    let mut compiler = Compiler::with_opt_level(OptLevel::O2);
+    let mut buffer = vec![0u8; Compiler::code_size(program.len())];
+    let size = compiler.compile(&program, &mut buffer);
+    let optimized = peephole::optimize(&fold::fold(&program));
+    let code = Translator::translate(
+        &optimized,
+        &Options {
+            optimize: true,
+            ..Options::default()
+        },
+    );
+    assert_eq!(size, code.code.len() * 4);
+    assert!(size < Compiler::new().compile(&program, &mut buffer));
+
+    // The copy to itself emits nothing and the branches skip their jump by
+    // testing x1 (w21) directly, jumping back over the block charges
+    let back = |index: usize| {
+        let at = code.offsets[index] + charge(&code, index).len() + 1;
+        (code.offsets[index - 1] as i32 - at as i32) * 4
+    };
+    assert!(slot(&code, 0).is_empty());
+    assert_eq!(slot(&code, 1), [arm64::cbnz(21, 8), arm64::b(back(1))]);
+    assert_eq!(slot(&code, 2), [arm64::tbz(21, 31, 8), arm64::b(back(2))]);
+    // Unsigned comparisons keep the CMP
+    assert_eq!(slot(&code, 3)[0], arm64::cmp(21, arm64::ZR));
+}
+
+#[test]
+fn source_map_round_trips() {
+    let instructions = [
+        Instruction::Addi {
+            rd: 5,
+            rs1: 0,
+            imm: 1,
+        },
+        Instruction::Beq {
+            rs1: 5,
+            rs2: 0,
+            imm: -4,
+        },
+        Instruction::Add {
+            rd: 6,
+            rs1: 5,
+            rs2: 5,
+        },
+    ];
+    let mut buffer = vec![0u8; Compiler::code_size(instructions.len())];
+    let (size, map) = Compiler::new().compile_mapped(&instructions, &mut buffer);
+    let translation = compile(&instructions);
+    assert_eq!(size, translation.code.len() * 4);
+    assert_eq!(map.len(), 3);
+
+    for (index, window) in translation.offsets.windows(2).enumerate() {
+        let pc = index as u32 * 4;
+        assert_eq!(map.code(pc), Some(window[0] * 4..window[1] * 4));
+        assert_eq!(map.native(pc), Some(window[0] * 4));
+        for offset in window[0] * 4..window[1] * 4 {
+            assert_eq!(map.guest(offset), Some(pc));
+        }
+    }
+    assert_eq!(map.guest(0), None);
+    assert_eq!(map.guest(translation.offsets[3] * 4), None);
+    assert_eq!(map.native(12), None);
+    assert_eq!(map.native(2), None);
+}
+
+#[test]
+fn source_map_empty_without_space() {
+    let instructions = [Instruction::Jalr {
+        rd: 0,
+        rs1: 1,
+        imm: 0,
+    }];
+    let mut buffer = [0u8; 4];
+    let (size, map) = Compiler::new().compile_mapped(&instructions, &mut buffer);
     assert_eq!(size, 0);
+    assert_eq!(map, SourceMap::default());
+    assert!(map.is_empty());
+}
+
+#[test]
+fn listing_annotates_guest_code() {
+    let instructions = [
+        Instruction::Addi {
+            rd: 5,
+            rs1: 0,
+            imm: 1,
+        },
+        Instruction::Beq {
+            rs1: 5,
+            rs2: 0,
+            imm: -4,
+        },
+    ];
+    let mut buffer = vec![0u8; Compiler::code_size(instructions.len())];
+    let (size, map) = Compiler::new().compile_mapped(&instructions, &mut buffer);
+    let listing = Compiler::listing(&instructions, &buffer[..size], &map);
+    let lines: Vec<&str> = listing.lines().collect();
+
+    // Every word is listed once, under its guest instruction or a header
+    let native = lines
+        .iter()
+        .filter(|line| line.starts_with("    0x"))
+        .count();
+    assert_eq!(native, size / 4);
+    assert_eq!(lines[0], "prologue:");
+    assert_eq!(lines[1], "    0x0000:  stp x29, x30, [sp, #-96]!");
+
+    let addi = lines
+        .iter()
+        .position(|line| *line == "0x00000000:  addi x5, x0, 1");
+    let beq = lines
+        .iter()
+        .position(|line| *line == "0x00000004:  beq x5, x0, 0x0");
+    let end = lines.iter().position(|line| *line == "end:");
+    let (addi, beq, end) = (addi.unwrap(), beq.unwrap(), end.unwrap());
+    assert!(addi < beq && beq < end);
+    let first = format!("    0x{:04x}:  ", map.native(0).unwrap());
+    assert!(lines[addi + 1].starts_with(&first));
+    assert_eq!(beq - addi - 1, map.code(0).unwrap().len() / 4);
+    assert!(lines[..end].iter().all(|line| !line.contains(".word")));
+    assert!(lines.last().unwrap().ends_with(":  ret"));
+}
+
+#[test]
+fn pc_relative_values_use_base() {
+    let program = [
+        Instruction::Auipc { rd: 5, imm: 0x12 },
+        Instruction::Jal { rd: 1, imm: 8 },
+        Instruction::Ebreak,
+        Instruction::Ebreak,
+    ];
+    let code = Translator::translate(
+        &program,
+        &Options {
+            base: 0x10000,
+            ..Options::default()
+        },
+    );
+    assert_eq!(
+        slot(&code, 0),
+        arm64::mov_imm(host(&program, 5), 0x10000 + 0x12000)
+    );
+    assert!(
+        slot(&code, 1)
+            .windows(2)
+            .any(|words| words == arm64::mov_imm(host(&program, 1), 0x10008))
+    );
+    let plain = Translator::translate(&program, &Options::default());
+    assert_eq!(slot(&plain, 0), arm64::mov_imm(host(&program, 5), 0x12000));
+}
+
+#[test]
+fn dispatch_rebases_pc() {
+    let program = [Instruction::Jalr {
+        rd: 0,
+        rs1: 1,
+        imm: 0,
+    }];
+    let sub = arm64::sub(11, 9, 10);
+    let plain = Translator::translate(&program, &Options::default());
+    assert!(!plain.code.contains(&sub));
+    let code = Translator::translate(
+        &program,
+        &Options {
+            base: 0x4000,
+            ..Options::default()
+        },
+    );
+    assert!(code.code.contains(&sub));
+    assert_eq!(code.counters, plain.counters);
+}
+
+#[test]
+fn literal_pool_shares_constants() {
+    let program = [
+        Instruction::Lui {
+            rd: 5,
+            imm: 0x12345,
+        },
+        Instruction::Lui {
+            rd: 6,
+            imm: 0x12345,
+        },
+        Instruction::Lui { rd: 7, imm: 0x1 },
+    ];
+    let plain = Translator::translate(&program, &Options::default());
+    let pooled = Translator::translate(
+        &program,
+        &Options {
+            literal_pool: true,
+            ..Options::default()
+        },
+    );
+    assert_eq!(
+        slot(&plain, 0),
+        arm64::mov_imm(host(&program, 5), 0x12345000)
+    );
+    // One load each, from a single pool entry at the very end
+    assert_eq!(pooled.code.len(), plain.code.len() - 1);
+    assert_eq!(pooled.code.last(), Some(&0x12345000));
+    let pool = pooled.code.len() as i32 - 1;
+    for (index, rd) in [(0, 5), (1, 6)] {
+        let at = pooled.offsets[index + 1] as i32 - 1;
+        assert_eq!(
+            slot(&pooled, index),
+            [arm64::ldr_literal(host(&program, rd), (pool - at) * 4)]
+        );
+    }
+    // Constants one move can make stay moves
+    assert_eq!(slot(&pooled, 2), slot(&plain, 2));
+}
+
+#[test]
+fn literal_pool_needs_small_code() {
+    let program = vec![
+        Instruction::Lui {
+            rd: 5,
+            imm: 0x12345,
+        };
+        1 << 17
+    ];
+    assert!(Compiler::estimate_size(&program) > 1 << 20);
+    let plain = Translator::translate(&program, &Options::default());
+    let pooled = Translator::translate(
+        &program,
+        &Options {
+            literal_pool: true,
+            ..Options::default()
+        },
+    );
+    assert_eq!(pooled.code, plain.code);
+}
+
+#[test]
+fn literal_pool_matches_across_threads() {
+    // Stores swapped out, for code small enough to reach the pool
+    let program: Vec<_> = long_program(3 * MIN_REGION)
+        .into_iter()
+        .map(|instruction| match instruction {
+            Instruction::Sw { .. } => Instruction::Ori {
+                rd: 6,
+                rs1: 5,
+                imm: 0x7F,
+            },
+            other => other,
+        })
+        .collect();
+    let mut compiler = Compiler::with_opt_level(OptLevel::O1);
+    // Link values then need both halves
+    compiler.set_base(0x12340000);
+    compiler.set_constants(ConstantStrategy::LiteralPool);
+    assert_eq!(compiler.constants(), ConstantStrategy::LiteralPool);
+    let mut serial = vec![0u8; Compiler::estimate_size(&program)];
+    let size = compiler.compile(&program, &mut serial);
+    compiler.set_threads(3);
+    let mut parallel = vec![0u8; serial.len()];
+    assert_eq!(compiler.compile(&program, &mut parallel), size);
+    assert_eq!(serial, parallel);
+    // Distinct values take as many words either way
+    compiler.set_constants(ConstantStrategy::MoveWide);
+    assert_eq!(compiler.compile(&program, &mut parallel), size);
+    assert_ne!(serial, parallel);
+}
+
+#[test]
+fn stats_describe_last_compile() {
+    // More registers than host registers, so some are spilled
+    let program: Vec<_> = (1..=12)
+        .map(|reg| Instruction::Add {
+            rd: reg,
+            rs1: reg,
+            rs2: 13 - reg,
+        })
+        .collect();
+    let mut compiler = Compiler::new();
+    assert_eq!(compiler.stats(), None);
+    let mut buffer = vec![0u8; Compiler::estimate_size(&program)];
+    let (size, map) = compiler.compile_mapped(&program, &mut buffer);
+    let stats = compiler.stats().unwrap().clone();
+    assert_eq!((stats.instructions, stats.words * 4), (program.len(), size));
+    assert_eq!(stats.expansion(), stats.words as f64 / 12.0);
+    assert_eq!(stats.veneers, 0);
+
+    // Every load or store through x20 in the guest code is a spill
+    let body = &buffer[map.native(0).unwrap()..map.code(44).unwrap().end];
+    let spills = body
+        .chunks_exact(4)
+        .map(|chunk| u32::from_le_bytes(chunk.try_into().unwrap()))
+        .filter(|word| word & 0xFF800000 == 0xB9000000 && word >> 5 & 0x1F == 20)
+        .count();
+    assert!(spills > 0);
+    assert_eq!(stats.spills, spills);
+
+    // Passes in the order they run at each level
+    let passes = |compiler: &Compiler| {
+        let stats = compiler.stats().unwrap();
+        assert_eq!(stats.time(), stats.passes.iter().map(|pass| pass.1).sum());
+        stats.passes.iter().map(|pass| pass.0).collect::<Vec<_>>()
+    };
+    assert_eq!(passes(&compiler), [Pass::Translate, Pass::Link]);
+    compiler.set_opt_level(OptLevel::O1);
+    compiler.compile(&program, &mut buffer);
+    assert_eq!(
+        passes(&compiler),
+        [Pass::Peephole, Pass::Translate, Pass::Link]
+    );
+    compiler.set_opt_level(OptLevel::O2);
+    compiler.compile(&program, &mut buffer);
+    assert_eq!(
+        passes(&compiler),
+        [Pass::Fold, Pass::Peephole, Pass::Translate, Pass::Link]
+    );
+
+    // Nothing written, nothing to report
+    assert_eq!(compiler.compile(&program, &mut buffer[..4]), 0);
+    assert_eq!(compiler.stats(), None);
+    assert_eq!(CompileStats::default().expansion(), 0.0);
+}
+
+#[test]
+fn base_rounds_down() {
+    let mut compiler = Compiler::new();
+    assert_eq!(compiler.base(), 0);
+    compiler.set_base(0x1003);
+    assert_eq!(compiler.base(), 0x1000);
+}
+
+#[test]
+fn source_map_uses_base() {
+    let instructions = [
+        Instruction::Addi {
+            rd: 5,
+            rs1: 0,
+            imm: 1,
+        },
+        Instruction::Add {
+            rd: 6,
+            rs1: 5,
+            rs2: 5,
+        },
+    ];
+    let mut buffer = vec![0u8; Compiler::code_size(instructions.len())];
+    let plain = Compiler::new().compile_mapped(&instructions, &mut buffer).1;
+    let mut compiler = Compiler::new();
+    compiler.set_base(0x8000);
+    let (size, map) = compiler.compile_mapped(&instructions, &mut buffer);
+    assert_eq!(map.base(), 0x8000);
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.code(0x8004), plain.code(4));
+    assert_eq!(map.code(4), None);
+    let offset = map.native(0x8004).unwrap();
+    assert_eq!(map.guest(offset), Some(0x8004));
+
+    let listing = Compiler::listing(&instructions, &buffer[..size], &map);
+    assert!(listing.contains("0x00008004:  add x6, x5, x5"));
+}
+
+#[test]
+fn code_refers_to_itself_pc_relative() {
+    let program = long_program(2000);
+    let code = Translator::translate(
+        &peephole::optimize(&program),
+        &Options {
+            optimize: true,
+            resumable: true,
+            count_blocks: true,
+            ..Options::default()
+        },
+    );
+    // Everything before the dispatch table is instructions
+    let table = code.code.len() - program.len() * 2;
+    let mut references = 0;
+    for (at, &word) in code.code[..table].iter().enumerate() {
+        assert!(!arm64::disassemble(word, at * 4).starts_with(".word"));
+        if let Some(delta) = arm64::pc_relative(word) {
+            let target = at as i64 * 4 + delta;
+            assert!((0..code.code.len() as i64 * 4).contains(&target));
+            references += 1;
+        }
+    }
+    assert!(references > program.len() / 2);
+
+    // Table entries are offsets from themselves to code before the table
+    for entry in code.code[table..].chunks_exact(2) {
+        assert!((entry[0] as i32) < 0);
+    }
+}
+
+#[test]
+fn listing_of_nothing_compiled() {
+    assert_eq!(Compiler::listing(&[], &[], &SourceMap::default()), "");
+}
+
+/// Host register holding guest register `reg` in code for `instructions`
+fn host(instructions: &[Instruction], reg: u8) -> u8 {
+    RegisterMap::allocate(instructions).host(reg).unwrap()
+}
+
+#[test]
+fn fused_load_immediate() {
+    let program = [
+        Instruction::Lui {
+            rd: 5,
+            imm: 0x12345,
+        },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 5,
+            imm: -1,
+        },
+    ];
+    let code = Translator::translate(
+        &program,
+        &Options {
+            optimize: true,
+            ..Options::default()
+        },
+    );
+    assert_eq!(
+        slot(&code, 0),
+        arm64::mov_imm(host(&program, 5), 0x12344FFF)
+    );
+    assert!(slot(&code, 1).is_empty());
+    assert!(code.code.len() < compile(&program).code.len());
+}
+
+#[test]
+fn fused_zero_extend() {
+    let program = [
+        Instruction::Slli {
+            rd: 5,
+            rs1: 6,
+            shamt: 20,
+        },
+        Instruction::Srli {
+            rd: 5,
+            rs1: 5,
+            shamt: 20,
+        },
+    ];
+    let code = Translator::translate(
+        &program,
+        &Options {
+            optimize: true,
+            ..Options::default()
+        },
+    );
+    let (rd, rs) = (host(&program, 5), host(&program, 6));
+    assert_eq!(slot(&code, 0), [arm64::ubfx(rd, rs, 0, 12)]);
+    assert!(slot(&code, 1).is_empty());
+}
+
+#[test]
+fn fused_compare_branch() {
+    let program = [
+        Instruction::Sltu {
+            rd: 5,
+            rs1: 6,
+            rs2: 7,
+        },
+        Instruction::Bne {
+            rs1: 5,
+            rs2: 0,
+            imm: -4,
+        },
+    ];
+    let code = Translator::translate(
+        &program,
+        &Options {
+            optimize: true,
+            ..Options::default()
+        },
+    );
+    let at = code.offsets[0] + charge(&code, 0).len() + 3;
+    let back = (code.offsets[0] as i32 - at as i32) * 4;
+    let (rd, rs1, rs2) = (host(&program, 5), host(&program, 6), host(&program, 7));
+    assert_eq!(
+        slot(&code, 0),
+        [
+            arm64::cmp(rs1, rs2),
+            arm64::cset(rd, Condition::Lo),
+            arm64::b_cond(Condition::Hs, 8),
+            arm64::b(back),
+        ]
+    );
+    assert!(slot(&code, 1).is_empty());
+}
+
+#[test]
+fn fused_far_jump_branches_directly() {
+    // call: auipc ra, 0 ; jalr ra, 12(ra) lands inside the block after it
+    let program = [
+        Instruction::Auipc { rd: 1, imm: 0 },
+        Instruction::Jalr {
+            rd: 1,
+            rs1: 1,
+            imm: 12,
+        },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 0,
+            imm: 1,
+        },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 5,
+            imm: 1,
+        },
+    ];
+    let code = Translator::translate(
+        &program,
+        &Options {
+            optimize: true,
+            ..Options::default()
+        },
+    );
+    let words = slot(&code, 0);
+    let ra = host(&program, 1);
+    let last = code.offsets[1] - 1;
+    assert_eq!(words[0], arm64::movz(ra, 8, 0));
+    // Charges the one instruction left in the target's block, then branches
+    assert_eq!(words[1], arm64::ldr_x(16, 19, 48));
+    assert_eq!(words[2], arm64::subs_imm_x(16, 16, 1));
+    assert_eq!(
+        code.code[last],
+        arm64::b((code.offsets[3] as i32 - last as i32) * 4)
+    );
+    assert!(slot(&code, 1).is_empty());
+    // Without a JALR left, there is no dispatch routine
+    assert!(!code.code.contains(&arm64::br(16)));
+}
+
+#[test]
+fn fusion_skips_block_leaders() {
+    // The ADDI is a branch target, so it keeps its own code
+    let program = [
+        Instruction::Lui { rd: 5, imm: 1 },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 5,
+            imm: 1,
+        },
+        Instruction::Beq {
+            rs1: 5,
+            rs2: 6,
+            imm: -4,
+        },
+    ];
+    let code = Translator::translate(
+        &program,
+        &Options {
+            optimize: true,
+            ..Options::default()
+        },
+    );
+    assert_eq!(slot(&code, 0), arm64::mov_imm(host(&program, 5), 0x1000));
+    assert!(!slot(&code, 1).is_empty());
+}
+
+#[test]
+fn fusion_skips_far_jump_targets() {
+    // The far jump lands on the ADDI, which must keep its own code
+    let program = [
+        Instruction::Auipc { rd: 6, imm: 0 },
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 6,
+            imm: 12,
+        },
+        Instruction::Lui { rd: 5, imm: 1 },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 5,
+            imm: 1,
+        },
+    ];
+    let code = Translator::translate(
+        &program,
+        &Options {
+            optimize: true,
+            ..Options::default()
+        },
+    );
+    assert!(slot(&code, 1).is_empty());
+    assert!(!slot(&code, 3).is_empty());
+}
+
+#[test]
+fn dispatch_enters_fused_pairs_out_of_line() {
+    let program = [
+        Instruction::Lui { rd: 5, imm: 1 },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 5,
+            imm: 1,
+        },
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 5,
+            imm: 0,
+        },
+    ];
+    let code = Translator::translate(
+        &program,
+        &Options {
+            optimize: true,
+            ..Options::default()
+        },
+    );
+    assert!(slot(&code, 1).is_empty());
+
+    // The table entry for the ADDI leads to its own code, then to the JALR
+    let entry = code.code.len() - 2 * program.len() + 2;
+    let stub = (entry as i32 + code.code[entry] as i32 / 4) as usize;
+    let rd = host(&program, 5);
+    assert_eq!(code.code[stub], arm64::add_imm(rd, rd, 1));
+    let next = code.offsets[2] as i32 - (stub + 1) as i32;
+    assert_eq!(code.code[stub + 1], arm64::b(next * 4));
+}
+
+#[test]
+fn resumable_entry_follows_exit_stub() {
+    let instructions = [
+        Instruction::Addi {
+            rd: 5,
+            rs1: 0,
+            imm: 1,
+        },
+        Instruction::Add {
+            rd: 6,
+            rs1: 5,
+            rs2: 5,
+        },
+    ];
+    let plain = Translator::translate(&instructions, &Options::default());
+    let resumable = Translator::translate(
+        &instructions,
+        &Options {
+            resumable: true,
+            ..Options::default()
+        },
+    );
+    assert_eq!(plain.resume, None);
+    let resume = resumable.resume.unwrap();
+
+    // Same code up to the exit stub, then a second prologue
+    assert_eq!(resumable.code[..resume], plain.code[..]);
+    assert_eq!(resumable.offsets, plain.offsets);
+    assert_eq!(
+        resumable.code[resume..resume + PROLOGUE_WORDS],
+        plain.code[..PROLOGUE_WORDS]
+    );
+    assert!(resumable.code.len() * 4 <= Compiler::code_size(instructions.len()));
+
+    let mut buffer = vec![0u8; Compiler::code_size(instructions.len())];
+    let mut compiler = Compiler::new();
+    compiler.set_resumable(true);
+    let (size, map) = compiler.compile_mapped(&instructions, &mut buffer);
+    assert_eq!(size, resumable.code.len() * 4);
+    assert_eq!(map.resume(), Some(resume * 4));
+    assert_eq!(map.guest(resume * 4), None);
+}
+
+#[test]
+fn function_entries_dispatch_on_their_pc() {
+    let instructions = [
+        Instruction::Addi {
+            rd: 5,
+            rs1: 0,
+            imm: 1,
+        },
+        Instruction::Add {
+            rd: 6,
+            rs1: 5,
+            rs2: 5,
+        },
+        Instruction::Addi {
+            rd: 7,
+            rs1: 6,
+            imm: 2,
+        },
+    ];
+    let functions = [0x1008, 0x1000, 0x1008];
+    let code = Translator::translate(
+        &instructions,
+        &Options {
+            base: 0x1000,
+            functions: &functions,
+            ..Options::default()
+        },
+    );
+
+    // Functions sharing a PC share an entry, which moves the PC into w9 and
+    // branches to the call entry, a prologue and a branch to the dispatch
+    // routine
+    assert_eq!(code.functions.len(), 3);
+    assert_eq!(code.functions[0], code.functions[2]);
+    let call = code.functions[1] + 2;
+    for (&entry, pc) in code.functions.iter().zip(functions) {
+        assert_eq!(code.code[entry], arm64::mov_imm(9, pc)[0]);
+        assert_eq!(
+            code.code[entry + 1],
+            arm64::b((call - entry - 1) as i32 * 4)
+        );
+    }
+    assert_eq!(
+        code.code[call..call + PROLOGUE_WORDS],
+        code.code[..PROLOGUE_WORDS]
+    );
+    let dispatch = call + PROLOGUE_WORDS;
+    assert_eq!(code.code[dispatch], arm64::b(4));
+
+    let mut compiler = Compiler::new();
+    compiler.set_base(0x1000);
+    compiler.set_functions(&functions);
+    assert_eq!(compiler.functions(), functions);
+    let mut buffer = vec![0u8; Compiler::estimate_size_with(&instructions, 3)];
+    let (size, map) = compiler.compile_mapped(&instructions, &mut buffer);
+    assert_eq!(size, code.code.len() * 4);
+    assert_eq!(map.functions(), 3);
+    assert_eq!(map.function(1), Some(code.functions[1] * 4));
+    assert_eq!(map.function(3), None);
+    assert_eq!(map.guest(code.functions[1] * 4), None);
+}
+
+#[test]
+fn functions_must_be_instructions() {
+    let instructions = [Instruction::Ecall, Instruction::Ebreak];
+    assert_eq!(
+        Compiler::check_functions(&instructions, 0x100, &[0x104, 0x100]),
+        Ok(())
+    );
+    for (functions, index, address) in [
+        (&[0x100, 0x108][..], 1, 0x108),
+        (&[0x102][..], 0, 0x102),
+        (&[0xFC][..], 0, 0xFC),
+    ] {
+        assert_eq!(
+            Compiler::check_functions(&instructions, 0x100, functions),
+            Err(CompileError::InvalidFunction { index, address })
+        );
+        let mut compiler = Compiler::new();
+        compiler.set_base(0x100);
+        compiler.set_functions(functions);
+        let mut buffer = vec![0u8; Compiler::code_size(instructions.len())];
+        assert_eq!(compiler.compile(&instructions, &mut buffer), 0);
+    }
+
+    // Without a table the code is one function at its first instruction
+    let mut buffer = vec![0u8; Compiler::code_size(instructions.len())];
+    let (_, map) = Compiler::new().compile_mapped(&instructions, &mut buffer);
+    assert_eq!(map.functions(), 0);
+    assert_eq!(map.function(0), Some(0));
+    assert_eq!(map.function(1), None);
+}
+
+/// A long program with branches, calls and fusible pairs crossing regions
+fn long_program(count: usize) -> Vec<Instruction> {
+    (0..count)
+        .map(|index| {
+            let back = -4 * (index.min(64) as i32);
+            match index % 8 {
+                0 => Instruction::Lui {
+                    rd: 5,
+                    imm: index as u32,
+                },
+                1 => Instruction::Addi {
+                    rd: 5,
+                    rs1: 5,
+                    imm: 1,
+                },
+                2 => Instruction::Beq {
+                    rs1: 5,
+                    rs2: 6,
+                    imm: back,
+                },
+                3 if index % 1000 == 3 => Instruction::Jalr {
+                    rd: 1,
+                    rs1: 5,
+                    imm: 0,
+                },
+                3 => Instruction::Jal {
+                    rd: 1,
+                    imm: if index < count / 2 { 0x8000 } else { -0x8000 },
+                },
+                4 => Instruction::Sw {
+                    rs1: 2,
+                    rs2: 5,
+                    imm: 4,
+                },
+                5 => Instruction::Lw {
+                    rd: 7,
+                    rs1: 2,
+                    imm: 4,
+                },
+                6 => Instruction::Slli {
+                    rd: 6,
+                    rs1: 7,
+                    shamt: 3,
+                },
+                _ => Instruction::Add {
+                    rd: 6,
+                    rs1: 6,
+                    rs2: 5,
+                },
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn regions_start_at_blocks() {
+    let count = 3 * MIN_REGION + 100;
+    let mut leaders = vec![false; count];
+    for index in (0..count).step_by(7) {
+        leaders[index] = true;
+    }
+    let ranges = translator::regions(&leaders, 8);
+    assert_eq!(ranges.len(), 3);
+    assert_eq!(ranges[0].start, 0);
+    assert_eq!(ranges.last().unwrap().end, count);
+    for pair in ranges.windows(2) {
+        assert_eq!(pair[0].end, pair[1].start);
+    }
+    for range in &ranges {
+        assert!(leaders[range.start]);
+        assert!(range.len() >= MIN_REGION);
+    }
+
+    // Too few instructions, or threads, for more than one region, or no
+    // leader far enough from the end to start another
+    let mut sparse = vec![false; 2 * MIN_REGION];
+    sparse[0] = true;
+    sparse[MIN_REGION + 1] = true;
+    for (leaders, threads) in [
+        (&leaders[..], 1),
+        (&leaders[..MIN_REGION], 8),
+        (&[][..], 8),
+        (&sparse[..], 2),
+    ] {
+        let ranges = translator::regions(leaders, threads);
+        assert_eq!(ranges.len(), 1);
+        assert_eq!(ranges[0], 0..leaders.len());
+    }
+}
+
+#[test]
+fn parallel_compile_matches_serial() {
+    let program = long_program(3 * MIN_REGION + 13);
+    for opt_level in [OptLevel::O0, OptLevel::O1, OptLevel::O2] {
+        for resumable in [false, true] {
+            let compiled = |threads| {
+                let mut compiler = Compiler::with_opt_level(opt_level);
+                compiler.set_resumable(resumable);
+                compiler.set_threads(threads);
+                let mut buffer = vec![0u8; Compiler::code_size(program.len())];
+                let (size, map) = compiler.compile_mapped(&program, &mut buffer);
+                buffer.truncate(size);
+                (buffer, map)
+            };
+            let serial = compiled(1);
+            assert!(!serial.0.is_empty());
+            assert_eq!(compiled(4), serial);
+            assert_eq!(compiled(64), serial);
+        }
+    }
+}
+
+#[test]
+fn thread_count_at_least_one() {
+    let mut compiler = Compiler::new();
+    assert_eq!(compiler.threads(), 1);
+    compiler.set_threads(0);
+    assert_eq!(compiler.threads(), 1);
+    compiler.set_threads(6);
+    assert_eq!(compiler.threads(), 6);
 }
@@ -0,0 +1,85 @@
+use crate::{CostModel, Instruction, InstructionKind};
+
+#[test]
+fn default_table() {
+    let model = CostModel::default();
+    assert_eq!(
+        model.cost(&Instruction::Add {
+            rd: 1,
+            rs1: 2,
+            rs2: 3
+        }),
+        1
+    );
+    assert_eq!(
+        model.cost(&Instruction::Lw {
+            rd: 1,
+            rs1: 2,
+            imm: 0
+        }),
+        3
+    );
+    assert_eq!(
+        model.cost(&Instruction::Mul {
+            rd: 1,
+            rs1: 2,
+            rs2: 3
+        }),
+        3
+    );
+    assert_eq!(
+        model.cost(&Instruction::Remu {
+            rd: 1,
+            rs1: 2,
+            rs2: 3
+        }),
+        20
+    );
+    assert_eq!(model.cost(&Instruction::Ecall), 1);
+}
+
+#[test]
+fn uniform() {
+    let model = CostModel::uniform(5);
+    for kind in InstructionKind::ALL {
+        assert_eq!(model.get(kind), 5);
+    }
+}
+
+#[test]
+fn override_kind() {
+    let model = CostModel::uniform(1).with(InstructionKind::Store, 10);
+    assert_eq!(
+        model.cost(&Instruction::Sw {
+            rs1: 2,
+            rs2: 1,
+            imm: 0
+        }),
+        10
+    );
+    assert_eq!(model.get(InstructionKind::Load), 1);
+}
+
+#[test]
+fn total() {
+    let model = CostModel::default();
+    let code = [
+        Instruction::Lw {
+            rd: 1,
+            rs1: 2,
+            imm: 0,
+        },
+        Instruction::Div {
+            rd: 1,
+            rs1: 1,
+            rs2: 3,
+        },
+        Instruction::Sw {
+            rs1: 2,
+            rs2: 1,
+            imm: 0,
+        },
+    ];
+    assert_eq!(model.total(&code), 24);
+    assert_eq!(model.total(&[]), 0);
+}
@@ -0,0 +1,41 @@
+use crate::csr;
+
+#[test]
+fn names() {
+    assert_eq!(csr::name(csr::CYCLE), Some("cycle"));
+    assert_eq!(csr::name(csr::TIME), Some("time"));
+    assert_eq!(csr::name(csr::INSTRETH), Some("instreth"));
+    assert_eq!(csr::name(0x300), Some("mstatus"));
+    assert_eq!(csr::name(0x001), Some("fflags"));
+    assert_eq!(csr::name(0xF14), Some("mhartid"));
+}
+
+#[test]
+fn unknown_name() {
+    assert_eq!(csr::name(0x7FF), None);
+    assert_eq!(csr::address("bogus"), None);
+    assert_eq!(csr::address("CYCLE"), None);
+}
+
+#[test]
+fn addresses() {
+    assert_eq!(csr::address("cycle"), Some(0xC00));
+    assert_eq!(csr::address("satp"), Some(0x180));
+    assert_eq!(csr::address("vlenb"), Some(0xC22));
+}
+
+#[test]
+fn format() {
+    assert_eq!(csr::format(0xC02), "instret");
+    assert_eq!(csr::format(0x7C0), "0x7c0");
+}
+
+#[test]
+fn sorted_and_unique() {
+    let entries: Vec<_> = csr::all().collect();
+    assert!(entries.windows(2).all(|pair| pair[0].0 < pair[1].0));
+    for (address, name) in entries {
+        assert_eq!(csr::address(name), Some(address));
+        assert!(address <= 0xFFF);
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{Memory, MemoryCursor, MemoryError, PageStore};
+use std::io::{Read, Seek, SeekFrom, Write};
+
+#[test]
+fn write_then_read() {
+    let store = PageStore::new(16);
+    let mut memory = Memory::new(&store, 16, 4);
+    let mut cursor = MemoryCursor::new(&mut memory, 0x3ffe, 16);
+    cursor.write_all(b"across pages").unwrap();
+    assert_eq!(cursor.position(), 12);
+    assert_eq!(cursor.guest_address(), 0x400a);
+
+    cursor.rewind().unwrap();
+    let mut text = String::new();
+    cursor.read_to_string(&mut text).unwrap();
+    assert_eq!(text, "across pages\0\0\0\0");
+    assert_eq!(memory.read_u8(0x3ffe), b'a');
+    assert_eq!(memory.num_pages, 2);
+}
+
+#[test]
+fn window_limits() {
+    let store = PageStore::new(16);
+    let mut memory = Memory::new(&store, 16, 4);
+    let mut cursor = MemoryCursor::new(&mut memory, 0x100, 4);
+    assert_eq!(cursor.len(), 4);
+    assert!(!cursor.is_empty());
+    assert_eq!(cursor.write(b"abcdef").unwrap(), 4);
+    assert_eq!(cursor.write(b"gh").unwrap(), 0);
+    assert!(cursor.write_all(b"gh").is_err());
+
+    let mut buffer = [0u8; 8];
+    cursor.set_position(2);
+    assert_eq!(cursor.read(&mut buffer).unwrap(), 2);
+    assert_eq!(&buffer[..2], b"cd");
+    assert_eq!(cursor.read(&mut buffer).unwrap(), 0);
+    assert_eq!(memory.read_u8(0x104), 0);
+}
+
+#[test]
+fn seek() {
+    let store = PageStore::new(16);
+    let mut memory = Memory::new(&store, 16, 4);
+    let mut cursor = MemoryCursor::new(&mut memory, 0x100, 10);
+    assert_eq!(cursor.seek(SeekFrom::End(-3)).unwrap(), 7);
+    assert_eq!(cursor.seek(SeekFrom::Current(-2)).unwrap(), 5);
+    assert_eq!(cursor.seek(SeekFrom::Start(20)).unwrap(), 20);
+    assert!(cursor.seek(SeekFrom::Current(-21)).is_err());
+    assert_eq!(cursor.position(), 20);
+    assert_eq!(cursor.read(&mut [0u8; 4]).unwrap(), 0);
+}
+
+#[test]
+fn wraps_around() {
+    let store = PageStore::new(16);
+    let mut memory = Memory::new(&store, 16, 4);
+    let mut cursor = MemoryCursor::new(&mut memory, 0xFFFF_FFFE, 4);
+    cursor.write_all(&[1, 2, 3, 4]).unwrap();
+    assert_eq!(memory.read_array::<2>(0), [3, 4]);
+}
+
+#[test]
+fn memory_error() {
+    let store = PageStore::new(16);
+    let mut memory = Memory::new(&store, 0, 4);
+    let mut cursor = MemoryCursor::new(&mut memory, 0, 4);
+    let error = cursor.write(&[1]).unwrap_err();
+    assert_eq!(
+        error.get_ref().unwrap().downcast_ref::<MemoryError>(),
+        Some(&MemoryError::PageLimit)
+    );
+    assert_eq!(cursor.position(), 0);
+}
@@ -0,0 +1,261 @@
+use crate::{
+    Decoder, Extension, Extensions, Instance, Instruction, Interpreter, Memory, Module, PageStore,
+    Trap,
+};
+
+/// Encoding of mul x1, x2, x3
+const MUL: u32 = 0x023100B3;
+
+#[test]
+fn default_matches_decode() {
+    let decoder = Decoder::default();
+    assert_eq!(decoder.decode(MUL), Instruction::decode(MUL));
+    assert_eq!(decoder.extensions(), &Extensions::default());
+}
+
+#[test]
+fn m_disabled() {
+    let decoder = Decoder::new(Extensions::base());
+    assert_eq!(decoder.decode(MUL), Instruction::Unsupported(MUL));
+    assert_eq!(
+        decoder.decode(0x003100B3),
+        Instruction::Add {
+            rd: 1,
+            rs1: 2,
+            rs2: 3
+        }
+    );
+    assert!(!decoder.accepts(&Instruction::Remu {
+        rd: 1,
+        rs1: 2,
+        rs2: 3
+    }));
+}
+
+#[test]
+fn extension() {
+    assert_eq!(
+        Instruction::Div {
+            rd: 1,
+            rs1: 2,
+            rs2: 3
+        }
+        .extension(),
+        Extension::M
+    );
+    assert_eq!(Instruction::Ecall.extension(), Extension::I);
+    assert_eq!(Instruction::Unsupported(0).extension(), Extension::I);
+}
+
+#[test]
+fn enabled() {
+    let extensions = Extensions::base();
+    assert!(extensions.enabled(Extension::I));
+    assert!(!extensions.enabled(Extension::M));
+    assert!(Extensions::all().enabled(Extension::C));
+}
+
+#[test]
+fn alignment() {
+    assert_eq!(Extensions::default().alignment(), 4);
+    assert_eq!(Extensions::all().alignment(), 2);
+}
+
+#[test]
+fn interpreter_rejects_disabled() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    instance.memory_mut().write(0, &MUL.to_le_bytes()).unwrap();
+    let mut interpreter = Interpreter::with_decoder(Decoder::new(Extensions::base()));
+    assert_eq!(interpreter.step(&mut instance), Err(Trap::Illegal(MUL)));
+}
+
+#[test]
+fn interpreter_compressed_alignment() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    instance
+        .memory_mut()
+        .write(2, &0x00000013u32.to_le_bytes())
+        .unwrap();
+    instance.set_pc(2);
+    let mut interpreter = Interpreter::with_decoder(Decoder::new(Extensions::all()));
+    assert_eq!(interpreter.step(&mut instance), Ok(()));
+    assert_eq!(instance.pc(), 6);
+}
+
+#[test]
+fn module_decoder() {
+    let mut module = Module::new(1024).unwrap();
+    module.set_decoder(Decoder::new(Extensions::base()));
+    assert_eq!(module.decoder().extensions(), &Extensions::base());
+    assert!(module.set_code(&MUL.to_le_bytes()).is_ok());
+}
+
+#[test]
+fn v_disabled_by_default() {
+    // vadd.vv v1, v2, v3
+    let word = 0x022180D7;
+    assert_eq!(
+        Decoder::default().decode(word),
+        Instruction::Unsupported(word)
+    );
+    let decoder = Decoder::new(Extensions {
+        v: true,
+        ..Extensions::default()
+    });
+    assert_eq!(
+        decoder.decode(word),
+        Instruction::VaddVv {
+            vd: 1,
+            vs2: 2,
+            vs1: 3,
+            vm: true
+        }
+    );
+    assert_eq!(Instruction::decode(word).extension(), Extension::V);
+}
+
+#[test]
+fn interpreter_vector_illegal() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    instance
+        .memory_mut()
+        .write(0, &0x022180D7u32.to_le_bytes())
+        .unwrap();
+    let mut interpreter = Interpreter::with_decoder(Decoder::new(Extensions::all()));
+    assert_eq!(
+        interpreter.step(&mut instance),
+        Err(Trap::Illegal(0x022180D7))
+    );
+}
+
+#[test]
+fn zfh_disabled_by_default() {
+    // fadd.h f1, f2, f3
+    let word = 0x043170D3;
+    assert_eq!(
+        Decoder::default().decode(word),
+        Instruction::Unsupported(word)
+    );
+    let decoder = Decoder::new(Extensions {
+        zfh: true,
+        ..Extensions::default()
+    });
+    assert_eq!(
+        decoder.decode(word),
+        Instruction::FaddH {
+            rd: 1,
+            rs1: 2,
+            rs2: 3,
+            rm: 7
+        }
+    );
+    assert_eq!(Instruction::decode(word).extension(), Extension::Zfh);
+}
+
+#[test]
+fn size() {
+    let decoder = Decoder::default();
+    assert_eq!(decoder.size(&Instruction::decode(MUL)), 4);
+    assert_eq!(decoder.size(&Instruction::Unsupported(0xFFFFFFFF)), 4);
+    // A zero word is a whole unsupported word without C
+    assert_eq!(decoder.size(&decoder.decode(0)), 4);
+    assert_eq!(decoder.size(&Instruction::Unsupported(0x0073_0001)), 4);
+    let compressed = Decoder::new(Extensions {
+        c: true,
+        ..Extensions::default()
+    });
+    assert_eq!(compressed.size(&Instruction::Unsupported(0x0001)), 2);
+    assert_eq!(compressed.size(&Instruction::Unsupported(0xFFFFFFFF)), 4);
+    assert_eq!(Instruction::length(0x4501), 2);
+    assert_eq!(Instruction::length(0x00000073), 4);
+}
+
+#[test]
+fn stream() {
+    // mul x1, x2, x3 ; c.li a0, 0 ; ecall
+    let mut code = MUL.to_le_bytes().to_vec();
+    code.extend_from_slice(&[0x01, 0x45]);
+    code.extend_from_slice(&0x00000073u32.to_le_bytes());
+    let decoded: Vec<(u32, Instruction)> = Decoder::new(Extensions::all()).stream(&code).collect();
+    assert_eq!(
+        decoded,
+        vec![
+            (
+                0,
+                Instruction::Mul {
+                    rd: 1,
+                    rs1: 2,
+                    rs2: 3
+                }
+            ),
+            (4, Instruction::Unsupported(0x4501)),
+            (6, Instruction::Ecall),
+        ]
+    );
+}
+
+#[test]
+fn stream_without_compressed() {
+    // Without C, a word whose low bits look compressed is still a word
+    let mut code = 0x0000_4501u32.to_le_bytes().to_vec();
+    code.extend_from_slice(&MUL.to_le_bytes());
+    let decoded: Vec<(u32, Instruction)> = Decoder::default().stream(&code).collect();
+    assert_eq!(
+        decoded,
+        vec![
+            (0, Instruction::Unsupported(0x4501)),
+            (
+                4,
+                Instruction::Mul {
+                    rd: 1,
+                    rs1: 2,
+                    rs2: 3
+                }
+            ),
+        ]
+    );
+    let instructions: Vec<_> = decoded
+        .into_iter()
+        .map(|(_, instruction)| instruction)
+        .collect();
+    assert_eq!(Decoder::default().decode_words(&code), instructions);
+}
+
+#[test]
+fn width() {
+    assert_eq!(Decoder::default().width(0x4501), 4);
+    assert_eq!(Decoder::new(Extensions::all()).width(0x4501), 2);
+    assert_eq!(Decoder::new(Extensions::all()).width(0x0073), 4);
+}
+
+#[test]
+fn decode_words() {
+    // Parcels are not split even with C; trailing bytes are ignored
+    let mut code = 0x0073_4501u32.to_le_bytes().to_vec();
+    code.extend_from_slice(&[0x73, 0x00]);
+    assert_eq!(
+        Decoder::new(Extensions::all()).decode_words(&code),
+        [Instruction::Unsupported(0x0073_4501)]
+    );
+}
+
+#[test]
+fn stream_filters_extensions() {
+    let code = MUL.to_le_bytes();
+    let decoded: Vec<(u32, Instruction)> = Decoder::new(Extensions::base()).stream(&code).collect();
+    assert_eq!(decoded, vec![(0, Instruction::Unsupported(MUL))]);
+}
+
+#[test]
+fn stream_truncated() {
+    // A full-width instruction cut short and a lone trailing byte are dropped
+    let decoder = Decoder::new(Extensions::all());
+    assert_eq!(decoder.stream(&[0x73, 0x00, 0x00]).count(), 0);
+    assert_eq!(decoder.stream(&[0x01, 0x00, 0x73]).count(), 1);
+    assert_eq!(decoder.stream(&[]).count(), 0);
+    // Without C, the parcel is the start of an incomplete word
+    assert_eq!(Decoder::default().stream(&[0x01, 0x00, 0x73]).count(), 0);
+}
@@ -0,0 +1,203 @@
+use crate::memory::PAGE_SIZE;
+use crate::{DiffRange, Memory, PageStore, Permissions};
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+
+#[test]
+fn identical() {
+    let store = PageStore::new(16);
+    let mut left = Memory::new(&store, 8, 4);
+    let mut right = Memory::new(&store, 8, 4);
+    left.write(0x4000, b"same").unwrap();
+    right.write(0x4000, b"same").unwrap();
+    let diff = left.diff(&right);
+    assert!(diff.is_empty());
+    assert_eq!(diff.bytes(), 0);
+}
+
+#[test]
+fn byte_ranges() {
+    let store = PageStore::new(16);
+    let mut left = Memory::new(&store, 8, 4);
+    let mut right = Memory::new(&store, 8, 4);
+    left.write(0x4000, &[1, 2, 3, 4, 5, 6]).unwrap();
+    right.write(0x4000, &[1, 0, 0, 4, 5, 0]).unwrap();
+    let diff = left.diff(&right);
+    assert_eq!(diff.pages, [0x4000]);
+    assert_eq!(
+        diff.ranges,
+        [
+            DiffRange {
+                address: 0x4001,
+                len: 2
+            },
+            DiffRange {
+                address: 0x4005,
+                len: 1
+            },
+        ]
+    );
+    assert_eq!(diff.bytes(), 3);
+    assert_eq!(diff.ranges[0].to_string(), "0x00004001-0x00004002");
+}
+
+#[test]
+fn range_across_pages() {
+    let store = PageStore::new(16);
+    let mut left = Memory::new(&store, 8, 4);
+    let right = Memory::new(&store, 8, 4);
+    left.write(0x3ffe, &[0xff; 4]).unwrap();
+    let diff = left.diff(&right);
+    assert_eq!(diff.pages, [0, 0x4000]);
+    assert_eq!(
+        diff.ranges,
+        [DiffRange {
+            address: 0x3ffe,
+            len: 4
+        }]
+    );
+}
+
+#[test]
+fn unallocated_matches_zeros() {
+    let store = PageStore::new(16);
+    let mut left = Memory::new(&store, 8, 4);
+    let right = Memory::new(&store, 8, 4);
+    left.allocate_page(0x8000).unwrap();
+    assert!(left.diff(&right).is_empty());
+    assert!(right.diff(&left).is_empty());
+}
+
+#[test]
+fn permissions() {
+    let store = PageStore::new(16);
+    let mut left = Memory::new(&store, 8, 4);
+    let mut right = Memory::new(&store, 8, 4);
+    left.allocate_page(0x4000).unwrap();
+    right.allocate_page(0x4000).unwrap();
+    left.protect(0x4000, PAGE_SIZE as u32, Permissions::READ_ONLY)
+        .unwrap();
+    let diff = left.diff(&right);
+    assert_eq!(diff.pages, [0x4000]);
+    assert!(diff.ranges.is_empty());
+}
+
+#[test]
+fn snapshot() {
+    let store = PageStore::new(16);
+    let mut memory = Memory::new(&store, 8, 4);
+    memory.write(0x4000, b"before").unwrap();
+    let snapshot = memory.snapshot();
+    assert_eq!(snapshot.pages(), 1);
+    assert!(memory.diff_snapshot(&snapshot).is_empty());
+
+    memory.write(0x4000, b"after!").unwrap();
+    memory.write(0xc000, &[7]).unwrap();
+    let diff = memory.diff_snapshot(&snapshot);
+    assert_eq!(diff.pages, [0x4000, 0xc000]);
+    assert_eq!(diff.bytes(), 6);
+
+    // The snapshot is unaffected by a reset
+    memory.reset();
+    assert_eq!(memory.diff_snapshot(&snapshot).bytes(), 6);
+    assert_eq!(memory.snapshot().diff(&snapshot).pages, [0x4000]);
+}
+
+#[test]
+fn shared_and_host_pages() {
+    let store = PageStore::new(16);
+    let shared = store.share(b"image").unwrap();
+    let mut left = Memory::new(&store, 8, 4);
+    let mut right = Memory::new(&store, 8, 4);
+    left.map_shared(0, &shared).unwrap();
+    right.write(0, b"image").unwrap();
+    right
+        .protect(0, PAGE_SIZE as u32, Permissions::READ_EXECUTE)
+        .unwrap();
+    assert!(left.diff(&right).is_empty());
+
+    let mut buffer = vec![0u8; PAGE_SIZE];
+    buffer[10] = 1;
+    unsafe { left.map_host(0x8000, &mut buffer).unwrap() };
+    let diff = left.diff(&right);
+    assert_eq!(diff.pages, [0x8000]);
+    assert_eq!(
+        diff.ranges,
+        [DiffRange {
+            address: 0x800a,
+            len: 1
+        }]
+    );
+}
+
+#[test]
+fn state_root_ignores_allocation() {
+    let store = PageStore::new(16);
+    let mut left = Memory::new(&store, 8, 4);
+    let mut right = Memory::new(&store, 8, 4);
+    assert_eq!(
+        left.state_root::<DefaultHasher>(),
+        right.state_root::<DefaultHasher>()
+    );
+    left.write(0x4000, b"state").unwrap();
+    right
+        .write(0x3ffc, &[0, 0, 0, 0, b's', b't', b'a', b't', b'e', 0])
+        .unwrap();
+    assert_eq!(right.num_pages, 2);
+    assert_eq!(
+        left.state_root::<DefaultHasher>(),
+        right.state_root::<DefaultHasher>()
+    );
+
+    right.write(0x4002, b"x").unwrap();
+    assert_ne!(
+        left.state_root::<DefaultHasher>(),
+        right.state_root::<DefaultHasher>()
+    );
+}
+
+#[test]
+fn state_root_depends_on_address() {
+    let store = PageStore::new(16);
+    let mut left = Memory::new(&store, 8, 4);
+    let mut right = Memory::new(&store, 8, 4);
+    left.write(0x4000, b"state").unwrap();
+    right.write(0x5000, b"state").unwrap();
+    assert_ne!(
+        left.state_root::<DefaultHasher>(),
+        right.state_root::<DefaultHasher>()
+    );
+}
+
+#[test]
+fn hash_range_limits() {
+    let store = PageStore::new(16);
+    let mut left = Memory::new(&store, 8, 4);
+    let mut right = Memory::new(&store, 8, 4);
+    left.write(0x4000, b"same").unwrap();
+    right.write(0x4000, b"same").unwrap();
+    left.write(0x6000, b"left").unwrap();
+    right.write(0x6000, b"right").unwrap();
+    let hash = |memory: &Memory, range| {
+        let mut hasher = DefaultHasher::new();
+        memory.hash_range(range, &mut hasher);
+        hasher.finish()
+    };
+    assert_eq!(hash(&left, 0x4000..0x6000), hash(&right, 0x4000..0x6000));
+    assert_eq!(hash(&left, 0..0x4002), hash(&right, 0..0x4002));
+    assert_ne!(hash(&left, 0x4000..0x6001), hash(&right, 0x4000..0x6001));
+    assert_ne!(hash(&left, 0..u64::MAX), hash(&right, 0..u64::MAX));
+}
+
+#[test]
+fn snapshot_state_root() {
+    let store = PageStore::new(16);
+    let mut memory = Memory::new(&store, 8, 4);
+    memory.write(0x4000, b"before").unwrap();
+    let snapshot = memory.snapshot();
+    let root = memory.state_root::<DefaultHasher>();
+    assert_eq!(snapshot.state_root::<DefaultHasher>(), root);
+    memory.write(0x4000, b"after").unwrap();
+    assert_ne!(memory.state_root::<DefaultHasher>(), root);
+    assert_eq!(snapshot.state_root::<DefaultHasher>(), root);
+}
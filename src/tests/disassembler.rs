@@ -0,0 +1,219 @@
+use crate::{Decoder, Disassembler, Extensions, Instruction};
+
+fn assemble(instructions: &[Instruction]) -> Vec<u8> {
+    instructions
+        .iter()
+        .flat_map(|instr| instr.encode().unwrap().to_le_bytes())
+        .collect()
+}
+
+#[test]
+fn branch_target_absolute() {
+    let disassembler = Disassembler::new(0x1000);
+    let instr = Instruction::Beq {
+        rs1: 1,
+        rs2: 2,
+        imm: 8,
+    };
+    assert_eq!(disassembler.format(&instr, 0x1000), "beq x1, x2, 0x1008");
+}
+
+#[test]
+fn all_branches() {
+    let disassembler = Disassembler::new(0);
+    let cases = [
+        (
+            Instruction::Bne {
+                rs1: 3,
+                rs2: 4,
+                imm: -8,
+            },
+            "bne x3, x4, 0x18",
+        ),
+        (
+            Instruction::Blt {
+                rs1: 3,
+                rs2: 4,
+                imm: 4,
+            },
+            "blt x3, x4, 0x24",
+        ),
+        (
+            Instruction::Bge {
+                rs1: 3,
+                rs2: 4,
+                imm: 0,
+            },
+            "bge x3, x4, 0x20",
+        ),
+        (
+            Instruction::Bltu {
+                rs1: 3,
+                rs2: 4,
+                imm: 16,
+            },
+            "bltu x3, x4, 0x30",
+        ),
+        (
+            Instruction::Bgeu {
+                rs1: 3,
+                rs2: 4,
+                imm: -32,
+            },
+            "bgeu x3, x4, 0x0",
+        ),
+    ];
+    for (instr, expected) in cases {
+        assert_eq!(disassembler.format(&instr, 0x20), expected);
+    }
+}
+
+#[test]
+fn jal_target_absolute() {
+    let disassembler = Disassembler::new(0);
+    let instr = Instruction::Jal { rd: 1, imm: -16 };
+    assert_eq!(disassembler.format(&instr, 0x2010), "jal x1, 0x2000");
+}
+
+#[test]
+fn target_wraps_address_space() {
+    let disassembler = Disassembler::new(0);
+    let instr = Instruction::Jal { rd: 0, imm: -4 };
+    assert_eq!(disassembler.format(&instr, 0), "jal x0, 0xfffffffc");
+}
+
+#[test]
+fn labelled_target() {
+    let mut disassembler = Disassembler::new(0);
+    disassembler.label(0x40, "exit");
+    let instr = Instruction::Jal { rd: 0, imm: 0x20 };
+    assert_eq!(disassembler.format(&instr, 0x20), "jal x0, <exit>");
+}
+
+#[test]
+fn non_branch_unchanged() {
+    let disassembler = Disassembler::new(0x1000);
+    let instr = Instruction::Jalr {
+        rd: 0,
+        rs1: 1,
+        imm: 0,
+    };
+    assert_eq!(disassembler.format(&instr, 0x1000), "jalr x0, 0(x1)");
+}
+
+#[test]
+fn listing() {
+    let code = assemble(&[
+        Instruction::Addi {
+            rd: 10,
+            rs1: 0,
+            imm: 5,
+        },
+        Instruction::Beq {
+            rs1: 10,
+            rs2: 0,
+            imm: 8,
+        },
+        Instruction::Jal { rd: 0, imm: -8 },
+    ]);
+    let disassembler = Disassembler::new(0x8000);
+    assert_eq!(
+        disassembler.disassemble(&code),
+        "0x00008000:  addi x10, x0, 5\n\
+         0x00008004:  beq x10, x0, 0x800c\n\
+         0x00008008:  jal x0, 0x8000\n"
+    );
+}
+
+#[test]
+fn listing_with_labels() {
+    let code = assemble(&[
+        Instruction::Addi {
+            rd: 1,
+            rs1: 1,
+            imm: -1,
+        },
+        Instruction::Bne {
+            rs1: 1,
+            rs2: 0,
+            imm: -4,
+        },
+        Instruction::Ecall,
+    ]);
+    let mut disassembler = Disassembler::new(0x1000);
+    disassembler.label(0x1000, "loop");
+    disassembler.label(0x1008, "done");
+    assert_eq!(
+        disassembler.disassemble(&code),
+        "loop:\n\
+         0x00001000:  addi x1, x1, -1\n\
+         0x00001004:  bne x1, x0, <loop>\n\
+         done:\n\
+         0x00001008:  ecall\n"
+    );
+}
+
+#[test]
+fn listing_ignores_trailing_bytes() {
+    let mut code = assemble(&[Instruction::Ecall]);
+    code.extend_from_slice(&[0x13, 0x00]);
+    let disassembler = Disassembler::new(0);
+    assert_eq!(disassembler.disassemble(&code), "0x00000000:  ecall\n");
+}
+
+#[test]
+fn listing_compressed() {
+    // With C, a 2-byte compressed parcel (c.nop) keeps the following
+    // instruction in sync
+    let mut code = vec![0x01, 0x00];
+    code.extend(assemble(&[Instruction::Ecall]));
+    let mut disassembler = Disassembler::new(0);
+    disassembler.set_decoder(Decoder::new(Extensions::all()));
+    assert_eq!(
+        disassembler.disassemble(&code),
+        "0x00000000:  unsupported: 0x00000001\n0x00000002:  ecall\n"
+    );
+
+    // Without C, code is read as whole words
+    disassembler.set_decoder(Decoder::default());
+    assert_eq!(
+        disassembler.disassemble(&code),
+        "0x00000000:  unsupported: 0x00730001\n"
+    );
+}
+
+#[test]
+fn listing_decoder() {
+    // mul x1, x2, x3 is unsupported without M
+    let code = assemble(&[Instruction::Mul {
+        rd: 1,
+        rs1: 2,
+        rs2: 3,
+    }]);
+    let mut disassembler = Disassembler::new(0);
+    assert_eq!(
+        disassembler.disassemble(&code),
+        "0x00000000:  mul x1, x2, x3\n"
+    );
+    disassembler.set_decoder(Decoder::new(Extensions::base()));
+    assert_eq!(
+        disassembler.disassemble(&code),
+        "0x00000000:  unsupported: 0x023100b3\n"
+    );
+}
+
+#[test]
+fn listing_empty() {
+    let disassembler = Disassembler::new(0);
+    assert_eq!(disassembler.disassemble(&[]), "");
+}
+
+#[test]
+fn target_none_for_non_branch() {
+    let instr = Instruction::Add {
+        rd: 1,
+        rs1: 2,
+        rs2: 3,
+    };
+    assert_eq!(instr.target(0x1000), None);
+}
@@ -0,0 +1,696 @@
+use crate::{
+    CompileError, Elf, ElfError, Instruction, Memory, MemoryError, Module, PageStore,
+    elf::{LoadSegment, MAX_CODE_SIZE, STB_GLOBAL, STB_WEAK, STT_FUNC, Section, Symbol},
+};
+use std::{env, fs, io, path::PathBuf, process};
+
+/// (address, file contents, size in memory, flags) of a PT_LOAD segment
+type SegmentSpec<'a> = (u32, &'a [u8], u32, u32);
+
+/// (name, type, flags, address, size) of a section; its file offset is that
+/// of `address` in the segment holding it
+type SectionSpec<'a> = (&'a str, u32, u32, u32, u32);
+
+/// Build an executable entered at `entry` with the given segments and,
+/// unless `sections` is empty, a null section, the given sections and a
+/// section name table
+fn build(entry: u32, segments: &[SegmentSpec], sections: &[SectionSpec]) -> Vec<u8> {
+    let mut bytes = vec![0; 52];
+    bytes[..7].copy_from_slice(&[0x7F, b'E', b'L', b'F', 1, 1, 1]);
+    put16(&mut bytes, 16, 2);
+    put16(&mut bytes, 18, 243);
+    put32(&mut bytes, 20, 1);
+    put32(&mut bytes, 24, entry);
+    put32(&mut bytes, 28, 52);
+    put16(&mut bytes, 40, 52);
+    put16(&mut bytes, 42, 32);
+    put16(&mut bytes, 44, segments.len() as u16);
+
+    let data_offset = 52 + 32 * segments.len();
+    let mut offsets = Vec::new();
+    let mut offset = data_offset;
+    for (address, data, size, flags) in segments {
+        let header = [
+            1,
+            offset as u32,
+            *address,
+            *address,
+            data.len() as u32,
+            *size,
+            *flags,
+            4,
+        ];
+        bytes.extend(header.iter().flat_map(|word| word.to_le_bytes()));
+        offsets.push(offset);
+        offset += data.len();
+    }
+    for (_, data, ..) in segments {
+        bytes.extend_from_slice(data);
+    }
+    if sections.is_empty() {
+        return bytes;
+    }
+
+    let mut names = vec![0];
+    let mut headers = vec![0; 40];
+    let names_offset = bytes.len() as u32;
+    for (name, kind, flags, address, size) in sections {
+        let file_offset = segments
+            .iter()
+            .zip(&offsets)
+            .find(|((start, data, ..), _)| (*start..*start + data.len() as u32).contains(address))
+            .map_or(0, |((start, ..), offset)| {
+                (*offset as u32) + address - start
+            });
+        let header = [
+            names.len() as u32,
+            *kind,
+            *flags,
+            *address,
+            file_offset,
+            *size,
+            0,
+            0,
+            4,
+            0,
+        ];
+        headers.extend(header.iter().flat_map(|word| word.to_le_bytes()));
+        names.extend(name.bytes().chain([0]));
+    }
+    let header = [names.len() as u32, 3, 0, 0, names_offset, 0, 0, 0, 1, 0];
+    names.extend(b".shstrtab\0");
+    headers.extend(header.iter().flat_map(|word| word.to_le_bytes()));
+    let size_offset = headers.len() - 40 + 20;
+    put32(&mut headers, size_offset, names.len() as u32);
+    bytes.extend(names);
+
+    let count = sections.len() as u16 + 2;
+    let headers_offset = bytes.len() as u32;
+    put32(&mut bytes, 32, headers_offset);
+    put16(&mut bytes, 46, 40);
+    put16(&mut bytes, 48, count);
+    put16(&mut bytes, 50, count - 1);
+    bytes.extend(headers);
+    bytes
+}
+
+/// (name, address, type, binding) of a symbol defined in section 1
+type SymbolSpec<'a> = (&'a str, u32, u8, u8);
+
+/// Append a symbol table holding the null symbol and `symbols`, and its
+/// string table, to the sections of `bytes`
+fn with_symbols(mut bytes: Vec<u8>, symbols: &[SymbolSpec]) -> Vec<u8> {
+    let headers_offset = u32::from_le_bytes(bytes[32..36].try_into().unwrap()) as usize;
+    let count = u16::from_le_bytes(bytes[48..50].try_into().unwrap());
+    let headers = bytes[headers_offset..headers_offset + 40 * count as usize].to_vec();
+
+    let mut names = vec![0];
+    let mut table = vec![0; 16];
+    for (name, address, kind, binding) in symbols {
+        table.extend((names.len() as u32).to_le_bytes());
+        table.extend(address.to_le_bytes());
+        table.extend(4u32.to_le_bytes());
+        table.extend([binding << 4 | kind, 0]);
+        table.extend(1u16.to_le_bytes());
+        names.extend(name.bytes().chain([0]));
+    }
+    let names_offset = bytes.len() as u32;
+    bytes.extend(&names);
+    let table_offset = bytes.len() as u32;
+    bytes.extend(&table);
+
+    let headers_offset = bytes.len() as u32;
+    bytes.extend(headers);
+    let string_table = [0, 3, 0, 0, names_offset, names.len() as u32, 0, 0, 1, 0];
+    let symbol_table = [
+        0,
+        2,
+        0,
+        0,
+        table_offset,
+        table.len() as u32,
+        count as u32,
+        1,
+        4,
+        16,
+    ];
+    for header in [string_table, symbol_table] {
+        bytes.extend(header.iter().flat_map(|word| word.to_le_bytes()));
+    }
+    put32(&mut bytes, 32, headers_offset);
+    put16(&mut bytes, 48, count + 2);
+    bytes
+}
+
+fn put16(bytes: &mut [u8], offset: usize, value: u16) {
+    bytes[offset..offset + 2].copy_from_slice(&value.to_le_bytes());
+}
+
+fn put32(bytes: &mut [u8], offset: usize, value: u32) {
+    bytes[offset..offset + 4].copy_from_slice(&value.to_le_bytes());
+}
+
+/// Write `bytes` to a temporary file named after `name`
+fn temp_file(name: &str, bytes: &[u8]) -> PathBuf {
+    let path = env::temp_dir().join(format!("jigs-elf-{}-{}", name, process::id()));
+    fs::write(&path, bytes).unwrap();
+    path
+}
+
+/// Text and read-only data in an executable segment at 0x1000, and data
+/// with a .bss tail in a writable segment at 0x4000
+fn sample() -> Vec<u8> {
+    let mut text = Instruction::encode_all(&[
+        Instruction::Lui { rd: 10, imm: 0x4 },
+        Instruction::Lw {
+            rd: 11,
+            rs1: 10,
+            imm: 0,
+        },
+        Instruction::Ecall,
+    ])
+    .unwrap();
+    text.extend(b"rodata\0\0");
+    build(
+        0x1000,
+        &[
+            (0x1000, &text, 20, 5),
+            (0x4000, &42u32.to_le_bytes(), 0x10, 6),
+        ],
+        &[
+            (".text", 1, 6, 0x1000, 12),
+            (".rodata", 1, 2, 0x100C, 8),
+            (".data", 1, 3, 0x4000, 4),
+            (".bss", 8, 3, 0x4004, 12),
+        ],
+    )
+}
+
+#[test]
+fn parse() {
+    let elf = Elf::parse(&sample()).unwrap();
+    assert_eq!(elf.entry, 0x1000);
+    assert_eq!(elf.flags, 0);
+    assert_eq!(elf.segments.len(), 2);
+    assert_eq!(
+        elf.segments[1],
+        LoadSegment {
+            address: 0x4000,
+            data: 42u32.to_le_bytes().to_vec(),
+            size: 0x10,
+            flags: 6,
+        }
+    );
+    let names: Vec<_> = elf
+        .sections
+        .iter()
+        .map(|section| &section.name[..])
+        .collect();
+    assert_eq!(
+        names,
+        ["", ".text", ".rodata", ".data", ".bss", ".shstrtab"]
+    );
+    assert_eq!(
+        elf.section(".bss"),
+        Some(&Section {
+            name: ".bss".to_string(),
+            kind: 8,
+            flags: 3,
+            address: 0x4004,
+            offset: 0,
+            size: 12,
+        })
+    );
+    assert_eq!(elf.section(".comment"), None);
+}
+
+#[test]
+fn parse_skips_other_segments() {
+    let mut bytes = build(0, &[(0, &[0x73, 0, 0, 0], 4, 5), (0x100, &[1], 1, 4)], &[]);
+    // PT_NOTE
+    put32(&mut bytes, 52 + 32, 4);
+    let elf = Elf::parse(&bytes).unwrap();
+    assert_eq!(elf.segments.len(), 1);
+    assert!(elf.sections.is_empty());
+}
+
+#[test]
+fn code_spans_executable_sections() {
+    let elf = Elf::parse(&sample()).unwrap();
+    let (base, code) = elf.code().unwrap();
+    assert_eq!(base, 0x1000);
+    assert_eq!(code, Elf::parse(&sample()).unwrap().segments[0].data[..12]);
+
+    // A gap between two executable sections is zeroed
+    let text = Instruction::encode_all(&[Instruction::Ecall, Instruction::Ecall]).unwrap();
+    let bytes = build(
+        0x2000,
+        &[(0x2000, &[text.clone(), vec![0xFF; 4], text].concat(), 20, 5)],
+        &[(".init", 1, 6, 0x2000, 8), (".text", 1, 6, 0x200C, 6)],
+    );
+    let (base, code) = Elf::parse(&bytes).unwrap().code().unwrap();
+    assert_eq!(base, 0x2000);
+    assert_eq!(code.len(), 20);
+    assert_eq!(code[8..12], [0; 4]);
+    assert_eq!(
+        code[12..16],
+        Instruction::Ecall.encode().unwrap().to_le_bytes()
+    );
+    assert_eq!(code[18..], [0; 2]);
+}
+
+#[test]
+fn section_at_segment_end() {
+    let ecall = Instruction::encode_all(&[Instruction::Ecall]).unwrap();
+    let ebreak = Instruction::encode_all(&[Instruction::Ebreak]).unwrap();
+    let bytes = build(
+        0x2000,
+        &[(0x2000, &ecall, 4, 5), (0x2004, &ebreak, 4, 5)],
+        &[(".init", 1, 6, 0x2000, 4), (".text", 1, 6, 0x2004, 4)],
+    );
+    let (base, code) = Elf::parse(&bytes).unwrap().code().unwrap();
+    assert_eq!(base, 0x2000);
+    assert_eq!(code, [ecall, ebreak].concat());
+}
+
+#[test]
+fn code_from_segments_without_sections() {
+    let text = Instruction::encode_all(&[Instruction::Ecall]).unwrap();
+    let bytes = build(
+        0x800,
+        &[(0x800, &text, 4, 5), (0x1000, &[1, 2, 3, 4], 4, 6)],
+        &[],
+    );
+    assert_eq!(Elf::parse(&bytes).unwrap().code(), Some((0x800, text)));
+}
+
+#[test]
+fn no_code() {
+    let bytes = build(0, &[(0x1000, &[1, 2, 3, 4], 4, 6)], &[]);
+    let elf = Elf::parse(&bytes).unwrap();
+    assert_eq!(elf.code(), None);
+    let mut module = Module::new(1024).unwrap();
+    assert_eq!(elf.compile(&mut module), Err(ElfError::NoCode));
+}
+
+#[test]
+fn image_zeroes_tails() {
+    let elf = Elf::parse(&sample()).unwrap();
+    let image = elf.image();
+    assert_eq!(image.len(), 2);
+    assert_eq!(image[0].0, 0x1000);
+    assert_eq!((image[0].1.len(), image[0].2), (20, 0));
+    assert_eq!(image[1], (0x4000, 42u32.to_le_bytes().to_vec(), 12));
+}
+
+#[test]
+fn huge_tail() {
+    // A 4 byte file asking for almost 4GB of zeroed memory
+    let bytes = build(0, &[(0x1000, &[1; 4], 0xF000_0000, 6)], &[]);
+    let elf = Elf::parse(&bytes).unwrap();
+    assert_eq!(elf.image(), [(0x1000, vec![1; 4], 0xEFFF_FFFC)]);
+
+    let store = PageStore::new(16);
+    let mut memory = Memory::new(&store, 16, 4);
+    memory.write(0x2000, &[0xFF; 4]).unwrap();
+    elf.load_memory(&mut memory).unwrap();
+    assert_eq!(memory.read_u32(0x1000), 0x0101_0101);
+    assert_eq!(memory.read_u32(0x2000), 0);
+    assert_eq!(memory.stats().peak_pages, 1);
+}
+
+#[test]
+fn code_too_large() {
+    let bytes = build(
+        0,
+        &[
+            (0x1000, &[0; 4], 4, 5),
+            (0x1000 + MAX_CODE_SIZE, &[0; 4], 4, 5),
+        ],
+        &[],
+    );
+    assert_eq!(
+        Elf::parse(&bytes),
+        Err(ElfError::CodeTooLarge(MAX_CODE_SIZE as u64 + 4))
+    );
+
+    // Hand-built values skip parse(), so code() checks the span itself
+    let segment = |address| LoadSegment {
+        address,
+        data: vec![0; 4],
+        size: 4,
+        flags: 5,
+    };
+    let elf = Elf {
+        segments: vec![segment(0), segment(0xFFFF_0000)],
+        ..Elf::default()
+    };
+    assert_eq!(elf.code(), None);
+    assert_eq!(elf.functions(), [0]);
+
+    // Exactly the limit is fine
+    let bytes = build(
+        0,
+        &[(0, &[0; 4], 4, 5), (MAX_CODE_SIZE - 4, &[0; 4], 4, 5)],
+        &[],
+    );
+    let (_, code) = Elf::parse(&bytes).unwrap().code().unwrap();
+    assert_eq!(code.len(), MAX_CODE_SIZE as usize);
+}
+
+#[test]
+fn functions_at_end_of_address_space() {
+    let elf = Elf {
+        entry: 0xFFFF_FFF8,
+        segments: vec![LoadSegment {
+            address: 0xFFFF_FFF8,
+            data: vec![0; 8],
+            size: 8,
+            flags: 5,
+        }],
+        symbols: vec![Symbol {
+            name: "last".to_string(),
+            address: 0xFFFF_FFFC,
+            size: 4,
+            kind: STT_FUNC,
+            binding: STB_GLOBAL,
+            section: 1,
+        }],
+        ..Elf::default()
+    };
+    assert_eq!(elf.functions(), [0xFFFF_FFF8, 0xFFFF_FFFC]);
+}
+
+#[test]
+fn compile_at_code_address() {
+    let elf = Elf::parse(&sample()).unwrap();
+    let mut module = Module::new(1024).unwrap();
+    elf.compile(&mut module).unwrap();
+    assert_eq!(module.base(), 0x1000);
+    assert!(module.listing().contains("ecall"));
+    assert!(!module.code().is_empty());
+
+    // Modules sized for less code grow
+    let mut small = Module::new(4).unwrap();
+    elf.compile(&mut small).unwrap();
+    assert_eq!(small.code(), module.code());
+}
+
+#[test]
+fn load_memory() {
+    let elf = Elf::parse(&sample()).unwrap();
+    let store = PageStore::new(16);
+    let mut memory = Memory::new(&store, 16, 4);
+    memory.write(0x4008, &[0xFF; 4]).unwrap();
+    elf.load_memory(&mut memory).unwrap();
+    assert_eq!(memory.read_u32(0x4000), 42);
+    assert_eq!(memory.read_u32(0x4008), 0);
+    let mut rodata = [0; 6];
+    memory.read(0x100C, &mut rodata);
+    assert_eq!(&rodata, b"rodata");
+
+    let mut full = Memory::new(&store, 1, 4);
+    assert_eq!(
+        elf.load_memory(&mut full),
+        Err(ElfError::MemoryError(MemoryError::PageLimit))
+    );
+}
+
+#[test]
+fn invalid_header() {
+    let bytes = sample();
+    let mut invalid = bytes.clone();
+    invalid[1] = b'X';
+    assert_eq!(Elf::parse(&invalid), Err(ElfError::InvalidMagic));
+    let mut invalid = bytes.clone();
+    invalid[4] = 2;
+    assert_eq!(Elf::parse(&invalid), Err(ElfError::UnsupportedClass(2)));
+    let mut invalid = bytes.clone();
+    invalid[5] = 2;
+    assert_eq!(Elf::parse(&invalid), Err(ElfError::UnsupportedEncoding(2)));
+    let mut invalid = bytes.clone();
+    put16(&mut invalid, 16, 3);
+    assert_eq!(Elf::parse(&invalid), Err(ElfError::UnsupportedType(3)));
+    let mut invalid = bytes;
+    put16(&mut invalid, 18, 62);
+    assert_eq!(Elf::parse(&invalid), Err(ElfError::UnsupportedMachine(62)));
+}
+
+#[test]
+fn truncated() {
+    let bytes = sample();
+    for length in [0, 51, 52 + 40, 52 + 64 + 4, bytes.len() - 1] {
+        assert_eq!(Elf::parse(&bytes[..length]), Err(ElfError::Truncated));
+    }
+    // Program headers smaller than the ELF32 layout
+    let mut invalid = bytes;
+    put16(&mut invalid, 42, 16);
+    assert_eq!(Elf::parse(&invalid), Err(ElfError::Truncated));
+}
+
+#[test]
+fn invalid_segment() {
+    // Larger in the file than in memory
+    let bytes = build(0, &[(0, &[0; 4], 4, 5), (0x100, &[0; 8], 4, 6)], &[]);
+    assert_eq!(Elf::parse(&bytes), Err(ElfError::InvalidSegment(1)));
+    // Past the end of the address space
+    let bytes = build(0, &[(0xFFFF_FFF0, &[0; 4], 0x20, 6)], &[]);
+    assert_eq!(Elf::parse(&bytes), Err(ElfError::InvalidSegment(0)));
+}
+
+#[test]
+fn invalid_section_name() {
+    let mut bytes = sample();
+    let sections = u32::from_le_bytes(bytes[32..36].try_into().unwrap()) as usize;
+    put32(&mut bytes, sections + 2 * 40, 0x1000);
+    assert_eq!(Elf::parse(&bytes), Err(ElfError::InvalidSectionName(2)));
+}
+
+#[test]
+fn symbols() {
+    let bytes = with_symbols(
+        sample(),
+        &[
+            ("_start", 0x1000, STT_FUNC, STB_GLOBAL),
+            ("local", 0x1004, STT_FUNC, 0),
+        ],
+    );
+    let elf = Elf::parse(&bytes).unwrap();
+    assert_eq!(
+        elf.symbols,
+        [
+            Symbol {
+                name: "_start".to_string(),
+                address: 0x1000,
+                size: 4,
+                kind: STT_FUNC,
+                binding: STB_GLOBAL,
+                section: 1,
+            },
+            Symbol {
+                name: "local".to_string(),
+                address: 0x1004,
+                size: 4,
+                kind: STT_FUNC,
+                binding: 0,
+                section: 1,
+            },
+        ]
+    );
+    assert!(Elf::parse(&sample()).unwrap().symbols.is_empty());
+}
+
+#[test]
+fn functions() {
+    let bytes = with_symbols(
+        sample(),
+        &[
+            // Entry point
+            ("_start", 0x1000, STT_FUNC, STB_GLOBAL),
+            ("load", 0x1004, STT_FUNC, STB_GLOBAL),
+            // Not exported
+            ("local", 0x1000, STT_FUNC, 0),
+            ("object", 0x1004, 1, STB_GLOBAL),
+            ("call", 0x1008, STT_FUNC, STB_WEAK),
+            // Alias of load
+            ("alias", 0x1004, STT_FUNC, STB_GLOBAL),
+            // Outside the code
+            ("data", 0x4000, STT_FUNC, STB_GLOBAL),
+        ],
+    );
+    let elf = Elf::parse(&bytes).unwrap();
+    assert_eq!(elf.functions(), [0x1000, 0x1004, 0x1008]);
+    assert_eq!(elf.function_index("_start"), Some(0));
+    assert_eq!(elf.function_index("load"), Some(1));
+    assert_eq!(elf.function_index("alias"), Some(1));
+    assert_eq!(elf.function_index("call"), Some(2));
+    assert_eq!(elf.function_index("local"), None);
+    assert_eq!(elf.function_index("data"), None);
+    assert_eq!(elf.function_index("missing"), None);
+
+    // Without code only the entry point is known
+    let elf = Elf::parse(&build(0x1000, &[(0x1000, &[1, 2, 3, 4], 4, 6)], &[])).unwrap();
+    assert_eq!(elf.functions(), [0x1000]);
+}
+
+#[test]
+fn invalid_symbol_name() {
+    let mut bytes = with_symbols(sample(), &[("_start", 0x1000, STT_FUNC, STB_GLOBAL)]);
+    let headers = u32::from_le_bytes(bytes[32..36].try_into().unwrap()) as usize;
+    let table = headers - 32;
+    put32(&mut bytes, table + 16, 0x1000);
+    assert_eq!(Elf::parse(&bytes), Err(ElfError::InvalidSymbolName(1)));
+}
+
+#[test]
+fn load() {
+    let path = temp_file("load", &sample());
+    let elf = Elf::load(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(elf, Elf::parse(&sample()));
+}
+
+#[test]
+fn from_elf_file() {
+    let path = temp_file("module", &sample());
+    let result = Module::from_elf_file(&path);
+    fs::remove_file(&path).unwrap();
+    let module = result.unwrap();
+    assert_eq!(module.base(), 0x1000);
+    assert_eq!(module.functions(), [0x1000]);
+    assert_eq!(module.max_code_size(), 12);
+    assert!(module.listing().contains("ecall"));
+    assert_eq!(module.data(), Elf::parse(&sample()).unwrap().image());
+
+    let bytes = with_symbols(
+        sample(),
+        &[
+            ("_start", 0x1000, STT_FUNC, STB_GLOBAL),
+            ("load", 0x1004, STT_FUNC, STB_GLOBAL),
+        ],
+    );
+    let path = temp_file("functions", &bytes);
+    let result = Module::from_elf_file(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(result.unwrap().functions(), [0x1000, 0x1004]);
+}
+
+#[test]
+fn from_elf_file_errors() {
+    let path = env::temp_dir().join("jigs-elf-missing");
+    assert_eq!(
+        Module::from_elf_file(path).err(),
+        Some(ElfError::Io(io::ErrorKind::NotFound))
+    );
+
+    let mut bytes = sample();
+    put16(&mut bytes, 18, 62);
+    let path = temp_file("machine", &bytes);
+    let result = Module::from_elf_file(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(result.err(), Some(ElfError::UnsupportedMachine(62)));
+
+    // 64-bit files are rejected before their headers are read
+    let mut bytes = sample();
+    bytes[4] = 2;
+    let path = temp_file("class", &bytes);
+    let result = Module::from_elf_file(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(result.err(), Some(ElfError::UnsupportedClass(2)));
+
+    // The entry point must be an instruction of the code
+    let mut bytes = sample();
+    put32(&mut bytes, 24, 0x1002);
+    let path = temp_file("entry", &bytes);
+    let result = Module::from_elf_file(&path);
+    fs::remove_file(&path).unwrap();
+    assert_eq!(
+        result.err(),
+        Some(ElfError::CompileError(CompileError::InvalidFunction {
+            index: 0,
+            address: 0x1002
+        }))
+    );
+}
+
+#[test]
+fn display() {
+    assert_eq!(ElfError::InvalidMagic.to_string(), "Not an ELF file");
+    assert_eq!(
+        ElfError::Io(io::ErrorKind::NotFound).to_string(),
+        "I/O error: entity not found"
+    );
+    assert_eq!(
+        ElfError::UnsupportedMachine(62).to_string(),
+        "Unsupported machine: 62"
+    );
+    assert_eq!(ElfError::NoCode.to_string(), "ELF file has no code");
+    assert_eq!(
+        ElfError::CodeTooLarge(1 << 25).to_string(),
+        "Code image spans 33554432 bytes, more than 16777216"
+    );
+    assert_eq!(
+        ElfError::InvalidSymbolName(3).to_string(),
+        "Invalid name of symbol 3"
+    );
+}
+
+#[test]
+#[cfg(target_arch = "aarch64")]
+fn run() {
+    use crate::Instance;
+
+    let elf = Elf::parse(&sample()).unwrap();
+    let mut module = Module::new(1024).unwrap();
+    elf.compile(&mut module).unwrap();
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    elf.load_memory(instance.memory_mut()).unwrap();
+    instance.attach(&mut module).unwrap();
+    let result = unsafe { instance.call_function(0) };
+    assert_eq!(result, Err("Environment call"));
+    assert_eq!(instance.read_register(11), 42);
+}
+
+#[test]
+#[cfg(target_arch = "aarch64")]
+fn run_exported_function() {
+    use crate::Instance;
+
+    let bytes = with_symbols(sample(), &[("load", 0x1004, STT_FUNC, STB_GLOBAL)]);
+    let path = temp_file("exported", &bytes);
+    let result = Module::from_elf_file(&path);
+    fs::remove_file(&path).unwrap();
+    let mut module = result.unwrap();
+    let index = Elf::parse(&bytes).unwrap().function_index("load").unwrap();
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    instance.write_register(10, 0x4000);
+    instance.attach(&mut module).unwrap();
+    let result = unsafe { instance.call_function(index) };
+    assert_eq!(result, Err("Environment call"));
+    assert_eq!(instance.pc(), 0x1008);
+    assert_eq!(instance.read_register(11), 42);
+}
+
+#[test]
+#[cfg(target_arch = "aarch64")]
+fn run_from_elf_file() {
+    use crate::Instance;
+
+    // Enter past the LUI, so x10 stays at its address of the data
+    let mut bytes = sample();
+    put32(&mut bytes, 24, 0x1004);
+    let path = temp_file("run", &bytes);
+    let result = Module::from_elf_file(&path);
+    fs::remove_file(&path).unwrap();
+    let mut module = result.unwrap();
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    instance.write_register(10, 0x4000);
+    instance.attach(&mut module).unwrap();
+    let result = unsafe { instance.call_function(0) };
+    assert_eq!(result, Err("Environment call"));
+    assert_eq!(instance.pc(), 0x1008);
+    assert_eq!(instance.read_register(11), 42);
+}
@@ -0,0 +1,128 @@
+use crate::ExecutableBuffer;
+
+/// Native code returning `value` from an `extern "C" fn() -> u32`
+#[cfg(target_arch = "x86_64")]
+fn returning(value: u32) -> Vec<u8> {
+    // mov eax, value; ret
+    let mut code = vec![0xB8];
+    code.extend(value.to_le_bytes());
+    code.push(0xC3);
+    code
+}
+
+/// Native code returning `value` from an `extern "C" fn() -> u32`
+#[cfg(target_arch = "aarch64")]
+fn returning(value: u32) -> Vec<u8> {
+    [crate::arm64::mov_imm(0, value), vec![crate::arm64::RET]]
+        .concat()
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect()
+}
+
+#[test]
+fn new_is_zeroed() {
+    let buffer = ExecutableBuffer::new(8192).unwrap();
+    assert_eq!(buffer.len(), 8192);
+    assert!(!buffer.is_empty());
+    assert_eq!(buffer.as_ptr() as usize % 4096, 0);
+    assert!(buffer.as_slice().iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn new_empty_fails() {
+    assert!(ExecutableBuffer::new(0).is_err());
+}
+
+#[test]
+fn grow_within_capacity() {
+    let mut buffer = ExecutableBuffer::with_capacity(4096, 1 << 20).unwrap();
+    assert_eq!((buffer.len(), buffer.capacity()), (4096, 1 << 20));
+    buffer
+        .write(|code| code[..4].copy_from_slice(&[1, 2, 3, 4]))
+        .unwrap();
+    let start = buffer.as_ptr();
+
+    buffer.grow(3 * 4096 + 8).unwrap();
+    assert_eq!(buffer.len(), 3 * 4096 + 8);
+    assert_eq!(buffer.as_ptr(), start);
+    assert_eq!(buffer.as_slice()[..4], [1, 2, 3, 4]);
+    assert!(buffer.as_slice()[4..].iter().all(|&byte| byte == 0));
+    buffer.write(|code| code[3 * 4096] = 5).unwrap();
+    assert_eq!(buffer.as_slice()[3 * 4096], 5);
+
+    // Smaller sizes are ignored
+    buffer.grow(16).unwrap();
+    assert_eq!(buffer.len(), 3 * 4096 + 8);
+    let error = buffer.grow((1 << 20) + 1).unwrap_err();
+    assert_eq!(error.kind(), std::io::ErrorKind::OutOfMemory);
+    assert_eq!(buffer.len(), 3 * 4096 + 8);
+}
+
+#[test]
+fn capacity_at_least_len() {
+    let buffer = ExecutableBuffer::with_capacity(8192, 4096).unwrap();
+    assert_eq!(buffer.capacity(), 8192);
+    assert_eq!(ExecutableBuffer::new(4096).unwrap().capacity(), 4096);
+    assert!(ExecutableBuffer::with_capacity(0, 4096).is_err());
+}
+
+#[test]
+fn write_keeps_contents() {
+    let mut buffer = ExecutableBuffer::new(4096).unwrap();
+    let written = buffer
+        .write(|code| {
+            code[..4].copy_from_slice(&[1, 2, 3, 4]);
+            4
+        })
+        .unwrap();
+    assert_eq!(written, 4);
+    assert_eq!(buffer.as_slice()[..5], [1, 2, 3, 4, 0]);
+
+    buffer.write(|code| code[2] = 9).unwrap();
+    assert_eq!(buffer.as_slice()[..5], [1, 2, 9, 4, 0]);
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+#[test]
+fn grown_code_runs() {
+    let mut buffer = ExecutableBuffer::with_capacity(4096, 3 * 4096).unwrap();
+    buffer.grow(3 * 4096).unwrap();
+    let code = returning(42);
+    buffer
+        .write(|bytes| bytes[2 * 4096..2 * 4096 + code.len()].copy_from_slice(&code))
+        .unwrap();
+    let function: extern "C" fn() -> u32 =
+        unsafe { std::mem::transmute(buffer.as_ptr().add(2 * 4096)) };
+    assert_eq!(function(), 42);
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+#[test]
+fn code_runs_after_panicking_write() {
+    let mut buffer = ExecutableBuffer::new(4096).unwrap();
+    let code = returning(42);
+    let outcome = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+        buffer.write(|bytes| {
+            bytes[..code.len()].copy_from_slice(&code);
+            panic!("writer failed");
+        })
+    }));
+    assert!(outcome.is_err());
+    let function: extern "C" fn() -> u32 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+    assert_eq!(function(), 42);
+}
+
+#[cfg(any(target_arch = "x86_64", target_arch = "aarch64"))]
+#[test]
+fn rewritten_code_runs() {
+    let mut buffer = ExecutableBuffer::new(4096).unwrap();
+    for value in [42, 7, 0x12345678] {
+        let code = returning(value);
+        buffer
+            .write(|bytes| bytes[..code.len()].copy_from_slice(&code))
+            .unwrap();
+        let function: extern "C" fn() -> u32 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+        assert_eq!(function(), value);
+    }
+}
@@ -0,0 +1,202 @@
+use super::interpreter::run;
+use crate::fold::{fold, fold_at};
+use crate::{Instruction, PageStore};
+
+/// `addi rd, x0, imm`
+fn li(rd: u8, imm: i32) -> Instruction {
+    Instruction::Addi { rd, rs1: 0, imm }
+}
+
+#[test]
+fn known_results_become_constants() {
+    let code = [
+        li(5, 0x100),
+        Instruction::Slli {
+            rd: 6,
+            rs1: 5,
+            shamt: 4,
+        },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 5,
+            imm: -0x7FF,
+        },
+        Instruction::Divu {
+            rd: 7,
+            rs1: 6,
+            rs2: 0,
+        },
+        Instruction::Auipc { rd: 8, imm: 0 },
+    ];
+    assert_eq!(
+        fold(&code),
+        [
+            li(5, 0x100),
+            Instruction::Lui { rd: 6, imm: 0x1 },
+            li(5, -0x6FF),
+            li(7, -1),
+            li(8, 16),
+        ]
+    );
+}
+
+#[test]
+fn auipc_folds_at_base() {
+    let code = [li(5, 1), Instruction::Auipc { rd: 6, imm: 2 }];
+    // 0x2004 needs both LUI and ADDI, 0x3000 only LUI
+    assert_eq!(fold(&code), code);
+    assert_eq!(
+        fold_at(&code, 0xFFC),
+        [li(5, 1), Instruction::Lui { rd: 6, imm: 3 }]
+    );
+}
+
+#[test]
+fn large_constants_kept() {
+    // 0x12345678 needs both LUI and ADDI, so the ADDI stays as it is
+    let code = [
+        Instruction::Lui {
+            rd: 5,
+            imm: 0x12345,
+        },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 5,
+            imm: 0x678,
+        },
+    ];
+    assert_eq!(fold(&code), code);
+}
+
+#[test]
+fn known_operands_become_immediates() {
+    let code = [
+        li(5, 0x800 - 1),
+        Instruction::Add {
+            rd: 6,
+            rs1: 5,
+            rs2: 7,
+        },
+        Instruction::Sub {
+            rd: 6,
+            rs1: 7,
+            rs2: 5,
+        },
+        li(5, 35),
+        Instruction::Sra {
+            rd: 6,
+            rs1: 7,
+            rs2: 5,
+        },
+    ];
+    assert_eq!(
+        fold(&code),
+        [
+            li(5, 0x7FF),
+            Instruction::Addi {
+                rd: 6,
+                rs1: 7,
+                imm: 0x7FF,
+            },
+            Instruction::Addi {
+                rd: 6,
+                rs1: 7,
+                imm: -0x7FF,
+            },
+            li(5, 35),
+            Instruction::Srai {
+                rd: 6,
+                rs1: 7,
+                shamt: 3,
+            },
+        ]
+    );
+}
+
+#[test]
+fn unknown_values_forget_constants() {
+    let add = Instruction::Add {
+        rd: 6,
+        rs1: 5,
+        rs2: 5,
+    };
+    // A load overwrites the constant, and a branch target may be reached
+    // with other values
+    let code = [
+        li(5, 1),
+        Instruction::Lw {
+            rd: 5,
+            rs1: 0,
+            imm: 0,
+        },
+        add.clone(),
+        li(5, 1),
+        add.clone(),
+    ];
+    let mut branching = code.to_vec();
+    branching.push(Instruction::Bne {
+        rs1: 6,
+        rs2: 0,
+        imm: -4,
+    });
+    assert_eq!(fold(&code)[2], add);
+    assert_eq!(fold(&code)[4], li(6, 2));
+    assert_eq!(fold(&branching)[4], add);
+}
+
+#[test]
+fn folded_programs_compute_the_same() {
+    let program = [
+        Instruction::Lui {
+            rd: 10,
+            imm: 0x80000,
+        },
+        Instruction::Addi {
+            rd: 11,
+            rs1: 0,
+            imm: -1,
+        },
+        Instruction::Div {
+            rd: 12,
+            rs1: 10,
+            rs2: 11,
+        },
+        Instruction::Rem {
+            rd: 13,
+            rs1: 10,
+            rs2: 11,
+        },
+        Instruction::Mulhsu {
+            rd: 14,
+            rs1: 11,
+            rs2: 11,
+        },
+        Instruction::Sltiu {
+            rd: 15,
+            rs1: 11,
+            imm: -1,
+        },
+        Instruction::Sub {
+            rd: 16,
+            rs1: 13,
+            rs2: 10,
+        },
+        Instruction::Sll {
+            rd: 17,
+            rs1: 11,
+            rs2: 11,
+        },
+        Instruction::Ecall,
+    ];
+    let store = PageStore::new(32);
+    let original = run(&store, &program);
+    let folded = run(&store, &fold(&program));
+    for reg in 10..=17 {
+        assert_eq!(
+            folded.read_register(reg),
+            original.read_register(reg),
+            "x{}",
+            reg
+        );
+    }
+}
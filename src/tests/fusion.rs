@@ -0,0 +1,230 @@
+use crate::Instruction;
+use crate::fusion::{self, Fusion, FusionKind};
+
+#[test]
+fn load_immediate() {
+    let code = [
+        Instruction::Lui {
+            rd: 5,
+            imm: 0x12345,
+        },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 5,
+            imm: 0x678,
+        },
+    ];
+    assert_eq!(
+        fusion::find(&code),
+        vec![Fusion {
+            start: 0,
+            kind: FusionKind::LoadImmediate
+        }]
+    );
+}
+
+#[test]
+fn load_immediate_mismatch() {
+    // addi reads a different register than lui wrote
+    let code = [
+        Instruction::Lui {
+            rd: 5,
+            imm: 0x12345,
+        },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 6,
+            imm: 0x678,
+        },
+    ];
+    assert!(fusion::find(&code).is_empty());
+}
+
+#[test]
+fn far_jump() {
+    // call: auipc ra, hi ; jalr ra, ra, lo
+    let code = [
+        Instruction::Auipc { rd: 1, imm: 0x10 },
+        Instruction::Jalr {
+            rd: 1,
+            rs1: 1,
+            imm: -16,
+        },
+        // tail: auipc t1, hi ; jalr x0, t1, lo
+        Instruction::Auipc { rd: 6, imm: 0x10 },
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 6,
+            imm: 8,
+        },
+    ];
+    let kinds: Vec<(usize, FusionKind)> = fusion::find(&code)
+        .iter()
+        .map(|fusion| (fusion.start, fusion.kind))
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![(0, FusionKind::FarJump), (2, FusionKind::FarJump)]
+    );
+}
+
+#[test]
+fn zero_extend() {
+    // zext.h a0, a1
+    let code = [
+        Instruction::Slli {
+            rd: 10,
+            rs1: 11,
+            shamt: 16,
+        },
+        Instruction::Srli {
+            rd: 10,
+            rs1: 10,
+            shamt: 16,
+        },
+    ];
+    assert_eq!(fusion::find(&code)[0].kind, FusionKind::ZeroExtend);
+    // Different shift amounts are not a zero-extension
+    let code = [
+        Instruction::Slli {
+            rd: 10,
+            rs1: 11,
+            shamt: 16,
+        },
+        Instruction::Srli {
+            rd: 10,
+            rs1: 10,
+            shamt: 8,
+        },
+    ];
+    assert!(fusion::find(&code).is_empty());
+}
+
+#[test]
+fn x0_ignored() {
+    let code = [
+        Instruction::Lui { rd: 0, imm: 1 },
+        Instruction::Addi {
+            rd: 0,
+            rs1: 0,
+            imm: 1,
+        },
+    ];
+    assert!(fusion::find(&code).is_empty());
+}
+
+#[test]
+fn non_overlapping() {
+    // lui a0 ; addi a0 ; addi a0 ; the second addi is not part of a pair
+    let code = [
+        Instruction::Addi {
+            rd: 1,
+            rs1: 0,
+            imm: 1,
+        },
+        Instruction::Lui { rd: 10, imm: 1 },
+        Instruction::Addi {
+            rd: 10,
+            rs1: 10,
+            imm: 1,
+        },
+        Instruction::Addi {
+            rd: 10,
+            rs1: 10,
+            imm: 1,
+        },
+    ];
+    assert_eq!(
+        fusion::find(&code),
+        vec![Fusion {
+            start: 1,
+            kind: FusionKind::LoadImmediate
+        }]
+    );
+}
+
+#[test]
+fn display() {
+    assert_eq!(FusionKind::LoadImmediate.to_string(), "lui+addi");
+    assert_eq!(FusionKind::FarJump.to_string(), "auipc+jalr");
+    assert_eq!(FusionKind::ZeroExtend.to_string(), "slli+srli");
+    assert_eq!(FusionKind::CompareBranch.to_string(), "slt+beq");
+}
+
+#[test]
+fn compare_branch() {
+    let compare = |signed: bool| {
+        let (rd, rs1, rs2) = (5, 6, 7);
+        if signed {
+            Instruction::Slt { rd, rs1, rs2 }
+        } else {
+            Instruction::Sltu { rd, rs1, rs2 }
+        }
+    };
+    for signed in [true, false] {
+        let code = [
+            compare(signed),
+            Instruction::Beq {
+                rs1: 5,
+                rs2: 0,
+                imm: 8,
+            },
+            compare(signed),
+            Instruction::Bne {
+                rs1: 5,
+                rs2: 0,
+                imm: -8,
+            },
+        ];
+        let starts: Vec<(usize, FusionKind)> = fusion::find(&code)
+            .iter()
+            .map(|fusion| (fusion.start, fusion.kind))
+            .collect();
+        assert_eq!(
+            starts,
+            [
+                (0, FusionKind::CompareBranch),
+                (2, FusionKind::CompareBranch)
+            ]
+        );
+    }
+}
+
+#[test]
+fn compare_branch_mismatch() {
+    // The branch tests another register, compares with a non-zero register,
+    // or the comparison writes x0
+    let code = [
+        Instruction::Slt {
+            rd: 5,
+            rs1: 6,
+            rs2: 7,
+        },
+        Instruction::Beq {
+            rs1: 6,
+            rs2: 0,
+            imm: 8,
+        },
+        Instruction::Sltu {
+            rd: 5,
+            rs1: 6,
+            rs2: 7,
+        },
+        Instruction::Bne {
+            rs1: 5,
+            rs2: 6,
+            imm: 8,
+        },
+        Instruction::Slt {
+            rd: 0,
+            rs1: 6,
+            rs2: 7,
+        },
+        Instruction::Bne {
+            rs1: 0,
+            rs2: 0,
+            imm: 8,
+        },
+    ];
+    assert!(fusion::find(&code).is_empty());
+}
@@ -0,0 +1,53 @@
+use crate::{Extension, Extensions, Instruction, generate};
+use arbitrary::{Arbitrary, Unstructured};
+
+/// Deterministic pseudo-random bytes
+fn bytes(seed: u32, length: usize) -> Vec<u8> {
+    let mut state = seed;
+    (0..length)
+        .map(|_| {
+            state = state.wrapping_mul(1664525).wrapping_add(1013904223);
+            (state >> 24) as u8
+        })
+        .collect()
+}
+
+#[test]
+fn encodings_roundtrip() {
+    let data = bytes(1, 1 << 20);
+    let mut u = Unstructured::new(&data);
+    for _ in 0..50_000 {
+        let word = generate::encoding(&mut u, &Extensions::all()).unwrap();
+        let instruction = Instruction::decode(word);
+        assert_eq!(instruction.encode(), Ok(word), "{}", instruction);
+        assert_eq!(instruction.to_string().parse(), Ok(instruction));
+    }
+}
+
+#[test]
+fn respects_extensions() {
+    let data = bytes(2, 1 << 16);
+    let mut u = Unstructured::new(&data);
+    for _ in 0..5_000 {
+        let instruction = generate::instruction(&mut u, &Extensions::base()).unwrap();
+        assert_eq!(instruction.extension(), Extension::I, "{}", instruction);
+    }
+}
+
+#[test]
+fn arbitrary() {
+    let data = bytes(3, 1 << 16);
+    let mut u = Unstructured::new(&data);
+    for _ in 0..5_000 {
+        let instruction = Instruction::arbitrary(&mut u).unwrap();
+        assert!(instruction.encode().is_ok(), "{}", instruction);
+    }
+}
+
+#[test]
+fn exhausted() {
+    // Running out of data still yields valid instructions
+    let mut u = Unstructured::new(&[]);
+    let instruction = Instruction::arbitrary(&mut u).unwrap();
+    assert!(instruction.encode().is_ok());
+}
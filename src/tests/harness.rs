@@ -0,0 +1,167 @@
+use crate::Instruction;
+use crate::harness::Harness;
+use crate::module::CompileError;
+#[cfg(target_arch = "aarch64")]
+use crate::{compiler::OptLevel, harness::Outcome};
+
+#[test]
+fn run_rejects_unencodable_instructions() {
+    let mut harness = Harness::new();
+    assert_eq!(
+        harness.run(&[Instruction::Addi {
+            rd: 5,
+            rs1: 0,
+            imm: 5000
+        }]),
+        Err(CompileError::InvalidCode)
+    );
+}
+
+#[test]
+fn run_rejects_invalid_jump_targets() {
+    let mut harness = Harness::new();
+    assert_eq!(
+        harness.run(&[
+            Instruction::Addi {
+                rd: 0,
+                rs1: 0,
+                imm: 0
+            },
+            Instruction::Jal { rd: 1, imm: -2 },
+        ]),
+        Err(CompileError::InvalidJumpTarget {
+            offset: 4,
+            target: 2
+        })
+    );
+}
+
+#[test]
+fn registers_carry_over() {
+    let mut harness = Harness::default();
+    harness.set_register(7, 0x1234);
+    harness.set_register(0, 99);
+    assert_eq!((harness.register(7), harness.register(0)), (0x1234, 0));
+}
+
+#[test]
+#[cfg(not(target_arch = "aarch64"))]
+fn run_needs_aarch64_host() {
+    let mut harness = Harness::new();
+    harness.set_register(5, 3);
+    let outcome = harness
+        .run(&[Instruction::Addi {
+            rd: 6,
+            rs1: 5,
+            imm: 1,
+        }])
+        .unwrap();
+    assert_eq!(outcome.result, Err("Compiled code needs an aarch64 host"));
+    assert_eq!((outcome.registers[5], outcome.registers[6]), (3, 0));
+    assert_eq!(outcome.pc, 0);
+}
+
+#[test]
+#[cfg(target_arch = "aarch64")]
+fn run_returns_registers() {
+    let mut harness = Harness::new();
+    harness.set_register(5, 40);
+    let outcome = harness
+        .run(&[
+            Instruction::Addi {
+                rd: 6,
+                rs1: 5,
+                imm: 2,
+            },
+            Instruction::Slli {
+                rd: 7,
+                rs1: 6,
+                shamt: 4,
+            },
+        ])
+        .unwrap();
+    assert_eq!(outcome.result, Ok(()));
+    assert_eq!(outcome.pc, 8);
+    assert_eq!(
+        (
+            outcome.registers[5],
+            outcome.registers[6],
+            outcome.registers[7]
+        ),
+        (40, 42, 42 << 4)
+    );
+    assert_eq!(harness.register(7), 42 << 4);
+}
+
+#[test]
+#[cfg(target_arch = "aarch64")]
+fn run_uses_scratch_memory() {
+    let mut harness = Harness::new();
+    harness.memory_mut().write_u32(0x2000, 7).unwrap();
+    harness.set_register(10, 0x2000);
+    let outcome = harness
+        .run(&[
+            Instruction::Lw {
+                rd: 11,
+                rs1: 10,
+                imm: 0,
+            },
+            Instruction::Addi {
+                rd: 11,
+                rs1: 11,
+                imm: 5,
+            },
+            Instruction::Sw {
+                rs1: 10,
+                rs2: 11,
+                imm: 4,
+            },
+        ])
+        .unwrap();
+    assert_eq!(outcome.result, Ok(()));
+    assert_eq!(outcome.registers[11], 12);
+    assert_eq!(harness.memory().read_u32(0x2004), 12);
+}
+
+#[test]
+#[cfg(target_arch = "aarch64")]
+fn opt_levels_agree() {
+    let code = [
+        Instruction::Addi {
+            rd: 5,
+            rs1: 0,
+            imm: 10,
+        },
+        Instruction::Addi {
+            rd: 6,
+            rs1: 5,
+            imm: 10,
+        },
+        Instruction::Add {
+            rd: 7,
+            rs1: 5,
+            rs2: 6,
+        },
+    ];
+    let outcomes: Vec<Outcome> = [OptLevel::O0, OptLevel::O1, OptLevel::O2]
+        .into_iter()
+        .map(|opt_level| {
+            let mut harness = Harness::new();
+            harness.set_opt_level(opt_level);
+            harness.run(&code).unwrap()
+        })
+        .collect();
+    assert_eq!(outcomes[0], outcomes[1]);
+    assert_eq!(outcomes[0], outcomes[2]);
+    assert_eq!(outcomes[0].registers[7], 30);
+}
+
+#[test]
+#[cfg(target_arch = "aarch64")]
+fn gas_stops_loops() {
+    let mut harness = Harness::new();
+    harness.set_gas(100);
+    let outcome = harness.run(&[Instruction::Jal { rd: 0, imm: 0 }]).unwrap();
+    assert_eq!(outcome.result, Err("Out of gas"));
+    assert_eq!(outcome.pc, 0);
+}
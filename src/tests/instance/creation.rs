@@ -1,35 +1,35 @@
 use crate::{
     instance::Instance,
-    memory::{Memory, PageStore},
+    memory::{Memory, MemoryError, PageStore},
     module::Module,
 };
 
 #[test]
 fn create_instance() {
-    let mut store = PageStore::new(100);
-    let memory = Memory::new(&mut store, 50, 10);
+    let store = PageStore::new(100);
+    let memory = Memory::new(&store, 50, 10);
     let instance = Instance::new(memory);
     assert!(!instance.attached());
 }
 
 #[test]
 fn attach_to_module() {
-    let mut store = PageStore::new(100);
-    let memory = Memory::new(&mut store, 50, 10);
+    let store = PageStore::new(100);
+    let memory = Memory::new(&store, 50, 10);
     let mut module = Module::new(1).unwrap();
     let mut instance = Instance::new(memory);
-    instance.attach(&mut module);
+    instance.attach(&mut module).unwrap();
     assert!(instance.attached());
     assert_eq!(module.instance_count, 1);
 }
 
 #[test]
 fn detach_from_module() {
-    let mut store = PageStore::new(100);
-    let memory = Memory::new(&mut store, 50, 10);
+    let store = PageStore::new(100);
+    let memory = Memory::new(&store, 50, 10);
     let mut module = Module::new(1).unwrap();
     let mut instance = Instance::new(memory);
-    instance.attach(&mut module);
+    instance.attach(&mut module).unwrap();
     assert_eq!(module.instance_count, 1);
     instance.detach();
     assert!(!instance.attached());
@@ -38,12 +38,12 @@ fn detach_from_module() {
 
 #[test]
 fn auto_detach_on_drop() {
-    let mut store = PageStore::new(100);
+    let store = PageStore::new(100);
     let mut module = Module::new(1).unwrap();
     {
-        let memory = Memory::new(&mut store, 50, 10);
+        let memory = Memory::new(&store, 50, 10);
         let mut instance = Instance::new(memory);
-        instance.attach(&mut module);
+        instance.attach(&mut module).unwrap();
         assert_eq!(module.instance_count, 1);
     }
     assert_eq!(module.instance_count, 0);
@@ -51,14 +51,14 @@ fn auto_detach_on_drop() {
 
 #[test]
 fn multiple_instances_same_module() {
-    let mut store = PageStore::new(100);
-    let memory1 = Memory::new(&mut store, 50, 10);
-    let memory2 = Memory::new(&mut store, 50, 10);
+    let store = PageStore::new(100);
+    let memory1 = Memory::new(&store, 50, 10);
+    let memory2 = Memory::new(&store, 50, 10);
     let mut module = Module::new(1).unwrap();
     let mut instance1 = Instance::new(memory1);
     let mut instance2 = Instance::new(memory2);
-    instance1.attach(&mut module);
-    instance2.attach(&mut module);
+    instance1.attach(&mut module).unwrap();
+    instance2.attach(&mut module).unwrap();
     assert_eq!(module.instance_count, 2);
     instance1.detach();
     assert_eq!(module.instance_count, 1);
@@ -68,25 +68,57 @@ fn multiple_instances_same_module() {
 
 #[test]
 fn reattach_to_different_module() {
-    let mut store = PageStore::new(100);
-    let memory = Memory::new(&mut store, 50, 10);
+    let store = PageStore::new(100);
+    let memory = Memory::new(&store, 50, 10);
     let mut module1 = Module::new(1).unwrap();
     let mut module2 = Module::new(1).unwrap();
     let mut instance = Instance::new(memory);
 
-    instance.attach(&mut module1);
+    instance.attach(&mut module1).unwrap();
     assert_eq!(module1.instance_count, 1);
     assert_eq!(module2.instance_count, 0);
 
-    instance.attach(&mut module2);
+    instance.attach(&mut module2).unwrap();
     assert_eq!(module1.instance_count, 0);
     assert_eq!(module2.instance_count, 1);
 }
 
+#[test]
+fn attach_writes_module_data() {
+    let store = PageStore::new(100);
+    let mut module = Module::new(1).unwrap();
+    module.set_data(vec![(0x1000, b"data".to_vec(), 4), (0x8000, vec![7; 8], 0)]);
+    let mut first = Instance::new(Memory::new(&store, 50, 10));
+    let mut second = Instance::new(Memory::new(&store, 50, 10));
+    first.attach(&mut module).unwrap();
+    second.attach(&mut module).unwrap();
+    assert_eq!(first.memory().read_array(0x1000), *b"data");
+    assert_eq!(first.memory().read_u64(0x8000), 0x0707_0707_0707_0707);
+
+    // Each instance has its own copy, restored by attaching again
+    first.memory_mut().write(0x1000, b"DATA-TAIL").unwrap();
+    assert_eq!(second.memory().read_array(0x1000), *b"data");
+    first.attach(&mut module).unwrap();
+    assert_eq!(first.memory().read_array(0x1000), *b"data\0\0\0\0L");
+    assert_eq!(module.instance_count, 2);
+}
+
+#[test]
+fn attach_data_error() {
+    let store = PageStore::new(100);
+    let mut module = Module::new(1).unwrap();
+    module.set_data(vec![(0x1000, vec![1; 4], 0), (0x100000, vec![2; 4], 0)]);
+    let mut instance = Instance::new(Memory::new(&store, 1, 10));
+    assert_eq!(instance.attach(&mut module), Err(MemoryError::PageLimit));
+    assert!(!instance.attached());
+    assert_eq!(module.instance_count, 0);
+    assert_eq!(instance.memory().read_u32(0x1000), 0x0101_0101);
+}
+
 #[test]
 fn detach_unattached() {
-    let mut store = PageStore::new(100);
-    let memory = Memory::new(&mut store, 50, 10);
+    let store = PageStore::new(100);
+    let memory = Memory::new(&store, 50, 10);
     let mut instance = Instance::new(memory);
     instance.detach(); // Should not panic
     assert!(!instance.attached());
@@ -94,8 +126,8 @@ fn detach_unattached() {
 
 #[test]
 fn memory_access() {
-    let mut store = PageStore::new(100);
-    let memory = Memory::new(&mut store, 50, 10);
+    let store = PageStore::new(100);
+    let memory = Memory::new(&store, 50, 10);
     let instance = Instance::new(memory);
     let mem_ref = instance.memory();
     assert_eq!(mem_ref.max_pages, 50);
@@ -103,10 +135,32 @@ fn memory_access() {
 
 #[test]
 fn memory_mut_access() {
-    let mut store = PageStore::new(100);
-    let memory = Memory::new(&mut store, 50, 10);
+    let store = PageStore::new(100);
+    let memory = Memory::new(&store, 50, 10);
     let mut instance = Instance::new(memory);
     let mem_mut = instance.memory_mut();
     let page_result = mem_mut.allocate_page(0);
-    assert_eq!(page_result, 0);
+    assert_eq!(page_result, Ok(()));
+}
+
+#[test]
+fn gas_defaults_to_unlimited() {
+    let store = PageStore::new(100);
+    let memory = Memory::new(&store, 50, 10);
+    let mut instance = Instance::new(memory);
+    assert_eq!(instance.gas(), u64::MAX);
+    instance.set_gas(100);
+    assert_eq!(instance.gas(), 100);
+}
+
+#[test]
+fn ecall_handler_set_and_taken() {
+    let store = PageStore::new(100);
+    let memory = Memory::new(&store, 50, 10);
+    let mut instance = Instance::new(memory);
+    assert!(instance.take_ecall_handler().is_none());
+    instance.set_ecall_handler(Box::new(|_| true));
+    let mut handler = instance.take_ecall_handler().unwrap();
+    assert!(handler(&mut instance));
+    assert!(instance.take_ecall_handler().is_none());
 }
@@ -1 +1,2 @@
 mod creation;
+mod registers;
@@ -0,0 +1,65 @@
+use crate::{
+    instance::Instance,
+    memory::{Memory, PageStore},
+};
+
+#[test]
+fn initial_zero() {
+    let store = PageStore::new(10);
+    let memory = Memory::new(&store, 5, 2);
+    let instance = Instance::new(memory);
+    for reg in 0..32 {
+        assert_eq!(instance.read_register(reg), 0);
+    }
+    assert_eq!(instance.pc(), 0);
+}
+
+#[test]
+fn write_and_read() {
+    let store = PageStore::new(10);
+    let memory = Memory::new(&store, 5, 2);
+    let mut instance = Instance::new(memory);
+    instance.write_register(5, 0xDEADBEEF);
+    assert_eq!(instance.read_register(5), 0xDEADBEEF);
+}
+
+#[test]
+fn all_registers_independent() {
+    let store = PageStore::new(10);
+    let memory = Memory::new(&store, 5, 2);
+    let mut instance = Instance::new(memory);
+    for reg in 1..32 {
+        instance.write_register(reg, reg as u32 * 100);
+    }
+    for reg in 1..32 {
+        assert_eq!(instance.read_register(reg), reg as u32 * 100);
+    }
+}
+
+#[test]
+fn x0_hardwired() {
+    let store = PageStore::new(10);
+    let memory = Memory::new(&store, 5, 2);
+    let mut instance = Instance::new(memory);
+    instance.write_register(0, 123);
+    assert_eq!(instance.read_register(0), 0);
+}
+
+#[test]
+fn out_of_range_ignored() {
+    let store = PageStore::new(10);
+    let memory = Memory::new(&store, 5, 2);
+    let mut instance = Instance::new(memory);
+    instance.write_register(32, 123);
+    assert_eq!(instance.read_register(32), 0);
+    assert_eq!(instance.read_register(255), 0);
+}
+
+#[test]
+fn set_pc() {
+    let store = PageStore::new(10);
+    let memory = Memory::new(&store, 5, 2);
+    let mut instance = Instance::new(memory);
+    instance.set_pc(0x1000);
+    assert_eq!(instance.pc(), 0x1000);
+}
@@ -0,0 +1,161 @@
+use crate::{EncodeError, Instruction};
+
+#[test]
+fn register() {
+    assert_eq!(
+        Instruction::add(1, 2, 3),
+        Ok(Instruction::Add {
+            rd: 1,
+            rs1: 2,
+            rs2: 3
+        })
+    );
+    assert_eq!(
+        Instruction::and(4, 5, 6),
+        Ok(Instruction::And {
+            rd: 4,
+            rs1: 5,
+            rs2: 6
+        })
+    );
+    assert_eq!(
+        Instruction::remu(31, 0, 31),
+        Ok(Instruction::Remu {
+            rd: 31,
+            rs1: 0,
+            rs2: 31
+        })
+    );
+}
+
+#[test]
+fn invalid_register() {
+    assert_eq!(
+        Instruction::sub(32, 0, 0),
+        Err(EncodeError::InvalidRegister("rd", 32))
+    );
+    assert_eq!(
+        Instruction::mul(0, 40, 0),
+        Err(EncodeError::InvalidRegister("rs1", 40))
+    );
+    assert_eq!(
+        Instruction::sw(0, 255, 0),
+        Err(EncodeError::InvalidRegister("rs2", 255))
+    );
+}
+
+#[test]
+fn immediate() {
+    assert_eq!(
+        Instruction::addi(1, 2, -2048),
+        Ok(Instruction::Addi {
+            rd: 1,
+            rs1: 2,
+            imm: -2048
+        })
+    );
+    assert_eq!(
+        Instruction::lw(1, 2, 2047),
+        Ok(Instruction::Lw {
+            rd: 1,
+            rs1: 2,
+            imm: 2047
+        })
+    );
+    assert_eq!(
+        Instruction::addi(1, 2, 2048),
+        Err(EncodeError::InvalidImmediate("imm", 2048))
+    );
+    assert_eq!(
+        Instruction::jalr(1, 2, -2049),
+        Err(EncodeError::InvalidImmediate("imm", -2049))
+    );
+}
+
+#[test]
+fn shift() {
+    assert_eq!(
+        Instruction::slli(1, 2, 31),
+        Ok(Instruction::Slli {
+            rd: 1,
+            rs1: 2,
+            shamt: 31
+        })
+    );
+    assert_eq!(
+        Instruction::srai(1, 2, 32),
+        Err(EncodeError::InvalidImmediate("shamt", 32))
+    );
+}
+
+#[test]
+fn store() {
+    assert_eq!(
+        Instruction::sb(1, 2, -1),
+        Ok(Instruction::Sb {
+            rs1: 1,
+            rs2: 2,
+            imm: -1
+        })
+    );
+    assert_eq!(
+        Instruction::sh(1, 2, 4096),
+        Err(EncodeError::InvalidImmediate("imm", 4096))
+    );
+}
+
+#[test]
+fn branch() {
+    assert_eq!(
+        Instruction::beq(1, 2, -4096),
+        Ok(Instruction::Beq {
+            rs1: 1,
+            rs2: 2,
+            imm: -4096
+        })
+    );
+    assert_eq!(
+        Instruction::bne(1, 2, 3),
+        Err(EncodeError::InvalidImmediate("imm", 3))
+    );
+    assert_eq!(
+        Instruction::bgeu(1, 2, 4096),
+        Err(EncodeError::InvalidImmediate("imm", 4096))
+    );
+}
+
+#[test]
+fn jump() {
+    assert_eq!(
+        Instruction::jal(1, 1048574),
+        Ok(Instruction::Jal {
+            rd: 1,
+            imm: 1048574
+        })
+    );
+    assert_eq!(
+        Instruction::jal(1, 1),
+        Err(EncodeError::InvalidImmediate("imm", 1))
+    );
+}
+
+#[test]
+fn upper() {
+    assert_eq!(
+        Instruction::lui(1, 0xFFFFF),
+        Ok(Instruction::Lui {
+            rd: 1,
+            imm: 0xFFFFF
+        })
+    );
+    assert_eq!(
+        Instruction::auipc(1, 0x100000),
+        Err(EncodeError::InvalidImmediate("imm", 0x100000))
+    );
+}
+
+#[test]
+fn system() {
+    assert_eq!(Instruction::ecall(), Instruction::Ecall);
+    assert_eq!(Instruction::ebreak(), Instruction::Ebreak);
+}
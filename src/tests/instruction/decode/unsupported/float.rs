@@ -0,0 +1,22 @@
+use crate::Instruction;
+
+#[test]
+fn reserved_rounding_mode() {
+    // fadd.h f1, f2, f3 with rm=5
+    let word = 0x043150D3;
+    assert_eq!(Instruction::decode(word), Instruction::Unsupported(word));
+}
+
+#[test]
+fn single_precision_arithmetic() {
+    // fadd.s f1, f2, f3 is not decoded
+    let word = 0x003170D3;
+    assert_eq!(Instruction::decode(word), Instruction::Unsupported(word));
+}
+
+#[test]
+fn conversion_invalid_rs2() {
+    // fcvt.w.h with rs2=3 is not a valid conversion
+    let word = 0xC43170D3;
+    assert_eq!(Instruction::decode(word), Instruction::Unsupported(word));
+}
@@ -1,4 +1,5 @@
 mod branch;
+mod float;
 mod general;
 mod immediate;
 mod jump;
@@ -6,3 +7,4 @@ mod load;
 mod register;
 mod store;
 mod system;
+mod vector;
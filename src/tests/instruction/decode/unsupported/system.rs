@@ -61,3 +61,17 @@ fn ebreak_invalid_with_nonzero_funct3() {
     let instruction = Instruction::decode(instruction_word);
     assert_eq!(instruction, Instruction::Unsupported(instruction_word));
 }
+
+#[test]
+fn csr_read_other_than_counter() {
+    // csrrs x5, mcycle, x0
+    let word = 0xB00022F3;
+    assert_eq!(Instruction::decode(word), Instruction::Unsupported(word));
+}
+
+#[test]
+fn counter_with_nonzero_rs1() {
+    // csrrs x5, cycle, x1 writes the counter and is not a plain read
+    let word = 0xC000A2F3;
+    assert_eq!(Instruction::decode(word), Instruction::Unsupported(word));
+}
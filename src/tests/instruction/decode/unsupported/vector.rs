@@ -0,0 +1,36 @@
+use crate::Instruction;
+
+#[test]
+fn strided_load() {
+    // vlse32.v v1, (x2), x3 uses mop=0b10, which is not decoded
+    let word = 0x0A316087;
+    assert_eq!(Instruction::decode(word), Instruction::Unsupported(word));
+}
+
+#[test]
+fn segment_load() {
+    // vlseg2e32.v v1, (x2) uses nf=1, which is not decoded
+    let word = 0x22016087;
+    assert_eq!(Instruction::decode(word), Instruction::Unsupported(word));
+}
+
+#[test]
+fn scalar_float_load() {
+    // flw f1, 0(x2) shares the LOAD-FP opcode with width funct3=0x2
+    let word = 0x00012087;
+    assert_eq!(Instruction::decode(word), Instruction::Unsupported(word));
+}
+
+#[test]
+fn vsetivli() {
+    // vsetivli x1, 4, e32, m1, ta, ma has bits 31:30 = 0b11
+    let word = 0xCD0270D7;
+    assert_eq!(Instruction::decode(word), Instruction::Unsupported(word));
+}
+
+#[test]
+fn other_funct6() {
+    // vsub.vv v1, v2, v3 has funct6=0b000010
+    let word = 0x0A2180D7;
+    assert_eq!(Instruction::decode(word), Instruction::Unsupported(word));
+}
@@ -0,0 +1,47 @@
+use crate::instruction::Instruction;
+
+#[test]
+fn dynamic_rounding() {
+    let instruction = Instruction::FaddH {
+        rd: 1,
+        rs1: 2,
+        rs2: 3,
+        rm: 7,
+    };
+    assert_eq!(format!("{}", instruction), "fadd.h f1, f2, f3");
+}
+
+#[test]
+fn explicit_rounding() {
+    let names = ["rne", "rtz", "rdn", "rup", "rmm"];
+    for (rm, name) in names.iter().enumerate() {
+        let instruction = Instruction::FmulH {
+            rd: 1,
+            rs1: 2,
+            rs2: 3,
+            rm: rm as u8,
+        };
+        assert_eq!(
+            format!("{}", instruction),
+            format!("fmul.h f1, f2, f3, {}", name)
+        );
+    }
+}
+
+#[test]
+fn fsub_and_fdiv() {
+    let fsub = Instruction::FsubH {
+        rd: 4,
+        rs1: 5,
+        rs2: 6,
+        rm: 7,
+    };
+    let fdiv = Instruction::FdivH {
+        rd: 4,
+        rs1: 5,
+        rs2: 6,
+        rm: 3,
+    };
+    assert_eq!(format!("{}", fsub), "fsub.h f4, f5, f6");
+    assert_eq!(format!("{}", fdiv), "fdiv.h f4, f5, f6, rup");
+}
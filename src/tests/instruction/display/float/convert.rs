@@ -0,0 +1,49 @@
+use crate::instruction::Instruction;
+
+#[test]
+fn to_integer() {
+    let signed = Instruction::FcvtWH {
+        rd: 1,
+        rs1: 2,
+        rm: 1,
+    };
+    let unsigned = Instruction::FcvtWuH {
+        rd: 1,
+        rs1: 2,
+        rm: 7,
+    };
+    assert_eq!(format!("{}", signed), "fcvt.w.h x1, f2, rtz");
+    assert_eq!(format!("{}", unsigned), "fcvt.wu.h x1, f2");
+}
+
+#[test]
+fn from_integer() {
+    let signed = Instruction::FcvtHW {
+        rd: 1,
+        rs1: 2,
+        rm: 7,
+    };
+    let unsigned = Instruction::FcvtHWu {
+        rd: 1,
+        rs1: 2,
+        rm: 7,
+    };
+    assert_eq!(format!("{}", signed), "fcvt.h.w f1, x2");
+    assert_eq!(format!("{}", unsigned), "fcvt.h.wu f1, x2");
+}
+
+#[test]
+fn precision() {
+    let widen = Instruction::FcvtSH {
+        rd: 1,
+        rs1: 2,
+        rm: 0,
+    };
+    let narrow = Instruction::FcvtHS {
+        rd: 1,
+        rs1: 2,
+        rm: 7,
+    };
+    assert_eq!(format!("{}", widen), "fcvt.s.h f1, f2, rne");
+    assert_eq!(format!("{}", narrow), "fcvt.h.s f1, f2");
+}
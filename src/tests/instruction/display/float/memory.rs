@@ -0,0 +1,21 @@
+use crate::instruction::Instruction;
+
+#[test]
+fn flh() {
+    let instruction = Instruction::Flh {
+        rd: 1,
+        rs1: 2,
+        imm: -8,
+    };
+    assert_eq!(format!("{}", instruction), "flh f1, -8(x2)");
+}
+
+#[test]
+fn fsh() {
+    let instruction = Instruction::Fsh {
+        rs1: 5,
+        rs2: 3,
+        imm: 4,
+    };
+    assert_eq!(format!("{}", instruction), "fsh f3, 4(x5)");
+}
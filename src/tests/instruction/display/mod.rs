@@ -1,4 +1,5 @@
 mod branch;
+mod float;
 mod immediate;
 mod jump;
 mod load;
@@ -8,3 +9,4 @@ mod store;
 mod system;
 mod unsupported;
 mod utype;
+mod vector;
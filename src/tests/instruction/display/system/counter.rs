@@ -0,0 +1,11 @@
+use crate::instruction::Instruction;
+
+#[test]
+fn basic() {
+    assert_eq!(format!("{}", Instruction::Rdcycle { rd: 5 }), "rdcycle x5");
+    assert_eq!(format!("{}", Instruction::Rdtimeh { rd: 1 }), "rdtimeh x1");
+    assert_eq!(
+        format!("{}", Instruction::Rdinstret { rd: 31 }),
+        "rdinstret x31"
+    );
+}
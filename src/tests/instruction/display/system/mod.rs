@@ -1,2 +1,3 @@
+mod counter;
 mod ebreak;
 mod ecall;
@@ -0,0 +1,4 @@
+mod vadd;
+mod vle;
+mod vse;
+mod vsetvli;
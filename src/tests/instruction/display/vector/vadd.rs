@@ -0,0 +1,34 @@
+use crate::instruction::Instruction;
+
+#[test]
+fn vv() {
+    let instruction = Instruction::VaddVv {
+        vd: 1,
+        vs2: 2,
+        vs1: 3,
+        vm: true,
+    };
+    assert_eq!(format!("{}", instruction), "vadd.vv v1, v2, v3");
+}
+
+#[test]
+fn vx_masked() {
+    let instruction = Instruction::VaddVx {
+        vd: 1,
+        vs2: 2,
+        rs1: 3,
+        vm: false,
+    };
+    assert_eq!(format!("{}", instruction), "vadd.vx v1, v2, x3, v0.t");
+}
+
+#[test]
+fn vi() {
+    let instruction = Instruction::VaddVi {
+        vd: 1,
+        vs2: 2,
+        imm: -16,
+        vm: true,
+    };
+    assert_eq!(format!("{}", instruction), "vadd.vi v1, v2, -16");
+}
@@ -0,0 +1,23 @@
+use crate::instruction::Instruction;
+
+#[test]
+fn basic() {
+    let instruction = Instruction::Vle {
+        vd: 1,
+        rs1: 2,
+        width: 32,
+        vm: true,
+    };
+    assert_eq!(format!("{}", instruction), "vle32.v v1, (x2)");
+}
+
+#[test]
+fn masked() {
+    let instruction = Instruction::Vle {
+        vd: 4,
+        rs1: 10,
+        width: 8,
+        vm: false,
+    };
+    assert_eq!(format!("{}", instruction), "vle8.v v4, (x10), v0.t");
+}
@@ -0,0 +1,23 @@
+use crate::instruction::Instruction;
+
+#[test]
+fn basic() {
+    let instruction = Instruction::Vse {
+        vs3: 3,
+        rs1: 5,
+        width: 64,
+        vm: true,
+    };
+    assert_eq!(format!("{}", instruction), "vse64.v v3, (x5)");
+}
+
+#[test]
+fn masked() {
+    let instruction = Instruction::Vse {
+        vs3: 1,
+        rs1: 2,
+        width: 16,
+        vm: false,
+    };
+    assert_eq!(format!("{}", instruction), "vse16.v v1, (x2), v0.t");
+}
@@ -0,0 +1,60 @@
+use crate::instruction::Instruction;
+
+#[test]
+fn basic() {
+    let instruction = Instruction::Vsetvli {
+        rd: 1,
+        rs1: 2,
+        vtypei: 0xD0,
+    };
+    assert_eq!(
+        format!("{}", instruction),
+        "vsetvli x1, x2, e32, m1, ta, ma"
+    );
+}
+
+#[test]
+fn fractional_lmul() {
+    let instruction = Instruction::Vsetvli {
+        rd: 0,
+        rs1: 10,
+        vtypei: 0x07,
+    };
+    assert_eq!(
+        format!("{}", instruction),
+        "vsetvli x0, x10, e8, mf2, tu, mu"
+    );
+}
+
+#[test]
+fn e64_m8() {
+    let instruction = Instruction::Vsetvli {
+        rd: 5,
+        rs1: 6,
+        vtypei: 0x5B,
+    };
+    assert_eq!(
+        format!("{}", instruction),
+        "vsetvli x5, x6, e64, m8, ta, mu"
+    );
+}
+
+#[test]
+fn reserved_lmul() {
+    let instruction = Instruction::Vsetvli {
+        rd: 1,
+        rs1: 2,
+        vtypei: 0x04,
+    };
+    assert_eq!(format!("{}", instruction), "vsetvli x1, x2, 4");
+}
+
+#[test]
+fn reserved_bits() {
+    let instruction = Instruction::Vsetvli {
+        rd: 1,
+        rs1: 2,
+        vtypei: 0x100,
+    };
+    assert_eq!(format!("{}", instruction), "vsetvli x1, x2, 256");
+}
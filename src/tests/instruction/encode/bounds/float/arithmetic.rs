@@ -0,0 +1,39 @@
+use crate::{EncodeError, Instruction};
+
+#[test]
+fn invalid_register() {
+    let instr = Instruction::FaddH {
+        rd: 1,
+        rs1: 2,
+        rs2: 40,
+        rm: 7,
+    };
+    assert_eq!(instr.encode(), Err(EncodeError::InvalidRegister("rs2", 40)));
+}
+
+#[test]
+fn reserved_rounding_mode() {
+    for rm in [5, 6] {
+        let instr = Instruction::FsubH {
+            rd: 1,
+            rs1: 2,
+            rs2: 3,
+            rm,
+        };
+        assert_eq!(
+            instr.encode(),
+            Err(EncodeError::InvalidImmediate("rm", rm as i32))
+        );
+    }
+}
+
+#[test]
+fn rounding_mode_too_large() {
+    let instr = Instruction::FmulH {
+        rd: 1,
+        rs1: 2,
+        rs2: 3,
+        rm: 8,
+    };
+    assert_eq!(instr.encode(), Err(EncodeError::InvalidImmediate("rm", 8)));
+}
@@ -0,0 +1,31 @@
+use crate::{EncodeError, Instruction};
+
+#[test]
+fn invalid_rd() {
+    let instr = Instruction::FcvtWH {
+        rd: 32,
+        rs1: 2,
+        rm: 7,
+    };
+    assert_eq!(instr.encode(), Err(EncodeError::InvalidRegister("rd", 32)));
+}
+
+#[test]
+fn invalid_rs1() {
+    let instr = Instruction::FcvtHW {
+        rd: 1,
+        rs1: 32,
+        rm: 7,
+    };
+    assert_eq!(instr.encode(), Err(EncodeError::InvalidRegister("rs1", 32)));
+}
+
+#[test]
+fn reserved_rounding_mode() {
+    let instr = Instruction::FcvtHS {
+        rd: 1,
+        rs1: 2,
+        rm: 5,
+    };
+    assert_eq!(instr.encode(), Err(EncodeError::InvalidImmediate("rm", 5)));
+}
@@ -0,0 +1,34 @@
+use crate::{EncodeError, Instruction};
+
+#[test]
+fn flh_invalid_rd() {
+    let instr = Instruction::Flh {
+        rd: 32,
+        rs1: 2,
+        imm: 0,
+    };
+    assert_eq!(instr.encode(), Err(EncodeError::InvalidRegister("rd", 32)));
+}
+
+#[test]
+fn flh_invalid_immediate() {
+    let instr = Instruction::Flh {
+        rd: 1,
+        rs1: 2,
+        imm: 2048,
+    };
+    assert_eq!(
+        instr.encode(),
+        Err(EncodeError::InvalidImmediate("imm", 2048))
+    );
+}
+
+#[test]
+fn fsh_invalid_rs2() {
+    let instr = Instruction::Fsh {
+        rs1: 1,
+        rs2: 32,
+        imm: 0,
+    };
+    assert_eq!(instr.encode(), Err(EncodeError::InvalidRegister("rs2", 32)));
+}
@@ -1,4 +1,5 @@
 mod branch;
+mod float;
 mod immediate;
 mod jump;
 mod load;
@@ -6,3 +7,4 @@ mod multiply;
 mod register;
 mod store;
 mod utype;
+mod vector;
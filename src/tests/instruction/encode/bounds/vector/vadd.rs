@@ -0,0 +1,73 @@
+use crate::{EncodeError, Instruction};
+
+#[test]
+fn invalid_vd() {
+    let instr = Instruction::VaddVv {
+        vd: 32,
+        vs2: 2,
+        vs1: 3,
+        vm: true,
+    };
+    assert_eq!(instr.encode(), Err(EncodeError::InvalidRegister("vd", 32)));
+}
+
+#[test]
+fn invalid_vs1() {
+    let instr = Instruction::VaddVv {
+        vd: 1,
+        vs2: 2,
+        vs1: 32,
+        vm: true,
+    };
+    assert_eq!(instr.encode(), Err(EncodeError::InvalidRegister("vs1", 32)));
+}
+
+#[test]
+fn invalid_vs2() {
+    let instr = Instruction::VaddVx {
+        vd: 1,
+        vs2: 32,
+        rs1: 3,
+        vm: true,
+    };
+    assert_eq!(instr.encode(), Err(EncodeError::InvalidRegister("vs2", 32)));
+}
+
+#[test]
+fn invalid_rs1() {
+    let instr = Instruction::VaddVx {
+        vd: 1,
+        vs2: 2,
+        rs1: 32,
+        vm: true,
+    };
+    assert_eq!(instr.encode(), Err(EncodeError::InvalidRegister("rs1", 32)));
+}
+
+#[test]
+fn immediate_too_small() {
+    let instr = Instruction::VaddVi {
+        vd: 1,
+        vs2: 2,
+        imm: -17,
+        vm: true,
+    };
+    assert_eq!(
+        instr.encode(),
+        Err(EncodeError::InvalidImmediate("imm", -17))
+    );
+}
+
+#[test]
+fn immediate_too_large() {
+    let instr = Instruction::VaddVi {
+        vd: 1,
+        vs2: 2,
+        imm: 16,
+        vm: true,
+    };
+    assert_eq!(
+        instr.encode(),
+        Err(EncodeError::InvalidImmediate("imm", 16))
+    );
+}
@@ -0,0 +1,37 @@
+use crate::{EncodeError, Instruction};
+
+#[test]
+fn invalid_vd() {
+    let instr = Instruction::Vle {
+        vd: 32,
+        rs1: 2,
+        width: 32,
+        vm: true,
+    };
+    assert_eq!(instr.encode(), Err(EncodeError::InvalidRegister("vd", 32)));
+}
+
+#[test]
+fn invalid_rs1() {
+    let instr = Instruction::Vle {
+        vd: 1,
+        rs1: 32,
+        width: 32,
+        vm: true,
+    };
+    assert_eq!(instr.encode(), Err(EncodeError::InvalidRegister("rs1", 32)));
+}
+
+#[test]
+fn invalid_width() {
+    let instr = Instruction::Vle {
+        vd: 1,
+        rs1: 2,
+        width: 24,
+        vm: true,
+    };
+    assert_eq!(
+        instr.encode(),
+        Err(EncodeError::InvalidImmediate("width", 24))
+    );
+}
@@ -0,0 +1,26 @@
+use crate::{EncodeError, Instruction};
+
+#[test]
+fn invalid_vs3() {
+    let instr = Instruction::Vse {
+        vs3: 40,
+        rs1: 2,
+        width: 8,
+        vm: true,
+    };
+    assert_eq!(instr.encode(), Err(EncodeError::InvalidRegister("vs3", 40)));
+}
+
+#[test]
+fn invalid_width() {
+    let instr = Instruction::Vse {
+        vs3: 1,
+        rs1: 2,
+        width: 0,
+        vm: true,
+    };
+    assert_eq!(
+        instr.encode(),
+        Err(EncodeError::InvalidImmediate("width", 0))
+    );
+}
@@ -0,0 +1,24 @@
+use crate::{EncodeError, Instruction};
+
+#[test]
+fn invalid_rd() {
+    let instr = Instruction::Vsetvli {
+        rd: 32,
+        rs1: 2,
+        vtypei: 0,
+    };
+    assert_eq!(instr.encode(), Err(EncodeError::InvalidRegister("rd", 32)));
+}
+
+#[test]
+fn invalid_vtypei() {
+    let instr = Instruction::Vsetvli {
+        rd: 1,
+        rs1: 2,
+        vtypei: 0x800,
+    };
+    assert_eq!(
+        instr.encode(),
+        Err(EncodeError::InvalidImmediate("vtypei", 0x800))
+    );
+}
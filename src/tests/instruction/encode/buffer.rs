@@ -0,0 +1,46 @@
+use crate::{EncodeError, Instruction};
+
+#[test]
+fn encode_to() {
+    let mut buffer = [0xAA; 6];
+    Instruction::Ecall.encode_to(&mut buffer).unwrap();
+    assert_eq!(buffer, [0x73, 0x00, 0x00, 0x00, 0xAA, 0xAA]);
+}
+
+#[test]
+fn too_small() {
+    let mut buffer = [0; 3];
+    assert_eq!(
+        Instruction::Ecall.encode_to(&mut buffer),
+        Err(EncodeError::BufferTooSmall(3))
+    );
+    assert_eq!(buffer, [0; 3]);
+}
+
+#[test]
+fn invalid() {
+    let mut buffer = [0; 4];
+    assert_eq!(
+        Instruction::Unsupported(0).encode_to(&mut buffer),
+        Err(EncodeError::NotImplemented("Unsupported"))
+    );
+}
+
+#[test]
+fn encode_all() {
+    let program = [Instruction::addi(10, 0, 42).unwrap(), Instruction::Ecall];
+    assert_eq!(
+        Instruction::encode_all(&program),
+        Ok(vec![0x13, 0x05, 0xA0, 0x02, 0x73, 0x00, 0x00, 0x00])
+    );
+    assert_eq!(Instruction::encode_all(&[]), Ok(Vec::new()));
+}
+
+#[test]
+fn encode_all_error() {
+    let program = [Instruction::Ecall, Instruction::Unsupported(0)];
+    assert_eq!(
+        Instruction::encode_all(&program),
+        Err(EncodeError::NotImplemented("Unsupported"))
+    );
+}
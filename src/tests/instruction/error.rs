@@ -48,3 +48,18 @@ fn via_instruction() {
         _ => panic!("Expected NotImplemented error for Unsupported instruction"),
     }
 }
+
+#[test]
+fn display_buffer_too_small() {
+    let error = EncodeError::BufferTooSmall(2);
+    assert_eq!(
+        error.to_string(),
+        "Buffer too small for instruction: 2 bytes"
+    );
+}
+
+#[test]
+fn display_not_relocatable() {
+    let error = EncodeError::NotRelocatable;
+    assert_eq!(error.to_string(), "Only branches and JAL can be relocated");
+}
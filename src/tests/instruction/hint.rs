@@ -0,0 +1,218 @@
+use crate::Instruction;
+
+#[test]
+fn canonical_nop() {
+    let instr = Instruction::decode(0x00000013);
+    assert!(instr.nop());
+    assert!(!instr.hint());
+}
+
+#[test]
+fn addi_nonzero_not_nop() {
+    let instr = Instruction::Addi {
+        rd: 0,
+        rs1: 0,
+        imm: 1,
+    };
+    assert!(!instr.nop());
+    assert!(instr.hint());
+}
+
+#[test]
+fn addi_nonzero_source_hint() {
+    let instr = Instruction::Addi {
+        rd: 0,
+        rs1: 5,
+        imm: 0,
+    };
+    assert!(!instr.nop());
+    assert!(instr.hint());
+}
+
+#[test]
+fn addi_nonzero_destination() {
+    let instr = Instruction::Addi {
+        rd: 1,
+        rs1: 0,
+        imm: 0,
+    };
+    assert!(!instr.nop());
+    assert!(!instr.hint());
+}
+
+#[test]
+fn register_hints() {
+    let hints = [
+        Instruction::Add {
+            rd: 0,
+            rs1: 1,
+            rs2: 2,
+        },
+        Instruction::Sub {
+            rd: 0,
+            rs1: 1,
+            rs2: 2,
+        },
+        Instruction::Sll {
+            rd: 0,
+            rs1: 1,
+            rs2: 2,
+        },
+        Instruction::Xor {
+            rd: 0,
+            rs1: 1,
+            rs2: 2,
+        },
+        Instruction::Or {
+            rd: 0,
+            rs1: 1,
+            rs2: 2,
+        },
+        Instruction::Srl {
+            rd: 0,
+            rs1: 1,
+            rs2: 2,
+        },
+        Instruction::Sra {
+            rd: 0,
+            rs1: 1,
+            rs2: 2,
+        },
+        Instruction::Slt {
+            rd: 0,
+            rs1: 1,
+            rs2: 2,
+        },
+        Instruction::Sltu {
+            rd: 0,
+            rs1: 1,
+            rs2: 2,
+        },
+        Instruction::And {
+            rd: 0,
+            rs1: 1,
+            rs2: 2,
+        },
+    ];
+    for instr in hints {
+        assert!(instr.hint(), "{} should be a hint", instr);
+        assert!(!instr.nop());
+    }
+}
+
+#[test]
+fn immediate_hints() {
+    let hints = [
+        Instruction::Slti {
+            rd: 0,
+            rs1: 1,
+            imm: 3,
+        },
+        Instruction::Sltiu {
+            rd: 0,
+            rs1: 1,
+            imm: 3,
+        },
+        Instruction::Xori {
+            rd: 0,
+            rs1: 1,
+            imm: 3,
+        },
+        Instruction::Ori {
+            rd: 0,
+            rs1: 1,
+            imm: 3,
+        },
+        Instruction::Andi {
+            rd: 0,
+            rs1: 1,
+            imm: 3,
+        },
+        Instruction::Slli {
+            rd: 0,
+            rs1: 1,
+            shamt: 3,
+        },
+        Instruction::Srli {
+            rd: 0,
+            rs1: 1,
+            shamt: 3,
+        },
+        Instruction::Srai {
+            rd: 0,
+            rs1: 1,
+            shamt: 3,
+        },
+    ];
+    for instr in hints {
+        assert!(instr.hint(), "{} should be a hint", instr);
+    }
+}
+
+#[test]
+fn upper_immediate_hints() {
+    assert!(
+        Instruction::Lui {
+            rd: 0,
+            imm: 0x12345
+        }
+        .hint()
+    );
+    assert!(
+        Instruction::Auipc {
+            rd: 0,
+            imm: 0x12345
+        }
+        .hint()
+    );
+}
+
+#[test]
+fn nonzero_destination_not_hint() {
+    let instrs = [
+        Instruction::Add {
+            rd: 1,
+            rs1: 1,
+            rs2: 2,
+        },
+        Instruction::Xori {
+            rd: 1,
+            rs1: 1,
+            imm: 3,
+        },
+        Instruction::Lui {
+            rd: 1,
+            imm: 0x12345,
+        },
+    ];
+    for instr in instrs {
+        assert!(!instr.hint(), "{} should not be a hint", instr);
+    }
+}
+
+#[test]
+fn loads_not_hint() {
+    let instr = Instruction::Lw {
+        rd: 0,
+        rs1: 1,
+        imm: 0,
+    };
+    assert!(!instr.hint());
+}
+
+#[test]
+fn multiply_not_hint() {
+    let instr = Instruction::Mul {
+        rd: 0,
+        rs1: 1,
+        rs2: 2,
+    };
+    assert!(!instr.hint());
+}
+
+#[test]
+fn control_flow_not_hint() {
+    assert!(!Instruction::Jal { rd: 0, imm: 8 }.hint());
+    assert!(!Instruction::Ecall.hint());
+    assert!(!Instruction::Unsupported(0).hint());
+}
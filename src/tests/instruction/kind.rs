@@ -0,0 +1,92 @@
+use crate::{Instruction, InstructionKind};
+
+#[test]
+fn integer() {
+    // add, lui, slli
+    assert_eq!(
+        Instruction::decode(0x003100B3).kind(),
+        InstructionKind::Arithmetic
+    );
+    assert_eq!(
+        Instruction::decode(0x123452B7).kind(),
+        InstructionKind::Arithmetic
+    );
+    assert_eq!(
+        Instruction::decode(0x00511093).kind(),
+        InstructionKind::Arithmetic
+    );
+    // mul, remu
+    assert_eq!(Instruction::decode(0x023100B3).kind(), InstructionKind::Mul);
+    assert_eq!(Instruction::decode(0x023170B3).kind(), InstructionKind::Div);
+}
+
+#[test]
+fn memory() {
+    // lw, sw
+    assert_eq!(
+        Instruction::decode(0x00832283).kind(),
+        InstructionKind::Load
+    );
+    assert_eq!(
+        Instruction::decode(0x00742223).kind(),
+        InstructionKind::Store
+    );
+    let load = Instruction::Flh {
+        rd: 1,
+        rs1: 2,
+        imm: 0,
+    };
+    assert_eq!(load.kind(), InstructionKind::Load);
+    let store = Instruction::Vse {
+        vs3: 1,
+        rs1: 2,
+        width: 8,
+        vm: true,
+    };
+    assert_eq!(store.kind(), InstructionKind::Store);
+}
+
+#[test]
+fn control() {
+    // beq, jal, jalr, ecall, ebreak
+    assert_eq!(
+        Instruction::decode(0xFE208CE3).kind(),
+        InstructionKind::Branch
+    );
+    assert_eq!(
+        Instruction::decode(0x010000EF).kind(),
+        InstructionKind::Jump
+    );
+    assert_eq!(
+        Instruction::decode(0x00008067).kind(),
+        InstructionKind::Jump
+    );
+    assert_eq!(Instruction::Ecall.kind(), InstructionKind::System);
+    assert_eq!(Instruction::Ebreak.kind(), InstructionKind::System);
+}
+
+#[test]
+fn extensions() {
+    let vector = Instruction::Vsetvli {
+        rd: 1,
+        rs1: 2,
+        vtypei: 0,
+    };
+    assert_eq!(vector.kind(), InstructionKind::Vector);
+    let float = Instruction::FcvtSH {
+        rd: 1,
+        rs1: 2,
+        rm: 7,
+    };
+    assert_eq!(float.kind(), InstructionKind::Float);
+    assert_eq!(
+        Instruction::Unsupported(0).kind(),
+        InstructionKind::Unsupported
+    );
+}
+
+#[test]
+fn display() {
+    assert_eq!(InstructionKind::Arithmetic.to_string(), "arithmetic");
+    assert_eq!(InstructionKind::Div.to_string(), "div");
+}
@@ -1,7 +1,12 @@
+mod constructor;
 mod decode;
 mod display;
 mod encode;
 mod error;
+mod hint;
+mod kind;
+mod operand;
+mod relocation;
 mod roundtrip;
 
 use crate::Instruction;
@@ -0,0 +1,115 @@
+use crate::{Instruction, Register};
+
+/// Collect the integer (rd, rs1, rs2) and imm for an instruction word
+fn operands(word: u32) -> (Option<u8>, Option<u8>, Option<u8>, Option<i32>) {
+    let instruction = Instruction::decode(word);
+    let integer = |register: Option<Register>| register.and_then(Register::integer);
+    (
+        integer(instruction.rd()),
+        integer(instruction.rs1()),
+        integer(instruction.rs2()),
+        instruction.imm(),
+    )
+}
+
+#[test]
+fn register() {
+    // add x1, x2, x3
+    assert_eq!(operands(0x003100B3), (Some(1), Some(2), Some(3), None));
+    // mul x1, x2, x3
+    assert_eq!(operands(0x023100B3), (Some(1), Some(2), Some(3), None));
+}
+
+#[test]
+fn immediate() {
+    // addi x1, x2, -1
+    assert_eq!(operands(0xFFF10093), (Some(1), Some(2), None, Some(-1)));
+    // slli x1, x2, 5
+    assert_eq!(operands(0x00511093), (Some(1), Some(2), None, Some(5)));
+    // lw x5, 8(x6)
+    assert_eq!(operands(0x00832283), (Some(5), Some(6), None, Some(8)));
+}
+
+#[test]
+fn store_and_branch() {
+    // sw x7, 4(x8)
+    assert_eq!(operands(0x00742223), (None, Some(8), Some(7), Some(4)));
+    // beq x1, x2, -8
+    assert_eq!(operands(0xFE208CE3), (None, Some(1), Some(2), Some(-8)));
+}
+
+#[test]
+fn jump_and_upper() {
+    // jal x1, 16
+    assert_eq!(operands(0x010000EF), (Some(1), None, None, Some(16)));
+    // jalr x0, 0(x1)
+    assert_eq!(operands(0x00008067), (Some(0), Some(1), None, Some(0)));
+    // lui x5, 0x12345
+    assert_eq!(operands(0x123452B7), (Some(5), None, None, Some(0x12345)));
+}
+
+#[test]
+fn vector() {
+    let add = Instruction::VaddVv {
+        vd: 1,
+        vs2: 2,
+        vs1: 3,
+        vm: true,
+    };
+    assert_eq!(
+        (add.rd(), add.rs1(), add.rs2()),
+        (
+            Some(Register::V(1)),
+            Some(Register::V(3)),
+            Some(Register::V(2))
+        )
+    );
+    let store = Instruction::Vse {
+        vs3: 4,
+        rs1: 5,
+        width: 32,
+        vm: true,
+    };
+    assert_eq!(
+        (store.rd(), store.rs1(), store.rs2()),
+        (None, Some(Register::X(5)), None)
+    );
+}
+
+#[test]
+fn float() {
+    let convert = Instruction::FcvtWH {
+        rd: 1,
+        rs1: 2,
+        rm: 7,
+    };
+    assert_eq!(
+        (convert.rd(), convert.rs1(), convert.rs2()),
+        (Some(Register::X(1)), Some(Register::F(2)), None)
+    );
+    let load = Instruction::Flh {
+        rd: 3,
+        rs1: 4,
+        imm: 0,
+    };
+    assert_eq!(
+        (load.rd(), load.rs1()),
+        (Some(Register::F(3)), Some(Register::X(4)))
+    );
+}
+
+#[test]
+fn register_files() {
+    assert_eq!(Register::F(7).number(), 7);
+    assert_eq!(Register::X(7).integer(), Some(7));
+    assert_eq!(Register::V(7).integer(), None);
+    assert_eq!(Register::X(1).to_string(), "x1");
+    assert_eq!(Register::F(2).to_string(), "f2");
+    assert_eq!(Register::V(3).to_string(), "v3");
+}
+
+#[test]
+fn none() {
+    assert_eq!(operands(0x00000073), (None, None, None, None));
+    assert_eq!(operands(0xFFFFFFFF), (None, None, None, None));
+}
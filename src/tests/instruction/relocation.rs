@@ -0,0 +1,91 @@
+use crate::{EncodeError, Instruction, Relocation, RelocationKind};
+
+#[test]
+fn placeholder() {
+    let beq = Instruction::Beq {
+        rs1: 1,
+        rs2: 2,
+        imm: 64,
+    };
+    let (word, relocation) = beq.encode_relocatable(8, "target").unwrap();
+    assert_eq!(word, 0x00208063);
+    assert_eq!(
+        relocation,
+        Relocation::new(8, RelocationKind::Branch, "target")
+    );
+}
+
+#[test]
+fn not_relocatable() {
+    assert_eq!(
+        Instruction::Ecall.encode_relocatable(0, "a"),
+        Err(EncodeError::NotRelocatable)
+    );
+    let jalr = Instruction::Jalr {
+        rd: 0,
+        rs1: 1,
+        imm: 0,
+    };
+    assert_eq!(
+        jalr.encode_relocatable(0, "a"),
+        Err(EncodeError::NotRelocatable)
+    );
+}
+
+#[test]
+fn apply_branch() {
+    let mut code = [0x13, 0x00, 0x00, 0x00, 0x63, 0x80, 0x20, 0x00];
+    let relocation = Relocation::new(4, RelocationKind::Branch, "start");
+    relocation.apply(&mut code, 0).unwrap();
+    // beq x1, x2, -4
+    assert_eq!(
+        Instruction::decode(u32::from_le_bytes([code[4], code[5], code[6], code[7]])),
+        Instruction::Beq {
+            rs1: 1,
+            rs2: 2,
+            imm: -4
+        }
+    );
+    assert_eq!(code[..4], [0x13, 0x00, 0x00, 0x00]);
+}
+
+#[test]
+fn apply_jump() {
+    let mut code = 0x000000EFu32.to_le_bytes();
+    Relocation::new(0, RelocationKind::Jump, "f")
+        .apply(&mut code, 16)
+        .unwrap();
+    // jal x1, 16
+    assert_eq!(u32::from_le_bytes(code), 0x010000EF);
+}
+
+#[test]
+fn apply_twice() {
+    // Re-applying replaces the previous target rather than accumulating
+    let mut code = 0x00208063u32.to_le_bytes();
+    let relocation = Relocation::new(0, RelocationKind::Branch, "a");
+    relocation.apply(&mut code, 8).unwrap();
+    relocation.apply(&mut code, 4).unwrap();
+    assert_eq!(u32::from_le_bytes(code), 0x00208263);
+}
+
+#[test]
+fn apply_out_of_range() {
+    let mut code = 0x00208063u32.to_le_bytes();
+    let relocation = Relocation::new(0, RelocationKind::Branch, "far");
+    assert_eq!(
+        relocation.apply(&mut code, 4096),
+        Err(EncodeError::InvalidImmediate("imm", 4096))
+    );
+    assert_eq!(u32::from_le_bytes(code), 0x00208063);
+}
+
+#[test]
+fn apply_outside_code() {
+    let mut code = [0; 6];
+    let relocation = Relocation::new(4, RelocationKind::Jump, "f");
+    assert_eq!(
+        relocation.apply(&mut code, 0),
+        Err(EncodeError::BufferTooSmall(6))
+    );
+}
@@ -0,0 +1,58 @@
+use crate::{Instruction, tests::instruction::assert_encode_decode};
+
+#[test]
+fn fadd_dynamic() {
+    // fadd.h f1, f2, f3
+    let instr = Instruction::FaddH {
+        rd: 1,
+        rs1: 2,
+        rs2: 3,
+        rm: 7,
+    };
+    assert_encode_decode(&instr, 0x043170D3);
+}
+
+#[test]
+fn fadd_rtz() {
+    // fadd.h f1, f2, f3, rtz
+    let instr = Instruction::FaddH {
+        rd: 1,
+        rs1: 2,
+        rs2: 3,
+        rm: 1,
+    };
+    assert_encode_decode(&instr, 0x043110D3);
+}
+
+#[test]
+fn fsub() {
+    let instr = Instruction::FsubH {
+        rd: 1,
+        rs1: 2,
+        rs2: 3,
+        rm: 7,
+    };
+    assert_encode_decode(&instr, 0x0C3170D3);
+}
+
+#[test]
+fn fmul() {
+    let instr = Instruction::FmulH {
+        rd: 1,
+        rs1: 2,
+        rs2: 3,
+        rm: 7,
+    };
+    assert_encode_decode(&instr, 0x143170D3);
+}
+
+#[test]
+fn fdiv() {
+    let instr = Instruction::FdivH {
+        rd: 1,
+        rs1: 2,
+        rs2: 3,
+        rm: 7,
+    };
+    assert_encode_decode(&instr, 0x1C3170D3);
+}
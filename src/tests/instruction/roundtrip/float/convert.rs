@@ -0,0 +1,62 @@
+use crate::{Instruction, tests::instruction::assert_encode_decode};
+
+#[test]
+fn fcvt_w_h() {
+    // fcvt.w.h x1, f2, rtz
+    let instr = Instruction::FcvtWH {
+        rd: 1,
+        rs1: 2,
+        rm: 1,
+    };
+    assert_encode_decode(&instr, 0xC40110D3);
+}
+
+#[test]
+fn fcvt_wu_h() {
+    let instr = Instruction::FcvtWuH {
+        rd: 1,
+        rs1: 2,
+        rm: 7,
+    };
+    assert_encode_decode(&instr, 0xC41170D3);
+}
+
+#[test]
+fn fcvt_h_w() {
+    let instr = Instruction::FcvtHW {
+        rd: 1,
+        rs1: 2,
+        rm: 7,
+    };
+    assert_encode_decode(&instr, 0xD40170D3);
+}
+
+#[test]
+fn fcvt_h_wu() {
+    let instr = Instruction::FcvtHWu {
+        rd: 1,
+        rs1: 2,
+        rm: 7,
+    };
+    assert_encode_decode(&instr, 0xD41170D3);
+}
+
+#[test]
+fn fcvt_s_h() {
+    let instr = Instruction::FcvtSH {
+        rd: 1,
+        rs1: 2,
+        rm: 0,
+    };
+    assert_encode_decode(&instr, 0x402100D3);
+}
+
+#[test]
+fn fcvt_h_s() {
+    let instr = Instruction::FcvtHS {
+        rd: 1,
+        rs1: 2,
+        rm: 7,
+    };
+    assert_encode_decode(&instr, 0x440170D3);
+}
@@ -0,0 +1,44 @@
+use crate::{Instruction, tests::instruction::assert_encode_decode};
+
+#[test]
+fn flh() {
+    // flh f1, 8(x2)
+    let instr = Instruction::Flh {
+        rd: 1,
+        rs1: 2,
+        imm: 8,
+    };
+    assert_encode_decode(&instr, 0x00811087);
+}
+
+#[test]
+fn flh_min_immediate() {
+    // flh f31, -2048(x31)
+    let instr = Instruction::Flh {
+        rd: 31,
+        rs1: 31,
+        imm: -2048,
+    };
+    assert_encode_decode(&instr, 0x800F9F87);
+}
+
+#[test]
+fn fsh() {
+    // fsh f3, -4(x5)
+    let instr = Instruction::Fsh {
+        rs1: 5,
+        rs2: 3,
+        imm: -4,
+    };
+    assert_encode_decode(&instr, 0xFE329E27);
+}
+
+#[test]
+fn fsh_max_immediate() {
+    let instr = Instruction::Fsh {
+        rs1: 1,
+        rs2: 2,
+        imm: 2047,
+    };
+    assert_encode_decode(&instr, 0x7E209FA7);
+}
@@ -0,0 +1,3 @@
+mod arithmetic;
+mod convert;
+mod memory;
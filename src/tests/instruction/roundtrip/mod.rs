@@ -1,4 +1,5 @@
 mod branch;
+mod float;
 mod immediate;
 mod jump;
 mod load;
@@ -7,3 +8,4 @@ mod register;
 mod store;
 mod system;
 mod utype;
+mod vector;
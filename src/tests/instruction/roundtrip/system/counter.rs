@@ -0,0 +1,26 @@
+use crate::Instruction;
+use crate::tests::instruction::assert_encode_decode;
+
+#[test]
+fn cycle() {
+    assert_encode_decode(&Instruction::Rdcycle { rd: 5 }, 0xC00022F3);
+    assert_encode_decode(&Instruction::Rdcycleh { rd: 6 }, 0xC8002373);
+}
+
+#[test]
+fn time() {
+    assert_encode_decode(&Instruction::Rdtime { rd: 10 }, 0xC0102573);
+    assert_encode_decode(&Instruction::Rdtimeh { rd: 11 }, 0xC81025F3);
+}
+
+#[test]
+fn instret() {
+    assert_encode_decode(&Instruction::Rdinstret { rd: 31 }, 0xC0202FF3);
+    assert_encode_decode(&Instruction::Rdinstreth { rd: 0 }, 0xC8202073);
+}
+
+#[test]
+fn invalid_register() {
+    assert!(Instruction::rdcycle(32).is_err());
+    assert_eq!(Instruction::rdtime(1), Ok(Instruction::Rdtime { rd: 1 }));
+}
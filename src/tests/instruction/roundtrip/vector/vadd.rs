@@ -0,0 +1,61 @@
+use crate::{Instruction, tests::instruction::assert_encode_decode};
+
+#[test]
+fn vv() {
+    // vadd.vv v1, v2, v3
+    let instr = Instruction::VaddVv {
+        vd: 1,
+        vs2: 2,
+        vs1: 3,
+        vm: true,
+    };
+    assert_encode_decode(&instr, 0x022180D7);
+}
+
+#[test]
+fn vv_masked() {
+    // vadd.vv v1, v2, v3, v0.t
+    let instr = Instruction::VaddVv {
+        vd: 1,
+        vs2: 2,
+        vs1: 3,
+        vm: false,
+    };
+    assert_encode_decode(&instr, 0x002180D7);
+}
+
+#[test]
+fn vx() {
+    // vadd.vx v1, v2, x3
+    let instr = Instruction::VaddVx {
+        vd: 1,
+        vs2: 2,
+        rs1: 3,
+        vm: true,
+    };
+    assert_encode_decode(&instr, 0x0221C0D7);
+}
+
+#[test]
+fn vi_min() {
+    // vadd.vi v1, v2, -16
+    let instr = Instruction::VaddVi {
+        vd: 1,
+        vs2: 2,
+        imm: -16,
+        vm: true,
+    };
+    assert_encode_decode(&instr, 0x022830D7);
+}
+
+#[test]
+fn vi_max() {
+    // vadd.vi v1, v2, 15
+    let instr = Instruction::VaddVi {
+        vd: 1,
+        vs2: 2,
+        imm: 15,
+        vm: true,
+    };
+    assert_encode_decode(&instr, 0x0227B0D7);
+}
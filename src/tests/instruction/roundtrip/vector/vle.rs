@@ -0,0 +1,49 @@
+use crate::{Instruction, tests::instruction::assert_encode_decode};
+
+#[test]
+fn vle32() {
+    // vle32.v v1, (x2)
+    let instr = Instruction::Vle {
+        vd: 1,
+        rs1: 2,
+        width: 32,
+        vm: true,
+    };
+    assert_encode_decode(&instr, 0x02016087);
+}
+
+#[test]
+fn vle8_masked() {
+    // vle8.v v4, (x10), v0.t
+    let instr = Instruction::Vle {
+        vd: 4,
+        rs1: 10,
+        width: 8,
+        vm: false,
+    };
+    assert_encode_decode(&instr, 0x00050207);
+}
+
+#[test]
+fn vle16_max_registers() {
+    // vle16.v v31, (x31)
+    let instr = Instruction::Vle {
+        vd: 31,
+        rs1: 31,
+        width: 16,
+        vm: true,
+    };
+    assert_encode_decode(&instr, 0x020FDF87);
+}
+
+#[test]
+fn vle64_zero_registers() {
+    // vle64.v v0, (x0)
+    let instr = Instruction::Vle {
+        vd: 0,
+        rs1: 0,
+        width: 64,
+        vm: true,
+    };
+    assert_encode_decode(&instr, 0x02007007);
+}
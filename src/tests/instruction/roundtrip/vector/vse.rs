@@ -0,0 +1,25 @@
+use crate::{Instruction, tests::instruction::assert_encode_decode};
+
+#[test]
+fn vse32() {
+    // vse32.v v3, (x5)
+    let instr = Instruction::Vse {
+        vs3: 3,
+        rs1: 5,
+        width: 32,
+        vm: true,
+    };
+    assert_encode_decode(&instr, 0x0202E1A7);
+}
+
+#[test]
+fn vse8_masked() {
+    // vse8.v v1, (x2), v0.t
+    let instr = Instruction::Vse {
+        vs3: 1,
+        rs1: 2,
+        width: 8,
+        vm: false,
+    };
+    assert_encode_decode(&instr, 0x000100A7);
+}
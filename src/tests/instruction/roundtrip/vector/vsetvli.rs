@@ -0,0 +1,33 @@
+use crate::{Instruction, tests::instruction::assert_encode_decode};
+
+#[test]
+fn basic() {
+    // vsetvli x1, x2, e32, m1, ta, ma
+    let instr = Instruction::Vsetvli {
+        rd: 1,
+        rs1: 2,
+        vtypei: 0xD0,
+    };
+    assert_encode_decode(&instr, 0x0D0170D7);
+}
+
+#[test]
+fn fractional_lmul() {
+    // vsetvli x0, x10, e8, mf2, tu, mu
+    let instr = Instruction::Vsetvli {
+        rd: 0,
+        rs1: 10,
+        vtypei: 0x07,
+    };
+    assert_encode_decode(&instr, 0x00757057);
+}
+
+#[test]
+fn max_vtypei() {
+    let instr = Instruction::Vsetvli {
+        rd: 31,
+        rs1: 31,
+        vtypei: 0x7FF,
+    };
+    assert_encode_decode(&instr, 0x7FFFFFD7);
+}
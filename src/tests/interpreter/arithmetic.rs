@@ -0,0 +1,262 @@
+use super::run;
+use crate::{Instruction, PageStore};
+
+#[test]
+fn addi_and_add() {
+    let store = PageStore::new(16);
+    let instance = run(
+        &store,
+        &[
+            Instruction::Addi {
+                rd: 1,
+                rs1: 0,
+                imm: 20,
+            },
+            Instruction::Addi {
+                rd: 2,
+                rs1: 0,
+                imm: 22,
+            },
+            Instruction::Add {
+                rd: 3,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Ecall,
+        ],
+    );
+    assert_eq!(instance.read_register(3), 42);
+}
+
+#[test]
+fn add_wraps() {
+    let store = PageStore::new(16);
+    let instance = run(
+        &store,
+        &[
+            Instruction::Addi {
+                rd: 1,
+                rs1: 0,
+                imm: -1,
+            },
+            Instruction::Addi {
+                rd: 2,
+                rs1: 0,
+                imm: 2,
+            },
+            Instruction::Add {
+                rd: 3,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Sub {
+                rd: 4,
+                rs1: 0,
+                rs2: 2,
+            },
+            Instruction::Ecall,
+        ],
+    );
+    assert_eq!(instance.read_register(3), 1);
+    assert_eq!(instance.read_register(4), 0xFFFFFFFE);
+}
+
+#[test]
+fn logical() {
+    let store = PageStore::new(16);
+    let instance = run(
+        &store,
+        &[
+            Instruction::Addi {
+                rd: 1,
+                rs1: 0,
+                imm: 0b1100,
+            },
+            Instruction::Addi {
+                rd: 2,
+                rs1: 0,
+                imm: 0b1010,
+            },
+            Instruction::And {
+                rd: 3,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Or {
+                rd: 4,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Xor {
+                rd: 5,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Andi {
+                rd: 6,
+                rs1: 1,
+                imm: 0b0110,
+            },
+            Instruction::Ori {
+                rd: 7,
+                rs1: 1,
+                imm: 0b0011,
+            },
+            Instruction::Xori {
+                rd: 8,
+                rs1: 1,
+                imm: -1,
+            },
+            Instruction::Ecall,
+        ],
+    );
+    assert_eq!(instance.read_register(3), 0b1000);
+    assert_eq!(instance.read_register(4), 0b1110);
+    assert_eq!(instance.read_register(5), 0b0110);
+    assert_eq!(instance.read_register(6), 0b0100);
+    assert_eq!(instance.read_register(7), 0b1111);
+    assert_eq!(instance.read_register(8), !0b1100);
+}
+
+#[test]
+fn shifts() {
+    let store = PageStore::new(16);
+    let instance = run(
+        &store,
+        &[
+            Instruction::Addi {
+                rd: 1,
+                rs1: 0,
+                imm: -16,
+            },
+            Instruction::Addi {
+                rd: 2,
+                rs1: 0,
+                imm: 33,
+            },
+            Instruction::Sll {
+                rd: 3,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Srl {
+                rd: 4,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Sra {
+                rd: 5,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Slli {
+                rd: 6,
+                rs1: 1,
+                shamt: 4,
+            },
+            Instruction::Srli {
+                rd: 7,
+                rs1: 1,
+                shamt: 28,
+            },
+            Instruction::Srai {
+                rd: 8,
+                rs1: 1,
+                shamt: 2,
+            },
+            Instruction::Ecall,
+        ],
+    );
+    // Register shift amounts use only the low 5 bits (33 -> 1)
+    assert_eq!(instance.read_register(3), 0xFFFFFFE0);
+    assert_eq!(instance.read_register(4), 0x7FFFFFF8);
+    assert_eq!(instance.read_register(5), 0xFFFFFFF8);
+    assert_eq!(instance.read_register(6), 0xFFFFFF00);
+    assert_eq!(instance.read_register(7), 0xF);
+    assert_eq!(instance.read_register(8), 0xFFFFFFFC);
+}
+
+#[test]
+fn comparisons() {
+    let store = PageStore::new(16);
+    let instance = run(
+        &store,
+        &[
+            Instruction::Addi {
+                rd: 1,
+                rs1: 0,
+                imm: -1,
+            },
+            Instruction::Addi {
+                rd: 2,
+                rs1: 0,
+                imm: 1,
+            },
+            Instruction::Slt {
+                rd: 3,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Sltu {
+                rd: 4,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Slti {
+                rd: 5,
+                rs1: 1,
+                imm: 0,
+            },
+            Instruction::Sltiu {
+                rd: 6,
+                rs1: 2,
+                imm: -1,
+            },
+            Instruction::Ecall,
+        ],
+    );
+    assert_eq!(instance.read_register(3), 1);
+    assert_eq!(instance.read_register(4), 0);
+    assert_eq!(instance.read_register(5), 1);
+    assert_eq!(instance.read_register(6), 1);
+}
+
+#[test]
+fn upper_immediates() {
+    let store = PageStore::new(16);
+    let instance = run(
+        &store,
+        &[
+            Instruction::Addi {
+                rd: 0,
+                rs1: 0,
+                imm: 0,
+            },
+            Instruction::Lui {
+                rd: 1,
+                imm: 0x12345,
+            },
+            Instruction::Auipc { rd: 2, imm: 0x1 },
+            Instruction::Ecall,
+        ],
+    );
+    assert_eq!(instance.read_register(1), 0x12345000);
+    assert_eq!(instance.read_register(2), 0x1008);
+}
+
+#[test]
+fn x0_writes_discarded() {
+    let store = PageStore::new(16);
+    let instance = run(
+        &store,
+        &[
+            Instruction::Addi {
+                rd: 0,
+                rs1: 0,
+                imm: 5,
+            },
+            Instruction::Ecall,
+        ],
+    );
+    assert_eq!(instance.read_register(0), 0);
+}
@@ -0,0 +1,205 @@
+use super::run;
+use crate::{Instruction, PageStore};
+
+#[test]
+fn countdown_loop() {
+    let store = PageStore::new(16);
+    let instance = run(
+        &store,
+        &[
+            Instruction::Addi {
+                rd: 1,
+                rs1: 0,
+                imm: 10,
+            },
+            Instruction::Addi {
+                rd: 2,
+                rs1: 2,
+                imm: 3,
+            },
+            Instruction::Addi {
+                rd: 1,
+                rs1: 1,
+                imm: -1,
+            },
+            Instruction::Bne {
+                rs1: 1,
+                rs2: 0,
+                imm: -8,
+            },
+            Instruction::Ecall,
+        ],
+    );
+    assert_eq!(instance.read_register(1), 0);
+    assert_eq!(instance.read_register(2), 30);
+}
+
+#[test]
+fn branches_taken_and_not_taken() {
+    let store = PageStore::new(16);
+    let skip = |instr| {
+        [
+            instr,
+            Instruction::Addi {
+                rd: 10,
+                rs1: 10,
+                imm: 1,
+            },
+        ]
+    };
+    let mut program = vec![
+        Instruction::Addi {
+            rd: 1,
+            rs1: 0,
+            imm: -1,
+        },
+        Instruction::Addi {
+            rd: 2,
+            rs1: 0,
+            imm: 1,
+        },
+    ];
+    // Taken branches skip the increment
+    program.extend(skip(Instruction::Beq {
+        rs1: 1,
+        rs2: 1,
+        imm: 8,
+    }));
+    program.extend(skip(Instruction::Bne {
+        rs1: 1,
+        rs2: 2,
+        imm: 8,
+    }));
+    program.extend(skip(Instruction::Blt {
+        rs1: 1,
+        rs2: 2,
+        imm: 8,
+    }));
+    program.extend(skip(Instruction::Bge {
+        rs1: 2,
+        rs2: 1,
+        imm: 8,
+    }));
+    program.extend(skip(Instruction::Bltu {
+        rs1: 2,
+        rs2: 1,
+        imm: 8,
+    }));
+    program.extend(skip(Instruction::Bgeu {
+        rs1: 1,
+        rs2: 2,
+        imm: 8,
+    }));
+    // Not-taken branches fall through to the increment
+    program.extend(skip(Instruction::Beq {
+        rs1: 1,
+        rs2: 2,
+        imm: 8,
+    }));
+    program.extend(skip(Instruction::Bne {
+        rs1: 1,
+        rs2: 1,
+        imm: 8,
+    }));
+    program.extend(skip(Instruction::Blt {
+        rs1: 2,
+        rs2: 1,
+        imm: 8,
+    }));
+    program.extend(skip(Instruction::Bge {
+        rs1: 1,
+        rs2: 2,
+        imm: 8,
+    }));
+    program.extend(skip(Instruction::Bltu {
+        rs1: 1,
+        rs2: 2,
+        imm: 8,
+    }));
+    program.extend(skip(Instruction::Bgeu {
+        rs1: 2,
+        rs2: 1,
+        imm: 8,
+    }));
+    program.push(Instruction::Ecall);
+    let instance = run(&store, &program);
+    assert_eq!(instance.read_register(10), 6);
+}
+
+#[test]
+fn call_and_return() {
+    let store = PageStore::new(16);
+    let instance = run(
+        &store,
+        &[
+            Instruction::Jal { rd: 1, imm: 12 },
+            Instruction::Addi {
+                rd: 11,
+                rs1: 10,
+                imm: 1,
+            },
+            Instruction::Ecall,
+            Instruction::Addi {
+                rd: 10,
+                rs1: 0,
+                imm: 41,
+            },
+            Instruction::Jalr {
+                rd: 0,
+                rs1: 1,
+                imm: 0,
+            },
+        ],
+    );
+    assert_eq!(instance.read_register(1), 4);
+    assert_eq!(instance.read_register(11), 42);
+}
+
+#[test]
+fn jalr_same_source_and_destination() {
+    let store = PageStore::new(16);
+    let instance = run(
+        &store,
+        &[
+            Instruction::Addi {
+                rd: 5,
+                rs1: 0,
+                imm: 13,
+            },
+            Instruction::Jalr {
+                rd: 5,
+                rs1: 5,
+                imm: -1,
+            },
+            Instruction::Ecall,
+            Instruction::Ecall,
+        ],
+    );
+    // Target is (13 - 1) & !1 = 12 and the link value is 8
+    assert_eq!(instance.pc(), 12);
+    assert_eq!(instance.read_register(5), 8);
+}
+
+#[test]
+fn counters() {
+    let store = PageStore::new(16);
+    let instance = run(
+        &store,
+        &[
+            Instruction::Rdcycle { rd: 1 },
+            Instruction::Addi {
+                rd: 0,
+                rs1: 0,
+                imm: 0,
+            },
+            Instruction::Rdinstret { rd: 2 },
+            Instruction::Rdtime { rd: 3 },
+            Instruction::Rdcycleh { rd: 4 },
+            Instruction::Ecall,
+        ],
+    );
+    assert_eq!(instance.read_register(1), 0);
+    assert_eq!(instance.read_register(2), 2);
+    assert_eq!(instance.read_register(3), 3);
+    assert_eq!(instance.read_register(4), 0);
+}
@@ -0,0 +1,128 @@
+use super::run;
+use crate::{Instruction, PageStore};
+
+#[test]
+fn store_and_load_word() {
+    let store = PageStore::new(16);
+    let instance = run(
+        &store,
+        &[
+            Instruction::Lui { rd: 1, imm: 0x10 },
+            Instruction::Addi {
+                rd: 2,
+                rs1: 0,
+                imm: -2,
+            },
+            Instruction::Sw {
+                rs1: 1,
+                rs2: 2,
+                imm: 8,
+            },
+            Instruction::Lw {
+                rd: 3,
+                rs1: 1,
+                imm: 8,
+            },
+            Instruction::Ecall,
+        ],
+    );
+    assert_eq!(instance.read_register(3), 0xFFFFFFFE);
+    let mut bytes = [0u8; 4];
+    instance.memory().read(0x10008, &mut bytes);
+    assert_eq!(bytes, [0xFE, 0xFF, 0xFF, 0xFF]);
+}
+
+#[test]
+fn sign_and_zero_extension() {
+    let store = PageStore::new(16);
+    let instance = run(
+        &store,
+        &[
+            Instruction::Lui { rd: 1, imm: 0x10 },
+            Instruction::Addi {
+                rd: 2,
+                rs1: 0,
+                imm: -128,
+            },
+            Instruction::Sh {
+                rs1: 1,
+                rs2: 2,
+                imm: 0,
+            },
+            Instruction::Lb {
+                rd: 3,
+                rs1: 1,
+                imm: 0,
+            },
+            Instruction::Lbu {
+                rd: 4,
+                rs1: 1,
+                imm: 0,
+            },
+            Instruction::Lh {
+                rd: 5,
+                rs1: 1,
+                imm: 0,
+            },
+            Instruction::Lhu {
+                rd: 6,
+                rs1: 1,
+                imm: 0,
+            },
+            Instruction::Ecall,
+        ],
+    );
+    assert_eq!(instance.read_register(3), 0xFFFFFF80);
+    assert_eq!(instance.read_register(4), 0x80);
+    assert_eq!(instance.read_register(5), 0xFFFFFF80);
+    assert_eq!(instance.read_register(6), 0xFF80);
+}
+
+#[test]
+fn store_byte() {
+    let store = PageStore::new(16);
+    let instance = run(
+        &store,
+        &[
+            Instruction::Lui { rd: 1, imm: 0x10 },
+            Instruction::Addi {
+                rd: 2,
+                rs1: 0,
+                imm: 0x1AB,
+            },
+            Instruction::Sb {
+                rs1: 1,
+                rs2: 2,
+                imm: -1,
+            },
+            Instruction::Lw {
+                rd: 3,
+                rs1: 1,
+                imm: -4,
+            },
+            Instruction::Ecall,
+        ],
+    );
+    assert_eq!(instance.read_register(3), 0xAB000000);
+}
+
+#[test]
+fn unmapped_reads_zero() {
+    let store = PageStore::new(16);
+    let instance = run(
+        &store,
+        &[
+            Instruction::Lui {
+                rd: 1,
+                imm: 0x80000,
+            },
+            Instruction::Lw {
+                rd: 2,
+                rs1: 1,
+                imm: 0,
+            },
+            Instruction::Ecall,
+        ],
+    );
+    assert_eq!(instance.read_register(2), 0);
+}
@@ -0,0 +1,233 @@
+use super::load;
+use crate::{
+    Access, Instance, Instruction, Interpreter, Memory, MmioDevice, PageStore, Permissions, Trap,
+    WatchAction,
+};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+/// (offset, size, value) of each store a device received
+type Stores = Rc<RefCell<Vec<(u32, u32, u32)>>>;
+
+/// Device logging stores and reading back a fixed register
+struct Device {
+    stores: Stores,
+}
+
+impl MmioDevice for Device {
+    fn read(&mut self, offset: u32, _size: u32) -> u32 {
+        0xaabb_cc00 | offset
+    }
+
+    fn write(&mut self, offset: u32, size: u32, value: u32) {
+        self.stores.borrow_mut().push((offset, size, value));
+    }
+}
+
+/// Instance with a device at 0x10000 (8 bytes) running `program` with x1 = 0x10000
+fn program(store: &PageStore, program: &[Instruction]) -> (Instance, Stores) {
+    let mut memory = Memory::new(store, 16, 4);
+    let stores = Rc::new(RefCell::new(Vec::new()));
+    let device = Device {
+        stores: Rc::clone(&stores),
+    };
+    memory.map_mmio(0x10000, 8, Box::new(device)).unwrap();
+    let mut instance = Instance::new(memory);
+    let mut code = vec![Instruction::Lui { rd: 1, imm: 0x10 }];
+    code.extend_from_slice(program);
+    code.push(Instruction::Ecall);
+    load(&mut instance, 0, &code);
+    (instance, stores)
+}
+
+#[test]
+fn stores_go_to_device() {
+    let store = PageStore::new(16);
+    let (mut instance, stores) = program(
+        &store,
+        &[
+            Instruction::Addi {
+                rd: 2,
+                rs1: 0,
+                imm: -1,
+            },
+            Instruction::Sb {
+                rs1: 1,
+                rs2: 2,
+                imm: 0,
+            },
+            Instruction::Sh {
+                rs1: 1,
+                rs2: 2,
+                imm: 2,
+            },
+            Instruction::Sw {
+                rs1: 1,
+                rs2: 2,
+                imm: 4,
+            },
+        ],
+    );
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.run(&mut instance, 100), Err(Trap::Ecall));
+    assert_eq!(
+        *stores.borrow(),
+        [(0, 1, 0xff), (2, 2, 0xffff), (4, 4, 0xffff_ffff)]
+    );
+
+    // RAM behind the device is untouched
+    assert_eq!(instance.memory().read_u32(0x10000), 0);
+    assert_eq!(instance.memory().num_pages, 1);
+}
+
+#[test]
+fn loads_come_from_device() {
+    let store = PageStore::new(16);
+    let (mut instance, _) = program(
+        &store,
+        &[
+            Instruction::Lw {
+                rd: 2,
+                rs1: 1,
+                imm: 4,
+            },
+            Instruction::Lbu {
+                rd: 3,
+                rs1: 1,
+                imm: 1,
+            },
+            Instruction::Lh {
+                rd: 4,
+                rs1: 1,
+                imm: 2,
+            },
+        ],
+    );
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.run(&mut instance, 100), Err(Trap::Ecall));
+    assert_eq!(instance.read_register(2), 0xaabb_cc04);
+    assert_eq!(instance.read_register(3), 0x01);
+    assert_eq!(instance.read_register(4), 0xffff_cc02);
+}
+
+#[test]
+fn bypasses_permissions() {
+    let store = PageStore::new(16);
+    let (mut instance, stores) = program(
+        &store,
+        &[Instruction::Sw {
+            rs1: 1,
+            rs2: 0,
+            imm: 0,
+        }],
+    );
+    instance
+        .memory_mut()
+        .protect(0x10000, 8, Permissions::NONE)
+        .unwrap();
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.run(&mut instance, 100), Err(Trap::Ecall));
+    assert_eq!(stores.borrow().len(), 1);
+}
+
+#[test]
+fn straddling_access_faults() {
+    let store = PageStore::new(16);
+    let (mut instance, stores) = program(
+        &store,
+        &[Instruction::Sw {
+            rs1: 1,
+            rs2: 0,
+            imm: 6,
+        }],
+    );
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter.run(&mut instance, 100),
+        Err(Trap::Fault(0x10006))
+    );
+    assert!(stores.borrow().is_empty());
+    drop(instance);
+
+    let (mut instance, _) = program(
+        &store,
+        &[Instruction::Lw {
+            rd: 2,
+            rs1: 1,
+            imm: -2,
+        }],
+    );
+    assert_eq!(
+        interpreter.run(&mut instance, 100),
+        Err(Trap::Fault(0xfffe))
+    );
+}
+
+#[test]
+fn watched() {
+    let store = PageStore::new(16);
+    let (mut instance, stores) = program(
+        &store,
+        &[Instruction::Sw {
+            rs1: 1,
+            rs2: 0,
+            imm: 4,
+        }],
+    );
+    let mut interpreter = Interpreter::new();
+    interpreter.watch(0x10004, 4, Access::Write, WatchAction::Trap);
+    assert_eq!(
+        interpreter.run(&mut instance, 100),
+        Err(Trap::Watchpoint(0x10004, Access::Write))
+    );
+    assert!(stores.borrow().is_empty());
+}
+
+/// Device popping a counter on every read, like a FIFO
+struct Fifo {
+    reads: Rc<Cell<u32>>,
+}
+
+impl MmioDevice for Fifo {
+    fn read(&mut self, _offset: u32, _size: u32) -> u32 {
+        self.reads.set(self.reads.get() + 1);
+        self.reads.get()
+    }
+
+    fn write(&mut self, _offset: u32, _size: u32, _value: u32) {}
+}
+
+#[test]
+fn watched_load_skips_device() {
+    let store = PageStore::new(16);
+    let mut memory = Memory::new(&store, 16, 4);
+    let reads = Rc::new(Cell::new(0));
+    let fifo = Fifo {
+        reads: Rc::clone(&reads),
+    };
+    memory.map_mmio(0x10000, 4, Box::new(fifo)).unwrap();
+    let mut instance = Instance::new(memory);
+    let code = [
+        Instruction::Lui { rd: 1, imm: 0x10 },
+        Instruction::Lw {
+            rd: 2,
+            rs1: 1,
+            imm: 0,
+        },
+        Instruction::Ecall,
+    ];
+    load(&mut instance, 0, &code);
+    let mut interpreter = Interpreter::new();
+    let index = interpreter.watch(0x10000, 4, Access::Read, WatchAction::Trap);
+    assert_eq!(
+        interpreter.run(&mut instance, 100),
+        Err(Trap::Watchpoint(0x10000, Access::Read))
+    );
+    assert_eq!((reads.get(), instance.read_register(2)), (0, 0));
+
+    interpreter.unwatch(index);
+    assert_eq!(interpreter.run(&mut instance, 100), Err(Trap::Ecall));
+    assert_eq!((reads.get(), instance.read_register(2)), (1, 1));
+}
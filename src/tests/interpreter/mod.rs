@@ -0,0 +1,28 @@
+mod arithmetic;
+mod control;
+mod memory;
+mod mmio;
+mod multiply;
+mod traps;
+mod watch;
+
+use crate::{Instance, Instruction, Interpreter, Memory, PageStore, Trap};
+
+/// Load a program at address 0 and run it until it reaches ECALL
+pub fn run(store: &PageStore, program: &[Instruction]) -> Instance {
+    let memory = Memory::new(store, 16, 4);
+    let mut instance = Instance::new(memory);
+    load(&mut instance, 0, program);
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.run(&mut instance, 10_000), Err(Trap::Ecall));
+    instance
+}
+
+/// Encode instructions into guest memory at the given address
+pub fn load(instance: &mut Instance, address: u32, program: &[Instruction]) {
+    let bytes: Vec<u8> = program
+        .iter()
+        .flat_map(|instr| instr.encode().unwrap().to_le_bytes())
+        .collect();
+    assert_eq!(instance.memory_mut().write(address, &bytes), Ok(()));
+}
@@ -0,0 +1,95 @@
+use super::run;
+use crate::{Instruction, PageStore};
+
+/// Run a single M-extension instruction with x1 = a and x2 = b, returning x3
+fn compute(instr: fn(u8, u8, u8) -> Instruction, a: u32, b: u32) -> u32 {
+    let store = PageStore::new(16);
+    let upper = |value: u32| (value.wrapping_add(0x800) >> 12) & 0xFFFFF;
+    let lower = |value: u32| ((value & 0xFFF) as i32) << 20 >> 20;
+    let instance = run(
+        &store,
+        &[
+            Instruction::Lui {
+                rd: 1,
+                imm: upper(a),
+            },
+            Instruction::Addi {
+                rd: 1,
+                rs1: 1,
+                imm: lower(a),
+            },
+            Instruction::Lui {
+                rd: 2,
+                imm: upper(b),
+            },
+            Instruction::Addi {
+                rd: 2,
+                rs1: 2,
+                imm: lower(b),
+            },
+            instr(3, 1, 2),
+            Instruction::Ecall,
+        ],
+    );
+    instance.read_register(3)
+}
+
+#[test]
+fn mul() {
+    let mul = |rd, rs1, rs2| Instruction::Mul { rd, rs1, rs2 };
+    assert_eq!(compute(mul, 6, 7), 42);
+    assert_eq!(compute(mul, 0x10000, 0x10000), 0);
+    assert_eq!(compute(mul, -3i32 as u32, 5), -15i32 as u32);
+}
+
+#[test]
+fn mulh() {
+    let mulh = |rd, rs1, rs2| Instruction::Mulh { rd, rs1, rs2 };
+    assert_eq!(compute(mulh, 0x10000, 0x10000), 1);
+    assert_eq!(compute(mulh, -1i32 as u32, -1i32 as u32), 0);
+    assert_eq!(compute(mulh, -2i32 as u32, 3), 0xFFFFFFFF);
+}
+
+#[test]
+fn mulhsu() {
+    let mulhsu = |rd, rs1, rs2| Instruction::Mulhsu { rd, rs1, rs2 };
+    assert_eq!(compute(mulhsu, -1i32 as u32, 0xFFFFFFFF), 0xFFFFFFFF);
+    assert_eq!(compute(mulhsu, 2, 0x80000000), 1);
+}
+
+#[test]
+fn mulhu() {
+    let mulhu = |rd, rs1, rs2| Instruction::Mulhu { rd, rs1, rs2 };
+    assert_eq!(compute(mulhu, 0xFFFFFFFF, 0xFFFFFFFF), 0xFFFFFFFE);
+    assert_eq!(compute(mulhu, 2, 0x80000000), 1);
+}
+
+#[test]
+fn div() {
+    let div = |rd, rs1, rs2| Instruction::Div { rd, rs1, rs2 };
+    assert_eq!(compute(div, -7i32 as u32, 2), -3i32 as u32);
+    assert_eq!(compute(div, 7, 0), 0xFFFFFFFF);
+    assert_eq!(compute(div, 0x80000000, -1i32 as u32), 0x80000000);
+}
+
+#[test]
+fn divu() {
+    let divu = |rd, rs1, rs2| Instruction::Divu { rd, rs1, rs2 };
+    assert_eq!(compute(divu, 0xFFFFFFFE, 2), 0x7FFFFFFF);
+    assert_eq!(compute(divu, 7, 0), 0xFFFFFFFF);
+}
+
+#[test]
+fn rem() {
+    let rem = |rd, rs1, rs2| Instruction::Rem { rd, rs1, rs2 };
+    assert_eq!(compute(rem, -7i32 as u32, 2), -1i32 as u32);
+    assert_eq!(compute(rem, 7, 0), 7);
+    assert_eq!(compute(rem, 0x80000000, -1i32 as u32), 0);
+}
+
+#[test]
+fn remu() {
+    let remu = |rd, rs1, rs2| Instruction::Remu { rd, rs1, rs2 };
+    assert_eq!(compute(remu, 7, 3), 1);
+    assert_eq!(compute(remu, 7, 0), 7);
+}
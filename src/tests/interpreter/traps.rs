@@ -0,0 +1,281 @@
+use super::load;
+use crate::memory::PAGE_SIZE;
+use crate::{Access, Instance, Instruction, Interpreter, Memory, PageStore, Permissions, Trap};
+
+#[test]
+fn ecall_leaves_pc() {
+    let store = PageStore::new(16);
+    let memory = Memory::new(&store, 16, 4);
+    let mut instance = Instance::new(memory);
+    load(&mut instance, 0x100, &[Instruction::Ecall]);
+    instance.set_pc(0x100);
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.step(&mut instance), Err(Trap::Ecall));
+    assert_eq!(instance.pc(), 0x100);
+    assert_eq!(interpreter.retired(), 0);
+}
+
+#[test]
+fn ebreak() {
+    let store = PageStore::new(16);
+    let memory = Memory::new(&store, 16, 4);
+    let mut instance = Instance::new(memory);
+    load(&mut instance, 0, &[Instruction::Ebreak]);
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.step(&mut instance), Err(Trap::Ebreak));
+}
+
+#[test]
+fn illegal_instruction() {
+    let store = PageStore::new(16);
+    let memory = Memory::new(&store, 16, 4);
+    let mut instance = Instance::new(memory);
+    let mut interpreter = Interpreter::new();
+    // Unmapped memory reads as zero, which is not a valid instruction
+    assert_eq!(interpreter.step(&mut instance), Err(Trap::Illegal(0)));
+}
+
+#[test]
+fn misaligned_pc() {
+    let store = PageStore::new(16);
+    let memory = Memory::new(&store, 16, 4);
+    let mut instance = Instance::new(memory);
+    instance.set_pc(2);
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.step(&mut instance), Err(Trap::Misaligned(2)));
+}
+
+#[test]
+fn store_fault() {
+    let store = PageStore::new(16);
+    let memory = Memory::new(&store, 1, 4);
+    let mut instance = Instance::new(memory);
+    load(
+        &mut instance,
+        0,
+        &[
+            Instruction::Lui { rd: 1, imm: 0x10 },
+            Instruction::Sw {
+                rs1: 1,
+                rs2: 0,
+                imm: 0,
+            },
+        ],
+    );
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter.run(&mut instance, 10),
+        Err(Trap::Fault(0x10000))
+    );
+    assert_eq!(instance.pc(), 4);
+    assert_eq!(interpreter.retired(), 1);
+}
+
+#[test]
+fn run_step_limit() {
+    let store = PageStore::new(16);
+    let memory = Memory::new(&store, 16, 4);
+    let mut instance = Instance::new(memory);
+    load(&mut instance, 0, &[Instruction::Jal { rd: 0, imm: 0 }]);
+    let mut interpreter = Interpreter::new();
+    assert_eq!(interpreter.run(&mut instance, 100), Ok(()));
+    assert_eq!(interpreter.retired(), 100);
+}
+
+#[test]
+fn display() {
+    assert_eq!(Trap::Ecall.to_string(), "ecall");
+    assert_eq!(Trap::Ebreak.to_string(), "ebreak");
+    assert_eq!(
+        Trap::Illegal(0xFFFFFFFF).to_string(),
+        "illegal instruction 0xffffffff"
+    );
+    assert_eq!(
+        Trap::Fault(0x1000).to_string(),
+        "memory fault at 0x00001000"
+    );
+    assert_eq!(
+        Trap::Misaligned(0x2).to_string(),
+        "misaligned fetch at 0x00000002"
+    );
+}
+
+#[test]
+fn write_to_code() {
+    let store = PageStore::new(16);
+    let memory = Memory::new(&store, 16, 4);
+    let mut instance = Instance::new(memory);
+    // sw x0, 0(x0) overwrites its own page
+    load(
+        &mut instance,
+        0,
+        &[Instruction::Sw {
+            rs1: 0,
+            rs2: 0,
+            imm: 0,
+        }],
+    );
+    instance
+        .memory_mut()
+        .protect(0, PAGE_SIZE as u32, Permissions::READ_EXECUTE)
+        .unwrap();
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter.step(&mut instance),
+        Err(Trap::Protection(0, Access::Write))
+    );
+    assert_eq!(instance.pc(), 0);
+    assert_eq!(interpreter.retired(), 0);
+}
+
+#[test]
+fn execute_data() {
+    let store = PageStore::new(16);
+    let memory = Memory::new(&store, 16, 4);
+    let mut instance = Instance::new(memory);
+    load(&mut instance, 0, &[Instruction::Ecall]);
+    instance
+        .memory_mut()
+        .protect(0, PAGE_SIZE as u32, Permissions::READ_WRITE)
+        .unwrap();
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter.step(&mut instance),
+        Err(Trap::Protection(0, Access::Execute))
+    );
+}
+
+#[test]
+fn read_unreadable() {
+    let store = PageStore::new(16);
+    let memory = Memory::new(&store, 16, 4);
+    let mut instance = Instance::new(memory);
+    load(
+        &mut instance,
+        0,
+        &[
+            Instruction::Lui { rd: 1, imm: 0x10 },
+            Instruction::Lw {
+                rd: 2,
+                rs1: 1,
+                imm: 4,
+            },
+        ],
+    );
+    instance
+        .memory_mut()
+        .protect(0x10000, PAGE_SIZE as u32, Permissions::NONE)
+        .unwrap();
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter.run(&mut instance, 10),
+        Err(Trap::Protection(0x10004, Access::Read))
+    );
+    assert_eq!(
+        Trap::Protection(0x10004, Access::Read).to_string(),
+        "read protection fault at 0x00010004"
+    );
+}
+
+#[test]
+fn stack_overflow_into_guard() {
+    let store = PageStore::new(16);
+    let memory = Memory::new(&store, 16, 4);
+    let mut instance = Instance::new(memory);
+    load(
+        &mut instance,
+        0,
+        &[
+            Instruction::Lui { rd: 2, imm: 0x20 },
+            Instruction::Sw {
+                rs1: 2,
+                rs2: 0,
+                imm: -4,
+            },
+        ],
+    );
+    let guard = 0x20000 - PAGE_SIZE as u32;
+    instance
+        .memory_mut()
+        .guard(guard, PAGE_SIZE as u32)
+        .unwrap();
+    let mut interpreter = Interpreter::new();
+    assert_eq!(
+        interpreter.run(&mut instance, 10),
+        Err(Trap::Guard(0x1FFFC))
+    );
+    assert_eq!(instance.memory().num_pages, 1);
+    assert_eq!(
+        Trap::Guard(0x1FFFC).to_string(),
+        "guard page fault at 0x0001fffc"
+    );
+}
+
+#[test]
+fn misaligned_access() {
+    let store = PageStore::new(16);
+    let memory = Memory::new(&store, 16, 4);
+    let mut instance = Instance::new(memory);
+    load(
+        &mut instance,
+        0,
+        &[
+            Instruction::Addi {
+                rd: 1,
+                rs1: 0,
+                imm: 0x102,
+            },
+            Instruction::Lh {
+                rd: 2,
+                rs1: 1,
+                imm: 0,
+            },
+            Instruction::Lw {
+                rd: 3,
+                rs1: 1,
+                imm: 0,
+            },
+            Instruction::Sw {
+                rs1: 1,
+                rs2: 1,
+                imm: 1,
+            },
+            Instruction::Sb {
+                rs1: 1,
+                rs2: 1,
+                imm: 1,
+            },
+            Instruction::Ecall,
+        ],
+    );
+
+    // Serviced by default
+    let mut interpreter = Interpreter::new();
+    assert!(!interpreter.check_alignment());
+    assert_eq!(interpreter.run(&mut instance, 10), Err(Trap::Ecall));
+
+    instance.set_pc(0);
+    interpreter.set_check_alignment(true);
+    assert_eq!(interpreter.step(&mut instance), Ok(()));
+    // Halfword at 0x102 is aligned
+    assert_eq!(interpreter.step(&mut instance), Ok(()));
+    assert_eq!(
+        interpreter.step(&mut instance),
+        Err(Trap::MisalignedAccess(0x102, Access::Read))
+    );
+    assert_eq!(instance.pc(), 8);
+
+    instance.set_pc(12);
+    assert_eq!(
+        interpreter.step(&mut instance),
+        Err(Trap::MisalignedAccess(0x103, Access::Write))
+    );
+    assert_eq!(
+        Trap::MisalignedAccess(0x103, Access::Write).to_string(),
+        "misaligned write at 0x00000103"
+    );
+
+    // Byte accesses are never misaligned
+    instance.set_pc(16);
+    assert_eq!(interpreter.step(&mut instance), Ok(()));
+}
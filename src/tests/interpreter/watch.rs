@@ -0,0 +1,139 @@
+use super::load;
+use crate::{
+    Access, Instance, Instruction, Interpreter, Memory, PageStore, Trap, WatchAction, WatchHit,
+};
+use std::{cell::RefCell, rc::Rc};
+
+/// Instance running `sw x2, 8(x1)` then `lw x3, 6(x1)` with x1 = 0x10000, x2 = 0x1234
+fn program(store: &PageStore) -> Instance {
+    let memory = Memory::new(store, 16, 4);
+    let mut instance = Instance::new(memory);
+    load(
+        &mut instance,
+        0,
+        &[
+            Instruction::Lui { rd: 1, imm: 0x10 },
+            Instruction::Addi {
+                rd: 2,
+                rs1: 0,
+                imm: 0x234,
+            },
+            Instruction::Sw {
+                rs1: 1,
+                rs2: 2,
+                imm: 8,
+            },
+            Instruction::Lw {
+                rd: 3,
+                rs1: 1,
+                imm: 6,
+            },
+            Instruction::Ecall,
+        ],
+    );
+    instance
+}
+
+#[test]
+fn trap_on_write() {
+    let store = PageStore::new(16);
+    let mut instance = program(&store);
+    let mut interpreter = Interpreter::new();
+    interpreter.watch(0x1000a, 2, Access::Write, WatchAction::Trap);
+    assert_eq!(
+        interpreter.run(&mut instance, 10),
+        Err(Trap::Watchpoint(0x10008, Access::Write))
+    );
+    assert_eq!(instance.pc(), 8);
+    assert_eq!(instance.memory().read_u32(0x10008), 0);
+    assert_eq!(
+        Trap::Watchpoint(0x10008, Access::Write).to_string(),
+        "write watchpoint at 0x00010008"
+    );
+}
+
+#[test]
+fn trap_on_read_leaves_register() {
+    let store = PageStore::new(16);
+    let mut instance = program(&store);
+    let mut interpreter = Interpreter::new();
+    interpreter.watch(0x10004, 3, Access::Read, WatchAction::Trap);
+    assert_eq!(
+        interpreter.run(&mut instance, 10),
+        Err(Trap::Watchpoint(0x10006, Access::Read))
+    );
+    assert_eq!(instance.pc(), 12);
+    assert_eq!(instance.read_register(3), 0);
+}
+
+#[test]
+fn notify_and_continue() {
+    let store = PageStore::new(16);
+    let mut instance = program(&store);
+    let mut interpreter = Interpreter::new();
+    let hits = Rc::new(RefCell::new(Vec::new()));
+    let recorded = Rc::clone(&hits);
+    let handler = Box::new(move |hit: &WatchHit| recorded.borrow_mut().push(*hit));
+    interpreter.watch(0x10008, 4, Access::Write, WatchAction::Notify(handler));
+    let recorded = Rc::clone(&hits);
+    let handler = Box::new(move |hit: &WatchHit| recorded.borrow_mut().push(*hit));
+    let index = interpreter.watch(0x10000, 0x10, Access::Read, WatchAction::Notify(handler));
+
+    assert_eq!(interpreter.run(&mut instance, 10), Err(Trap::Ecall));
+    assert_eq!(
+        *hits.borrow(),
+        [
+            WatchHit {
+                index: 0,
+                pc: 8,
+                address: 0x10008,
+                access: Access::Write,
+                value: 0x234,
+            },
+            WatchHit {
+                index,
+                pc: 12,
+                address: 0x10006,
+                access: Access::Read,
+                value: 0x02340000,
+            },
+        ]
+    );
+    assert_eq!(instance.read_register(3), 0x02340000);
+}
+
+#[test]
+fn execute() {
+    let store = PageStore::new(16);
+    let mut instance = program(&store);
+    let mut interpreter = Interpreter::new();
+    interpreter.watch(0x10, 4, Access::Execute, WatchAction::Trap);
+    assert_eq!(
+        interpreter.run(&mut instance, 10),
+        Err(Trap::Watchpoint(0x10, Access::Execute))
+    );
+    assert_eq!(interpreter.retired(), 4);
+}
+
+#[test]
+fn unwatch() {
+    let store = PageStore::new(16);
+    let mut instance = program(&store);
+    let mut interpreter = Interpreter::new();
+    let index = interpreter.watch(0x10008, 4, Access::Write, WatchAction::Trap);
+    assert!(interpreter.unwatch(index));
+    assert!(!interpreter.unwatch(index));
+    assert!(!interpreter.unwatch(7));
+    assert_eq!(interpreter.run(&mut instance, 10), Err(Trap::Ecall));
+}
+
+#[test]
+fn outside_range_and_other_access() {
+    let store = PageStore::new(16);
+    let mut instance = program(&store);
+    let mut interpreter = Interpreter::new();
+    interpreter.watch(0x1000c, 4, Access::Write, WatchAction::Trap);
+    interpreter.watch(0x10008, 4, Access::Execute, WatchAction::Trap);
+    interpreter.watch(0x10000, 0, Access::Read, WatchAction::Trap);
+    assert_eq!(interpreter.run(&mut instance, 10), Err(Trap::Ecall));
+}
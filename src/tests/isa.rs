@@ -0,0 +1,78 @@
+use crate::isa::{self, ENCODINGS, Format};
+use crate::{Extension, Instruction};
+
+#[test]
+fn table() {
+    for encoding in ENCODINGS {
+        assert_eq!(encoding.value & !encoding.mask, 0, "{}", encoding.mnemonic);
+        let instruction = Instruction::decode(encoding.value);
+        assert_eq!(instruction.mnemonic(), encoding.mnemonic);
+        assert_eq!(
+            instruction.extension(),
+            encoding.extension,
+            "{}",
+            encoding.mnemonic
+        );
+        assert!(encoding.matches(encoding.value));
+    }
+}
+
+#[test]
+fn unique() {
+    for (index, encoding) in ENCODINGS.iter().enumerate() {
+        for other in &ENCODINGS[index + 1..] {
+            assert_ne!(encoding.mnemonic, other.mnemonic);
+            // No word can match two entries
+            let common = encoding.mask & other.mask;
+            assert_ne!(
+                encoding.value & common,
+                other.value & common,
+                "{} overlaps {}",
+                encoding.mnemonic,
+                other.mnemonic
+            );
+        }
+    }
+}
+
+#[test]
+fn fields() {
+    let add = isa::lookup("add").unwrap();
+    assert_eq!(
+        (add.opcode(), add.funct3(), add.funct7()),
+        (0x33, Some(0), Some(0))
+    );
+    assert_eq!(isa::lookup("sra").unwrap().funct7(), Some(0x20));
+    assert_eq!(isa::lookup("mul").unwrap().extension, Extension::M);
+    let jal = isa::lookup("jal").unwrap();
+    assert_eq!((jal.format, jal.funct3()), (Format::J, None));
+    assert_eq!(jal.operands, ["rd", "imm"]);
+    assert_eq!(isa::lookup("bogus"), None);
+}
+
+#[test]
+fn matches() {
+    let beq = isa::lookup("beq").unwrap();
+    assert!(beq.matches(0x00208463)); // beq x1, x2, 8
+    assert!(!beq.matches(0x00209463)); // bne x1, x2, 8
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn json() {
+    let json: serde_json::Value = serde_json::from_str(&isa::to_json()).unwrap();
+    let rows = json.as_array().unwrap();
+    assert_eq!(rows.len(), ENCODINGS.len());
+    let add = &rows[0];
+    assert_eq!(add["mnemonic"], "add");
+    assert_eq!(add["extension"], "I");
+    assert_eq!(add["format"], "R");
+    assert_eq!(add["opcode"], 0x33);
+    assert_eq!(add["funct3"], 0);
+    assert_eq!(add["funct7"], 0);
+    assert_eq!(add["mask"], 0xFE00707Fu32);
+    assert_eq!(add["match"], 0x33);
+    assert_eq!(add["operands"], serde_json::json!(["rd", "rs1", "rs2"]));
+    let jal = rows.iter().find(|row| row["mnemonic"] == "jal").unwrap();
+    assert!(jal["funct3"].is_null());
+}
@@ -0,0 +1,104 @@
+use crate::memory::PAGE_SIZE;
+use crate::{Instance, Memory, MemoryError, MemoryLayout, PageStore, Permissions, Segment};
+
+#[test]
+fn default_is_valid() {
+    let layout = MemoryLayout::default();
+    assert_eq!(layout.validate(), Ok(()));
+    assert_eq!(layout.stack_top(), 0x8000_0000);
+    assert_eq!(layout.guard(), Segment::new(0x7fef_c000, PAGE_SIZE as u32));
+}
+
+#[test]
+fn misaligned() {
+    let mut layout = MemoryLayout::default();
+    layout.heap.size += 1;
+    assert_eq!(layout.validate(), Err(MemoryError::Misaligned));
+}
+
+#[test]
+fn overlap() {
+    let mut layout = MemoryLayout::default();
+    layout.rodata.base = layout.text.base + PAGE_SIZE as u32;
+    assert_eq!(layout.validate(), Err(MemoryError::Overlap));
+    // The guard must not overlap the heap either
+    let mut layout = MemoryLayout::default();
+    layout.stack.base = layout.heap.end() as u32;
+    assert_eq!(layout.validate(), Err(MemoryError::Overlap));
+}
+
+#[test]
+fn address_space_limits() {
+    let layout = MemoryLayout {
+        stack: Segment::new(0xfff0_0000, 0x0020_0000),
+        ..Default::default()
+    };
+    assert_eq!(layout.validate(), Err(MemoryError::PageLimit));
+
+    let layout = MemoryLayout {
+        text: Segment::new(0, 0),
+        stack: Segment::new(0, 0x0010_0000),
+        ..Default::default()
+    };
+    assert_eq!(layout.validate(), Err(MemoryError::PageLimit));
+
+    // A stack reaching the top of the address space wraps the stack pointer
+    let layout = MemoryLayout {
+        stack: Segment::new(0xfff0_0000, 0x0010_0000),
+        ..Default::default()
+    };
+    assert_eq!(layout.validate(), Ok(()));
+    assert_eq!(layout.stack_top(), 0);
+}
+
+#[test]
+fn apply() {
+    let store = PageStore::new(64);
+    let layout = MemoryLayout::default();
+    let mut memory = Memory::new(&store, 64, 8);
+    memory.apply_layout(&layout).unwrap();
+
+    assert_eq!(
+        memory.permissions(layout.text.base),
+        Permissions::READ_EXECUTE
+    );
+    assert_eq!(
+        memory.permissions(layout.rodata.base),
+        Permissions::READ_ONLY
+    );
+    assert_eq!(
+        memory.permissions(layout.heap.base),
+        Permissions::READ_WRITE
+    );
+    assert_eq!(
+        memory.permissions(layout.stack_top() - 4),
+        Permissions::READ_WRITE
+    );
+    assert!(memory.guarded(layout.stack.base - 1));
+    assert!(!memory.guarded(layout.stack.base));
+    assert_eq!(memory.num_pages, 0);
+
+    assert_eq!(memory.heap_limit(), 1024);
+    assert_eq!(memory.grow(1024), Err(MemoryError::PageLimit));
+    assert_eq!(memory.grow(4), Ok(0));
+    assert_eq!(memory.brk(), layout.heap.base + 4 * PAGE_SIZE as u32);
+}
+
+#[test]
+fn apply_invalid() {
+    let store = PageStore::new(16);
+    let mut layout = MemoryLayout::default();
+    layout.text.base += 1;
+    let mut memory = Memory::new(&store, 16, 8);
+    assert_eq!(memory.apply_layout(&layout), Err(MemoryError::Misaligned));
+    assert_eq!(memory.heap_limit(), u32::MAX);
+}
+
+#[test]
+fn setup_stack() {
+    let store = PageStore::new(16);
+    let layout = MemoryLayout::default();
+    let mut instance = Instance::new(Memory::new(&store, 16, 8));
+    instance.setup_stack(&layout);
+    assert_eq!(instance.read_register(2), 0x8000_0000);
+}
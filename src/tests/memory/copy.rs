@@ -0,0 +1,103 @@
+use crate::memory::{Memory, MemoryError, PAGE_SIZE, PageStore};
+
+fn pattern(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i % 251) as u8 + 1).collect()
+}
+
+#[test]
+fn copy_across_pages() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    let data = pattern(PAGE_SIZE + 100);
+    mem.write(10, &data).unwrap();
+    mem.copy_within(10, 3 * PAGE_SIZE as u32 - 7, data.len() as u32)
+        .unwrap();
+    let mut copy = vec![0u8; data.len()];
+    mem.read(3 * PAGE_SIZE as u32 - 7, &mut copy);
+    assert_eq!(copy, data);
+}
+
+#[test]
+fn overlapping_forward_and_backward() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    let data = pattern(2 * PAGE_SIZE);
+    let mut expected = vec![0u8; 4 * PAGE_SIZE];
+    expected[100..100 + data.len()].copy_from_slice(&data);
+    mem.write(100, &data).unwrap();
+
+    // Destination above the source
+    mem.copy_within(100, 300, data.len() as u32).unwrap();
+    expected.copy_within(100..100 + data.len(), 300);
+    let mut actual = vec![0u8; expected.len()];
+    mem.read(0, &mut actual);
+    assert_eq!(actual, expected);
+
+    // Destination below the source
+    mem.copy_within(300, 5, data.len() as u32).unwrap();
+    expected.copy_within(300..300 + data.len(), 5);
+    mem.read(0, &mut actual);
+    assert_eq!(actual, expected);
+}
+
+#[test]
+fn unallocated_source_reads_as_zeros() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    mem.fill(0x1000, 0x20, 0xff).unwrap();
+    mem.copy_within(0x10000, 0x1008, 8).unwrap();
+    assert_eq!(mem.read_u64(0x1000), u64::MAX);
+    assert_eq!(mem.read_u64(0x1008), 0);
+    assert_eq!(mem.read_u64(0x1010), u64::MAX);
+
+    // Copying zeros into unallocated pages allocates nothing
+    mem.copy_within(0x10000, 0x20000, 2 * PAGE_SIZE as u32)
+        .unwrap();
+    assert_eq!(mem.num_pages, 1);
+}
+
+#[test]
+fn copy_wraps() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    mem.write(0xffff_fffe, &[1, 2, 3, 4]).unwrap();
+    mem.copy_within(0xffff_fffe, 0x100, 4).unwrap();
+    assert_eq!(mem.read_u32(0x100), 0x0403_0201);
+}
+
+#[test]
+fn copy_errors() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 2, 2);
+    mem.write(0, &[1]).unwrap();
+    mem.write(PAGE_SIZE as u32, &[2]).unwrap();
+    assert_eq!(
+        mem.copy_within(0, 0x10000, 2 * PAGE_SIZE as u32),
+        Err(MemoryError::PageLimit)
+    );
+    mem.reset();
+    mem.write(0, &[1]).unwrap();
+
+    mem.guard(0x100000, 1).unwrap();
+    assert_eq!(mem.copy_within(0, 0x100000, 1), Err(MemoryError::Guard));
+
+    let shared = store.share(b"shared").unwrap();
+    mem.map_shared(0x200000, &shared).unwrap();
+    assert_eq!(mem.copy_within(0, 0x200000, 1), Err(MemoryError::ReadOnly));
+    // Shared pages can be a source
+    mem.copy_within(0x200000, 8, 6).unwrap();
+    let mut text = [0u8; 6];
+    mem.read(8, &mut text);
+    assert_eq!(&text, b"shared");
+}
+
+#[test]
+fn copy_invalidates_reservation() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    mem.reserve(0x104, 4);
+    mem.copy_within(0x100, 0x200, 8).unwrap();
+    assert!(mem.reservation().is_some());
+    mem.copy_within(0x200, 0x100, 8).unwrap();
+    assert!(mem.reservation().is_none());
+}
@@ -0,0 +1,67 @@
+use crate::memory::{Memory, MemoryError, PAGE_SIZE, PageStore};
+
+#[test]
+fn fill_across_pages() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    mem.fill(PAGE_SIZE as u32 - 2, PAGE_SIZE as u32 + 4, 0xaa)
+        .unwrap();
+    assert_eq!(mem.num_pages, 3);
+    assert_eq!(mem.read_u8(PAGE_SIZE as u32 - 3), 0);
+    assert_eq!(mem.read_u16(PAGE_SIZE as u32 - 2), 0xaaaa);
+    assert_eq!(mem.read_u16(2 * PAGE_SIZE as u32), 0xaaaa);
+    assert_eq!(mem.read_u8(2 * PAGE_SIZE as u32 + 2), 0);
+
+    let mut page = vec![0u8; PAGE_SIZE];
+    mem.read(PAGE_SIZE as u32, &mut page);
+    assert!(page.iter().all(|&byte| byte == 0xaa));
+}
+
+#[test]
+fn zero_fill_skips_unallocated() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    mem.write(PAGE_SIZE as u32 + 8, &[1, 2, 3]).unwrap();
+    mem.fill(0, 4 * PAGE_SIZE as u32, 0).unwrap();
+    assert_eq!(mem.num_pages, 1);
+    assert_eq!(mem.read_u32(PAGE_SIZE as u32 + 8), 0);
+}
+
+#[test]
+fn fill_wraps() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    mem.fill(0xffff_fffe, 4, 0x55).unwrap();
+    assert_eq!(mem.read_u16(0xffff_fffe), 0x5555);
+    assert_eq!(mem.read_u16(0), 0x5555);
+}
+
+#[test]
+fn fill_errors() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 2, 2);
+    assert_eq!(
+        mem.fill(0, 3 * PAGE_SIZE as u32, 1),
+        Err(MemoryError::PageLimit)
+    );
+    assert_eq!(mem.read_u8(2 * PAGE_SIZE as u32 - 1), 1);
+
+    mem.guard(0x100000, 1).unwrap();
+    assert_eq!(mem.fill(0x100000, 1, 0), Err(MemoryError::Guard));
+
+    let shared = store.share(b"shared").unwrap();
+    mem.map_shared(0x200000, &shared).unwrap();
+    assert_eq!(mem.fill(0x200000, 1, 0), Err(MemoryError::ReadOnly));
+}
+
+#[test]
+fn fill_host_buffer() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    let mut buffer = vec![1u8; PAGE_SIZE];
+    unsafe { mem.map_host(0x4000, &mut buffer).unwrap() };
+    mem.fill(0x4000, 16, 0).unwrap();
+    mem.unmap_host(0x4000);
+    assert!(buffer[..16].iter().all(|&byte| byte == 0));
+    assert_eq!(buffer[16], 1);
+}
@@ -0,0 +1,67 @@
+use crate::memory::{Memory, PAGE_SIZE, PageStore};
+
+const ALL: std::ops::Range<u64> = 0..1 << 32;
+
+#[test]
+fn find_in_page() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    mem.write(0x2010, b"needle in a haystack").unwrap();
+    assert_eq!(mem.find(b"hay", ALL), Some(0x201c));
+    assert_eq!(mem.find(b"needle", 0x2000..0x3000), Some(0x2010));
+    assert_eq!(mem.find(b"needle", 0x2011..0x3000), None);
+    assert_eq!(mem.find(b"needle", 0..0x2015), None);
+    assert_eq!(mem.find(b"missing", ALL), None);
+}
+
+#[test]
+fn find_across_pages() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    let address = 3 * PAGE_SIZE as u32 - 3;
+    mem.write(address, b"boundary").unwrap();
+    assert_eq!(mem.find(b"boundary", ALL), Some(address));
+    assert_eq!(mem.find(b"ary", ALL), Some(address + 5));
+}
+
+#[test]
+fn skips_unmapped_pages() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    assert_eq!(mem.find(&[0], ALL), None);
+
+    // A match cannot cross an unallocated page
+    mem.write(PAGE_SIZE as u32 - 2, b"ab").unwrap();
+    mem.write(2 * PAGE_SIZE as u32, b"cd").unwrap();
+    assert_eq!(mem.find(b"abcd", ALL), None);
+    assert_eq!(mem.find(b"cd", ALL), Some(2 * PAGE_SIZE as u32));
+    assert_eq!(
+        mem.find(&[0; 4], PAGE_SIZE as u64..ALL.end),
+        Some(2 * PAGE_SIZE as u32 + 2)
+    );
+}
+
+#[test]
+fn find_in_host_and_shared_pages() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    let shared = store.share(b"shared text").unwrap();
+    mem.map_shared(0x10000, &shared).unwrap();
+    let mut buffer = vec![0u8; PAGE_SIZE];
+    buffer[100..104].copy_from_slice(b"host");
+    unsafe { mem.map_host(0x20000, &mut buffer).unwrap() };
+    assert_eq!(mem.find(b"text", ALL), Some(0x10007));
+    assert_eq!(mem.find(b"host", ALL), Some(0x20064));
+    mem.unmap_host(0x20000);
+}
+
+#[test]
+fn empty_needle_and_ranges() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    mem.write(0xffff_fff0, b"top").unwrap();
+    assert_eq!(mem.find(b"top", 0xffff_0000..u64::MAX), Some(0xffff_fff0));
+    assert_eq!(mem.find(b"", 0x40..0x50), Some(0x40));
+    assert_eq!(mem.find(b"", 0x50..0x50), None);
+    assert_eq!(mem.find(b"top", 1 << 32..u64::MAX), None);
+}
@@ -0,0 +1,96 @@
+use crate::memory::{Access, Memory, MemoryError, PAGE_SIZE, PageStore};
+
+#[test]
+fn write_faults() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.guard(PAGE_SIZE as u32, PAGE_SIZE as u32), Ok(()));
+    assert!(memory.guarded(PAGE_SIZE as u32));
+    assert!(memory.guarded(2 * PAGE_SIZE as u32 - 1));
+    assert!(!memory.guarded(2 * PAGE_SIZE as u32));
+    assert!(!memory.guarded(PAGE_SIZE as u32 - 1));
+
+    assert_eq!(
+        memory.write(PAGE_SIZE as u32 + 8, &[1]),
+        Err(MemoryError::Guard)
+    );
+    assert_eq!(
+        memory.allocate_page(PAGE_SIZE as u32),
+        Err(MemoryError::Guard)
+    );
+    assert_eq!(memory.num_pages, 0);
+    assert_eq!(memory.read_u32(PAGE_SIZE as u32), 0);
+}
+
+#[test]
+fn rounds_out_to_pages() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.guard(PAGE_SIZE as u32 - 1, 2), Ok(()));
+    assert!(memory.guarded(0));
+    assert!(memory.guarded(PAGE_SIZE as u32));
+    assert!(!memory.guarded(2 * PAGE_SIZE as u32));
+}
+
+#[test]
+fn empty_range() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.guard(0, 0), Ok(()));
+    assert!(!memory.guarded(0));
+}
+
+#[test]
+fn wraparound() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.guard(0xFFFF_FFFF, 2), Ok(()));
+    assert!(memory.guarded(0xFFFF_C000));
+    assert!(memory.guarded(0));
+    assert!(!memory.guarded(PAGE_SIZE as u32));
+}
+
+#[test]
+fn check_rejects_every_access() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.guard(0, PAGE_SIZE as u32), Ok(()));
+    for access in [Access::Read, Access::Write, Access::Execute] {
+        assert_eq!(memory.check(0, 4, access), Err(MemoryError::Guard));
+        assert_eq!(
+            memory.check(PAGE_SIZE as u32 - 2, 4, access),
+            Err(MemoryError::Guard)
+        );
+        assert_eq!(memory.check(PAGE_SIZE as u32, 4, access), Ok(()));
+    }
+}
+
+#[test]
+fn overlap() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.allocate_page(0), Ok(()));
+    assert_eq!(
+        memory.guard(0, 2 * PAGE_SIZE as u32),
+        Err(MemoryError::Overlap)
+    );
+    assert!(!memory.guarded(PAGE_SIZE as u32));
+
+    assert_eq!(memory.guard(PAGE_SIZE as u32, PAGE_SIZE as u32), Ok(()));
+    let mut buffer = vec![0u8; PAGE_SIZE];
+    assert_eq!(
+        unsafe { memory.map_host(PAGE_SIZE as u32, &mut buffer) },
+        Err(MemoryError::Overlap)
+    );
+}
+
+#[test]
+fn kept_across_reset() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.guard(0, PAGE_SIZE as u32), Ok(()));
+    assert_eq!(memory.write(PAGE_SIZE as u32, &[1]), Ok(()));
+    memory.reset();
+    assert!(memory.guarded(0));
+    assert_eq!(memory.write(0, &[1]), Err(MemoryError::Guard));
+}
@@ -0,0 +1,98 @@
+use crate::memory::{Memory, MemoryError, PAGE_SIZE, PageStore};
+
+#[test]
+fn grow_returns_old_size() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.set_heap_base(0x10000), Ok(()));
+    assert_eq!(memory.heap_size(), 0);
+    assert_eq!(memory.brk(), 0x10000);
+
+    assert_eq!(memory.grow(2), Ok(0));
+    assert_eq!(memory.grow(1), Ok(2));
+    assert_eq!(memory.grow(0), Ok(3));
+    assert_eq!(memory.heap_size(), 3);
+    assert_eq!(memory.brk(), 0x10000 + 3 * PAGE_SIZE as u32);
+    assert_eq!(memory.num_pages, 3);
+}
+
+#[test]
+fn grown_pages_are_zeroed_and_writable() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.grow(1), Ok(0));
+    assert_eq!(memory.read_u64(0x100), 0);
+    assert_eq!(memory.write_u32(0x100, 7), Ok(()));
+    assert_eq!(memory.num_pages, 1);
+}
+
+#[test]
+fn page_limit() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 2, 2);
+    assert_eq!(memory.grow(3), Err(MemoryError::PageLimit));
+    assert_eq!(memory.heap_size(), 0);
+    assert_eq!(memory.grow(2), Ok(0));
+    assert_eq!(memory.grow(1), Err(MemoryError::PageLimit));
+    assert_eq!(memory.heap_size(), 2);
+}
+
+#[test]
+fn top_of_address_space() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    let base = 0u32.wrapping_sub(2 * PAGE_SIZE as u32);
+    assert_eq!(memory.set_heap_base(base), Ok(()));
+    assert_eq!(memory.grow(3), Err(MemoryError::PageLimit));
+    assert_eq!(memory.num_pages, 0);
+    assert_eq!(memory.grow(2), Ok(0));
+    assert_eq!(memory.brk(), 0);
+}
+
+#[test]
+fn guard_stops_growth() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.guard(2 * PAGE_SIZE as u32, PAGE_SIZE as u32), Ok(()));
+    assert_eq!(memory.grow(3), Err(MemoryError::Guard));
+    assert_eq!(memory.heap_size(), 0);
+    assert_eq!(memory.grow(2), Ok(0));
+}
+
+#[test]
+fn set_heap_base_errors() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.set_heap_base(0x100), Err(MemoryError::Misaligned));
+    assert_eq!(memory.grow(1), Ok(0));
+    assert_eq!(
+        memory.set_heap_base(PAGE_SIZE as u32),
+        Err(MemoryError::Overlap)
+    );
+}
+
+#[test]
+fn reset_empties_heap() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.set_heap_base(0x40000), Ok(()));
+    assert_eq!(memory.grow(2), Ok(0));
+    memory.reset();
+    assert_eq!(memory.heap_size(), 0);
+    assert_eq!(memory.brk(), 0x40000);
+    assert_eq!(memory.grow(1), Ok(0));
+}
+
+#[test]
+fn limit() {
+    let store = PageStore::new(16);
+    let mut mem = Memory::new(&store, 16, 4);
+    assert_eq!(mem.heap_limit(), u32::MAX);
+    mem.set_heap_limit(2).unwrap();
+    assert_eq!(mem.grow(3), Err(MemoryError::PageLimit));
+    assert_eq!(mem.grow(2), Ok(0));
+    assert_eq!(mem.grow(1), Err(MemoryError::PageLimit));
+    assert_eq!(mem.set_heap_limit(1), Err(MemoryError::Overlap));
+    mem.set_heap_limit(3).unwrap();
+    assert_eq!(mem.grow(1), Ok(2));
+}
@@ -0,0 +1,129 @@
+use crate::memory::{Access, Memory, MemoryError, PAGE_SIZE, PageStore, Permissions};
+
+#[test]
+fn read_and_write_through_mapping() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    let mut buffer = vec![0u8; 2 * PAGE_SIZE];
+    buffer[0] = 0x11;
+    buffer[PAGE_SIZE] = 0x22;
+    unsafe { memory.map_host(0x10000, &mut buffer) }.unwrap();
+
+    assert_eq!(memory.read_u8(0x10000), 0x11);
+    assert_eq!(memory.read_u8(0x10000 + PAGE_SIZE as u32), 0x22);
+    assert_eq!(
+        memory.write_u32(0x10000 + PAGE_SIZE as u32 - 2, 0xAABBCCDD),
+        Ok(())
+    );
+    assert_eq!(memory.num_pages, 0);
+
+    assert!(memory.unmap_host(0x10000));
+    assert_eq!(
+        &buffer[PAGE_SIZE - 2..PAGE_SIZE + 2],
+        &[0xDD, 0xCC, 0xBB, 0xAA]
+    );
+}
+
+#[test]
+fn unmapped_range_reads_zero() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    let mut buffer = vec![0x55u8; PAGE_SIZE];
+    unsafe { memory.map_host(0, &mut buffer) }.unwrap();
+    assert_eq!(memory.read_u8(0), 0x55);
+    assert!(memory.unmap_host(0));
+    assert!(!memory.unmap_host(0));
+    assert_eq!(memory.read_u8(0), 0);
+}
+
+#[test]
+fn write_spanning_pool_and_host_pages() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    let mut buffer = vec![0u8; PAGE_SIZE];
+    unsafe { memory.map_host(PAGE_SIZE as u32, &mut buffer) }.unwrap();
+    assert_eq!(memory.write(PAGE_SIZE as u32 - 2, &[1, 2, 3, 4]), Ok(()));
+    assert_eq!(memory.num_pages, 1);
+    assert_eq!(memory.read_array::<4>(PAGE_SIZE as u32 - 2), [1, 2, 3, 4]);
+    memory.reset();
+    assert_eq!(&buffer[..2], &[3, 4]);
+}
+
+#[test]
+fn misaligned() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    let mut buffer = vec![0u8; 2 * PAGE_SIZE];
+    unsafe {
+        assert_eq!(
+            memory.map_host(0x100, &mut buffer),
+            Err(MemoryError::Misaligned)
+        );
+        assert_eq!(
+            memory.map_host(0, &mut buffer[..PAGE_SIZE + 1]),
+            Err(MemoryError::Misaligned)
+        );
+        assert_eq!(
+            memory.map_host(0, &mut buffer[..0]),
+            Err(MemoryError::Misaligned)
+        );
+        assert_eq!(
+            memory.map_host(0u32.wrapping_sub(PAGE_SIZE as u32), &mut buffer),
+            Err(MemoryError::Misaligned)
+        );
+    }
+}
+
+#[test]
+fn overlap() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    let mut first = vec![0u8; PAGE_SIZE];
+    let mut second = vec![0u8; 2 * PAGE_SIZE];
+    assert_eq!(memory.allocate_page(0), Ok(()));
+    unsafe {
+        assert_eq!(memory.map_host(0, &mut first), Err(MemoryError::Overlap));
+        assert_eq!(memory.map_host(PAGE_SIZE as u32, &mut first), Ok(()));
+        assert_eq!(
+            memory.map_host(PAGE_SIZE as u32, &mut second),
+            Err(MemoryError::Overlap)
+        );
+        assert_eq!(memory.map_host(2 * PAGE_SIZE as u32, &mut second), Ok(()));
+    }
+}
+
+#[test]
+fn allocate_mapped_page() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    let mut buffer = vec![0u8; PAGE_SIZE];
+    unsafe { memory.map_host(0, &mut buffer) }.unwrap();
+    assert_eq!(memory.allocate_page(0x10), Ok(()));
+    assert_eq!(memory.num_pages, 0);
+}
+
+#[test]
+fn permissions_apply() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    let mut buffer = vec![0u8; PAGE_SIZE];
+    unsafe { memory.map_host(0, &mut buffer) }.unwrap();
+    memory
+        .protect(0, PAGE_SIZE as u32, Permissions::READ_ONLY)
+        .unwrap();
+    assert_eq!(
+        memory.check(0, 4, Access::Write),
+        Err(MemoryError::Permission)
+    );
+}
+
+#[test]
+fn reset_unmaps() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    let mut buffer = vec![0x77u8; PAGE_SIZE];
+    unsafe { memory.map_host(0, &mut buffer) }.unwrap();
+    memory.reset();
+    assert_eq!(memory.read_u8(0), 0);
+    assert!(!memory.unmap_host(0));
+}
@@ -0,0 +1,109 @@
+use crate::memory::{Memory, MemoryError, PageStore};
+use crate::mmio::MmioDevice;
+use std::any::Any;
+
+/// Device with a single 32-bit register at every offset
+#[derive(Default)]
+struct Register(u32);
+
+impl MmioDevice for Register {
+    fn read(&mut self, _offset: u32, _size: u32) -> u32 {
+        self.0
+    }
+
+    fn write(&mut self, _offset: u32, _size: u32, value: u32) {
+        self.0 = value;
+    }
+}
+
+/// Device using the default callbacks
+struct Empty;
+
+impl MmioDevice for Empty {}
+
+#[test]
+fn read_write() {
+    let store = PageStore::new(4);
+    let mut mem = Memory::new(&store, 4, 1);
+    mem.map_mmio(0x1000, 16, Box::new(Register::default()))
+        .unwrap();
+    assert!(mem.mmio_write(0x1004, 4, 0x1234_5678));
+    assert_eq!(mem.mmio_read(0x100c, 4), Some(0x1234_5678));
+    assert_eq!(mem.mmio_read(0x1000, 2), Some(0x5678));
+    assert_eq!(mem.mmio_read(0x1000, 1), Some(0x78));
+
+    // Stores are truncated to the access size
+    assert!(mem.mmio_write(0x1000, 1, 0xabcd));
+    assert_eq!(mem.mmio_read(0x1000, 4), Some(0xcd));
+
+    // Host accesses still go to RAM
+    assert_eq!(mem.read_u32(0x1000), 0);
+    assert_eq!(mem.num_pages, 0);
+}
+
+#[test]
+fn outside_or_straddling() {
+    let store = PageStore::new(4);
+    let mut mem = Memory::new(&store, 4, 1);
+    mem.map_mmio(0x1000, 8, Box::new(Empty)).unwrap();
+    assert_eq!(mem.mmio_read(0x1008, 4), None);
+    assert_eq!(mem.mmio_read(0x0ffe, 4), None);
+    assert_eq!(mem.mmio_read(0x1006, 4), None);
+    assert!(!mem.mmio_write(0x1006, 4, 0));
+    assert_eq!(mem.mmio_read(0x1004, 4), Some(0));
+
+    assert!(mem.mmio(0x1006, 4));
+    assert!(mem.mmio(0x0ffe, 4));
+    assert!(!mem.mmio(0x0ffc, 4));
+    assert!(!mem.mmio(0x1008, 4));
+}
+
+#[test]
+fn overlap() {
+    let store = PageStore::new(4);
+    let mut mem = Memory::new(&store, 4, 1);
+    mem.map_mmio(0x1000, 8, Box::new(Empty)).unwrap();
+    assert_eq!(
+        mem.map_mmio(0x1004, 8, Box::new(Empty)),
+        Err(MemoryError::Overlap)
+    );
+    assert_eq!(
+        mem.map_mmio(0x0ffc, 8, Box::new(Empty)),
+        Err(MemoryError::Overlap)
+    );
+    assert_eq!(
+        mem.map_mmio(0x2000, 0, Box::new(Empty)),
+        Err(MemoryError::Overlap)
+    );
+    assert_eq!(
+        mem.map_mmio(0xffff_fff0, 0x20, Box::new(Empty)),
+        Err(MemoryError::Overlap)
+    );
+    assert_eq!(mem.map_mmio(0x1008, 8, Box::new(Empty)), Ok(()));
+    assert_eq!(mem.map_mmio(0xffff_fff0, 0x10, Box::new(Empty)), Ok(()));
+}
+
+#[test]
+fn unmap_returns_device() {
+    let store = PageStore::new(4);
+    let mut mem = Memory::new(&store, 4, 1);
+    mem.map_mmio(0x1000, 4, Box::new(Register::default()))
+        .unwrap();
+    mem.mmio_write(0x1000, 4, 7);
+    assert!(mem.unmap_mmio(0x1004).is_none());
+
+    let device: Box<dyn Any> = mem.unmap_mmio(0x1000).unwrap();
+    assert_eq!(device.downcast::<Register>().unwrap().0, 7);
+    assert_eq!(mem.mmio_read(0x1000, 4), None);
+}
+
+#[test]
+fn kept_across_reset() {
+    let store = PageStore::new(4);
+    let mut mem = Memory::new(&store, 4, 1);
+    mem.map_mmio(0x1000, 4, Box::new(Register(3))).unwrap();
+    mem.reset();
+    assert_eq!(mem.mmio_read(0x1000, 4), Some(3));
+    mem.soft_reset();
+    assert_eq!(mem.mmio_read(0x1000, 4), Some(3));
+}
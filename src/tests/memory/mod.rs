@@ -1,9 +1,26 @@
 mod allocation;
+mod basics;
 mod boundaries;
+mod copy;
 mod edge_cases;
-mod memory;
+mod fill;
+mod find;
+mod guard;
+mod heap;
+mod host;
+mod mmio;
 mod page_store;
+mod permissions;
+mod poison;
+mod quota;
 mod read;
+mod regions;
+mod reservation;
 mod reset;
+mod shared;
+mod stats;
 mod stress;
+mod strings;
+mod translate;
+mod typed;
 mod write;
@@ -1,28 +1,30 @@
-use crate::memory::{MAX_PAGES, PAGE_SIZE, PageStore};
+use crate::memory::{MAX_PAGES, Memory, MemoryError, PAGE_SIZE, PageEvent, PageStore, PageTuning};
+use std::cell::RefCell;
+use std::rc::Rc;
 
 #[test]
 fn basic() {
     let store = PageStore::new(10);
-    assert_eq!(store.num_available_pages, 10);
-    assert_eq!(store.instance_count, 0);
-    assert_eq!(store.page_memory_size, 10 * PAGE_SIZE);
-    assert_eq!(store.available_pages_capacity, 10);
+    assert_eq!(store.available_pages(), 10);
+    assert_eq!(store.instance_count(), 0);
+    assert_eq!((store.total_pages() * PAGE_SIZE), 10 * PAGE_SIZE);
+    assert_eq!(store.total_pages(), 10);
 }
 
 #[test]
 fn zero_pages() {
     let store = PageStore::new(0);
-    assert_eq!(store.num_available_pages, 0);
-    assert_eq!(store.page_memory_size, 0);
-    assert_eq!(store.available_pages_capacity, 0);
+    assert_eq!(store.available_pages(), 0);
+    assert_eq!((store.total_pages() * PAGE_SIZE), 0);
+    assert_eq!(store.total_pages(), 0);
 }
 
 #[test]
 fn max_pages() {
     let store = PageStore::new(MAX_PAGES);
-    assert_eq!(store.num_available_pages, MAX_PAGES);
-    assert_eq!(store.page_memory_size, MAX_PAGES * PAGE_SIZE);
-    assert_eq!(store.available_pages_capacity, MAX_PAGES);
+    assert_eq!(store.available_pages(), MAX_PAGES);
+    assert_eq!((store.total_pages() * PAGE_SIZE), MAX_PAGES * PAGE_SIZE);
+    assert_eq!(store.total_pages(), MAX_PAGES);
 }
 
 #[test]
@@ -33,22 +35,30 @@ fn exceeds_max_pages() {
 
 #[test]
 fn available_pages_initialization() {
+    // Free pages are handed out from the end of the initial list 0..5
     let store = PageStore::new(5);
-    unsafe {
-        assert_eq!(*store.available_pages.add(0), 0);
-        assert_eq!(*store.available_pages.add(1), 1);
-        assert_eq!(*store.available_pages.add(2), 2);
-        assert_eq!(*store.available_pages.add(3), 3);
-        assert_eq!(*store.available_pages.add(4), 4);
+    let events = record(&store);
+    let mut mem = Memory::new(&store, 5, 1);
+    for page in 0..5 {
+        mem.write_u8(page * PAGE_SIZE as u32, 1).unwrap();
     }
+    let pages: Vec<u16> = events
+        .borrow()
+        .iter()
+        .map(|event| match event {
+            PageEvent::Allocated { page, .. } => *page,
+            _ => u16::MAX,
+        })
+        .collect();
+    assert_eq!(pages, [4, 3, 2, 1, 0]);
 }
 
 #[test]
 fn page_memory_zeroed() {
     let store = PageStore::new(2);
     unsafe {
-        for i in 0..store.page_memory_size {
-            assert_eq!(*store.page_memory.add(i), 0);
+        for i in 0..(store.total_pages() * PAGE_SIZE) {
+            assert_eq!(*store.page_memory().add(i), 0);
         }
     }
 }
@@ -60,17 +70,255 @@ fn drop_with_no_instances() {
 }
 
 #[test]
-#[should_panic(expected = "PageStore dropped while 1 Memory instance(s) still exist")]
 fn drop_with_active_instance() {
-    let mut store = PageStore::new(10);
-    store.instance_count = 1;
-    drop(store);
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 2);
+    assert_eq!(mem.write(0x1000, &[1, 2, 3]), Ok(()));
+    drop(store); // Memory keeps the pool alive
+
+    let mut buffer = [0u8; 3];
+    mem.read(0x1000, &mut buffer);
+    assert_eq!(buffer, [1, 2, 3]);
+    assert_eq!(mem.write(0x8000, &[4]), Ok(()));
+    mem.reset();
 }
 
 #[test]
-#[should_panic(expected = "PageStore dropped while 3 Memory instance(s) still exist")]
 fn drop_with_multiple_instances() {
-    let mut store = PageStore::new(10);
-    store.instance_count = 3;
+    let store = PageStore::new(10);
+    let mut mem1 = Memory::new(&store, 5, 2);
+    let mem2 = Memory::new(&store, 5, 2);
+    assert_eq!(mem1.allocate_page(0), Ok(()));
     drop(store);
+    drop(mem1);
+    drop(mem2);
+}
+
+#[test]
+fn pages_return_after_store_dropped() {
+    let store = PageStore::new(10);
+    let mut mem1 = Memory::new(&store, 10, 2);
+    let mut mem2 = Memory::new(&store, 10, 2);
+    drop(store);
+
+    for page in 0..10 {
+        assert_eq!(mem1.allocate_page(page * PAGE_SIZE as u32), Ok(()));
+    }
+    assert_eq!(mem2.allocate_page(0), Err(MemoryError::NoPagesAvailable));
+    drop(mem1);
+    assert_eq!(mem2.allocate_page(0), Ok(()));
+}
+
+#[test]
+fn default_tuning() {
+    let store = PageStore::new(4);
+    assert_eq!(store.tuning(), PageTuning::default());
+}
+
+#[test]
+fn huge_pages() {
+    let tuning = PageTuning {
+        huge_pages: true,
+        release_on_return: false,
+    };
+    let store = PageStore::with_tuning(4, tuning);
+    assert_eq!(store.tuning(), tuning);
+    assert_eq!((store.total_pages() * PAGE_SIZE), 4 * PAGE_SIZE);
+    assert_eq!(store.page_memory() as usize % (2 << 20), 0);
+
+    let mut mem = Memory::new(&store, 4, 1);
+    mem.write(0x4000, &[1, 2, 3]).unwrap();
+    mem.reset();
+    mem.allocate_page(0x4000).unwrap();
+    let mut buffer = [0xAA; 3];
+    mem.read(0x4000, &mut buffer);
+    assert_eq!(buffer, [0, 0, 0]);
+    assert_eq!(store.stats().pages_released, 0);
+}
+
+#[test]
+fn release_on_return() {
+    let store = PageStore::with_tuning(
+        4,
+        PageTuning {
+            huge_pages: false,
+            release_on_return: true,
+        },
+    );
+    let mut mem = Memory::new(&store, 4, 1);
+    for page in 0..4 {
+        mem.write(page * PAGE_SIZE as u32, &[0xFF; PAGE_SIZE])
+            .unwrap();
+    }
+    mem.reset();
+    assert_eq!(store.stats().pages_released, 4);
+    assert_eq!(store.stats().pages_zeroed, 4);
+
+    // Released pages read as zeros when taken again
+    for page in 0..4 {
+        mem.allocate_page(page * PAGE_SIZE as u32).unwrap();
+    }
+    let mut buffer = vec![0xAA; 4 * PAGE_SIZE];
+    mem.read(0, &mut buffer);
+    assert!(buffer.iter().all(|&byte| byte == 0));
+}
+
+#[test]
+fn tuned_zero_pages() {
+    let store = PageStore::with_tuning(
+        0,
+        PageTuning {
+            huge_pages: true,
+            release_on_return: true,
+        },
+    );
+    assert_eq!(store.available_pages(), 0);
+    assert_eq!((store.total_pages() * PAGE_SIZE), 0);
+}
+
+#[test]
+fn grow() {
+    let store = PageStore::new(2);
+    let mut mem1 = Memory::new(&store, 2, 1);
+    let mut mem2 = Memory::new(&store, 2, 1);
+    mem1.write(0, &[1; 2 * PAGE_SIZE]).unwrap();
+    assert_eq!(mem2.allocate_page(0), Err(MemoryError::NoPagesAvailable));
+    let page_memory = store.page_memory();
+
+    store.grow(3).unwrap();
+    assert_eq!(store.page_memory(), page_memory);
+    assert_eq!(store.available_pages(), 3);
+    assert_eq!(store.total_pages(), 5);
+    assert_eq!((store.total_pages() * PAGE_SIZE), 5 * PAGE_SIZE);
+
+    // Existing instances can use the new pages and keep their contents
+    mem2.write(0, &[2; 2 * PAGE_SIZE]).unwrap();
+    assert_eq!(mem1.read_u8(2 * PAGE_SIZE as u32 - 1), 1);
+    assert_eq!(mem2.read_u8(2 * PAGE_SIZE as u32 - 1), 2);
+    let mut mem3 = Memory::new(&store, 1, 1);
+    assert_eq!(mem3.allocate_page(0), Ok(()));
+    drop(mem1);
+    drop(mem2);
+    drop(mem3);
+    assert_eq!(store.available_pages(), 5);
+}
+
+#[test]
+fn grow_from_empty() {
+    let store = PageStore::new(0);
+    store.grow(1).unwrap();
+    let mut mem = Memory::new(&store, 1, 1);
+    mem.write(0, b"grown").unwrap();
+    assert_eq!(mem.read_u8(0), b'g');
+}
+
+#[test]
+fn grow_past_max_pages() {
+    let store = PageStore::new(MAX_PAGES - 1);
+    assert_eq!(store.grow(2), Err(MemoryError::PageLimit));
+    assert_eq!(store.total_pages(), MAX_PAGES - 1);
+    assert_eq!(store.grow(1), Ok(()));
+    assert_eq!(store.available_pages(), MAX_PAGES);
+
+    // Counts that overflow are over the limit too
+    let store = PageStore::new(1);
+    assert_eq!(store.grow(usize::MAX), Err(MemoryError::PageLimit));
+    assert_eq!(store.total_pages(), 1);
+}
+
+#[test]
+fn try_with_tuning() {
+    let store = PageStore::try_with_tuning(4, PageTuning::default()).unwrap();
+    assert_eq!(store.total_pages(), 4);
+    assert_eq!(
+        PageStore::try_with_tuning(MAX_PAGES + 1, PageTuning::default()).err(),
+        Some(MemoryError::PageLimit)
+    );
+}
+
+/// Install an observer recording every event
+fn record(store: &PageStore) -> Rc<RefCell<Vec<PageEvent>>> {
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let log = Rc::clone(&events);
+    store.set_observer(Some(Box::new(move |event| log.borrow_mut().push(event))));
+    events
+}
+
+#[test]
+fn observer_allocation_and_free() {
+    let store = PageStore::new(4);
+    let events = record(&store);
+    let mut mem = Memory::new(&store, 4, 2);
+    let id = Some(mem.id());
+    mem.write_u8(0, 1).unwrap();
+    let page = match events.borrow()[..] {
+        [PageEvent::Allocated { instance, page }] if instance == id => page,
+        ref other => panic!("unexpected events {:?}", other),
+    };
+    mem.reset();
+    assert_eq!(
+        events.borrow()[1..],
+        [PageEvent::Freed { instance: id, page }]
+    );
+
+    let shared = store.share(b"shared").unwrap();
+    drop(shared);
+    assert!(matches!(
+        events.borrow()[2..],
+        [
+            PageEvent::Allocated { instance: None, .. },
+            PageEvent::Freed { instance: None, .. }
+        ]
+    ));
+}
+
+#[test]
+fn observer_exhaustion() {
+    let store = PageStore::new(1);
+    let events = record(&store);
+    let mut first = Memory::new(&store, 1, 2);
+    let mut second = Memory::new(&store, 1, 2);
+    first.write_u8(0, 1).unwrap();
+    assert_eq!(second.write_u8(0, 1), Err(MemoryError::NoPagesAvailable));
+    assert!(store.share(b"x").is_err());
+    assert_eq!(second.set_min_pages(1), Err(MemoryError::NoPagesAvailable));
+    assert_eq!(
+        events.borrow()[1..],
+        [
+            PageEvent::Exhausted {
+                instance: Some(second.id())
+            },
+            PageEvent::Exhausted { instance: None },
+            PageEvent::Exhausted {
+                instance: Some(second.id())
+            },
+        ]
+    );
+    assert_eq!(store.stats().exhausted, 3);
+
+    // Removing the observer stops reporting
+    store.set_observer(None);
+    drop(first);
+    assert_eq!(events.borrow().len(), 4);
+}
+
+#[test]
+fn observer_uses_store() {
+    let store = PageStore::new(4);
+    let handle = store.handle();
+    let seen = Rc::new(RefCell::new(Vec::new()));
+    let log = Rc::clone(&seen);
+    store.set_observer(Some(Box::new(move |event| {
+        log.borrow_mut().push(handle.available_pages());
+        if let PageEvent::Freed { .. } = event {
+            handle.set_observer(None);
+        }
+    })));
+    let mut mem = Memory::new(&store, 4, 2);
+    mem.write_u8(0, 1).unwrap();
+    mem.write_u8(PAGE_SIZE as u32, 1).unwrap();
+    // The observer removes itself on the first free
+    mem.reset();
+    mem.write_u8(0, 1).unwrap();
+    assert_eq!(*seen.borrow(), [3, 2, 4]);
 }
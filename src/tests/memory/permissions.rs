@@ -0,0 +1,196 @@
+use crate::memory::{Access, Memory, MemoryError, PAGE_SIZE, PageStore, Permissions};
+
+#[test]
+fn default_all() {
+    let store = PageStore::new(10);
+    let mem = Memory::new(&store, 5, 3);
+    assert_eq!(mem.permissions(0), Permissions::ALL);
+    assert_eq!(mem.permissions(0xFFFF_FFFF), Permissions::ALL);
+    assert_eq!(mem.check(0, 16, Access::Execute), Ok(()));
+}
+
+#[test]
+fn protect_without_allocating() {
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
+    assert_eq!(mem.protect(0, 4, Permissions::READ_EXECUTE), Ok(()));
+    assert_eq!(mem.num_pages, 0);
+    assert_eq!(mem.num_l2_tables, 1);
+    assert_eq!(mem.permissions(0), Permissions::READ_EXECUTE);
+    assert_eq!(mem.permissions(PAGE_SIZE as u32), Permissions::ALL);
+}
+
+#[test]
+fn check_access() {
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
+
+    assert_eq!(
+        mem.protect(0, PAGE_SIZE as u32, Permissions::READ_EXECUTE),
+        Ok(())
+    );
+    assert_eq!(mem.check(0, 4, Access::Read), Ok(()));
+    assert_eq!(mem.check(0, 4, Access::Execute), Ok(()));
+    assert_eq!(mem.check(0, 4, Access::Write), Err(MemoryError::Permission));
+    assert_eq!(mem.check(0, 0, Access::Write), Ok(()));
+}
+
+#[test]
+fn range_spans_pages() {
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
+
+    // The last byte of page 0 and the first byte of page 1
+    assert_eq!(
+        mem.protect(PAGE_SIZE as u32 - 1, 2, Permissions::READ_ONLY),
+        Ok(())
+    );
+    assert_eq!(mem.permissions(0), Permissions::READ_ONLY);
+    assert_eq!(mem.permissions(PAGE_SIZE as u32), Permissions::READ_ONLY);
+    assert_eq!(mem.permissions(PAGE_SIZE as u32 * 2), Permissions::ALL);
+
+    // A write straddling into a writable page still faults
+    assert_eq!(
+        mem.protect(PAGE_SIZE as u32, PAGE_SIZE as u32, Permissions::ALL),
+        Ok(())
+    );
+    assert_eq!(
+        mem.check(PAGE_SIZE as u32 - 2, 4, Access::Write),
+        Err(MemoryError::Permission)
+    );
+    assert_eq!(mem.check(PAGE_SIZE as u32, 4, Access::Write), Ok(()));
+}
+
+#[test]
+fn wraparound() {
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
+
+    assert_eq!(mem.protect(0xFFFF_FFFE, 4, Permissions::NONE), Ok(()));
+    assert_eq!(mem.permissions(0xFFFF_FFFF), Permissions::NONE);
+    assert_eq!(mem.permissions(0), Permissions::NONE);
+    assert_eq!(
+        mem.check(0xFFFF_FFFF, 2, Access::Read),
+        Err(MemoryError::Permission)
+    );
+}
+
+#[test]
+fn host_access_unchecked() {
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
+    assert_eq!(mem.protect(0, 4, Permissions::NONE), Ok(()));
+    assert_eq!(mem.write(0, &[1, 2, 3, 4]), Ok(()));
+    let mut buffer = [0u8; 4];
+    mem.read(0, &mut buffer);
+    assert_eq!(buffer, [1, 2, 3, 4]);
+}
+
+#[test]
+fn no_l2_tables() {
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 1);
+    assert_eq!(mem.protect(0, 4, Permissions::READ_ONLY), Ok(()));
+    // 0x400000 needs a second L2 table
+    assert_eq!(
+        mem.protect(0x400000, 4, Permissions::READ_ONLY),
+        Err(MemoryError::NoL2Tables)
+    );
+}
+
+#[test]
+fn reset_restores_default() {
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
+
+    assert_eq!(mem.protect(0, 4, Permissions::READ_ONLY), Ok(()));
+    mem.reset();
+    assert_eq!(mem.num_l2_tables, 0);
+    assert_eq!(mem.permissions(0), Permissions::ALL);
+
+    // A reused L2 table starts with default permissions
+    assert_eq!(mem.allocate_page(0x400000), Ok(()));
+    assert_eq!(mem.permissions(0), Permissions::ALL);
+    assert_eq!(mem.permissions(0x400000), Permissions::ALL);
+}
+
+#[test]
+fn display() {
+    assert_eq!(Permissions::ALL.to_string(), "rwx");
+    assert_eq!(Permissions::READ_EXECUTE.to_string(), "r-x");
+    assert_eq!(Permissions::NONE.to_string(), "---");
+    assert_eq!(Access::Execute.to_string(), "execute");
+}
+
+#[test]
+fn mprotect() {
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
+    let page = PAGE_SIZE as u32;
+
+    assert_eq!(mem.mprotect(page, 1, Permissions::READ_WRITE), Ok(()));
+    assert_eq!(mem.permissions(page), Permissions::READ_WRITE);
+    assert_eq!(mem.permissions(2 * page), Permissions::ALL);
+    assert_eq!(
+        mem.mprotect(page + 1, 1, Permissions::NONE),
+        Err(MemoryError::Misaligned)
+    );
+    assert_eq!(mem.permissions(page), Permissions::READ_WRITE);
+}
+
+#[test]
+fn mprotect_checks_whole_range() {
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
+    let page = PAGE_SIZE as u32;
+    let shared = store.share(b"code").unwrap();
+    mem.map_shared(page, &shared).unwrap();
+    mem.guard(2 * page, page).unwrap();
+
+    assert_eq!(
+        mem.mprotect(0, 2 * page, Permissions::ALL),
+        Err(MemoryError::ReadOnly)
+    );
+    assert_eq!(mem.permissions(0), Permissions::ALL);
+    assert_eq!(mem.permissions(page), Permissions::READ_EXECUTE);
+    assert_eq!(mem.mprotect(page, 1, Permissions::READ_ONLY), Ok(()));
+    assert_eq!(
+        mem.mprotect(page, 2 * page, Permissions::NONE),
+        Err(MemoryError::Guard)
+    );
+    assert_eq!(mem.permissions(page), Permissions::READ_ONLY);
+}
+
+#[test]
+fn mprotect_checks_l2_tables() {
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 2);
+    // 0x3FC000 is the last page of the first L2 table; the range needs three
+    assert_eq!(
+        mem.mprotect(0x3FC000, 0x400000 + 2 * PAGE_SIZE as u32, Permissions::NONE),
+        Err(MemoryError::NoL2Tables)
+    );
+    assert_eq!(mem.permissions(0x3FC000), Permissions::ALL);
+    assert_eq!(mem.num_l2_tables, 0);
+    assert_eq!(
+        mem.mprotect(0x3FC000, 2 * PAGE_SIZE as u32, Permissions::NONE),
+        Ok(())
+    );
+    assert_eq!(mem.num_l2_tables, 2);
+}
+
+#[test]
+fn mprotect_rejects_wrap() {
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
+    let last = 0u32.wrapping_sub(PAGE_SIZE as u32);
+    assert_eq!(
+        mem.mprotect(last, 2 * PAGE_SIZE as u32, Permissions::NONE),
+        Err(MemoryError::OutOfRange)
+    );
+    assert_eq!(mem.permissions(0), Permissions::ALL);
+    assert_eq!(
+        mem.mprotect(last, PAGE_SIZE as u32, Permissions::NONE),
+        Ok(())
+    );
+}
@@ -0,0 +1,98 @@
+use crate::DiffRange;
+use crate::memory::{Memory, MemoryError, PAGE_SIZE, PageStore, REDZONE_BYTE};
+
+#[test]
+fn poisoned_pages() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    mem.set_poison(Some(0xa5));
+    assert_eq!(mem.poison(), Some(0xa5));
+    mem.write_u8(0x10, 1).unwrap();
+    assert_eq!(mem.read_u8(0x10), 1);
+    assert_eq!(mem.read_u8(0x11), 0xa5);
+    assert_eq!(mem.read_u8(PAGE_SIZE as u32 - 1), 0xa5);
+    // Unallocated memory still reads as zeros
+    assert_eq!(mem.read_u8(PAGE_SIZE as u32), 0);
+
+    mem.soft_reset();
+    assert_eq!(mem.read_u8(0x10), 0xa5);
+
+    // Pages return to the pool zeroed for other instances
+    mem.reset();
+    mem.set_poison(None);
+    mem.write_u8(0x10, 1).unwrap();
+    assert_eq!(mem.read_u8(0x11), 0);
+}
+
+#[test]
+fn zeros_survive_poisoning() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    mem.set_poison(Some(0xa5));
+    mem.fill(0, 16, 0).unwrap();
+    mem.copy_within(0x10_0000, 0x20, 16).unwrap();
+    assert_eq!(mem.num_pages, 1);
+    mem.write_u8(0x40, 1).unwrap();
+    assert_eq!(mem.read_u64(0), 0);
+    assert_eq!(mem.read_u64(0x28), 0);
+    assert_eq!(mem.read_u8(0x30), 0xa5);
+}
+
+#[test]
+fn redzones() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    mem.add_redzone(0x100, 16).unwrap();
+    mem.add_redzone(0x200, 16).unwrap();
+    assert_eq!(mem.read_u8(0x10f), REDZONE_BYTE);
+    assert!(mem.check_redzones().is_empty());
+
+    mem.write(0x10e, &[0, 0, 0, 0]).unwrap();
+    mem.write_u8(0x205, 7).unwrap();
+    let expected = [
+        DiffRange {
+            address: 0x10e,
+            len: 2,
+        },
+        DiffRange {
+            address: 0x205,
+            len: 1,
+        },
+    ];
+    assert_eq!(mem.check_redzones(), expected);
+    assert!(mem.take_redzone_violations().is_empty());
+
+    mem.reset();
+    assert_eq!(mem.take_redzone_violations(), expected);
+    assert!(mem.take_redzone_violations().is_empty());
+    assert!(mem.check_redzones().is_empty());
+}
+
+#[test]
+fn soft_reset_checks_redzones() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    mem.add_redzone(0x100, 4).unwrap();
+    mem.soft_reset();
+    assert!(mem.take_redzone_violations().is_empty());
+
+    mem.add_redzone(0x100, 4).unwrap();
+    mem.write_u8(0x100, 0).unwrap();
+    mem.soft_reset();
+    assert_eq!(
+        mem.take_redzone_violations(),
+        [DiffRange {
+            address: 0x100,
+            len: 1
+        }]
+    );
+}
+
+#[test]
+fn redzone_errors() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    mem.guard(0x10_0000, 1).unwrap();
+    assert_eq!(mem.add_redzone(0x10_0000, 4), Err(MemoryError::Guard));
+    assert!(mem.check_redzones().is_empty());
+}
@@ -0,0 +1,116 @@
+use crate::memory::{InstanceUsage, Memory, MemoryError, PAGE_SIZE, PageStore};
+
+/// Allocate `count` pages starting at address 0
+fn allocate(mem: &mut Memory, count: u32) -> Result<(), MemoryError> {
+    (0..count).try_for_each(|page| mem.allocate_page(page * PAGE_SIZE as u32))
+}
+
+#[test]
+fn usage_per_instance() {
+    let store = PageStore::new(10);
+    let mut mem1 = Memory::new(&store, 10, 2);
+    let mut mem2 = Memory::new(&store, 10, 2);
+    assert_eq!((mem1.id(), mem2.id()), (0, 1));
+    allocate(&mut mem1, 3).unwrap();
+    allocate(&mut mem2, 1).unwrap();
+    let usage: Vec<InstanceUsage> = store.instances();
+    assert_eq!(
+        usage,
+        [
+            InstanceUsage {
+                id: 0,
+                pages: 3,
+                min_pages: 0
+            },
+            InstanceUsage {
+                id: 1,
+                pages: 1,
+                min_pages: 0
+            },
+        ]
+    );
+
+    mem1.reset();
+    assert_eq!(store.instances().first().unwrap().pages, 0);
+
+    // Ids are reused after drop
+    drop(mem1);
+    assert_eq!(store.instances().len(), 1);
+    let mem3 = Memory::new(&store, 5, 2);
+    assert_eq!(mem3.id(), 0);
+}
+
+#[test]
+fn minimum_is_guaranteed() {
+    let store = PageStore::new(10);
+    let mut greedy = Memory::new(&store, 10, 2);
+    let mut tenant = Memory::new(&store, 10, 2);
+    tenant.set_min_pages(4).unwrap();
+    assert_eq!(tenant.min_pages(), 4);
+    assert_eq!(store.reserved_pages(), 4);
+    assert_eq!(store.unreserved_pages(), 6);
+
+    assert_eq!(
+        allocate(&mut greedy, 10),
+        Err(MemoryError::NoPagesAvailable)
+    );
+    assert_eq!(greedy.num_pages, 6);
+    assert_eq!(store.available_pages(), 4);
+
+    // The tenant gets its minimum but nothing beyond it
+    allocate(&mut tenant, 4).unwrap();
+    assert_eq!(store.reserved_pages(), 0);
+    assert_eq!(
+        tenant.allocate_page(0x100000),
+        Err(MemoryError::NoPagesAvailable)
+    );
+}
+
+#[test]
+fn pages_above_minimum_are_shared() {
+    let store = PageStore::new(10);
+    let mut mem1 = Memory::new(&store, 10, 2);
+    let mut mem2 = Memory::new(&store, 10, 2);
+    mem1.set_min_pages(2).unwrap();
+    allocate(&mut mem1, 5).unwrap();
+    assert_eq!(store.reserved_pages(), 0);
+    allocate(&mut mem2, 5).unwrap();
+    assert_eq!(store.available_pages(), 0);
+
+    // Releasing pages restores the reservation up to the minimum
+    mem1.reset();
+    assert_eq!(store.reserved_pages(), 2);
+    assert_eq!(store.unreserved_pages(), 3);
+}
+
+#[test]
+fn set_min_pages_errors() {
+    let store = PageStore::new(10);
+    let mut mem1 = Memory::new(&store, 4, 2);
+    let mut mem2 = Memory::new(&store, 10, 2);
+    assert_eq!(mem1.set_min_pages(5), Err(MemoryError::PageLimit));
+    mem2.set_min_pages(8).unwrap();
+    assert_eq!(mem1.set_min_pages(3), Err(MemoryError::NoPagesAvailable));
+    assert_eq!(mem1.min_pages(), 0);
+    mem1.set_min_pages(2).unwrap();
+
+    // Lowering or releasing a minimum frees the held-back pages
+    mem2.set_min_pages(0).unwrap();
+    assert_eq!(store.reserved_pages(), 2);
+    drop(mem1);
+    assert_eq!(store.reserved_pages(), 0);
+}
+
+#[test]
+fn shared_pages_respect_reservations() {
+    let store = PageStore::new(4);
+    let mut mem = Memory::new(&store, 4, 2);
+    mem.set_min_pages(3).unwrap();
+    assert_eq!(
+        store
+            .share(&[0; 2 * PAGE_SIZE])
+            .map(|shared| shared.pages()),
+        Err(MemoryError::NoPagesAvailable)
+    );
+    assert_eq!(store.share(&[1; PAGE_SIZE]).unwrap().pages(), 1);
+}
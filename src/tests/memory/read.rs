@@ -1,4 +1,4 @@
-use crate::memory::{MEM_SUCCESS, Memory, PAGE_OFFSET_MASK, PAGE_SIZE, PageStore, UNMAPPED_PAGE};
+use crate::memory::{Memory, PAGE_OFFSET_MASK, PAGE_SIZE, PageStore, UNMAPPED_PAGE};
 
 /// Helper function to get the physical page pointer for a given address
 fn get_page_ptr(memory: &Memory, address: u32) -> Option<*mut u8> {
@@ -23,16 +23,16 @@ fn get_page_ptr(memory: &Memory, address: u32) -> Option<*mut u8> {
 
 #[test]
 fn empty_buffer() {
-    let mut store = PageStore::new(10);
-    let memory = Memory::new(&mut store, 5, 2);
+    let store = PageStore::new(10);
+    let memory = Memory::new(&store, 5, 2);
     let mut buffer = [];
     memory.read(0, &mut buffer);
 }
 
 #[test]
 fn single_byte_unallocated() {
-    let mut store = PageStore::new(10);
-    let memory = Memory::new(&mut store, 5, 2);
+    let store = PageStore::new(10);
+    let memory = Memory::new(&store, 5, 2);
     let mut buffer = [0xFF];
     memory.read(0, &mut buffer);
     assert_eq!(buffer[0], 0);
@@ -40,8 +40,8 @@ fn single_byte_unallocated() {
 
 #[test]
 fn multiple_bytes_unallocated() {
-    let mut store = PageStore::new(10);
-    let memory = Memory::new(&mut store, 5, 2);
+    let store = PageStore::new(10);
+    let memory = Memory::new(&store, 5, 2);
     let mut buffer = vec![0xFF; 100];
     memory.read(0, &mut buffer);
     assert!(buffer.iter().all(|&b| b == 0));
@@ -49,9 +49,9 @@ fn multiple_bytes_unallocated() {
 
 #[test]
 fn single_byte_allocated() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
-    assert_eq!(memory.allocate_page(0), MEM_SUCCESS);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.allocate_page(0), Ok(()));
     unsafe {
         let page_ptr = get_page_ptr(&memory, 0).unwrap();
         *page_ptr = 42;
@@ -63,9 +63,9 @@ fn single_byte_allocated() {
 
 #[test]
 fn multiple_bytes_same_page() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
-    assert_eq!(memory.allocate_page(0), MEM_SUCCESS);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.allocate_page(0), Ok(()));
     unsafe {
         let page_ptr = get_page_ptr(&memory, 0).unwrap();
         for i in 0..10 {
@@ -74,18 +74,18 @@ fn multiple_bytes_same_page() {
     }
     let mut buffer = vec![0xFF; 10];
     memory.read(0, &mut buffer);
-    for i in 0..10 {
-        assert_eq!(buffer[i], i as u8);
+    for (i, &byte) in buffer.iter().enumerate() {
+        assert_eq!(byte, i as u8);
     }
 }
 
 #[test]
 fn read_across_page_boundary() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
     let first_page_end = PAGE_SIZE as u32 - 2;
-    assert_eq!(memory.allocate_page(first_page_end), MEM_SUCCESS);
-    assert_eq!(memory.allocate_page(PAGE_SIZE as u32), MEM_SUCCESS);
+    assert_eq!(memory.allocate_page(first_page_end), Ok(()));
+    assert_eq!(memory.allocate_page(PAGE_SIZE as u32), Ok(()));
     unsafe {
         let first_page = get_page_ptr(&memory, 0).unwrap();
         let second_page = get_page_ptr(&memory, PAGE_SIZE as u32).unwrap();
@@ -101,10 +101,10 @@ fn read_across_page_boundary() {
 
 #[test]
 fn read_mixed_allocated_unallocated() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
-    assert_eq!(memory.allocate_page(0), MEM_SUCCESS);
-    assert_eq!(memory.allocate_page(2 * PAGE_SIZE as u32), MEM_SUCCESS);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.allocate_page(0), Ok(()));
+    assert_eq!(memory.allocate_page(2 * PAGE_SIZE as u32), Ok(()));
     unsafe {
         let first_page = get_page_ptr(&memory, 0).unwrap();
         let third_page = get_page_ptr(&memory, 2 * PAGE_SIZE as u32).unwrap();
@@ -115,22 +115,24 @@ fn read_mixed_allocated_unallocated() {
     }
     let mut buffer = vec![0xFF; PAGE_SIZE * 3];
     memory.read(0, &mut buffer);
-    for i in 0..PAGE_SIZE {
-        assert_eq!(buffer[i], 0x11);
-    }
-    for i in PAGE_SIZE..(2 * PAGE_SIZE) {
-        assert_eq!(buffer[i], 0);
-    }
-    for i in (2 * PAGE_SIZE)..(3 * PAGE_SIZE) {
-        assert_eq!(buffer[i], 0x33);
-    }
+    assert!(buffer[0..PAGE_SIZE].iter().all(|&byte| byte == 0x11));
+    assert!(
+        buffer[PAGE_SIZE..(2 * PAGE_SIZE)]
+            .iter()
+            .all(|&byte| byte == 0)
+    );
+    assert!(
+        buffer[(2 * PAGE_SIZE)..(3 * PAGE_SIZE)]
+            .iter()
+            .all(|&byte| byte == 0x33)
+    );
 }
 
 #[test]
 fn read_with_offset_in_page() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
-    assert_eq!(memory.allocate_page(100), MEM_SUCCESS);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.allocate_page(100), Ok(()));
     unsafe {
         let page_ptr = get_page_ptr(&memory, 100).unwrap();
         for i in 0..PAGE_SIZE {
@@ -139,16 +141,16 @@ fn read_with_offset_in_page() {
     }
     let mut buffer = vec![0; 10];
     memory.read(100, &mut buffer);
-    for i in 0..10 {
-        assert_eq!(buffer[i], ((100 + i) % 256) as u8);
+    for (i, &byte) in buffer.iter().enumerate() {
+        assert_eq!(byte, ((100 + i) % 256) as u8);
     }
 }
 
 #[test]
 fn read_entire_page() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
-    assert_eq!(memory.allocate_page(0), MEM_SUCCESS);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.allocate_page(0), Ok(()));
     unsafe {
         let page_ptr = get_page_ptr(&memory, 0).unwrap();
         for i in 0..PAGE_SIZE {
@@ -157,17 +159,17 @@ fn read_entire_page() {
     }
     let mut buffer = vec![0xFF; PAGE_SIZE];
     memory.read(0, &mut buffer);
-    for i in 0..PAGE_SIZE {
-        assert_eq!(buffer[i], (i % 256) as u8);
+    for (i, &byte) in buffer.iter().enumerate() {
+        assert_eq!(byte, (i % 256) as u8);
     }
 }
 
 #[test]
 fn read_multiple_pages() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
     for i in 0..3 {
-        assert_eq!(memory.allocate_page(i * PAGE_SIZE as u32), MEM_SUCCESS);
+        assert_eq!(memory.allocate_page(i * PAGE_SIZE as u32), Ok(()));
     }
     unsafe {
         for page in 0..3 {
@@ -188,9 +190,9 @@ fn read_multiple_pages() {
 
 #[test]
 fn read_at_page_boundary() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
-    assert_eq!(memory.allocate_page(0), MEM_SUCCESS);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.allocate_page(0), Ok(()));
     unsafe {
         let page_ptr = get_page_ptr(&memory, 0).unwrap();
         *page_ptr.add(PAGE_SIZE - 1) = 0x42;
@@ -202,8 +204,8 @@ fn read_at_page_boundary() {
 
 #[test]
 fn read_unallocated_l2_table() {
-    let mut store = PageStore::new(10);
-    let memory = Memory::new(&mut store, 5, 2);
+    let store = PageStore::new(10);
+    let memory = Memory::new(&store, 5, 2);
     let high_address = 0x40000000;
     let mut buffer = vec![0xFF; 100];
     memory.read(high_address, &mut buffer);
@@ -212,10 +214,10 @@ fn read_unallocated_l2_table() {
 
 #[test]
 fn read_partial_page_at_end() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
     let addr = PAGE_SIZE as u32 - 10;
-    assert_eq!(memory.allocate_page(addr), MEM_SUCCESS);
+    assert_eq!(memory.allocate_page(addr), Ok(()));
     unsafe {
         let page_ptr = get_page_ptr(&memory, addr).unwrap();
         for i in (PAGE_SIZE - 10)..PAGE_SIZE {
@@ -224,18 +226,14 @@ fn read_partial_page_at_end() {
     }
     let mut buffer = vec![0; 20];
     memory.read(addr, &mut buffer);
-    for i in 0..10 {
-        assert_eq!(buffer[i], 0xEE);
-    }
-    for i in 10..20 {
-        assert_eq!(buffer[i], 0);
-    }
+    assert!(buffer[0..10].iter().all(|&byte| byte == 0xEE));
+    assert!(buffer[10..20].iter().all(|&byte| byte == 0));
 }
 
 #[test]
 fn read_zero_at_various_alignments() {
-    let mut store = PageStore::new(10);
-    let memory = Memory::new(&mut store, 5, 2);
+    let store = PageStore::new(10);
+    let memory = Memory::new(&store, 5, 2);
     let alignments = [0, 1, 2, 3, 4, 7, 8, 15, 16, 31, 32, 63, 64, 127, 128];
     for &align in &alignments {
         let mut buffer = vec![0xFF; 256];
@@ -246,9 +244,9 @@ fn read_zero_at_various_alignments() {
 
 #[test]
 fn read_after_reset() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
-    assert_eq!(memory.allocate_page(0), MEM_SUCCESS);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.allocate_page(0), Ok(()));
     unsafe {
         let page_ptr = get_page_ptr(&memory, 0).unwrap();
         *page_ptr = 0x42;
@@ -261,10 +259,10 @@ fn read_after_reset() {
 
 #[test]
 fn read_sparse_l2_entries() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
-    assert_eq!(memory.allocate_page(0), MEM_SUCCESS);
-    assert_eq!(memory.allocate_page(10 * PAGE_SIZE as u32), MEM_SUCCESS);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.allocate_page(0), Ok(()));
+    assert_eq!(memory.allocate_page(10 * PAGE_SIZE as u32), Ok(()));
     unsafe {
         let page1 = get_page_ptr(&memory, 0).unwrap();
         let page2 = get_page_ptr(&memory, 10 * PAGE_SIZE as u32).unwrap();
@@ -284,10 +282,10 @@ fn read_sparse_l2_entries() {
 
 #[test]
 fn read_large_buffer_performance() {
-    let mut store = PageStore::new(100);
-    let mut memory = Memory::new(&mut store, 50, 10);
+    let store = PageStore::new(100);
+    let mut memory = Memory::new(&store, 50, 10);
     for i in 0..10 {
-        assert_eq!(memory.allocate_page(i * PAGE_SIZE as u32), MEM_SUCCESS);
+        assert_eq!(memory.allocate_page(i * PAGE_SIZE as u32), Ok(()));
     }
     unsafe {
         for page in 0..10 {
@@ -299,17 +297,17 @@ fn read_large_buffer_performance() {
     }
     let mut buffer = vec![0; PAGE_SIZE * 10];
     memory.read(0, &mut buffer);
-    for i in 0..buffer.len() {
-        assert_eq!(buffer[i], (i % 256) as u8);
+    for (i, &byte) in buffer.iter().enumerate() {
+        assert_eq!(byte, (i % 256) as u8);
     }
 }
 
 #[test]
 fn read_with_high_l1_index() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
     let high_addr = 0xFFC00000;
-    assert_eq!(memory.allocate_page(high_addr), MEM_SUCCESS);
+    assert_eq!(memory.allocate_page(high_addr), Ok(()));
     unsafe {
         let page_ptr = get_page_ptr(&memory, high_addr).unwrap();
         *page_ptr = 0x99;
@@ -321,10 +319,10 @@ fn read_with_high_l1_index() {
 
 #[test]
 fn read_with_high_l2_index() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
     let addr = (255 << 14) as u32;
-    assert_eq!(memory.allocate_page(addr), MEM_SUCCESS);
+    assert_eq!(memory.allocate_page(addr), Ok(()));
     unsafe {
         let page_ptr = get_page_ptr(&memory, addr).unwrap();
         *page_ptr = 0x88;
@@ -336,9 +334,9 @@ fn read_with_high_l2_index() {
 
 #[test]
 fn read_all_page_offsets() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
-    assert_eq!(memory.allocate_page(0), MEM_SUCCESS);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.allocate_page(0), Ok(()));
     unsafe {
         let page_ptr = get_page_ptr(&memory, 0).unwrap();
         for i in 0..PAGE_SIZE {
@@ -354,10 +352,10 @@ fn read_all_page_offsets() {
 
 #[test]
 fn read_crosses_multiple_pages() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 4);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 4);
     for i in 0..4 {
-        assert_eq!(memory.allocate_page(i * PAGE_SIZE as u32), MEM_SUCCESS);
+        assert_eq!(memory.allocate_page(i * PAGE_SIZE as u32), Ok(()));
     }
     unsafe {
         for page in 0..4 {
@@ -371,26 +369,30 @@ fn read_crosses_multiple_pages() {
     let len = PAGE_SIZE * 3;
     let mut buffer = vec![0xFF; len];
     memory.read(start as u32, &mut buffer);
-    for i in 0..(PAGE_SIZE / 2) {
-        assert_eq!(buffer[i], 0);
-    }
-    for i in (PAGE_SIZE / 2)..((PAGE_SIZE * 3) / 2) {
-        assert_eq!(buffer[i], 1);
-    }
-    for i in ((PAGE_SIZE * 3) / 2)..((PAGE_SIZE * 5) / 2) {
-        assert_eq!(buffer[i], 2);
-    }
-    for i in ((PAGE_SIZE * 5) / 2)..len {
-        assert_eq!(buffer[i], 3);
-    }
+    assert!(buffer[0..(PAGE_SIZE / 2)].iter().all(|&byte| byte == 0));
+    assert!(
+        buffer[(PAGE_SIZE / 2)..((PAGE_SIZE * 3) / 2)]
+            .iter()
+            .all(|&byte| byte == 1)
+    );
+    assert!(
+        buffer[((PAGE_SIZE * 3) / 2)..((PAGE_SIZE * 5) / 2)]
+            .iter()
+            .all(|&byte| byte == 2)
+    );
+    assert!(
+        buffer[((PAGE_SIZE * 5) / 2)..len]
+            .iter()
+            .all(|&byte| byte == 3)
+    );
 }
 
 #[test]
 fn read_single_byte_each_page() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 3);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 3);
     for i in 0..3 {
-        assert_eq!(memory.allocate_page(i * PAGE_SIZE as u32), MEM_SUCCESS);
+        assert_eq!(memory.allocate_page(i * PAGE_SIZE as u32), Ok(()));
     }
     unsafe {
         for page in 0..3 {
@@ -407,10 +409,10 @@ fn read_single_byte_each_page() {
 
 #[test]
 fn read_exact_page_alignment() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
-    assert_eq!(memory.allocate_page(0), MEM_SUCCESS);
-    assert_eq!(memory.allocate_page(PAGE_SIZE as u32), MEM_SUCCESS);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.allocate_page(0), Ok(()));
+    assert_eq!(memory.allocate_page(PAGE_SIZE as u32), Ok(()));
     unsafe {
         let page1 = get_page_ptr(&memory, 0).unwrap();
         let page2 = get_page_ptr(&memory, PAGE_SIZE as u32).unwrap();
@@ -421,18 +423,16 @@ fn read_exact_page_alignment() {
     }
     let mut buffer = vec![0; PAGE_SIZE * 2];
     memory.read(0, &mut buffer);
-    for i in 0..PAGE_SIZE {
-        assert_eq!(buffer[i], 0x11);
-        assert_eq!(buffer[PAGE_SIZE + i], 0x22);
-    }
+    assert!(buffer[..PAGE_SIZE].iter().all(|&byte| byte == 0x11));
+    assert!(buffer[PAGE_SIZE..].iter().all(|&byte| byte == 0x22));
 }
 
 #[test]
 fn read_with_wraparound() {
-    let mut store = PageStore::new(10);
-    let mut memory = Memory::new(&mut store, 5, 2);
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
     let addr = 0xFFFFFFFC;
-    assert_eq!(memory.allocate_page(addr), MEM_SUCCESS);
+    assert_eq!(memory.allocate_page(addr), Ok(()));
     unsafe {
         let page_ptr = get_page_ptr(&memory, addr).unwrap();
         let offset = (addr & PAGE_OFFSET_MASK) as usize;
@@ -446,7 +446,5 @@ fn read_with_wraparound() {
     assert_eq!(buffer[1], 0xF1);
     assert_eq!(buffer[2], 0xF2);
     assert_eq!(buffer[3], 0xF3);
-    for i in 4..8 {
-        assert_eq!(buffer[i], 0);
-    }
+    assert!(buffer[4..8].iter().all(|&byte| byte == 0));
 }
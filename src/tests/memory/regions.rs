@@ -0,0 +1,114 @@
+use crate::memory::{
+    MAX_DUMP_LENGTH, Memory, PAGE_SIZE, PageStore, Permissions, Region, RegionKind,
+};
+
+#[test]
+fn empty() {
+    let store = PageStore::new(10);
+    let memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.regions().count(), 0);
+}
+
+#[test]
+fn merges_adjacent_pages() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.write(0, &vec![1; 2 * PAGE_SIZE]), Ok(()));
+    assert_eq!(memory.allocate_page(0x10000), Ok(()));
+    let regions: Vec<Region> = memory.regions().collect();
+    assert_eq!(
+        regions,
+        [
+            Region {
+                address: 0,
+                pages: 2,
+                kind: RegionKind::Private,
+                permissions: Permissions::ALL,
+            },
+            Region {
+                address: 0x10000,
+                pages: 1,
+                kind: RegionKind::Private,
+                permissions: Permissions::ALL,
+            },
+        ]
+    );
+    assert_eq!(regions[0].size(), 2 * PAGE_SIZE as u64);
+    assert_eq!(regions[1].end(), 0x14000);
+}
+
+#[test]
+fn splits_on_permissions_and_kind() {
+    let store = PageStore::new(10);
+    let shared = store.share(&[1]).unwrap();
+    let mut memory = Memory::new(&store, 5, 2);
+    let mut buffer = vec![0u8; PAGE_SIZE];
+    assert_eq!(memory.map_shared(0, &shared), Ok(()));
+    assert_eq!(
+        memory.write(PAGE_SIZE as u32, &vec![1; 2 * PAGE_SIZE]),
+        Ok(())
+    );
+    memory
+        .protect(2 * PAGE_SIZE as u32, 1, Permissions::READ_ONLY)
+        .unwrap();
+    unsafe { memory.map_host(3 * PAGE_SIZE as u32, &mut buffer) }.unwrap();
+
+    let lines: Vec<String> = memory.regions().map(|region| region.to_string()).collect();
+    assert_eq!(
+        lines,
+        [
+            "0x00000000-0x00003fff r-x shared",
+            "0x00004000-0x00007fff rwx private",
+            "0x00008000-0x0000bfff r-- private",
+            "0x0000c000-0x0000ffff rwx host",
+        ]
+    );
+}
+
+#[test]
+fn host_mapping_without_l2_table() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    let mut buffer = vec![0u8; 2 * PAGE_SIZE];
+    unsafe { memory.map_host(0x800000, &mut buffer) }.unwrap();
+    let regions: Vec<Region> = memory.regions().collect();
+    assert_eq!(regions.len(), 1);
+    assert_eq!(regions[0].address, 0x800000);
+    assert_eq!(regions[0].pages, 2);
+    assert_eq!(regions[0].kind, RegionKind::Host);
+}
+
+#[test]
+fn top_of_address_space() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.write(0xFFFF_FFFF, &[1]), Ok(()));
+    let region = memory.regions().next().unwrap();
+    assert_eq!(region.end(), 1 << 32);
+    assert_eq!(region.to_string(), "0xffffc000-0xffffffff rwx private");
+}
+
+#[test]
+fn dump() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.write(0x2000, &[0x01, 0x02, 0xff]), Ok(()));
+    assert_eq!(memory.dump(0x2000, 4), "0x00002000: 01 02 ff 00");
+    let output = memory.dump(0x1ff8, 20);
+    assert_eq!(
+        output,
+        "0x00001ff8: 00 00 00 00 00 00 00 00 01 02 ff 00 00 00 00 00\n0x00002008: 00 00 00 00"
+    );
+    assert_eq!(memory.dump(0, 0), "");
+
+    // Long dumps are cut off
+    let output = memory.dump(0, u32::MAX);
+    assert_eq!(output.lines().count(), MAX_DUMP_LENGTH as usize / 16);
+    assert!(output.ends_with("0x0000fff0: 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00 00"));
+
+    // Lines wrap around the address space
+    assert_eq!(
+        memory.dump(0xFFFF_FFFC, 8),
+        "0xfffffffc: 00 00 00 00 00 00 00 00"
+    );
+}
@@ -0,0 +1,88 @@
+use crate::memory::{Memory, MemoryError, PageStore, Reservation};
+
+#[test]
+fn store_conditional_succeeds() {
+    let store = PageStore::new(4);
+    let mut mem = Memory::new(&store, 4, 1);
+    assert_eq!(mem.reservation(), None);
+    mem.reserve(0x1000, 4);
+    assert_eq!(
+        mem.reservation(),
+        Some(Reservation {
+            address: 0x1000,
+            size: 4
+        })
+    );
+    assert_eq!(mem.write_conditional(0x1000, &7u32.to_le_bytes()), Ok(true));
+    assert_eq!(mem.read_u32(0x1000), 7);
+    assert_eq!(mem.reservation(), None);
+
+    // The reservation is consumed by the first store-conditional
+    assert_eq!(
+        mem.write_conditional(0x1000, &8u32.to_le_bytes()),
+        Ok(false)
+    );
+    assert_eq!(mem.read_u32(0x1000), 7);
+}
+
+#[test]
+fn store_conditional_outside_reservation() {
+    let store = PageStore::new(4);
+    let mut mem = Memory::new(&store, 4, 1);
+    mem.reserve(0x1000, 4);
+    assert_eq!(mem.write_conditional(0x1002, &[1, 2, 3, 4]), Ok(false));
+    assert_eq!(mem.reservation(), None);
+    assert_eq!(mem.read_u32(0x1002), 0);
+
+    // A narrower store inside the reservation succeeds
+    mem.reserve(0x1000, 4);
+    assert_eq!(mem.write_conditional(0x1002, &[1, 2]), Ok(true));
+}
+
+#[test]
+fn conflicting_write_invalidates() {
+    let store = PageStore::new(4);
+    let mut mem = Memory::new(&store, 4, 1);
+    mem.reserve(0x1000, 4);
+    mem.write(0x0ffc, &[0; 4]).unwrap();
+    mem.write(0x1004, &[0; 4]).unwrap();
+    assert!(mem.reservation().is_some());
+
+    mem.write(0x1003, &[0]).unwrap();
+    assert_eq!(mem.reservation(), None);
+    assert_eq!(mem.write_conditional(0x1000, &[1; 4]), Ok(false));
+
+    mem.reserve(0x1000, 4);
+    mem.write(0x0ffe, &[0; 4]).unwrap();
+    assert_eq!(mem.reservation(), None);
+}
+
+#[test]
+fn clear_and_reset() {
+    let store = PageStore::new(4);
+    let mut mem = Memory::new(&store, 4, 1);
+    mem.reserve(0x1000, 4);
+    mem.clear_reservation();
+    assert_eq!(mem.reservation(), None);
+
+    mem.reserve(0x1000, 4);
+    mem.reset();
+    assert_eq!(mem.reservation(), None);
+
+    mem.reserve(0x1000, 4);
+    mem.soft_reset();
+    assert_eq!(mem.reservation(), None);
+}
+
+#[test]
+fn store_conditional_error() {
+    let store = PageStore::new(4);
+    let mut mem = Memory::new(&store, 4, 1);
+    mem.guard(0x4000, 1).unwrap();
+    mem.reserve(0x4000, 4);
+    assert_eq!(
+        mem.write_conditional(0x4000, &[0; 4]),
+        Err(MemoryError::Guard)
+    );
+    assert_eq!(mem.reservation(), None);
+}
@@ -1,9 +1,9 @@
-use crate::memory::{MEM_SUCCESS, Memory, PAGE_SIZE, PageStore, UNMAPPED_PAGE};
+use crate::memory::{Memory, PAGE_SIZE, PageStore, Permissions, RegionKind, UNMAPPED_PAGE};
 
 #[test]
 fn empty_memory() {
-    let mut store = PageStore::new(10);
-    let mut mem = Memory::new(&mut store, 5, 3);
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
 
     mem.reset();
     assert_eq!(mem.num_pages, 0);
@@ -12,81 +12,81 @@ fn empty_memory() {
 
 #[test]
 fn single_page() {
-    let mut store = PageStore::new(10);
-    let mut mem = Memory::new(&mut store, 5, 3);
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
 
-    assert_eq!(mem.allocate_page(0), MEM_SUCCESS);
+    assert_eq!(mem.allocate_page(0), Ok(()));
     assert_eq!(mem.num_pages, 1);
-    assert_eq!(store.num_available_pages, 9);
+    assert_eq!(store.available_pages(), 9);
 
     mem.reset();
     assert_eq!(mem.num_pages, 0);
     assert_eq!(mem.num_l2_tables, 0);
-    assert_eq!(store.num_available_pages, 10);
+    assert_eq!(store.available_pages(), 10);
 }
 
 #[test]
 fn multiple_pages() {
-    let mut store = PageStore::new(10);
-    let mut mem = Memory::new(&mut store, 5, 3);
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
 
-    assert_eq!(mem.allocate_page(0), MEM_SUCCESS);
-    assert_eq!(mem.allocate_page(PAGE_SIZE as u32), MEM_SUCCESS);
-    assert_eq!(mem.allocate_page(1 << 22), MEM_SUCCESS);
+    assert_eq!(mem.allocate_page(0), Ok(()));
+    assert_eq!(mem.allocate_page(PAGE_SIZE as u32), Ok(()));
+    assert_eq!(mem.allocate_page(1 << 22), Ok(()));
     assert_eq!(mem.num_pages, 3);
     assert_eq!(mem.num_l2_tables, 2);
 
     mem.reset();
     assert_eq!(mem.num_pages, 0);
     assert_eq!(mem.num_l2_tables, 0);
-    assert_eq!(store.num_available_pages, 10);
+    assert_eq!(store.available_pages(), 10);
 }
 
 #[test]
 fn memory_cleared() {
-    let mut store = PageStore::new(10);
-    let mut mem = Memory::new(&mut store, 5, 3);
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
 
-    assert_eq!(mem.allocate_page(0), MEM_SUCCESS);
+    assert_eq!(mem.allocate_page(0), Ok(()));
 
     // Write some data to the page
     unsafe {
         let page_idx = *mem.allocated_indices.add(0) as usize;
         let offset = page_idx * PAGE_SIZE;
-        *store.page_memory.add(offset) = 0x42;
-        *store.page_memory.add(offset + 1) = 0x43;
+        *store.page_memory().add(offset) = 0x42;
+        *store.page_memory().add(offset + 1) = 0x43;
 
         mem.reset();
 
         // Verify memory was cleared
-        assert_eq!(*store.page_memory.add(offset), 0);
-        assert_eq!(*store.page_memory.add(offset + 1), 0);
+        assert_eq!(*store.page_memory().add(offset), 0);
+        assert_eq!(*store.page_memory().add(offset + 1), 0);
     }
 }
 
 #[test]
 fn can_reallocate_after_reset() {
-    let mut store = PageStore::new(10);
-    let mut mem = Memory::new(&mut store, 5, 3);
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
 
-    assert_eq!(mem.allocate_page(0), MEM_SUCCESS);
-    assert_eq!(mem.allocate_page(1 << 22), MEM_SUCCESS);
+    assert_eq!(mem.allocate_page(0), Ok(()));
+    assert_eq!(mem.allocate_page(1 << 22), Ok(()));
     mem.reset();
 
     // Should be able to allocate again
-    assert_eq!(mem.allocate_page(0), MEM_SUCCESS);
-    assert_eq!(mem.allocate_page(PAGE_SIZE as u32), MEM_SUCCESS);
+    assert_eq!(mem.allocate_page(0), Ok(()));
+    assert_eq!(mem.allocate_page(PAGE_SIZE as u32), Ok(()));
     assert_eq!(mem.num_pages, 2);
     assert_eq!(mem.num_l2_tables, 1);
 }
 
 #[test]
 fn l1_table_cleared() {
-    let mut store = PageStore::new(10);
-    let mut mem = Memory::new(&mut store, 5, 3);
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
 
-    assert_eq!(mem.allocate_page(0), MEM_SUCCESS);
-    assert_eq!(mem.allocate_page(1 << 22), MEM_SUCCESS);
+    assert_eq!(mem.allocate_page(0), Ok(()));
+    assert_eq!(mem.allocate_page(1 << 22), Ok(()));
 
     // Verify L1 entries are set
     assert_ne!(mem.l1_table[0], 0xFF);
@@ -102,11 +102,11 @@ fn l1_table_cleared() {
 
 #[test]
 fn l2_tables_cleared() {
-    let mut store = PageStore::new(10);
-    let mut mem = Memory::new(&mut store, 5, 3);
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
 
-    assert_eq!(mem.allocate_page(0), MEM_SUCCESS);
-    assert_eq!(mem.allocate_page(PAGE_SIZE as u32), MEM_SUCCESS);
+    assert_eq!(mem.allocate_page(0), Ok(()));
+    assert_eq!(mem.allocate_page(PAGE_SIZE as u32), Ok(()));
 
     // Verify L2 entries are set
     unsafe {
@@ -127,3 +127,52 @@ fn l2_tables_cleared() {
         }
     }
 }
+
+#[test]
+fn soft_reset_keeps_pages() {
+    let store = PageStore::new(10);
+    let mut mem = Memory::new(&store, 5, 3);
+    mem.write(0x4000, &[1, 2, 3]).unwrap();
+    mem.write(0x800000, &[4]).unwrap();
+    let l1_table = mem.l1_table;
+
+    mem.soft_reset();
+    assert_eq!(mem.num_pages, 2);
+    assert_eq!(mem.num_l2_tables, 2);
+    assert_eq!(mem.l1_table, l1_table);
+    assert_eq!(store.available_pages(), 8);
+    assert_eq!(mem.stats().pages_zeroed, 2);
+    assert_eq!(mem.read_u32(0x4000), 0);
+    assert_eq!(mem.read_u8(0x800000), 0);
+
+    // Writing again reuses the mapped pages
+    mem.write(0x4000, &[5]).unwrap();
+    assert_eq!(mem.num_pages, 2);
+    assert_eq!(mem.stats().pages_allocated, 2);
+}
+
+#[test]
+fn soft_reset_restores_state() {
+    let store = PageStore::new(10);
+    let shared = store.share(b"code").unwrap();
+    let mut mem = Memory::new(&store, 5, 3);
+    mem.map_shared(0, &shared).unwrap();
+    mem.set_heap_base(0x10000).unwrap();
+    mem.grow(2).unwrap();
+    mem.protect(0x10000, PAGE_SIZE as u32, Permissions::NONE)
+        .unwrap();
+    let mut buffer = vec![0u8; PAGE_SIZE];
+    unsafe { mem.map_host(0x40000, &mut buffer).unwrap() };
+
+    mem.soft_reset();
+    assert_eq!(mem.heap_size(), 0);
+    assert_eq!(mem.permissions(0x10000), Permissions::ALL);
+    assert!(mem.shared(0));
+    assert_eq!(mem.permissions(0), Permissions::READ_EXECUTE);
+    assert_eq!(mem.read_u32(0), u32::from_le_bytes(*b"code"));
+    assert!(mem.regions().all(|region| region.kind != RegionKind::Host));
+
+    // The heap grows back into its existing pages
+    assert_eq!(mem.grow(2), Ok(0));
+    assert_eq!(mem.num_pages, 2);
+}
@@ -0,0 +1,151 @@
+use crate::memory::{Access, Memory, MemoryError, PAGE_SIZE, PageStore, Permissions};
+
+#[test]
+fn share_pads_to_pages() {
+    let store = PageStore::new(10);
+    let shared = store.share(&vec![0xAA; PAGE_SIZE + 1]).unwrap();
+    assert_eq!(shared.pages(), 2);
+    assert_eq!(shared.len(), 2 * PAGE_SIZE);
+    assert!(!shared.is_empty());
+    assert_eq!(store.available_pages(), 8);
+    drop(shared);
+    assert_eq!(store.available_pages(), 10);
+}
+
+#[test]
+fn share_without_pages() {
+    let store = PageStore::new(1);
+    assert_eq!(
+        store.share(&vec![0; 2 * PAGE_SIZE]).err(),
+        Some(MemoryError::NoPagesAvailable)
+    );
+    assert_eq!(store.available_pages(), 1);
+}
+
+#[test]
+fn mapped_by_many_instances() {
+    let store = PageStore::new(10);
+    let shared = store.share(&[1, 2, 3, 4]).unwrap();
+    let mut instances: Vec<Memory> = (0..4).map(|_| Memory::new(&store, 2, 2)).collect();
+    for memory in &mut instances {
+        assert_eq!(memory.map_shared(0x10000, &shared), Ok(()));
+        assert!(memory.shared(0x10000));
+        assert_eq!(memory.read_u32(0x10000), 0x04030201);
+        assert_eq!(memory.read_u8(0x10004), 0);
+        assert_eq!(memory.num_pages, 0);
+    }
+    assert_eq!(store.available_pages(), 9);
+    assert_eq!(
+        format!("{:?}", shared),
+        "SharedPages { pages: 1, mappings: 4 }"
+    );
+}
+
+#[test]
+fn read_only() {
+    let store = PageStore::new(10);
+    let shared = store.share(&[7; 8]).unwrap();
+    let mut memory = Memory::new(&store, 2, 2);
+    assert_eq!(memory.map_shared(0, &shared), Ok(()));
+    assert_eq!(memory.permissions(0), Permissions::READ_EXECUTE);
+    assert_eq!(
+        memory.check(0, 4, Access::Write),
+        Err(MemoryError::Permission)
+    );
+    assert_eq!(memory.write(0, &[1]), Err(MemoryError::ReadOnly));
+
+    memory.protect(0, 4, Permissions::ALL).unwrap();
+    assert_eq!(memory.permissions(0), Permissions::ALL);
+    assert_eq!(
+        memory.write(PAGE_SIZE as u32 - 1, &[1]),
+        Err(MemoryError::ReadOnly)
+    );
+    assert_eq!(memory.read_u8(0), 7);
+}
+
+#[test]
+fn pages_outlive_store_and_return_on_reset() {
+    let store = PageStore::new(10);
+    let shared = store.share(&[9; 4]).unwrap();
+    let mut memory = Memory::new(&store, 2, 2);
+    assert_eq!(memory.map_shared(PAGE_SIZE as u32, &shared), Ok(()));
+    drop(shared);
+    assert_eq!(store.available_pages(), 9);
+    assert_eq!(memory.read_u8(PAGE_SIZE as u32), 9);
+
+    memory.reset();
+    assert!(!memory.shared(PAGE_SIZE as u32));
+    assert_eq!(memory.read_u8(PAGE_SIZE as u32), 0);
+    assert_eq!(store.available_pages(), 10);
+}
+
+#[test]
+fn unmap() {
+    let store = PageStore::new(10);
+    let shared = store.share(&[5; 4]).unwrap();
+    let mut memory = Memory::new(&store, 2, 2);
+    assert_eq!(memory.map_shared(0, &shared), Ok(()));
+    assert!(memory.unmap_shared(0));
+    assert!(!memory.unmap_shared(0));
+    assert_eq!(memory.read_u8(0), 0);
+    assert_eq!(memory.permissions(0), Permissions::ALL);
+    assert_eq!(memory.write(0, &[1]), Ok(()));
+    assert_eq!(memory.num_pages, 1);
+}
+
+#[test]
+fn unmap_restores_permissions() {
+    let store = PageStore::new(10);
+    let shared = store.share(&vec![5; 2 * PAGE_SIZE]).unwrap();
+    let mut memory = Memory::new(&store, 2, 2);
+    let base = 4 * PAGE_SIZE as u32;
+    memory
+        .protect(base, PAGE_SIZE as u32, Permissions::READ_ONLY)
+        .unwrap();
+    assert_eq!(memory.map_shared(base, &shared), Ok(()));
+    assert!(memory.unmap_shared(base));
+    assert_eq!(memory.permissions(base), Permissions::READ_ONLY);
+    assert_eq!(
+        memory.permissions(base + PAGE_SIZE as u32),
+        Permissions::ALL
+    );
+
+    // A soft reset restores the defaults, so unmapping afterwards does too
+    memory
+        .protect(base, PAGE_SIZE as u32, Permissions::READ_ONLY)
+        .unwrap();
+    assert_eq!(memory.map_shared(base, &shared), Ok(()));
+    memory.soft_reset();
+    assert!(memory.unmap_shared(base));
+    assert_eq!(memory.permissions(base), Permissions::ALL);
+}
+
+#[test]
+fn map_errors() {
+    let store = PageStore::new(10);
+    let shared = store.share(&vec![1; 2 * PAGE_SIZE]).unwrap();
+    let mut memory = Memory::new(&store, 2, 1);
+    assert_eq!(
+        memory.map_shared(0x10, &shared),
+        Err(MemoryError::Misaligned)
+    );
+    assert_eq!(
+        memory.map_shared(0u32.wrapping_sub(PAGE_SIZE as u32), &shared),
+        Err(MemoryError::Misaligned)
+    );
+
+    assert_eq!(memory.allocate_page(PAGE_SIZE as u32), Ok(()));
+    assert_eq!(memory.map_shared(0, &shared), Err(MemoryError::Overlap));
+    assert_eq!(memory.guard(0x10000, 1), Ok(()));
+    assert_eq!(
+        memory.map_shared(0x10000, &shared),
+        Err(MemoryError::Overlap)
+    );
+
+    // The only L2 table covers 0..4MB
+    assert_eq!(
+        memory.map_shared(0x400000, &shared),
+        Err(MemoryError::NoL2Tables)
+    );
+    assert_eq!(memory.map_shared(0x20000, &shared), Ok(()));
+}
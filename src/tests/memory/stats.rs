@@ -0,0 +1,73 @@
+use crate::memory::{Memory, MemoryError, MemoryStats, PAGE_SIZE, PageStore, PageStoreStats};
+
+#[test]
+fn initial() {
+    let store = PageStore::new(10);
+    let memory = Memory::new(&store, 5, 2);
+    assert_eq!(*memory.stats(), MemoryStats::default());
+    assert_eq!(store.stats(), PageStoreStats::default());
+}
+
+#[test]
+fn peaks_survive_reset() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.write(0, &vec![1; 3 * PAGE_SIZE]), Ok(()));
+    assert_eq!(memory.allocate_page(0x400000), Ok(()));
+    memory.reset();
+    assert_eq!(memory.allocate_page(0), Ok(()));
+
+    let stats = memory.stats();
+    assert_eq!(stats.pages_allocated, 5);
+    assert_eq!(stats.peak_pages, 4);
+    assert_eq!(stats.peak_l2_tables, 2);
+    assert_eq!(stats.pages_zeroed, 4);
+}
+
+#[test]
+fn failures_by_cause() {
+    let store = PageStore::new(3);
+    let mut memory = Memory::new(&store, 2, 1);
+    assert_eq!(memory.guard(0x10000, 1), Ok(()));
+    assert_eq!(memory.allocate_page(0x10000), Err(MemoryError::Guard));
+    assert_eq!(memory.allocate_page(0), Ok(()));
+    assert_eq!(memory.allocate_page(0x400000), Err(MemoryError::NoL2Tables));
+    assert_eq!(memory.allocate_page(PAGE_SIZE as u32), Ok(()));
+    assert_eq!(
+        memory.write(2 * PAGE_SIZE as u32, &[1]),
+        Err(MemoryError::PageLimit)
+    );
+    assert_eq!(memory.allocate_page(0), Ok(())); // Already mapped
+
+    let mut other = Memory::new(&store, 1, 1);
+    let _shared = store.share(&[1]).unwrap();
+    assert_eq!(other.allocate_page(0), Err(MemoryError::NoPagesAvailable));
+
+    let stats = memory.stats();
+    assert_eq!(stats.guard, 1);
+    assert_eq!(stats.no_l2_tables, 1);
+    assert_eq!(stats.page_limit, 1);
+    assert_eq!(stats.no_pages_available, 0);
+    assert_eq!(other.stats().no_pages_available, 1);
+}
+
+#[test]
+fn page_store() {
+    let store = PageStore::new(4);
+    let mut first = Memory::new(&store, 4, 1);
+    let mut second = Memory::new(&store, 4, 1);
+    assert_eq!(first.write(0, &vec![1; 2 * PAGE_SIZE]), Ok(()));
+    assert_eq!(second.write(0, &vec![1; 2 * PAGE_SIZE]), Ok(()));
+    assert_eq!(
+        second.allocate_page(0x8000),
+        Err(MemoryError::NoPagesAvailable)
+    );
+    assert_eq!(store.share(&[1]).err(), Some(MemoryError::NoPagesAvailable));
+    first.reset();
+    drop(second);
+
+    let stats = store.stats();
+    assert_eq!(stats.peak_pages, 4);
+    assert_eq!(stats.exhausted, 2);
+    assert_eq!(stats.pages_zeroed, 4);
+}
@@ -0,0 +1,56 @@
+use crate::memory::{Memory, MemoryError, PAGE_SIZE, PageStore};
+
+#[test]
+fn write_and_read_cstr() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.write_str(0x100, "/tmp/file"), Ok(()));
+    assert_eq!(memory.read_u8(0x109), 0);
+    assert_eq!(memory.read_cstr(0x100, 64), Ok(b"/tmp/file".to_vec()));
+    assert_eq!(memory.read_cstr(0x104, 64), Ok(b"/file".to_vec()));
+}
+
+#[test]
+fn cstr_max_includes_terminator() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.write_str(0, "abcd"), Ok(()));
+    assert_eq!(memory.read_cstr(0, 5), Ok(b"abcd".to_vec()));
+    assert_eq!(memory.read_cstr(0, 4), Err(MemoryError::Unterminated));
+    assert_eq!(memory.read_cstr(0, 0), Err(MemoryError::Unterminated));
+}
+
+#[test]
+fn cstr_across_pages() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    let text = "x".repeat(200);
+    let address = PAGE_SIZE as u32 - 100;
+    assert_eq!(memory.write_str(address, &text), Ok(()));
+    assert_eq!(memory.read_cstr(address, 1000), Ok(text.into_bytes()));
+}
+
+#[test]
+fn unallocated_is_empty() {
+    let store = PageStore::new(10);
+    let memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.read_cstr(0x5000, 16), Ok(Vec::new()));
+}
+
+#[test]
+fn read_utf8() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.write(0, "héllo".as_bytes()), Ok(()));
+    assert_eq!(memory.read_utf8(0, 6), Ok("héllo".to_string()));
+    assert_eq!(memory.read_utf8(0, 2), Err(MemoryError::InvalidString));
+    assert_eq!(memory.read_utf8(0, 0), Ok(String::new()));
+}
+
+#[test]
+fn interior_nul() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.write_str(0, "a\0b"), Err(MemoryError::InvalidString));
+    assert_eq!(memory.num_pages, 0);
+}
@@ -0,0 +1,74 @@
+use crate::memory::{L2TableInfo, Memory, PAGE_SIZE, PageStore, Permissions, PhysicalOffset};
+
+#[test]
+fn translate_allocated() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 2);
+    assert_eq!(mem.translate(0x4010), None);
+    mem.write(0x4010, &[0xab]).unwrap();
+
+    let physical = mem.translate(0x4010).unwrap();
+    assert_eq!(physical.offset, physical.page as usize * PAGE_SIZE + 0x10);
+    assert_eq!(unsafe { *store.page_memory().add(physical.offset) }, 0xab);
+    assert_eq!(
+        mem.translate(0x7fff),
+        Some(PhysicalOffset {
+            page: physical.page,
+            offset: physical.page as usize * PAGE_SIZE + PAGE_SIZE - 1
+        })
+    );
+    assert_eq!(mem.translate(0x8000), None);
+}
+
+#[test]
+fn translate_shared_and_host() {
+    let store = PageStore::new(8);
+    let shared = store.share(b"shared").unwrap();
+    let mut mem = Memory::new(&store, 4, 2);
+    mem.map_shared(0, &shared).unwrap();
+    let physical = mem.translate(2).unwrap();
+    assert_eq!(unsafe { *store.page_memory().add(physical.offset) }, b'a');
+
+    let mut buffer = vec![0u8; PAGE_SIZE];
+    unsafe { mem.map_host(0x8000, &mut buffer).unwrap() };
+    assert_eq!(mem.translate(0x8000), None);
+}
+
+#[test]
+fn l2_table_occupancy() {
+    let store = PageStore::new(8);
+    let mut mem = Memory::new(&store, 8, 4);
+    assert_eq!(mem.l2_tables().count(), 0);
+    mem.allocate_page(0x0080_0000).unwrap();
+    mem.allocate_page(0x0000_4000).unwrap();
+    mem.allocate_page(0x0000_8000).unwrap();
+    mem.protect(0x00c0_0000, 1, Permissions::READ_ONLY).unwrap();
+
+    let tables: Vec<L2TableInfo> = mem.l2_tables().collect();
+    assert_eq!(
+        tables,
+        [
+            L2TableInfo {
+                l1_index: 0,
+                table: 1,
+                address: 0,
+                mapped: 2
+            },
+            L2TableInfo {
+                l1_index: 2,
+                table: 0,
+                address: 0x0080_0000,
+                mapped: 1
+            },
+            L2TableInfo {
+                l1_index: 3,
+                table: 2,
+                address: 0x00c0_0000,
+                mapped: 0
+            },
+        ]
+    );
+
+    mem.reset();
+    assert_eq!(mem.l2_tables().count(), 0);
+}
@@ -0,0 +1,65 @@
+use crate::memory::{Memory, MemoryError, PAGE_SIZE, PageStore};
+
+#[test]
+fn roundtrip_unsigned() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.write_u8(0x100, 0xAB), Ok(()));
+    assert_eq!(memory.write_u16(0x200, 0xBEEF), Ok(()));
+    assert_eq!(memory.write_u32(0x300, 0xDEADBEEF), Ok(()));
+    assert_eq!(memory.write_u64(0x400, 0x0123_4567_89AB_CDEF), Ok(()));
+    assert_eq!(memory.read_u8(0x100), 0xAB);
+    assert_eq!(memory.read_u16(0x200), 0xBEEF);
+    assert_eq!(memory.read_u32(0x300), 0xDEADBEEF);
+    assert_eq!(memory.read_u64(0x400), 0x0123_4567_89AB_CDEF);
+}
+
+#[test]
+fn roundtrip_signed() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.write_i8(0x100, -2), Ok(()));
+    assert_eq!(memory.write_i16(0x200, -300), Ok(()));
+    assert_eq!(memory.write_i32(0x300, i32::MIN), Ok(()));
+    assert_eq!(memory.write_i64(0x400, -1), Ok(()));
+    assert_eq!(memory.read_i8(0x100), -2);
+    assert_eq!(memory.read_i16(0x200), -300);
+    assert_eq!(memory.read_i32(0x300), i32::MIN);
+    assert_eq!(memory.read_i64(0x400), -1);
+    assert_eq!(memory.read_u8(0x100), 0xFE);
+}
+
+#[test]
+fn little_endian() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.write_u32(0x100, 0x11223344), Ok(()));
+    assert_eq!(memory.read_array::<4>(0x100), [0x44, 0x33, 0x22, 0x11]);
+    assert_eq!(memory.read_u16(0x102), 0x1122);
+}
+
+#[test]
+fn across_page_boundary() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 5, 2);
+    let address = PAGE_SIZE as u32 - 3;
+    assert_eq!(memory.write_u64(address, 0x0807_0605_0403_0201), Ok(()));
+    assert_eq!(memory.num_pages, 2);
+    assert_eq!(memory.read_u64(address), 0x0807_0605_0403_0201);
+    assert_eq!(memory.read_u8(PAGE_SIZE as u32), 0x04);
+}
+
+#[test]
+fn unallocated_reads_zero() {
+    let store = PageStore::new(10);
+    let memory = Memory::new(&store, 5, 2);
+    assert_eq!(memory.read_u64(0x1000), 0);
+    assert_eq!(memory.read_i32(0xFFFF_FFFE), 0);
+}
+
+#[test]
+fn write_error() {
+    let store = PageStore::new(10);
+    let mut memory = Memory::new(&store, 0, 2);
+    assert_eq!(memory.write_u32(0x100, 1), Err(MemoryError::PageLimit));
+}
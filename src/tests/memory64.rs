@@ -0,0 +1,121 @@
+use crate::memory::{Access, MemoryError, PAGE_SIZE, PageStore, Permissions};
+use crate::memory64::{Memory64, WINDOW_SIZE};
+
+#[test]
+fn windows_created_on_write() {
+    let store = PageStore::new(16);
+    let mut mem = Memory64::new(&store, 8, 4);
+    assert_eq!(mem.read_u64(0x1234_5678_9abc), 0);
+    assert_eq!(mem.windows().count(), 0);
+
+    mem.write_u32(0x1234_5678_9abc, 0xdead_beef).unwrap();
+    mem.write_u32(0x9abc, 7).unwrap();
+    assert_eq!(mem.read_u32(0x1234_5678_9abc), 0xdead_beef);
+    assert_eq!(mem.read_u32(0x5678_9abc), 0);
+    assert_eq!(mem.read_u32(0x9abc), 7);
+    let bases: Vec<u64> = mem.windows().map(|(base, _)| base).collect();
+    assert_eq!(bases, [0, 0x1234_0000_0000]);
+    assert_eq!(mem.num_pages(), 2);
+    assert!(mem.window(0x1234_0000_0000).is_some());
+    assert!(mem.window(WINDOW_SIZE).is_none());
+}
+
+#[test]
+fn access_spans_windows() {
+    let store = PageStore::new(16);
+    let mut mem = Memory64::new(&store, 8, 4);
+    mem.write_u64(2 * WINDOW_SIZE - 4, 0x0807_0605_0403_0201)
+        .unwrap();
+    assert_eq!(mem.read_u64(2 * WINDOW_SIZE - 4), 0x0807_0605_0403_0201);
+    assert_eq!(mem.read_u32(2 * WINDOW_SIZE), 0x0807_0605);
+    assert_eq!(mem.windows().count(), 2);
+
+    mem.write(u64::MAX, &[1, 2]).unwrap();
+    assert_eq!(mem.read_u8(u64::MAX), 1);
+    assert_eq!(mem.read_u8(0), 2);
+}
+
+#[test]
+fn limits_are_shared() {
+    let store = PageStore::new(16);
+    let mut mem = Memory64::new(&store, 2, 8);
+    mem.write_u8(0, 1).unwrap();
+    mem.write_u8(WINDOW_SIZE, 1).unwrap();
+    assert_eq!(
+        mem.write_u8(2 * WINDOW_SIZE, 1),
+        Err(MemoryError::PageLimit)
+    );
+    assert_eq!(
+        mem.write_u8(PAGE_SIZE as u64, 1),
+        Err(MemoryError::PageLimit)
+    );
+    assert_eq!(mem.num_pages(), 2);
+
+    mem.reset();
+    assert_eq!(mem.num_pages(), 0);
+    assert_eq!(mem.windows().count(), 0);
+    mem.write_u8(5 * WINDOW_SIZE, 1).unwrap();
+    mem.write_u8(5 * WINDOW_SIZE + PAGE_SIZE as u64, 1).unwrap();
+}
+
+#[test]
+fn relimit_keeps_capacity() {
+    let store = PageStore::new(16);
+    let mut mem = Memory64::new(&store, 4, 8);
+    mem.write_u8(0, 1).unwrap();
+    mem.write_u8(WINDOW_SIZE, 1).unwrap();
+    mem.write_u8(PAGE_SIZE as u64, 1).unwrap();
+    mem.write_u8(WINDOW_SIZE + PAGE_SIZE as u64, 1).unwrap();
+    assert_eq!(
+        mem.write_u8(2 * PAGE_SIZE as u64, 1),
+        Err(MemoryError::PageLimit)
+    );
+    assert_eq!(mem.window(0).unwrap().max_pages, 4);
+    assert_eq!(mem.window(WINDOW_SIZE).unwrap().max_pages, 3);
+    assert_eq!(mem.window(WINDOW_SIZE).unwrap().max_l2_tables, 7);
+    drop(mem);
+    assert_eq!(store.available_pages(), 16);
+}
+
+#[test]
+fn l2_tables_are_shared() {
+    let store = PageStore::new(16);
+    let mut mem = Memory64::new(&store, 8, 2);
+    mem.write_u8(0, 1).unwrap();
+    mem.write_u8(WINDOW_SIZE, 1).unwrap();
+    assert_eq!(mem.num_l2_tables(), 2);
+    assert_eq!(
+        mem.write_u8(2 * WINDOW_SIZE, 1),
+        Err(MemoryError::NoL2Tables)
+    );
+    assert_eq!(
+        mem.write_u8(WINDOW_SIZE / 2, 1),
+        Err(MemoryError::NoL2Tables)
+    );
+    assert_eq!(mem.windows().count(), 2);
+}
+
+#[test]
+fn permissions() {
+    let store = PageStore::new(16);
+    let mut mem = Memory64::new(&store, 8, 4);
+    let base = 0x10_0000_0000u64;
+    mem.protect(
+        base - PAGE_SIZE as u64,
+        2 * PAGE_SIZE as u64,
+        Permissions::READ_ONLY,
+    )
+    .unwrap();
+    assert_eq!(mem.permissions(base - 1), Permissions::READ_ONLY);
+    assert_eq!(mem.permissions(base), Permissions::READ_ONLY);
+    assert_eq!(mem.permissions(base + PAGE_SIZE as u64), Permissions::ALL);
+    assert_eq!(mem.permissions(0x20_0000_0000), Permissions::ALL);
+    assert_eq!(mem.num_pages(), 0);
+
+    assert_eq!(mem.check(base - 8, 16, Access::Read), Ok(()));
+    assert_eq!(
+        mem.check(base - 8, 16, Access::Write),
+        Err(MemoryError::Permission)
+    );
+    assert_eq!(mem.check(0x30_0000_0000, 16, Access::Write), Ok(()));
+}
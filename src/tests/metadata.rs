@@ -0,0 +1,140 @@
+use crate::{
+    CodeCache, CompileError, ConstantStrategy, Decoder, Extensions, Instruction, Module, OptLevel,
+    metadata::source_hash, serial::COMPILER,
+};
+use std::time::SystemTime;
+
+#[test]
+fn hash() {
+    assert_eq!(source_hash(b""), 0xCBF2_9CE4_8422_2325);
+    assert_eq!(source_hash(b"a"), 0xAF63_DC4C_8601_EC8C);
+    assert_eq!(source_hash(b"foobar"), 0x8594_4171_F739_67E8);
+}
+
+#[test]
+fn none_without_code() {
+    assert!(Module::new(4).unwrap().metadata().is_none());
+}
+
+#[test]
+fn recorded() {
+    let mut module = Module::new(64).unwrap();
+    module.set_opt_level(OptLevel::O1);
+    module.set_resumable(true);
+    module.set_base(0x400);
+    module.set_constants(ConstantStrategy::LiteralPool);
+    module.set_functions(&[0x404]);
+    let code = Instruction::encode_all(&[
+        Instruction::Addi {
+            rd: 5,
+            rs1: 0,
+            imm: 1,
+        },
+        Instruction::Ecall,
+    ])
+    .unwrap();
+    let before = SystemTime::now();
+    module.set_code(&code).unwrap();
+    let metadata = module.metadata().unwrap();
+    assert_eq!(metadata.source_hash, source_hash(&code));
+    assert_eq!(metadata.compiler, COMPILER);
+    assert_eq!(metadata.opt_level, OptLevel::O1);
+    assert!(metadata.resumable);
+    assert!(!metadata.block_counters);
+    assert_eq!(metadata.base, 0x400);
+    assert_eq!(metadata.constants, ConstantStrategy::LiteralPool);
+    assert_eq!(metadata.functions, [0x404]);
+    assert_eq!(metadata.decoder, Decoder::default());
+    assert_eq!(metadata.extensions, Extensions::base());
+    assert!(metadata.created >= before && metadata.created <= SystemTime::now());
+}
+
+#[test]
+fn extensions() {
+    let mut module = Module::new(64).unwrap();
+    module.set_decoder(Decoder::new(Extensions::all()));
+    let code = Instruction::encode_all(&[
+        Instruction::Mul {
+            rd: 5,
+            rs1: 6,
+            rs2: 7,
+        },
+        Instruction::VaddVv {
+            vd: 1,
+            vs2: 2,
+            vs1: 3,
+            vm: true,
+        },
+    ])
+    .unwrap();
+    module.set_code(&code).unwrap();
+    let extensions = module.metadata().unwrap().extensions;
+    assert!(extensions.m && extensions.v);
+    assert!(!extensions.c && !extensions.zfh);
+
+    // Instructions the decoder rejects use no extension
+    module.set_decoder(Decoder::new(Extensions::base()));
+    module.set_code(&code).unwrap();
+    assert_eq!(module.metadata().unwrap().extensions, Extensions::base());
+}
+
+#[test]
+fn kept_on_error() {
+    let mut module = Module::new(64).unwrap();
+    let code = Instruction::encode_all(&[Instruction::Ecall]).unwrap();
+    module.set_code(&code).unwrap();
+    let metadata = module.metadata().cloned();
+    let jump = Instruction::encode_all(&[Instruction::Jal { rd: 0, imm: 2 }]).unwrap();
+    assert_eq!(
+        module.set_code(&jump),
+        Err(CompileError::InvalidJumpTarget {
+            offset: 0,
+            target: 2
+        })
+    );
+    assert_eq!(module.metadata().cloned(), metadata);
+}
+
+#[test]
+fn cache_hit() {
+    let cache = CodeCache::new();
+    let code = Instruction::encode_all(&[Instruction::Ecall]).unwrap();
+    let mut first = Module::new(4).unwrap();
+    first.set_code_cache(cache.clone());
+    first.set_code(&code).unwrap();
+    let mut second = Module::new(4).unwrap();
+    second.set_code_cache(cache.clone());
+    second.set_code(&code).unwrap();
+    assert_eq!(cache.hits(), 1);
+    let (first, second) = (first.metadata().unwrap(), second.metadata().unwrap());
+    assert_eq!(second.source_hash, first.source_hash);
+    assert!(second.created >= first.created);
+}
+
+#[test]
+fn current() {
+    let mut module = Module::new(64).unwrap();
+    let code = Instruction::encode_all(&[Instruction::Ecall]).unwrap();
+    module.set_code(&code).unwrap();
+    let metadata = module.metadata().unwrap().clone();
+    assert!(metadata.current(&code, &module));
+    assert!(!metadata.current(
+        &Instruction::encode_all(&[Instruction::Ebreak]).unwrap(),
+        &module
+    ));
+
+    module.set_opt_level(OptLevel::O2);
+    assert!(!metadata.current(&code, &module));
+    module.set_opt_level(OptLevel::O0);
+    module.set_decoder(Decoder::new(Extensions::all()));
+    assert!(!metadata.current(&code, &module));
+    module.set_decoder(Decoder::default());
+    module.set_functions(&[0]);
+    assert!(!metadata.current(&code, &module));
+    module.set_functions(&[]);
+    assert!(metadata.current(&code, &module));
+
+    let mut stale = metadata;
+    stale.compiler = "0.0.0".to_string();
+    assert!(!stale.current(&code, &module));
+}
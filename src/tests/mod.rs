@@ -1,6 +1,41 @@
+mod arm64;
+mod assembler;
+mod bundle;
+mod cache;
 mod compiler;
+mod cost;
+mod csr;
+mod cursor;
+mod decoder;
+mod diff;
+mod disassembler;
+mod elf;
+mod executable;
+mod fold;
+mod fusion;
+#[cfg(feature = "arbitrary")]
+mod generate;
+mod harness;
 mod instance;
 mod instruction;
+mod interpreter;
+mod isa;
+mod layout;
 mod memory;
+mod memory64;
+mod metadata;
 mod module;
+mod neon;
+mod parser;
+mod pattern;
+mod peephole;
+mod regalloc;
+mod repl;
 mod runtime;
+mod serial;
+mod syscall;
+mod tier;
+mod unwind;
+mod veneer;
+mod verify;
+mod visitor;
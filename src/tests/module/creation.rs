@@ -1,7 +1,10 @@
 use crate::{
+    OptLevel,
+    compiler::Compiler,
+    decoder::Decoder,
     instance::Instance,
     memory::{Memory, PageStore},
-    module::{CompileError, Module},
+    module::{CODE_RESERVE, CompileError, Module},
 };
 
 #[test]
@@ -45,15 +48,27 @@ fn set_code_multiple_times() {
     assert!(result.is_ok());
 }
 
+#[test]
+fn set_code_records_compile_stats() {
+    let mut module = Module::new(100).unwrap();
+    assert_eq!(module.compile_stats(), None);
+    // addi x1, x0, 1; addi x2, x1, 1
+    let code = [0x93, 0x00, 0x10, 0x00, 0x13, 0x81, 0x10, 0x00];
+    module.set_code(&code).unwrap();
+    let stats = module.compile_stats().unwrap();
+    assert_eq!(stats.instructions, 2);
+    assert_eq!(stats.words * 4, module.code().len());
+}
+
 #[test]
 fn set_code_with_attached_instance() {
-    let mut store = PageStore::new(100);
-    let memory = Memory::new(&mut store, 50, 10);
+    let store = PageStore::new(100);
+    let memory = Memory::new(&store, 50, 10);
     let mut module = Module::new(100).unwrap();
     let mut instance = Instance::new(memory);
 
     // Attach instance
-    instance.attach(&mut module);
+    instance.attach(&mut module).unwrap();
     assert_eq!(module.instance_count, 1);
 
     // Try to set code - should fail
@@ -65,13 +80,13 @@ fn set_code_with_attached_instance() {
 
 #[test]
 fn set_code_after_detaching_instance() {
-    let mut store = PageStore::new(100);
-    let memory = Memory::new(&mut store, 50, 10);
+    let store = PageStore::new(100);
+    let memory = Memory::new(&store, 50, 10);
     let mut module = Module::new(100).unwrap();
     let mut instance = Instance::new(memory);
 
     // Attach and then detach instance
-    instance.attach(&mut module);
+    instance.attach(&mut module).unwrap();
     assert_eq!(module.instance_count, 1);
     instance.detach();
     assert_eq!(module.instance_count, 0);
@@ -105,24 +120,24 @@ fn initial_instance_count() {
 
 #[test]
 fn attach_instance() {
-    let mut store = PageStore::new(100);
-    let memory = Memory::new(&mut store, 50, 10);
+    let store = PageStore::new(100);
+    let memory = Memory::new(&store, 50, 10);
     let mut module = Module::new(1).unwrap();
     let mut instance = Instance::new(memory);
-    instance.attach(&mut module);
+    instance.attach(&mut module).unwrap();
     assert_eq!(module.instance_count, 1);
 }
 
 #[test]
 fn detach_instance() {
-    let mut store = PageStore::new(100);
-    let memory1 = Memory::new(&mut store, 50, 10);
-    let memory2 = Memory::new(&mut store, 50, 10);
+    let store = PageStore::new(100);
+    let memory1 = Memory::new(&store, 50, 10);
+    let memory2 = Memory::new(&store, 50, 10);
     let mut module = Module::new(1).unwrap();
     let mut instance1 = Instance::new(memory1);
     let mut instance2 = Instance::new(memory2);
-    instance1.attach(&mut module);
-    instance2.attach(&mut module);
+    instance1.attach(&mut module).unwrap();
+    instance2.attach(&mut module).unwrap();
     assert_eq!(module.instance_count, 2);
     instance1.detach();
     assert_eq!(module.instance_count, 1);
@@ -130,13 +145,13 @@ fn detach_instance() {
 
 #[test]
 fn multiple_attachments() {
-    let mut store = PageStore::new(500);
+    let store = PageStore::new(500);
     let mut module = Module::new(1).unwrap();
     let mut instances = Vec::new();
     for _ in 0..5 {
-        let memory = Memory::new(&mut store, 50, 10);
+        let memory = Memory::new(&store, 50, 10);
         let mut instance = Instance::new(memory);
-        instance.attach(&mut module);
+        instance.attach(&mut module).unwrap();
         instances.push(instance);
     }
     assert_eq!(module.instance_count, 5);
@@ -160,11 +175,11 @@ fn drop_with_multiple_attached_instances() {
 
 #[test]
 fn drop_after_detach() {
-    let mut store = PageStore::new(100);
-    let memory = Memory::new(&mut store, 50, 10);
+    let store = PageStore::new(100);
+    let memory = Memory::new(&store, 50, 10);
     let mut module = Module::new(1).unwrap();
     let mut instance = Instance::new(memory);
-    instance.attach(&mut module);
+    instance.attach(&mut module).unwrap();
     assert_eq!(module.instance_count, 1);
     drop(instance);
     assert_eq!(module.instance_count, 0);
@@ -221,18 +236,93 @@ fn new_allocation_failure() {
     }
 }
 
+#[test]
+fn set_code_grows_buffer() {
+    // addi x1, x1, 1
+    let adds: Vec<u8> = [0x00108093u32; 64]
+        .iter()
+        .flat_map(|w| w.to_le_bytes())
+        .collect();
+    let mut module = Module::new(4).unwrap();
+    module.set_code(&adds[..4]).unwrap();
+    let (start, capacity) = (module.code().as_ptr(), module.code_capacity());
+
+    module.set_code(&adds).unwrap();
+    assert!(module.code_capacity() > capacity);
+    assert_eq!(module.code().as_ptr(), start);
+    assert_eq!(module.max_code_size(), 4);
+    let mut expected = Module::new(adds.len()).unwrap();
+    expected.set_code(&adds).unwrap();
+    assert_eq!(module.code(), expected.code());
+    assert_eq!(module.source_map(), expected.source_map());
+}
+
 #[test]
 fn set_code_too_large() {
-    // Create a module with small buffer
-    let mut module = Module::new(10).unwrap();
+    // Enough lw x1, 0(x2) that their code could exceed the reservation
+    let load = Decoder::default().decode(0x00012083);
+    let size =
+        Compiler::estimate_size(&[load.clone(), load.clone()]) - Compiler::estimate_size(&[load]);
+    let code: Vec<u8> = vec![0x00012083u32; CODE_RESERVE / size + 1]
+        .iter()
+        .flat_map(|w| w.to_le_bytes())
+        .collect();
+    let mut module = Module::new(4).unwrap();
+    let nop = 0x00000013u32.to_le_bytes();
+    module.set_code(&nop).unwrap();
+    let compiled = module.code().to_vec();
+    assert_eq!(module.set_code(&code), Err(CompileError::CodeTooLarge));
+    assert_eq!(module.code(), compiled);
+}
 
-    // Try to set code that's larger than the buffer capacity
-    // The module can hold 10 * 4 = 40 bytes of ARM64 code
-    // So trying to set 11 bytes of RISC-V code should fail
-    let code = vec![0u8; 11];
+#[test]
+fn set_code_rejects_jump_into_instruction() {
+    let mut module = Module::new(64).unwrap();
+    let nop = 0x00000013u32.to_le_bytes(); // addi x0, x0, 0
+    module.set_code(&nop).unwrap();
+    let compiled = module.code().to_vec();
+
+    // nop; beq x0, x0, 6; nop
+    let code: Vec<u8> = [0x00000013u32, 0x00000363, 0x00000013]
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
     let result = module.set_code(&code);
-    assert!(result.is_err());
-    assert_eq!(result.unwrap_err(), CompileError::CodeTooLarge);
+    assert_eq!(
+        result.unwrap_err(),
+        CompileError::InvalidJumpTarget {
+            offset: 4,
+            target: 10
+        }
+    );
+    // The previous code is kept
+    assert_eq!(module.code(), compiled);
+}
+
+#[test]
+fn set_code_rejects_function_outside_code() {
+    let mut module = Module::new(64).unwrap();
+    let code: Vec<u8> = [0x00000013u32, 0x00000013]
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect();
+    module.set_base(0x100);
+    module.set_functions(&[0x104, 0x108]);
+    assert_eq!(module.functions(), [0x104, 0x108]);
+    assert_eq!(
+        module.set_code(&code),
+        Err(CompileError::InvalidFunction {
+            index: 1,
+            address: 0x108
+        })
+    );
+    assert!(module.code().is_empty());
+
+    module.set_functions(&[0x104, 0x100]);
+    module.set_code(&code).unwrap();
+    let map = module.source_map();
+    assert_eq!(map.functions(), 2);
+    assert!(map.function(0).unwrap() < map.function(1).unwrap());
 }
 
 #[test]
@@ -245,3 +335,95 @@ fn set_code_exactly_at_limit() {
     let result = module.set_code(&code);
     assert!(result.is_ok());
 }
+
+#[test]
+fn sized_for_fits_its_code() {
+    // addi x1, x1, 1 and lw x1, 0(x2)
+    let words =
+        |word: u32| -> Vec<u8> { [word; 64].iter().flat_map(|w| w.to_le_bytes()).collect() };
+    let (adds, loads) = (words(0x00108093), words(0x00012083));
+
+    let mut module = Module::sized_for(&adds).unwrap();
+    assert_eq!(module.max_code_size(), adds.len());
+    module.set_opt_level(OptLevel::O2);
+    module.set_resumable(true);
+    module.set_block_counters(true);
+    module.set_code(&adds).unwrap();
+    let mut expected = Module::new(adds.len()).unwrap();
+    expected.set_opt_level(OptLevel::O2);
+    expected.set_resumable(true);
+    expected.set_block_counters(true);
+    expected.set_code(&adds).unwrap();
+    assert_eq!(module.code(), expected.code());
+
+    // Loads need more room than the buffer was sized for, so it grows
+    let capacity = module.code_capacity();
+    module.set_code(&loads).unwrap();
+    assert!(module.code_capacity() > capacity);
+    Module::sized_for(&loads).unwrap().set_code(&loads).unwrap();
+}
+
+#[test]
+fn opt_level_applies_to_set_code() {
+    // add x1, x1, x0 is a copy of x1 to itself, which O1 removes
+    let code = 0x000080B3u32.to_le_bytes();
+    let mut module = Module::new(4).unwrap();
+    assert_eq!(module.opt_level(), OptLevel::O0);
+    module.set_code(&code).unwrap();
+    let unoptimized = module.code().len();
+
+    module.set_opt_level(OptLevel::O1);
+    assert_eq!(module.opt_level(), OptLevel::O1);
+    module.set_code(&code).unwrap();
+    assert!(module.code().len() < unoptimized);
+}
+
+#[test]
+fn compile_threads_do_not_change_code() {
+    // 8192 copies of addi x1, x1, 1, enough for two regions
+    let code: Vec<u8> = std::iter::repeat_n(0x00108093u32.to_le_bytes(), 8192)
+        .flatten()
+        .collect();
+    let mut module = Module::new(code.len()).unwrap();
+    assert!(module.compile_threads() >= 1);
+    module.set_compile_threads(0);
+    assert_eq!(module.compile_threads(), 1);
+    module.set_code(&code).unwrap();
+    let serial = module.code().to_vec();
+
+    module.set_compile_threads(2);
+    module.set_code(&code).unwrap();
+    assert_eq!(module.compile_threads(), 2);
+    assert_eq!(module.code(), serial);
+}
+
+#[test]
+fn source_map_covers_code() {
+    // addi x1, x0, 1; jalr x0, 0(x1)
+    let code = [0x00100093u32, 0x00008067]
+        .iter()
+        .flat_map(|word| word.to_le_bytes())
+        .collect::<Vec<_>>();
+    let mut module = Module::new(16).unwrap();
+    assert!(module.source_map().is_empty());
+    module.set_code(&code).unwrap();
+    let map = module.source_map();
+    assert_eq!(map.len(), 2);
+    let end = map.code(4).unwrap().end;
+    assert!(map.native(0).unwrap() < map.native(4).unwrap());
+    assert!(end <= module.code().len());
+    assert_eq!(map.guest(map.native(4).unwrap()), Some(4));
+}
+
+#[test]
+fn listing_shows_compiled_code() {
+    // addi x1, x0, 1
+    let code = 0x00100093u32.to_le_bytes();
+    let mut module = Module::new(4).unwrap();
+    assert_eq!(module.listing(), "");
+    module.set_code(&code).unwrap();
+    let listing = module.listing();
+    assert!(listing.starts_with("prologue:\n"));
+    assert!(listing.contains("\n0x00000000:  addi x1, x0, 1\n"));
+    assert!(listing.contains("\nend:\n"));
+}
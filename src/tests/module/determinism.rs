@@ -0,0 +1,80 @@
+use crate::{Instruction, Module, compiler::Compiler};
+
+/// A program exercising every instruction format
+fn program() -> Vec<Instruction> {
+    vec![
+        Instruction::Lui {
+            rd: 5,
+            imm: 0x12345,
+        },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 5,
+            imm: 0x678,
+        },
+        Instruction::Add {
+            rd: 6,
+            rs1: 5,
+            rs2: 5,
+        },
+        Instruction::Sw {
+            rs1: 2,
+            rs2: 6,
+            imm: -4,
+        },
+        Instruction::Lw {
+            rd: 7,
+            rs1: 2,
+            imm: -4,
+        },
+        Instruction::Beq {
+            rs1: 6,
+            rs2: 7,
+            imm: 8,
+        },
+        Instruction::Mul {
+            rd: 8,
+            rs1: 6,
+            rs2: 7,
+        },
+        Instruction::Jal { rd: 1, imm: -28 },
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 1,
+            imm: 0,
+        },
+    ]
+}
+
+#[test]
+fn identical_across_modules() {
+    let code = Instruction::encode_all(&program()).unwrap();
+    let mut first = Module::new(1024).unwrap();
+    let mut second = Module::new(4096).unwrap();
+    first.set_code(&code).unwrap();
+    second.set_code(&code).unwrap();
+    assert_eq!(first.code(), second.code());
+}
+
+#[test]
+fn identical_across_recompilation() {
+    let code = Instruction::encode_all(&program()).unwrap();
+    let mut module = Module::new(1024).unwrap();
+    module.set_code(&code).unwrap();
+    let expected = module.code().to_vec();
+    module
+        .set_code(&Instruction::encode_all(&[Instruction::Ecall]).unwrap())
+        .unwrap();
+    module.set_code(&code).unwrap();
+    assert_eq!(module.code(), expected.as_slice());
+}
+
+#[test]
+fn independent_of_buffer_contents() {
+    let program = program();
+    let mut clean = vec![0u8; 1024];
+    let mut dirty = vec![0xA5u8; 1024];
+    let size = Compiler::new().compile(&program, &mut clean);
+    assert_eq!(Compiler::new().compile(&program, &mut dirty), size);
+    assert_eq!(clean[..size], dirty[..size]);
+}
@@ -0,0 +1,137 @@
+use crate::Instruction;
+use crate::arm64::{self, Arrangement};
+use crate::neon;
+
+/// `vsetvli rd, rs1` with the `vtypei` setting
+fn vsetvli(rd: u8, rs1: u8, vtypei: u16) -> Instruction {
+    Instruction::Vsetvli { rd, rs1, vtypei }
+}
+
+/// `vadd.vi v1, v2, imm`, masked when `vm` is false
+fn vadd_vi(imm: i32, vm: bool) -> Instruction {
+    Instruction::VaddVi {
+        vd: 1,
+        vs2: 2,
+        imm,
+        vm,
+    }
+}
+
+#[test]
+fn arrangement_of_vtype() {
+    assert_eq!(neon::arrangement(0xC0), Some(Arrangement::B16));
+    assert_eq!(neon::arrangement(0x08), Some(Arrangement::H8));
+    assert_eq!(neon::arrangement(0xD0), Some(Arrangement::S4));
+    assert_eq!(neon::arrangement(0x18), Some(Arrangement::D2));
+    // m2, mf2, e128 and a reserved bit
+    for vtypei in [0xC9, 0xC7, 0xE0, 0x1C0] {
+        assert_eq!(neon::arrangement(vtypei), None);
+    }
+}
+
+#[test]
+fn plan_tracks_vtype_within_blocks() {
+    let code = [
+        vsetvli(5, 0, 0xC0),
+        Instruction::VaddVv {
+            vd: 1,
+            vs2: 2,
+            vs1: 3,
+            vm: true,
+        },
+        vadd_vi(1, false),
+        Instruction::Beq {
+            rs1: 5,
+            rs2: 0,
+            imm: 8,
+        },
+        // A new block starts with the type unknown
+        Instruction::VaddVx {
+            vd: 1,
+            vs2: 2,
+            rs1: 6,
+            vm: true,
+        },
+        // Keeping vl, taking it from a register, and grouping registers
+        vsetvli(0, 0, 0xC8),
+        vadd_vi(1, true),
+        vsetvli(5, 6, 0xC8),
+        vadd_vi(1, true),
+        vsetvli(5, 0, 0xC9),
+        vadd_vi(1, true),
+        vsetvli(5, 0, 0xC8),
+        vadd_vi(1, true),
+    ];
+    let mut expected = vec![None; code.len()];
+    expected[1] = Some(Arrangement::B16);
+    expected[12] = Some(Arrangement::H8);
+    assert_eq!(neon::plan(&code), expected);
+}
+
+#[test]
+fn lower_element_wise_adds() {
+    let vv = Instruction::VaddVv {
+        vd: 1,
+        vs2: 2,
+        vs1: 3,
+        vm: true,
+    };
+    assert_eq!(
+        neon::lower(&vv, Arrangement::S4, 20, 9),
+        Some(vec![
+            arm64::ldr_q(17, 20, 48),
+            arm64::ldr_q(16, 20, 32),
+            arm64::add_v(Arrangement::S4, 16, 16, 17),
+            arm64::str_q(16, 20, 16),
+        ])
+    );
+    let vx = Instruction::VaddVx {
+        vd: 31,
+        vs2: 31,
+        rs1: 6,
+        vm: true,
+    };
+    assert_eq!(
+        neon::lower(&vx, Arrangement::B16, 20, 9),
+        Some(vec![
+            arm64::dup_v(Arrangement::B16, 17, 9),
+            arm64::ldr_q(16, 20, 496),
+            arm64::add_v(Arrangement::B16, 16, 16, 17),
+            arm64::str_q(16, 20, 496),
+        ])
+    );
+    assert_eq!(
+        neon::lower(&vadd_vi(-1, true), Arrangement::H8, 20, 9),
+        Some(vec![
+            arm64::movn(9, 0, 0),
+            arm64::dup_v(Arrangement::H8, 17, 9),
+            arm64::ldr_q(16, 20, 32),
+            arm64::add_v(Arrangement::H8, 16, 16, 17),
+            arm64::str_q(16, 20, 16),
+        ])
+    );
+    assert!(neon::lower(&vv, Arrangement::D2, 20, 9).is_some());
+}
+
+#[test]
+fn lower_rejects_other_forms() {
+    let vx = Instruction::VaddVx {
+        vd: 1,
+        vs2: 2,
+        rs1: 6,
+        vm: true,
+    };
+    let load = Instruction::Vle {
+        vd: 1,
+        rs1: 6,
+        width: 32,
+        vm: true,
+    };
+    assert_eq!(neon::lower(&vx, Arrangement::D2, 20, 9), None);
+    assert_eq!(neon::lower(&vadd_vi(1, true), Arrangement::D2, 20, 9), None);
+    assert_eq!(
+        neon::lower(&vadd_vi(1, false), Arrangement::S4, 20, 9),
+        None
+    );
+    assert_eq!(neon::lower(&load, Arrangement::S4, 20, 9), None);
+}
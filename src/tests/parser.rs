@@ -0,0 +1,254 @@
+use crate::{EncodeError, Instruction, ParseError};
+
+/// Parse `text` and check it matches `expected`
+fn assert_parse(text: &str, expected: Instruction) {
+    assert_eq!(Instruction::parse(text), Ok(expected), "{}", text);
+}
+
+#[test]
+fn display_roundtrip() {
+    // Every decodable word survives decode -> display -> parse -> encode
+    let mut word: u32 = 0x12345678;
+    let mut checked = 0;
+    for _ in 0..200_000 {
+        word = word.wrapping_mul(1664525).wrapping_add(1013904223);
+        let instruction = Instruction::decode(word);
+        if let Instruction::Unsupported(_) = instruction {
+            continue;
+        }
+        let text = instruction.to_string();
+        assert!(text.starts_with(instruction.mnemonic()), "{}", text);
+        assert_eq!(
+            text.parse::<Instruction>(),
+            Ok(instruction.clone()),
+            "{}",
+            text
+        );
+        checked += 1;
+    }
+    assert!(checked > 5_000);
+}
+
+#[test]
+fn register() {
+    assert_parse(
+        "add x1, x2, x3",
+        Instruction::Add {
+            rd: 1,
+            rs1: 2,
+            rs2: 3,
+        },
+    );
+    assert_parse(
+        "mul a0, a1, t6",
+        Instruction::Mul {
+            rd: 10,
+            rs1: 11,
+            rs2: 31,
+        },
+    );
+}
+
+#[test]
+fn immediate() {
+    assert_parse(
+        "addi x1, x2, -5",
+        Instruction::Addi {
+            rd: 1,
+            rs1: 2,
+            imm: -5,
+        },
+    );
+    assert_parse(
+        "andi sp, zero, 0x7ff",
+        Instruction::Andi {
+            rd: 2,
+            rs1: 0,
+            imm: 2047,
+        },
+    );
+    assert_parse(
+        "slli x1, x1, 31",
+        Instruction::Slli {
+            rd: 1,
+            rs1: 1,
+            shamt: 31,
+        },
+    );
+    assert_parse(
+        "lui x5, 0x12345",
+        Instruction::Lui {
+            rd: 5,
+            imm: 0x12345,
+        },
+    );
+}
+
+#[test]
+fn memory() {
+    assert_parse(
+        "lw a0, 8(sp)",
+        Instruction::Lw {
+            rd: 10,
+            rs1: 2,
+            imm: 8,
+        },
+    );
+    assert_parse(
+        "sb x7, -1(x8)",
+        Instruction::Sb {
+            rs1: 8,
+            rs2: 7,
+            imm: -1,
+        },
+    );
+    assert_parse(
+        "jalr ra, (x5)",
+        Instruction::Jalr {
+            rd: 1,
+            rs1: 5,
+            imm: 0,
+        },
+    );
+}
+
+#[test]
+fn control() {
+    assert_parse(
+        "beq x1, x2, -8",
+        Instruction::Beq {
+            rs1: 1,
+            rs2: 2,
+            imm: -8,
+        },
+    );
+    assert_parse("jal x1, 2048", Instruction::Jal { rd: 1, imm: 2048 });
+    assert_parse("ecall", Instruction::Ecall);
+    assert_parse("  EBREAK  ", Instruction::Ebreak);
+    assert_parse("rdcycle a0", Instruction::Rdcycle { rd: 10 });
+    assert_parse("rdinstreth x3", Instruction::Rdinstreth { rd: 3 });
+}
+
+#[test]
+fn vector() {
+    assert_parse(
+        "vsetvli x1, x2, e32, m1, ta, ma",
+        Instruction::Vsetvli {
+            rd: 1,
+            rs1: 2,
+            vtypei: 0xD0,
+        },
+    );
+    assert_parse(
+        "vle32.v v1, (x2), v0.t",
+        Instruction::Vle {
+            vd: 1,
+            rs1: 2,
+            width: 32,
+            vm: false,
+        },
+    );
+    assert_parse(
+        "vadd.vi v1, v2, -16",
+        Instruction::VaddVi {
+            vd: 1,
+            vs2: 2,
+            imm: -16,
+            vm: true,
+        },
+    );
+}
+
+#[test]
+fn float() {
+    assert_parse(
+        "fadd.h f1, f2, f3, rtz",
+        Instruction::FaddH {
+            rd: 1,
+            rs1: 2,
+            rs2: 3,
+            rm: 1,
+        },
+    );
+    assert_parse(
+        "fcvt.h.w f1, a0",
+        Instruction::FcvtHW {
+            rd: 1,
+            rs1: 10,
+            rm: 7,
+        },
+    );
+    assert_parse(
+        "fcvt.w.h x1, f2, dyn",
+        Instruction::FcvtWH {
+            rd: 1,
+            rs1: 2,
+            rm: 7,
+        },
+    );
+}
+
+#[test]
+fn unsupported() {
+    assert_parse(
+        "unsupported: 0xffffffff",
+        Instruction::Unsupported(0xFFFFFFFF),
+    );
+}
+
+#[test]
+fn errors() {
+    assert_eq!(Instruction::parse("   "), Err(ParseError::Empty));
+    assert_eq!(
+        Instruction::parse("frob x1"),
+        Err(ParseError::UnknownMnemonic("frob".to_string()))
+    );
+    assert_eq!(
+        Instruction::parse("add x1, x2"),
+        Err(ParseError::OperandCount(3, 2))
+    );
+    assert_eq!(
+        Instruction::parse("add x1, x2, x32"),
+        Err(ParseError::InvalidRegister("x32".to_string()))
+    );
+    assert_eq!(
+        Instruction::parse("fadd.h x1, f2, f3"),
+        Err(ParseError::InvalidRegister("x1".to_string()))
+    );
+    assert_eq!(
+        Instruction::parse("addi x1, x2, five"),
+        Err(ParseError::InvalidImmediate("five".to_string()))
+    );
+    assert_eq!(
+        Instruction::parse("lw x1, 8[x2]"),
+        Err(ParseError::InvalidOperand("8[x2]".to_string()))
+    );
+}
+
+#[test]
+fn out_of_range() {
+    assert_eq!(
+        Instruction::parse("addi x1, x2, 2048"),
+        Err(ParseError::EncodeError(EncodeError::InvalidImmediate(
+            "imm", 2048
+        )))
+    );
+    assert_eq!(
+        Instruction::parse("beq x1, x2, 3"),
+        Err(ParseError::EncodeError(EncodeError::InvalidImmediate(
+            "imm", 3
+        )))
+    );
+}
+
+#[test]
+fn display() {
+    assert_eq!(
+        ParseError::OperandCount(3, 2).to_string(),
+        "Expected 3 operands, found 2"
+    );
+    assert_eq!(
+        ParseError::InvalidRegister("x32".to_string()).to_string(),
+        "Invalid register: x32"
+    );
+}
@@ -0,0 +1,132 @@
+use crate::{
+    Instruction,
+    pattern::{Field, Operand, Pattern, Template},
+};
+
+/// Parse a sequence of instructions
+fn program(lines: &[&str]) -> Vec<Instruction> {
+    lines.iter().map(|line| line.parse().unwrap()).collect()
+}
+
+/// `lui a, _ ; addi a, a, _`
+fn lui_addi() -> Pattern {
+    Pattern::new()
+        .then(Template::new("lui").with(Field::Rd, Operand::Var("a")))
+        .then(
+            Template::new("addi")
+                .with(Field::Rd, Operand::Var("a"))
+                .with(Field::Rs1, Operand::Var("a")),
+        )
+}
+
+#[test]
+fn bindings() {
+    let code = program(&["lui x5, 0x12345", "addi x5, x5, 1656"]);
+    let found = lui_addi().match_at(&code, 0).unwrap();
+    assert_eq!((found.start, found.len), (0, 2));
+    assert_eq!(found.get("a"), Some(5));
+    assert_eq!(found.get("b"), None);
+}
+
+#[test]
+fn variable_mismatch() {
+    let code = program(&["lui x5, 0x12345", "addi x6, x5, 1"]);
+    assert_eq!(lui_addi().match_at(&code, 0), None);
+}
+
+#[test]
+fn value_and_any() {
+    let pattern = Pattern::new().then(
+        Template::new("addi")
+            .with(Field::Rd, Operand::Any)
+            .with(Field::Imm, Operand::Value(0)),
+    );
+    let code = program(&["addi x1, x2, 0", "addi x1, x2, 1"]);
+    assert!(pattern.match_at(&code, 0).is_some());
+    assert!(pattern.match_at(&code, 1).is_none());
+}
+
+#[test]
+fn missing_field() {
+    // ecall has no rd, so any constraint on it fails
+    let pattern = Pattern::new().then(Template::any().with(Field::Rd, Operand::Any));
+    assert!(pattern.match_at(&[Instruction::Ecall], 0).is_none());
+    assert!(
+        Pattern::new()
+            .then(Template::any())
+            .match_at(&[Instruction::Ecall], 0)
+            .is_some()
+    );
+}
+
+#[test]
+fn find() {
+    let code = program(&[
+        "lui x5, 0x1",
+        "addi x5, x5, 1",
+        "ecall",
+        "lui x6, 0x2",
+        "addi x6, x6, 2",
+        "lui x7, 0x3",
+    ]);
+    let starts: Vec<usize> = lui_addi()
+        .find(&code)
+        .iter()
+        .map(|found| found.start)
+        .collect();
+    assert_eq!(starts, vec![0, 3]);
+}
+
+#[test]
+fn find_non_overlapping() {
+    let pattern = Pattern::new()
+        .then(Template::new("ecall"))
+        .then(Template::new("ecall"));
+    let code = vec![Instruction::Ecall; 5];
+    assert_eq!(pattern.find(&code).len(), 2);
+}
+
+#[test]
+fn empty_pattern() {
+    assert!(Pattern::new().is_empty());
+    assert!(Pattern::new().find(&[Instruction::Ecall]).is_empty());
+}
+
+#[test]
+fn out_of_bounds() {
+    let code = program(&["lui x5, 0x1"]);
+    assert_eq!(lui_addi().match_at(&code, 0), None);
+    assert_eq!(lui_addi().match_at(&code, usize::MAX), None);
+}
+
+#[test]
+fn rewrite() {
+    // Fold `addi a, a, 0` after a LUI away
+    let pattern = Pattern::new()
+        .then(Template::new("lui").with(Field::Rd, Operand::Var("a")))
+        .then(
+            Template::new("addi")
+                .with(Field::Rd, Operand::Var("a"))
+                .with(Field::Rs1, Operand::Var("a"))
+                .with(Field::Imm, Operand::Value(0)),
+        );
+    let code = program(&["ecall", "lui x5, 0x1", "addi x5, x5, 0", "ebreak"]);
+    let rewritten = pattern.rewrite(&code, |_, matched| Some(vec![matched[0].clone()]));
+    assert_eq!(rewritten, program(&["ecall", "lui x5, 0x1", "ebreak"]));
+}
+
+#[test]
+fn rewrite_keep() {
+    let code = program(&["lui x5, 0x1", "addi x5, x5, 1"]);
+    assert_eq!(lui_addi().rewrite(&code, |_, _| None), code);
+}
+
+#[test]
+fn mnemonic() {
+    assert_eq!(Instruction::Ecall.mnemonic(), "ecall");
+    assert_eq!(
+        Instruction::parse("vle16.v v1, (x2)").unwrap().mnemonic(),
+        "vle16.v"
+    );
+    assert_eq!(Instruction::Unsupported(0).mnemonic(), "unsupported");
+}
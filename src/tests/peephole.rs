@@ -0,0 +1,149 @@
+use crate::Instruction;
+use crate::peephole::{self, simplify};
+
+/// `addi rd, rs1, 0`
+fn copy(rd: u8, rs1: u8) -> Instruction {
+    Instruction::Addi { rd, rs1, imm: 0 }
+}
+
+#[test]
+fn writes_to_x0_become_nop() {
+    for instruction in [
+        Instruction::Add {
+            rd: 0,
+            rs1: 1,
+            rs2: 2,
+        },
+        Instruction::Sltiu {
+            rd: 0,
+            rs1: 1,
+            imm: 5,
+        },
+        Instruction::Lui { rd: 0, imm: 1 },
+        Instruction::Auipc { rd: 0, imm: 1 },
+        Instruction::Mulh {
+            rd: 0,
+            rs1: 1,
+            rs2: 2,
+        },
+        Instruction::Div {
+            rd: 0,
+            rs1: 1,
+            rs2: 0,
+        },
+    ] {
+        assert!(simplify(&instruction).nop(), "{}", instruction);
+    }
+}
+
+#[test]
+fn side_effects_kept() {
+    // Loads can fault and jumps still jump
+    for instruction in [
+        Instruction::Lw {
+            rd: 0,
+            rs1: 1,
+            imm: 0,
+        },
+        Instruction::Jal { rd: 0, imm: 8 },
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 1,
+            imm: 0,
+        },
+        Instruction::Rdcycle { rd: 0 },
+    ] {
+        assert_eq!(simplify(&instruction), instruction);
+    }
+}
+
+#[test]
+fn register_copies_become_addi() {
+    assert_eq!(
+        simplify(&Instruction::Add {
+            rd: 5,
+            rs1: 6,
+            rs2: 0
+        }),
+        copy(5, 6)
+    );
+    assert_eq!(
+        simplify(&Instruction::Xor {
+            rd: 5,
+            rs1: 0,
+            rs2: 6
+        }),
+        copy(5, 6)
+    );
+    assert_eq!(
+        simplify(&Instruction::Sra {
+            rd: 5,
+            rs1: 6,
+            rs2: 0
+        }),
+        copy(5, 6)
+    );
+    assert_eq!(
+        simplify(&Instruction::Ori {
+            rd: 5,
+            rs1: 6,
+            imm: 0
+        }),
+        copy(5, 6)
+    );
+    assert_eq!(
+        simplify(&Instruction::Srli {
+            rd: 5,
+            rs1: 6,
+            shamt: 0
+        }),
+        copy(5, 6)
+    );
+    // Both sources x0 loads zero
+    assert_eq!(
+        simplify(&Instruction::Or {
+            rd: 5,
+            rs1: 0,
+            rs2: 0
+        }),
+        copy(5, 0)
+    );
+}
+
+#[test]
+fn self_copies_become_nop() {
+    assert!(simplify(&copy(5, 5)).nop());
+    assert!(
+        simplify(&Instruction::Sub {
+            rd: 7,
+            rs1: 7,
+            rs2: 0
+        })
+        .nop()
+    );
+}
+
+#[test]
+fn other_instructions_unchanged() {
+    // Negation is not a copy
+    let negate = Instruction::Sub {
+        rd: 5,
+        rs1: 0,
+        rs2: 6,
+    };
+    let code = [
+        negate.clone(),
+        Instruction::Addi {
+            rd: 5,
+            rs1: 5,
+            imm: 1,
+        },
+        Instruction::Beq {
+            rs1: 5,
+            rs2: 0,
+            imm: -8,
+        },
+    ];
+    assert_eq!(peephole::optimize(&code), code);
+    assert_eq!(simplify(&negate), negate);
+}
@@ -0,0 +1,111 @@
+use crate::Instruction;
+use crate::regalloc::{HOST_REGISTERS, RegisterMap};
+
+#[test]
+fn most_used_registers_mapped() {
+    let program = [
+        Instruction::Add {
+            rd: 20,
+            rs1: 20,
+            rs2: 31,
+        },
+        Instruction::Addi {
+            rd: 20,
+            rs1: 31,
+            imm: 1,
+        },
+    ];
+    let map = RegisterMap::allocate(&program);
+    assert_eq!(map.host(20), Some(21));
+    assert_eq!(map.host(31), Some(22));
+    // Unused registers fill the remaining slots in register order
+    assert_eq!(map.host(1), Some(23));
+    assert_eq!(map.host(6), Some(28));
+    assert_eq!(map.host(7), None);
+}
+
+#[test]
+fn x0_never_mapped() {
+    let program = [Instruction::Add {
+        rd: 0,
+        rs1: 0,
+        rs2: 0,
+    }];
+    let map = RegisterMap::allocate(&program);
+    assert_eq!(map.host(0), None);
+    assert_eq!(map.host(1), Some(21));
+}
+
+#[test]
+fn every_host_register_assigned() {
+    let map = RegisterMap::allocate(&[]);
+    let hosts: Vec<u8> = map.mapped().map(|(_, host)| host).collect();
+    assert_eq!(hosts, HOST_REGISTERS);
+    let guests: Vec<u8> = map.mapped().map(|(reg, _)| reg).collect();
+    assert_eq!(guests, [1, 2, 3, 4, 5, 6, 7, 8]);
+    assert_eq!(map.host(32), None);
+}
+
+#[test]
+fn float_registers_not_counted() {
+    let program = [
+        Instruction::FaddH {
+            rd: 30,
+            rs1: 30,
+            rs2: 30,
+            rm: 0,
+        },
+        Instruction::Flh {
+            rd: 30,
+            rs1: 2,
+            imm: 0,
+        },
+    ];
+    let map = RegisterMap::allocate(&program);
+    assert_eq!(map.host(30), None);
+    assert_eq!(map.host(2), Some(21));
+}
+
+#[test]
+fn integer_operands_of_float_and_vector_counted() {
+    let program = [
+        Instruction::FcvtWH {
+            rd: 20,
+            rs1: 9,
+            rm: 0,
+        },
+        Instruction::FcvtHW {
+            rd: 20,
+            rs1: 9,
+            rm: 0,
+        },
+        Instruction::Vsetvli {
+            rd: 20,
+            rs1: 0,
+            vtypei: 0,
+        },
+    ];
+    let map = RegisterMap::allocate(&program);
+    assert_eq!(map.host(20), Some(21));
+    assert_eq!(map.host(9), Some(22));
+}
+
+#[test]
+fn deterministic() {
+    let program = [
+        Instruction::Sw {
+            rs1: 2,
+            rs2: 15,
+            imm: 0,
+        },
+        Instruction::Lw {
+            rd: 15,
+            rs1: 2,
+            imm: 4,
+        },
+    ];
+    assert_eq!(
+        RegisterMap::allocate(&program),
+        RegisterMap::allocate(&program)
+    );
+}
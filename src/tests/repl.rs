@@ -0,0 +1,295 @@
+use crate::{Instance, Instruction, Memory, MemoryError, PageStore, Repl, ReplError};
+
+/// Create a REPL with the given program loaded at address 0
+fn repl(store: &PageStore, program: &[Instruction]) -> Repl {
+    let memory = Memory::new(store, 16, 4);
+    let mut instance = Instance::new(memory);
+    let bytes: Vec<u8> = program
+        .iter()
+        .flat_map(|instr| instr.encode().unwrap().to_le_bytes())
+        .collect();
+    assert_eq!(instance.memory_mut().write(0, &bytes), Ok(()));
+    Repl::new(instance)
+}
+
+#[test]
+fn blank_line() {
+    let store = PageStore::new(16);
+    let mut repl = repl(&store, &[]);
+    assert_eq!(repl.execute("   "), Ok(String::new()));
+}
+
+#[test]
+fn unknown_command() {
+    let store = PageStore::new(16);
+    let mut repl = repl(&store, &[]);
+    assert_eq!(
+        repl.execute("frobnicate"),
+        Err(ReplError::UnknownCommand("frobnicate".to_string()))
+    );
+}
+
+#[test]
+fn help() {
+    let store = PageStore::new(16);
+    let mut repl = repl(&store, &[]);
+    let output = repl.execute("help").unwrap();
+    assert!(output.contains("continue [limit]"));
+}
+
+#[test]
+fn register_read_and_write() {
+    let store = PageStore::new(16);
+    let mut repl = repl(&store, &[]);
+    assert_eq!(repl.execute("reg a0 0x2a").unwrap(), "x10 = 0x0000002a");
+    assert_eq!(repl.execute("reg x10").unwrap(), "x10 = 0x0000002a");
+    assert_eq!(repl.execute("reg fp 7").unwrap(), "x8 = 0x00000007");
+    assert_eq!(repl.execute("reg zero 1").unwrap(), "x0 = 0x00000000");
+    assert_eq!(repl.execute("reg pc 0x100").unwrap(), "pc = 0x00000100");
+    assert_eq!(repl.instance().pc(), 0x100);
+}
+
+#[test]
+fn register_errors() {
+    let store = PageStore::new(16);
+    let mut repl = repl(&store, &[]);
+    assert_eq!(
+        repl.execute("reg"),
+        Err(ReplError::MissingArgument("register"))
+    );
+    assert_eq!(
+        repl.execute("reg x32"),
+        Err(ReplError::InvalidArgument("x32".to_string()))
+    );
+    assert_eq!(
+        repl.execute("reg a0 0xZZ"),
+        Err(ReplError::InvalidArgument("0xZZ".to_string()))
+    );
+}
+
+#[test]
+fn registers() {
+    let store = PageStore::new(16);
+    let mut repl = repl(&store, &[]);
+    repl.execute("reg x31 0xdeadbeef").unwrap();
+    let output = repl.execute("regs").unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 9);
+    assert!(lines[7].ends_with("x31 = 0xdeadbeef"));
+    assert_eq!(lines[8], "  pc = 0x00000000");
+}
+
+#[test]
+fn write_and_dump_memory() {
+    let store = PageStore::new(16);
+    let mut repl = repl(&store, &[]);
+    assert_eq!(
+        repl.execute("write 0x2000 1 0x02 255").unwrap(),
+        "wrote 3 bytes at 0x00002000"
+    );
+    assert_eq!(
+        repl.execute("mem 0x2000 4").unwrap(),
+        "0x00002000: 01 02 ff 00"
+    );
+    let output = repl.execute("mem 0x2000 20").unwrap();
+    assert_eq!(output.lines().nth(1), Some("0x00002010: 00 00 00 00"));
+
+    let output = repl.execute("mem 0 0x10000").unwrap();
+    assert_eq!(output.lines().count(), 0x1000);
+    assert_eq!(
+        repl.execute("mem 0 0x10001"),
+        Err(ReplError::InvalidArgument("0x10001".to_string()))
+    );
+}
+
+#[test]
+fn regions() {
+    let store = PageStore::new(16);
+    let mut repl = repl(&store, &[]);
+    assert_eq!(repl.execute("regions").unwrap(), "");
+    repl.execute("write 0x8000 1").unwrap();
+    assert_eq!(
+        repl.execute("regions").unwrap(),
+        "0x00008000-0x0000bfff rwx private"
+    );
+}
+
+#[test]
+fn write_errors() {
+    let store = PageStore::new(16);
+    let mut repl = repl(&store, &[]);
+    assert_eq!(
+        repl.execute("write 0"),
+        Err(ReplError::MissingArgument("bytes"))
+    );
+    assert_eq!(
+        repl.execute("write 0 256"),
+        Err(ReplError::InvalidArgument("256".to_string()))
+    );
+}
+
+#[test]
+fn write_memory_error() {
+    let store = PageStore::new(16);
+    let memory = Memory::new(&store, 1, 4);
+    let mut repl = Repl::new(Instance::new(memory));
+    repl.execute("write 0 1").unwrap();
+    // The single-page limit has been reached
+    assert_eq!(
+        repl.execute("write 0x10000 1"),
+        Err(ReplError::MemoryError(MemoryError::PageLimit))
+    );
+}
+
+#[test]
+fn disassemble_marks_pc() {
+    let store = PageStore::new(16);
+    let mut repl = repl(
+        &store,
+        &[
+            Instruction::Addi {
+                rd: 1,
+                rs1: 0,
+                imm: 1,
+            },
+            Instruction::Jal { rd: 0, imm: -4 },
+        ],
+    );
+    repl.execute("reg pc 4").unwrap();
+    assert_eq!(
+        repl.execute("dis 0 2").unwrap(),
+        "   0x00000000:  addi x1, x0, 1\n=> 0x00000004:  jal x0, 0x0"
+    );
+}
+
+#[test]
+fn step() {
+    let store = PageStore::new(16);
+    let mut repl = repl(
+        &store,
+        &[
+            Instruction::Addi {
+                rd: 1,
+                rs1: 0,
+                imm: 1,
+            },
+            Instruction::Addi {
+                rd: 1,
+                rs1: 1,
+                imm: 1,
+            },
+            Instruction::Ecall,
+        ],
+    );
+    assert_eq!(
+        repl.execute("step").unwrap(),
+        "stepped at 0x00000004:  addi x1, x1, 1"
+    );
+    assert_eq!(
+        repl.execute("step 5").unwrap(),
+        "trap: ecall at 0x00000008:  ecall"
+    );
+    assert_eq!(repl.instance().read_register(1), 2);
+}
+
+#[test]
+fn breakpoints() {
+    let store = PageStore::new(16);
+    let mut repl = repl(&store, &[]);
+    assert_eq!(
+        repl.execute("break 0x10").unwrap(),
+        "breakpoint at 0x00000010"
+    );
+    repl.execute("break 8").unwrap();
+    assert_eq!(repl.execute("breaks").unwrap(), "0x00000008\n0x00000010");
+    assert_eq!(
+        repl.execute("delete 8").unwrap(),
+        "deleted breakpoint at 0x00000008"
+    );
+    assert_eq!(
+        repl.execute("delete 8"),
+        Err(ReplError::InvalidArgument("8".to_string()))
+    );
+    assert_eq!(repl.execute("breaks").unwrap(), "0x00000010");
+}
+
+#[test]
+fn continue_to_breakpoint() {
+    let store = PageStore::new(16);
+    let mut repl = repl(
+        &store,
+        &[
+            Instruction::Addi {
+                rd: 1,
+                rs1: 1,
+                imm: 1,
+            },
+            Instruction::Jal { rd: 0, imm: -4 },
+        ],
+    );
+    repl.execute("break 4").unwrap();
+    assert_eq!(
+        repl.execute("continue").unwrap(),
+        "breakpoint at 0x00000004:  jal x0, 0x0"
+    );
+    // Continuing from a breakpoint executes past it before stopping again
+    repl.execute("continue").unwrap();
+    assert_eq!(repl.instance().read_register(1), 2);
+}
+
+#[test]
+fn continue_to_trap() {
+    let store = PageStore::new(16);
+    let mut repl = repl(&store, &[Instruction::Ebreak]);
+    assert_eq!(
+        repl.execute("continue").unwrap(),
+        "trap: ebreak at 0x00000000:  ebreak"
+    );
+}
+
+#[test]
+fn continue_step_limit() {
+    let store = PageStore::new(16);
+    let mut repl = repl(&store, &[Instruction::Jal { rd: 0, imm: 0 }]);
+    assert_eq!(
+        repl.execute("continue 10").unwrap(),
+        "step limit reached at 0x00000000:  jal x0, 0x0"
+    );
+}
+
+#[test]
+fn call_detached() {
+    let store = PageStore::new(16);
+    let mut repl = repl(&store, &[]);
+    assert!(matches!(
+        repl.execute("call 0"),
+        Err(ReplError::CallFailed(_))
+    ));
+}
+
+#[test]
+#[cfg(not(target_arch = "aarch64"))]
+fn call_needs_aarch64() {
+    let store = PageStore::new(16);
+    let mut repl = repl(&store, &[]);
+    assert_eq!(
+        repl.execute("call 0"),
+        Err(ReplError::CallFailed("compiled code only runs on aarch64"))
+    );
+}
+
+#[test]
+fn display() {
+    assert_eq!(
+        ReplError::UnknownCommand("x".to_string()).to_string(),
+        "Unknown command: x"
+    );
+    assert_eq!(
+        ReplError::MissingArgument("address").to_string(),
+        "Missing argument: address"
+    );
+    assert_eq!(
+        ReplError::MemoryError(MemoryError::PageLimit).to_string(),
+        "Memory error: Instance page limit reached"
+    );
+}
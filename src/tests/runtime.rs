@@ -1,9 +1,14 @@
+#[cfg(target_arch = "aarch64")]
+use crate::{
+    CodeCache, ConstantStrategy, Instruction, MmioDevice, OptLevel, Permissions, Tiered,
+    memory::PAGE_SIZE,
+};
 use crate::{Instance, Memory, Module, PageStore};
 
 #[test]
 fn call_function_without_module() {
-    let mut page_store = PageStore::new(256); // 256 pages (1MB with 4KB pages)
-    let memory = Memory::new(&mut page_store, 256, 16);
+    let page_store = PageStore::new(256); // 256 pages (1MB with 4KB pages)
+    let memory = Memory::new(&page_store, 256, 16);
     let mut instance = Instance::new(memory);
 
     let result = unsafe { instance.call_function(0) };
@@ -13,12 +18,12 @@ fn call_function_without_module() {
 
 #[test]
 fn call_function_with_empty_module() {
-    let mut page_store = PageStore::new(256); // 256 pages (1MB with 4KB pages)
-    let memory = Memory::new(&mut page_store, 256, 16);
+    let page_store = PageStore::new(256); // 256 pages (1MB with 4KB pages)
+    let memory = Memory::new(&page_store, 256, 16);
     let mut instance = Instance::new(memory);
     let mut module = Module::new(1024).unwrap();
 
-    instance.attach(&mut module);
+    instance.attach(&mut module).unwrap();
 
     let result = unsafe { instance.call_function(0) };
 
@@ -27,26 +32,1251 @@ fn call_function_with_empty_module() {
     instance.detach();
 }
 
+#[test]
+fn resume_without_resume_entry() {
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
+    let mut instance = Instance::new(memory);
+    let mut module = Module::new(4).unwrap();
+    module.set_code(&0x00100093u32.to_le_bytes()).unwrap();
+
+    instance.attach(&mut module).unwrap();
+
+    let result = unsafe { instance.resume() };
+
+    assert_eq!(result, Err("Module is not resumable"));
+
+    instance.detach();
+}
+
+#[test]
+fn call_function_with_invalid_index() {
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
+    let mut instance = Instance::new(memory);
+    let mut module = Module::new(8).unwrap();
+    module.set_code(&0x00100093u32.to_le_bytes()).unwrap();
+
+    instance.attach(&mut module).unwrap();
+    assert_eq!(
+        unsafe { instance.call_function(1) },
+        Err("Invalid function index")
+    );
+    instance.detach();
+
+    module.set_functions(&[0]);
+    module.set_code(&0x00100093u32.to_le_bytes()).unwrap();
+    instance.attach(&mut module).unwrap();
+    assert_eq!(
+        unsafe { instance.call_function(1) },
+        Err("Invalid function index")
+    );
+    instance.detach();
+}
+
+/// Compile a program, run it from its first instruction and return the result
+#[cfg(target_arch = "aarch64")]
+fn run(instance: &mut Instance, program: &[Instruction]) -> Result<(), &'static str> {
+    let code = Instruction::encode_all(program).unwrap();
+    let mut module = Module::new(code.len()).unwrap();
+    module.set_code(&code).unwrap();
+    instance.attach(&mut module).unwrap();
+    let result = unsafe { instance.call_function(0) };
+    instance.detach();
+    result
+}
+
 #[cfg(target_arch = "aarch64")]
 #[test]
 fn call_function_with_ret_instruction() {
-    let mut page_store = PageStore::new(256); // 256 pages (1MB with 4KB pages)
-    let memory = Memory::new(&mut page_store, 256, 16);
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
     let mut instance = Instance::new(memory);
-    let mut module = Module::new(1024).unwrap();
+    instance.write_register(1, 0x1000);
+
+    let result = run(
+        &mut instance,
+        &[
+            Instruction::Addi {
+                rd: 0,
+                rs1: 0,
+                imm: 0,
+            },
+            Instruction::Jalr {
+                rd: 0,
+                rs1: 1,
+                imm: 0,
+            },
+        ],
+    );
+
+    assert_eq!(result, Ok(()));
+    assert_eq!(instance.pc(), 0x1000);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_runs_off_end() {
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
+    let mut instance = Instance::new(memory);
+
+    let result = run(
+        &mut instance,
+        &[Instruction::Lui {
+            rd: 5,
+            imm: 0xABCDE,
+        }],
+    );
+
+    assert_eq!(result, Ok(()));
+    assert_eq!(instance.pc(), 4);
+    assert_eq!(instance.read_register(5), 0xABCDE000);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_arithmetic() {
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
+    let mut instance = Instance::new(memory);
+    instance.write_register(1, 7);
+    instance.write_register(2, 0xFFFF_FFF0);
+
+    let result = run(
+        &mut instance,
+        &[
+            Instruction::Add {
+                rd: 3,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Sub {
+                rd: 4,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Slt {
+                rd: 5,
+                rs1: 2,
+                rs2: 1,
+            },
+            Instruction::Sltu {
+                rd: 6,
+                rs1: 2,
+                rs2: 1,
+            },
+            Instruction::Sra {
+                rd: 7,
+                rs1: 2,
+                rs2: 1,
+            },
+            Instruction::Srli {
+                rd: 8,
+                rs1: 2,
+                shamt: 4,
+            },
+            Instruction::Xori {
+                rd: 9,
+                rs1: 1,
+                imm: -1,
+            },
+            Instruction::Sltiu {
+                rd: 10,
+                rs1: 1,
+                imm: -1,
+            },
+            Instruction::Auipc { rd: 11, imm: 1 },
+            Instruction::Addi {
+                rd: 0,
+                rs1: 1,
+                imm: 1,
+            },
+        ],
+    );
 
-    // Create a simple RISC-V program (doesn't matter what it is, will compile to RET)
-    let riscv_code = vec![
-        0x00, 0x00, 0x00, 0x00, // NOP (addi x0, x0, 0)
+    assert_eq!(result, Ok(()));
+    assert_eq!(instance.read_register(3), 0xFFFF_FFF7);
+    assert_eq!(instance.read_register(4), 0x17);
+    assert_eq!(instance.read_register(5), 1);
+    assert_eq!(instance.read_register(6), 0);
+    assert_eq!(instance.read_register(7), 0xFFFF_FFFF);
+    assert_eq!(instance.read_register(8), 0x0FFF_FFFF);
+    assert_eq!(instance.read_register(9), !7);
+    assert_eq!(instance.read_register(10), 1);
+    assert_eq!(instance.read_register(11), 0x1020);
+    assert_eq!(instance.read_register(0), 0);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_loop() {
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
+    let mut instance = Instance::new(memory);
+    instance.write_register(1, 0x2000);
+
+    // Sum 10 + 9 + ... + 1 and return
+    let result = run(
+        &mut instance,
+        &[
+            Instruction::Addi {
+                rd: 5,
+                rs1: 0,
+                imm: 10,
+            },
+            Instruction::Addi {
+                rd: 6,
+                rs1: 0,
+                imm: 0,
+            },
+            Instruction::Add {
+                rd: 6,
+                rs1: 6,
+                rs2: 5,
+            },
+            Instruction::Addi {
+                rd: 5,
+                rs1: 5,
+                imm: -1,
+            },
+            Instruction::Bne {
+                rs1: 5,
+                rs2: 0,
+                imm: -8,
+            },
+            Instruction::Jal { rd: 7, imm: 8 },
+            Instruction::Ebreak,
+            Instruction::Jalr {
+                rd: 0,
+                rs1: 1,
+                imm: 0,
+            },
+        ],
+    );
+
+    assert_eq!(result, Ok(()));
+    assert_eq!(instance.pc(), 0x2000);
+    assert_eq!(instance.read_register(6), 55);
+    assert_eq!(instance.read_register(7), 24);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_indirect_jumps() {
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
+    let mut instance = Instance::new(memory);
+    instance.write_register(1, 0x3000);
+
+    // Call a subroutine with x6 as the link register, return through it
+    // inside the module, then return to the caller outside it
+    let result = run(
+        &mut instance,
+        &[
+            Instruction::Addi {
+                rd: 5,
+                rs1: 0,
+                imm: 3,
+            },
+            Instruction::Jal { rd: 6, imm: 12 },
+            Instruction::Add {
+                rd: 7,
+                rs1: 5,
+                rs2: 5,
+            },
+            Instruction::Jalr {
+                rd: 0,
+                rs1: 1,
+                imm: 0,
+            },
+            Instruction::Addi {
+                rd: 5,
+                rs1: 5,
+                imm: 1,
+            },
+            Instruction::Jalr {
+                rd: 0,
+                rs1: 6,
+                imm: 0,
+            },
+        ],
+    );
+
+    assert_eq!(result, Ok(()));
+    assert_eq!(instance.pc(), 0x3000);
+    assert_eq!(instance.read_register(5), 4);
+    assert_eq!(instance.read_register(6), 8);
+    assert_eq!(instance.read_register(7), 8);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_meters_gas() {
+    // One instruction before the loop, two per iteration and the return
+    let program = [
+        Instruction::Addi {
+            rd: 5,
+            rs1: 0,
+            imm: 3,
+        },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 5,
+            imm: -1,
+        },
+        Instruction::Bne {
+            rs1: 5,
+            rs2: 0,
+            imm: -4,
+        },
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 1,
+            imm: 0,
+        },
     ];
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
+    let mut instance = Instance::new(memory);
+    instance.write_register(1, 0x1000);
 
-    module.set_code(&riscv_code).unwrap();
-    instance.attach(&mut module);
+    instance.set_gas(20);
+    assert_eq!(run(&mut instance, &program), Ok(()));
+    assert_eq!(instance.gas(), 12);
 
-    // This should execute the RET instruction and return without crashing
-    let result = unsafe { instance.call_function(0) };
+    // The third iteration is refused before it runs
+    instance.set_gas(6);
+    assert_eq!(run(&mut instance, &program), Err("Out of gas"));
+    assert_eq!(instance.pc(), 4);
+    assert_eq!(instance.read_register(5), 1);
+    assert_eq!(instance.gas(), 1);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_budgets_share_module() {
+    let code = countdown();
+    let mut module = Module::new(code.len()).unwrap();
+    module.set_code(&code).unwrap();
+    let compiled = module.code().to_vec();
+    let page_store = PageStore::new(256);
+
+    // Budgets set between calls, on separate instances and on the same one
+    for (gas, result, left) in [
+        (100, Ok(()), 89),
+        (11, Ok(()), 0),
+        (6, Err("Out of gas"), 2),
+    ] {
+        let mut instance = Instance::new(Memory::new(&page_store, 16, 16));
+        instance.set_gas(gas);
+        instance.attach(&mut module).unwrap();
+        assert_eq!(unsafe { instance.call_function(0) }, result);
+        assert_eq!(instance.gas(), left);
+
+        instance.set_pc(0);
+        instance.set_gas(gas);
+        assert_eq!(unsafe { instance.call_function(0) }, result);
+        assert_eq!(instance.gas(), left);
+        instance.detach();
+    }
+    assert_eq!(module.code(), compiled);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_fused_pairs() {
+    let program = [
+        Instruction::Lui {
+            rd: 5,
+            imm: 0x12345,
+        },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 5,
+            imm: -1,
+        },
+        Instruction::Slli {
+            rd: 6,
+            rs1: 5,
+            shamt: 20,
+        },
+        Instruction::Srli {
+            rd: 6,
+            rs1: 6,
+            shamt: 20,
+        },
+        // Call into the middle of the block at 28
+        Instruction::Auipc { rd: 1, imm: 0 },
+        Instruction::Jalr {
+            rd: 1,
+            rs1: 1,
+            imm: 16,
+        },
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 7,
+            imm: 0,
+        },
+        Instruction::Addi {
+            rd: 9,
+            rs1: 0,
+            imm: 99,
+        },
+        Instruction::Sltu {
+            rd: 8,
+            rs1: 6,
+            rs2: 5,
+        },
+        Instruction::Bne {
+            rs1: 8,
+            rs2: 0,
+            imm: -12,
+        },
+    ];
+    let code = Instruction::encode_all(&program).unwrap();
+
+    // Both levels run the same instructions with the same results and gas
+    for opt_level in [OptLevel::O0, OptLevel::O1] {
+        let page_store = PageStore::new(256);
+        let memory = Memory::new(&page_store, 256, 16);
+        let mut instance = Instance::new(memory);
+        instance.write_register(7, 0x3000);
+        let mut module = Module::new(code.len()).unwrap();
+        module.set_opt_level(opt_level);
+        module.set_code(&code).unwrap();
+        instance.attach(&mut module).unwrap();
+        let result = unsafe { instance.call_function(0) };
+        instance.detach();
+
+        assert_eq!(result, Ok(()));
+        assert_eq!(instance.pc(), 0x3000);
+        assert_eq!(instance.read_register(1), 24);
+        assert_eq!(instance.read_register(5), 0x12344FFF);
+        assert_eq!(instance.read_register(6), 0xFFF);
+        assert_eq!(instance.read_register(8), 1);
+        assert_eq!(instance.read_register(9), 0);
+        assert_eq!(u64::MAX - instance.gas(), 9);
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_ecall_handler() {
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
+    let mut instance = Instance::new(memory);
+    instance.set_gas(1000);
+
+    // Adds a0 and a1 into a0 for a7 = 64 and charges 10 gas per call; stops
+    // on any other number
+    instance.set_ecall_handler(Box::new(|instance| {
+        if instance.read_register(17) != 64 {
+            return false;
+        }
+        let sum = instance.read_register(10) + instance.read_register(11);
+        instance.write_register(10, sum);
+        instance.set_gas(instance.gas() - 10);
+        true
+    }));
+
+    let ecall = |number| {
+        [
+            Instruction::Addi {
+                rd: 17,
+                rs1: 0,
+                imm: number,
+            },
+            Instruction::Ecall,
+        ]
+    };
+    let mut program = vec![
+        Instruction::Addi {
+            rd: 10,
+            rs1: 0,
+            imm: 2,
+        },
+        Instruction::Addi {
+            rd: 11,
+            rs1: 0,
+            imm: 3,
+        },
+    ];
+    program.extend(ecall(64));
+    program.push(Instruction::Add {
+        rd: 5,
+        rs1: 10,
+        rs2: 10,
+    });
+    program.extend(ecall(93));
+    let result = run(&mut instance, &program);
+
+    assert_eq!(result, Err("Environment call"));
+    assert_eq!(instance.pc(), 24);
+    assert_eq!(instance.read_register(10), 5);
+    assert_eq!(instance.read_register(5), 10);
+    // Two blocks of four and three instructions and two calls
+    assert_eq!(instance.gas(), 1000 - 4 - 3 - 20);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_breakpoint() {
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
+    let mut instance = Instance::new(memory);
+    instance.set_gas(100);
+
+    let increment = || Instruction::Addi {
+        rd: 5,
+        rs1: 5,
+        imm: 1,
+    };
+    let result = run(
+        &mut instance,
+        &[increment(), increment(), Instruction::Ebreak, increment()],
+    );
+
+    // Stops at the EBREAK without running or charging what follows
+    assert_eq!(result, Err("Breakpoint"));
+    assert_eq!(instance.pc(), 8);
+    assert_eq!(instance.read_register(5), 2);
+    assert_eq!(instance.gas(), 97);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_loads_and_stores() {
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
+    let mut instance = Instance::new(memory);
+    instance.write_register(2, 0x8000);
+    instance.write_register(5, 0x8081_82F3);
+
+    let result = run(
+        &mut instance,
+        &[
+            Instruction::Sw {
+                rs1: 2,
+                rs2: 5,
+                imm: -4,
+            },
+            Instruction::Lw {
+                rd: 6,
+                rs1: 2,
+                imm: -4,
+            },
+            Instruction::Lb {
+                rd: 7,
+                rs1: 2,
+                imm: -4,
+            },
+            Instruction::Lbu {
+                rd: 8,
+                rs1: 2,
+                imm: -4,
+            },
+            Instruction::Lh {
+                rd: 9,
+                rs1: 2,
+                imm: -2,
+            },
+            Instruction::Lhu {
+                rd: 10,
+                rs1: 2,
+                imm: -2,
+            },
+            // Misaligned and reaching into the unallocated page at 0x8000
+            Instruction::Lw {
+                rd: 11,
+                rs1: 2,
+                imm: -3,
+            },
+            Instruction::Sb {
+                rs1: 2,
+                rs2: 5,
+                imm: 0,
+            },
+            Instruction::Sh {
+                rs1: 2,
+                rs2: 5,
+                imm: 2,
+            },
+        ],
+    );
 
     assert_eq!(result, Ok(()));
+    assert_eq!(instance.read_register(6), 0x8081_82F3);
+    assert_eq!(instance.read_register(7), 0xFFFF_FFF3);
+    assert_eq!(instance.read_register(8), 0xF3);
+    assert_eq!(instance.read_register(9), 0xFFFF_8081);
+    assert_eq!(instance.read_register(10), 0x8081);
+    assert_eq!(instance.read_register(11), 0x0080_8182);
+    assert_eq!(instance.memory().read_u32(0x8000), 0x82F3_00F3);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_shared_pages_stay_read_only() {
+    let page_store = PageStore::new(256);
+    let shared = page_store.share(&[1, 2, 3, 4]).unwrap();
+    let mut memory = Memory::new(&page_store, 16, 16);
+    memory.map_shared(0x10000, &shared).unwrap();
+    memory.protect(0x10000, 4, Permissions::ALL).unwrap();
+    let mut instance = Instance::new(memory);
+    instance.write_register(2, 0x10000);
+
+    let result = run(
+        &mut instance,
+        &[
+            Instruction::Lw {
+                rd: 6,
+                rs1: 2,
+                imm: 0,
+            },
+            Instruction::Sw {
+                rs1: 2,
+                rs2: 0,
+                imm: 0,
+            },
+        ],
+    );
+
+    assert_eq!(result, Err("Memory access fault"));
+    assert_eq!(instance.pc(), 4);
+    assert_eq!(instance.read_register(6), 0x0403_0201);
+    assert_eq!(instance.memory().read_u32(0x10000), 0x0403_0201);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_devices_override_pages() {
+    /// Device holding a single register
+    struct Register(u32);
+
+    impl MmioDevice for Register {
+        fn read(&mut self, _offset: u32, _size: u32) -> u32 {
+            self.0
+        }
+
+        fn write(&mut self, _offset: u32, _size: u32, value: u32) {
+            self.0 = value;
+        }
+    }
+
+    let page_store = PageStore::new(256);
+    let mut memory = Memory::new(&page_store, 256, 16);
+    memory.write(0x8000, &[0x44, 0x33, 0x22, 0x11]).unwrap();
+    memory
+        .map_mmio(0x8000, 4, Box::new(Register(0x1234)))
+        .unwrap();
+    let mut instance = Instance::new(memory);
+    instance.write_register(2, 0x8000);
+    instance.write_register(5, 0x5678);
+
+    // The page under the device is allocated, but accesses go to the device
+    let result = run(
+        &mut instance,
+        &[
+            Instruction::Lw {
+                rd: 6,
+                rs1: 2,
+                imm: 0,
+            },
+            Instruction::Sw {
+                rs1: 2,
+                rs2: 5,
+                imm: 0,
+            },
+            Instruction::Lw {
+                rd: 7,
+                rs1: 2,
+                imm: 0,
+            },
+        ],
+    );
+
+    assert_eq!(result, Ok(()));
+    assert_eq!(instance.read_register(6), 0x1234);
+    assert_eq!(instance.read_register(7), 0x5678);
+    assert_eq!(instance.memory().read_u32(0x8000), 0x1122_3344);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_tlb_follows_memory_changes() {
+    let page_store = PageStore::new(256);
+    let mut memory = Memory::new(&page_store, 256, 16);
+    memory.write(0x8000, &[1, 0, 0, 0, 2, 0, 0, 0]).unwrap();
+    let mut instance = Instance::new(memory);
+    instance.write_register(2, 0x8000);
+    instance.write_register(5, 3);
+    // Takes every permission away from the page
+    instance.set_ecall_handler(Box::new(|instance| {
+        let memory = instance.memory_mut();
+        memory
+            .protect(0x8000, PAGE_SIZE as u32, Permissions::NONE)
+            .is_ok()
+    }));
+
+    // The second load and store of each kind hit the TLB, and the ECALL
+    // empties it
+    let access = [
+        Instruction::Lw {
+            rd: 6,
+            rs1: 2,
+            imm: 0,
+        },
+        Instruction::Lw {
+            rd: 7,
+            rs1: 2,
+            imm: 4,
+        },
+        Instruction::Sw {
+            rs1: 2,
+            rs2: 5,
+            imm: 8,
+        },
+        Instruction::Sw {
+            rs1: 2,
+            rs2: 5,
+            imm: 12,
+        },
+    ];
+    let mut program = access.to_vec();
+    program.push(Instruction::Ecall);
+    program.extend(access);
+    let result = run(&mut instance, &program);
+
+    assert_eq!(result, Err("Memory access fault"));
+    assert_eq!(instance.pc(), 20);
+    assert_eq!(
+        (instance.read_register(6), instance.read_register(7)),
+        (1, 2)
+    );
+    assert_eq!(instance.memory().read_u32(0x800C), 3);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_stops() {
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
+    let mut instance = Instance::new(memory);
+    instance
+        .memory_mut()
+        .protect(0, PAGE_SIZE as u32, Permissions::READ_ONLY)
+        .unwrap();
+
+    let store = Instruction::Sw {
+        rs1: 0,
+        rs2: 0,
+        imm: 0,
+    };
+    assert_eq!(
+        run(&mut instance, &[Instruction::Ecall]),
+        Err("Environment call")
+    );
+    assert_eq!(
+        run(
+            &mut instance,
+            &[
+                Instruction::Addi {
+                    rd: 1,
+                    rs1: 0,
+                    imm: 1
+                },
+                Instruction::Ebreak
+            ]
+        ),
+        Err("Breakpoint")
+    );
+    assert_eq!(instance.pc(), 4);
+    assert_eq!(run(&mut instance, &[store]), Err("Memory access fault"));
+    assert_eq!(instance.pc(), 0);
+    assert_eq!(
+        run(&mut instance, &[Instruction::Rdcycle { rd: 1 }]),
+        Err("Unsupported instruction")
+    );
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_multiply_divide() {
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
+    let mut instance = Instance::new(memory);
+    instance.write_register(1, 0x8000_0000);
+    instance.write_register(2, 0xFFFF_FFFF);
+    instance.write_register(3, 7);
+
+    let result = run(
+        &mut instance,
+        &[
+            Instruction::Mul {
+                rd: 10,
+                rs1: 3,
+                rs2: 2,
+            },
+            Instruction::Mulh {
+                rd: 11,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Mulhsu {
+                rd: 12,
+                rs1: 2,
+                rs2: 2,
+            },
+            Instruction::Mulhu {
+                rd: 13,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Div {
+                rd: 14,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Rem {
+                rd: 15,
+                rs1: 1,
+                rs2: 2,
+            },
+            Instruction::Div {
+                rd: 16,
+                rs1: 3,
+                rs2: 0,
+            },
+            Instruction::Divu {
+                rd: 17,
+                rs1: 3,
+                rs2: 0,
+            },
+            Instruction::Rem {
+                rd: 18,
+                rs1: 3,
+                rs2: 0,
+            },
+            Instruction::Remu {
+                rd: 19,
+                rs1: 3,
+                rs2: 0,
+            },
+            Instruction::Div {
+                rd: 20,
+                rs1: 2,
+                rs2: 3,
+            },
+            Instruction::Remu {
+                rd: 21,
+                rs1: 2,
+                rs2: 3,
+            },
+        ],
+    );
+
+    assert_eq!(result, Ok(()));
+    assert_eq!(instance.read_register(10), 0xFFFF_FFF9);
+    assert_eq!(instance.read_register(11), 0);
+    assert_eq!(instance.read_register(12), 0xFFFF_FFFF);
+    assert_eq!(instance.read_register(13), 0x7FFF_FFFF);
+    // Signed overflow
+    assert_eq!(instance.read_register(14), 0x8000_0000);
+    assert_eq!(instance.read_register(15), 0);
+    // Division by zero
+    assert_eq!(instance.read_register(16), 0xFFFF_FFFF);
+    assert_eq!(instance.read_register(17), 0xFFFF_FFFF);
+    assert_eq!(instance.read_register(18), 7);
+    assert_eq!(instance.read_register(19), 7);
+    assert_eq!(instance.read_register(20), 0);
+    assert_eq!(instance.read_register(21), 0xFFFF_FFFF % 7);
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_spilled_registers() {
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
+    let mut instance = Instance::new(memory);
+    instance.write_register(2, 0x8000);
+
+    // Chain values through x10-x31 so most of them are spilled
+    let mut program = vec![Instruction::Addi {
+        rd: 10,
+        rs1: 0,
+        imm: 1,
+    }];
+    for reg in 11..32 {
+        program.push(Instruction::Add {
+            rd: reg,
+            rs1: reg - 1,
+            rs2: reg - 1,
+        });
+    }
+    program.push(Instruction::Sw {
+        rs1: 2,
+        rs2: 31,
+        imm: 0,
+    });
+    program.push(Instruction::Lbu {
+        rd: 30,
+        rs1: 2,
+        imm: 3,
+    });
+
+    let result = run(&mut instance, &program);
+
+    assert_eq!(result, Ok(()));
+    for reg in 10..30 {
+        assert_eq!(instance.read_register(reg), 1 << (reg - 10));
+    }
+    assert_eq!(instance.read_register(31), 1 << 21);
+    assert_eq!(instance.read_register(30), 0);
+    assert_eq!(instance.read_register(2), 0x8000);
+}
+
+/// x1 = 3; loop: x2 += x1; x1 -= 1; bne x1, x0, loop; jal x0, 0x100
+#[cfg(target_arch = "aarch64")]
+fn countdown() -> Vec<u8> {
+    Instruction::encode_all(&[
+        Instruction::Addi {
+            rd: 1,
+            rs1: 0,
+            imm: 3,
+        },
+        Instruction::Add {
+            rd: 2,
+            rs1: 2,
+            rs2: 1,
+        },
+        Instruction::Addi {
+            rd: 1,
+            rs1: 1,
+            imm: -1,
+        },
+        Instruction::Bne {
+            rs1: 1,
+            rs2: 0,
+            imm: -8,
+        },
+        Instruction::Jal { rd: 0, imm: 0xF0 },
+    ])
+    .unwrap()
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn resume_mid_block() {
+    let code = countdown();
+    for opt_level in [OptLevel::O0, OptLevel::O1] {
+        let page_store = PageStore::new(256);
+        let memory = Memory::new(&page_store, 256, 16);
+        let mut instance = Instance::new(memory);
+        let mut module = Module::new(code.len()).unwrap();
+        module.set_opt_level(opt_level);
+        module.set_resumable(true);
+        module.set_code(&code).unwrap();
+        instance.attach(&mut module).unwrap();
+
+        // Enter at the decrement with x1 = 2: two more loops
+        instance.write_register(1, 2);
+        instance.set_pc(8);
+        instance.set_gas(100);
+        let result = unsafe { instance.resume() };
+        assert_eq!(result, Ok(()));
+        assert_eq!(instance.pc(), 0x100);
+        assert_eq!(instance.read_register(2), 1);
+        // The rest of the block, one more loop and the JAL
+        assert_eq!(instance.gas(), 100 - 2 - 3 - 1);
+
+        // Outside the module, nothing runs
+        let result = unsafe { instance.resume() };
+        assert_eq!(result, Ok(()));
+        assert_eq!(instance.gas(), 94);
+
+        instance.detach();
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn resume_off_instruction_boundary() {
+    let code = countdown();
+    for base in [0, 0x1000] {
+        let page_store = PageStore::new(256);
+        let memory = Memory::new(&page_store, 256, 16);
+        let mut instance = Instance::new(memory);
+        let mut module = Module::new(code.len()).unwrap();
+        module.set_base(base);
+        module.set_resumable(true);
+        module.set_code(&code).unwrap();
+        instance.attach(&mut module).unwrap();
+
+        for pc in [base + 1, base + 2, base + 7] {
+            instance.set_pc(pc);
+            instance.set_gas(100);
+            let result = unsafe { instance.resume() };
+            assert_eq!(result, Ok(()));
+            assert_eq!((instance.pc(), instance.gas()), (pc, 100));
+        }
+
+        instance.detach();
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn tiered_run_moves_to_compiled_code() {
+    let code = countdown();
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
+    let mut instance = Instance::new(memory);
+    let mut tiered = Tiered::new(Module::new(code.len()).unwrap(), &code).unwrap();
+    tiered.set_threshold(1);
+    instance.attach(tiered.module_mut()).unwrap();
+
+    // Compiled code takes over at some block entry, with the same results
+    instance.set_gas(100);
+    let result = unsafe { tiered.run(&mut instance) };
+    assert_eq!(result, Ok(()));
+    assert_eq!((instance.pc(), instance.read_register(2)), (0x100, 6));
+    assert_eq!(instance.gas(), 100 - 1 - 9 - 1);
+
+    tiered.wait().unwrap();
+    assert!(tiered.compiled());
+    instance.set_pc(0);
+    instance.write_register(2, 0);
+    let result = unsafe { tiered.run(&mut instance) };
+    assert_eq!(result, Ok(()));
+    assert_eq!((instance.pc(), instance.read_register(2)), (0x100, 6));
+    assert_eq!(tiered.entries(0), 1);
 
     instance.detach();
 }
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn block_counts() {
+    let code = countdown();
+    let page_store = PageStore::new(256);
+    let memory = Memory::new(&page_store, 256, 16);
+    let mut instance = Instance::new(memory);
+    let mut module = Module::new(code.len()).unwrap();
+    module.set_block_counters(true);
+    module.set_code(&code).unwrap();
+    assert_eq!(module.source_map().counters(), [0, 4, 16]);
+    instance.attach(&mut module).unwrap();
+    assert!(instance.block_counts().is_empty());
+
+    // Counts add up over calls
+    for calls in 1..=2 {
+        instance.write_register(2, 0);
+        let result = unsafe { instance.call_function(0) };
+        assert_eq!(result, Ok(()));
+        assert_eq!(instance.read_register(2), 6);
+        assert_eq!(instance.block_counts(), [calls, 3 * calls, calls]);
+    }
+
+    instance.reset_block_counts();
+    assert_eq!(instance.block_counts(), [0, 0, 0]);
+    instance.detach();
+    instance.attach(&mut module).unwrap();
+    assert!(instance.block_counts().is_empty());
+    instance.detach();
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn code_runs_at_another_address() {
+    // A cache hit copies the compiled bytes into the second module's buffer
+    let code = countdown();
+    let cache = CodeCache::new();
+    let mut modules = [
+        Module::new(code.len()).unwrap(),
+        Module::new(code.len()).unwrap(),
+    ];
+    for module in &mut modules {
+        module.set_code_cache(cache.clone());
+        module.set_resumable(true);
+        module.set_code(&code).unwrap();
+    }
+    assert_eq!(cache.hits(), 1);
+    assert_ne!(modules[0].code().as_ptr(), modules[1].code().as_ptr());
+    assert_eq!(modules[0].code(), modules[1].code());
+
+    for module in &mut modules {
+        let page_store = PageStore::new(256);
+        let mut instance = Instance::new(Memory::new(&page_store, 256, 16));
+        instance.attach(module).unwrap();
+        let result = unsafe { instance.call_function(0) };
+        assert_eq!(result, Ok(()));
+        assert_eq!((instance.pc(), instance.read_register(2)), (0x100, 6));
+        // Through the dispatch table as well
+        instance.write_register(1, 1);
+        instance.set_pc(8);
+        let result = unsafe { instance.resume() };
+        assert_eq!(result, Ok(()));
+        assert_eq!((instance.pc(), instance.read_register(2)), (0x100, 6));
+        instance.detach();
+    }
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn call_function_enters_each_function() {
+    let program = [
+        Instruction::Addi {
+            rd: 5,
+            rs1: 0,
+            imm: 1,
+        },
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 1,
+            imm: 0,
+        },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 0,
+            imm: 2,
+        },
+        Instruction::Addi {
+            rd: 6,
+            rs1: 5,
+            imm: 3,
+        },
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 1,
+            imm: 0,
+        },
+    ];
+    let code = Instruction::encode_all(&program).unwrap();
+    let mut module = Module::new(code.len()).unwrap();
+    module.set_base(0x2000);
+    module.set_functions(&[0x2000, 0x2008, 0x200C]);
+    module.set_code(&code).unwrap();
+
+    let page_store = PageStore::new(256);
+    let mut instance = Instance::new(Memory::new(&page_store, 256, 16));
+    instance.write_register(1, 0x100);
+    instance.set_gas(100);
+    instance.attach(&mut module).unwrap();
+    assert_eq!(unsafe { instance.call_function(0) }, Ok(()));
+    assert_eq!(instance.read_register(5), 1);
+    assert_eq!(instance.gas(), 98);
+
+    assert_eq!(unsafe { instance.call_function(1) }, Ok(()));
+    assert_eq!(instance.read_register(6), 5);
+    assert_eq!(instance.gas(), 95);
+
+    // Entering mid-block charges the rest of the block
+    instance.write_register(5, 10);
+    assert_eq!(unsafe { instance.call_function(2) }, Ok(()));
+    assert_eq!(instance.read_register(5), 10);
+    assert_eq!(instance.read_register(6), 13);
+    assert_eq!(instance.gas(), 93);
+    assert_eq!(instance.pc(), 0x100);
+    instance.detach();
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn code_runs_at_guest_base() {
+    // Call the instruction at base + 16 through an absolute address, which
+    // returns to the JAL out of the module
+    let program = [
+        Instruction::Auipc { rd: 5, imm: 0 },
+        Instruction::Addi {
+            rd: 5,
+            rs1: 5,
+            imm: 16,
+        },
+        Instruction::Jalr {
+            rd: 1,
+            rs1: 5,
+            imm: 0,
+        },
+        Instruction::Jal { rd: 0, imm: 0x100 },
+        Instruction::Addi {
+            rd: 2,
+            rs1: 0,
+            imm: 7,
+        },
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 1,
+            imm: 0,
+        },
+    ];
+    let code = Instruction::encode_all(&program).unwrap();
+    let mut module = Module::new(code.len()).unwrap();
+    module.set_base(0x4000);
+    module.set_resumable(true);
+    module.set_code(&code).unwrap();
+
+    let page_store = PageStore::new(256);
+    let mut instance = Instance::new(Memory::new(&page_store, 256, 16));
+    instance.attach(&mut module).unwrap();
+    let result = unsafe { instance.call_function(0) };
+    assert_eq!(result, Ok(()));
+    assert_eq!(instance.read_register(2), 7);
+    assert_eq!(instance.read_register(1), 0x400C);
+    assert_eq!(instance.pc(), 0x410C);
+
+    // Resuming at a guest address inside the module
+    instance.write_register(2, 0);
+    instance.set_pc(0x4010);
+    let result = unsafe { instance.resume() };
+    assert_eq!(result, Ok(()));
+    assert_eq!((instance.pc(), instance.read_register(2)), (0x410C, 7));
+    // Addresses below the base are outside the module
+    instance.set_pc(0x10);
+    assert_eq!(unsafe { instance.resume() }, Ok(()));
+    assert_eq!(instance.pc(), 0x10);
+    instance.detach();
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn literal_pool_loads_constants() {
+    // Repeated and distinct constants, a link value and a fused pair
+    let program = [
+        Instruction::Lui {
+            rd: 5,
+            imm: 0x12345,
+        },
+        Instruction::Lui {
+            rd: 6,
+            imm: 0x12345,
+        },
+        Instruction::Lui {
+            rd: 7,
+            imm: 0xABCDE,
+        },
+        Instruction::Addi {
+            rd: 7,
+            rs1: 7,
+            imm: 0x123,
+        },
+        Instruction::Jal { rd: 1, imm: 0x100 },
+    ];
+    let code = Instruction::encode_all(&program).unwrap();
+    let mut results = Vec::new();
+    for constants in [ConstantStrategy::MoveWide, ConstantStrategy::LiteralPool] {
+        let mut module = Module::new(code.len()).unwrap();
+        module.set_opt_level(OptLevel::O1);
+        module.set_base(0x56780000);
+        module.set_constants(constants);
+        module.set_code(&code).unwrap();
+        let page_store = PageStore::new(256);
+        let mut instance = Instance::new(Memory::new(&page_store, 256, 16));
+        instance.attach(&mut module).unwrap();
+        assert_eq!(unsafe { instance.call_function(0) }, Ok(()));
+        results.push([5, 6, 7, 1].map(|reg| instance.read_register(reg)));
+        instance.detach();
+    }
+    assert_eq!(results[0], [0x12345000, 0x12345000, 0xABCDE123, 0x56780014]);
+    assert_eq!(results[1], results[0]);
+}
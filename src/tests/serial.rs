@@ -0,0 +1,300 @@
+use crate::{
+    CompileError, ConstantStrategy, Decoder, Extensions, Instruction, Module, OptLevel,
+    SerializeError,
+    serial::{COMPILER, MODULE_MAGIC, MODULE_VERSION},
+};
+
+/// Offset of the maximum code size, the first setting
+const SETTINGS: usize = 12 + COMPILER.len();
+
+/// A module compiled with every setting changed from its default
+fn sample() -> Module {
+    let mut module = Module::new(64).unwrap();
+    module.set_opt_level(OptLevel::O2);
+    module.set_resumable(true);
+    module.set_block_counters(true);
+    module.set_base(0x1000);
+    module.set_constants(ConstantStrategy::LiteralPool);
+    module.set_decoder(Decoder::new(Extensions::all()));
+    module.set_functions(&[0x1000, 0x1008]);
+    module.set_data(vec![(0x8000, b"data".to_vec(), 0x1000)]);
+    let mut code = Instruction::encode_all(&[
+        Instruction::Lui {
+            rd: 5,
+            imm: 0x12345,
+        },
+        Instruction::Beq {
+            rs1: 5,
+            rs2: 0,
+            imm: 8,
+        },
+        Instruction::Mul {
+            rd: 6,
+            rs1: 5,
+            rs2: 5,
+        },
+        Instruction::Ecall,
+    ])
+    .unwrap();
+    // Not an instruction
+    code.extend(0xFFFF_FFFFu32.to_le_bytes());
+    module.set_code(&code).unwrap();
+    module
+}
+
+#[test]
+fn roundtrip() {
+    let module = sample();
+    let loaded = Module::deserialize(&module.serialize().unwrap()).unwrap();
+    assert_eq!(loaded.code(), module.code());
+    assert_eq!(loaded.source_map(), module.source_map());
+    assert_eq!(loaded.listing(), module.listing());
+    assert_eq!(loaded.max_code_size(), 64);
+    assert_eq!(loaded.opt_level(), OptLevel::O2);
+    assert!(loaded.resumable());
+    assert!(loaded.block_counters());
+    assert_eq!(loaded.base(), 0x1000);
+    assert_eq!(loaded.constants(), ConstantStrategy::LiteralPool);
+    assert_eq!(loaded.decoder(), module.decoder());
+    assert_eq!(loaded.functions(), [0x1000, 0x1008]);
+    assert_eq!(loaded.data(), module.data());
+    assert!(loaded.compile_stats().is_none());
+    assert_eq!(loaded.metadata(), module.metadata());
+    assert_eq!(loaded.serialize().unwrap(), module.serialize().unwrap());
+}
+
+#[test]
+fn empty_roundtrip() {
+    let module = Module::new(16).unwrap();
+    let loaded = Module::deserialize(&module.serialize().unwrap()).unwrap();
+    assert!(loaded.code().is_empty());
+    assert_eq!(loaded.decoder(), &Decoder::default());
+    assert!(loaded.metadata().is_none());
+    assert_eq!(loaded.serialize().unwrap(), module.serialize().unwrap());
+}
+
+#[test]
+fn recompile() {
+    let mut loaded = Module::deserialize(&sample().serialize().unwrap()).unwrap();
+    let code = Instruction::encode_all(&[Instruction::Ecall]).unwrap();
+    assert_eq!(
+        loaded.set_code(&code),
+        Err(CompileError::InvalidFunction {
+            index: 1,
+            address: 0x1008
+        })
+    );
+    loaded.set_functions(&[]);
+    loaded.set_code(&code).unwrap();
+    assert_eq!(loaded.source_map().len(), 1);
+    // Longer code than the module was sized for grows its buffer
+    loaded.set_code(&[0; 68]).unwrap();
+    assert_eq!(loaded.source_map().len(), 17);
+}
+
+#[test]
+fn header() {
+    let bytes = Module::new(4).unwrap().serialize().unwrap();
+    assert_eq!(bytes[..4], MODULE_MAGIC);
+    assert_eq!(bytes[4..8], MODULE_VERSION.to_le_bytes());
+    assert_eq!(bytes[8..12], (COMPILER.len() as u32).to_le_bytes());
+    assert_eq!(&bytes[12..SETTINGS], COMPILER.as_bytes());
+}
+
+#[test]
+fn invalid_magic() {
+    let mut bytes = sample().serialize().unwrap();
+    bytes[3] = b'X';
+    assert_eq!(
+        Module::deserialize(&bytes).err(),
+        Some(SerializeError::InvalidMagic)
+    );
+}
+
+#[test]
+fn unsupported_version() {
+    let mut bytes = sample().serialize().unwrap();
+    bytes[4] = 99;
+    assert_eq!(
+        Module::deserialize(&bytes).err(),
+        Some(SerializeError::UnsupportedVersion(99))
+    );
+}
+
+#[test]
+fn compiler_mismatch() {
+    let mut bytes = sample().serialize().unwrap();
+    bytes[SETTINGS - 1] = b'x';
+    let mut version = COMPILER.to_string();
+    version.pop();
+    version.push('x');
+    assert_eq!(
+        Module::deserialize(&bytes).err(),
+        Some(SerializeError::CompilerMismatch(version))
+    );
+}
+
+#[test]
+fn truncated() {
+    let bytes = sample().serialize().unwrap();
+    for length in [0, 3, 10, SETTINGS + 8, bytes.len() / 2, bytes.len() - 1] {
+        assert_eq!(
+            Module::deserialize(&bytes[..length]).err(),
+            Some(SerializeError::Truncated)
+        );
+    }
+}
+
+#[test]
+fn invalid_setting() {
+    let bytes = sample().serialize().unwrap();
+    for (offset, setting) in [
+        (4, "opt level"),
+        (8, "resumable setting"),
+        (12, "block counters setting"),
+        (20, "constant strategy"),
+        (24, "extensions"),
+    ] {
+        let mut invalid = bytes.clone();
+        invalid[SETTINGS + offset] = 0x10;
+        assert_eq!(
+            Module::deserialize(&invalid).err(),
+            Some(SerializeError::InvalidSetting(setting))
+        );
+    }
+}
+
+#[test]
+fn large_max_code_size() {
+    let module = Module::new(4 << 20).unwrap();
+    let loaded = Module::deserialize(&module.serialize().unwrap()).unwrap();
+    assert_eq!(loaded.max_code_size(), 4 << 20);
+
+    // The size does not size the buffer, so corrupt sizes allocate nothing
+    let mut bytes = sample().serialize().unwrap();
+    bytes[SETTINGS + 3] = 0xD8;
+    let loaded = Module::deserialize(&bytes).unwrap();
+    assert_eq!(loaded.max_code_size(), 0xD800_0040);
+    assert_eq!(loaded.code(), sample().code());
+}
+
+#[test]
+fn invalid_source_map() {
+    // A module without data or block counters ends with the last frame, the
+    // empty counter list, the metadata and the empty data list
+    let mut module = Module::new(4).unwrap();
+    module
+        .set_code(&Instruction::encode_all(&[Instruction::Ecall]).unwrap())
+        .unwrap();
+    let bytes = module.serialize().unwrap();
+    let mut metadata = Vec::new();
+    module.metadata().unwrap().write(&mut metadata);
+    let end = bytes.len() - metadata.len() - 8;
+
+    let mut invalid = bytes.clone();
+    invalid[end - 8] = 3;
+    assert_eq!(
+        Module::deserialize(&invalid).err(),
+        Some(SerializeError::InvalidSourceMap)
+    );
+    let mut invalid = bytes.clone();
+    invalid[end - 10] = 0xFF;
+    assert_eq!(
+        Module::deserialize(&invalid).err(),
+        Some(SerializeError::InvalidSourceMap)
+    );
+
+    // More instructions than the source map covers
+    let mut module = Module::new(4).unwrap();
+    module.set_code(&[]).unwrap();
+    let mut invalid = module.serialize().unwrap();
+    invalid[SETTINGS + 32] = 1;
+    invalid.splice(SETTINGS + 36..SETTINGS + 36, 0x73u32.to_le_bytes());
+    assert_eq!(
+        Module::deserialize(&invalid).err(),
+        Some(SerializeError::InvalidSourceMap)
+    );
+}
+
+#[test]
+fn source_map_mismatch() {
+    // A base other than the one compiled for
+    let mut invalid = sample().serialize().unwrap();
+    invalid[SETTINGS + 16..SETTINGS + 20].copy_from_slice(&0x2000u32.to_le_bytes());
+    assert_eq!(
+        Module::deserialize(&invalid).err(),
+        Some(SerializeError::InvalidSourceMap)
+    );
+
+    // A function table other than the one compiled for: of another length,
+    // or of the same length with a function outside the code
+    for functions in [&[0x1000][..], &[0x1000, 0x1100]] {
+        let mut module = sample();
+        module.set_functions(functions);
+        assert_eq!(
+            Module::deserialize(&module.serialize().unwrap()).err(),
+            Some(SerializeError::InvalidSourceMap)
+        );
+    }
+}
+
+#[test]
+fn invalid_metadata() {
+    let mut module = Module::new(4).unwrap();
+    module
+        .set_code(&Instruction::encode_all(&[Instruction::Ecall]).unwrap())
+        .unwrap();
+    let bytes = module.serialize().unwrap();
+    // Nanoseconds of the creation time, before the empty data list
+    let nanos = bytes.len() - 8;
+    let mut invalid = bytes.clone();
+    invalid[nanos..nanos + 4].copy_from_slice(&1_000_000_000u32.to_le_bytes());
+    assert_eq!(
+        Module::deserialize(&invalid).err(),
+        Some(SerializeError::InvalidSetting("creation time"))
+    );
+    let mut metadata = Vec::new();
+    module.metadata().unwrap().write(&mut metadata);
+    let flag = bytes.len() - metadata.len() - 8;
+    let mut invalid = bytes;
+    invalid[flag] = 2;
+    assert_eq!(
+        Module::deserialize(&invalid).err(),
+        Some(SerializeError::InvalidSetting("metadata flag"))
+    );
+}
+
+#[test]
+fn display() {
+    assert_eq!(
+        SerializeError::InvalidMagic.to_string(),
+        "Not a serialized module"
+    );
+    assert_eq!(
+        SerializeError::CompilerMismatch("0.0.1".to_string()).to_string(),
+        "Module was compiled by jigs 0.0.1"
+    );
+    assert_eq!(
+        SerializeError::InvalidSetting("opt level").to_string(),
+        "Invalid opt level"
+    );
+}
+
+#[test]
+#[cfg(target_arch = "aarch64")]
+fn run() {
+    use crate::{Instance, Memory, PageStore};
+
+    let mut loaded = Module::deserialize(&sample().serialize().unwrap()).unwrap();
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    instance.attach(&mut loaded).unwrap();
+    let result = unsafe { instance.call_function(0) };
+    assert_eq!(result, Err("Environment call"));
+    assert_eq!(instance.pc(), 0x100C);
+    assert_eq!(
+        instance.read_register(6),
+        0x12345000u32.wrapping_mul(0x12345000)
+    );
+    assert_eq!(instance.memory().read_array(0x8000), *b"data");
+}
@@ -0,0 +1,441 @@
+use crate::memory::PAGE_SIZE;
+use crate::{
+    Instance, Memory, PageStore, Permissions,
+    syscall::{
+        Action, EACCES, EAGAIN, EDQUOT, EINVAL, EMFILE, ENOENT, ENOMEM, ENOSYS, EPERM, Limits,
+        PROT_EXEC, PROT_READ, PROT_WRITE, Policy, Predicate, Quota, Rule, SYS_CLOSE, SYS_MPROTECT,
+        SYS_OPENAT, SYS_WRITE, Syscall, TokenBucket, Verdict, mprotect,
+    },
+};
+use std::time::{Duration, Instant};
+
+/// Set up a syscall request in the instance's registers
+fn request(instance: &mut Instance, number: u32, args: &[u32]) {
+    instance.write_register(17, number);
+    for (index, arg) in args.iter().enumerate() {
+        instance.write_register(10 + index as u8, *arg);
+    }
+}
+
+#[test]
+fn from_instance() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    request(&mut instance, 93, &[1, 2, 3, 4, 5, 6]);
+    let syscall = Syscall::from_instance(&instance);
+    assert_eq!(syscall.number, 93);
+    assert_eq!(syscall.args, [1, 2, 3, 4, 5, 6]);
+}
+
+#[test]
+fn default_action() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    let mut policy = Policy::new(Action::Deny(ENOSYS));
+    request(&mut instance, 1234, &[7]);
+    assert_eq!(policy.dispatch(&mut instance), Verdict::Denied(ENOSYS));
+    assert_eq!(instance.read_register(10), -ENOSYS as u32);
+    assert_eq!(policy.audit()[0].rule, None);
+}
+
+#[test]
+fn allow_leaves_registers() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    let mut policy = Policy::new(Action::Deny(EPERM));
+    policy.add(Rule::new(64, Action::Allow));
+    request(&mut instance, 64, &[1, 0x1000, 5]);
+    assert_eq!(policy.dispatch(&mut instance), Verdict::Allowed);
+    assert_eq!(instance.read_register(10), 1);
+}
+
+#[test]
+fn first_match_wins() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    let mut policy = Policy::new(Action::Allow);
+    let deny = policy
+        .add(Rule::new(64, Action::Deny(EACCES)).with(Predicate::Equals { arg: 0, value: 2 }));
+    policy.add(Rule::new(64, Action::Deny(EPERM)));
+    request(&mut instance, 64, &[2]);
+    assert_eq!(policy.dispatch(&mut instance), Verdict::Denied(EACCES));
+    request(&mut instance, 64, &[1]);
+    assert_eq!(policy.dispatch(&mut instance), Verdict::Denied(EPERM));
+    assert_eq!(policy.audit()[0].rule, Some(deny));
+    assert_eq!(policy.audit()[1].rule, Some(1));
+}
+
+#[test]
+fn masked_predicate() {
+    let store = PageStore::new(16);
+    let memory = Memory::new(&store, 16, 4);
+    let predicate = Predicate::Masked {
+        arg: 2,
+        mask: 0x3,
+        value: 0,
+    };
+    let read_only = Syscall {
+        number: 56,
+        args: [0, 0, 0x40, 0, 0, 0],
+    };
+    let writable = Syscall {
+        number: 56,
+        args: [0, 0, 0x41, 0, 0, 0],
+    };
+    assert!(predicate.matches(&read_only, &memory));
+    assert!(!predicate.matches(&writable, &memory));
+}
+
+#[test]
+fn out_of_range_argument() {
+    let store = PageStore::new(16);
+    let memory = Memory::new(&store, 16, 4);
+    let syscall = Syscall {
+        number: 0,
+        args: [0; 6],
+    };
+    assert!(!Predicate::Equals { arg: 6, value: 0 }.matches(&syscall, &memory));
+    assert!(
+        !Predicate::Masked {
+            arg: 9,
+            mask: 0,
+            value: 0
+        }
+        .matches(&syscall, &memory)
+    );
+}
+
+#[test]
+fn path_prefix() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    instance
+        .memory_mut()
+        .write(0x1000, b"/tmp/scratch\0")
+        .unwrap();
+    instance
+        .memory_mut()
+        .write(0x2000, b"/etc/passwd\0")
+        .unwrap();
+    let mut policy = Policy::new(Action::Deny(EACCES));
+    policy.add(Rule::new(56, Action::Allow).with(Predicate::PathPrefix {
+        arg: 1,
+        prefix: "/tmp/".to_string(),
+    }));
+    request(&mut instance, 56, &[0xFFFFFF9C, 0x1000]);
+    assert_eq!(policy.dispatch(&mut instance), Verdict::Allowed);
+    request(&mut instance, 56, &[0xFFFFFF9C, 0x2000]);
+    assert_eq!(policy.dispatch(&mut instance), Verdict::Denied(EACCES));
+}
+
+#[test]
+fn unterminated_path() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    instance.memory_mut().write(0, &[b'a'; 8192]).unwrap();
+    let predicate = Predicate::PathPrefix {
+        arg: 0,
+        prefix: "a".to_string(),
+    };
+    let syscall = Syscall {
+        number: 56,
+        args: [0; 6],
+    };
+    assert!(!predicate.matches(&syscall, instance.memory()));
+}
+
+#[test]
+fn handler() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    let mut policy = Policy::new(Action::Deny(ENOSYS));
+    policy.add(Rule::new(
+        172,
+        Action::Handle(Box::new(|instance, syscall| {
+            instance.write_register(11, syscall.number);
+            42
+        })),
+    ));
+    request(&mut instance, 172, &[]);
+    assert_eq!(policy.dispatch(&mut instance), Verdict::Handled(42));
+    assert_eq!(instance.read_register(10), 42);
+    assert_eq!(instance.read_register(11), 172);
+}
+
+#[test]
+fn audit_log() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    let mut policy = Policy::new(Action::Deny(EPERM));
+    instance.set_pc(0x400);
+    request(&mut instance, 64, &[1, 2, 3, 4, 5, 6]);
+    policy.dispatch(&mut instance);
+    let entry = policy.audit()[0];
+    assert_eq!(entry.pc, 0x400);
+    assert_eq!(entry.syscall.number, 64);
+    assert_eq!(entry.verdict, Verdict::Denied(EPERM));
+    let audit = policy.take_audit();
+    assert_eq!(audit.len(), 1);
+    assert!(policy.audit().is_empty());
+}
+
+#[test]
+fn audit_display() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    let mut policy = Policy::new(Action::Allow);
+    policy.add(Rule::new(93, Action::Deny(EPERM)));
+    request(&mut instance, 93, &[0x10, 0, 0, 0, 0, 0]);
+    policy.dispatch(&mut instance);
+    request(&mut instance, 64, &[1]);
+    policy.dispatch(&mut instance);
+    assert_eq!(
+        policy.audit()[0].to_string(),
+        "0x00000000: syscall 93(0x10, 0x0, 0x0, 0x0, 0x0, 0x0) denied errno 1 (rule 0)"
+    );
+    assert_eq!(
+        policy.audit()[1].to_string(),
+        "0x00000000: syscall 64(0x1, 0x0, 0x0, 0x0, 0x0, 0x0) allowed (default)"
+    );
+}
+
+#[test]
+fn quota_errno() {
+    assert_eq!(Quota::Rate.errno(), EAGAIN);
+    assert_eq!(Quota::OpenFiles.errno(), EMFILE);
+    assert_eq!(Quota::BytesWritten.errno(), EDQUOT);
+}
+
+#[test]
+fn token_bucket() {
+    let start = Instant::now();
+    let mut bucket = TokenBucket::new(2, 2, start);
+    assert!(bucket.take(start));
+    assert!(bucket.take(start));
+    assert!(!bucket.take(start));
+    assert_eq!(bucket.wait(), Duration::from_millis(500));
+    assert!(bucket.take(start + Duration::from_millis(500)));
+    // Refill is capped at the burst size
+    let later = start + Duration::from_secs(60);
+    assert!(bucket.take(later) && bucket.take(later));
+    assert!(!bucket.take(later));
+}
+
+#[test]
+fn token_bucket_without_rate() {
+    let start = Instant::now();
+    let mut bucket = TokenBucket::new(0, 1, start);
+    assert_eq!(bucket.wait(), Duration::ZERO);
+    assert!(bucket.take(start));
+    assert_eq!(bucket.wait(), Duration::MAX);
+    assert!(!bucket.take(start + Duration::from_secs(60)));
+}
+
+#[test]
+fn rate_limit() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    let mut policy = Policy::new(Action::Allow);
+    policy.set_limits(Limits {
+        rate: Some((1, 2)),
+        ..Limits::default()
+    });
+    let now = Instant::now();
+    request(&mut instance, 172, &[]);
+    assert_eq!(policy.dispatch_at(&mut instance, now), Verdict::Allowed);
+    assert_eq!(policy.dispatch_at(&mut instance, now), Verdict::Allowed);
+    assert_eq!(
+        policy.dispatch_at(&mut instance, now),
+        Verdict::Exceeded(Quota::Rate)
+    );
+    assert_eq!(instance.read_register(10), -EAGAIN as u32);
+    assert_eq!(
+        policy.dispatch_at(&mut instance, now + Duration::from_secs(1)),
+        Verdict::Allowed
+    );
+    assert_eq!(policy.metrics().throttled, 1);
+    assert_eq!(policy.metrics().syscalls, 4);
+}
+
+#[test]
+fn open_files() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    let mut policy = Policy::new(Action::Allow);
+    policy.set_limits(Limits {
+        open_files: Some(1),
+        ..Limits::default()
+    });
+    request(&mut instance, SYS_OPENAT, &[0, 0x1000]);
+    assert_eq!(policy.dispatch(&mut instance), Verdict::Allowed);
+    // The host opened fd 3
+    instance.write_register(10, 3);
+    policy.complete(&instance);
+    request(&mut instance, SYS_OPENAT, &[0, 0x1000]);
+    assert_eq!(
+        policy.dispatch(&mut instance),
+        Verdict::Exceeded(Quota::OpenFiles)
+    );
+    assert_eq!(instance.read_register(10), -EMFILE as u32);
+    request(&mut instance, SYS_CLOSE, &[3]);
+    assert_eq!(policy.dispatch(&mut instance), Verdict::Allowed);
+    instance.write_register(10, 0);
+    policy.complete(&instance);
+    request(&mut instance, SYS_OPENAT, &[0, 0x1000]);
+    assert_eq!(policy.dispatch(&mut instance), Verdict::Allowed);
+    assert_eq!(policy.metrics().peak_open_files, 1);
+    assert_eq!(policy.metrics().exceeded, 1);
+}
+
+#[test]
+fn failed_open_not_counted() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    let mut policy = Policy::new(Action::Allow);
+    request(&mut instance, SYS_OPENAT, &[0, 0x1000]);
+    policy.dispatch(&mut instance);
+    instance.write_register(10, -ENOENT as u32);
+    policy.complete(&instance);
+    assert_eq!(policy.metrics().open_files, 0);
+}
+
+#[test]
+fn close_of_untracked_fd_not_counted() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    let mut policy = Policy::new(Action::Allow);
+    request(&mut instance, SYS_OPENAT, &[0, 0x1000]);
+    policy.dispatch(&mut instance);
+    instance.write_register(10, 3);
+    policy.complete(&instance);
+    // stdout was never opened through the policy
+    request(&mut instance, SYS_CLOSE, &[1]);
+    policy.dispatch(&mut instance);
+    instance.write_register(10, 0);
+    policy.complete(&instance);
+    assert_eq!(policy.metrics().open_files, 1);
+    request(&mut instance, SYS_CLOSE, &[3]);
+    policy.dispatch(&mut instance);
+    instance.write_register(10, 0);
+    policy.complete(&instance);
+    assert_eq!(policy.metrics().open_files, 0);
+}
+
+#[test]
+fn bytes_written() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    let mut policy = Policy::new(Action::Allow);
+    policy.set_limits(Limits {
+        bytes_written: Some(100),
+        ..Limits::default()
+    });
+    request(&mut instance, SYS_WRITE, &[1, 0x1000, 80]);
+    assert_eq!(policy.dispatch(&mut instance), Verdict::Allowed);
+    // The host performed a short write of 60 bytes
+    instance.write_register(10, 60);
+    policy.complete(&instance);
+    request(&mut instance, SYS_WRITE, &[1, 0x1000, 41]);
+    assert_eq!(
+        policy.dispatch(&mut instance),
+        Verdict::Exceeded(Quota::BytesWritten)
+    );
+    assert_eq!(instance.read_register(10), -EDQUOT as u32);
+    request(&mut instance, SYS_WRITE, &[1, 0x1000, 40]);
+    assert_eq!(policy.dispatch(&mut instance), Verdict::Allowed);
+    assert_eq!(policy.metrics().bytes_written, 60);
+}
+
+#[test]
+fn quotas_skip_denied_and_handled() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    let mut policy = Policy::new(Action::Handle(Box::new(|_, syscall| syscall.args[2])));
+    policy.set_limits(Limits {
+        bytes_written: Some(0),
+        ..Limits::default()
+    });
+    request(&mut instance, SYS_WRITE, &[1, 0x1000, 10]);
+    assert_eq!(policy.dispatch(&mut instance), Verdict::Handled(10));
+    policy.complete(&instance);
+    assert_eq!(policy.metrics().bytes_written, 0);
+    assert_eq!(policy.metrics().handled, 1);
+}
+
+#[test]
+fn exceeded_display() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    let mut policy = Policy::new(Action::Allow);
+    policy.set_limits(Limits {
+        rate: Some((1, 0)),
+        ..Limits::default()
+    });
+    request(&mut instance, 172, &[]);
+    policy.dispatch(&mut instance);
+    assert_eq!(
+        policy.audit()[0].to_string(),
+        "0x00000000: syscall 172(0x0, 0x0, 0x0, 0x0, 0x0, 0x0) exceeded rate quota"
+    );
+    assert_eq!(policy.audit()[0].rule, None);
+}
+
+#[test]
+fn mprotect_handler() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    let mut policy = Policy::new(Action::Deny(ENOSYS));
+    policy.add(Rule::new(SYS_MPROTECT, Action::Handle(Box::new(mprotect))));
+    let page = PAGE_SIZE as u32;
+
+    // W^X: write code, then flip the pages to read/execute
+    request(
+        &mut instance,
+        SYS_MPROTECT,
+        &[page, page + 1, PROT_READ | PROT_EXEC],
+    );
+    assert_eq!(policy.dispatch(&mut instance), Verdict::Handled(0));
+    assert_eq!(
+        instance.memory().permissions(page),
+        Permissions::READ_EXECUTE
+    );
+    assert_eq!(
+        instance.memory().permissions(2 * page),
+        Permissions::READ_EXECUTE
+    );
+    assert_eq!(instance.memory().permissions(3 * page), Permissions::ALL);
+
+    request(
+        &mut instance,
+        SYS_MPROTECT,
+        &[page, page, PROT_READ | PROT_WRITE],
+    );
+    assert_eq!(policy.dispatch(&mut instance), Verdict::Handled(0));
+    assert_eq!(instance.memory().permissions(page), Permissions::READ_WRITE);
+
+    request(&mut instance, SYS_MPROTECT, &[page, page, 0]);
+    policy.dispatch(&mut instance);
+    assert_eq!(instance.memory().permissions(page), Permissions::NONE);
+}
+
+#[test]
+fn mprotect_errors() {
+    let store = PageStore::new(16);
+    let mut instance = Instance::new(Memory::new(&store, 16, 4));
+    let page = PAGE_SIZE as u32;
+    let shared = store.share(b"shared").unwrap();
+    instance.memory_mut().map_shared(page, &shared).unwrap();
+    instance.memory_mut().guard(2 * page, 1).unwrap();
+    let mut policy = Policy::new(Action::Handle(Box::new(mprotect)));
+
+    let mut errno = |instance: &mut Instance, args: &[u32]| {
+        request(instance, SYS_MPROTECT, args);
+        policy.dispatch(instance);
+        instance.read_register(10) as i32
+    };
+    assert_eq!(errno(&mut instance, &[page + 1, 1, PROT_READ]), -EINVAL);
+    assert_eq!(errno(&mut instance, &[page, 1, 0x8]), -EINVAL);
+    assert_eq!(errno(&mut instance, &[page, 1, PROT_WRITE]), -EACCES);
+    assert_eq!(errno(&mut instance, &[page, 1, PROT_READ]), 0);
+    assert_eq!(errno(&mut instance, &[2 * page, 1, PROT_READ]), -ENOMEM);
+}
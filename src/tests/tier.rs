@@ -0,0 +1,299 @@
+use crate::{CompileError, Instance, Instruction, Memory, Module, OptLevel, PageStore, Tiered};
+
+/// Interpret a program from PC 0 with compilation off, returning the result
+fn run(instance: &mut Instance, program: &[Instruction]) -> Result<(), &'static str> {
+    let code = Instruction::encode_all(program).unwrap();
+    let mut tiered = Tiered::new(Module::new(code.len()).unwrap(), &code).unwrap();
+    tiered.set_threshold(0);
+    instance.attach(tiered.module_mut()).unwrap();
+    let result = unsafe { tiered.run(instance) };
+    instance.detach();
+    assert!(!tiered.compiling() && !tiered.compiled());
+    result
+}
+
+fn instance(store: &PageStore) -> Instance {
+    Instance::new(Memory::new(store, 16, 4))
+}
+
+/// x1 = 3; loop: x2 += x1; x1 -= 1; bne x1, x0, loop; jal x0, 0x100
+fn countdown() -> [Instruction; 5] {
+    [
+        Instruction::Addi {
+            rd: 1,
+            rs1: 0,
+            imm: 3,
+        },
+        Instruction::Add {
+            rd: 2,
+            rs1: 2,
+            rs2: 1,
+        },
+        Instruction::Addi {
+            rd: 1,
+            rs1: 1,
+            imm: -1,
+        },
+        Instruction::Bne {
+            rs1: 1,
+            rs2: 0,
+            imm: -8,
+        },
+        Instruction::Jal { rd: 0, imm: 0xF0 },
+    ]
+}
+
+#[test]
+fn interprets_until_jump_out() {
+    let store = PageStore::new(16);
+    let mut instance = instance(&store);
+    instance.set_gas(100);
+    assert_eq!(run(&mut instance, &countdown()), Ok(()));
+    assert_eq!(instance.read_register(2), 6);
+    assert_eq!(instance.pc(), 0x100);
+    // One for the first block, three per loop and one for the JAL
+    assert_eq!(instance.gas(), 100 - 1 - 9 - 1);
+}
+
+#[test]
+fn counts_block_entries() {
+    let code = Instruction::encode_all(&countdown()).unwrap();
+    let mut tiered = Tiered::new(Module::new(code.len()).unwrap(), &code).unwrap();
+    tiered.set_threshold(0);
+    assert_eq!(tiered.threshold(), 0);
+    assert!(tiered.module().resumable());
+    let store = PageStore::new(16);
+    let mut instance = instance(&store);
+    instance.attach(tiered.module_mut()).unwrap();
+    assert_eq!(unsafe { tiered.run(&mut instance) }, Ok(()));
+    instance.detach();
+    assert_eq!(tiered.entries(0), 1);
+    assert_eq!(tiered.entries(4), 3);
+    assert_eq!(tiered.entries(12), 3);
+    assert_eq!(tiered.entries(16), 1);
+    assert_eq!(tiered.entries(20), 0);
+}
+
+#[test]
+fn runs_at_base() {
+    let code = Instruction::encode_all(&countdown()).unwrap();
+    let mut module = Module::new(code.len()).unwrap();
+    module.set_base(0x1000);
+    let mut tiered = Tiered::new(module, &code).unwrap();
+    tiered.set_threshold(0);
+    let store = PageStore::new(16);
+    let mut instance = instance(&store);
+    instance.set_pc(0x1000);
+    instance.set_gas(100);
+    instance.attach(tiered.module_mut()).unwrap();
+    assert_eq!(unsafe { tiered.run(&mut instance) }, Ok(()));
+    instance.detach();
+    assert_eq!(instance.read_register(2), 6);
+    assert_eq!(instance.pc(), 0x1100);
+    assert_eq!(tiered.entries(0x1004), 3);
+    assert_eq!(tiered.entries(4), 0);
+}
+
+#[test]
+fn out_of_gas_at_block() {
+    let store = PageStore::new(16);
+    let mut instance = instance(&store);
+    instance.set_gas(3);
+    assert_eq!(run(&mut instance, &countdown()), Err("Out of gas"));
+    // The first block fits, the loop does not
+    assert_eq!(instance.pc(), 4);
+    assert_eq!(instance.gas(), 2);
+}
+
+#[test]
+fn resumes_mid_block() {
+    let code = Instruction::encode_all(&countdown()).unwrap();
+    let mut tiered = Tiered::new(Module::new(code.len()).unwrap(), &code).unwrap();
+    tiered.set_threshold(0);
+    let store = PageStore::new(16);
+    let mut instance = instance(&store);
+    instance.write_register(1, 1);
+    instance.set_pc(8);
+    instance.set_gas(10);
+    instance.attach(tiered.module_mut()).unwrap();
+    assert_eq!(unsafe { tiered.run(&mut instance) }, Ok(()));
+    instance.detach();
+    // The rest of the loop block, then the JAL
+    assert_eq!(instance.gas(), 10 - 2 - 1);
+    assert_eq!(instance.pc(), 0x100);
+}
+
+#[test]
+fn ecall_handler() {
+    let program = [
+        Instruction::Ecall,
+        Instruction::Addi {
+            rd: 1,
+            rs1: 0,
+            imm: 7,
+        },
+        Instruction::Ecall,
+    ];
+    let store = PageStore::new(16);
+    let mut instance = instance(&store);
+    assert_eq!(run(&mut instance, &program), Err("Environment call"));
+    assert_eq!((instance.pc(), instance.read_register(1)), (0, 0));
+
+    instance.set_ecall_handler(Box::new(|instance| {
+        instance.write_register(2, instance.read_register(2) + 1);
+        instance.read_register(1) == 0
+    }));
+    assert_eq!(run(&mut instance, &program), Err("Environment call"));
+    assert_eq!(instance.pc(), 8);
+    assert_eq!(instance.read_register(1), 7);
+    assert_eq!(instance.read_register(2), 2);
+}
+
+#[test]
+fn stops_like_compiled_code() {
+    let store = PageStore::new(16);
+    let mut instance = instance(&store);
+    assert_eq!(
+        run(&mut instance, &[Instruction::Ebreak]),
+        Err("Breakpoint")
+    );
+    assert_eq!(instance.pc(), 0);
+
+    let program = [
+        Instruction::Addi {
+            rd: 1,
+            rs1: 0,
+            imm: 1,
+        },
+        Instruction::Rdcycle { rd: 2 },
+    ];
+    let mut instance = self::instance(&store);
+    assert_eq!(run(&mut instance, &program), Err("Unsupported instruction"));
+    assert_eq!((instance.pc(), instance.read_register(1)), (4, 1));
+
+    let load = [Instruction::Lw {
+        rd: 1,
+        rs1: 0,
+        imm: 0x100,
+    }];
+    let mut instance = self::instance(&store);
+    instance.memory_mut().guard(0, 0x1000).unwrap();
+    assert_eq!(run(&mut instance, &load), Err("Memory access fault"));
+    assert_eq!(instance.pc(), 0);
+}
+
+#[test]
+fn hot_block_compiles_in_background() {
+    // A single block that runs off the end, so no block is entered after the
+    // one reaching the threshold
+    let program = [Instruction::Addi {
+        rd: 1,
+        rs1: 1,
+        imm: 1,
+    }];
+    let code = Instruction::encode_all(&program).unwrap();
+    let mut module = Module::new(code.len()).unwrap();
+    module.set_opt_level(OptLevel::O1);
+    let mut tiered = Tiered::new(module, &code).unwrap();
+    tiered.set_threshold(3);
+    let store = PageStore::new(16);
+    let mut instance = instance(&store);
+    instance.attach(tiered.module_mut()).unwrap();
+    for _ in 0..3 {
+        assert!(!tiered.compiling());
+        instance.set_pc(0);
+        assert_eq!(unsafe { tiered.run(&mut instance) }, Ok(()));
+    }
+    instance.detach();
+    assert_eq!(instance.read_register(1), 3);
+    assert!(tiered.compiling());
+
+    tiered.wait().unwrap();
+    assert!(!tiered.compiling() && tiered.compiled());
+    let mut expected = Module::new(code.len()).unwrap();
+    expected.set_opt_level(OptLevel::O1);
+    expected.set_resumable(true);
+    expected.set_code(&code).unwrap();
+    assert_eq!(tiered.module().code(), expected.code());
+    assert_eq!(tiered.module().source_map(), expected.source_map());
+    assert_eq!(tiered.module().listing(), expected.listing());
+}
+
+#[test]
+fn drops_existing_code() {
+    let mut module = Module::new(4).unwrap();
+    let old = Instruction::encode_all(&[Instruction::Addi {
+        rd: 1,
+        rs1: 0,
+        imm: 1,
+    }])
+    .unwrap();
+    module.set_code(&old).unwrap();
+    let code = Instruction::encode_all(&[Instruction::Addi {
+        rd: 1,
+        rs1: 0,
+        imm: 2,
+    }])
+    .unwrap();
+    let mut tiered = Tiered::new(module, &code).unwrap();
+    assert!(!tiered.compiled() && tiered.module().source_map().is_empty());
+    tiered.set_threshold(1);
+    let store = PageStore::new(16);
+    let mut instance = instance(&store);
+    instance.attach(tiered.module_mut()).unwrap();
+    assert_eq!(unsafe { tiered.run(&mut instance) }, Ok(()));
+    instance.detach();
+    assert_eq!(instance.read_register(1), 2);
+
+    tiered.wait().unwrap();
+    let mut expected = Module::new(4).unwrap();
+    expected.set_resumable(true);
+    expected.set_code(&code).unwrap();
+    assert_eq!(tiered.module().code(), expected.code());
+}
+
+#[test]
+fn compiles_function_table() {
+    let code = Instruction::encode_all(&countdown()).unwrap();
+    let mut module = Module::new(code.len()).unwrap();
+    module.set_functions(&[0, 4]);
+    let mut tiered = Tiered::new(module, &code).unwrap();
+    tiered.compile();
+    tiered.wait().unwrap();
+    assert_eq!(tiered.module().source_map().functions(), 2);
+    let mut expected = Module::new(code.len()).unwrap();
+    expected.set_resumable(true);
+    expected.set_functions(&[0, 4]);
+    expected.set_code(&code).unwrap();
+    assert_eq!(tiered.module().code(), expected.code());
+
+    let mut module = Module::new(code.len()).unwrap();
+    module.set_functions(&[0, 6]);
+    assert_eq!(
+        Tiered::new(module, &code).err(),
+        Some(CompileError::InvalidFunction {
+            index: 1,
+            address: 6
+        })
+    );
+}
+
+#[test]
+fn errors() {
+    let code = Instruction::encode_all(&countdown()).unwrap();
+    let mut tiered = Tiered::new(Module::new(4).unwrap(), &code).unwrap();
+    assert_eq!(tiered.threshold(), Tiered::DEFAULT_THRESHOLD);
+    let store = PageStore::new(16);
+    let mut instance = instance(&store);
+    assert_eq!(
+        unsafe { tiered.run(&mut instance) },
+        Err("Instance not attached to module")
+    );
+    let mut other = Module::new(4).unwrap();
+    instance.attach(&mut other).unwrap();
+    assert_eq!(
+        unsafe { tiered.run(&mut instance) },
+        Err("Instance not attached to module")
+    );
+    instance.detach();
+}
@@ -0,0 +1,231 @@
+use crate::translator::{Options, PROLOGUE_WORDS, Translator};
+use crate::unwind::{self, Frame};
+use crate::{Instruction, Module};
+use std::collections::BTreeMap;
+
+/// CFA register and offset, and the CFA-relative slot of each saved register
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Row {
+    cfa: (u64, u64),
+    saved: BTreeMap<u8, i64>,
+}
+
+/// Code address range of an `.eh_frame` section's FDE, and its row at each
+/// of `offsets`
+fn rows(section: &[u8], offsets: &[usize]) -> ((u64, u64), Vec<Row>) {
+    let word = |at: usize| u32::from_le_bytes(section[at..at + 4].try_into().unwrap()) as usize;
+    let cie_len = word(0);
+    assert_eq!((4 + cie_len) % 8, 0);
+    assert_eq!(word(4), 0);
+    // Version, "zR", code factor 4, data factor -8, x30, 1 byte of absptr
+    assert_eq!(section[8..18], [1, b'z', b'R', 0, 4, 0x78, 30, 1, 0, 0x0C]);
+
+    let fde = 4 + cie_len;
+    let fde_len = word(fde);
+    assert_eq!((4 + fde_len) % 8, 0);
+    assert_eq!(word(fde + 4), fde + 4);
+    let long = |at: usize| u64::from_le_bytes(section[at..at + 8].try_into().unwrap());
+    let range = (long(fde + 8), long(fde + 16));
+    assert_eq!(section[fde + 24], 0);
+    assert_eq!(section[fde + 4 + fde_len..], [0; 4]);
+
+    let uleb = |at: &mut usize| {
+        let (mut value, mut shift) = (0, 0);
+        loop {
+            let byte = section[*at];
+            *at += 1;
+            value |= ((byte & 0x7F) as u64) << shift;
+            shift += 7;
+            if byte & 0x80 == 0 {
+                return value;
+            }
+        }
+    };
+    let mut row = Row {
+        cfa: (31, 0),
+        saved: BTreeMap::new(),
+    };
+    let mut location = 0;
+    let mut result = Vec::new();
+    let mut wanted = offsets.iter().peekable();
+    let mut at = fde + 25;
+    let end = fde + 4 + fde_len;
+    loop {
+        let op = if at < end { section[at] } else { 0 };
+        let delta = match op {
+            0x40..0x80 => (op & 0x3F) as usize,
+            0x02 => section[at + 1] as usize,
+            0x03 => u16::from_le_bytes(section[at + 1..at + 3].try_into().unwrap()) as usize,
+            0x04 => word(at + 1),
+            _ => 0,
+        };
+        while let Some(&&offset) = wanted.peek() {
+            if at < end && offset >= location + delta * 4 {
+                break;
+            }
+            result.push(row.clone());
+            wanted.next();
+        }
+        if at >= end {
+            return (range, result);
+        }
+        location += delta * 4;
+        at += 1;
+        match op {
+            0x40..0x80 => {}
+            0x02 => at += 1,
+            0x03 => at += 2,
+            0x04 => at += 4,
+            0x80..0xC0 => {
+                let slot = uleb(&mut at) as i64 * -8;
+                row.saved.insert(op & 0x3F, slot);
+            }
+            0xC0.. => {
+                row.saved.remove(&(op & 0x3F));
+            }
+            0x0C => row.cfa = (uleb(&mut at), uleb(&mut at)),
+            0x00 => {}
+            _ => panic!("unexpected CFA instruction {op:#x}"),
+        }
+    }
+}
+
+/// Row of a frame set up by the prologue
+fn framed() -> Row {
+    let mut saved = BTreeMap::from([(29, -96), (30, -88)]);
+    for reg in 19..=28 {
+        saved.insert(reg, -80 + 8 * (reg as i64 - 19));
+    }
+    Row {
+        cfa: (29, 96),
+        saved,
+    }
+}
+
+#[test]
+fn eh_frame_describes_each_state() {
+    let frames = [
+        (0, Frame::Entry),
+        (4, Frame::Pushed),
+        (28, Frame::Framed),
+        (400, Frame::Entry),
+        (404, Frame::Framed),
+    ];
+    let section = unwind::eh_frame(0x1234_5000, 600, &frames);
+    let (range, rows) = rows(&section, &[0, 4, 8, 28, 396, 400, 404, 596]);
+    assert_eq!(range, (0x1234_5000, 600));
+
+    let entry = Row {
+        cfa: (31, 0),
+        saved: BTreeMap::new(),
+    };
+    let pushed = Row {
+        cfa: (31, 96),
+        saved: BTreeMap::from([(29, -96), (30, -88)]),
+    };
+    assert_eq!(
+        rows,
+        [
+            entry.clone(),
+            pushed.clone(),
+            pushed,
+            framed(),
+            framed(),
+            entry,
+            framed(),
+            framed()
+        ]
+    );
+}
+
+#[test]
+fn eh_frame_advances_any_distance() {
+    let frames = [
+        (0, Frame::Framed),
+        (4 * 0x50, Frame::Entry),
+        (4 * 0x250, Frame::Framed),
+        (4 * 0x20250, Frame::Entry),
+    ];
+    let section = unwind::eh_frame(0, 4 * 0x20251, &frames);
+    let offsets = [0, 4 * 0x4F, 4 * 0x50, 4 * 0x24F, 4 * 0x250, 4 * 0x20250];
+    let (_, rows) = rows(&section, &offsets);
+    let states: Vec<_> = rows.iter().map(|row| row.cfa).collect();
+    assert_eq!(
+        states,
+        [(29, 96), (29, 96), (31, 0), (31, 0), (29, 96), (31, 0)]
+    );
+}
+
+#[test]
+fn frames_follow_entries_and_exit_stub() {
+    let instructions = [
+        Instruction::Addi {
+            rd: 5,
+            rs1: 0,
+            imm: 1,
+        },
+        Instruction::Jalr {
+            rd: 0,
+            rs1: 1,
+            imm: 0,
+        },
+    ];
+    let plain = Translator::translate(&instructions, &Options::default());
+    let ret = plain
+        .code
+        .iter()
+        .position(|&word| word == crate::arm64::RET)
+        .unwrap();
+    assert_eq!(
+        plain.frames,
+        [
+            (0, Frame::Entry),
+            (1, Frame::Pushed),
+            (7, Frame::Framed),
+            (ret, Frame::Entry),
+            (ret + 1, Frame::Framed)
+        ]
+    );
+
+    // Each entry runs its own prologue, and function entries come before it
+    let code = Translator::translate(
+        &instructions,
+        &Options {
+            resumable: true,
+            functions: &[4],
+            ..Options::default()
+        },
+    );
+    let resume = code.resume.unwrap();
+    let stub = code.functions[0];
+    let call = stub + 2;
+    assert_eq!(
+        code.frames[3..],
+        [
+            (ret, Frame::Entry),
+            (resume, Frame::Entry),
+            (resume + 1, Frame::Pushed),
+            (resume + 7, Frame::Framed),
+            (stub, Frame::Entry),
+            (call, Frame::Entry),
+            (call + 1, Frame::Pushed),
+            (call + 7, Frame::Framed)
+        ]
+    );
+    assert!(call + PROLOGUE_WORDS < code.code.len());
+}
+
+#[test]
+fn module_describes_its_code() {
+    let mut module = Module::new(8).unwrap();
+    assert!(module.unwind_info().is_empty());
+    // addi x1, x0, 1
+    module.set_code(&0x00100093u32.to_le_bytes()).unwrap();
+    let section = module.unwind_info();
+    let code = module.code();
+    let body = module.source_map().native(0).unwrap();
+    let (range, rows) = rows(&section, &[0, body]);
+    assert_eq!(range, (code.as_ptr() as u64, code.len() as u64));
+    assert_eq!(rows[0].cfa, (31, 0));
+    assert_eq!(rows[1], framed());
+}
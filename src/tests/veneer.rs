@@ -0,0 +1,97 @@
+use crate::arm64;
+use crate::veneer::{self, Branch, VENEER_WORDS};
+
+/// Veneer branching `offset` bytes from its start
+fn veneer(offset: i32) -> [u32; VENEER_WORDS] {
+    [
+        arm64::adr(16, 0),
+        arm64::ldrsw(17, 16, 16),
+        arm64::add_x(16, 16, 17),
+        arm64::br(16),
+        offset as u32,
+    ]
+}
+
+#[test]
+fn branches_in_reach_patched_in_place() {
+    let mut code = vec![arm64::NOP; 8];
+    let branches = [Branch { at: 1, target: 7 }, Branch { at: 6, target: 0 }];
+    let layout = veneer::link(&mut code, &branches, &[0, 4, 8], 28);
+    assert_eq!(code.len(), 8);
+    assert_eq!((code[1], code[6]), (arm64::b(24), arm64::b(-24)));
+    assert_eq!(layout.relocate(5), 5);
+    assert_eq!(layout.veneers(), 0);
+}
+
+#[test]
+fn far_branch_takes_veneer() {
+    let mut code = vec![arm64::NOP; 16];
+    let branches = [Branch { at: 1, target: 15 }];
+    let layout = veneer::link(&mut code, &branches, &[0, 4, 8, 12, 16], 32);
+    // An island at the nearest point, branched over by the code before it
+    assert_eq!(code.len(), 16 + 1 + VENEER_WORDS);
+    assert_eq!(code[0], arm64::b(24));
+    assert_eq!(code[1..6], veneer((21 - 1) * 4));
+    assert_eq!((layout.relocate(0), layout.relocate(15)), (6, 21));
+    assert_eq!(code[7], arm64::b(-24));
+    assert_eq!(layout.veneers(), 1);
+}
+
+#[test]
+fn islands_move_branches_out_of_reach() {
+    let mut code = vec![arm64::NOP; 16];
+    // The first is out of reach; its island then moves the second's target
+    let branches = [Branch { at: 5, target: 15 }, Branch { at: 2, target: 8 }];
+    let layout = veneer::link(&mut code, &branches, &[0, 4, 8, 12, 16], 32);
+    let island = 1 + VENEER_WORDS;
+    assert_eq!(code.len(), 16 + 2 * island);
+    let relocated = [0, 2, 4, 5, 8, 15].map(|offset| layout.relocate(offset));
+    assert_eq!(relocated, [6, 8, 16, 17, 20, 27]);
+    // Each takes the veneer in the island nearest to it
+    assert_eq!(code[1..6], veneer((20 - 1) * 4));
+    assert_eq!(code[8], arm64::b((1 - 8) * 4));
+    assert_eq!(code[11..16], veneer((27 - 11) * 4));
+    assert_eq!(code[17], arm64::b((11 - 17) * 4));
+    assert_eq!(layout.veneers(), 2);
+}
+
+#[test]
+fn veneers_shared_by_target() {
+    let mut code = vec![arm64::NOP; 32];
+    let branches = [
+        Branch { at: 1, target: 30 },
+        Branch { at: 2, target: 30 },
+        Branch { at: 3, target: 29 },
+    ];
+    let layout = veneer::link(&mut code, &branches, &[0, 16, 32], 64);
+    assert_eq!(code.len(), 32 + 1 + 2 * VENEER_WORDS);
+    assert_eq!(layout.veneers(), 2);
+    assert_eq!(code[0], arm64::b((1 + 2 * VENEER_WORDS) as i32 * 4));
+    // Veneers in order of first use, at 1 for word 30 and 6 for word 29
+    assert_eq!(code[1..6], veneer((41 - 1) * 4));
+    assert_eq!(code[6..11], veneer((40 - 6) * 4));
+    assert_eq!(
+        code[12..15],
+        [-11, -12, -8].map(|words| arm64::b(words * 4))
+    );
+}
+
+#[cfg(target_arch = "aarch64")]
+#[test]
+fn veneer_runs() {
+    use crate::ExecutableBuffer;
+    // B to code returning 2, out of reach, falling through to return 1
+    let mut code = vec![arm64::NOP; 16];
+    code[1..3].copy_from_slice(&[arm64::movz(0, 1, 0), arm64::RET]);
+    code[12..14].copy_from_slice(&[arm64::movz(0, 2, 0), arm64::RET]);
+    let branches = [Branch { at: 0, target: 12 }];
+    veneer::link(&mut code, &branches, &[0, 1, 3, 12, 14], 16);
+    assert_eq!(code.len(), 16 + 1 + VENEER_WORDS);
+    let bytes: Vec<u8> = code.iter().flat_map(|word| word.to_le_bytes()).collect();
+    let mut buffer = ExecutableBuffer::new(4096).unwrap();
+    buffer
+        .write(|memory| memory[..bytes.len()].copy_from_slice(&bytes))
+        .unwrap();
+    let function: extern "C" fn() -> u32 = unsafe { std::mem::transmute(buffer.as_ptr()) };
+    assert_eq!(function(), 2);
+}
@@ -0,0 +1,59 @@
+use crate::isa::{self, ENCODINGS, Encoding, Format};
+use crate::verify::{self, VerifyError};
+use crate::{EncodeError, Extension};
+
+#[test]
+fn all_encodings() {
+    let checked = verify::check().unwrap();
+    assert!(checked > ENCODINGS.len() * 10, "{}", checked);
+}
+
+#[test]
+fn every_entry_checked() {
+    for encoding in ENCODINGS {
+        assert!(
+            verify::check_encoding(encoding).unwrap() > 0,
+            "{}",
+            encoding.mnemonic
+        );
+    }
+}
+
+#[test]
+fn wrong_mnemonic() {
+    // An entry claiming the ADD encoding is SUB
+    let encoding = Encoding {
+        mnemonic: "sub",
+        ..*isa::lookup("add").unwrap()
+    };
+    assert_eq!(
+        verify::check_encoding(&encoding),
+        Err(VerifyError::Mnemonic(0x00000033, "sub"))
+    );
+}
+
+#[test]
+fn unsupported_skipped() {
+    // Reserved system encodings never decode, so nothing is checked
+    let encoding = Encoding {
+        mnemonic: "reserved",
+        mask: 0xFFFFFFFF,
+        value: 0x00200073,
+        format: Format::I,
+        extension: Extension::I,
+        operands: &[],
+    };
+    assert_eq!(verify::check_encoding(&encoding), Ok(0));
+}
+
+#[test]
+fn display() {
+    assert_eq!(
+        VerifyError::Roundtrip(0x33, 0x13).to_string(),
+        "0x00000033 re-encodes as 0x00000013"
+    );
+    assert_eq!(
+        VerifyError::EncodeError(0x33, EncodeError::InvalidRegister("rd", 32)).to_string(),
+        "0x00000033 decodes but fails to encode: Invalid register value for rd: 32 (must be 0-31)"
+    );
+}
@@ -0,0 +1,118 @@
+use crate::{Instruction, InstructionKind, InstructionVisitor};
+
+/// Records the kind reported by each callback
+#[derive(Default)]
+struct Recorder(Vec<InstructionKind>);
+
+impl InstructionVisitor for Recorder {
+    fn visit_arithmetic(&mut self, _: &Instruction) {
+        self.0.push(InstructionKind::Arithmetic);
+    }
+    fn visit_load(&mut self, _: &Instruction) {
+        self.0.push(InstructionKind::Load);
+    }
+    fn visit_store(&mut self, _: &Instruction) {
+        self.0.push(InstructionKind::Store);
+    }
+    fn visit_branch(&mut self, _: &Instruction) {
+        self.0.push(InstructionKind::Branch);
+    }
+    fn visit_jump(&mut self, _: &Instruction) {
+        self.0.push(InstructionKind::Jump);
+    }
+    fn visit_system(&mut self, _: &Instruction) {
+        self.0.push(InstructionKind::System);
+    }
+    fn visit_mul(&mut self, _: &Instruction) {
+        self.0.push(InstructionKind::Mul);
+    }
+    fn visit_div(&mut self, _: &Instruction) {
+        self.0.push(InstructionKind::Div);
+    }
+    fn visit_vector(&mut self, _: &Instruction) {
+        self.0.push(InstructionKind::Vector);
+    }
+    fn visit_float(&mut self, _: &Instruction) {
+        self.0.push(InstructionKind::Float);
+    }
+    fn visit_unsupported(&mut self, _: &Instruction) {
+        self.0.push(InstructionKind::Unsupported);
+    }
+}
+
+/// Only overrides branches
+#[derive(Default)]
+struct Branches(Vec<u8>);
+
+impl InstructionVisitor for Branches {
+    fn visit_branch(&mut self, instruction: &Instruction) {
+        if let Instruction::Beq { rs1, .. } = instruction {
+            self.0.push(*rs1);
+        }
+    }
+}
+
+#[test]
+fn dispatch() {
+    // add, lw, sw, beq, jal, ecall, mul, div, vadd.vv, fadd.h, unsupported
+    let words = [
+        0x003100B3, 0x00832283, 0x00532423, 0x00208463, 0x008000EF, 0x00000073, 0x023100B3,
+        0x023140B3, 0x022180D7, 0x04208053, 0xFFFFFFFF,
+    ];
+    let code: Vec<Instruction> = words.iter().map(|&w| Instruction::decode(w)).collect();
+    let mut recorder = Recorder::default();
+    recorder.visit_all(&code);
+    assert_eq!(
+        recorder.0,
+        vec![
+            InstructionKind::Arithmetic,
+            InstructionKind::Load,
+            InstructionKind::Store,
+            InstructionKind::Branch,
+            InstructionKind::Jump,
+            InstructionKind::System,
+            InstructionKind::Mul,
+            InstructionKind::Div,
+            InstructionKind::Vector,
+            InstructionKind::Float,
+            InstructionKind::Unsupported,
+        ]
+    );
+}
+
+#[test]
+fn defaults() {
+    let code = [
+        Instruction::Addi {
+            rd: 1,
+            rs1: 0,
+            imm: 1,
+        },
+        Instruction::Beq {
+            rs1: 1,
+            rs2: 0,
+            imm: 8,
+        },
+        Instruction::Ecall,
+        Instruction::Bne {
+            rs1: 2,
+            rs2: 0,
+            imm: 8,
+        },
+        Instruction::Beq {
+            rs1: 3,
+            rs2: 0,
+            imm: -8,
+        },
+    ];
+    let mut branches = Branches::default();
+    branches.visit_all(&code);
+    assert_eq!(branches.0, vec![1, 3]);
+}
+
+#[test]
+fn single() {
+    let mut recorder = Recorder::default();
+    recorder.visit(&Instruction::Ebreak);
+    assert_eq!(recorder.0, vec![InstructionKind::System]);
+}
@@ -0,0 +1,283 @@
+//! Tiered execution: interpret cold code, compile hot code in the background
+//!
+//! A `Tiered` module starts out without compiled code, running guest code
+//! with the `Interpreter` one basic block at a time. Each interpreted entry
+//! into a block bumps its counter, and the first block to reach the
+//! threshold starts compiling on a background thread. The interpreter keeps
+//! running meanwhile, and once the compiled code is ready it is installed
+//! into the module and execution continues in it through
+//! `Instance::resume()` at the next block entry.
+//!
+//! Hot blocks are not compiled on their own: the compiler only produces
+//! code for whole modules, with one dispatch table and source map, so the
+//! first hot block compiles the whole module. What tiering saves is the
+//! wait before the first instruction runs, not compile work; a large module
+//! takes as long to compile as with `Module::set_code()`, only off the
+//! interpreter's path. The background thread owns a copy of the
+//! instructions and hands the code back through `join()`, so no guest state
+//! crosses threads (see `docs/DEVELOPMENT.md`).
+//!
+//! The interpreter tier behaves like compiled code: blocks are charged gas on
+//! entry (the rest of the block when entered in the middle), ECALLs go to the
+//! instance's handler, the instructions compiled code does not support stop
+//! with an unsupported instruction error, and jumps outside the module return
+//! with the target as the PC.
+//!
+//! # Example
+//! ```
+//! use jigs::{Instance, Memory, Module, PageStore, Tiered};
+//!
+//! let code = 0x00100093u32.to_le_bytes(); // addi x1, x0, 1
+//! let mut tiered = Tiered::new(Module::new(4).unwrap(), &code).unwrap();
+//! tiered.set_threshold(0);
+//! let store = PageStore::new(16);
+//! let mut instance = Instance::new(Memory::new(&store, 16, 4));
+//! instance.attach(tiered.module_mut()).unwrap();
+//! unsafe { tiered.run(&mut instance) }.unwrap();
+//! assert_eq!((instance.read_register(1), instance.pc()), (1, 4));
+//! instance.detach();
+//! ```
+
+use crate::{
+    Instance, Instruction, Interpreter, Trap,
+    cache::Compiled,
+    compiler::{Compiler, SourceMap},
+    module::{CompileError, Module},
+    translator,
+};
+use std::{ops::Range, thread::JoinHandle};
+
+/// A module whose code is interpreted until it gets hot
+pub struct Tiered {
+    /// Module receiving the compiled code (boxed so attached instances can
+    /// point at it)
+    module: Box<Module>,
+    /// Decoded RISC-V instructions
+    instructions: Vec<Instruction>,
+    /// Basic blocks, in address order
+    blocks: Vec<Range<usize>>,
+    /// Index of the block holding each instruction
+    block_of: Vec<usize>,
+    /// Interpreted entries into each block
+    entries: Vec<u32>,
+    /// Entries into one block that start compiling; 0 never does
+    threshold: u32,
+    /// Interpreter for cold code
+    interpreter: Interpreter,
+    /// Background compilation, until installed
+    compiling: Option<JoinHandle<Compiled>>,
+}
+
+impl Tiered {
+    /// Default number of entries into one block that starts compiling
+    pub const DEFAULT_THRESHOLD: u32 = 100;
+
+    /// Decode `code` for tiered execution in `module`
+    ///
+    /// The module's decoder, optimization level, block counters, base
+    /// address, constant strategy and function table are used, and it is made resumable.
+    /// Any code it already holds is dropped, and nothing is compiled until a
+    /// block gets hot.
+    ///
+    /// # Errors
+    /// Returns error if instances are attached, the code jumps into the
+    /// middle of an instruction, or a function is not at an instruction
+    pub fn new(mut module: Module, code: &[u8]) -> Result<Self, CompileError> {
+        if module.instance_count != 0 {
+            return Err(CompileError::InstancesAttached);
+        }
+        module.set_resumable(true);
+        // Code from an earlier `set_code()` would be run instead of `code`
+        module.install(&Compiled {
+            code: Vec::new(),
+            source_map: SourceMap::default(),
+            instructions: Vec::new(),
+        })?;
+
+        let decoder = *module.decoder();
+        let instructions = decoder.decode_words(code);
+        Compiler::check_targets(&instructions)?;
+        Compiler::check_functions(&instructions, module.base(), module.functions())?;
+        let blocks: Vec<_> = Compiler::basic_blocks(&instructions)
+            .into_iter()
+            .map(|block| block.start..block.end)
+            .collect();
+        let mut block_of = vec![0; instructions.len()];
+        for (index, block) in blocks.iter().enumerate() {
+            block_of[block.clone()].fill(index);
+        }
+
+        Ok(Self {
+            module: Box::new(module),
+            entries: vec![0; blocks.len()],
+            instructions,
+            blocks,
+            block_of,
+            threshold: Self::DEFAULT_THRESHOLD,
+            interpreter: Interpreter::with_decoder(decoder),
+            compiling: None,
+        })
+    }
+
+    /// Set the number of entries into one block that starts compiling
+    ///
+    /// A threshold of 0 keeps the code interpreted.
+    pub fn set_threshold(&mut self, threshold: u32) {
+        self.threshold = threshold;
+    }
+
+    /// Get the number of entries into one block that starts compiling
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    /// Get the module, holding the compiled code once installed
+    pub fn module(&self) -> &Module {
+        &self.module
+    }
+
+    /// Get the module mutably, to attach instances
+    pub fn module_mut(&mut self) -> &mut Module {
+        &mut self.module
+    }
+
+    /// Number of interpreted entries into the basic block holding `pc`
+    ///
+    /// Returns 0 for PCs outside the module.
+    pub fn entries(&self, pc: u32) -> u32 {
+        self.index(pc)
+            .map_or(0, |index| self.entries[self.block_of[index]])
+    }
+
+    /// Check if compiled code is being produced in the background
+    pub fn compiling(&self) -> bool {
+        self.compiling.is_some()
+    }
+
+    /// Check if compiled code has been installed
+    pub fn compiled(&self) -> bool {
+        !self.module.code().is_empty()
+    }
+
+    /// Start compiling the whole module in the background now, unless
+    /// already started
+    pub fn compile(&mut self) {
+        if self.compiling.is_some() || self.compiled() {
+            return;
+        }
+        let instructions = self.instructions.clone();
+        let opt_level = self.module.opt_level();
+        let block_counters = self.module.block_counters();
+        let base = self.module.base();
+        let constants = self.module.constants();
+        let threads = self.module.compile_threads();
+        let functions = self.module.functions().to_vec();
+        self.compiling = Some(std::thread::spawn(move || {
+            let mut compiler = Compiler::with_opt_level(opt_level);
+            compiler.set_resumable(true);
+            compiler.set_block_counters(block_counters);
+            compiler.set_base(base);
+            compiler.set_constants(constants);
+            compiler.set_threads(threads);
+            compiler.set_functions(&functions);
+            let mut code = vec![0; Compiler::estimate_size_with(&instructions, functions.len())];
+            let (size, source_map) = compiler.compile_mapped(&instructions, &mut code);
+            code.truncate(size);
+            Compiled {
+                code,
+                source_map,
+                instructions,
+            }
+        }));
+    }
+
+    /// Wait for the background compilation, if any, and install its code
+    ///
+    /// # Errors
+    /// Returns error if the code could not be installed
+    pub fn wait(&mut self) -> Result<(), CompileError> {
+        let Some(handle) = self.compiling.take() else {
+            return Ok(());
+        };
+        let compiled = handle
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+        self.module.install(&compiled)
+    }
+
+    /// Run from the instance's PC until execution stops
+    ///
+    /// Returns like `Instance::call_function()`, interpreting until the
+    /// compiled code is ready and running that from then on.
+    ///
+    /// # Safety
+    /// Once installed, the compiled code must be valid ARM64 instructions for
+    /// the host
+    pub unsafe fn run(&mut self, instance: &mut Instance) -> Result<(), &'static str> {
+        if !instance.attached_to(&self.module) {
+            return Err("Instance not attached to module");
+        }
+        loop {
+            if self
+                .compiling
+                .as_ref()
+                .is_some_and(|handle| handle.is_finished())
+            {
+                self.wait().map_err(|_| "Compilation failed")?;
+            }
+            if self.compiled() {
+                return unsafe { instance.resume() };
+            }
+
+            let Some(index) = self.index(instance.pc()) else {
+                return Ok(());
+            };
+            let block = self.block_of[index];
+            let end = self.blocks[block].end;
+            let cost = (end - index) as u64;
+            if instance.gas() < cost {
+                return Err("Out of gas");
+            }
+            instance.set_gas(instance.gas() - cost);
+
+            self.entries[block] = self.entries[block].saturating_add(1);
+            if self.entries[block] == self.threshold {
+                self.compile();
+            }
+
+            for instruction in &self.instructions[index..end] {
+                if !translator::supported(instruction) {
+                    return Err("Unsupported instruction");
+                }
+                let next = instance.pc().wrapping_add(4);
+                match self.interpreter.execute(instance, instruction) {
+                    Ok(()) => {}
+                    Err(Trap::Ecall) => {
+                        let Some(mut handler) = instance.take_ecall_handler() else {
+                            return Err("Environment call");
+                        };
+                        let resume = handler(instance);
+                        instance.set_ecall_handler(handler);
+                        if !resume {
+                            return Err("Environment call");
+                        }
+                        instance.set_pc(next);
+                    }
+                    Err(Trap::Ebreak) => return Err("Breakpoint"),
+                    Err(Trap::Illegal(_)) => return Err("Unsupported instruction"),
+                    Err(_) => return Err("Memory access fault"),
+                }
+                if instance.pc() != next {
+                    break;
+                }
+            }
+        }
+    }
+
+    /// Index of the instruction at `pc`, if inside the module
+    fn index(&self, pc: u32) -> Option<usize> {
+        let offset = pc.wrapping_sub(self.module.base());
+        let index = (offset / 4) as usize;
+        (offset % 4 == 0 && index < self.instructions.len()).then_some(index)
+    }
+}
@@ -0,0 +1,1644 @@
+//! Per-instruction RISC-V to ARM64 translation
+//!
+//! Every guest instruction expands to a variable number of ARM64 instructions,
+//! at most `MAX_WORDS`. The guest instruction at index `i` has guest PC
+//! `base + 4 * i`, for the guest address `base` the code is loaded at, and
+//! `Translation::offsets` records where its code starts. Everything PC-based
+//! (AUIPC and link values, branch targets, exit PCs and the dispatch range
+//! check) uses these guest PCs.
+//!
+//! # Layout
+//! A prologue saves the frame and callee-saved registers and loads the mapped
+//! guest registers, then comes the code for each guest instruction, an end
+//! sequence that exits when execution runs off the end, and a shared exit stub
+//! that writes the mapped guest registers back, records the PC and exit
+//! reason in the `Context` and returns.
+//!
+//! A resumable translation adds a second entry after the exit stub, which
+//! runs the same prologue, then enters the code for the PC in the `Context`
+//! through the dispatch routine.
+//!
+//! With a function table, each distinct function PC gets an entry after
+//! that, moving the PC into w9 and branching to a shared call entry: the
+//! prologue again, then the dispatch routine, which charges the rest of the
+//! block when a function starts inside one.
+//!
+//! # Registers
+//! - x19 holds the `Context` pointer and x20 the guest register file
+//! - Guest registers chosen by `RegisterMap` live in x21-x28; the rest are
+//!   spilled to the register file at `[x20, #4 * r]`
+//! - x0 reads as WZR and writes to x0 are dropped
+//! - w9-w11 are scratch; on a jump to the exit stub w9 and w10 hold the guest
+//!   PC and exit reason
+//! - Loads and stores walk the page tables with x0 holding the `Memory`
+//!   pointer, w1 the address and x16/x17 as scratch, and call the `Context`
+//!   helpers through x16 with the usual AAPCS64 argument registers when the
+//!   inline access cannot be used
+//! - Host registers holding guest values always have their upper 32 bits
+//!   clear, as every write goes through a W register or is shifted down
+//!
+//! # Frame
+//! Entry points are AAPCS64 functions taking the `Context` pointer in x0.
+//! The prologue pushes a frame record (x29 pointing at it, so host
+//! unwinders and profilers can walk through compiled code), saves x19-x28,
+//! then sets up the reserved registers: x19 from x0, and x20 from the
+//! `Context`. The memory base and gas counter are reached through x19, so
+//! they need no registers of their own. Every path out of the code,
+//! including the dispatch routine and the resume entry, goes through the
+//! exit stub, the only RET, which restores them all in reverse. SP stays
+//! 16-byte aligned in between, so the helpers and ECALL trampoline are
+//! called as ordinary C functions. x18, reserved on some platforms, and the
+//! SIMD registers are never touched.
+//!
+//! The translator records where each entry's frame changes state, for the
+//! DWARF unwind table `unwind::eh_frame()` builds.
+//!
+//! # Exits
+//! Branches and JAL to a PC inside the module branch straight to its code;
+//! other targets exit with `EXIT_JUMP` and the target PC. JALR branches to a
+//! shared dispatch routine that looks the target up in a table of instruction
+//! offsets emitted after it, exiting the same way when the target is outside
+//! the module or misaligned.
+//! Counters and unsupported instructions exit with `EXIT_ILLEGAL` at their
+//! own PC.
+//!
+//! EBREAK always exits with `EXIT_EBREAK` at its own PC and never falls
+//! through to the code after it.
+//!
+//! ECALL writes the mapped registers back and calls the `Context` ECALL
+//! trampoline, which runs the instance's handler, then reloads them and
+//! either continues or exits with `EXIT_ECALL` as the handler decides.
+//!
+//! # Gas
+//! Instructions are grouped into `Compiler::basic_blocks`, which end at every
+//! ECALL and EBREAK, so a stop at one charges nothing past it. Each block
+//! subtracts its instruction count from `Context::gas` on entry, exiting with
+//! `EXIT_OUT_OF_GAS` at its own PC, gas untouched, when not enough is left.
+//! JALR can land inside a block, so the dispatch table also records the gas
+//! for the rest of the block at each instruction.
+//!
+//! # Position independence
+//! The code refers to itself only through PC-relative branches, the ADR
+//! of the dispatch table, whose entries are offsets from themselves, and
+//! literal loads from the pool, and reaches everything else through the
+//! `Context`. It holds no absolute
+//! addresses, so a compiled buffer can be copied to, saved and reloaded at
+//! any address. Debug builds check that every PC-relative reference lands
+//! inside the code.
+//!
+//! # Constants
+//! Guest constants are moved into registers with MOVZ/MOVK/MOVN, taking two
+//! instructions when both halves are needed. With a literal pool, those
+//! take one LDR (literal) instead, from a pool of distinct values appended
+//! after everything else and shared by the whole module: shorter code when
+//! values repeat, at the cost of a load. LDR reaches 1MB, so modules whose
+//! code could be larger keep the moves.
+//!
+//! # Block counters
+//! When counting, each block increments its own `u64` in the `Context`
+//! counter array after charging its gas, so only entries through the
+//! block's first instruction are counted, not JALR or resume entries
+//! landing inside it. Counters are numbered in address order.
+//!
+//! # Fusion
+//! When optimizing, the pairs `fusion::find` reports are translated together
+//! at the first instruction's slot, and the second emits nothing: LUI+ADDI as
+//! one constant, SLLI+SRLI as UBFX, SLT/SLTU+BEQ/BNE branching on the flags of
+//! the comparison, and AUIPC+JALR as a direct branch charging the rest of the
+//! target's block, as the dispatch routine would. Pairs whose second
+//! instruction starts a block or is a fused jump target are left alone, and
+//! with a dispatch routine each second instruction also gets code out of line
+//! for JALR to land on, branching to the instruction after the pair.
+//!
+//! # Fixups
+//! Branches to guest instructions and to the exit stub are emitted as B
+//! placeholders and recorded as fixups, since the target's ARM64 offset is
+//! only known once everything before it has been expanded. A final pass,
+//! `veneer::link`, patches them, so forward and backward branches are handled
+//! alike, and sends any beyond the reach of B through veneers in islands
+//! between instructions. The dispatch table and literal pool follow. Short
+//! branches over an exit sequence within one instruction are patched as soon
+//! as the sequence is emitted.
+//!
+//! # Regions
+//! Apart from its fixups, the code for an instruction does not depend on
+//! where it lands, so large inputs are split into regions of whole basic
+//! blocks translated on separate threads. Each region's offsets and fixups
+//! are relocated by where it lands after the prologue and the regions before
+//! it, and the fixup pass then patches branches between regions like any
+//! other, giving the same code as translating on one thread.
+//!
+//! # Memory
+//! Loads and stores find the page through the `Memory` L1 and L2 tables and
+//! its permission bytes, and access it directly. Anything the walk cannot
+//! handle takes the helper call: mapped devices (flagged in the `Context`),
+//! addresses not aligned to the access size (which may cross a page), missing
+//! L2 tables or pages (unallocated memory, host buffers, guard regions),
+//! denied permissions and shared pages. The helpers mirror the interpreter, so
+//! both paths give the same results.
+//!
+//! The walk is skipped for the page last found by a load, or by a store,
+//! kept in the `Context` TLB entries: an aligned access to that page goes
+//! straight to it, and a walk that succeeds replaces the entry. The entries
+//! start empty on entry and are emptied after each ECALL, so they never
+//! outlive a change to the memory.
+//!
+//! # Division
+//! ARM64 SDIV/UDIV already give the RISC-V results for signed overflow
+//! (`i32::MIN / -1 == i32::MIN`), and their zero quotient for division by zero
+//! makes `rs1 - q * rs2` the RISC-V remainder (`rs1`). Only DIV and DIVU by
+//! zero need fixing up, to all ones.
+
+use crate::{
+    Instruction,
+    arm64::{self, Condition, FP, LR, SP, ZR},
+    compiler::Compiler,
+    fusion::{self, FusionKind},
+    instance::{
+        Context, EXIT_EBREAK, EXIT_ECALL, EXIT_FAULT, EXIT_ILLEGAL, EXIT_JUMP, EXIT_OUT_OF_GAS,
+        LOAD_FAULT_BIT,
+    },
+    memory::{
+        L1_INDEX_SHIFT, L2_INDEX_BITS, L2_INDEX_SHIFT, Memory, PAGE_OFFSET_BITS, PERMISSION_READ,
+        PERMISSION_SHARED, PERMISSION_WRITE, UNMAPPED_L2_TABLE, UNMAPPED_PAGE,
+    },
+    regalloc::{HOST_REGISTERS, RegisterMap},
+    unwind::Frame,
+    veneer::{self, Branch, VENEER_WORDS},
+};
+use std::{
+    collections::HashMap,
+    mem::offset_of,
+    ops::Range,
+    thread,
+    time::{Duration, Instant},
+};
+
+/// Maximum ARM64 instructions emitted for one guest instruction (a store
+/// starting a basic block, with its gas charge, block counter, the TLB
+/// check, inline page table walk and the helper call)
+pub(crate) const MAX_WORDS: usize = 54 + COUNTER_WORDS;
+
+/// Maximum ARM64 instructions in a block counter increment
+pub(crate) const COUNTER_WORDS: usize = 7;
+
+/// Most branches resolved in the fixup pass for one guest instruction (or
+/// any other unit of code veneer islands can go between)
+pub(crate) const MAX_BRANCHES: usize = 3;
+
+/// Maximum ARM64 instructions in a gas charge
+const CHARGE_WORDS: usize = 10;
+
+/// Maximum ARM64 instructions exiting to the host or continuing at a guest PC
+const EXIT_SEQUENCE_WORDS: usize = 4;
+
+/// ARM64 instructions in the prologue
+pub(crate) const PROLOGUE_WORDS: usize = 9 + HOST_REGISTERS.len();
+
+/// ARM64 instructions in the exit stub
+const EXIT_WORDS: usize = 9 + HOST_REGISTERS.len();
+
+/// ARM64 instructions in the resume entry: a prologue, the PC load and the
+/// branch to the dispatch routine
+const RESUME_WORDS: usize = PROLOGUE_WORDS + 2;
+
+/// Maximum ARM64 instructions in a function entry: the PC moved into T0 and
+/// the branch to the shared call entry
+const FUNCTION_WORDS: usize = 3;
+
+/// ARM64 instructions in the call entry shared by function entries: a
+/// prologue and the branch to the dispatch routine
+const CALL_WORDS: usize = PROLOGUE_WORDS + 1;
+
+/// Maximum ARM64 instructions in the dispatch routine, excluding its table
+const DISPATCH_WORDS: usize = 23;
+
+/// Fewest instructions translated on a thread of their own
+pub(crate) const MIN_REGION: usize = 4096;
+
+/// Largest code, in bytes, whose every LDR (literal) reaches the pool
+const LITERAL_RANGE: usize = 1 << 20;
+
+/// Words per dispatch table entry: code offset and gas cost
+const DISPATCH_ENTRY_WORDS: usize = 2;
+
+/// Callee-saved register pairs kept in the frame, after the frame record
+pub(crate) const SAVED_PAIRS: [(u8, u8); 5] = [
+    (CONTEXT, REGISTERS),
+    (HOST_REGISTERS[0], HOST_REGISTERS[1]),
+    (HOST_REGISTERS[2], HOST_REGISTERS[3]),
+    (HOST_REGISTERS[4], HOST_REGISTERS[5]),
+    (HOST_REGISTERS[6], HOST_REGISTERS[7]),
+];
+
+/// Stack frame size: frame record plus `SAVED_PAIRS`, a multiple of 16 so
+/// SP stays aligned for helper calls
+pub(crate) const FRAME_SIZE: i32 = 16 * (1 + SAVED_PAIRS.len() as i32);
+
+/// Register holding the `Context` pointer
+const CONTEXT: u8 = 19;
+/// Register holding the guest register file pointer
+const REGISTERS: u8 = 20;
+/// First scratch register; holds the PC on exit
+const T0: u8 = 9;
+/// Second scratch register; holds the exit reason on exit
+const T1: u8 = 10;
+/// Third scratch register
+const T2: u8 = 11;
+/// Intra-procedure-call scratch register used for helper calls and veneers
+pub(crate) const IP0: u8 = 16;
+/// Second intra-procedure-call scratch register
+pub(crate) const IP1: u8 = 17;
+
+/// Upper bound on the bytes of ARM64 code generated for `count` guest
+/// instructions
+pub(crate) fn code_size(count: usize) -> usize {
+    // Each instruction's code, dispatch table entry and function entry, then
+    // the fixed parts
+    let words = count
+        .saturating_mul(MAX_WORDS + DISPATCH_ENTRY_WORDS + FUNCTION_WORDS)
+        .saturating_add(
+            MAX_WORDS + PROLOGUE_WORDS + EXIT_WORDS + DISPATCH_WORDS + RESUME_WORDS + CALL_WORDS,
+        );
+    with_veneers(words, count).saturating_mul(4)
+}
+
+/// Upper bound on the bytes of ARM64 code generated for `instructions` with
+/// `functions` function entries, at any guest address, with any other
+/// options and optimization passes
+///
+/// Sums the bound of each instruction's class rather than assuming the
+/// longest sequence for all of them. The passes only rewrite instructions
+/// into classes with bounds no larger and keep the basic blocks, but may
+/// form new fused pairs, so every adjacent pair gets room for fusing.
+pub(crate) fn max_size(instructions: &[Instruction], functions: usize) -> usize {
+    use Instruction::*;
+    let blocks = Compiler::basic_blocks(instructions).len();
+    let mut words: usize = instructions.iter().map(max_words).sum();
+    words += blocks * (CHARGE_WORDS + COUNTER_WORDS);
+    // A fused pair's code replaces the first's, and the second gets a copy
+    // out of line for JALR, ending in a branch back. Constant folding can
+    // turn almost any instruction into either half of LUI+ADDI or SLLI+SRLI,
+    // which fuse into no more than the first's bound.
+    for pair in instructions.windows(2) {
+        let kind = match pair {
+            [Auipc { .. }, Jalr { .. }] => FusionKind::FarJump,
+            [Slt { .. } | Sltu { .. }, Beq { .. } | Bne { .. }] => FusionKind::CompareBranch,
+            _ => FusionKind::LoadImmediate,
+        };
+        words += (max_fused_words(kind) + 1).saturating_sub(max_words(&pair[0]));
+    }
+    if functions > 0 {
+        words = words.saturating_add(functions.saturating_mul(FUNCTION_WORDS) + CALL_WORDS);
+    }
+    let words = words
+        .saturating_add(instructions.len() * DISPATCH_ENTRY_WORDS)
+        .saturating_add(
+            EXIT_SEQUENCE_WORDS + PROLOGUE_WORDS + EXIT_WORDS + DISPATCH_WORDS + RESUME_WORDS,
+        );
+    with_veneers(words, instructions.len()).saturating_mul(4)
+}
+
+/// `words` of code for `count` guest instructions, plus room for veneer
+/// islands if the code could outgrow the reach of B
+///
+/// Islands go between units of code (each instruction, its copy out of
+/// line, its function entry and five stubs), and take a word to branch over
+/// them and a veneer for each of the branches around them.
+fn with_veneers(words: usize, count: usize) -> usize {
+    if words.saturating_mul(4) <= veneer::REACH {
+        return words;
+    }
+    let points = count.saturating_mul(3).saturating_add(6);
+    words.saturating_add(points.saturating_mul(1 + VENEER_WORDS * MAX_BRANCHES))
+}
+
+/// Maximum ARM64 instructions for `instruction`, excluding the gas charge
+/// and block counter of a block it starts
+fn max_words(instruction: &Instruction) -> usize {
+    use Instruction::*;
+    match instruction {
+        // Sources loaded, the operation and the result stored
+        Add { .. }
+        | Sub { .. }
+        | Sll { .. }
+        | Srl { .. }
+        | Sra { .. }
+        | Xor { .. }
+        | Or { .. }
+        | And { .. }
+        | Mul { .. } => 4,
+        Mulh { .. } | Mulhsu { .. } | Mulhu { .. } => 6,
+        Div { .. } | Divu { .. } | Rem { .. } | Remu { .. } => 6,
+        Slt { .. } | Sltu { .. } => 5,
+        Addi { .. } | Slli { .. } | Srli { .. } | Srai { .. } | Lui { .. } | Auipc { .. } => 3,
+        Xori { .. } | Ori { .. } | Andi { .. } => 5,
+        Slti { .. } | Sltiu { .. } => 6,
+        // Address, TLB check, page table walk, access and the helper call
+        Lb { .. } | Lh { .. } | Lw { .. } | Lbu { .. } | Lhu { .. } => 43,
+        Sb { .. } | Sh { .. } | Sw { .. } => 44,
+        Beq { .. } | Bne { .. } | Blt { .. } | Bge { .. } | Bltu { .. } | Bgeu { .. } => {
+            4 + EXIT_SEQUENCE_WORDS
+        }
+        Jal { .. } => 3 + EXIT_SEQUENCE_WORDS,
+        Jalr { .. } => 8,
+        // Mapped registers written back and reloaded around the trampoline
+        Ecall => 10 + 2 * HOST_REGISTERS.len(),
+        _ => EXIT_SEQUENCE_WORDS,
+    }
+}
+
+/// Maximum ARM64 instructions for a fused pair of `kind`
+fn max_fused_words(kind: FusionKind) -> usize {
+    match kind {
+        FusionKind::LoadImmediate | FusionKind::ZeroExtend => 3,
+        // Both results, then landing like JALR
+        FusionKind::FarJump => 6 + CHARGE_WORDS + EXIT_SEQUENCE_WORDS,
+        FusionKind::CompareBranch => 6 + EXIT_SEQUENCE_WORDS,
+    }
+}
+
+/// ARM64 code for a sequence of guest instructions
+pub(crate) struct Translation {
+    /// ARM64 instructions
+    pub(crate) code: Vec<u32>,
+    /// Word offset of the code for each guest instruction, followed by the
+    /// offset of the end sequence
+    pub(crate) offsets: Vec<usize>,
+    /// Word offset of the resume entry, if translated resumable
+    pub(crate) resume: Option<usize>,
+    /// Word offset of the entry of each function
+    pub(crate) functions: Vec<usize>,
+    /// Word offset where each state of the frame starts, ascending
+    pub(crate) frames: Vec<(usize, Frame)>,
+    /// Guest PC of the block each counter belongs to, if translated with
+    /// block counters
+    pub(crate) counters: Vec<u32>,
+    /// Loads and stores of spilled guest registers emitted
+    pub(crate) spills: usize,
+    /// Veneers inserted by the fixup pass
+    pub(crate) veneers: usize,
+    /// Time spent in the fixup pass
+    pub(crate) link: Duration,
+}
+
+/// Branch emitted as a NOP at a word offset, with the encoder to patch it
+/// once its byte offset is known
+type Placeholder = (usize, fn(i32) -> u32);
+
+/// Destination of a branch resolved in the fixup pass
+#[derive(Debug, Clone, Copy)]
+enum Label {
+    /// Code for the guest instruction at this index
+    Instruction(usize),
+    /// Shared exit stub
+    Exit,
+    /// Indirect jump dispatch routine
+    Dispatch,
+    /// Call entry shared by function entries
+    Call,
+}
+
+/// What `Translator::translate()` compiles besides the instructions
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Options<'a> {
+    /// Guest address of the first instruction
+    pub(crate) base: u32,
+    /// Whether to fold comparisons with zero and fuse pairs
+    pub(crate) optimize: bool,
+    /// Whether to add the resume entry
+    pub(crate) resumable: bool,
+    /// Whether to count basic block entries
+    pub(crate) count_blocks: bool,
+    /// Whether to load constants from a pool when the code is small enough
+    pub(crate) literal_pool: bool,
+    /// Guest PC of each function to add an entry for
+    pub(crate) functions: &'a [u32],
+    /// Most threads translating at once (0 meaning 1)
+    pub(crate) threads: usize,
+}
+
+/// B placeholder patched once its target's offset is known
+#[derive(Debug, Clone, Copy)]
+struct Fixup {
+    /// Word offset of the branch
+    at: usize,
+    /// Branch destination
+    target: Label,
+}
+
+/// LDR (literal) placeholder patched once the pool is laid out
+#[derive(Debug, Clone, Copy)]
+struct Literal {
+    /// Word offset of the load
+    at: usize,
+    /// Constant to load
+    value: u32,
+}
+
+/// Translates a sequence of guest instructions into ARM64 words
+///
+/// The analysis of the whole module is borrowed, so translators for
+/// separate regions can run on separate threads.
+pub(crate) struct Translator<'a> {
+    /// Emitted ARM64 instructions
+    code: Vec<u32>,
+    /// Number of guest instructions
+    count: usize,
+    /// Guest address of the first instruction
+    base: u32,
+    /// Guest registers kept in host registers
+    registers: &'a RegisterMap,
+    /// Branches awaiting their target offsets
+    fixups: Vec<Fixup>,
+    /// Loads awaiting their pool entries, if constants come from a pool
+    literals: Option<Vec<Literal>>,
+    /// Whether each instruction starts a basic block
+    leaders: &'a [bool],
+    /// Gas for each instruction and the rest of its basic block
+    costs: &'a [u32],
+    /// Counter index of each block leader, if counting block entries
+    counters: Option<&'a [usize]>,
+    /// Whether to fold comparisons with zero into the branch
+    optimize: bool,
+    /// Word offset where each state of the frame starts
+    frames: Vec<(usize, Frame)>,
+    /// Loads and stores of spilled guest registers emitted
+    spills: usize,
+}
+
+impl<'a> Translator<'a> {
+    /// Translate all instructions with the given `Options`
+    ///
+    /// The instructions are split into regions for up to `threads` threads.
+    pub(crate) fn translate(instructions: &[Instruction], options: &Options) -> Translation {
+        let &Options {
+            base,
+            optimize,
+            resumable,
+            count_blocks,
+            literal_pool,
+            functions,
+            threads,
+        } = options;
+        let count = instructions.len();
+        let mut leaders = vec![false; count];
+        let mut costs = vec![0; count];
+        for block in Compiler::basic_blocks(instructions) {
+            leaders[block.start] = true;
+            for (cost, left) in costs[block.start..block.end]
+                .iter_mut()
+                .zip((1..=block.end - block.start).rev())
+            {
+                *cost = left as u32;
+            }
+        }
+        let fused = if optimize {
+            fusions(instructions, &leaders, base)
+        } else {
+            vec![None; count]
+        };
+        let registers = RegisterMap::allocate(instructions);
+        let mut numbers = vec![0; count];
+        let mut counters = Vec::new();
+        if count_blocks {
+            for index in (0..count).filter(|&index| leaders[index]) {
+                numbers[index] = counters.len();
+                counters.push(base.wrapping_add(index as u32 * 4));
+            }
+        }
+        let pool = literal_pool && max_size(instructions, functions.len()) <= LITERAL_RANGE;
+        let new = |capacity| Translator {
+            code: Vec::with_capacity(capacity),
+            count,
+            base,
+            registers: &registers,
+            fixups: Vec::new(),
+            literals: pool.then(Vec::new),
+            leaders: &leaders,
+            costs: &costs,
+            counters: count_blocks.then_some(&numbers[..]),
+            optimize,
+            frames: Vec::new(),
+            spills: 0,
+        };
+
+        // Translate each region on its own, then relocate its offsets and
+        // fixups to where it lands after the prologue and the regions before
+        let ranges = regions(&leaders, threads);
+        let translate = |range: &Range<usize>| {
+            let mut region = new(range.len() * MAX_WORDS);
+            let offsets = region.region(instructions, &fused, range.clone());
+            (region, offsets)
+        };
+        let regions: Vec<_> = match ranges.len() {
+            1 => ranges.iter().map(translate).collect(),
+            _ => thread::scope(|scope| {
+                let handles: Vec<_> = ranges
+                    .iter()
+                    .map(|range| scope.spawn(|| translate(range)))
+                    .collect();
+                handles
+                    .into_iter()
+                    .map(|handle| handle.join().unwrap())
+                    .collect()
+            }),
+        };
+        let mut translator = new(code_size(count) / 4);
+        let mut offsets = Vec::with_capacity(count + 1);
+        translator.prologue();
+        for (region, region_offsets) in regions {
+            let base = translator.code.len();
+            offsets.extend(region_offsets.iter().map(|offset| base + offset));
+            translator
+                .fixups
+                .extend(region.fixups.iter().map(|fixup| Fixup {
+                    at: base + fixup.at,
+                    target: fixup.target,
+                }));
+            if let (Some(literals), Some(region)) = (&mut translator.literals, region.literals) {
+                literals.extend(region.iter().map(|literal| Literal {
+                    at: base + literal.at,
+                    value: literal.value,
+                }));
+            }
+            translator.code.extend(region.code);
+            translator.spills += region.spills;
+        }
+        offsets.push(translator.code.len());
+        // Veneer islands can go between any units of code
+        let mut points = offsets.clone();
+        translator.exit(translator.pc(count), EXIT_JUMP);
+        let exit = translator.code.len();
+        points.push(exit);
+        translator.exit_stub();
+        let mut dispatch = None;
+        let mut resume = None;
+        if resumable {
+            resume = Some(translator.code.len());
+            points.push(translator.code.len());
+            translator.prologue();
+            translator
+                .code
+                .push(arm64::ldr(T0, CONTEXT, offset_of!(Context, pc) as u32));
+            translator.branch_to(Label::Dispatch);
+        }
+        // Each distinct function PC gets an entry moving it into T0 for the
+        // shared call entry, which dispatches on it like JALR
+        let mut function_entries = Vec::with_capacity(functions.len());
+        let mut call = None;
+        if !functions.is_empty() {
+            translator.frame(Frame::Entry);
+            let mut stubs = HashMap::new();
+            for &pc in functions {
+                debug_assert!(translator.index(pc).is_some());
+                let stub = *stubs.entry(pc).or_insert_with(|| {
+                    let stub = translator.code.len();
+                    points.push(stub);
+                    translator.code.extend(arm64::mov_imm(T0, pc));
+                    translator.branch_to(Label::Call);
+                    stub
+                });
+                function_entries.push(stub);
+            }
+            call = Some(translator.code.len());
+            points.push(translator.code.len());
+            translator.prologue();
+            translator.branch_to(Label::Dispatch);
+        }
+        let mut entries = Vec::new();
+        if translator
+            .fixups
+            .iter()
+            .any(|fixup| matches!(fixup.target, Label::Dispatch))
+        {
+            // JALR can land on the second instruction of a fused pair, so give
+            // each one its own code out of line, continuing after the pair
+            entries = offsets[..count].to_vec();
+            for (index, kind) in fused.iter().enumerate() {
+                if kind.is_some() {
+                    let second = index + 1;
+                    entries[second] = translator.code.len();
+                    points.push(translator.code.len());
+                    translator.instruction(translator.pc(second), &instructions[second]);
+                    translator.branch_to(Label::Instruction(second + 1));
+                    debug_assert!(
+                        translator.code.len() - entries[second]
+                            <= max_words(&instructions[second]) + 1
+                    );
+                }
+            }
+            dispatch = Some(translator.code.len());
+            points.push(translator.code.len());
+            translator.dispatch();
+        } else {
+            points.push(translator.code.len());
+        }
+        points.dedup();
+
+        let branches: Vec<_> = translator
+            .fixups
+            .iter()
+            .map(|fixup| Branch {
+                at: fixup.at,
+                target: match fixup.target {
+                    Label::Instruction(index) => offsets[index],
+                    Label::Exit => exit,
+                    Label::Dispatch => dispatch.unwrap(),
+                    Label::Call => call.unwrap(),
+                },
+            })
+            .collect();
+        let start = Instant::now();
+        let layout = veneer::link(&mut translator.code, &branches, &points, veneer::REACH);
+        let link = start.elapsed();
+        let mut frames = std::mem::take(&mut translator.frames);
+        for offset in offsets
+            .iter_mut()
+            .chain(&mut resume)
+            .chain(&mut function_entries)
+            .chain(&mut entries)
+            .chain(frames.iter_mut().map(|(at, _)| at))
+        {
+            *offset = layout.relocate(*offset);
+        }
+        let end = translator.code.len();
+        if dispatch.is_some() {
+            translator.table(&entries);
+        }
+        if let Some(literals) = translator.literals.take() {
+            let literals: Vec<_> = literals
+                .iter()
+                .map(|literal| Literal {
+                    at: layout.relocate(literal.at),
+                    value: literal.value,
+                })
+                .collect();
+            translator.pool(&literals);
+        }
+        debug_assert!(position_independent(&translator.code, end));
+
+        Translation {
+            code: translator.code,
+            offsets,
+            resume,
+            functions: function_entries,
+            frames,
+            counters,
+            spills: translator.spills,
+            veneers: layout.veneers(),
+            link,
+        }
+    }
+
+    /// Translate the instructions in `range`, returning the word offset of
+    /// each one's code
+    ///
+    /// Fused pairs must not straddle the ends of the range.
+    fn region(
+        &mut self,
+        instructions: &[Instruction],
+        fused: &[Option<FusionKind>],
+        range: Range<usize>,
+    ) -> Vec<usize> {
+        let mut offsets = Vec::with_capacity(range.len());
+        for index in range {
+            let start = self.code.len();
+            offsets.push(start);
+            let fixups = self.fixups.len();
+            if index > 0 && fused[index - 1].is_some() {
+                // Second of a fused pair, translated with the first
+                continue;
+            }
+            let pc = self.pc(index);
+            let mut bound = 0;
+            if self.leaders[index] {
+                bound += CHARGE_WORDS + COUNTER_WORDS;
+                self.charge(pc, self.costs[index]);
+                if let Some(counters) = self.counters {
+                    self.count(counters[index]);
+                }
+            }
+            let instruction = &instructions[index];
+            match fused[index] {
+                Some(kind) => {
+                    self.fuse(pc, kind, instruction, &instructions[index + 1]);
+                    bound += max_fused_words(kind);
+                }
+                None => {
+                    self.instruction(pc, instruction);
+                    bound += max_words(instruction);
+                }
+            }
+            debug_assert!(self.code.len() - start <= bound);
+            debug_assert!(self.fixups.len() - fixups <= MAX_BRANCHES);
+            assert!(
+                self.code.len() - start <= MAX_WORDS,
+                "code for instruction {} exceeds {} words",
+                index,
+                MAX_WORDS
+            );
+        }
+        offsets
+    }
+
+    /// Branch to the code for the guest PC in T0 through the table of offsets
+    /// `table()` appends after the routine
+    ///
+    /// PCs outside the module or not on an instruction boundary exit with
+    /// `EXIT_JUMP`. Each table entry holds the byte offset from the entry to
+    /// the instruction's code and the gas to charge before branching there:
+    /// nothing for block leaders, which charge themselves, and the rest of the
+    /// block for instructions inside one. Entries are two words, so the entry
+    /// for a guest PC is at twice its offset from the first instruction.
+    fn dispatch(&mut self) {
+        let start = self.code.len();
+        // Offset of the PC from the first instruction, in T2 unless that is
+        // the PC itself, which the misses still need
+        let offset = match self.base {
+            0 => T0,
+            base => {
+                self.code.extend(arm64::mov_imm(T1, base));
+                self.code.push(arm64::sub(T2, T0, T1));
+                T2
+            }
+        };
+        self.code
+            .extend(arm64::mov_imm(T1, (self.count as u32).wrapping_mul(4)));
+        self.code.push(arm64::cmp(offset, T1));
+        let bounds = self.code.len();
+        self.code.extend([arm64::NOP, arm64::NOP, arm64::NOP]);
+        let address = self.code.len();
+        self.code.extend([
+            arm64::NOP,
+            arm64::add_x_lsl(IP0, IP0, offset, 1),
+            arm64::ldr(IP1, IP0, 4),
+            arm64::ldr_x(T1, CONTEXT, offset_of!(Context, gas) as u32),
+            arm64::subs_x(T1, T1, IP1),
+        ]);
+        let charge = self.code.len();
+        self.code.extend([
+            arm64::NOP,
+            arm64::str_x(T1, CONTEXT, offset_of!(Context, gas) as u32),
+            arm64::ldrsw(IP1, IP0, 0),
+            arm64::add_x(IP0, IP0, IP1),
+            arm64::br(IP0),
+        ]);
+        let miss = self.code.len();
+        self.code.push(arm64::movz(T1, EXIT_JUMP as u16, 0));
+        self.branch_to(Label::Exit);
+        let out = self.code.len();
+        self.code.push(arm64::movz(T1, EXIT_OUT_OF_GAS as u16, 0));
+        self.branch_to(Label::Exit);
+        debug_assert!(self.code.len() - start <= DISPATCH_WORDS);
+
+        // The table follows
+        let table = self.code.len();
+        self.code[bounds] = arm64::b_cond(Condition::Hs, (miss - bounds) as i32 * 4);
+        // Both low bits, since an odd offset would index between table entries
+        self.code[bounds + 1] = arm64::tbnz(offset, 0, (miss - bounds - 1) as i32 * 4);
+        self.code[bounds + 2] = arm64::tbnz(offset, 1, (miss - bounds - 2) as i32 * 4);
+        self.code[address] = arm64::adr(IP0, (table - address) as i32 * 4);
+        self.code[charge] = arm64::b_cond(Condition::Lo, (out - charge) as i32 * 4);
+    }
+
+    /// Append the dispatch routine's table of the code `offsets` of each
+    /// instruction, right after the routine
+    fn table(&mut self, offsets: &[usize]) {
+        for (index, &offset) in offsets.iter().enumerate() {
+            let entry = self.code.len();
+            let cost = if self.leaders[index] {
+                0
+            } else {
+                self.costs[index]
+            };
+            self.code
+                .extend([((offset as i32 - entry as i32) * 4) as u32, cost]);
+        }
+    }
+
+    /// Append each distinct literal value once, in order of first use, and
+    /// point the loads at their entries
+    fn pool(&mut self, literals: &[Literal]) {
+        let mut entries = HashMap::new();
+        for literal in literals {
+            let entry = *entries.entry(literal.value).or_insert_with(|| {
+                self.code.push(literal.value);
+                self.code.len() - 1
+            });
+            let offset = (entry - literal.at) as i32 * 4;
+            let rt = (self.code[literal.at] & 0x1F) as u8;
+            self.code[literal.at] = arm64::ldr_literal(rt, offset);
+        }
+    }
+
+    /// Charge `cost` gas on entry to the basic block at `pc`, exiting with
+    /// `EXIT_OUT_OF_GAS` and the gas untouched if not enough is left
+    fn charge(&mut self, pc: u32, cost: u32) {
+        let gas = offset_of!(Context, gas) as u32;
+        self.code.push(arm64::ldr_x(IP0, CONTEXT, gas));
+        if cost < 4096 {
+            self.code.push(arm64::subs_imm_x(IP0, IP0, cost as u16));
+        } else {
+            self.code.extend(arm64::mov_imm(IP1, cost));
+            self.code.push(arm64::subs_x(IP0, IP0, IP1));
+        }
+        self.check(pc, EXIT_OUT_OF_GAS, |skip| {
+            arm64::b_cond(Condition::Hs, skip)
+        });
+        self.code.push(arm64::str_x(IP0, CONTEXT, gas));
+    }
+
+    /// Increment the `Context` block counter at `index`
+    fn count(&mut self, index: usize) {
+        let counters = offset_of!(Context, counters) as u32;
+        self.code.push(arm64::ldr_x(IP1, CONTEXT, counters));
+        let offset = index as u32 * 8;
+        let offset = if offset < 32768 {
+            offset
+        } else {
+            self.code.extend(arm64::mov_imm(IP0, offset));
+            self.code.push(arm64::add_x(IP1, IP1, IP0));
+            0
+        };
+        self.code.extend([
+            arm64::ldr_x(IP0, IP1, offset),
+            arm64::add_imm_x(IP0, IP0, 1),
+            arm64::str_x(IP0, IP1, offset),
+        ]);
+    }
+
+    /// Guest PC of the instruction at `index`
+    fn pc(&self, index: usize) -> u32 {
+        self.base.wrapping_add(index as u32 * 4)
+    }
+
+    /// Index of the instruction at guest PC `pc`, if inside the module
+    fn index(&self, pc: u32) -> Option<usize> {
+        let offset = pc.wrapping_sub(self.base);
+        let index = (offset / 4) as usize;
+        (offset % 4 == 0 && index < self.count).then_some(index)
+    }
+
+    /// Emit a B to `target`, patched in the fixup pass
+    fn branch_to(&mut self, target: Label) {
+        self.fixups.push(Fixup {
+            at: self.code.len(),
+            target,
+        });
+        self.code.push(arm64::NOP);
+    }
+
+    /// Record that the frame is in state `frame` from the next instruction
+    fn frame(&mut self, frame: Frame) {
+        let at = self.code.len();
+        if self.frames.last().is_some_and(|&(last, _)| last == at) {
+            self.frames.pop();
+        }
+        self.frames.push((at, frame));
+    }
+
+    /// Save the frame, load the context pointers and the mapped registers
+    fn prologue(&mut self) {
+        let start = self.code.len();
+        self.frame(Frame::Entry);
+        self.code.push(arm64::stp_x_pre(FP, LR, SP, -FRAME_SIZE));
+        self.frame(Frame::Pushed);
+        self.code.push(arm64::add_imm_x(FP, SP, 0));
+        for (slot, &(first, second)) in SAVED_PAIRS.iter().enumerate() {
+            self.code
+                .push(arm64::stp_x(first, second, SP, 16 * (1 + slot as i32)));
+        }
+        self.frame(Frame::Framed);
+        self.code.extend([
+            arm64::mov_x(CONTEXT, 0),
+            arm64::ldr_x(REGISTERS, CONTEXT, offset_of!(Context, registers) as u32),
+        ]);
+        for (reg, host) in self.registers.mapped() {
+            self.code.push(arm64::ldr(host, REGISTERS, reg as u32 * 4));
+        }
+        debug_assert_eq!(self.code.len() - start, PROLOGUE_WORDS);
+    }
+
+    /// Write back the mapped registers, store the PC in T0 and exit reason in
+    /// T1, restore and return
+    fn exit_stub(&mut self) {
+        for (reg, host) in self.registers.mapped() {
+            self.code.push(arm64::str(host, REGISTERS, reg as u32 * 4));
+        }
+        self.code.extend([
+            arm64::str(T0, CONTEXT, offset_of!(Context, pc) as u32),
+            arm64::str(T1, CONTEXT, offset_of!(Context, exit) as u32),
+        ]);
+        for (slot, &(first, second)) in SAVED_PAIRS.iter().enumerate().rev() {
+            self.code
+                .push(arm64::ldp_x(first, second, SP, 16 * (1 + slot as i32)));
+        }
+        self.code.push(arm64::ldp_x_post(FP, LR, SP, FRAME_SIZE));
+        self.frame(Frame::Entry);
+        self.code.push(arm64::RET);
+        // Code after it that is not an entry runs in the frame
+        self.frame(Frame::Framed);
+    }
+
+    /// Exit to the host with the given PC and reason
+    fn exit(&mut self, pc: u32, reason: u32) {
+        self.code.extend(arm64::mov_imm(T0, pc));
+        self.code.push(arm64::movz(T1, reason as u16, 0));
+        self.branch_to(Label::Exit);
+    }
+
+    /// Continue at guest PC `target`, branching to its code when it has some
+    fn jump(&mut self, target: u32) {
+        if let Some(index) = self.index(target) {
+            self.branch_to(Label::Instruction(index));
+        } else {
+            self.exit(target, EXIT_JUMP);
+        }
+    }
+
+    /// Get a register holding guest register `reg`, loading it into `scratch`
+    /// if it is spilled
+    ///
+    /// x0 gives ZR, which only reads as zero in register operand positions.
+    fn source(&mut self, reg: u8, scratch: u8) -> u8 {
+        if reg == 0 {
+            return ZR;
+        }
+        match self.registers.host(reg) {
+            Some(host) => host,
+            None => {
+                self.code
+                    .push(arm64::ldr(scratch, REGISTERS, reg as u32 * 4));
+                self.spills += 1;
+                scratch
+            }
+        }
+    }
+
+    /// Get the register to compute guest register `reg` into, `scratch` if it
+    /// is spilled; pair with `commit`
+    fn target(&self, reg: u8, scratch: u8) -> u8 {
+        self.registers.host(reg).unwrap_or(scratch)
+    }
+
+    /// Store a result computed into `target(reg, ..)` if `reg` is spilled
+    fn commit(&mut self, reg: u8, value: u8) {
+        if reg != 0 && self.registers.host(reg).is_none() {
+            self.code.push(arm64::str(value, REGISTERS, reg as u32 * 4));
+            self.spills += 1;
+        }
+    }
+
+    /// Copy `value` into guest register `reg`, dropping writes to x0
+    fn assign(&mut self, reg: u8, value: u8) {
+        match self.registers.host(reg) {
+            Some(host) => self.code.push(arm64::mov(host, value)),
+            None => self.commit(reg, value),
+        }
+    }
+
+    /// rd = rn + imm for a sign-extended 12-bit immediate; `rn` must not be ZR
+    fn add_imm(&mut self, rd: u8, rn: u8, imm: i32) {
+        if imm < 0 {
+            self.code
+                .push(arm64::sub_imm(rd, rn, imm.unsigned_abs() as u16));
+        } else if imm > 0 {
+            self.code.push(arm64::add_imm(rd, rn, imm as u16));
+        } else if rd != rn {
+            self.code.push(arm64::mov(rd, rn));
+        }
+    }
+
+    /// Compute guest register `rs1` + imm into `rd`
+    fn offset(&mut self, rd: u8, rs1: u8, imm: i32) {
+        if rs1 == 0 {
+            self.code.extend(arm64::mov_imm(rd, imm as u32));
+        } else {
+            let base = self.source(rs1, rd);
+            self.add_imm(rd, base, imm);
+        }
+    }
+
+    /// rd = op(rs1, rs2)
+    fn register_op(&mut self, rd: u8, rs1: u8, rs2: u8, op: fn(u8, u8, u8) -> u32) {
+        if rd == 0 {
+            return;
+        }
+        let lhs = self.source(rs1, T0);
+        let rhs = self.source(rs2, T1);
+        let result = self.target(rd, T0);
+        self.code.push(op(result, lhs, rhs));
+        self.commit(rd, result);
+    }
+
+    /// rd = op(rs1, imm)
+    fn immediate_op(&mut self, rd: u8, rs1: u8, imm: i32, op: fn(u8, u8, u8) -> u32) {
+        if rd == 0 {
+            return;
+        }
+        let lhs = self.source(rs1, T0);
+        self.code.extend(arm64::mov_imm(T1, imm as u32));
+        let result = self.target(rd, T0);
+        self.code.push(op(result, lhs, T1));
+        self.commit(rd, result);
+    }
+
+    /// rd = (rs1 < rhs) under `condition`
+    fn set_less_than(&mut self, rd: u8, rs1: u8, rhs: u8, condition: Condition) {
+        let lhs = self.source(rs1, T0);
+        self.code.push(arm64::cmp(lhs, rhs));
+        let result = self.target(rd, T0);
+        self.code.push(arm64::cset(result, condition));
+        self.commit(rd, result);
+    }
+
+    /// rd = shift(rs1, shamt)
+    fn shift_imm(&mut self, rd: u8, rs1: u8, shamt: u8, op: fn(u8, u8, u8) -> u32) {
+        if rd == 0 {
+            return;
+        }
+        let value = self.source(rs1, T0);
+        let result = self.target(rd, T0);
+        self.code.push(op(result, value, shamt & 0x1F));
+        self.commit(rd, result);
+    }
+
+    /// rd = high word of the 64-bit product of rs1 and rs2
+    fn multiply_high(&mut self, rd: u8, rs1: u8, rs2: u8, signed1: bool, signed2: bool) {
+        if rd == 0 {
+            return;
+        }
+        let lhs = self.source(rs1, T0);
+        let rhs = self.source(rs2, T1);
+        let result = self.target(rd, T0);
+        match (signed1, signed2) {
+            (true, true) => self.code.push(arm64::smull(result, lhs, rhs)),
+            (false, false) => self.code.push(arm64::umull(result, lhs, rhs)),
+            // Guest values are zero-extended, so only rs1 needs extending
+            _ => self
+                .code
+                .extend([arm64::sxtw(T2, lhs), arm64::mul_x(result, T2, rhs)]),
+        }
+        self.code.push(arm64::lsr_32_x(result, result));
+        self.commit(rd, result);
+    }
+
+    /// rd = rs1 / rs2 or rs1 % rs2 with RISC-V results for division by zero
+    fn divide(&mut self, rd: u8, rs1: u8, rs2: u8, signed: bool, remainder: bool) {
+        if rd == 0 {
+            return;
+        }
+        let lhs = self.source(rs1, T0);
+        let rhs = self.source(rs2, T1);
+        let result = self.target(rd, T0);
+        let divide = if signed { arm64::sdiv } else { arm64::udiv };
+        self.code.push(divide(T2, lhs, rhs));
+        if remainder {
+            self.code.push(arm64::msub(result, T2, rhs, lhs));
+        } else {
+            self.code.push(arm64::cmp(rhs, ZR));
+            self.code.push(arm64::csinv(result, T2, ZR, Condition::Ne));
+        }
+        self.commit(rd, result);
+    }
+
+    /// Guest load through the load helper; `funct3` selects width and sign
+    fn load(&mut self, pc: u32, rd: u8, rs1: u8, imm: i32, funct3: u16) {
+        self.offset(1, rs1, imm);
+        self.code
+            .push(arm64::ldr_x(0, CONTEXT, offset_of!(Context, memory) as u32));
+        let access = match funct3 {
+            0 => arm64::ldrsb_reg(0, IP0, IP1),
+            1 => arm64::ldrsh_reg(0, IP0, IP1),
+            4 => arm64::ldrb_reg(0, IP0, IP1),
+            5 => arm64::ldrh_reg(0, IP0, IP1),
+            _ => arm64::ldr_reg(0, IP0, IP1),
+        };
+        let done = self.access(1 << (funct3 & 3), false, access);
+        self.code.extend([
+            arm64::movz(2, funct3, 0),
+            arm64::ldr_x(IP0, CONTEXT, offset_of!(Context, load) as u32),
+            arm64::blr(IP0),
+        ]);
+        self.check(pc, EXIT_FAULT, |skip| arm64::tbz(0, LOAD_FAULT_BIT, skip));
+        self.code[done] = arm64::b((self.code.len() - done) as i32 * 4);
+        self.assign(rd, 0);
+    }
+
+    /// Guest store through the store helper; `funct3` selects width
+    fn store(&mut self, pc: u32, rs1: u8, rs2: u8, imm: i32, funct3: u16) {
+        self.offset(1, rs1, imm);
+        let value = self.source(rs2, 2);
+        if value != 2 {
+            self.code.push(arm64::mov(2, value));
+        }
+        self.code
+            .push(arm64::ldr_x(0, CONTEXT, offset_of!(Context, memory) as u32));
+        let access = match funct3 {
+            0 => arm64::strb_reg(2, IP0, IP1),
+            1 => arm64::strh_reg(2, IP0, IP1),
+            _ => arm64::str_reg(2, IP0, IP1),
+        };
+        let done = self.access(1 << (funct3 & 3), true, access);
+        self.code.extend([
+            arm64::movz(3, funct3, 0),
+            arm64::ldr_x(IP0, CONTEXT, offset_of!(Context, store) as u32),
+            arm64::blr(IP0),
+        ]);
+        self.check(pc, EXIT_FAULT, |skip| arm64::cbz(0, skip));
+        self.code[done] = arm64::b((self.code.len() - done) as i32 * 4);
+    }
+
+    /// Call the ECALL trampoline, exiting with `EXIT_ECALL` unless the handler
+    /// asks to resume
+    fn ecall(&mut self, pc: u32) {
+        for (reg, host) in self.registers.mapped() {
+            self.code.push(arm64::str(host, REGISTERS, reg as u32 * 4));
+        }
+        self.code.push(arm64::mov_x(0, CONTEXT));
+        self.code.extend(arm64::mov_imm(1, pc));
+        self.code.extend([
+            arm64::ldr_x(IP0, CONTEXT, offset_of!(Context, ecall) as u32),
+            arm64::blr(IP0),
+        ]);
+        for (reg, host) in self.registers.mapped() {
+            self.code.push(arm64::ldr(host, REGISTERS, reg as u32 * 4));
+        }
+        self.check(pc, EXIT_ECALL, |skip| arm64::cbnz(0, skip));
+    }
+
+    /// Perform the `len`-byte access at the address in w1 inline with
+    /// `access`, which addresses the page at x16 with the offset in x17, with
+    /// the `Memory` pointer in x0
+    ///
+    /// The page comes from the `Context` TLB entry for the kind of access
+    /// when it matches, and from the page table walk otherwise, which then
+    /// refills the entry. Addresses not aligned to `len` (so the access stays
+    /// in one page) and everything the walk refuses take the helper call,
+    /// which follows; returns the placeholder branch over it, to be patched
+    /// once it is emitted.
+    fn access(&mut self, len: u32, write: bool, access: u32) -> usize {
+        let mut slow: Vec<Placeholder> = Vec::new();
+        if len > 1 {
+            slow.push((self.code.len(), |offset| arm64::tbnz(1, 0, offset)));
+            self.code.push(arm64::NOP);
+        }
+        if len > 2 {
+            slow.push((self.code.len(), |offset| arm64::tbnz(1, 1, offset)));
+            self.code.push(arm64::NOP);
+        }
+
+        let (host, page) = if write {
+            (
+                offset_of!(Context, tlb_write.host),
+                offset_of!(Context, tlb_write.page),
+            )
+        } else {
+            (
+                offset_of!(Context, tlb_read.host),
+                offset_of!(Context, tlb_read.page),
+            )
+        };
+        self.code.extend([
+            arm64::ldr(IP0, CONTEXT, page as u32),
+            arm64::lsr_imm(IP1, 1, PAGE_OFFSET_BITS as u8),
+            arm64::cmp(IP0, IP1),
+        ]);
+        let miss = self.code.len();
+        self.code.push(arm64::NOP);
+        self.code.push(arm64::ldr_x(IP0, CONTEXT, host as u32));
+        let hit = self.code.len();
+        self.code
+            .extend([arm64::ubfx(IP1, 1, 0, PAGE_OFFSET_BITS as u8), access]);
+        let done = self.code.len();
+        self.code.push(arm64::NOP);
+
+        self.code[miss] = arm64::b_cond(Condition::Ne, (self.code.len() - miss) as i32 * 4);
+        slow.extend(self.walk(write));
+        self.code.extend([
+            arm64::str_x(IP0, CONTEXT, host as u32),
+            arm64::lsr_imm(IP1, 1, PAGE_OFFSET_BITS as u8),
+            arm64::str(IP1, CONTEXT, page as u32),
+            arm64::b((hit as i32 - self.code.len() as i32 - 3) * 4),
+        ]);
+        for (at, encode) in slow {
+            self.code[at] = encode((self.code.len() - at) as i32 * 4);
+        }
+        done
+    }
+
+    /// Walk the page tables for an access at the address in w1, with the
+    /// `Memory` pointer in x0
+    ///
+    /// Leaves the host address of the page in x16. Returns the branches to
+    /// take the helper call instead: when devices are mapped, there is no L2
+    /// table or page, or the page permissions deny the access. Stores are
+    /// also refused for shared pages.
+    fn walk(&mut self, write: bool) -> Vec<Placeholder> {
+        let mut slow: Vec<Placeholder> = Vec::new();
+        let mut branch = |code: &mut Vec<u32>, encode: fn(i32) -> u32| {
+            slow.push((code.len(), encode));
+            code.push(arm64::NOP);
+        };
+
+        self.code.push(arm64::ldr(
+            IP0,
+            CONTEXT,
+            offset_of!(Context, devices) as u32,
+        ));
+        branch(&mut self.code, |offset| arm64::cbnz(IP0, offset));
+
+        // L1 entry: index of the L2 table, or UNMAPPED_L2_TABLE. The upper
+        // half of x1 is clear, since the address was written through w1.
+        self.code.extend([
+            arm64::add_x_lsr(IP0, 0, 1, L1_INDEX_SHIFT as u8),
+            arm64::ldrb(IP0, IP0, offset_of!(Memory, l1_table) as u32),
+            arm64::cmp_imm(IP0, UNMAPPED_L2_TABLE as u16),
+        ]);
+        branch(&mut self.code, |offset| {
+            arm64::b_cond(Condition::Eq, offset)
+        });
+
+        // x16 = index of the L2 entry across all L2 tables
+        self.code.extend([
+            arm64::ubfx(IP1, 1, L2_INDEX_SHIFT as u8, L2_INDEX_BITS as u8),
+            arm64::add_lsl(IP0, IP1, IP0, L2_INDEX_BITS as u8),
+            arm64::ldr_x(IP1, 0, offset_of!(Memory, page_permissions) as u32),
+            arm64::ldrb_reg(IP1, IP1, IP0),
+        ]);
+        if write {
+            branch(&mut self.code, |offset| {
+                arm64::tbz(IP1, PERMISSION_WRITE.trailing_zeros() as u8, offset)
+            });
+            branch(&mut self.code, |offset| {
+                arm64::tbnz(IP1, PERMISSION_SHARED.trailing_zeros() as u8, offset)
+            });
+        } else {
+            branch(&mut self.code, |offset| {
+                arm64::tbz(IP1, PERMISSION_READ.trailing_zeros() as u8, offset)
+            });
+        }
+
+        // L2 entry: page index, or UNMAPPED_PAGE, the only value that carries
+        // into bit 16 when incremented
+        debug_assert_eq!(UNMAPPED_PAGE, u16::MAX);
+        self.code.extend([
+            arm64::ldr_x(IP1, 0, offset_of!(Memory, l2_tables) as u32),
+            arm64::ldrh_index(IP1, IP1, IP0),
+            arm64::add_imm(IP0, IP1, 1),
+        ]);
+        branch(&mut self.code, |offset| arm64::tbnz(IP0, 16, offset));
+
+        self.code.extend([
+            arm64::ldr_x(IP0, 0, offset_of!(Memory, page_memory) as u32),
+            arm64::add_x_lsl(IP0, IP0, IP1, PAGE_OFFSET_BITS as u8),
+        ]);
+        slow
+    }
+
+    /// Exit with `reason` unless `success` branches over the exit
+    fn check(&mut self, pc: u32, reason: u32, success: impl FnOnce(i32) -> u32) {
+        let branch = self.code.len();
+        self.code.push(arm64::NOP);
+        self.exit(pc, reason);
+        self.code[branch] = success((self.code.len() - branch) as i32 * 4);
+    }
+
+    /// Conditional branch on rs1 `condition` rs2
+    fn branch(&mut self, pc: u32, rs1: u8, rs2: u8, imm: i32, condition: Condition) {
+        if self.optimize && rs2 == 0 && rs1 != 0 {
+            // Test the register itself: CBZ/CBNZ for equality, the sign bit
+            // for signed comparisons
+            let skip: Option<fn(u8, i32) -> u32> = match condition {
+                Condition::Eq => Some(arm64::cbnz),
+                Condition::Ne => Some(arm64::cbz),
+                Condition::Lt => Some(|rt, offset| arm64::tbz(rt, 31, offset)),
+                Condition::Ge => Some(|rt, offset| arm64::tbnz(rt, 31, offset)),
+                _ => None,
+            };
+            if let Some(skip) = skip {
+                let value = self.source(rs1, T0);
+                let at = self.code.len();
+                self.code.push(arm64::NOP);
+                self.jump(pc.wrapping_add(imm as u32));
+                self.code[at] = skip(value, (self.code.len() - at) as i32 * 4);
+                return;
+            }
+        }
+        let lhs = self.source(rs1, T0);
+        let rhs = self.source(rs2, T1);
+        self.code.push(arm64::cmp(lhs, rhs));
+        let skip = self.code.len();
+        self.code.push(arm64::NOP);
+        self.jump(pc.wrapping_add(imm as u32));
+        let offset = (self.code.len() - skip) as i32 * 4;
+        self.code[skip] = arm64::b_cond(condition.invert(), offset);
+    }
+
+    /// Write a constant to guest register `rd`, using `scratch` if it is spilled
+    fn constant(&mut self, rd: u8, value: u32, scratch: u8) {
+        if rd != 0 {
+            let result = self.target(rd, scratch);
+            let moves = arm64::mov_imm(result, value);
+            match &mut self.literals {
+                Some(literals) if moves.len() > 1 => {
+                    literals.push(Literal {
+                        at: self.code.len(),
+                        value,
+                    });
+                    self.code.push(arm64::ldr_literal(result, 0));
+                }
+                _ => self.code.extend(moves),
+            }
+            self.commit(rd, result);
+        }
+    }
+
+    /// Continue at guest PC `target` the way JALR does, charging the rest of
+    /// its basic block when it does not start one
+    fn land(&mut self, target: u32) {
+        if let Some(index) = self.index(target).filter(|&index| !self.leaders[index]) {
+            self.charge(target, self.costs[index]);
+        }
+        self.jump(target);
+    }
+
+    /// Translate the pair `first`, `second` at guest PC `pc` as one operation
+    fn fuse(&mut self, pc: u32, kind: FusionKind, first: &Instruction, second: &Instruction) {
+        use Instruction::*;
+        match (kind, first, second) {
+            (FusionKind::LoadImmediate, &Lui { rd, imm: upper }, &Addi { imm, .. }) => {
+                self.constant(rd, (upper << 12).wrapping_add(imm as u32), T0);
+            }
+            (
+                FusionKind::FarJump,
+                &Auipc {
+                    rd: base,
+                    imm: upper,
+                },
+                &Jalr { rd, imm, .. },
+            ) => {
+                // A call through its own link register only keeps the link
+                if base != rd {
+                    self.constant(base, pc.wrapping_add(upper << 12), T0);
+                }
+                self.constant(rd, pc.wrapping_add(8), T0);
+                self.land(far_target(pc, base, upper, imm));
+            }
+            (FusionKind::ZeroExtend, &Slli { rd, rs1, shamt }, _) => {
+                let value = self.source(rs1, T0);
+                let result = self.target(rd, T0);
+                match shamt & 0x1F {
+                    0 if result == value => {}
+                    0 => self.code.push(arm64::mov(result, value)),
+                    shift => self.code.push(arm64::ubfx(result, value, 0, 32 - shift)),
+                }
+                self.commit(rd, result);
+            }
+            (
+                FusionKind::CompareBranch,
+                &(Slt { rd, rs1, rs2 } | Sltu { rd, rs1, rs2 }),
+                &(Beq { imm, .. } | Bne { imm, .. }),
+            ) => {
+                let less = match first {
+                    Slt { .. } => Condition::Lt,
+                    _ => Condition::Lo,
+                };
+                let taken = match second {
+                    Beq { .. } => less.invert(),
+                    _ => less,
+                };
+                let lhs = self.source(rs1, T0);
+                let rhs = self.source(rs2, T1);
+                self.code.push(arm64::cmp(lhs, rhs));
+                let result = self.target(rd, T0);
+                self.code.push(arm64::cset(result, less));
+                self.commit(rd, result);
+                let skip = self.code.len();
+                self.code.push(arm64::NOP);
+                self.jump(pc.wrapping_add(4).wrapping_add(imm as u32));
+                let offset = (self.code.len() - skip) as i32 * 4;
+                self.code[skip] = arm64::b_cond(taken.invert(), offset);
+            }
+            _ => unreachable!("{} pair does not match its pattern", kind),
+        }
+    }
+
+    /// Translate one instruction at guest PC `pc`
+    fn instruction(&mut self, pc: u32, instruction: &Instruction) {
+        use Instruction::*;
+        match *instruction {
+            Add { rd, rs1, rs2 } => self.register_op(rd, rs1, rs2, arm64::add),
+            Sub { rd, rs1, rs2 } => self.register_op(rd, rs1, rs2, arm64::sub),
+            Sll { rd, rs1, rs2 } => self.register_op(rd, rs1, rs2, arm64::lslv),
+            Srl { rd, rs1, rs2 } => self.register_op(rd, rs1, rs2, arm64::lsrv),
+            Sra { rd, rs1, rs2 } => self.register_op(rd, rs1, rs2, arm64::asrv),
+            Xor { rd, rs1, rs2 } => self.register_op(rd, rs1, rs2, arm64::eor),
+            Or { rd, rs1, rs2 } => self.register_op(rd, rs1, rs2, arm64::orr),
+            And { rd, rs1, rs2 } => self.register_op(rd, rs1, rs2, arm64::and),
+            Mul { rd, rs1, rs2 } => self.register_op(rd, rs1, rs2, arm64::mul),
+            Mulh { rd, rs1, rs2 } => self.multiply_high(rd, rs1, rs2, true, true),
+            Mulhsu { rd, rs1, rs2 } => self.multiply_high(rd, rs1, rs2, true, false),
+            Mulhu { rd, rs1, rs2 } => self.multiply_high(rd, rs1, rs2, false, false),
+            Div { rd, rs1, rs2 } => self.divide(rd, rs1, rs2, true, false),
+            Divu { rd, rs1, rs2 } => self.divide(rd, rs1, rs2, false, false),
+            Rem { rd, rs1, rs2 } => self.divide(rd, rs1, rs2, true, true),
+            Remu { rd, rs1, rs2 } => self.divide(rd, rs1, rs2, false, true),
+            Slt { rd, rs1, rs2 } | Sltu { rd, rs1, rs2 } if rd != 0 => {
+                let condition = match instruction {
+                    Slt { .. } => Condition::Lt,
+                    _ => Condition::Lo,
+                };
+                let rhs = self.source(rs2, T1);
+                self.set_less_than(rd, rs1, rhs, condition);
+            }
+            Slt { .. } | Sltu { .. } => {}
+            Addi { rd, rs1, imm } if rd != 0 => {
+                let result = self.target(rd, T0);
+                self.offset(result, rs1, imm);
+                self.commit(rd, result);
+            }
+            Addi { .. } => {}
+            Slti { rd, rs1, imm } | Sltiu { rd, rs1, imm } if rd != 0 => {
+                let condition = match instruction {
+                    Slti { .. } => Condition::Lt,
+                    _ => Condition::Lo,
+                };
+                self.code.extend(arm64::mov_imm(T1, imm as u32));
+                self.set_less_than(rd, rs1, T1, condition);
+            }
+            Slti { .. } | Sltiu { .. } => {}
+            Xori { rd, rs1, imm } => self.immediate_op(rd, rs1, imm, arm64::eor),
+            Ori { rd, rs1, imm } => self.immediate_op(rd, rs1, imm, arm64::orr),
+            Andi { rd, rs1, imm } => self.immediate_op(rd, rs1, imm, arm64::and),
+            Slli { rd, rs1, shamt } => self.shift_imm(rd, rs1, shamt, arm64::lsl_imm),
+            Srli { rd, rs1, shamt } => self.shift_imm(rd, rs1, shamt, arm64::lsr_imm),
+            Srai { rd, rs1, shamt } => self.shift_imm(rd, rs1, shamt, arm64::asr_imm),
+            Lb { rd, rs1, imm } => self.load(pc, rd, rs1, imm, 0),
+            Lh { rd, rs1, imm } => self.load(pc, rd, rs1, imm, 1),
+            Lw { rd, rs1, imm } => self.load(pc, rd, rs1, imm, 2),
+            Lbu { rd, rs1, imm } => self.load(pc, rd, rs1, imm, 4),
+            Lhu { rd, rs1, imm } => self.load(pc, rd, rs1, imm, 5),
+            Sb { rs1, rs2, imm } => self.store(pc, rs1, rs2, imm, 0),
+            Sh { rs1, rs2, imm } => self.store(pc, rs1, rs2, imm, 1),
+            Sw { rs1, rs2, imm } => self.store(pc, rs1, rs2, imm, 2),
+            Beq { rs1, rs2, imm } => self.branch(pc, rs1, rs2, imm, Condition::Eq),
+            Bne { rs1, rs2, imm } => self.branch(pc, rs1, rs2, imm, Condition::Ne),
+            Blt { rs1, rs2, imm } => self.branch(pc, rs1, rs2, imm, Condition::Lt),
+            Bge { rs1, rs2, imm } => self.branch(pc, rs1, rs2, imm, Condition::Ge),
+            Bltu { rs1, rs2, imm } => self.branch(pc, rs1, rs2, imm, Condition::Lo),
+            Bgeu { rs1, rs2, imm } => self.branch(pc, rs1, rs2, imm, Condition::Hs),
+            Jal { rd, imm } => {
+                self.constant(rd, pc.wrapping_add(4), T0);
+                self.jump(pc.wrapping_add(imm as u32));
+            }
+            Jalr { rd, rs1, imm } => {
+                // Compute the target before writing rd, since rd may equal rs1
+                self.offset(T0, rs1, imm);
+                self.code.push(arm64::movn(T1, 1, 0));
+                self.code.push(arm64::and(T0, T0, T1));
+                self.constant(rd, pc.wrapping_add(4), T1);
+                self.branch_to(Label::Dispatch);
+            }
+            Lui { rd, imm } => self.constant(rd, imm << 12, T0),
+            Auipc { rd, imm } => self.constant(rd, pc.wrapping_add(imm << 12), T0),
+            Ecall => self.ecall(pc),
+            Ebreak => self.exit(pc, EXIT_EBREAK),
+            _ => self.exit(pc, EXIT_ILLEGAL),
+        }
+    }
+}
+
+/// Whether every PC-relative reference in the first `end` words of `code`
+/// (the instructions, before the dispatch table) lands inside `code`
+fn position_independent(code: &[u32], end: usize) -> bool {
+    code[..end].iter().enumerate().all(|(at, &word)| {
+        arm64::pc_relative(word)
+            .is_none_or(|delta| (0..code.len() as i64 * 4).contains(&(at as i64 * 4 + delta)))
+    })
+}
+
+/// Split the instructions into contiguous ranges of at least `MIN_REGION`
+/// instructions, one per thread up to `threads`
+///
+/// Ranges start at basic block leaders, so no fused pair is split. There is
+/// always at least one range, empty for no instructions.
+pub(crate) fn regions(leaders: &[bool], threads: usize) -> Vec<Range<usize>> {
+    let count = leaders.len();
+    let parts = threads.min(count / MIN_REGION).max(1);
+    let mut starts = vec![0];
+    for part in 1..parts {
+        let last = *starts.last().unwrap();
+        let at = (part * count / parts).max(last + MIN_REGION);
+        match (at..count).find(|&index| leaders[index]) {
+            Some(start) if count - start >= MIN_REGION => starts.push(start),
+            _ => break,
+        }
+    }
+    starts.push(count);
+    starts.windows(2).map(|pair| pair[0]..pair[1]).collect()
+}
+
+/// Whether `instruction` is translated, rather than exiting with
+/// `EXIT_ILLEGAL`
+pub(crate) fn supported(instruction: &Instruction) -> bool {
+    use Instruction::*;
+    matches!(
+        instruction,
+        Add { .. }
+            | Sub { .. }
+            | Sll { .. }
+            | Srl { .. }
+            | Sra { .. }
+            | Xor { .. }
+            | Or { .. }
+            | And { .. }
+            | Mul { .. }
+            | Mulh { .. }
+            | Mulhsu { .. }
+            | Mulhu { .. }
+            | Div { .. }
+            | Divu { .. }
+            | Rem { .. }
+            | Remu { .. }
+            | Slt { .. }
+            | Sltu { .. }
+            | Addi { .. }
+            | Slti { .. }
+            | Sltiu { .. }
+            | Xori { .. }
+            | Ori { .. }
+            | Andi { .. }
+            | Slli { .. }
+            | Srli { .. }
+            | Srai { .. }
+            | Lb { .. }
+            | Lh { .. }
+            | Lw { .. }
+            | Lbu { .. }
+            | Lhu { .. }
+            | Sb { .. }
+            | Sh { .. }
+            | Sw { .. }
+            | Beq { .. }
+            | Bne { .. }
+            | Blt { .. }
+            | Bge { .. }
+            | Bltu { .. }
+            | Bgeu { .. }
+            | Jal { .. }
+            | Jalr { .. }
+            | Lui { .. }
+            | Auipc { .. }
+            | Ecall
+            | Ebreak
+    )
+}
+
+/// Fusible pairs to translate together, by the index of their first
+/// instruction
+///
+/// Pairs are only fused when their second instruction does not start a basic
+/// block, so it is only reached from the first or by JALR, and never when it
+/// is the target of a fused AUIPC+JALR, which branches straight to its code.
+fn fusions(instructions: &[Instruction], leaders: &[bool], base: u32) -> Vec<Option<FusionKind>> {
+    let mut fused = vec![None; instructions.len()];
+    let pairs: Vec<_> = fusion::find(instructions)
+        .into_iter()
+        .filter(|pair| !leaders[pair.start + 1])
+        .collect();
+    let targets: Vec<usize> = pairs
+        .iter()
+        .filter_map(|pair| match instructions[pair.start..] {
+            [
+                Instruction::Auipc {
+                    rd: register,
+                    imm: upper,
+                },
+                Instruction::Jalr { imm, .. },
+                ..,
+            ] => {
+                let pc = base.wrapping_add(pair.start as u32 * 4);
+                let target = far_target(pc, register, upper, imm).wrapping_sub(base);
+                Some((target / 4) as usize)
+            }
+            _ => None,
+        })
+        .collect();
+    for pair in pairs {
+        if !targets.contains(&(pair.start + 1)) {
+            fused[pair.start] = Some(pair.kind);
+        }
+    }
+    fused
+}
+
+/// Target of `auipc base, upper` at `pc` followed by `jalr rd, base, imm`
+fn far_target(pc: u32, base: u8, upper: u32, imm: i32) -> u32 {
+    let base = match base {
+        0 => 0,
+        _ => pc.wrapping_add(upper << 12),
+    };
+    base.wrapping_add(imm as u32) & !1
+}
@@ -0,0 +1,192 @@
+//! Unwind information for compiled code
+//!
+//! Compiled code keeps the frame-pointer chain: every entry pushes a frame
+//! record and points x29 at it before running guest code (see
+//! `translator`), so profilers walking frame pointers unwind through it
+//! already. Unwinders reading DWARF CFI instead, such as those of crash
+//! reporters and `perf --call-graph=dwarf`, need a description of each
+//! frame, which `eh_frame()` builds in the `.eh_frame` format.
+//!
+//! The frame goes through three states the translator records as it emits
+//! the code, the same for every entry: `Entry` on entry and at the exit
+//! stub's RET, `Pushed` once the frame record is stored and `Framed` once
+//! x29 is set up and the callee-saved registers are in their slots. Code
+//! that is not an entry, like the dispatch routine, runs `Framed`.
+//!
+//! The table holds absolute addresses, so it is built for where the code
+//! is and must be registered with the host unwinder (for instance with
+//! `__register_frame` from libgcc) while the code stays there.
+
+use crate::translator::{FRAME_SIZE, SAVED_PAIRS};
+
+/// State of the frame at some point in the code
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Frame {
+    /// Nothing pushed, the CFA in SP and the return address in x30
+    Entry,
+    /// Frame record pushed; the CFA is `FRAME_SIZE` above SP
+    Pushed,
+    /// Frame set up: the CFA is `FRAME_SIZE` above x29 and the callee-saved
+    /// registers are in the frame
+    Framed,
+}
+
+/// Bytes per instruction, the code alignment factor
+const CODE_ALIGNMENT: u8 = 4;
+/// Bytes per saved register, the negated data alignment factor
+const DATA_ALIGNMENT: usize = 8;
+
+/// Frame pointer
+const FP: u8 = 29;
+/// Link register, holding the return address
+const LR: u8 = 30;
+/// Stack pointer
+const SP: u8 = 31;
+
+/// Advance the location by the low 6 bits
+const DW_CFA_ADVANCE_LOC: u8 = 0x40;
+/// Register in the low 6 bits saved at a factored offset below the CFA
+const DW_CFA_OFFSET: u8 = 0x80;
+/// Register in the low 6 bits back to its initial rule
+const DW_CFA_RESTORE: u8 = 0xC0;
+/// Advance the location by a 1-byte delta
+const DW_CFA_ADVANCE_LOC1: u8 = 0x02;
+/// Advance the location by a 2-byte delta
+const DW_CFA_ADVANCE_LOC2: u8 = 0x03;
+/// Advance the location by a 4-byte delta
+const DW_CFA_ADVANCE_LOC4: u8 = 0x04;
+/// CFA as a register plus an offset
+const DW_CFA_DEF_CFA: u8 = 0x0C;
+/// Padding
+const DW_CFA_NOP: u8 = 0x00;
+/// Pointer encoding of the FDE addresses: absolute, 8 bytes
+const DW_EH_PE_ABSPTR: u8 = 0x00;
+
+/// Build an `.eh_frame` section describing the `len` bytes of code at
+/// `address`, given the byte offset where each frame state starts
+///
+/// Holds a CIE and a single FDE covering all of the code, followed by the
+/// zero terminator. `frames` must be in ascending order, starting at 0.
+pub(crate) fn eh_frame(address: u64, len: usize, frames: &[(usize, Frame)]) -> Vec<u8> {
+    let mut cie = vec![1];
+    cie.extend(b"zR\0");
+    uleb(&mut cie, CODE_ALIGNMENT as u64);
+    sleb(&mut cie, -(DATA_ALIGNMENT as i64));
+    cie.push(LR);
+    uleb(&mut cie, 1);
+    cie.push(DW_EH_PE_ABSPTR);
+    def_cfa(&mut cie, SP, 0);
+
+    let mut fde = Vec::new();
+    fde.extend(address.to_le_bytes());
+    fde.extend((len as u64).to_le_bytes());
+    uleb(&mut fde, 0);
+    let mut at = 0;
+    for &(offset, frame) in frames {
+        if offset > at {
+            advance(&mut fde, (offset - at) / CODE_ALIGNMENT as usize);
+            at = offset;
+        }
+        rules(&mut fde, frame);
+    }
+
+    let mut section = Vec::new();
+    entry(&mut section, &cie, None);
+    entry(&mut section, &fde, Some(0));
+    section.extend([0; 4]);
+    section
+}
+
+/// Append a CIE, or an FDE for the CIE at `cie`, padded to 8 bytes with
+/// NOPs
+fn entry(section: &mut Vec<u8>, body: &[u8], cie: Option<usize>) {
+    let start = section.len();
+    let id = cie.map_or(0, |cie| (start + 4 - cie) as u32);
+    let len = (8 + body.len()).next_multiple_of(8) - 4;
+    section.extend((len as u32).to_le_bytes());
+    section.extend(id.to_le_bytes());
+    section.extend(body);
+    section.resize(start + 4 + len, DW_CFA_NOP);
+}
+
+/// Append the rules of `frame`
+fn rules(out: &mut Vec<u8>, frame: Frame) {
+    let saved = |out: &mut Vec<u8>, reg: u8, slot: i32| {
+        out.push(DW_CFA_OFFSET | reg);
+        uleb(out, ((FRAME_SIZE - slot) as usize / DATA_ALIGNMENT) as u64);
+    };
+    match frame {
+        Frame::Entry => {
+            def_cfa(out, SP, 0);
+            out.push(DW_CFA_RESTORE | FP);
+            out.push(DW_CFA_RESTORE | LR);
+            for &(first, second) in &SAVED_PAIRS {
+                out.extend([DW_CFA_RESTORE | first, DW_CFA_RESTORE | second]);
+            }
+        }
+        Frame::Pushed => {
+            def_cfa(out, SP, FRAME_SIZE as u64);
+            saved(out, FP, 0);
+            saved(out, LR, 8);
+        }
+        Frame::Framed => {
+            def_cfa(out, FP, FRAME_SIZE as u64);
+            saved(out, FP, 0);
+            saved(out, LR, 8);
+            for (slot, &(first, second)) in SAVED_PAIRS.iter().enumerate() {
+                let slot = 16 * (1 + slot as i32);
+                saved(out, first, slot);
+                saved(out, second, slot + 8);
+            }
+        }
+    }
+}
+
+/// Append a rule computing the CFA as `reg` plus `offset`
+fn def_cfa(out: &mut Vec<u8>, reg: u8, offset: u64) {
+    out.push(DW_CFA_DEF_CFA);
+    uleb(out, reg as u64);
+    uleb(out, offset);
+}
+
+/// Append an advance of the location by `delta` instructions
+fn advance(out: &mut Vec<u8>, delta: usize) {
+    match delta {
+        0..0x40 => out.push(DW_CFA_ADVANCE_LOC | delta as u8),
+        0x40..0x100 => out.extend([DW_CFA_ADVANCE_LOC1, delta as u8]),
+        0x100..0x10000 => {
+            out.push(DW_CFA_ADVANCE_LOC2);
+            out.extend((delta as u16).to_le_bytes());
+        }
+        _ => {
+            out.push(DW_CFA_ADVANCE_LOC4);
+            out.extend((delta as u32).to_le_bytes());
+        }
+    }
+}
+
+/// Append `value` as unsigned LEB128
+fn uleb(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = value as u8 & 0x7F;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+/// Append `value` as signed LEB128
+fn sleb(out: &mut Vec<u8>, mut value: i64) {
+    loop {
+        let byte = value as u8 & 0x7F;
+        value >>= 7;
+        if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
@@ -0,0 +1,178 @@
+//! Veneers for branches beyond the reach of B
+//!
+//! The translator branches between guest instructions, to the exit stub and
+//! to the dispatch routine with B, which reaches 128MB either way. Larger code
+//! can put a target out of reach, so once everything is laid out, `link`
+//! sends those branches through veneers: stubs that load the target's offset
+//! from the word after them and branch there through x16, reaching anywhere
+//! in the code. AAPCS64 reserves x16 and x17 for this, and the translator
+//! never keeps anything in them across a branch.
+//!
+//! Veneers are grouped in islands, inserted at points between units of code
+//! (guest instructions, stubs) given by the translator. Each island starts
+//! with a B over its veneers for the code falling through into it. A branch
+//! uses the island at the point nearest to it, so it always reaches, and
+//! branches using one island share a veneer per target. Islands move the code
+//! after them, which can put more branches out of reach, so placement repeats
+//! until nothing changes. Points are never inside a unit, so the branches
+//! within one keep their offsets.
+//!
+//! Like the rest of the code, veneers are position independent: ADR finds
+//! the veneer and the target is an offset from it.
+
+use crate::{
+    arm64,
+    translator::{IP0, IP1},
+};
+use std::collections::BTreeMap;
+
+/// Bytes a B reaches in either direction
+pub(crate) const REACH: usize = 1 << 27;
+
+/// Words in a veneer, including its offset
+pub(crate) const VENEER_WORDS: usize = 5;
+
+/// B placeholder to patch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Branch {
+    /// Word offset of the placeholder
+    pub(crate) at: usize,
+    /// Word offset of the target
+    pub(crate) target: usize,
+}
+
+/// Islands inserted by `link`, for moving offsets into the linked code
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Layout {
+    /// Point of each island, ascending, with the words inserted up to and
+    /// including it
+    islands: Vec<(usize, usize)>,
+}
+
+impl Layout {
+    /// Offset in the linked code of the word at `offset` in the code as
+    /// translated
+    ///
+    /// Code at a point lands after its island.
+    pub(crate) fn relocate(&self, offset: usize) -> usize {
+        let count = self.islands.partition_point(|&(point, _)| point <= offset);
+        match count {
+            0 => offset,
+            _ => offset + self.islands[count - 1].1,
+        }
+    }
+
+    /// Number of veneers in the islands
+    pub(crate) fn veneers(&self) -> usize {
+        let words = self.islands.last().map_or(0, |island| island.1);
+        (words - self.islands.len()) / VENEER_WORDS
+    }
+}
+
+/// Patch `branches` in `code`, inserting islands of veneers at `points`
+/// (ascending word offsets between units of code) for the branches a B
+/// reaching `reach` bytes cannot take directly
+pub(crate) fn link(
+    code: &mut Vec<u32>,
+    branches: &[Branch],
+    points: &[usize],
+    reach: usize,
+) -> Layout {
+    // Island of each branch taking a veneer, by its point's index
+    let mut far = vec![None; branches.len()];
+    let (mut layout, mut islands) = plan(branches, &far, points);
+    loop {
+        let mut changed = false;
+        for (branch, island) in branches.iter().zip(&mut far) {
+            let offset = distance(layout.relocate(branch.at), layout.relocate(branch.target));
+            if island.is_none() && !(-(reach as i64)..reach as i64).contains(&offset) {
+                *island = Some(nearest(points, branch.at));
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+        (layout, islands) = plan(branches, &far, points);
+    }
+    if islands.is_empty() {
+        for branch in branches {
+            code[branch.at] = arm64::b(distance(branch.at, branch.target) as i32);
+        }
+        return layout;
+    }
+
+    // Copy the code between islands, emitting each island's veneers with
+    // their targets' offsets
+    let mut linked = Vec::with_capacity(code.len() + layout.islands.last().map_or(0, |at| at.1));
+    let mut start = 0;
+    let mut veneers = BTreeMap::new();
+    for (&index, targets) in &islands {
+        let point = points[index];
+        linked.extend_from_slice(&code[start..point]);
+        start = point;
+        linked.push(arm64::b((1 + targets.len() * VENEER_WORDS) as i32 * 4));
+        for &target in targets {
+            let at = linked.len();
+            veneers.insert((index, target), at);
+            linked.extend([
+                arm64::adr(IP0, 0),
+                arm64::ldrsw(IP1, IP0, 4 * (VENEER_WORDS as u32 - 1)),
+                arm64::add_x(IP0, IP0, IP1),
+                arm64::br(IP0),
+                distance(at, layout.relocate(target)) as i32 as u32,
+            ]);
+        }
+    }
+    linked.extend_from_slice(&code[start..]);
+    for (branch, island) in branches.iter().zip(&far) {
+        let at = layout.relocate(branch.at);
+        let target = match island {
+            Some(index) => veneers[&(*index, branch.target)],
+            None => layout.relocate(branch.target),
+        };
+        linked[at] = arm64::b(distance(at, target) as i32);
+    }
+    *code = linked;
+    layout
+}
+
+/// Lay out the islands the branches in `far` take, returning the layout and
+/// the distinct targets in each island, in order of first use
+fn plan(
+    branches: &[Branch],
+    far: &[Option<usize>],
+    points: &[usize],
+) -> (Layout, BTreeMap<usize, Vec<usize>>) {
+    let mut islands: BTreeMap<usize, Vec<usize>> = BTreeMap::new();
+    for (branch, island) in branches.iter().zip(far) {
+        if let Some(index) = *island {
+            let targets = islands.entry(index).or_default();
+            if !targets.contains(&branch.target) {
+                targets.push(branch.target);
+            }
+        }
+    }
+    let mut layout = Layout::default();
+    let mut words = 0;
+    for (&index, targets) in &islands {
+        words += 1 + targets.len() * VENEER_WORDS;
+        layout.islands.push((points[index], words));
+    }
+    (layout, islands)
+}
+
+/// Index of the point nearest to the word at `at`, the earlier on a tie
+fn nearest(points: &[usize], at: usize) -> usize {
+    let after = points.partition_point(|&point| point <= at);
+    match (after.checked_sub(1), points.get(after)) {
+        (Some(before), Some(&next)) if next - at < at - points[before] => after,
+        (Some(before), _) => before,
+        (None, _) => after,
+    }
+}
+
+/// Byte offset from the word at `from` to the word at `to`
+fn distance(from: usize, to: usize) -> i64 {
+    (to as i64 - from as i64) * 4
+}
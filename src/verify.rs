@@ -0,0 +1,142 @@
+//! Encode/decode consistency checker
+//!
+//! Sweeps representative operand values through every entry of the `isa`
+//! encoding table and checks that the encoder and decoder agree: each word
+//! decodes to the instruction named by its table entry, encodes back to the
+//! same word, and places its register operands in the standard `rd`, `rs1` and
+//! `rs2` fields. Downstream crates can call `check()` from their own tests to
+//! confirm the build they link against is consistent.
+//!
+//! # Example
+//! ```
+//! let checked = jigs::verify::check().unwrap();
+//! assert!(checked > 1000);
+//! ```
+
+use crate::{
+    EncodeError, Instruction,
+    isa::{ENCODINGS, Encoding},
+};
+use std::fmt;
+
+/// Register values placed in every register field at once
+const REGISTERS: [u32; 6] = [0, 1, 2, 15, 16, 31];
+
+/// Bits 11:7
+const RD_SHIFT: u32 = 7;
+/// Bits 19:15
+const RS1_SHIFT: u32 = 15;
+/// Bits 24:20
+const RS2_SHIFT: u32 = 20;
+
+/// An inconsistency found by the checker
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerifyError {
+    /// The word decoded to a different instruction than its table entry
+    Mnemonic(u32, &'static str),
+    /// The decoded instruction failed to encode
+    EncodeError(u32, EncodeError),
+    /// The decoded instruction encoded to a different word
+    Roundtrip(u32, u32),
+    /// A register accessor disagrees with the named field of the word
+    Field(u32, &'static str),
+}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VerifyError::Mnemonic(word, expected) => {
+                write!(f, "0x{:08x} does not decode as {}", word, expected)
+            }
+            VerifyError::EncodeError(word, error) => {
+                write!(f, "0x{:08x} decodes but fails to encode: {}", word, error)
+            }
+            VerifyError::Roundtrip(word, encoded) => {
+                write!(f, "0x{:08x} re-encodes as 0x{:08x}", word, encoded)
+            }
+            VerifyError::Field(word, field) => {
+                write!(f, "0x{:08x} reports {} outside its field", word, field)
+            }
+        }
+    }
+}
+
+impl std::error::Error for VerifyError {}
+
+/// Check every table entry, returning the number of words checked
+pub fn check() -> Result<usize, VerifyError> {
+    ENCODINGS.iter().map(check_encoding).sum()
+}
+
+/// Check one table entry, returning the number of words checked
+///
+/// Words with reserved operand values (which decode as `Unsupported`) are
+/// skipped.
+pub fn check_encoding(encoding: &Encoding) -> Result<usize, VerifyError> {
+    let mut checked = 0;
+    for word in words(encoding) {
+        let instruction = Instruction::decode(word);
+        if let Instruction::Unsupported(_) = instruction {
+            continue;
+        }
+        check_word(encoding, word, &instruction)?;
+        checked += 1;
+    }
+    Ok(checked)
+}
+
+/// Check a single decoded word against its table entry
+fn check_word(
+    encoding: &Encoding,
+    word: u32,
+    instruction: &Instruction,
+) -> Result<(), VerifyError> {
+    if instruction.mnemonic() != encoding.mnemonic {
+        return Err(VerifyError::Mnemonic(word, encoding.mnemonic));
+    }
+    let encoded = instruction
+        .encode()
+        .map_err(|error| VerifyError::EncodeError(word, error))?;
+    if encoded != word || Instruction::decode(encoded) != *instruction {
+        return Err(VerifyError::Roundtrip(word, encoded));
+    }
+    let fields = [
+        ("rd", instruction.rd(), RD_SHIFT),
+        ("rs1", instruction.rs1(), RS1_SHIFT),
+        ("rs2", instruction.rs2(), RS2_SHIFT),
+    ];
+    for (name, register, shift) in fields {
+        if let Some(register) = register
+            && register.number() as u32 != (word >> shift) & 0x1F
+        {
+            return Err(VerifyError::Field(word, name));
+        }
+    }
+    Ok(())
+}
+
+/// Representative words for an entry: the fixed bits combined with boundary
+/// patterns, each single free bit and a sweep of register numbers
+fn words(encoding: &Encoding) -> Vec<u32> {
+    let mut patterns = vec![
+        0,
+        u32::MAX,
+        0x5555_5555,
+        0xAAAA_AAAA,
+        0x8000_0000,
+        0x7FFF_FFFF,
+    ];
+    patterns.extend((0..32).map(|bit| 1u32 << bit));
+    patterns.extend(REGISTERS.iter().flat_map(|&rd| {
+        REGISTERS.iter().map(move |&rs| {
+            (rd << RD_SHIFT) | (rs << RS1_SHIFT) | (rs.wrapping_mul(7) & 0x1F) << RS2_SHIFT
+        })
+    }));
+    let mut words: Vec<u32> = patterns
+        .into_iter()
+        .map(|pattern| (pattern & !encoding.mask) | encoding.value)
+        .collect();
+    words.sort_unstable();
+    words.dedup();
+    words
+}
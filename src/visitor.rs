@@ -0,0 +1,101 @@
+//! Visitor trait for instruction analysis passes
+//!
+//! Analyses such as liveness, gas accounting and verification usually care
+//! about a few categories of instructions and ignore the rest. Implementing
+//! `InstructionVisitor` lets a pass override only the methods it needs: `visit()`
+//! dispatches each instruction to the method for its `InstructionKind`, and every
+//! method defaults to doing nothing. The instruction is passed through so a
+//! method can still match on individual variants.
+//!
+//! # Example
+//! ```
+//! use jigs::{Instruction, InstructionVisitor};
+//!
+//! /// Count memory accesses
+//! #[derive(Default)]
+//! struct Accesses(usize);
+//!
+//! impl InstructionVisitor for Accesses {
+//!     fn visit_load(&mut self, _: &Instruction) {
+//!         self.0 += 1;
+//!     }
+//!
+//!     fn visit_store(&mut self, _: &Instruction) {
+//!         self.0 += 1;
+//!     }
+//! }
+//!
+//! let code = [
+//!     Instruction::Lw { rd: 5, rs1: 2, imm: 0 },
+//!     Instruction::Addi { rd: 5, rs1: 5, imm: 1 },
+//!     Instruction::Sw { rs1: 2, rs2: 5, imm: 0 },
+//! ];
+//! let mut accesses = Accesses::default();
+//! accesses.visit_all(&code);
+//! assert_eq!(accesses.0, 2);
+//! ```
+
+use crate::{Instruction, InstructionKind};
+
+/// Per-category callbacks for walking instructions
+///
+/// All methods default to no-ops, except `visit()` which dispatches on
+/// `Instruction::kind()` and `visit_all()` which calls `visit()` in order.
+pub trait InstructionVisitor {
+    /// Visit a single instruction, dispatching to the method for its kind
+    fn visit(&mut self, instruction: &Instruction) {
+        match instruction.kind() {
+            InstructionKind::Arithmetic => self.visit_arithmetic(instruction),
+            InstructionKind::Load => self.visit_load(instruction),
+            InstructionKind::Store => self.visit_store(instruction),
+            InstructionKind::Branch => self.visit_branch(instruction),
+            InstructionKind::Jump => self.visit_jump(instruction),
+            InstructionKind::System => self.visit_system(instruction),
+            InstructionKind::Mul => self.visit_mul(instruction),
+            InstructionKind::Div => self.visit_div(instruction),
+            InstructionKind::Vector => self.visit_vector(instruction),
+            InstructionKind::Float => self.visit_float(instruction),
+            InstructionKind::Unsupported => self.visit_unsupported(instruction),
+        }
+    }
+
+    /// Visit a sequence of instructions in order
+    fn visit_all(&mut self, instructions: &[Instruction]) {
+        for instruction in instructions {
+            self.visit(instruction);
+        }
+    }
+
+    /// Integer arithmetic, logic, shifts, comparisons, LUI and AUIPC
+    fn visit_arithmetic(&mut self, _instruction: &Instruction) {}
+
+    /// Memory loads, including FLH and vector loads
+    fn visit_load(&mut self, _instruction: &Instruction) {}
+
+    /// Memory stores, including FSH and vector stores
+    fn visit_store(&mut self, _instruction: &Instruction) {}
+
+    /// Conditional branches
+    fn visit_branch(&mut self, _instruction: &Instruction) {}
+
+    /// JAL and JALR
+    fn visit_jump(&mut self, _instruction: &Instruction) {}
+
+    /// ECALL and EBREAK
+    fn visit_system(&mut self, _instruction: &Instruction) {}
+
+    /// Integer multiplication
+    fn visit_mul(&mut self, _instruction: &Instruction) {}
+
+    /// Integer division and remainder
+    fn visit_div(&mut self, _instruction: &Instruction) {}
+
+    /// Vector configuration and arithmetic
+    fn visit_vector(&mut self, _instruction: &Instruction) {}
+
+    /// Floating-point arithmetic and conversions
+    fn visit_float(&mut self, _instruction: &Instruction) {}
+
+    /// Unrecognized encodings
+    fn visit_unsupported(&mut self, _instruction: &Instruction) {}
+}